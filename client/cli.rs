@@ -1,4 +1,6 @@
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use chrono::prelude::*;
 use chrono::Duration;
@@ -6,13 +8,19 @@ use chrono_english::*;
 use clap::{ArgEnum, Clap};
 
 use pueue_lib::network::message::Signal;
+use pueue_lib::settings::{parse_duration, parse_size};
+use pueue_lib::task::WindowsShell;
 
+use crate::exit_code::EXIT_CODES_HELP;
+use crate::query::Query;
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Clap, Debug)]
 pub enum SubCommand {
     /// Enqueue a task for execution.
     Add {
         /// The command to be added.
-        #[clap(required = true)]
+        #[clap(required_unless_present_any = &["from-clipboard", "reserve"])]
         command: Vec<String>,
 
         /// Specify current working directory.
@@ -43,11 +51,31 @@ pub enum SubCommand {
         #[clap(name = "group", short, long)]
         group: Option<String>,
 
+        /// Mark the task as expired, if it hasn't been started before this point in time.
+        /// Tasks that depend on it will fail. See "enqueue" for accepted formats.
+        #[clap(name = "expire-at", long, conflicts_with = "expire-in", parse(try_from_str=parse_delay_until))]
+        expire_at: Option<DateTime<Local>>,
+
+        /// Mark the task as expired, if it hasn't been started within this duration, e.g. `30m`.
+        /// Accepted units are `s`, `m`, `h` and `d`.
+        #[clap(name = "expire-in", long, conflicts_with = "expire-at", parse(try_from_str=parse_expire_in))]
+        expire_in: Option<DateTime<Local>>,
+
         /// Start the task once all specified tasks have successfully finished.
         /// As soon as one of the dependencies fails, this task will fail as well.
-        #[clap(name = "after", short, long)]
+        #[clap(name = "after", short, long, conflicts_with_all = &["after-failure", "after-any"])]
         dependencies: Vec<usize>,
 
+        /// Start the task once all specified tasks have finished and at least one of them failed.
+        /// If all of them succeed, this task is skipped. Useful for error handling.
+        #[clap(name = "after-failure", long, conflicts_with = "after-any")]
+        after_failure: Vec<usize>,
+
+        /// Start the task once all specified tasks have finished, no matter whether they failed.
+        /// Useful for cleanup.
+        #[clap(name = "after-any", long)]
+        after_any: Vec<usize>,
+
         /// Add some information for yourself.
         /// This string will be shown in the "status" table.
         /// There's no additional logic connected to it.
@@ -58,13 +86,176 @@ pub enum SubCommand {
         /// This is useful when scripting and working with dependencies.
         #[clap(short, long)]
         print_task_id: bool,
+
+        /// Wait for the task to start and follow its output, until it finished.
+        /// The client then exits with the exit code of the task.
+        #[clap(long, conflicts_with = "reserve")]
+        follow: bool,
+
+        /// Keep the task's logs, when it's cleaned.
+        /// They're moved to the archive and can be viewed with `pueue log --archived`.
+        #[clap(long)]
+        keep_logs: bool,
+
+        /// Write the task's stderr to its stdout log, like `2>&1`.
+        /// Both streams end up in a single log, while the task still records,
+        /// whether anything has been written to stderr.
+        #[clap(long)]
+        stderr_to_stdout: bool,
+
+        /// Kill the task, once it ran for longer than this duration, e.g. `2h`.
+        /// The task is then marked as failed due to a timeout.
+        /// Overwrites the default of the group. Accepted units are `s`, `m`, `h` and `d`.
+        #[clap(name = "max-runtime", long, parse(try_from_str=parse_max_runtime))]
+        max_runtime: Option<std::time::Duration>,
+
+        /// The amount of CPU slots the task occupies, if its group has a CPU capacity.
+        /// The task is only started, once enough slots of the group are free.
+        #[clap(long)]
+        cpus: Option<usize>,
+
+        /// Request units of one of the daemon's resource pools, e.g. `--resource gpus=2`.
+        /// The task is only started, once enough units are free. The assigned units are
+        /// exported to the task, e.g. as `PUEUE_ASSIGNED_GPUS=0,1`.
+        #[clap(long = "resource", multiple_occurrences = true, multiple_values = false, parse(try_from_str=parse_resource))]
+        resources: Vec<(String, usize)>,
+
+        /// Limit the memory of the task, e.g. `--memory-limit 4G`. Only supported on Linux.
+        /// The task is started in its own cgroup, which requires `daemon.cgroup_root`.
+        #[clap(name = "memory-limit", long, parse(try_from_str=parse_memory_limit))]
+        memory_limit: Option<u64>,
+
+        /// Limit the amount of CPUs the task may use, e.g. `--cpu-limit 1.5`. Only supported on Linux.
+        /// The task is started in its own cgroup, which requires `daemon.cgroup_root`.
+        #[clap(name = "cpu-limit", long)]
+        cpu_limit: Option<f64>,
+
+        /// Attach some metadata to the task, e.g. `--meta commit=abc123 --meta pr=42`.
+        /// It's shown by "info", included in the json output and available in callbacks.
+        #[clap(long, multiple_occurrences = true, multiple_values = false, parse(try_from_str=parse_meta))]
+        meta: Vec<(String, String)>,
+
+        /// Tag the task, e.g. `--tag nightly --tag backup`.
+        /// Tags can be used to select multiple tasks at once, e.g. via `kill --tag <tag>`.
+        #[clap(long = "tag", multiple_occurrences = true, multiple_values = false)]
+        tags: Vec<String>,
+
+        /// Don't start the task, before this file exists, e.g. `--wait-for-file /data/export.done`.
+        /// Use `--expire-in` to give up after some time.
+        #[clap(long, multiple_occurrences = true, multiple_values = false)]
+        wait_for_file: Vec<String>,
+
+        /// Don't start the task, before a GET request to this url succeeds,
+        /// e.g. `--wait-for-url https://host/health`. Use `--expire-in` to give up after some time.
+        #[clap(long, multiple_occurrences = true, multiple_values = false)]
+        wait_for_url: Vec<String>,
+
+        /// Let the daemon run this command, once the task succeeded, e.g. `--on-success "pueue start 3"`.
+        /// The same variables as in the daemon's callback are available, e.g. `{{ id }}` or `{{ stdout }}`.
+        #[clap(long)]
+        on_success: Option<String>,
+
+        /// Let the daemon run this command, once the task failed.
+        /// The same variables as in the daemon's callback are available, e.g. `{{ exit_code }}`.
+        #[clap(long)]
+        on_failure: Option<String>,
+
+        /// The shell that runs the command on Windows, either `powershell` (default) or `cmd`.
+        /// It's ignored on other platforms, where `sh` is always used.
+        #[clap(long)]
+        shell: Option<WindowsShell>,
+
+        /// Take the command from the system clipboard instead of the commandline.
+        #[clap(name = "from-clipboard", long, conflicts_with = "command")]
+        from_clipboard: bool,
+
+        /// Only reserve a task id and print it. The task isn't added yet.
+        /// Use `--commit <id>` to add the actual task under this id.
+        /// This allows to know a task's id, before its command is known.
+        #[clap(long, conflicts_with_all = &["command", "from-clipboard", "commit"])]
+        reserve: bool,
+
+        /// Add the task under an id, that has previously been reserved via `--reserve`.
+        #[clap(long, value_name = "id")]
+        commit: Option<usize>,
+
+        /// Add an array of tasks, one for each index of this range, e.g. `--array 1-100`.
+        /// `{index}` in the command and the label is replaced with the index of each task.
+        /// All tasks share the id of the array's first task as array id, which allows to kill,
+        /// pause, start and clean them as a unit, e.g. via `kill --array <id>`.
+        #[clap(long, value_name = "range", conflicts_with_all = &["reserve", "commit", "follow"], parse(try_from_str=parse_array_range))]
+        array: Option<RangeInclusive<usize>>,
+
+        /// The output format.
+        /// `json` prints the id of the new task, e.g. `{"task_id": 42}`.
+        #[clap(long, arg_enum, default_value = "text", conflicts_with_all = &["print-task-id", "follow", "array"])]
+        output: OutputFormat,
     },
+
+    /// Add a task from one of the command templates in the `client.templates` setting.
+    /// E.g. `pueue run-template encode INPUT=file.mkv` replaces `{INPUT}` in the template's command.
+    RunTemplate {
+        /// The name of the template.
+        name: String,
+
+        /// The values of the template's placeholders, e.g. `INPUT=file.mkv`.
+        #[clap(parse(try_from_str=parse_env))]
+        variables: Vec<(String, String)>,
+
+        /// Immediately start the task.
+        #[clap(name = "immediate", short, long, conflicts_with = "stashed")]
+        start_immediately: bool,
+
+        /// Create the task in Stashed state.
+        #[clap(name = "stashed", short, long, conflicts_with = "immediate")]
+        stashed: bool,
+
+        /// Assign the task to this group instead of the template's group.
+        #[clap(short, long)]
+        group: Option<String>,
+
+        /// Add some information for yourself.
+        #[clap(short, long)]
+        label: Option<String>,
+
+        /// Only return the task id instead of a text.
+        #[clap(short, long)]
+        print_task_id: bool,
+    },
+
+    /// Add several tasks of a JSON file at once. Either all of them are added or none.
+    /// The file contains a list of tasks such as
+    /// `[{"command": "make"}, {"command": "make install", "after": [0]}]`.
+    /// `after` contains the indices of earlier tasks of the file, which have to succeed first.
+    /// Tasks may also have a `group`, a `label` and be `stashed`.
+    AddBatch {
+        /// The file with the tasks.
+        file: PathBuf,
+
+        /// The group of all tasks, that don't have a group in the file.
+        #[clap(short, long)]
+        group: Option<String>,
+    },
+
     /// Remove tasks from the list.
-    /// Running or paused tasks need to be killed first.
+    /// Running or paused tasks need to be killed first, unless `--force` is given.
     Remove {
         /// The task ids to be removed.
-        #[clap(required = true)]
+        #[clap(required_unless_present_any = &["tag", "query"])]
         task_ids: Vec<usize>,
+
+        /// Remove all tasks with this tag.
+        #[clap(long, conflicts_with = "task-ids")]
+        tag: Option<String>,
+
+        /// Remove all tasks that match this query. See `status` for the syntax.
+        #[clap(long = "where", value_name = "query", conflicts_with = "task-ids")]
+        query: Option<Query>,
+
+        /// Kill running or paused tasks including their child processes and remove them,
+        /// once they've exited. Their final result is shown.
+        #[clap(short, long)]
+        force: bool,
     },
     /// Switches the queue position of two commands.
     /// Only works on queued and stashed commands.
@@ -111,6 +302,22 @@ pub enum SubCommand {
         /// Delay enqueuing these tasks until <delay> elapses. See DELAY FORMAT below.
         #[clap(name = "delay", short, long, parse(try_from_str=parse_delay_until))]
         delay_until: Option<DateTime<Local>>,
+
+        /// The output format.
+        /// `json` prints the ids of the enqueued tasks and of the tasks that couldn't be enqueued.
+        #[clap(long, arg_enum, default_value = "text")]
+        output: OutputFormat,
+    },
+
+    /// Retry a failed task later, e.g. once an outage of an upstream service has been resolved.
+    /// The task is restarted in place, but stashed until the duration elapsed.
+    Snooze {
+        /// The failed task that should be retried.
+        task_id: usize,
+
+        /// Enqueue the task after this duration, e.g. `2h`.
+        #[clap(value_name = "duration", parse(try_from_str=parse_expire_in))]
+        enqueue_at: DateTime<Local>,
     },
 
     /// Resume operation of specific tasks or groups of tasks.
@@ -127,6 +334,14 @@ pub enum SubCommand {
         #[clap(short, long, conflicts_with = "all")]
         group: Option<String>,
 
+        /// Resume all paused tasks with this tag.
+        #[clap(long, conflicts_with_all = &["task-ids", "group", "all"])]
+        tag: Option<String>,
+
+        /// Resume the paused tasks of this array and enqueue its stashed tasks.
+        #[clap(long, value_name = "id", conflicts_with_all = &["task-ids", "group", "all", "tag"])]
+        array: Option<usize>,
+
         /// Resume all groups!
         /// All groups will be set to running and paused tasks will be resumed.
         #[clap(short, long)]
@@ -143,7 +358,12 @@ pub enum SubCommand {
     /// By default, a new task will be created.
     Restart {
         /// Restart these specific tasks.
-        task_ids: Vec<usize>,
+        /// Tasks can also be selected by their label via `label:<label>`.
+        task_ids: Vec<TaskRef>,
+
+        /// Restart the task with this label.
+        #[clap(long = "label", number_of_values = 1)]
+        labels: Vec<String>,
 
         /// Restart all failed tasks accross all groups.
         /// Nice to use in combination with `-i/--in-place`.
@@ -155,6 +375,18 @@ pub enum SubCommand {
         #[clap(short = 'g', long, conflicts_with = "all-failed")]
         failed_in_group: Option<String>,
 
+        /// Like `--all-failed`, but only restart failed tasks with a specific label.
+        #[clap(short = 'l', long, conflicts_with_all = &["all-failed", "failed-in-group"])]
+        failed_with_label: Option<String>,
+
+        /// Restart all finished tasks with this tag.
+        #[clap(long)]
+        tag: Option<String>,
+
+        /// Restart all finished tasks that match this query. See `status` for the syntax.
+        #[clap(long = "where", value_name = "query")]
+        query: Option<Query>,
+
         /// Immediately start the tasks, no matter how many open slots there are.
         /// This will ignore any dependencies tasks may have.
         #[clap(short = 'k', long, conflicts_with = "stashed")]
@@ -182,6 +414,12 @@ pub enum SubCommand {
         /// Edit the tasks' path before restarting.
         #[clap(short = 'p', long)]
         edit_path: bool,
+
+        /// The output format.
+        /// `json` prints the ids of the restarted tasks and of the tasks that couldn't be restarted.
+        /// Tasks that aren't restarted in place are listed with the ids of their new copies.
+        #[clap(long, arg_enum, default_value = "text")]
+        output: OutputFormat,
     },
 
     /// Either pause running tasks or specific groups of tasks.
@@ -197,14 +435,30 @@ pub enum SubCommand {
         #[clap(short, long, conflicts_with = "all")]
         group: Option<String>,
 
+        /// Pause all running tasks with this tag.
+        /// Their groups keep running.
+        #[clap(long, conflicts_with_all = &["task-ids", "group", "all"])]
+        tag: Option<String>,
+
+        /// Pause the running tasks of this array and stash its queued tasks.
+        /// Their groups keep running.
+        #[clap(long, value_name = "id", conflicts_with_all = &["task-ids", "group", "all", "tag"])]
+        array: Option<usize>,
+
         /// Pause all groups!
         #[clap(short, long)]
         all: bool,
 
         /// Only pause the specified group and let already running tasks finish by themselves.
-        #[clap(short, long)]
+        /// This is the default, if you have the soft_pause configuration set to true.
+        #[clap(short, long, visible_alias = "soft")]
         wait: bool,
 
+        /// Also pause the running tasks of the group.
+        /// Only applies, if you have the soft_pause configuration set to true.
+        #[clap(long, conflicts_with = "wait")]
+        hard: bool,
+
         /// Also pause direct child processes of a task's main process.
         /// By default only the main process will get a SIGSTOP.
         /// This is useful when calling bash scripts, which start other processes themselves.
@@ -217,12 +471,29 @@ pub enum SubCommand {
     /// Kills all tasks of the default group when no ids are provided.
     Kill {
         /// Kill these specific tasks.
-        task_ids: Vec<usize>,
+        /// Tasks can also be selected by their label via `label:<label>`.
+        task_ids: Vec<TaskRef>,
+
+        /// Kill the task with this label.
+        #[clap(long = "label", number_of_values = 1, conflicts_with_all = &["group", "all"])]
+        labels: Vec<String>,
 
         /// Kill all running tasks in a group. This also pauses the group.
         #[clap(short, long, conflicts_with = "all")]
         group: Option<String>,
 
+        /// Kill all running tasks with this tag.
+        #[clap(long, conflicts_with_all = &["task-ids", "labels", "group", "all"])]
+        tag: Option<String>,
+
+        /// Kill the running tasks of this array. Its queued and stashed tasks are marked as killed.
+        #[clap(long, value_name = "id", conflicts_with_all = &["task-ids", "labels", "group", "all", "tag", "query"])]
+        array: Option<usize>,
+
+        /// Kill all running tasks that match this query. See `status` for the syntax.
+        #[clap(long = "where", value_name = "query", conflicts_with_all = &["group", "all", "tag"])]
+        query: Option<Query>,
+
         /// Kill all running tasks across ALL groups. This also pauses all groups.
         #[clap(short, long)]
         all: bool,
@@ -248,6 +519,15 @@ pub enum SubCommand {
         input: String,
     },
 
+    /// Attach your terminal to a running task.
+    /// Its output is followed and every line you type is sent to the task's stdin.
+    /// Press Ctrl-D to detach, the task keeps running.
+    Attach {
+        /// The id of the task.
+        /// The task can also be selected by its label via `label:<label>`.
+        task_id: TaskRef,
+    },
+
     /// Edit the command or path of a stashed or queued task.
     /// The command is edited by default.
     #[clap(verbatim_doc_comment)]
@@ -258,8 +538,119 @@ pub enum SubCommand {
         /// Edit the path of the task.
         #[clap(short, long)]
         path: bool,
+
+        /// Set an environment variable of the task instead of opening the editor,
+        /// e.g. `--env RUST_LOG=debug`.
+        #[clap(long = "env", multiple_occurrences = true, multiple_values = false, conflicts_with = "path", parse(try_from_str=parse_env))]
+        envs: Vec<(String, String)>,
+    },
+
+    /// Change the environment variables of a queued or stashed task.
+    Env {
+        #[clap(subcommand)]
+        cmd: EnvCommand,
+    },
+
+    /// Generate a static, self-contained HTML report of all tasks.
+    /// The report contains a table for each group and the last lines of each task's output.
+    Report {
+        #[clap(short, long)]
+        /// Only include tasks of a specific group
+        group: Option<String>,
+
+        /// Only include tasks that have been submitted by this client's user and host.
+        #[clap(long)]
+        mine: bool,
+
+        /// The amount of output lines that are embedded for each task.
+        #[clap(short, long, default_value = "10", conflicts_with = "gantt")]
+        lines: usize,
+
+        /// Only include tasks that finished after this point in time.
+        /// Accepts a duration into the past (`90s`, `30m`, `24h`, `7d`) or a date expression.
+        #[clap(long, parse(try_from_str=parse_since))]
+        since: Option<DateTime<Local>>,
+
+        /// Print a mermaid gantt chart with the start and end times of tasks in each group instead.
+        /// This helps to spot bottlenecks, where tasks could have been run in parallel.
+        #[clap(long)]
+        gantt: bool,
+    },
+
+    /// Show everything that's going to happen at a known point in time.
+    /// This includes the enqueueing of delayed tasks, expirations and timeouts of running tasks.
+    Schedule {
+        /// Print the events as json.
+        #[clap(short, long)]
+        json: bool,
+    },
+
+    /// Browse the history of cleaned tasks, including their results and the last lines of output.
+    History {
+        /// Only show tasks, whose command or label match this regular expression.
+        #[clap(short, long)]
+        search: Option<String>,
+
+        /// Only show tasks that finished after this point in time.
+        /// Accepts a duration into the past (`90s`, `30m`, `24h`, `7d`) or a date expression.
+        #[clap(long, parse(try_from_str=parse_since))]
+        since: Option<DateTime<Local>>,
+
+        /// Show the last lines of each task's output as well.
+        #[clap(short, long)]
+        output: bool,
+
+        /// Print the tasks as json.
+        #[clap(short, long)]
+        json: bool,
+    },
+
+    /// Show how many tasks have been run and failed in each group and their total runtime.
+    /// The statistics survive restarts, `clean` and `reset`.
+    Stats {
+        /// Print the statistics as json.
+        #[clap(short, long)]
+        json: bool,
+    },
+
+    /// Share connections to the daemon between commands, until this command is stopped.
+    /// While the broker runs, all other commands send their requests through its already
+    /// authenticated connections. This reduces the load on the daemon, if scripts run many
+    /// commands in parallel, e.g. via `xargs -P`.
+    ///
+    /// The broker listens on the unix socket configured via `client.broker_socket`.
+    Broker,
+
+    /// Print all unfinished tasks as json, e.g. `pueue export > queue.json`.
+    /// The tasks can be moved to another machine or re-created after a reset via `pueue import`.
+    Export,
+
+    /// Add the tasks of a file, that has been created via `pueue export`.
+    /// The tasks get new ids. Running tasks are queued again, stashed tasks stay stashed.
+    Import {
+        /// The file with the exported tasks.
+        file: PathBuf,
     },
 
+    /// Compare the unfinished tasks with a file, that has been created via `pueue export`.
+    /// This shows which tasks have been added, removed or changed since the snapshot,
+    /// or how the queue differs from the queue of another daemon.
+    ///
+    /// Tasks are matched by their label or, if they don't have one, by their command,
+    /// as ids differ between daemons.
+    Diff {
+        /// The file with the exported tasks.
+        file: PathBuf,
+
+        /// Print the differences as json.
+        #[clap(short, long)]
+        json: bool,
+    },
+
+    /// Show an interactive dashboard with all groups, tasks and the output of the selected task.
+    /// Tasks can be paused, started, killed and restarted from there.
+    Tui,
+
     /// Use this to add or remove groups.
     /// By default, this will simply display all known groups.
     Group {
@@ -274,7 +665,21 @@ pub enum SubCommand {
     },
 
     /// Display the current status of all tasks.
+    /// Tasks can be filtered with a query, e.g. `pueue status "status=failed AND label~=deploy"`.
+    ///
+    /// A query consists of conditions in the form of `<field><operator><value>`,
+    /// which can be combined with `AND`, `OR`, `NOT` and parentheses.
+    /// Put values with spaces or special characters into double quotes.
+    ///
+    /// Fields: id, status, group, label, command, path, tag
+    /// Operators: = (equals), != (doesn't equal), ~= (contains), <, <=, >, >= (only for the id)
+    /// Finished tasks match `status=done`, their result, e.g. `status=killed`,
+    /// and `status=failed`, if they didn't succeed.
+    #[clap(verbatim_doc_comment)]
     Status {
+        /// Only show tasks that match this query.
+        query: Option<Query>,
+
         /// Print the current state as json to stdout.
         /// This does not include stdout/stderr of tasks.
         /// Use `log -j` if you want everything.
@@ -284,6 +689,42 @@ pub enum SubCommand {
         #[clap(short, long)]
         /// Only show tasks of a specific group
         group: Option<String>,
+
+        /// The output format.
+        /// `tsv` prints unstyled tab-separated rows with a header and a stable column order.
+        /// `html` prints the same static page as `pueue report`.
+        #[clap(long, arg_enum, default_value = "table", conflicts_with = "json")]
+        format: StatusFormat,
+
+        /// Only show tasks that have been submitted by this client's user and host.
+        #[clap(long)]
+        mine: bool,
+
+        /// Show all groups, even if `client.default_group_filter` is set.
+        #[clap(short, long, conflicts_with = "group")]
+        all: bool,
+
+        /// Only show tasks with this tag.
+        #[clap(long)]
+        tag: Option<String>,
+
+        /// Show a single summary row for each array of tasks.
+        #[clap(long)]
+        collapse_arrays: bool,
+
+        /// Don't show failed tasks, that have been acknowledged via `ack`.
+        #[clap(long)]
+        hide_acknowledged: bool,
+    },
+
+    /// Display the details of a single task.
+    Info {
+        /// The id of the task.
+        task_id: usize,
+
+        /// Copy the task's command to the system clipboard.
+        #[clap(long)]
+        copy_command: bool,
     },
 
     /// Display the log output of finished tasks.
@@ -309,28 +750,72 @@ pub enum SubCommand {
         /// This is the default if only a single task is being looked at.
         #[clap(short, long)]
         full: bool,
+
+        /// Show the archived logs of cleaned tasks, see `pueue add --keep-logs`.
+        #[clap(long)]
+        archived: bool,
+
+        /// Only show tasks that finished after this point in time.
+        /// Tasks that didn't finish yet are always shown.
+        /// Accepts a duration into the past (`90s`, `30m`, `24h`, `7d`) or a date expression.
+        #[clap(long, parse(try_from_str=parse_since))]
+        since: Option<DateTime<Local>>,
+
+        /// Write the full logs of all selected tasks and a `manifest.json` into this directory,
+        /// e.g. to attach them to a bug report. Paths ending in `.tar` or `.tar.gz` create an archive.
+        #[clap(long, value_name = "path", conflicts_with_all = &["json", "lines", "full"])]
+        export: Option<PathBuf>,
     },
 
-    /// Follow the output of a currently running task.
+    /// Follow the output of currently running tasks.
     /// This command works like tail -f.
+    /// If several tasks are followed, each line is prefixed with the id of its task.
     Follow {
-        /// The id of the task you want to watch.
+        /// The ids of the tasks you want to watch.
         /// If no or multiple tasks are running, you have to specify the id.
         /// If only a single task is running, you can omit the id.
-        task_id: Option<usize>,
+        /// Tasks can also be selected by their label via `label:<label>`.
+        task_ids: Vec<TaskRef>,
+
+        /// Follow the task with this label.
+        #[clap(long, conflicts_with = "task-ids")]
+        label: Option<String>,
+
+        /// Follow all running tasks of this group.
+        #[clap(short, long, conflicts_with_all = &["task-ids", "label"])]
+        group: Option<String>,
 
         /// Show stderr instead of stdout.
         #[clap(short, long)]
         err: bool,
+
+        /// Print carriage returns as they are.
+        /// By default, they redraw the current line, so progress bars are updated in place.
+        #[clap(long)]
+        raw: bool,
+
+        /// Stop following, once the tasks finished.
+        /// The client then exits with a non-zero exit code, if any of the tasks failed.
+        /// The output is always streamed by the daemon, as it knows when the tasks finished.
+        #[clap(long)]
+        until_done: bool,
     },
 
     /// Wait until tasks are finished. This can be quite useful for scripting.
     /// By default, this will wait for all tasks in the default group to finish.
     /// Note: This will also wait for all tasks that aren't somehow 'Done'.
     /// Includes: [Paused, Stashed, Locked, Queued, ...]
+    ///
+    /// The exit code is non-zero, if any of the awaited tasks failed.
     Wait {
         /// This allows you to wait for specific tasks to finish.
-        task_ids: Vec<usize>,
+        /// Tasks can also be selected by their label via `label:<label>`.
+        task_ids: Vec<TaskRef>,
+
+        /// Wait for all tasks with this label, including tasks that are added while waiting.
+        /// Combined with `--group`, only the tasks of that group are awaited.
+        #[clap(long = "label", number_of_values = 1, conflicts_with = "all")]
+        labels: Vec<String>,
 
         /// Wait for all tasks in a specific group
         #[clap(short, long, conflicts_with = "all")]
@@ -340,16 +825,59 @@ pub enum SubCommand {
         #[clap(short, long)]
         all: bool,
 
+        /// When to stop waiting.
+        /// `done` waits until all tasks finished.
+        /// `success` stops as soon as a task failed, since not all tasks can succeed anymore.
+        /// `failed` waits until any task failed. Its exit code is non-zero, if all tasks
+        /// succeeded instead.
+        #[clap(long, arg_enum, default_value = "done")]
+        status: WaitStatus,
+
         /// Don't show any log output while waiting
         #[clap(short, long)]
         quiet: bool,
     },
 
+    /// Watch all tasks and log their status changes, until this command is stopped.
+    /// Unlike `wait`, this keeps running after all tasks finished.
+    Watch {
+        /// Only watch the tasks of this group.
+        #[clap(short, long)]
+        group: Option<String>,
+
+        /// Show a desktop notification, whenever a task finishes or fails.
+        /// This uses `notify-send` on Linux and `osascript` on macOS.
+        #[clap(short, long)]
+        notify: bool,
+    },
+
+    /// Acknowledge failed tasks, i.e. their failure is known and has been triaged.
+    /// Acknowledged tasks are shown dimmed by `status` and can be hidden via
+    /// `status --hide-acknowledged`. They and their logs are kept until they're cleaned.
+    Ack {
+        /// The failed tasks that should be acknowledged.
+        #[clap(required = true)]
+        task_ids: Vec<usize>,
+    },
+
     /// Remove all finished tasks from the list.
     Clean {
         /// Only clean tasks that finished successfully.
         #[clap(short, long)]
         successful_only: bool,
+
+        /// Only clean tasks with this tag.
+        #[clap(long)]
+        tag: Option<String>,
+
+        /// Only clean tasks of the array with this id.
+        #[clap(long, value_name = "id")]
+        array: Option<usize>,
+
+        /// Keep the logs of all cleaned tasks.
+        /// They're moved to the archive and can be viewed with `pueue log --archived`.
+        #[clap(long)]
+        keep_logs: bool,
     },
 
     /// Kill all tasks, clean up afterwards and reset EVERYTHING!
@@ -365,18 +893,58 @@ pub enum SubCommand {
     },
 
     /// Remotely shut down the daemon. Should only be used if the daemon isn't started by a service manager.
-    Shutdown,
+    Shutdown {
+        /// Don't kill running tasks. No new tasks are started and the daemon shuts down,
+        /// once all running tasks finished.
+        #[clap(long)]
+        drain: bool,
+    },
+
+    /// Manage the daemon itself.
+    Daemon {
+        #[clap(subcommand)]
+        cmd: DaemonCommand,
+    },
 
     /// Set the amount of allowed parallel tasks.
     /// By default, adjusts the amount of the default group.
     Parallel {
         /// The amount of allowed parallel tasks.
-        #[clap(validator=min_one)]
-        parallel_tasks: usize,
+        #[clap(name = "parallel-tasks", validator=min_one, required_unless_present = "serial")]
+        parallel_tasks: Option<usize>,
 
         /// Set the amount for a specific group.
         #[clap(name = "group", short, long)]
         group: Option<String>,
+
+        /// Run the tasks of the group strictly one after another, in the order they were added.
+        /// A task only starts, once all earlier tasks of the group are done.
+        /// Stashed tasks hold back all later tasks and a failed task pauses the group.
+        /// Restarting a task in-place keeps its position, while a normal restart appends a new task.
+        /// Setting an amount of parallel tasks ends the serial mode.
+        #[clap(name = "serial", long, conflicts_with = "parallel-tasks")]
+        serial: bool,
+    },
+
+    /// Check the configuration, the local setup and the connection to the daemon.
+    /// Prints all findings together with hints on how to fix any problems.
+    Doctor,
+
+    /// Update the client and the daemon to the latest release on GitHub.
+    /// Both binaries are verified against the checksums of the release before they're replaced.
+    /// This detects broken downloads, but the checksums aren't signed.
+    ///
+    /// Only use this, if pueue has been installed from the release binaries.
+    /// Otherwise use your package manager.
+    SelfUpdate {
+        /// Only check whether a newer version is available.
+        #[clap(long)]
+        check: bool,
+
+        /// Restart the daemon afterwards, so it runs the new version.
+        /// This is refused, if any tasks are running, as they would be killed.
+        #[clap(long)]
+        restart_daemon: bool,
     },
 
     /// Generates shell completion files.
@@ -390,6 +958,83 @@ pub enum SubCommand {
     },
 }
 
+#[derive(Clap, Debug)]
+pub enum DaemonCommand {
+    /// Save the state to disk right away.
+    /// Use this before backups, if the daemon delays saving the state via `daemon.state_save_mode`.
+    Flush,
+
+    /// Move the logs of all tasks to another directory, which is used for all new logs.
+    /// Running tasks keep writing to their current log files. Those are moved, once the task finished.
+    SetLogDir {
+        /// The new log directory. It's created, if it doesn't exist yet.
+        path: PathBuf,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub enum EnvCommand {
+    /// Set an environment variable of a task.
+    Set {
+        /// The id of the task.
+        task_id: usize,
+        /// The name of the variable.
+        key: String,
+        /// The new value of the variable.
+        value: String,
+    },
+    /// Remove an environment variable of a task.
+    Unset {
+        /// The id of the task.
+        task_id: usize,
+        /// The name of the variable.
+        key: String,
+    },
+}
+
+#[derive(Clap, Copy, Clone, Debug, PartialEq, ArgEnum)]
+pub enum StatusFormat {
+    Table,
+    Tsv,
+    Html,
+}
+
+/// The output format of commands that add or modify tasks.
+#[derive(Clap, Copy, Clone, Debug, PartialEq, ArgEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// The status, which `pueue wait` waits for.
+#[derive(Clap, Copy, Clone, Debug, PartialEq, ArgEnum)]
+pub enum WaitStatus {
+    Done,
+    Success,
+    Failed,
+}
+
+/// A task, that's either selected by its id or by its label (`label:<label>`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskRef {
+    Id(usize),
+    Label(String),
+}
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        if let Some(label) = src.strip_prefix("label:") {
+            return Ok(TaskRef::Label(label.to_string()));
+        }
+
+        src.parse()
+            .map(TaskRef::Id)
+            .map_err(|_| String::from("expected a task id or `label:<label>`"))
+    }
+}
+
 #[derive(Clap, Debug, PartialEq, ArgEnum)]
 pub enum Shell {
     Bash,
@@ -404,13 +1049,19 @@ pub enum Shell {
     name = "Pueue client",
     about = "Interact with the Pueue daemon",
     author = env!("CARGO_PKG_AUTHORS"),
-    version = env!("CARGO_PKG_VERSION")
+    version = env!("CARGO_PKG_VERSION"),
+    after_help = EXIT_CODES_HELP
 )]
 pub struct CliArguments {
     /// Verbose mode (-v, -vv, -vvv)
     #[clap(short, long, parse(from_occurrences))]
     pub verbose: u8,
 
+    /// Don't print anything and only set the exit code.
+    /// The output of `follow` is still shown.
+    #[clap(short, long)]
+    pub quiet: bool,
+
     /// Path to a specific pueue config file to use.
     /// This ignores all other config files.
     #[clap(short, long)]
@@ -435,6 +1086,96 @@ fn parse_delay_until(src: &str) -> Result<DateTime<Local>, String> {
     ))
 }
 
+/// Parse a duration such as `30m` and return the point in time, at which it elapses.
+fn parse_expire_in(src: &str) -> Result<DateTime<Local>, String> {
+    let duration = parse_duration(src)
+        .ok_or_else(|| String::from("could not parse as duration (e.g. 30m)"))?;
+    let duration = Duration::from_std(duration).map_err(|err| err.to_string())?;
+
+    Ok(Local::now() + duration)
+}
+
+/// Parse the maximum runtime of a task, such as `2h`.
+fn parse_max_runtime(src: &str) -> Result<std::time::Duration, String> {
+    parse_duration(src).ok_or_else(|| String::from("could not parse as duration (e.g. 2h)"))
+}
+
+fn parse_memory_limit(src: &str) -> Result<u64, String> {
+    parse_size(src).ok_or_else(|| String::from("could not parse as size (e.g. 4G)"))
+}
+
+/// Parse a `key=value` pair of task metadata.
+fn parse_meta(src: &str) -> Result<(String, String), String> {
+    match src.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(String::from(
+            "expected a key=value pair (e.g. commit=abc123)",
+        )),
+    }
+}
+
+/// Parse an environment variable such as `RUST_LOG=debug`.
+fn parse_env(src: &str) -> Result<(String, String), String> {
+    match src.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(String::from(
+            "expected a KEY=VALUE pair (e.g. RUST_LOG=debug)",
+        )),
+    }
+}
+
+/// Parse a resource request such as `gpus=2`.
+fn parse_resource(src: &str) -> Result<(String, usize), String> {
+    match src.split_once('=') {
+        Some((pool, amount)) if !pool.is_empty() => match amount.parse() {
+            Ok(amount) => Ok((pool.to_string(), amount)),
+            Err(_) => Err(format!("{} isn't a valid amount", amount)),
+        },
+        _ => Err(String::from("expected a pool=amount pair (e.g. gpus=2)")),
+    }
+}
+
+/// Parse an inclusive range of array indices such as `1-100`.
+fn parse_array_range(src: &str) -> Result<RangeInclusive<usize>, String> {
+    let error = || String::from("expected a range of indices (e.g. 1-100)");
+    let (start, end) = src.split_once('-').ok_or_else(error)?;
+    let start: usize = start.trim().parse().map_err(|_| error())?;
+    let end: usize = end.trim().parse().map_err(|_| error())?;
+    if start > end {
+        return Err(format!("the range {} is empty", src));
+    }
+
+    Ok(start..=end)
+}
+
+/// Parse a point in the past.
+/// Either a duration relative to now such as `24h` or a date expression.
+fn parse_since(src: &str) -> Result<DateTime<Local>, String> {
+    let src = src.trim();
+    if let Some(unit) = src.chars().last() {
+        if let Ok(amount) = src[..src.len() - unit.len_utf8()].parse::<i64>() {
+            let duration = match unit {
+                's' => Some(Duration::seconds(amount)),
+                'm' => Some(Duration::minutes(amount)),
+                'h' => Some(Duration::hours(amount)),
+                'd' => Some(Duration::days(amount)),
+                _ => None,
+            };
+            if let Some(duration) = duration {
+                return Ok(Local::now() - duration);
+            }
+        }
+    }
+
+    if let Ok(date_time) = parse_date_string(src, Local::now(), Dialect::Us) {
+        return Ok(date_time);
+    }
+
+    Err(String::from(
+        "could not parse as duration (e.g. 24h) or date expression",
+    ))
+}
+
 /// Validator function. The input string has to be parsable as int and bigger than 0
 fn min_one(value: &str) -> Result<(), String> {
     match value.parse::<usize>() {
@@ -1,24 +1,52 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::env::{current_dir, vars};
+use std::future::Future;
 use std::io::{self, Write};
-use std::{borrow::Cow, collections::HashMap};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::process::Child;
+use std::time::{Duration, Instant};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::crate_version;
 use colors::Colors;
+use locale::Locale;
 use log::error;
 
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
-use pueue_lib::network::secret::read_shared_secret;
-use pueue_lib::settings::{Settings, PUEUE_DEFAULT_GROUP};
+use pueue_lib::network::secret::get_handshake_secret;
+use pueue_lib::settings::{Settings, VersionMismatch, PUEUE_DEFAULT_GROUP};
+use pueue_lib::task::{DependencyMode, StartCondition, Task, TaskResult};
 
-use crate::cli::{CliArguments, SubCommand};
+use crate::cli::{CliArguments, DaemonCommand, EnvCommand, OutputFormat, StatusFormat, SubCommand};
+use crate::commands::add_follow::follow_new_task;
+use crate::commands::attach::attach;
+use crate::commands::batch::parse_batch;
+#[cfg(not(target_os = "windows"))]
+use crate::commands::broker::broker_settings;
+use crate::commands::clipboard::{copy_to_clipboard, read_clipboard};
+use crate::commands::diff::diff;
 use crate::commands::edit::edit;
-use crate::commands::get_state;
-use crate::commands::local_follow::local_follow;
+use crate::commands::export::export;
+use crate::commands::local_follow::{get_follow_task_id, get_log_directories, local_follow};
+use crate::commands::log_export::export_logs;
+use crate::commands::remote_follow::{remote_follow, remote_follow_multiple};
+use crate::commands::report::{report, ReportOptions, DEFAULT_REPORT_LINES};
 use crate::commands::restart::restart;
-use crate::commands::wait::wait;
+use crate::commands::start_daemon::{daemon_is_local, start_daemon};
+use crate::commands::template::expand_template;
+use crate::commands::tui::tui;
+use crate::commands::wait::{wait, WaitOptions};
+use crate::commands::watch::watch;
+use crate::commands::{
+    any_task_failed, find_tasks, get_state, resolve_task_ids, send_request, task_path,
+};
+use crate::display::helper::status_group_filter;
 use crate::display::*;
+use crate::exit_code::ExitCode;
+use crate::query::Query;
 
 /// This struct contains the base logic for the client.
 /// The client is responsible for connecting to the daemon, sending instructions
@@ -31,7 +59,10 @@ pub struct Client {
     opt: CliArguments,
     settings: Settings,
     colors: Colors,
+    locale: Locale,
     stream: GenericStream,
+    /// The timeout for requests, which expect a prompt answer from the daemon.
+    timeout: Option<Duration>,
 }
 
 /// This is a small helper which either returns a given group or the default group.
@@ -41,18 +72,55 @@ pub fn group_or_default(group: &Option<String>) -> String {
         .unwrap_or_else(|| PUEUE_DEFAULT_GROUP.to_string())
 }
 
+/// Determine which failed tasks should be restarted, if any.
+fn failed_selection(
+    all_failed: bool,
+    failed_in_group: &Option<String>,
+    failed_with_label: &Option<String>,
+) -> Option<FailedSelection> {
+    if all_failed {
+        Some(FailedSelection::All)
+    } else if let Some(group) = failed_in_group {
+        Some(FailedSelection::Group(group.clone()))
+    } else {
+        failed_with_label
+            .as_ref()
+            .map(|label| FailedSelection::Label(label.clone()))
+    }
+}
+
+/// Return the identity of this client in the form of `user@host`. \
+/// This identity is attached to all tasks that are submitted by this client.
+pub fn client_identity() -> String {
+    #[cfg(not(target_os = "windows"))]
+    let (user, host) = (whoami::username(), whoami::hostname());
+    #[cfg(target_os = "windows")]
+    let (user, host) = (
+        std::env::var("USERNAME").unwrap_or_default(),
+        std::env::var("COMPUTERNAME").unwrap_or_default(),
+    );
+
+    format!("{}@{}", user, host)
+}
+
 /// This is a small helper which determines the selection depending on given commandline
 /// parameters.
 /// If no parameters are given, it returns to the default group.
 pub fn selection_from_params(
     all: bool,
     group: &Option<String>,
+    tag: &Option<String>,
+    array: &Option<usize>,
     task_ids: &[usize],
 ) -> TaskSelection {
     if all {
         TaskSelection::All
     } else if let Some(group) = group {
         TaskSelection::Group(group.clone())
+    } else if let Some(tag) = tag {
+        TaskSelection::Tag(tag.clone())
+    } else if let Some(array) = array {
+        TaskSelection::Array(*array)
     } else if !task_ids.is_empty() {
         TaskSelection::TaskIds(task_ids.to_owned())
     } else {
@@ -60,49 +128,337 @@ pub fn selection_from_params(
     }
 }
 
-impl Client {
-    /// Connect to the daemon, authorize via secret and return a new initialized Client.
-    pub async fn new(settings: Settings, opt: CliArguments) -> Result<Self> {
-        // Connect to daemon and get stream used for communication.
-        let mut stream = get_client_stream(&settings.shared).await?;
-
-        // Next we do a handshake with the daemon
-        // 1. Client sends the secret to the daemon.
-        // 2. If successful, the daemon responds with their version.
-        let secret = read_shared_secret(&settings.shared.shared_secret_path())?;
-        send_bytes(&secret, &mut stream).await?;
-        let version_bytes = receive_bytes(&mut stream)
-            .await
-            .context("Failed sending secret during handshake with daemon.")?;
-
-        if version_bytes.is_empty() {
-            bail!("Daemon went away after sending secret. Did you use the correct secret?")
-        }
+/// An [AddMessage] for a task in the current directory with the client's environment.
+/// All other options are left at their defaults.
+fn plain_add_message(command: String, group: String) -> Result<AddMessage> {
+    Ok(AddMessage {
+        command,
+        path: task_path(&current_dir()?)?,
+        path_is_default: true,
+        envs: vars().collect(),
+        start_immediately: false,
+        stashed: false,
+        group,
+        enqueue_at: None,
+        dependencies: Vec::new(),
+        dependency_mode: DependencyMode::Success,
+        label: None,
+        print_task_id: false,
+        created_by: Some(client_identity()),
+        expire_at: None,
+        reserved_id: None,
+        keep_logs: false,
+        stderr_to_stdout: false,
+        max_runtime: None,
+        cpus: None,
+        resources: BTreeMap::new(),
+        memory_limit: None,
+        cpu_limit: None,
+        meta: BTreeMap::new(),
+        tags: Vec::new(),
+        start_conditions: Vec::new(),
+        on_success: None,
+        on_failure: None,
+        shell: None,
+    })
+}
+
+/// The placeholder for the index of a task in an array.
+const ARRAY_INDEX_PLACEHOLDER: &str = "{index}";
+
+/// Expand a task into an array with one task per index.
+/// `{index}` in the command and the label is replaced with the index of each task.
+fn array_from_task(message: AddMessage, indices: &RangeInclusive<usize>) -> Result<Message> {
+    if !message.command.contains(ARRAY_INDEX_PLACEHOLDER) {
+        bail!(
+            "The command of an array has to contain {}",
+            ARRAY_INDEX_PLACEHOLDER
+        );
+    }
 
-        // Check if we got valid utf8. Invalid utf8 should never happen and probably
-        let version = match String::from_utf8(version_bytes) {
-            Ok(version) => version,
-            Err(_) => {
-                bail!("Daemon went away after sending secret. Did you use the correct secret?")
+    let tasks = indices
+        .clone()
+        .map(|index| {
+            let index = index.to_string();
+            let mut task = message.clone();
+            task.command = task.command.replace(ARRAY_INDEX_PLACEHOLDER, &index);
+            task.label = task
+                .label
+                .map(|label| label.replace(ARRAY_INDEX_PLACEHOLDER, &index));
+            BatchTask {
+                task,
+                batch_dependencies: Vec::new(),
             }
-        };
+        })
+        .collect();
+
+    Ok(Message::AddBatch(AddBatchMessage { tasks, array: true }))
+}
+
+/// Whether a task has the given tag and matches the given query.
+/// Both are optional.
+fn is_selected(task: &Task, tag: &Option<String>, query: &Option<Query>) -> bool {
+    tag.iter().all(|tag| task.has_tag(tag)) && query.iter().all(|query| query.matches(task))
+}
+
+/// The configured timeout for connections and requests.
+/// A timeout of `0` disables it.
+pub fn request_timeout(settings: &Settings) -> Option<Duration> {
+    match settings.client.timeout {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    }
+}
+
+/// Wait for a request to the daemon to finish, but give up once the timeout has been reached.
+/// This way a wedged daemon or an unreachable host doesn't leave the client hanging forever.
+pub async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let duration = match timeout {
+        Some(duration) => duration,
+        None => return future.await,
+    };
+
+    match tokio::time::timeout(duration, future).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!(
+            "The daemon didn't respond within {} seconds. Is it stuck or unreachable?",
+            duration.as_secs()
+        )
+        .context(ExitCode::Connection)),
+    }
+}
+
+/// Connect to the daemon and authorize via secret.
+/// Returns the stream that's used for all further communication.
+///
+/// The whole handshake is subject to the configured timeout.
+pub async fn connect(settings: &Settings, quiet: bool) -> Result<GenericStream> {
+    with_timeout(request_timeout(settings), handshake(settings, quiet)).await
+}
+
+/// Open a new stream to the daemon and do the handshake.
+async fn handshake(settings: &Settings, quiet: bool) -> Result<GenericStream> {
+    // Connect to daemon and get stream used for communication.
+    let stream = open_stream(settings).await?;
+
+    finish_handshake(stream, settings, quiet).await
+}
+
+/// Open a new stream to the daemon.
+/// If a broker is running, the stream is opened to the broker instead.
+async fn open_stream(settings: &Settings) -> Result<GenericStream> {
+    #[cfg(not(target_os = "windows"))]
+    if let Some(shared) = broker_settings(settings) {
+        // The socket of a broker that has been stopped might still exist.
+        if let Ok(stream) = get_client_stream(&shared).await {
+            return Ok(stream);
+        }
+    }
 
-        // Info if the daemon runs a different version.
-        // Backward compatibility should work, but some features might not work as expected.
-        if version != crate_version!() {
+    get_client_stream(&settings.shared)
+        .await
+        .context(ExitCode::Connection)
+}
+
+/// Authenticate on a freshly opened stream and check the daemon's version.
+async fn finish_handshake(
+    mut stream: GenericStream,
+    settings: &Settings,
+    quiet: bool,
+) -> Result<GenericStream> {
+    let version = authenticate(&mut stream, settings).await?;
+
+    check_daemon_version(settings, &version, quiet)?;
+
+    Ok(stream)
+}
+
+/// Inform the user, if the daemon runs a different version.
+///
+/// Backward compatibility within the same minor version should work,
+/// but some features might not work as expected.
+/// Different minor versions might not understand each other's messages at all,
+/// which is why the client refuses to continue, if configured to do so.
+fn check_daemon_version(settings: &Settings, version: &str, quiet: bool) -> Result<()> {
+    if version == crate_version!() {
+        return Ok(());
+    }
+
+    if versions_compatible(version, crate_version!()) {
+        if !quiet {
             println!(
                 "Different daemon version detected '{}'. Consider restarting the daemon.",
                 version
             );
         }
+        return Ok(());
+    }
+
+    if settings.shared.version_mismatch == VersionMismatch::Refuse {
+        return Err(anyhow!(
+            "The daemon runs version {}, which isn't compatible with this client's version {}. Restart the daemon after upgrading.",
+            version,
+            crate_version!()
+        )
+        .context(ExitCode::Connection));
+    }
+
+    if !quiet {
+        eprintln!(
+            "WARNING: The daemon runs version {}, but this client runs version {}.\n\
+            Some commands might fail, since both versions might not understand each other.\n\
+            Restart the daemon after upgrading.",
+            version,
+            crate_version!()
+        );
+    }
+
+    Ok(())
+}
+
+/// Authenticate with the daemon on a freshly opened stream.
+/// Returns the version of the daemon.
+pub async fn authenticate(stream: &mut GenericStream, settings: &Settings) -> Result<String> {
+    // Next we do a handshake with the daemon
+    // 1. Client sends the secret to the daemon.
+    // 2. If successful, the daemon responds with their version.
+    let secret = get_handshake_secret(&settings.shared).context(ExitCode::Authentication)?;
+    send_bytes(&secret, stream)
+        .await
+        .context(ExitCode::Connection)?;
+    let version_bytes = receive_bytes(stream)
+        .await
+        .context("Failed sending secret during handshake with daemon.")
+        .context(ExitCode::Authentication)?;
+
+    if version_bytes.is_empty() {
+        return Err(anyhow!(
+            "Daemon went away after sending secret. Did you use the correct secret?"
+        )
+        .context(ExitCode::Authentication));
+    }
+
+    // Check if we got valid utf8. Invalid utf8 should never happen and probably
+    let version = match String::from_utf8(version_bytes) {
+        Ok(version) => version,
+        Err(_) => {
+            return Err(anyhow!(
+                "Daemon went away after sending secret. Did you use the correct secret?"
+            )
+            .context(ExitCode::Authentication));
+        }
+    };
+
+    Ok(version)
+}
+
+/// How long the client waits for an automatically started daemon to accept connections.
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connect to the daemon. \
+/// If no local daemon is running and `client.auto_start_daemon` is set, the daemon is started
+/// in the background and the client waits until it accepts connections.
+async fn connect_or_start_daemon(
+    settings: &Settings,
+    config: &Option<PathBuf>,
+    quiet: bool,
+) -> Result<GenericStream> {
+    if !settings.client.auto_start_daemon || !daemon_is_local(&settings.shared) {
+        return connect(settings, quiet).await;
+    }
+
+    let timeout = request_timeout(settings);
+    let connection = with_timeout(timeout, open_stream(settings));
+    if let Ok(stream) = connection.await {
+        return with_timeout(timeout, finish_handshake(stream, settings, quiet)).await;
+    }
+
+    let mut daemon = start_daemon(settings, config).context(ExitCode::Connection)?;
+    if !quiet {
+        eprintln!("The daemon wasn't running. Started it in the background.");
+    }
+
+    wait_for_daemon(settings, &mut daemon, quiet).await
+}
+
+/// Wait until a freshly started daemon has created its socket and accepts connections.
+pub async fn wait_for_daemon(
+    settings: &Settings,
+    daemon: &mut Child,
+    quiet: bool,
+) -> Result<GenericStream> {
+    let timeout = request_timeout(settings);
+    let start = Instant::now();
+    loop {
+        if let Ok(stream) = get_client_stream(&settings.shared).await {
+            return with_timeout(timeout, finish_handshake(stream, settings, quiet)).await;
+        }
+
+        if let Ok(Some(status)) = daemon.try_wait() {
+            return Err(anyhow!(
+                "The daemon exited right after being started ({}). Run `pueued` to see what's wrong.",
+                status
+            )
+            .context(ExitCode::Connection));
+        }
+
+        if start.elapsed() > DAEMON_START_TIMEOUT {
+            return Err(anyhow!(
+                "The daemon has been started, but didn't accept connections within {} seconds.",
+                DAEMON_START_TIMEOUT.as_secs()
+            )
+            .context(ExitCode::Connection));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// The amount of attempts to reconnect to the daemon, before giving up.
+const RECONNECT_ATTEMPTS: u32 = 10;
+
+/// Try to reconnect to the daemon after the connection has been lost.
+/// This happens, when the daemon restarts or the connection drops.
+/// Between the attempts, the client waits with an exponential backoff of up to 10 seconds.
+pub async fn reconnect(settings: &Settings, quiet: bool) -> Result<GenericStream> {
+    if !quiet {
+        eprintln!("Lost connection to the daemon. Reconnecting...");
+    }
+
+    let mut delay = Duration::from_millis(500);
+    let mut attempt = 1;
+    loop {
+        tokio::time::sleep(delay).await;
+        match connect(settings, true).await {
+            Ok(stream) => return Ok(stream),
+            Err(error) if attempt >= RECONNECT_ATTEMPTS => {
+                return Err(error.context("Failed to reconnect to the daemon"))
+            }
+            Err(_) => (),
+        }
+        attempt += 1;
+        delay = (delay * 2).min(Duration::from_secs(10));
+    }
+}
+
+impl Client {
+    /// Connect to the daemon, authorize via secret and return a new initialized Client.
+    pub async fn new(settings: Settings, opt: CliArguments) -> Result<Self> {
+        let stream = connect_or_start_daemon(&settings, &opt.config, opt.quiet).await?;
 
         let colors = Colors::new(&settings);
+        let locale = Locale::new(&settings);
+        let timeout = request_timeout(&settings);
 
         Ok(Client {
             opt,
             settings,
             colors,
+            locale,
             stream,
+            timeout,
         })
     }
 
@@ -136,7 +492,7 @@ impl Client {
         // This match handles all "complex" commands.
         match &self.opt.cmd {
             SubCommand::Reset { force, .. } => {
-                let state = get_state(&mut self.stream).await?;
+                let state = with_timeout(self.timeout, get_state(&mut self.stream)).await?;
                 let running_tasks = state
                     .tasks
                     .iter()
@@ -144,7 +500,7 @@ impl Client {
                     .collect::<Vec<_>>();
 
                 if !running_tasks.is_empty() && !force {
-                    self.handle_user_confirmation("remove running tasks", &running_tasks)?;
+                    self.handle_user_confirmation("prompt.action.remove_running", &running_tasks)?;
                 }
 
                 // Let handle_simple_command to handle `reset` after getting user permission to kill
@@ -152,78 +508,362 @@ impl Client {
                 Ok(false)
             }
 
-            SubCommand::Edit { task_id, path } => {
+            SubCommand::Edit {
+                task_id,
+                path,
+                envs,
+            } if envs.is_empty() => {
                 let message = edit(&mut self.stream, *task_id, *path).await?;
                 self.handle_response(message);
                 Ok(true)
             }
             SubCommand::Wait {
                 task_ids,
+                labels,
                 group,
                 all,
+                status,
                 quiet,
             } => {
-                let group = group_or_default(group);
-                wait(
-                    &mut self.stream,
-                    task_ids,
-                    &group,
-                    *all,
-                    *quiet,
-                    &self.colors,
-                )
-                .await?;
+                let options = WaitOptions {
+                    task_ids: resolve_task_ids(&mut self.stream, task_ids, &[]).await?,
+                    group: group.clone(),
+                    labels: labels.clone(),
+                    all: *all,
+                    status: *status,
+                    quiet: *quiet || self.opt.quiet,
+                };
+                let exit_code =
+                    wait(&mut self.stream, &options, &self.colors, &self.settings).await?;
+                if exit_code != ExitCode::Success {
+                    exit_code.exit();
+                }
                 Ok(true)
             }
             SubCommand::Restart {
                 task_ids,
+                labels,
                 all_failed,
                 failed_in_group,
+                failed_with_label,
+                tag,
+                query,
                 start_immediately,
                 stashed,
                 in_place,
                 not_in_place,
                 edit,
                 edit_path,
+                output,
             } => {
                 // `not_in_place` superseeds both other configs
                 let in_place =
                     (self.settings.client.restart_in_place || *in_place) && !*not_in_place;
-                restart(
+                // Editing the commands takes as long as the user needs.
+                let timeout = if *edit || *edit_path {
+                    None
+                } else {
+                    self.timeout
+                };
+                let mut task_ids = resolve_task_ids(&mut self.stream, task_ids, labels).await?;
+                if tag.is_some() || query.is_some() {
+                    let selected = find_tasks(&mut self.stream, |task| {
+                        task.is_done() && is_selected(task, tag, query)
+                    });
+                    task_ids.extend(selected.await?);
+                }
+                let restart = restart(
                     &mut self.stream,
-                    task_ids.clone(),
-                    *all_failed,
-                    failed_in_group.clone(),
+                    task_ids,
+                    failed_selection(*all_failed, failed_in_group, failed_with_label),
                     *start_immediately,
                     *stashed,
                     in_place,
                     *edit,
                     *edit_path,
+                    self.opt.quiet,
+                    *output == OutputFormat::Json,
+                );
+                let exit_code = with_timeout(timeout, restart).await?;
+                if exit_code != ExitCode::Success {
+                    exit_code.exit();
+                }
+                Ok(true)
+            }
+
+            SubCommand::Status {
+                group,
+                format: StatusFormat::Html,
+                mine,
+                all,
+                tag,
+                query,
+                ..
+            } => {
+                let options = ReportOptions {
+                    group: status_group_filter(group, *all, &self.settings),
+                    mine: *mine,
+                    tag: tag.clone(),
+                    query: query.clone(),
+                    lines: DEFAULT_REPORT_LINES,
+                    since: None,
+                    gantt: false,
+                };
+                let report = report(&mut self.stream, &self.settings, &self.locale, options);
+                with_timeout(self.timeout, report).await?;
+                Ok(true)
+            }
+            SubCommand::Report {
+                group,
+                mine,
+                lines,
+                since,
+                gantt,
+            } => {
+                let options = ReportOptions {
+                    group: group.clone(),
+                    mine: *mine,
+                    tag: None,
+                    query: None,
+                    lines: *lines,
+                    since: *since,
+                    gantt: *gantt,
+                };
+                let report = report(&mut self.stream, &self.settings, &self.locale, options);
+                with_timeout(self.timeout, report).await?;
+                Ok(true)
+            }
+
+            SubCommand::Watch { group, notify } => {
+                watch(
+                    &mut self.stream,
+                    group,
+                    *notify,
+                    &self.colors,
+                    &self.settings,
                 )
                 .await?;
                 Ok(true)
             }
 
-            SubCommand::Follow { task_id, err } => {
+            SubCommand::Export => {
+                with_timeout(self.timeout, export(&mut self.stream)).await?;
+                Ok(true)
+            }
+
+            SubCommand::Diff { file, json } => {
+                let diff = with_timeout(self.timeout, diff(&mut self.stream, file)).await?;
+                print_diff(&diff, *json, &self.colors, &self.locale);
+                Ok(true)
+            }
+
+            SubCommand::Log {
+                task_ids,
+                since,
+                archived,
+                export: Some(path),
+                ..
+            } => {
+                export_logs(
+                    &mut self.stream,
+                    &self.settings,
+                    task_ids,
+                    *since,
+                    *archived,
+                    path,
+                )
+                .await?;
+                Ok(true)
+            }
+
+            SubCommand::Stats { json } => {
+                let state = with_timeout(self.timeout, get_state(&mut self.stream)).await?;
+                print_statistics(&state, *json, &self.locale);
+                Ok(true)
+            }
+
+            SubCommand::Tui => {
+                tui(&mut self.stream, &self.settings, &self.colors, &self.locale).await?;
+                Ok(true)
+            }
+
+            SubCommand::Info {
+                task_id,
+                copy_command,
+            } => {
+                let state = with_timeout(self.timeout, get_state(&mut self.stream)).await?;
+                let task = match state.tasks.get(task_id) {
+                    Some(task) => task,
+                    None => {
+                        let text = format!("No task with id {}", task_id);
+                        print_error(&self.colors, &text);
+                        ExitCode::TaskNotFound.exit();
+                    }
+                };
+
+                print_task_info(task, &self.colors, &self.locale);
+                if *copy_command {
+                    copy_to_clipboard(&task.command)?;
+                }
+                Ok(true)
+            }
+
+            SubCommand::Follow {
+                task_ids,
+                label,
+                group,
+                err,
+                raw,
+                until_done,
+            } => {
+                let labels: Vec<_> = label.iter().cloned().collect();
+                let task_ids = match group {
+                    Some(group) => {
+                        let filter = |task: &Task| &task.group == group && task.is_running();
+                        find_tasks(&mut self.stream, filter).await?
+                    }
+                    None => resolve_task_ids(&mut self.stream, task_ids, &labels).await?,
+                };
+
+                // Several tasks are followed at once with prefixed output.
+                if task_ids.len() > 1 || group.is_some() {
+                    if self.settings.client.read_local_logs && !*until_done {
+                        let log_directories = get_log_directories(
+                            &mut self.stream,
+                            &self.settings.shared.pueue_directory(),
+                            &task_ids,
+                        )
+                        .await?;
+                        follow_multiple_local_task_logs(&log_directories, *err, *raw, &self.locale);
+                    } else {
+                        remote_follow_multiple(
+                            &self.settings,
+                            &task_ids,
+                            *err,
+                            *raw,
+                            self.opt.quiet,
+                            *until_done,
+                        )
+                        .await?;
+                    }
+                    if *until_done {
+                        self.exit_on_failed_tasks(&task_ids).await?;
+                    }
+                    return Ok(true);
+                }
+                // Resolve the task right away, as its result determines the exit code.
+                let task_id = task_ids.first().copied();
+                let task_id = get_follow_task_id(&mut self.stream, &task_id).await?;
+
                 // Simple log output follows for local logs don't need any communication with the daemon.
                 // Thereby we handle this separately over here.
-                if self.settings.client.read_local_logs {
+                if self.settings.client.read_local_logs && !*until_done {
                     local_follow(
                         &mut self.stream,
                         &self.settings.shared.pueue_directory(),
-                        task_id,
+                        &Some(task_id),
                         *err,
+                        *raw,
+                        &self.locale,
                     )
                     .await?;
-                    return Ok(true);
+                } else {
+                    // Remote logs are streamed by the daemon.
+                    let message = remote_follow(
+                        &mut self.stream,
+                        &self.settings,
+                        &Some(task_id),
+                        *err,
+                        *raw,
+                        self.opt.quiet,
+                        *until_done,
+                    )
+                    .await?;
+                    self.handle_response(message);
                 }
-                Ok(false)
+                if *until_done {
+                    self.exit_on_failed_tasks(&[task_id]).await?;
+                }
+                Ok(true)
+            }
+
+            SubCommand::Attach { task_id } => {
+                attach(&mut self.stream, &self.settings, task_id, self.opt.quiet).await?;
+                Ok(true)
+            }
+
+            SubCommand::Add { follow: true, .. } => {
+                // The daemon only responds with the id of the new task.
+                let mut message = self.get_message_from_opt().await?;
+                if let Message::Add(add_message) = &mut message {
+                    add_message.print_task_id = true;
+                }
+                send_request(message, &mut self.stream).await?;
+                let task_id = match receive_message(&mut self.stream).await? {
+                    Message::Success(text) => text
+                        .parse::<usize>()
+                        .context("The daemon didn't respond with a task id")?,
+                    response => {
+                        self.handle_response(response);
+                        return Ok(true);
+                    }
+                };
+
+                let result =
+                    follow_new_task(&mut self.stream, &self.settings, task_id, self.opt.quiet)
+                        .await?;
+                match result {
+                    TaskResult::Success => ExitCode::Success.exit(),
+                    TaskResult::Failed(code) => std::process::exit(code),
+                    result => {
+                        if !self.opt.quiet {
+                            let text = format!("Task {} failed: {}", task_id, result);
+                            print_error(&self.colors, &text);
+                        }
+                        ExitCode::TaskFailed.exit();
+                    }
+                }
+            }
+
+            SubCommand::Add {
+                output: OutputFormat::Json,
+                ..
+            } => {
+                // The daemon only responds with the id of the new task.
+                let mut message = self.get_message_from_opt().await?;
+                if let Message::Add(add_message) = &mut message {
+                    add_message.print_task_id = true;
+                }
+                send_request(message, &mut self.stream).await?;
+                match receive_message(&mut self.stream).await? {
+                    Message::Success(text) => {
+                        let task_id = text
+                            .parse::<usize>()
+                            .context("The daemon didn't respond with a task id")?;
+                        if !self.opt.quiet {
+                            print_added_task_json(task_id);
+                        }
+                    }
+                    response => {
+                        self.handle_response(response);
+                    }
+                };
+                Ok(true)
             }
 
             _ => Ok(false),
         }
     }
 
+    /// Exit with [ExitCode::TaskFailed], if any of the followed tasks failed.
+    async fn exit_on_failed_tasks(&mut self, task_ids: &[usize]) -> Result<()> {
+        if any_task_failed(&mut self.stream, task_ids).await? {
+            ExitCode::TaskFailed.exit();
+        }
+
+        Ok(())
+    }
+
     /// Handle logic that's super generic on the client-side.
     /// This (almost) always follows a singular ping-pong pattern.
     /// One message to the daemon, one response, Done.
@@ -233,13 +873,15 @@ impl Client {
     async fn handle_simple_command(&mut self) -> Result<()> {
         // Create the message that should be sent to the daemon
         // depending on the given commandline options.
-        let message = self.get_message_from_opt()?;
+        let message = self.get_message_from_opt().await?;
 
         // Create the message payload and send it to the daemon.
-        send_message(message, &mut self.stream).await?;
+        send_request(message, &mut self.stream).await?;
 
         // Check if we can receive the response from the daemon
-        let mut response = receive_message(&mut self.stream).await?;
+        let stream = &mut self.stream;
+        let receive = async move { Ok(receive_message(stream).await?) };
+        let mut response = with_timeout(self.timeout, receive).await?;
 
         // Check if we can receive the response from the daemon
         while self.handle_response(response) {
@@ -254,22 +896,76 @@ impl Client {
     ///
     /// If this function returns `Ok(true)`, the parent function will continue to receive
     /// and handle messages from the daemon. Otherwise the client will simply exit.
+    ///
+    /// Failures and partial successes exit the client with their respective [ExitCode].
+    /// In `--quiet` mode, nothing except streamed output is printed.
     fn handle_response(&self, message: Message) -> bool {
+        // Streamed output is the only output that's still shown in quiet mode.
+        if self.opt.quiet && !matches!(message, Message::Stream(_)) {
+            let exit_code = match &message {
+                Message::Failure(failure) => ExitCode::from_failure(failure),
+                Message::TaskActionResponse(response) => ExitCode::from_task_action(response),
+                _ => ExitCode::Success,
+            };
+            if exit_code != ExitCode::Success {
+                exit_code.exit();
+            }
+            return false;
+        }
+
         match message {
             Message::Success(text) => print_success(&self.colors, &text),
-            Message::Failure(text) => {
-                print_error(&self.colors, &text);
-                std::process::exit(1);
+            Message::Failure(failure) => {
+                print_failure(&self.colors, &failure);
+                ExitCode::from_failure(&failure).exit();
             }
-            Message::StatusResponse(state) => {
-                print_state(*state, &self.opt.cmd, &self.colors, &self.settings)
+            Message::TaskActionResponse(response) => {
+                let json = matches!(
+                    self.opt.cmd,
+                    SubCommand::Enqueue {
+                        output: OutputFormat::Json,
+                        ..
+                    }
+                );
+                if json {
+                    let failed: Vec<usize> = response.failed.keys().cloned().collect();
+                    print_task_ids_json(&response.succeeded, &failed);
+                } else {
+                    print_task_action_response(&self.colors, &response);
+                }
+                let exit_code = ExitCode::from_task_action(&response);
+                if exit_code != ExitCode::Success {
+                    exit_code.exit();
+                }
             }
-            Message::LogResponse(task_logs) => {
-                print_logs(task_logs, &self.opt.cmd, &self.colors, &self.settings)
+            Message::StatusResponse(state) => print_state(
+                *state,
+                &self.opt.cmd,
+                &self.colors,
+                &self.settings,
+                &self.locale,
+            ),
+            Message::LogResponse(task_logs) => print_logs(
+                task_logs,
+                &self.opt.cmd,
+                &self.colors,
+                &self.settings,
+                &self.locale,
+            ),
+            Message::GroupResponse(groups) => print_groups(groups, &self.colors, &self.locale),
+            Message::ScheduleResponse(events) => {
+                let json = matches!(self.opt.cmd, SubCommand::Schedule { json: true });
+                print_schedule(&events, json, &self.settings, &self.locale)
             }
-            Message::GroupResponse(groups) => print_groups(groups, &self.colors),
-            Message::Stream(text) => {
-                print!("{}", text);
+            Message::HistoryResponse(entries) => print_history(
+                &entries,
+                &self.opt.cmd,
+                &self.colors,
+                &self.settings,
+                &self.locale,
+            ),
+            Message::Stream(chunk) => {
+                print!("{}", chunk.text);
                 io::stdout().flush().unwrap();
                 return true;
             }
@@ -280,31 +976,33 @@ impl Client {
     }
 
     /// Prints a warning and prompt for given action and tasks.
+    /// The action is the catalog key of the action's description.
     /// Returns `Ok(())` if the action was confirmed.
     fn handle_user_confirmation(&self, action: &str, task_ids: &[usize]) -> Result<()> {
         // printing warning and prompt
+        let task_ids = task_ids
+            .iter()
+            .map(|t| format!("task{}", t))
+            .collect::<Vec<String>>()
+            .join(", ");
         println!(
-            "You are trying to {}: {}",
-            action,
-            task_ids
-                .iter()
-                .map(|t| format!("task{}", t.to_string()))
-                .collect::<Vec<String>>()
-                .join(", ")
+            "{}",
+            self.locale
+                .format("prompt.confirm", &[&self.locale.get(action), &task_ids])
         );
 
         let mut input = String::new();
 
         loop {
-            print!("Do you want to continue [Y/n]: ");
+            print!("{}", self.locale.get("prompt.continue"));
             io::stdout().flush().unwrap();
             input.clear();
             io::stdin().read_line(&mut input)?;
 
             match input.chars().next().unwrap() {
                 'N' | 'n' => {
-                    println!("Aborted!");
-                    std::process::exit(1);
+                    println!("{}", self.locale.get("prompt.aborted"));
+                    ExitCode::Failure.exit();
                 }
                 '\n' | 'Y' | 'y' => {
                     break;
@@ -320,7 +1018,7 @@ impl Client {
 
     /// Convert the cli command into the message that's being sent to the server,
     /// so it can be understood by the daemon.
-    fn get_message_from_opt(&self) -> Result<Message> {
+    async fn get_message_from_opt(&mut self) -> Result<Message> {
         match &self.opt.cmd {
             SubCommand::Add {
                 command,
@@ -330,20 +1028,44 @@ impl Client {
                 stashed,
                 group,
                 delay_until,
+                expire_at,
+                expire_in,
                 dependencies,
+                after_failure,
+                after_any,
                 label,
                 print_task_id,
+                keep_logs,
+                stderr_to_stdout,
+                max_runtime,
+                cpus,
+                resources,
+                memory_limit,
+                cpu_limit,
+                meta,
+                tags,
+                wait_for_file,
+                wait_for_url,
+                on_success,
+                on_failure,
+                shell,
+                from_clipboard,
+                reserve,
+                commit,
+                array,
+                follow: _,
+                output: _,
             } => {
+                if *reserve {
+                    return Ok(Message::Reserve(group_or_default(group)));
+                }
+
                 let cwd_pathbuf = if let Some(cwd) = cwd {
                     cwd.clone()
                 } else {
                     current_dir()?
                 };
-
-                let path = cwd_pathbuf
-                    .to_str()
-                    .context("Cannot parse current working directory (Invalid utf8?)")?
-                    .to_string();
+                let path = task_path(&cwd_pathbuf)?;
 
                 let mut envs = HashMap::new();
                 // Save all environment variables for later injection into the started task
@@ -353,7 +1075,9 @@ impl Client {
 
                 // Escape any special shell characters in all strings before we concatenated them
                 // to a single string.
-                let command: Vec<String> = if *escape {
+                let command: Vec<String> = if *from_clipboard {
+                    vec![read_clipboard()?]
+                } else if *escape {
                     command
                         .iter()
                         .map(|parameter| shell_escape::escape(Cow::from(parameter)).into_owned())
@@ -362,27 +1086,123 @@ impl Client {
                     command.clone()
                 };
 
+                let (dependencies, dependency_mode) = if !after_failure.is_empty() {
+                    (after_failure, DependencyMode::Failure)
+                } else if !after_any.is_empty() {
+                    (after_any, DependencyMode::Any)
+                } else {
+                    (dependencies, DependencyMode::Success)
+                };
+
+                let start_conditions = wait_for_file
+                    .iter()
+                    .cloned()
+                    .map(StartCondition::File)
+                    .chain(wait_for_url.iter().cloned().map(StartCondition::Url))
+                    .collect();
+
                 let group = group_or_default(group);
-                Ok(Message::Add(AddMessage {
+                let message = AddMessage {
                     command: command.join(" "),
                     path,
+                    path_is_default: cwd.is_none(),
                     envs,
                     start_immediately: *start_immediately,
                     stashed: *stashed,
                     group,
                     enqueue_at: *delay_until,
                     dependencies: dependencies.to_vec(),
+                    dependency_mode,
                     label: label.clone(),
                     print_task_id: *print_task_id,
-                }))
+                    created_by: Some(client_identity()),
+                    expire_at: expire_at.or(*expire_in),
+                    reserved_id: *commit,
+                    keep_logs: *keep_logs,
+                    stderr_to_stdout: *stderr_to_stdout,
+                    max_runtime: max_runtime.map(|duration| duration.as_secs()),
+                    cpus: *cpus,
+                    resources: resources.iter().cloned().collect(),
+                    memory_limit: *memory_limit,
+                    cpu_limit: *cpu_limit,
+                    meta: meta.iter().cloned().collect(),
+                    tags: tags.clone(),
+                    start_conditions,
+                    on_success: on_success.clone(),
+                    on_failure: on_failure.clone(),
+                    shell: *shell,
+                };
+
+                match array {
+                    Some(indices) => array_from_task(message, indices),
+                    None => Ok(Message::Add(message)),
+                }
             }
-            SubCommand::Remove { task_ids } => {
+            SubCommand::RunTemplate {
+                name,
+                variables,
+                start_immediately,
+                stashed,
+                group,
+                label,
+                print_task_id,
+            } => {
+                let template = match self.settings.client.templates.get(name) {
+                    Some(template) => template,
+                    None => bail!("There's no template named '{}' in client.templates", name),
+                };
+                let command = expand_template(&template.command, variables)?;
+
+                let group = group.clone().or_else(|| template.group.clone());
+                let mut message = plain_add_message(command, group_or_default(&group))?;
+                message.envs.extend(template.env.clone());
+                message.start_immediately = *start_immediately;
+                message.stashed = *stashed;
+                message.label = label.clone();
+                message.print_task_id = *print_task_id;
+
+                Ok(Message::Add(message))
+            }
+            SubCommand::AddBatch { file, group } => {
+                let content = std::fs::read_to_string(file)
+                    .with_context(|| format!("Failed to read {:?}", file))?;
+                let base = plain_add_message(String::new(), group_or_default(group))?;
+
+                Ok(Message::AddBatch(parse_batch(&content, &base)?))
+            }
+            SubCommand::Remove {
+                task_ids,
+                tag,
+                query,
+                force,
+            } => {
+                let task_ids = if tag.is_some() || query.is_some() {
+                    // Running tasks can only be removed by force.
+                    find_tasks(&mut self.stream, |task| {
+                        (*force || !task.is_running()) && is_selected(task, tag, query)
+                    })
+                    .await?
+                } else {
+                    task_ids.clone()
+                };
                 if self.settings.client.show_confirmation_questions {
-                    self.handle_user_confirmation("remove", task_ids)?;
+                    self.handle_user_confirmation("prompt.action.remove", &task_ids)?;
+                }
+                if *force {
+                    Ok(Message::ForceRemove(task_ids))
+                } else {
+                    Ok(Message::Remove(task_ids))
                 }
-                Ok(Message::Remove(task_ids.clone()))
             }
             SubCommand::Stash { task_ids } => Ok(Message::Stash(task_ids.clone())),
+            SubCommand::Snooze {
+                task_id,
+                enqueue_at,
+            } => Ok(Message::Snooze(SnoozeMessage {
+                task_ids: vec![*task_id],
+                enqueue_at: *enqueue_at,
+            })),
+            SubCommand::Ack { task_ids } => Ok(Message::Acknowledge(task_ids.clone())),
             SubCommand::Switch {
                 task_id_1,
                 task_id_2,
@@ -396,6 +1216,7 @@ impl Client {
             SubCommand::Enqueue {
                 task_ids,
                 delay_until,
+                output: _,
             } => {
                 let message = EnqueueMessage {
                     task_ids: task_ids.clone(),
@@ -406,10 +1227,12 @@ impl Client {
             SubCommand::Start {
                 task_ids,
                 group,
+                tag,
+                array,
                 all,
                 children,
             } => {
-                let selection = selection_from_params(*all, group, task_ids);
+                let selection = selection_from_params(*all, group, tag, array, task_ids);
                 let message = StartMessage {
                     tasks: selection,
                     children: *children,
@@ -419,29 +1242,47 @@ impl Client {
             SubCommand::Pause {
                 task_ids,
                 group,
+                tag,
+                array,
                 wait,
+                hard,
                 all,
                 children,
             } => {
-                let selection = selection_from_params(*all, group, task_ids);
+                // Explicitly selected tasks are always paused.
+                // The soft_pause configuration only applies to whole groups.
+                let wait =
+                    *wait || (self.settings.client.soft_pause && !*hard && task_ids.is_empty());
+                let selection = selection_from_params(*all, group, tag, array, task_ids);
                 let message = PauseMessage {
                     tasks: selection,
-                    wait: *wait,
+                    wait,
                     children: *children,
                 };
                 Ok(Message::Pause(message))
             }
             SubCommand::Kill {
                 task_ids,
+                labels,
                 group,
+                tag,
+                array,
+                query,
                 all,
                 children,
                 signal,
             } => {
+                let mut task_ids = resolve_task_ids(&mut self.stream, task_ids, labels).await?;
+                if let Some(query) = query {
+                    let selected = find_tasks(&mut self.stream, |task| {
+                        task.is_running() && query.matches(task)
+                    });
+                    task_ids.extend(selected.await?);
+                }
                 if self.settings.client.show_confirmation_questions {
-                    self.handle_user_confirmation("kill", task_ids)?;
+                    self.handle_user_confirmation("prompt.action.kill", &task_ids)?;
                 }
-                let selection = selection_from_params(*all, group, task_ids);
+                let selection = selection_from_params(*all, group, tag, array, &task_ids);
                 let message = KillMessage {
                     tasks: selection,
                     children: *children,
@@ -466,11 +1307,27 @@ impl Client {
                 }
             }
             SubCommand::Status { .. } => Ok(Message::Status),
+            SubCommand::Schedule { .. } => Ok(Message::Schedule),
+            SubCommand::Import { file } => {
+                let content = std::fs::read_to_string(file)
+                    .with_context(|| format!("Failed to read {:?}", file))?;
+                let tasks: Vec<Task> = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse the exported tasks in {:?}", file))?;
+                Ok(Message::Import(tasks))
+            }
+            SubCommand::History { search, since, .. } => {
+                Ok(Message::History(HistoryRequestMessage {
+                    search: search.clone(),
+                    since: *since,
+                }))
+            }
             SubCommand::Log {
                 task_ids,
                 lines,
                 full,
                 json,
+                archived,
+                ..
             } => {
                 let lines = determine_log_line_amount(*full, lines, *json, task_ids.len());
 
@@ -478,26 +1335,28 @@ impl Client {
                     task_ids: task_ids.clone(),
                     send_logs: !self.settings.client.read_local_logs,
                     lines,
+                    archived: *archived,
                 };
                 Ok(Message::Log(message))
             }
-            SubCommand::Follow { task_id, err } => {
-                let message = StreamRequestMessage {
-                    task_id: *task_id,
-                    err: *err,
-                };
-                Ok(Message::StreamRequest(message))
-            }
-            SubCommand::Clean { successful_only } => {
+            SubCommand::Clean {
+                successful_only,
+                tag,
+                array,
+                keep_logs,
+            } => {
                 let message = CleanMessage {
                     successful_only: *successful_only,
+                    keep_logs: *keep_logs,
+                    tag: tag.clone(),
+                    array: *array,
                 };
 
                 Ok(Message::Clean(message))
             }
             SubCommand::Reset { children, force } => {
                 if self.settings.client.show_confirmation_questions && !force {
-                    self.handle_user_confirmation("reset", &Vec::new())?;
+                    self.handle_user_confirmation("prompt.action.reset", &Vec::new())?;
                 }
 
                 let message = ResetMessage {
@@ -505,22 +1364,72 @@ impl Client {
                 };
                 Ok(Message::Reset(message))
             }
-            SubCommand::Shutdown => Ok(Message::DaemonShutdown(Shutdown::Graceful)),
+            SubCommand::Shutdown { drain } => {
+                let shutdown = if *drain {
+                    Shutdown::Drain
+                } else {
+                    Shutdown::Graceful
+                };
+                Ok(Message::DaemonShutdown(shutdown))
+            }
+            SubCommand::Daemon {
+                cmd: DaemonCommand::Flush,
+            } => Ok(Message::Flush),
+            SubCommand::Daemon {
+                cmd: DaemonCommand::SetLogDir { path },
+            } => Ok(Message::SetLogDirectory(current_dir()?.join(path))),
+            SubCommand::Edit { task_id, envs, .. } => Ok(Message::EditEnv(EditEnvMessage {
+                task_id: *task_id,
+                set: envs.iter().cloned().collect(),
+                unset: Vec::new(),
+            })),
+            SubCommand::Env { cmd } => {
+                let message = match cmd {
+                    EnvCommand::Set {
+                        task_id,
+                        key,
+                        value,
+                    } => EditEnvMessage {
+                        task_id: *task_id,
+                        set: BTreeMap::from([(key.clone(), value.clone())]),
+                        unset: Vec::new(),
+                    },
+                    EnvCommand::Unset { task_id, key } => EditEnvMessage {
+                        task_id: *task_id,
+                        set: BTreeMap::new(),
+                        unset: vec![key.clone()],
+                    },
+                };
+                Ok(Message::EditEnv(message))
+            }
             SubCommand::Parallel {
                 parallel_tasks,
                 group,
+                serial,
             } => {
                 let group = group_or_default(group);
                 let message = ParallelMessage {
-                    parallel_tasks: *parallel_tasks,
+                    parallel_tasks: parallel_tasks.unwrap_or(1),
                     group,
+                    serial: *serial,
                 };
                 Ok(Message::Parallel(message))
             }
             SubCommand::Completions { .. } => bail!("Completions have to be handled earlier"),
+            SubCommand::Doctor => bail!("The doctor has to be handled earlier"),
+            SubCommand::SelfUpdate { .. } => bail!("Self-updates have to be handled earlier"),
             SubCommand::Restart { .. } => bail!("Restarts have to be handled earlier"),
-            SubCommand::Edit { .. } => bail!("Edits have to be handled earlier"),
             SubCommand::Wait { .. } => bail!("Wait has to be handled earlier"),
+            SubCommand::Watch { .. } => bail!("Watch has to be handled earlier"),
+            SubCommand::Follow { .. } => bail!("Follow has to be handled earlier"),
+            SubCommand::Attach { .. } => bail!("Attach has to be handled earlier"),
+            SubCommand::Report { .. } => bail!("Reports have to be handled earlier"),
+            SubCommand::Export => bail!("Exports have to be handled earlier"),
+            SubCommand::Diff { .. } => bail!("Diffs have to be handled earlier"),
+            SubCommand::Broker => bail!("The broker has to be handled earlier"),
+            SubCommand::Stats { .. } => bail!("Statistics have to be handled earlier"),
+            SubCommand::Info { .. } => bail!("Info has to be handled earlier"),
+            SubCommand::Tui => bail!("The dashboard has to be handled earlier"),
         }
     }
 }
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use pueue_lib::network::message::Message;
+use pueue_lib::network::protocol::GenericStream;
+use pueue_lib::settings::Settings;
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use crate::commands::get_state;
+use crate::commands::remote_follow::remote_follow;
+
+/// How often the state is checked, while waiting for the task to start.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Wait for a newly added task to start, print its output until it finished
+/// and return the task's result.
+///
+/// The output is always streamed by the daemon, as it knows when the task finished.
+/// Tasks that never start, e.g. due to a failed dependency, aren't followed at all.
+pub async fn follow_new_task(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    task_id: usize,
+    quiet: bool,
+) -> Result<TaskResult> {
+    let started = loop {
+        let state = get_state(stream).await?;
+        let task = match state.tasks.get(&task_id) {
+            Some(task) => task,
+            None => bail!("Task {} has been removed", task_id),
+        };
+        if task.start.is_some() || task.is_done() {
+            break task.start.is_some();
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    };
+
+    if started {
+        let message =
+            remote_follow(stream, settings, &Some(task_id), false, false, quiet, true).await?;
+        if let Message::Failure(failure) = message {
+            bail!(failure.text);
+        }
+    }
+
+    let state = get_state(stream).await?;
+    match state.tasks.get(&task_id).map(|task| &task.status) {
+        Some(TaskStatus::Done(result)) => Ok(result.clone()),
+        Some(_) => bail!(
+            "Task {} didn't finish, but its log files have gone away",
+            task_id
+        ),
+        None => bail!("Task {} has been removed", task_id),
+    }
+}
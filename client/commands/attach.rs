@@ -0,0 +1,108 @@
+use std::io::{stdin, BufRead};
+use std::thread;
+
+use anyhow::{bail, Result};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::Settings;
+
+use crate::cli::TaskRef;
+use crate::client::connect;
+use crate::commands::remote_follow::remote_follow;
+use crate::commands::{find_task_by_label, get_state, send_request};
+
+/// Connect the terminal to a running task.
+///
+/// The task's output is streamed over a second connection, while every line read from stdin
+/// is sent to the task's stdin. The client detaches on EOF (Ctrl-D) and stops,
+/// once the task finished and all of its output has been printed.
+pub async fn attach(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    task_ref: &TaskRef,
+    quiet: bool,
+) -> Result<()> {
+    let state = get_state(stream).await?;
+    let task_id = match task_ref {
+        TaskRef::Id(id) => *id,
+        TaskRef::Label(label) => find_task_by_label(&state, label)?,
+    };
+    match state.tasks.get(&task_id) {
+        Some(task) if task.is_running() => (),
+        Some(task) => bail!("Task {} isn't running ({})", task_id, task.status),
+        None => bail!("There's no task with id {}", task_id),
+    }
+    if !quiet {
+        eprintln!("Attached to task {}. Press Ctrl-D to detach.", task_id);
+    }
+
+    let output = async {
+        let mut stream = connect(settings, quiet).await?;
+        remote_follow(
+            &mut stream,
+            settings,
+            &Some(task_id),
+            false,
+            false,
+            quiet,
+            true,
+        )
+        .await
+    };
+    tokio::pin!(output);
+
+    let mut lines = read_stdin_lines();
+    loop {
+        tokio::select! {
+            // The task finished.
+            message = &mut output => {
+                if let Message::Failure(failure) = message? {
+                    bail!(failure.text);
+                }
+                return Ok(());
+            }
+            line = lines.recv() => {
+                let line = match line {
+                    Some(line) => line,
+                    None => {
+                        if !quiet {
+                            eprintln!("Detached from task {}.", task_id);
+                        }
+                        return Ok(());
+                    }
+                };
+
+                let message = Message::Send(SendMessage {
+                    task_id,
+                    input: format!("{}\n", line),
+                });
+                send_request(message, stream).await?;
+                match receive_message(stream).await? {
+                    Message::Success(_) => (),
+                    Message::Failure(failure) => bail!(failure.text),
+                    message => bail!("Received an unexpected response: {:?}", message),
+                }
+            }
+        }
+    }
+}
+
+/// Read the lines of stdin on a separate thread.
+///
+/// Reads from stdin can't be cancelled. Other than tokio's stdin, which blocks the shutdown
+/// of the runtime until the pending read finished, this thread doesn't keep the client alive.
+/// The channel is closed on EOF or once stdin can't be read anymore.
+fn read_stdin_lines() -> UnboundedReceiver<String> {
+    let (sender, receiver) = unbounded_channel();
+    thread::spawn(move || {
+        for line in stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use serde_derive::Deserialize;
+
+use pueue_lib::network::message::{AddBatchMessage, AddMessage, BatchTask};
+
+/// A single task of a batch file, see `pueue add-batch`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchFileTask {
+    command: String,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    stashed: bool,
+    /// The indices of earlier tasks of the same file, that have to succeed first.
+    #[serde(default)]
+    after: Vec<usize>,
+}
+
+/// Parse a JSON batch file, which contains a list of tasks. \
+/// Every task is created from the `base` message, which contains the client's environment.
+/// Tasks reference earlier tasks of the file by their index, which is resolved by the daemon.
+pub fn parse_batch(content: &str, base: &AddMessage) -> Result<AddBatchMessage> {
+    let file_tasks: Vec<BatchFileTask> =
+        serde_json::from_str(content).context("Failed to parse the batch file")?;
+
+    let tasks = file_tasks
+        .into_iter()
+        .map(|file_task| {
+            let mut task = base.clone();
+            task.command = file_task.command;
+            task.label = file_task.label;
+            task.stashed = file_task.stashed;
+            if let Some(group) = file_task.group {
+                task.group = group;
+            }
+            BatchTask {
+                task,
+                batch_dependencies: file_task.after,
+            }
+        })
+        .collect();
+
+    Ok(AddBatchMessage {
+        tasks,
+        array: false,
+    })
+}
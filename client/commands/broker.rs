@@ -0,0 +1,158 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::{Settings, Shared};
+
+use crate::client::authenticate;
+
+/// The maximum amount of idle connections to the daemon, that are kept open.
+const MAX_IDLE_CONNECTIONS: usize = 16;
+
+/// The connection settings of the broker's unix socket, if a broker is configured.
+pub fn broker_settings(settings: &Settings) -> Option<Shared> {
+    let socket = settings.client.broker_socket.as_ref()?;
+    let mut shared = settings.shared.clone();
+    shared.use_unix_socket = true;
+    shared.unix_socket_path = socket.clone();
+
+    Some(shared)
+}
+
+/// Authenticated connections to the daemon, that aren't used by any client right now.
+struct Pool {
+    settings: Settings,
+    idle: Mutex<Vec<GenericStream>>,
+}
+
+impl Pool {
+    /// Reuse an idle connection or open a new one.
+    async fn checkout(&self) -> Result<GenericStream> {
+        if let Some(stream) = self.idle.lock().unwrap().pop() {
+            return Ok(stream);
+        }
+
+        self.connect().await.map(|(stream, _)| stream)
+    }
+
+    /// Open and authenticate a new connection to the daemon.
+    /// Returns the connection together with the daemon's version.
+    async fn connect(&self) -> Result<(GenericStream, String)> {
+        let mut stream = get_client_stream(&self.settings.shared)
+            .await
+            .context("Failed to connect to the daemon")?;
+        let version = authenticate(&mut stream, &self.settings).await?;
+
+        Ok((stream, version))
+    }
+
+    /// Keep a connection for the next client, once its request has been answered.
+    fn checkin(&self, stream: GenericStream) {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < MAX_IDLE_CONNECTIONS {
+            idle.push(stream);
+        }
+    }
+}
+
+/// Run a local broker, which lets bursts of commands share authenticated connections.
+///
+/// Commands connect to the broker's unix socket instead of the daemon and the broker forwards
+/// each request over an idle connection to the daemon. This way, the daemon only has to accept
+/// and authenticate a few connections, even if scripts run many commands in parallel.
+/// Only clients of the same user are accepted.
+pub async fn broker(settings: Settings) -> Result<()> {
+    let broker_shared = match broker_settings(&settings) {
+        Some(shared) => shared,
+        None => bail!("Please set client.broker_socket to run the broker"),
+    };
+
+    let pool = Arc::new(Pool {
+        settings,
+        idle: Mutex::new(Vec::new()),
+    });
+    // The broker reports the version of the daemon to its clients.
+    let (stream, version) = pool.connect().await?;
+    pool.checkin(stream);
+
+    let listener = get_listener(&broker_shared).await?;
+    info!("Broker listens on {:?}", broker_shared.unix_socket_path());
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to accept client: {:?}", err);
+                continue;
+            }
+        };
+
+        let pool = pool.clone();
+        let version = version.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle_client(stream, &pool, &version).await {
+                warn!("Client connection failed: {:?}", err);
+            }
+        });
+    }
+}
+
+/// Do the handshake with a client and forward all of its requests to the daemon.
+async fn handle_client(mut client: GenericStream, pool: &Pool, version: &str) -> Result<()> {
+    // The client sends its secret, but it's authenticated via the socket's peer credentials.
+    let payload = receive_bytes(&mut client).await?;
+    if payload.is_empty() {
+        return Ok(());
+    }
+    if !client.peer_is_same_user() {
+        bail!("Rejected client, which belongs to a different user");
+    }
+    send_bytes(version.as_bytes(), &mut client).await?;
+
+    loop {
+        // The client went away.
+        let request = match receive_message(&mut client).await {
+            Ok(request) => request,
+            Err(_) => return Ok(()),
+        };
+
+        let exclusive = needs_exclusive_connection(&request);
+        let mut daemon = pool.checkout().await?;
+        // Idle connections might have been closed by the daemon in the meantime.
+        if send_message(request.clone(), &mut daemon).await.is_err() {
+            daemon = pool.connect().await?.0;
+            send_message(request, &mut daemon).await?;
+        }
+
+        if exclusive {
+            // Relay everything, until either side goes away.
+            // The connection isn't reused, as the daemon might still be sending.
+            loop {
+                let response = match receive_message(&mut daemon).await {
+                    Ok(response) => response,
+                    Err(_) => return Ok(()),
+                };
+                if send_message(response, &mut client).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
+        let response = receive_message(&mut daemon).await?;
+        pool.checkin(daemon);
+        send_message(response, &mut client).await?;
+    }
+}
+
+/// Requests, to which the daemon responds with more than a single message
+/// or after which it closes the connection.
+fn needs_exclusive_connection(message: &Message) -> bool {
+    match message {
+        Message::Traced(traced) => needs_exclusive_connection(&traced.message),
+        Message::StreamRequest(_) | Message::MirrorRequest(_) | Message::DaemonShutdown(_) => true,
+        _ => false,
+    }
+}
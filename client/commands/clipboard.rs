@@ -0,0 +1,31 @@
+use anyhow::{bail, Context, Result};
+use arboard::Clipboard;
+
+/// Copy some text to the system clipboard.
+///
+/// On Linux, the clipboard content is owned by the process that set it.
+/// It only outlives the client, if a clipboard manager is running.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut clipboard = Clipboard::new().context("Failed to access the clipboard")?;
+    clipboard
+        .set_text(text.to_string())
+        .context("Failed to copy to the clipboard")?;
+
+    Ok(())
+}
+
+/// Read the current text content of the system clipboard.
+/// Surrounding whitespace, such as a trailing newline, is removed.
+pub fn read_clipboard() -> Result<String> {
+    let mut clipboard = Clipboard::new().context("Failed to access the clipboard")?;
+    let text = clipboard
+        .get_text()
+        .context("Failed to read from the clipboard")?;
+
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("The clipboard is empty");
+    }
+
+    Ok(text.to_string())
+}
@@ -0,0 +1,165 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_derive::Serialize;
+
+use pueue_lib::network::protocol::*;
+use pueue_lib::task::Task;
+
+use crate::commands::export::exported_tasks;
+use crate::commands::get_state;
+
+/// A task, that exists in both sets of tasks, but has been changed.
+#[derive(Serialize)]
+pub struct ChangedTask {
+    pub before: Task,
+    pub after: Task,
+    /// The names of all fields, that differ.
+    pub fields: Vec<&'static str>,
+}
+
+/// The differences between two sets of tasks.
+#[derive(Default, Serialize)]
+pub struct TaskDiff {
+    pub added: Vec<Task>,
+    pub removed: Vec<Task>,
+    pub changed: Vec<ChangedTask>,
+}
+
+impl TaskDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare the unfinished tasks of the daemon with the tasks of a file,
+/// that has been created via `pueue export`.
+pub async fn diff(stream: &mut GenericStream, file: &Path) -> Result<TaskDiff> {
+    let content =
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read {:?}", file))?;
+    let snapshot: Vec<Task> = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse the exported tasks in {:?}", file))?;
+
+    let state = get_state(stream).await?;
+    Ok(diff_tasks(snapshot, exported_tasks(state)))
+}
+
+/// Tasks are matched by their label or, if they don't have one, by their command.
+/// Ids aren't used, as they differ between daemons and whenever tasks are added again.
+/// If several tasks have the same label or command, they're matched in the order of their ids.
+fn diff_tasks(before: Vec<Task>, mut after: Vec<Task>) -> TaskDiff {
+    let mut unmatched: BTreeMap<String, VecDeque<Task>> = BTreeMap::new();
+    let mut before = before;
+    before.sort_by_key(|task| task.id);
+    for task in before {
+        unmatched
+            .entry(match_key(&task))
+            .or_default()
+            .push_back(task);
+    }
+
+    let mut diff = TaskDiff::default();
+    after.sort_by_key(|task| task.id);
+    for task in after {
+        let previous = unmatched
+            .get_mut(&match_key(&task))
+            .and_then(|tasks| tasks.pop_front());
+        match previous {
+            Some(previous) => {
+                let fields = changed_fields(&previous, &task);
+                if !fields.is_empty() {
+                    diff.changed.push(ChangedTask {
+                        before: previous,
+                        after: task,
+                        fields,
+                    });
+                }
+            }
+            None => diff.added.push(task),
+        }
+    }
+
+    diff.removed = unmatched.into_values().flatten().collect();
+    diff.removed.sort_by_key(|task| task.id);
+
+    diff
+}
+
+fn match_key(task: &Task) -> String {
+    match &task.label {
+        Some(label) => format!("label {}", label),
+        None => format!("command {}", task.command),
+    }
+}
+
+/// The fields, that describe what a task does and where it runs.
+/// The status and environment variables are ignored, as they naturally differ.
+fn changed_fields(before: &Task, after: &Task) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if before.command != after.command {
+        fields.push("command");
+    }
+    if before.path != after.path {
+        fields.push("path");
+    }
+    if before.group != after.group {
+        fields.push("group");
+    }
+    // Dependency ids differ between daemons, so only their number is compared.
+    if before.dependencies.len() != after.dependencies.len() {
+        fields.push("dependencies");
+    }
+    if before.tags != after.tags {
+        fields.push("tags");
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use pueue_lib::task::TaskStatus;
+
+    use super::*;
+
+    fn task(id: usize, command: &str, label: Option<&str>) -> Task {
+        let mut task = Task::new(
+            command.into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Queued,
+            Vec::new(),
+            label.map(String::from),
+        );
+        task.id = id;
+        task
+    }
+
+    #[test]
+    fn test_diff_tasks() {
+        let before = vec![
+            task(0, "ls", None),
+            task(1, "ls", None),
+            task(2, "migrate 1", Some("migration")),
+            task(3, "rm -rf build", None),
+        ];
+        let after = vec![
+            task(10, "ls", None),
+            task(11, "migrate 2", Some("migration")),
+            task(12, "make", None),
+        ];
+
+        let diff = diff_tasks(before, after);
+        let ids = |tasks: &[Task]| tasks.iter().map(|task| task.id).collect::<Vec<_>>();
+        assert_eq!(ids(&diff.added), vec![12]);
+        assert_eq!(ids(&diff.removed), vec![1, 3]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].before.id, 2);
+        assert_eq!(diff.changed[0].fields, vec!["command"]);
+    }
+}
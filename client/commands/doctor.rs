@@ -0,0 +1,276 @@
+use std::fs::{remove_file, File};
+use std::path::{Path, PathBuf};
+
+use clap::crate_version;
+use crossterm::style::Color;
+
+use pueue_lib::network::protocol::{get_client_stream, versions_compatible};
+use pueue_lib::settings::{Settings, VersionMismatch};
+
+use crate::client::{authenticate, request_timeout, with_timeout};
+use crate::display::helper::style_text;
+use crate::exit_code::ExitCode;
+
+/// Warn, if less than this amount of bytes is available for the task logs.
+const MIN_FREE_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// How bad a finding of the doctor is.
+#[derive(Clone, Copy, PartialEq)]
+enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// A single finding of the doctor. \
+/// Problems come with a hint on how to fix them.
+struct Finding {
+    severity: Severity,
+    message: String,
+    hint: Option<String>,
+}
+
+/// Collects the findings of all checks.
+#[derive(Default)]
+struct Findings(Vec<Finding>);
+
+impl Findings {
+    fn ok(&mut self, message: String) {
+        self.push(Severity::Ok, message, None);
+    }
+
+    fn warning(&mut self, message: String, hint: String) {
+        self.push(Severity::Warning, message, Some(hint));
+    }
+
+    fn error(&mut self, message: String, hint: String) {
+        self.push(Severity::Error, message, Some(hint));
+    }
+
+    fn push(&mut self, severity: Severity, message: String, hint: Option<String>) {
+        self.0.push(Finding {
+            severity,
+            message,
+            hint,
+        });
+    }
+
+    /// Print all findings and determine the exit code.
+    /// Warnings don't result in a failure.
+    fn print(&self) -> ExitCode {
+        for finding in &self.0 {
+            let (label, color) = match finding.severity {
+                Severity::Ok => ("[ok]     ", Color::Green),
+                Severity::Warning => ("[warning]", Color::Yellow),
+                Severity::Error => ("[error]  ", Color::Red),
+            };
+            println!(
+                "{} {}",
+                style_text(label, Some(color), None),
+                finding.message
+            );
+            if let Some(hint) = &finding.hint {
+                println!("          {}", hint);
+            }
+        }
+
+        if self
+            .0
+            .iter()
+            .any(|finding| finding.severity == Severity::Error)
+        {
+            ExitCode::Failure
+        } else {
+            ExitCode::Success
+        }
+    }
+}
+
+/// Check the configuration, the local setup and the connection to the daemon.
+/// Every check results in a finding, problems come with a hint on how to fix them.
+///
+/// This doesn't stop at the first problem, so users get the full picture in one go.
+pub async fn doctor(config: &Option<PathBuf>) -> ExitCode {
+    let mut findings = Findings::default();
+
+    let settings = match Settings::read_with_defaults(true, config) {
+        Ok(settings) => {
+            findings.ok("The configuration is valid".into());
+            settings
+        }
+        Err(err) => {
+            // Nothing else can be checked without a valid configuration.
+            findings.error(
+                format!("Failed to read the configuration: {}", err),
+                "Fix the configuration file or start the daemon once to create a default one."
+                    .into(),
+            );
+            return findings.print();
+        }
+    };
+
+    check_directories(&settings, &mut findings);
+    check_disk_space(&settings, &mut findings);
+    check_daemon(&settings, &mut findings).await;
+
+    findings.print()
+}
+
+/// Check whether the pueue directory and the task log directory exist and are writable.
+fn check_directories(settings: &Settings, findings: &mut Findings) {
+    let pueue_directory = settings.shared.pueue_directory();
+    for directory in [pueue_directory, settings.shared.log_directory()] {
+        if !directory.is_dir() {
+            findings.error(
+                format!("The directory {:?} doesn't exist", directory),
+                "Start the daemon once, it creates all directories it needs.".into(),
+            );
+        } else if !is_writable(&directory) {
+            findings.error(
+                format!("The directory {:?} isn't writable", directory),
+                "Check the owner and permissions of this directory.".into(),
+            );
+        } else {
+            findings.ok(format!("The directory {:?} is writable", directory));
+        }
+    }
+}
+
+/// Check whether we can create files inside a directory.
+fn is_writable(directory: &Path) -> bool {
+    let path = directory.join(".pueue_doctor");
+    match File::create(&path) {
+        Ok(_) => remove_file(&path).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Check whether there's enough space left for the task logs.
+#[cfg(not(target_os = "windows"))]
+fn check_disk_space(settings: &Settings, findings: &mut Findings) {
+    let pueue_directory = settings.shared.pueue_directory();
+    let stats = match nix::sys::statvfs::statvfs(&pueue_directory) {
+        Ok(stats) => stats,
+        // A missing directory has already been reported.
+        Err(_) => return,
+    };
+
+    let available = stats.blocks_available() as u64 * stats.fragment_size() as u64;
+    let available_mb = available / 1024 / 1024;
+    if available < MIN_FREE_SPACE {
+        findings.warning(
+            format!(
+                "Only {} MB of disk space left for the task logs",
+                available_mb
+            ),
+            "Free some disk space or clean up old task logs with `pueue clean`.".into(),
+        );
+    } else {
+        findings.ok(format!(
+            "{} MB of disk space left for the task logs",
+            available_mb
+        ));
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn check_disk_space(_settings: &Settings, _findings: &mut Findings) {}
+
+/// Check whether the daemon is reachable, whether it accepts our credentials and
+/// whether it runs the same version as this client.
+async fn check_daemon(settings: &Settings, findings: &mut Findings) {
+    let shared = &settings.shared;
+    #[cfg(not(target_os = "windows"))]
+    let address = if shared.use_unix_socket {
+        format!("the unix socket {:?}", shared.unix_socket_path())
+    } else {
+        format!("{}:{}", shared.host, shared.port)
+    };
+    #[cfg(target_os = "windows")]
+    let address = format!("{}:{}", shared.host, shared.port);
+
+    let timeout = request_timeout(settings);
+    let connection = with_timeout(timeout, async { Ok(get_client_stream(shared).await?) });
+    let mut stream = match connection.await {
+        Ok(stream) => {
+            findings.ok(format!("The daemon is reachable via {}", address));
+            stream
+        }
+        Err(err) => {
+            findings.error(
+                format!("The daemon isn't reachable via {}: {:#}", address, err),
+                "Start the daemon with `pueued -d` or check the connection settings.".into(),
+            );
+            return;
+        }
+    };
+
+    if !shared.use_peer_credentials() && !shared.shared_secret_path().exists() {
+        findings.error(
+            format!(
+                "The shared secret at {:?} doesn't exist",
+                shared.shared_secret_path()
+            ),
+            "Copy the secret from the daemon's machine or start the daemon once.".into(),
+        );
+        return;
+    }
+
+    let version = match with_timeout(timeout, authenticate(&mut stream, settings)).await {
+        Ok(version) => {
+            if shared.use_peer_credentials() {
+                findings.ok("The daemon accepted our unix socket credentials".into());
+            } else {
+                findings.ok("The daemon accepted our shared secret".into());
+            }
+            version
+        }
+        Err(err) => {
+            findings.error(
+                format!("The daemon rejected the connection: {:#}", err),
+                "Make sure that the client and the daemon use the same secret and run as the same user."
+                    .into(),
+            );
+            return;
+        }
+    };
+
+    let message = format!(
+        "The daemon runs version {}, but the client runs version {}",
+        version,
+        crate_version!()
+    );
+    let hint = "Restart the daemon, so it runs the same version as the client.".to_string();
+    if version == crate_version!() {
+        findings.ok(format!("The daemon and the client run version {}", version));
+    } else if versions_compatible(&version, crate_version!()) {
+        findings.warning(message, hint);
+    } else if shared.version_mismatch == VersionMismatch::Refuse {
+        findings.error(
+            format!("{}. Both refuse to talk to each other", message),
+            hint,
+        );
+    } else {
+        findings.warning(
+            format!("{}. They might not understand each other", message),
+            hint,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_only_errors_fail() {
+        let mut findings = Findings::default();
+        findings.ok("fine".into());
+        findings.warning("not great".into(), "hint".into());
+        assert_eq!(findings.print(), ExitCode::Success);
+
+        findings.error("broken".into(), "hint".into());
+        assert_eq!(findings.print(), ExitCode::Failure);
+    }
+}
@@ -1,12 +1,25 @@
 use std::env;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
 use std::process::Command;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use tempfile::NamedTempFile;
 
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
+use pueue_lib::task::TaskStatus;
+
+use crate::commands::{send_request, task_path};
+
+/// Some information about the task that's edited.
+/// This is shown as a commented header above the edited line.
+pub struct EditContext<'a> {
+    pub task_id: usize,
+    pub group: &'a str,
+    pub label: Option<&'a str>,
+    pub status: Option<&'a TaskStatus>,
+}
 
 /// This function handles the logic for editing tasks.
 /// At first, we request the daemon to send us the task to edit.
@@ -18,7 +31,7 @@ use pueue_lib::network::protocol::*;
 pub async fn edit(stream: &mut GenericStream, task_id: usize, edit_path: bool) -> Result<Message> {
     // Request the data to edit from the server and issue a task-lock while doing so.
     let init_message = Message::EditRequest(task_id);
-    send_message(init_message, stream).await?;
+    send_request(init_message, stream).await?;
 
     let init_response = receive_message(stream).await?;
 
@@ -31,13 +44,38 @@ pub async fn edit(stream: &mut GenericStream, task_id: usize, edit_path: bool) -
         return Ok(init_response);
     };
 
+    let context = EditContext {
+        task_id,
+        group: &init_response.group,
+        label: init_response.label.as_deref(),
+        status: init_response.prev_status.as_ref(),
+    };
+
     // Edit either the path or the command, depending on the `path` flag.
-    let mut command = init_response.command;
-    let mut path = init_response.path;
-    if edit_path {
-        path = edit_line(&path)?;
+    let original = if edit_path {
+        &init_response.path
     } else {
-        command = edit_line(&command)?
+        &init_response.command
+    };
+    let edited = match edit_line(original, &context, edit_path) {
+        Ok(edited) => edited,
+        Err(error) => {
+            // The task is still locked. Send the original values to unlock it again.
+            let unlock_message = Message::Edit(EditMessage {
+                task_id,
+                command: init_response.command.clone(),
+                path: init_response.path.clone(),
+            });
+            send_request(unlock_message, stream).await?;
+            receive_message(stream).await?;
+            return Err(error);
+        }
+    };
+
+    let (command, path) = if edit_path {
+        (init_response.command, task_path(Path::new(&edited))?)
+    } else {
+        (edited, init_response.path)
     };
 
     // Create a new message with the edited command.
@@ -46,7 +84,7 @@ pub async fn edit(stream: &mut GenericStream, task_id: usize, edit_path: bool) -
         command,
         path,
     });
-    send_message(edit_message, stream).await?;
+    send_request(edit_message, stream).await?;
 
     Ok(receive_message(stream).await?)
 }
@@ -54,9 +92,13 @@ pub async fn edit(stream: &mut GenericStream, task_id: usize, edit_path: bool) -
 /// This function allows the user to edit a task's command or path.
 /// Save the string to a temporary file, which is the edited by the user with $EDITOR.
 /// As soon as the editor is closed, read the file content and return the line
-pub fn edit_line(line: &str) -> Result<String> {
+///
+/// The file starts with a commented header, which describes the task.
+/// The header is removed and the result is validated, before it's returned.
+pub fn edit_line(line: &str, context: &EditContext, edit_path: bool) -> Result<String> {
     // Create a temporary file with the command so we can edit it with the editor.
     let mut file = NamedTempFile::new().expect("Failed to create a temporary file");
+    write!(file, "{}", edit_header(context, edit_path)).expect("Failed writing to temporary file");
     writeln!(file, "{}", line).expect("Failed writing to temporary file");
 
     // Start the editor on this file.
@@ -71,14 +113,83 @@ pub fn edit_line(line: &str) -> Result<String> {
     file.seek(SeekFrom::Start(0))
         .context("Couldn't seek to start of file. Aborting.")?;
 
-    let mut line = String::new();
-    file.read_to_string(&mut line)
+    let mut content = String::new();
+    file.read_to_string(&mut content)
         .context("Failed to read Command after editing")?;
 
-    // Remove any trailing newlines from the command.
-    while line.ends_with('\n') || line.ends_with('\r') {
-        line.pop();
+    parse_edited_line(&content, edit_path)
+}
+
+/// Build the commented header, that's shown above the edited command or path.
+fn edit_header(context: &EditContext, edit_path: bool) -> String {
+    let mut header = format!("# Task {} in group \"{}\"", context.task_id, context.group);
+    if let Some(label) = context.label {
+        header.push_str(&format!(" with label \"{}\"", label));
+    }
+    header.push('\n');
+
+    if let Some(status) = context.status {
+        let status = match status {
+            TaskStatus::Done(result) => format!("{:?}", result),
+            _ => status.to_string(),
+        };
+        header.push_str(&format!("# Previous result: {}\n", status));
     }
 
-    Ok(line)
+    let (field, other, flag) = if edit_path {
+        ("path", "command", "")
+    } else {
+        ("command", "path", " --path")
+    };
+    header.push_str(&format!(
+        "# Editing the {}. The {} can be edited via `pueue edit {}{}`.\n",
+        field, other, context.task_id, flag
+    ));
+    header.push_str("# All lines starting with '#' at the top of this file are ignored.\n");
+
+    header
+}
+
+/// Remove the commented header and any trailing newlines from the edited content.
+/// The result must not be empty and paths have to be absolute.
+fn parse_edited_line(content: &str, edit_path: bool) -> Result<String> {
+    let mut lines = content.lines().peekable();
+    while let Some(line) = lines.peek() {
+        if !line.starts_with('#') {
+            break;
+        }
+        lines.next();
+    }
+    let line = lines.collect::<Vec<&str>>().join("\n");
+    let line = line.trim_end_matches(['\n', '\r']);
+
+    if line.trim().is_empty() {
+        bail!(
+            "The edited {} is empty. Aborting.",
+            if edit_path { "path" } else { "command" }
+        );
+    }
+    if edit_path && !Path::new(line).is_absolute() {
+        bail!("The edited path has to be absolute. Aborting.");
+    }
+
+    Ok(line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_edited_line() {
+        let content = "# Task 0 in group \"default\"\n# Some hint\nls -al\necho test\n\n";
+        assert_eq!(
+            parse_edited_line(content, false).unwrap(),
+            "ls -al\necho test"
+        );
+
+        assert!(parse_edited_line("# Task 0\n\n", false).is_err());
+        assert!(parse_edited_line("relative/path\n", true).is_err());
+    }
 }
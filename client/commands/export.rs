@@ -0,0 +1,38 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use pueue_lib::network::protocol::*;
+use pueue_lib::state::State;
+use pueue_lib::task::{Task, TaskStatus};
+
+use crate::commands::get_state;
+
+/// Print all unfinished tasks as json, so they can be imported via `pueue import`.
+///
+/// Dependencies on finished tasks are dropped, as those dependencies have already been met.
+/// Otherwise, the depending task would have failed already.
+pub async fn export(stream: &mut GenericStream) -> Result<()> {
+    let state = get_state(stream).await?;
+    let tasks = exported_tasks(state);
+
+    println!("{}", serde_json::to_string_pretty(&tasks)?);
+
+    Ok(())
+}
+
+/// The unfinished tasks of the state, as they're exported.
+pub fn exported_tasks(state: State) -> Vec<Task> {
+    let mut tasks: Vec<Task> = state
+        .tasks
+        .into_values()
+        .filter(|task| !matches!(task.status, TaskStatus::Done(_) | TaskStatus::Reserved))
+        .collect();
+
+    let exported: BTreeSet<usize> = tasks.iter().map(|task| task.id).collect();
+    for task in tasks.iter_mut() {
+        task.dependencies.retain(|id| exported.contains(id));
+    }
+
+    tasks
+}
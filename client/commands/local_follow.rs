@@ -1,18 +1,56 @@
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Result};
 
+use pueue_lib::log::default_log_directory;
 use pueue_lib::network::protocol::GenericStream;
 
 use crate::commands::get_state;
 use crate::display::follow_local_task_logs;
+use crate::display::locale::Locale;
 
+/// Follow the output of a task by directly reading the local log files.
 pub async fn local_follow(
     stream: &mut GenericStream,
     pueue_directory: &Path,
     task_id: &Option<usize>,
     err: bool,
+    raw: bool,
+    locale: &Locale,
 ) -> Result<()> {
+    let task_id = get_follow_task_id(stream, task_id).await?;
+    let log_directories = get_log_directories(stream, pueue_directory, &[task_id]).await?;
+    follow_local_task_logs(&log_directories[&task_id], task_id, err, raw, locale);
+
+    Ok(())
+}
+
+/// Get the directories, that contain the log files of the given tasks.
+pub async fn get_log_directories(
+    stream: &mut GenericStream,
+    pueue_directory: &Path,
+    task_ids: &[usize],
+) -> Result<BTreeMap<usize, PathBuf>> {
+    let state = get_state(stream).await?;
+
+    Ok(task_ids
+        .iter()
+        .map(|task_id| {
+            let log_directory = match state.tasks.get(task_id) {
+                Some(task) => task.log_directory(pueue_directory),
+                None => default_log_directory(pueue_directory),
+            };
+            (*task_id, log_directory)
+        })
+        .collect())
+}
+
+/// Determine the task that should be followed.
+pub async fn get_follow_task_id(
+    stream: &mut GenericStream,
+    task_id: &Option<usize>,
+) -> Result<usize> {
     // The user can specify the id of the task they want to follow
     // If the id isn't specified and there's only a single running task, this task will be used.
     // However, if there are multiple running tasks, the user will have to specify an id.
@@ -46,7 +84,5 @@ pub async fn local_follow(
         }
     };
 
-    follow_local_task_logs(pueue_directory, task_id, err);
-
-    Ok(())
+    Ok(task_id)
 }
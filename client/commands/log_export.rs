@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_derive::Serialize;
+use snap::read::FrameDecoder;
+use tar::{Builder, Header};
+
+use pueue_lib::log::{archive_directory, get_log_file_handles};
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::task::{Task, TaskStatus};
+
+use crate::commands::send_request;
+
+/// The manifest of an export, which describes all exported tasks and their log files.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    created_at: DateTime<Local>,
+    tasks: Vec<ManifestEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ManifestEntry<'a> {
+    task: &'a Task,
+    stdout: String,
+    stderr: String,
+}
+
+/// Where the exported files are written to.
+enum Target {
+    Directory(PathBuf),
+    Tar(Builder<File>),
+    TarGz(Builder<GzEncoder<File>>),
+}
+
+impl Target {
+    /// Paths ending in `.tar`, `.tar.gz` or `.tgz` become an archive, anything else a directory.
+    fn create(path: &Path) -> Result<Self> {
+        let name = path.to_string_lossy();
+        if name.ends_with(".tar") || name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create archive at {:?}", path))?;
+            if name.ends_with(".tar") {
+                return Ok(Target::Tar(Builder::new(file)));
+            }
+            return Ok(Target::TarGz(Builder::new(GzEncoder::new(
+                file,
+                Compression::default(),
+            ))));
+        }
+
+        create_dir_all(path).with_context(|| format!("Failed to create directory {:?}", path))?;
+        Ok(Target::Directory(path.to_path_buf()))
+    }
+
+    /// Add a file with exactly `size` bytes from `reader`.
+    fn add(&mut self, name: &str, size: u64, reader: impl Read) -> Result<()> {
+        let mut reader = reader.take(size);
+        match self {
+            Target::Directory(directory) => {
+                let path = directory.join(name);
+                let mut file = File::create(&path)
+                    .with_context(|| format!("Failed to create file {:?}", path))?;
+                io::copy(&mut reader, &mut file)?;
+            }
+            Target::Tar(builder) => append(builder, name, size, reader)?,
+            Target::TarGz(builder) => append(builder, name, size, reader)?,
+        }
+
+        Ok(())
+    }
+
+    /// Write the end of archives.
+    fn finish(self) -> Result<()> {
+        match self {
+            Target::Directory(_) => (),
+            Target::Tar(builder) => builder.into_inner()?.flush()?,
+            Target::TarGz(builder) => {
+                builder.into_inner()?.finish()?.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Add a file to a tar archive.
+fn append<W: Write>(
+    builder: &mut Builder<W>,
+    name: &str,
+    size: u64,
+    reader: impl Read,
+) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(Local::now().timestamp().max(0) as u64);
+    builder
+        .append_data(&mut header, name, reader)
+        .with_context(|| format!("Failed to add {} to the archive", name))
+}
+
+/// Write the full logs of all selected tasks and a `manifest.json` into a directory or archive.
+/// Tasks that didn't finish yet are always included, if `since` is given.
+///
+/// The logs are exported one task at a time, so they never have to be kept in memory at once.
+/// If the logs can be read locally, they're even copied directly from the log files.
+pub async fn export_logs(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    task_ids: &[usize],
+    since: Option<DateTime<Local>>,
+    archived: bool,
+    path: &Path,
+) -> Result<()> {
+    // Only get the tasks first, their logs are requested one by one.
+    let tasks = request_logs(stream, task_ids.to_vec(), false, archived).await?;
+    let tasks: Vec<Task> = tasks
+        .into_values()
+        .map(|task_log| task_log.task)
+        .filter(|task| {
+            matches!(
+                task.status,
+                TaskStatus::Running | TaskStatus::Paused | TaskStatus::Done(_)
+            )
+        })
+        .filter(|task| match (since, task.end) {
+            (Some(since), Some(end)) => end >= since,
+            _ => true,
+        })
+        .collect();
+
+    let mut target = Target::create(path)?;
+    let mut entries = Vec::new();
+    for task in &tasks {
+        let stdout = format!("{}.stdout.log", task.id);
+        let stderr = format!("{}.stderr.log", task.id);
+
+        if settings.client.read_local_logs {
+            let directory = if archived {
+                archive_directory(&settings.shared.pueue_directory())
+            } else {
+                task.log_directory(&settings.shared.pueue_directory())
+            };
+            let (stdout_file, stderr_file) = get_log_file_handles(task.id, &directory)
+                .with_context(|| format!("Failed to open the logs of task {}", task.id))?;
+            target.add(&stdout, stdout_file.metadata()?.len(), stdout_file)?;
+            target.add(&stderr, stderr_file.metadata()?.len(), stderr_file)?;
+        } else {
+            let mut logs = request_logs(stream, vec![task.id], true, archived).await?;
+            let task_log = match logs.remove(&task.id) {
+                Some(task_log) => task_log,
+                None => bail!("Task {} disappeared during the export", task.id),
+            };
+            for (name, compressed) in [(&stdout, task_log.stdout), (&stderr, task_log.stderr)] {
+                let mut output = Vec::new();
+                FrameDecoder::new(&compressed.unwrap_or_default()[..]).read_to_end(&mut output)?;
+                target.add(name, output.len() as u64, &output[..])?;
+            }
+        }
+
+        entries.push(ManifestEntry {
+            task,
+            stdout,
+            stderr,
+        });
+    }
+
+    let manifest = serde_json::to_vec_pretty(&Manifest {
+        created_at: Local::now(),
+        tasks: entries,
+    })?;
+    target.add("manifest.json", manifest.len() as u64, &manifest[..])?;
+    target.finish()?;
+
+    println!("Exported the logs of {} tasks to {:?}", tasks.len(), path);
+    Ok(())
+}
+
+/// Request the full logs of some tasks.
+async fn request_logs(
+    stream: &mut GenericStream,
+    task_ids: Vec<usize>,
+    send_logs: bool,
+    archived: bool,
+) -> Result<BTreeMap<usize, TaskLogMessage>> {
+    let message = Message::Log(LogRequestMessage {
+        task_ids,
+        send_logs,
+        lines: None,
+        archived,
+    });
+    send_request(message, stream).await?;
+
+    match receive_message(stream).await? {
+        Message::LogResponse(task_logs) => Ok(task_logs),
+        Message::Failure(failure) => bail!(failure),
+        _ => unreachable!(),
+    }
+}
@@ -1,19 +1,73 @@
-use anyhow::Result;
+use std::path::Path;
 
-use pueue_lib::network::message::Message;
+use anyhow::{bail, Context, Result};
+use clap::crate_version;
+use log::info;
+
+use pueue_lib::network::message::{Message, TracedMessage};
 use pueue_lib::network::protocol::*;
 use pueue_lib::state::State;
+use pueue_lib::task::Task;
+
+use crate::cli::TaskRef;
 
+pub mod add_follow;
+pub mod attach;
+pub mod batch;
+#[cfg(not(target_os = "windows"))]
+pub mod broker;
+pub mod clipboard;
+pub mod diff;
+pub mod doctor;
 pub mod edit;
+pub mod export;
 pub mod local_follow;
+pub mod log_export;
+pub mod remote_follow;
+pub mod report;
 pub mod restart;
+pub mod self_update;
+pub mod start_daemon;
+pub mod template;
+pub mod tui;
 pub mod wait;
+pub mod watch;
+
+/// Send a message to the daemon, tagged with a new request id.
+/// The id is logged on both sides and shows up in the daemon's failure responses.
+pub async fn send_request(message: Message, stream: &mut GenericStream) -> Result<()> {
+    let traced = TracedMessage::new(message, crate_version!());
+    info!("Sending request {}", traced.request_id);
+    send_message(Message::Traced(traced), stream).await?;
+
+    Ok(())
+}
+
+/// Convert the working directory of a new task into the path, that's sent to the daemon.
+///
+/// On Windows, drive-relative paths such as `D:data` are resolved here, as only the client
+/// knows the current directory of each drive. Verbatim prefixes (`\\?\`) are removed and
+/// forward slashes are replaced, so `cmd.exe` accepts the path as well.
+pub fn task_path(path: &Path) -> Result<String> {
+    #[cfg(target_os = "windows")]
+    let path = std::path::absolute(path)?;
+
+    let path = path
+        .to_str()
+        .context("Cannot parse current working directory (Invalid utf8?)")?;
+
+    #[cfg(target_os = "windows")]
+    let path =
+        &pueue_lib::path::normalize_separators(&pueue_lib::path::strip_verbatim_prefix(path));
+
+    Ok(path.to_string())
+}
 
 // This is a helper function for easy retrieval of the current daemon state.
 // The current daemon state is often needed in more complex commands.
 pub async fn get_state(stream: &mut GenericStream) -> Result<State> {
     // Create the message payload and send it to the daemon.
-    send_message(Message::Status, stream).await?;
+    send_request(Message::Status, stream).await?;
 
     // Check if we can receive the response from the daemon
     let message = receive_message(stream).await?;
@@ -23,3 +77,136 @@ pub async fn get_state(stream: &mut GenericStream) -> Result<State> {
         _ => unreachable!(),
     }
 }
+
+/// Resolve the given task ids and labels to task ids.
+/// The state is only requested, if any labels are given.
+pub async fn resolve_task_ids(
+    stream: &mut GenericStream,
+    task_refs: &[TaskRef],
+    labels: &[String],
+) -> Result<Vec<usize>> {
+    let task_refs: Vec<TaskRef> = task_refs
+        .iter()
+        .cloned()
+        .chain(labels.iter().cloned().map(TaskRef::Label))
+        .collect();
+    if task_refs
+        .iter()
+        .all(|task_ref| matches!(task_ref, TaskRef::Id(_)))
+    {
+        return Ok(task_refs
+            .into_iter()
+            .filter_map(|task_ref| match task_ref {
+                TaskRef::Id(id) => Some(id),
+                TaskRef::Label(_) => None,
+            })
+            .collect());
+    }
+
+    let state = get_state(stream).await?;
+    task_refs
+        .iter()
+        .map(|task_ref| match task_ref {
+            TaskRef::Id(id) => Ok(*id),
+            TaskRef::Label(label) => find_task_by_label(&state, label),
+        })
+        .collect()
+}
+
+/// Get the ids of all tasks that match the filter.
+/// An error is returned, if there aren't any.
+pub async fn find_tasks<F>(stream: &mut GenericStream, filter: F) -> Result<Vec<usize>>
+where
+    F: Fn(&Task) -> bool,
+{
+    let state = get_state(stream).await?;
+    let (matching, _) = state.filter_tasks(filter, None);
+    if matching.is_empty() {
+        bail!("There are no matching tasks.");
+    }
+
+    Ok(matching)
+}
+
+/// Check whether any of the given tasks failed.
+pub async fn any_task_failed(stream: &mut GenericStream, task_ids: &[usize]) -> Result<bool> {
+    let state = get_state(stream).await?;
+
+    Ok(task_ids
+        .iter()
+        .filter_map(|task_id| state.tasks.get(task_id))
+        .any(|task| task.failed()))
+}
+
+/// Find the task with a specific label.
+///
+/// Finished tasks might share their label with newer tasks.
+/// That's why an unfinished task is preferred, otherwise the newest task is used.
+pub fn find_task_by_label(state: &State, label: &str) -> Result<usize> {
+    let candidates: Vec<_> = state
+        .tasks
+        .values()
+        .filter(|task| task.label.as_deref() == Some(label))
+        .collect();
+
+    let unfinished: Vec<_> = candidates.iter().filter(|task| !task.is_done()).collect();
+    match unfinished.as_slice() {
+        [task] => return Ok(task.id),
+        [] => (),
+        tasks => {
+            let ids: Vec<_> = tasks.iter().map(|task| task.id.to_string()).collect();
+            bail!(
+                "The label {} is ambiguous. It belongs to the tasks {}",
+                label,
+                ids.join(", ")
+            );
+        }
+    }
+
+    match candidates.iter().map(|task| task.id).max() {
+        Some(id) => Ok(id),
+        None => bail!("There's no task with the label {}", label),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use pueue_lib::settings::Settings;
+    use pueue_lib::task::{Task, TaskResult, TaskStatus};
+
+    use super::*;
+
+    fn add_task(state: &mut State, label: &str, status: TaskStatus) {
+        let task = Task::new(
+            "ls".into(),
+            "/tmp".into(),
+            Default::default(),
+            "default".into(),
+            status,
+            Vec::new(),
+            Some(label.into()),
+        );
+        state.add_task(task);
+    }
+
+    #[test]
+    fn test_find_task_by_label() {
+        let settings: Settings = Settings::default_config().unwrap().try_into().unwrap();
+        let mut state = State::new(&settings, None);
+        add_task(&mut state, "backup", TaskStatus::Done(TaskResult::Success));
+        add_task(&mut state, "backup", TaskStatus::Queued);
+        add_task(&mut state, "backup", TaskStatus::Done(TaskResult::Success));
+        add_task(&mut state, "build", TaskStatus::Done(TaskResult::Success));
+        add_task(&mut state, "build", TaskStatus::Done(TaskResult::Success));
+        add_task(&mut state, "test", TaskStatus::Running);
+        add_task(&mut state, "test", TaskStatus::Queued);
+
+        // The unfinished task is preferred, otherwise the newest one is used.
+        assert_eq!(find_task_by_label(&state, "backup").unwrap(), 1);
+        assert_eq!(find_task_by_label(&state, "build").unwrap(), 4);
+        assert!(find_task_by_label(&state, "test").is_err());
+        assert!(find_task_by_label(&state, "deploy").is_err());
+    }
+}
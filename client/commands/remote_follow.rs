@@ -0,0 +1,121 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::Settings;
+
+use crate::client::{connect, reconnect};
+use crate::commands::local_follow::get_follow_task_id;
+use crate::commands::send_request;
+use crate::display::{follow_output, PrefixedOutput, TaskOutput};
+
+/// Follow the output of a task, which is streamed by the daemon.
+///
+/// If the connection is lost, e.g. because the daemon restarted, the client reconnects and
+/// resumes the stream at the last received position.
+/// If `until_done` is set, the daemon ends the stream, once the task finished.
+/// Returns the final message of the daemon, which ends the stream.
+pub async fn remote_follow(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    task_id: &Option<usize>,
+    err: bool,
+    raw: bool,
+    quiet: bool,
+    until_done: bool,
+) -> Result<Message> {
+    let task_id = get_follow_task_id(stream, task_id).await?;
+    let mut output = follow_output(raw);
+
+    // The amount of bytes that have already been received.
+    let mut offset = 0;
+    loop {
+        let request = StreamRequestMessage {
+            task_id: Some(task_id),
+            err,
+            offset,
+            until_done,
+        };
+        match follow_stream(stream, request, &mut offset, &mut output).await {
+            Ok(message) => return Ok(message),
+            Err(_) => *stream = reconnect(settings, quiet).await?,
+        }
+    }
+}
+
+/// Follow the output of several tasks at once.
+///
+/// Each task is streamed over its own connection to the daemon
+/// and each line is prefixed with the id of its task.
+/// The final message of each stream is printed as the last line of the respective task.
+/// If `until_done` is set, the daemon ends each stream, once its task finished.
+pub async fn remote_follow_multiple(
+    settings: &Settings,
+    task_ids: &[usize],
+    err: bool,
+    raw: bool,
+    quiet: bool,
+    until_done: bool,
+) -> Result<()> {
+    let output = Arc::new(Mutex::new(PrefixedOutput::new(io::stdout(), task_ids, raw)));
+
+    let mut handles = Vec::new();
+    for &task_id in task_ids {
+        let settings = settings.clone();
+        let mut task_output = TaskOutput::new(output.clone(), task_id);
+        handles.push(tokio::spawn(async move {
+            let mut stream = connect(&settings, quiet).await?;
+            let mut offset = 0;
+            let message = loop {
+                let request = StreamRequestMessage {
+                    task_id: Some(task_id),
+                    err,
+                    offset,
+                    until_done,
+                };
+                match follow_stream(&mut stream, request, &mut offset, &mut task_output).await {
+                    Ok(message) => break message,
+                    Err(_) => stream = reconnect(&settings, quiet).await?,
+                }
+            };
+
+            match message {
+                Message::Success(text) => writeln!(task_output, "{}", text)?,
+                Message::Failure(failure) => writeln!(task_output, "{}", failure.text)?,
+                _ => (),
+            }
+            Ok::<(), anyhow::Error>(())
+        }));
+    }
+
+    for handle in handles {
+        handle.await??;
+    }
+
+    Ok(())
+}
+
+/// Request the stream and print all received output until the stream is ended by the daemon.
+/// Any error indicates that the connection has been lost.
+async fn follow_stream(
+    stream: &mut GenericStream,
+    request: StreamRequestMessage,
+    offset: &mut u64,
+    output: &mut impl Write,
+) -> Result<Message> {
+    send_request(Message::StreamRequest(request), stream).await?;
+
+    loop {
+        match receive_message(stream).await? {
+            Message::Stream(chunk) => {
+                output.write_all(chunk.text.as_bytes())?;
+                output.flush()?;
+                *offset += chunk.bytes;
+            }
+            message => return Ok(message),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::Settings;
+
+use crate::commands::{get_state, send_request};
+use crate::display::helper::retain_own_tasks;
+use crate::display::locale::Locale;
+use crate::display::{print_gantt, print_report};
+use crate::query::Query;
+
+/// The amount of output lines that are embedded per task, if not specified otherwise.
+pub const DEFAULT_REPORT_LINES: usize = 10;
+
+/// All options that influence the content of a report.
+pub struct ReportOptions {
+    /// Only include tasks of this group.
+    pub group: Option<String>,
+    /// Only include tasks that have been submitted by this client.
+    pub mine: bool,
+    /// Only include tasks with this tag.
+    pub tag: Option<String>,
+    /// Only include tasks that match this query.
+    pub query: Option<Query>,
+    /// The amount of output lines that are embedded per task.
+    pub lines: usize,
+    /// Only include tasks that finished after this point in time.
+    pub since: Option<DateTime<Local>>,
+    /// Print a mermaid gantt chart instead of the HTML page.
+    pub gantt: bool,
+}
+
+/// Print a static HTML report or a gantt chart of the current state.
+/// The HTML report needs the state for the group information and the logs of all tasks,
+/// which is why this requires two requests to the daemon.
+pub async fn report(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    locale: &Locale,
+    options: ReportOptions,
+) -> Result<()> {
+    let mut state = get_state(stream).await?;
+    if options.mine {
+        retain_own_tasks(&mut state);
+    }
+    if let Some(tag) = &options.tag {
+        state.tasks.retain(|_, task| task.has_tag(tag));
+    }
+    if let Some(query) = &options.query {
+        state.tasks.retain(|_, task| query.matches(task));
+    }
+    // Tasks that are still running or didn't finish yet are always included.
+    if let Some(since) = options.since {
+        state
+            .tasks
+            .retain(|_, task| task.end.map(|end| end >= since).unwrap_or(true));
+    }
+
+    if options.gantt {
+        print_gantt(&state, &options.group, locale);
+        return Ok(());
+    }
+
+    let message = Message::Log(LogRequestMessage {
+        task_ids: state.tasks.keys().cloned().collect(),
+        send_logs: !settings.client.read_local_logs,
+        lines: Some(options.lines),
+        archived: false,
+    });
+    send_request(message, stream).await?;
+
+    let task_logs = match receive_message(stream).await? {
+        Message::LogResponse(task_logs) => task_logs,
+        Message::Failure(text) => bail!(text),
+        _ => unreachable!(),
+    };
+
+    print_report(
+        &state,
+        task_logs,
+        &options.group,
+        settings,
+        locale,
+        options.lines,
+    );
+
+    Ok(())
+}
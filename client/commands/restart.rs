@@ -2,56 +2,70 @@ use anyhow::{bail, Result};
 
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
-use pueue_lib::task::{Task, TaskResult, TaskStatus};
+use pueue_lib::task::{DependencyMode, Task, TaskStatus};
 
-use crate::commands::edit::edit_line;
-use crate::commands::get_state;
+use crate::client::client_identity;
+use crate::commands::edit::{edit_line, EditContext};
+use crate::commands::{get_state, send_request};
+use crate::display::print_task_ids_json;
+use crate::exit_code::ExitCode;
 
 /// When Restarting tasks, the remote state is queried and a AddMessage
 /// is create from the existing task in the state.
 ///
 /// This is done on the client-side, so we can easily edit the task before restarting it.
-/// It's also necessary to get all failed tasks, in case the user specified the --all_failed flag.
+/// In-place restarts of failed tasks, which don't need to be edited, are resolved by the daemon.
+/// That way, the selection and the restart happen atomically.
+///
+/// Returns [ExitCode::PartialSuccess], if only some of the given tasks could be restarted.
+/// If none of the given tasks exist, [ExitCode::TaskNotFound] is returned.
+/// With `json`, the ids of the restarted tasks are printed as json instead of text.
 #[allow(clippy::too_many_arguments)]
 pub async fn restart(
     stream: &mut GenericStream,
     task_ids: Vec<usize>,
-    all_failed: bool,
-    failed_in_group: Option<String>,
+    failed: Option<FailedSelection>,
     start_immediately: bool,
     stashed: bool,
     in_place: bool,
     edit_command: bool,
     edit_path: bool,
-) -> Result<()> {
+    quiet: bool,
+    json: bool,
+) -> Result<ExitCode> {
     let new_status = if stashed {
         TaskStatus::Stashed { enqueue_at: None }
     } else {
         TaskStatus::Queued
     };
 
+    if let Some(selection) = &failed {
+        if in_place && !edit_command && !edit_path {
+            return restart_failed_in_place(
+                stream,
+                selection,
+                start_immediately,
+                stashed,
+                quiet,
+                json,
+            )
+            .await;
+        }
+    }
+
     let state = get_state(stream).await?;
 
     // Filter to get done tasks
     let done_filter = |task: &Task| task.is_done();
 
-    let (matching, mismatching) = if all_failed || failed_in_group.is_some() {
-        // Either all failed tasks or all failed tasks of a specific group need to be restarted.
-
-        // First we have to get all finished tasks (Done)
-        let (matching, _) = if let Some(group) = failed_in_group {
-            state.filter_tasks_of_group(done_filter, &group)
-        } else {
-            state.filter_tasks(done_filter, None)
-        };
-
-        // now pick the failed tasks
-        let failed = matching
-            .into_iter()
-            .filter(|task_id| {
-                let task = state.tasks.get(task_id).unwrap();
-                !matches!(task.status, TaskStatus::Done(TaskResult::Success))
-            })
+    let (matching, mismatching) = if let Some(selection) = &failed {
+        // Either all failed tasks or all failed tasks of a specific group or label need to be
+        // restarted.
+        let failed = state
+            .tasks
+            .iter()
+            .filter(|(_, task)| selection.matches(task))
+            .map(|(id, _)| *id)
             .collect();
 
         // We return an empty vec for the mismatching tasks, since there shouldn't be any.
@@ -69,8 +83,12 @@ pub async fn restart(
         tasks: Vec::new(),
         stashed,
         start_immediately,
+        failed: None,
     };
 
+    // The ids of the restarted tasks. Copies of tasks get a new id.
+    let mut restarted_ids = Vec::new();
+
     // Go through all Done commands we found and restart them
     for task_id in &matching {
         let task = state.tasks.get(task_id).unwrap();
@@ -80,11 +98,17 @@ pub async fn restart(
         // Path and command can be edited, if the use specified the -e or -p flag.
         let mut command = task.original_command.clone();
         let mut path = task.path.clone();
+        let context = EditContext {
+            task_id: *task_id,
+            group: &task.group,
+            label: task.label.as_deref(),
+            status: Some(&task.status),
+        };
         if edit_command {
-            command = edit_line(&command)?
+            command = edit_line(&command, &context, false)?
         };
         if edit_path {
-            path = edit_line(&path)?;
+            path = edit_line(&path, &context, true)?;
         }
 
         // Add the tasks to the singular message, if we want to restart the tasks in-place.
@@ -95,6 +119,7 @@ pub async fn restart(
                 command,
                 path,
             });
+            restarted_ids.push(*task_id);
 
             continue;
         }
@@ -103,37 +128,111 @@ pub async fn restart(
         let add_task_message = Message::Add(AddMessage {
             command,
             path,
+            path_is_default: false,
             envs: task.envs.clone(),
             start_immediately,
             stashed,
             group: task.group.clone(),
             enqueue_at: None,
             dependencies: Vec::new(),
+            dependency_mode: DependencyMode::Success,
             label: task.label.clone(),
-            print_task_id: false,
+            print_task_id: true,
+            created_by: Some(client_identity()),
+            expire_at: None,
+            reserved_id: None,
+            keep_logs: task.keep_logs,
+            stderr_to_stdout: task.stderr_to_stdout,
+            max_runtime: task.max_runtime,
+            cpus: task.cpus,
+            resources: task.resources.clone(),
+            memory_limit: task.memory_limit,
+            cpu_limit: task.cpu_limit,
+            meta: task.meta.clone(),
+            tags: task.tags.clone(),
+            start_conditions: task.start_conditions.clone(),
+            on_success: task.on_success.clone(),
+            on_failure: task.on_failure.clone(),
+            shell: task.shell,
         });
 
         // Send the cloned task to the daemon and abort on any failure messages.
-        send_message(add_task_message, stream).await?;
-        if let Message::Failure(message) = receive_message(stream).await? {
-            bail!(message);
+        // The daemon responds with the id of the new task.
+        send_request(add_task_message, stream).await?;
+        match receive_message(stream).await? {
+            Message::Success(text) => restarted_ids.push(text.parse().unwrap_or(*task_id)),
+            Message::Failure(message) => bail!(message),
+            _ => (),
         };
     }
 
     // Send the singular in-place restart message to the daemon.
     if in_place {
-        send_message(Message::Restart(restart_message), stream).await?;
+        send_request(Message::Restart(restart_message), stream).await?;
         if let Message::Failure(message) = receive_message(stream).await? {
             bail!(message);
         };
     }
 
-    if !matching.is_empty() {
-        println!("Restarted tasks: {:?}", matching);
-    }
-    if !mismatching.is_empty() {
-        println!("Couldn't restart tasks: {:?}", mismatching);
+    if !quiet && json {
+        print_task_ids_json(&restarted_ids, &mismatching);
+    } else if !quiet {
+        if !matching.is_empty() {
+            println!("Restarted tasks: {:?}", matching);
+        }
+        if !mismatching.is_empty() {
+            println!("Couldn't restart tasks: {:?}", mismatching);
+        }
     }
 
-    Ok(())
+    // Determine the exit code depending on the tasks that could be restarted.
+    let exit_code = match (matching.is_empty(), mismatching.is_empty()) {
+        (_, true) => ExitCode::Success,
+        (true, false) if mismatching.iter().all(|id| !state.tasks.contains_key(id)) => {
+            ExitCode::TaskNotFound
+        }
+        (true, false) => ExitCode::Failure,
+        (false, false) => ExitCode::PartialSuccess,
+    };
+
+    Ok(exit_code)
+}
+
+/// Let the daemon restart all failed tasks of the selection in-place.
+/// The daemon responds with a failure, if there are no failed tasks to restart.
+async fn restart_failed_in_place(
+    stream: &mut GenericStream,
+    selection: &FailedSelection,
+    start_immediately: bool,
+    stashed: bool,
+    quiet: bool,
+    json: bool,
+) -> Result<ExitCode> {
+    let message = Message::Restart(RestartMessage {
+        tasks: Vec::new(),
+        start_immediately,
+        stashed,
+        failed: Some(selection.clone()),
+    });
+    send_request(message, stream).await?;
+
+    match receive_message(stream).await? {
+        Message::Success(text) => {
+            if !quiet {
+                println!("{}", text);
+            }
+            Ok(ExitCode::Success)
+        }
+        Message::TaskActionResponse(response) => {
+            if !quiet && json {
+                let failed: Vec<usize> = response.failed.keys().cloned().collect();
+                print_task_ids_json(&response.succeeded, &failed);
+            } else if !quiet {
+                println!("Restarted tasks: {:?}", response.succeeded);
+            }
+            Ok(ExitCode::from_task_action(&response))
+        }
+        Message::Failure(text) => bail!(text),
+        _ => unreachable!(),
+    }
 }
@@ -0,0 +1,457 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use clap::crate_version;
+use ring::digest::{digest, SHA256};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde_derive::Deserialize;
+
+use pueue_lib::network::message::{Message, Shutdown};
+use pueue_lib::network::protocol::{get_client_stream, receive_message};
+use pueue_lib::settings::Settings;
+use pueue_lib::task::TaskStatus;
+
+use crate::client::{connect, wait_for_daemon};
+use crate::commands::start_daemon::start_daemon;
+use crate::commands::{get_state, send_request};
+
+/// The GitHub API endpoint of the latest release.
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/Nukesor/pueue/releases/latest";
+
+/// The hex encoded Ed25519 public key, with which the binaries of releases are signed. \
+/// It's built into the client by the release pipeline. Without it, updates can't be verified
+/// and are refused.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("PUEUE_RELEASE_PUBLIC_KEY");
+
+/// How long we wait for the old daemon to shut down, before giving up.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The parts of a GitHub release we're interested in.
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn download_url(&self, name: &str) -> Result<&str> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.as_str())
+            .context(format!(
+                "The release {} doesn't contain {}. Your platform might not be supported.",
+                self.tag_name, name
+            ))
+    }
+}
+
+/// Update the client and the daemon to the latest release on GitHub.
+///
+/// Each binary is verified against the SHA256 checksum and the Ed25519 signature that are
+/// published alongside it. The checksum detects corrupted or incomplete downloads, while the
+/// signature is checked against [RELEASE_PUBLIC_KEY], so a tampered release is rejected. \
+/// The daemon is only replaced, if it's installed next to the client.
+/// Both binaries are replaced together. If that fails, the old ones are restored.
+///
+/// If `quiet` is set, only the result of `check` and errors are printed.
+///
+/// If `restart_daemon` is set, a running daemon without any active tasks is shut down and
+/// started again with the new binary.
+pub async fn self_update(
+    config: &Option<PathBuf>,
+    check: bool,
+    restart_daemon: bool,
+    quiet: bool,
+) -> Result<()> {
+    let release: Release = serde_json::from_slice(&download(LATEST_RELEASE_URL)?)
+        .context("Failed to parse the release information from GitHub")?;
+    let latest = release.tag_name.trim_start_matches('v');
+
+    if !is_newer(latest, crate_version!()) {
+        if !quiet {
+            println!("Pueue {} is up to date.", crate_version!());
+        }
+        return Ok(());
+    }
+    if check {
+        println!(
+            "Pueue {} is available. You're running version {}.",
+            latest,
+            crate_version!()
+        );
+        return Ok(());
+    }
+
+    let public_key = match RELEASE_PUBLIC_KEY.and_then(from_hex) {
+        Some(public_key) => public_key,
+        None => bail!(
+            "This build of pueue can't verify the signatures of releases. \
+            Please update it the same way you installed it."
+        ),
+    };
+
+    let client_path = std::env::current_exe().context("Couldn't determine the client's path")?;
+    let daemon_path = client_path.with_file_name(binary_name("pueued"));
+
+    // Download and verify everything first, so we don't end up with mismatched versions.
+    let mut binaries = vec![(client_path, fetch_binary(&release, "pueue", &public_key)?)];
+    if daemon_path.exists() {
+        binaries.push((daemon_path, fetch_binary(&release, "pueued", &public_key)?));
+    } else if !quiet {
+        println!(
+            "Couldn't find the daemon at {:?}. Only the client is updated.",
+            daemon_path
+        );
+    }
+
+    replace_binaries(&binaries)?;
+    if !quiet {
+        println!("Updated pueue from {} to {}.", crate_version!(), latest);
+    }
+
+    if restart_daemon {
+        let settings = Settings::read_with_defaults(true, config)?;
+        restart(&settings, config, quiet).await?;
+    }
+
+    Ok(())
+}
+
+/// Download the release asset of a binary and verify its checksum and signature.
+fn fetch_binary(release: &Release, binary: &str, public_key: &[u8]) -> Result<Vec<u8>> {
+    let asset = asset_name(binary);
+    let content = download(release.download_url(&asset)?)?;
+    let checksum = download(release.download_url(&format!("{}.sha256", asset))?)?;
+
+    // Checksum files contain the hex digest, optionally followed by the file name.
+    let expected = String::from_utf8_lossy(&checksum)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual = to_hex(digest(&SHA256, &content).as_ref());
+    if expected != actual {
+        bail!(
+            "The checksum of {} doesn't match. Expected {}, got {}. Nothing has been updated.",
+            asset,
+            expected,
+            actual
+        );
+    }
+
+    let signature = download(release.download_url(&format!("{}.sig", asset))?)?;
+    if !verify_signature(public_key, &content, &signature) {
+        bail!(
+            "The signature of {} is invalid. Nothing has been updated.",
+            asset
+        );
+    }
+
+    Ok(content)
+}
+
+/// Check the raw Ed25519 signature of some content.
+fn verify_signature(public_key: &[u8], content: &[u8], signature: &[u8]) -> bool {
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(content, signature)
+        .is_ok()
+}
+
+/// A new binary, that has been written next to the one it replaces.
+struct StagedBinary {
+    target: PathBuf,
+    new: PathBuf,
+    old: PathBuf,
+}
+
+impl StagedBinary {
+    /// Write the new binary next to the target, without touching the target itself.
+    fn stage(target: &Path, content: &[u8]) -> Result<Self> {
+        let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+        let staged = StagedBinary {
+            target: target.to_path_buf(),
+            new: target.with_file_name(format!("{}.new", file_name)),
+            old: target.with_file_name(format!("{}.old", file_name)),
+        };
+
+        let context = || format!("Failed to write {:?}", staged.new);
+        fs::write(&staged.new, content).with_context(context)?;
+        #[cfg(not(target_os = "windows"))]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&staged.new, fs::Permissions::from_mode(0o755))
+                .with_context(context)?;
+        }
+
+        Ok(staged)
+    }
+
+    /// Move the new binary into place.
+    /// The running binary is moved aside first, since Windows doesn't allow to overwrite it.
+    fn swap(&self) -> Result<()> {
+        let context = || format!("Failed to replace {:?}", self.target);
+        fs::rename(&self.target, &self.old).with_context(context)?;
+        if let Err(error) = fs::rename(&self.new, &self.target) {
+            let _ = fs::rename(&self.old, &self.target);
+            return Err(error).with_context(context);
+        }
+
+        Ok(())
+    }
+
+    /// Put the old binary back in place of the new one.
+    fn restore(&self) -> Result<()> {
+        let context = || format!("Failed to restore {:?}", self.target);
+        fs::rename(&self.target, &self.new).with_context(context)?;
+        fs::rename(&self.old, &self.target).with_context(context)?;
+
+        Ok(())
+    }
+}
+
+/// Replace all binaries at once, so the client and the daemon never end up with
+/// mismatched versions.
+///
+/// All binaries are written next to their targets first. Only then they're swapped.
+/// If any of them can't be swapped, the binaries that have already been replaced are restored.
+fn replace_binaries(binaries: &[(PathBuf, Vec<u8>)]) -> Result<()> {
+    let mut staged = Vec::new();
+    for (target, content) in binaries {
+        match StagedBinary::stage(target, content) {
+            Ok(binary) => staged.push(binary),
+            Err(error) => {
+                remove_staged(&staged);
+                return Err(error);
+            }
+        }
+    }
+
+    for (index, binary) in staged.iter().enumerate() {
+        if let Err(error) = binary.swap() {
+            for swapped in staged[..index].iter().rev() {
+                if let Err(restore_error) = swapped.restore() {
+                    eprintln!("{:?}", restore_error);
+                }
+            }
+            remove_staged(&staged);
+            return Err(error.context("Nothing has been updated"));
+        }
+    }
+
+    // This fails on Windows, as long as the old binary is running. It's cleaned up next time.
+    for binary in staged.iter() {
+        let _ = fs::remove_file(&binary.old);
+    }
+
+    Ok(())
+}
+
+/// Remove the new binaries, that haven't been moved into place.
+fn remove_staged(staged: &[StagedBinary]) {
+    for binary in staged {
+        let _ = fs::remove_file(&binary.new);
+    }
+}
+
+/// Shut the daemon down and start it again with the new binary.
+/// This is only done, if no tasks are running, as they would be killed during the shutdown.
+async fn restart(settings: &Settings, config: &Option<PathBuf>, quiet: bool) -> Result<()> {
+    let mut stream = match connect(settings, true).await {
+        Ok(stream) => stream,
+        Err(_) => {
+            if !quiet {
+                println!("The daemon isn't running. It'll use the new version once it's started.");
+            }
+            return Ok(());
+        }
+    };
+
+    let state = get_state(&mut stream).await?;
+    let active = state
+        .tasks
+        .values()
+        .filter(|task| matches!(task.status, TaskStatus::Running | TaskStatus::Paused))
+        .count();
+    if active > 0 {
+        bail!(
+            "{} tasks are still running, which would be killed by a restart. \
+            Restart the daemon manually, once they finished.",
+            active
+        );
+    }
+
+    send_request(Message::DaemonShutdown(Shutdown::Graceful), &mut stream).await?;
+    receive_message(&mut stream).await?;
+    drop(stream);
+
+    // Wait until the old daemon has removed its socket.
+    let start = Instant::now();
+    while get_client_stream(&settings.shared).await.is_ok() {
+        if start.elapsed() > SHUTDOWN_TIMEOUT {
+            bail!("The daemon didn't shut down in time.");
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let mut daemon = start_daemon(settings, config)?;
+    wait_for_daemon(settings, &mut daemon, quiet).await?;
+    if !quiet {
+        println!("Restarted the daemon.");
+    }
+
+    Ok(())
+}
+
+/// Download a file via `curl`, which is available on all supported platforms.
+fn download(url: &str) -> Result<Vec<u8>> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", url])
+        .output()
+        .context("Failed to run curl. Is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(output.stdout)
+}
+
+/// The name of a release asset for the current platform, e.g. `pueue-linux-x86_64`.
+fn asset_name(binary: &str) -> String {
+    format!(
+        "{}-{}-{}{}",
+        binary,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// The file name of a binary on the current platform.
+fn binary_name(binary: &str) -> String {
+    format!("{}{}", binary, std::env::consts::EXE_SUFFIX)
+}
+
+/// Check whether the `latest` version is newer than the `current` one.
+/// Versions that can't be parsed are never considered newer.
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version
+            .split('-')
+            .next()?
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    };
+
+    match (parse(latest), parse(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    // Odd lengths fail, since the last pair is incomplete.
+    let hex = hex.trim();
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("1.0.0", "0.18.1"));
+        assert!(is_newer("0.18.10", "0.18.9"));
+        assert!(!is_newer("0.18.1", "0.18.1"));
+        assert!(!is_newer("0.17.2", "0.18.1"));
+        assert!(!is_newer("nightly", "0.18.1"));
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(
+            to_hex(digest(&SHA256, b"pueue").as_ref()),
+            "17a16ef3fbbad04761eacd70e43533b8cd27a6f9a263325b266d8b8ad1834d0c"
+        );
+    }
+
+    #[test]
+    fn test_signature() {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public_key = from_hex(&to_hex(key_pair.public_key().as_ref())).unwrap();
+
+        let signature = key_pair.sign(b"pueue");
+        assert!(verify_signature(&public_key, b"pueue", signature.as_ref()));
+        assert!(!verify_signature(
+            &public_key,
+            b"pueued",
+            signature.as_ref()
+        ));
+
+        let other_pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let other_key_pair = Ed25519KeyPair::from_pkcs8(other_pkcs8.as_ref()).unwrap();
+        let forged = other_key_pair.sign(b"pueue");
+        assert!(!verify_signature(&public_key, b"pueue", forged.as_ref()));
+    }
+
+    #[test]
+    fn test_replace_binaries() -> Result<()> {
+        let tempdir = TempDir::new()?;
+        let client = tempdir.path().join("pueue");
+        let daemon = tempdir.path().join("pueued");
+        fs::write(&client, "old client")?;
+        fs::write(&daemon, "old daemon")?;
+
+        replace_binaries(&[
+            (client.clone(), b"new client".to_vec()),
+            (daemon.clone(), b"new daemon".to_vec()),
+        ])?;
+        assert_eq!(fs::read_to_string(&client)?, "new client");
+        assert_eq!(fs::read_to_string(&daemon)?, "new daemon");
+
+        // The daemon can't be replaced, so the client is restored as well.
+        let missing = tempdir.path().join("missing");
+        assert!(replace_binaries(&[
+            (client.clone(), b"newer client".to_vec()),
+            (missing, b"newer daemon".to_vec()),
+        ])
+        .is_err());
+        assert_eq!(fs::read_to_string(&client)?, "new client");
+
+        let mut files: Vec<String> = fs::read_dir(tempdir.path())?
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["pueue", "pueued"]);
+
+        Ok(())
+    }
+}
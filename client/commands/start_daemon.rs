@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{Context, Result};
+
+use pueue_lib::settings::{Settings, Shared};
+
+/// Check whether the client connects to a daemon on this machine.
+/// Remote daemons can't be started by the client.
+pub fn daemon_is_local(shared: &Shared) -> bool {
+    #[cfg(not(target_os = "windows"))]
+    if shared.use_unix_socket {
+        return true;
+    }
+
+    matches!(shared.host.as_str(), "localhost" | "127.0.0.1" | "::1")
+}
+
+/// Start the daemon in the background. \
+/// The daemon uses the same configuration file as the client.
+pub fn start_daemon(settings: &Settings, config: &Option<PathBuf>) -> Result<Child> {
+    let program = match &settings.client.daemon_path {
+        Some(path) => Shared::expand(path),
+        None => default_daemon_path(),
+    };
+
+    let mut command = Command::new(&program);
+    if let Some(config) = config {
+        command.arg("--config").arg(config);
+    }
+    command
+        .args(&settings.client.daemon_arguments)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Move the daemon into its own process group.
+    // Otherwise a CTRL+C in the terminal would also kill the daemon.
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    command
+        .spawn()
+        .context(format!("Failed to start the daemon {:?}", program))
+}
+
+/// Prefer the daemon that has been installed alongside the client.
+/// Fall back to the one in the `PATH`.
+fn default_daemon_path() -> PathBuf {
+    let name = format!("pueued{}", std::env::consts::EXE_SUFFIX);
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|directory| directory.join(&name)))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
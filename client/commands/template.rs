@@ -0,0 +1,89 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Result};
+
+/// Replace the `{KEY}` placeholders in the command of a template with the shell-escaped values.
+///
+/// Only braces around a name of letters, digits and underscores are placeholders.
+/// Shell syntax such as `${HOME}`, `{a,b}` or `{ cmd; }` is left untouched.
+/// Placeholders without a value and values without a placeholder are rejected,
+/// as they're most likely typos.
+pub fn expand_template(command: &str, variables: &[(String, String)]) -> Result<String> {
+    let mut expanded = String::with_capacity(command.len());
+    let mut used = BTreeSet::new();
+
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        let (before, after) = rest.split_at(start);
+        expanded.push_str(before);
+
+        let end = after.find('}');
+        let name = end.map(|end| &after[1..end]).unwrap_or_default();
+        let is_placeholder = !before.ends_with('$')
+            && !name.is_empty()
+            && name
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '_');
+
+        if !is_placeholder {
+            expanded.push('{');
+            rest = &after[1..];
+            continue;
+        }
+
+        let value = match variables.iter().rev().find(|(key, _)| key == name) {
+            Some((_, value)) => value,
+            None => bail!("Missing a value for the placeholder {{{}}}", name),
+        };
+        expanded.push_str(&shell_escape::escape(Cow::from(value)));
+        used.insert(name);
+        rest = &after[name.len() + 2..];
+    }
+    expanded.push_str(rest);
+
+    if let Some((key, _)) = variables
+        .iter()
+        .find(|(key, _)| !used.contains(key.as_str()))
+    {
+        bail!("The template has no placeholder {{{}}}", key);
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_placeholders_are_escaped() {
+        let command = expand_template(
+            "ffmpeg -i {INPUT} {INPUT}.mp4 -crf {crf_1}",
+            &variables(&[("INPUT", "my file.mkv"), ("crf_1", "23")]),
+        )
+        .unwrap();
+        assert_eq!(command, "ffmpeg -i 'my file.mkv' 'my file.mkv'.mp4 -crf 23");
+    }
+
+    #[test]
+    fn test_shell_syntax_is_untouched() {
+        let command = "cp ${HOME}/{a,b} {} { echo; } {unclosed";
+        assert_eq!(expand_template(command, &[]).unwrap(), command);
+    }
+
+    #[test]
+    fn test_missing_and_unknown_variables() {
+        assert!(expand_template("echo {INPUT}", &[]).is_err());
+        assert!(expand_template("echo", &variables(&[("INPUT", "a")])).is_err());
+    }
+}
@@ -0,0 +1,502 @@
+use std::io::{stdout, Stdout, Write};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::settings::Settings;
+use pueue_lib::state::{GroupStatus, State};
+use pueue_lib::task::Task;
+
+use crate::commands::{get_state, send_request};
+use crate::display::colors::Colors;
+use crate::display::helper::{get_status_color, get_status_text};
+use crate::display::locale::Locale;
+use crate::display::{get_task_output, task_action_failure};
+
+/// How often the dashboard is refreshed, if no key is pressed.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Labels and group names are cut off after this many characters.
+const MAX_COLUMN_WIDTH: usize = 20;
+
+/// An action on the selected task or its group.
+enum Action {
+    Pause,
+    Start,
+    Kill,
+    Restart,
+    PauseGroup,
+    StartGroup,
+}
+
+/// Switch to the alternate screen and put the terminal into raw mode.
+/// The terminal is restored once this is dropped, which also happens on errors.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        execute!(stdout(), EnterAlternateScreen, Hide)?;
+
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Everything that has to be remembered between two refreshes of the dashboard.
+#[derive(Default)]
+struct Dashboard {
+    /// The id of the selected task.
+    /// We remember the id instead of the row, so the selection survives removed tasks.
+    selected: Option<usize>,
+    /// The first task that's visible in the table.
+    offset: usize,
+    /// The response to the last action and whether it failed.
+    message: Option<(String, bool)>,
+}
+
+/// Show a full-screen dashboard with all groups, tasks and the output of the selected task.
+/// It's refreshed every second and allows to pause, start, kill and restart tasks.
+pub async fn tui(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    colors: &Colors,
+    locale: &Locale,
+) -> Result<()> {
+    let _guard = TerminalGuard::new()?;
+    let mut dashboard = Dashboard::default();
+
+    loop {
+        let state = get_state(stream).await?;
+        let tasks: Vec<&Task> = state.tasks.values().collect();
+        let index = dashboard.select(&tasks);
+
+        let (_, height) = terminal::size()?;
+        let (table_height, log_height) = layout(height);
+        dashboard.scroll(index, table_height);
+
+        let log = match index {
+            Some(index) => get_log(stream, settings, locale, tasks[index].id, log_height).await?,
+            None => Vec::new(),
+        };
+        dashboard.draw(&state, &tasks, index, &log, colors, locale)?;
+
+        // Refresh the dashboard, if nothing happens in the meantime.
+        if !event::poll(REFRESH_INTERVAL)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            _ => continue,
+        };
+
+        let action = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Up => {
+                dashboard.move_selection(&tasks, index, -1);
+                continue;
+            }
+            KeyCode::Down => {
+                dashboard.move_selection(&tasks, index, 1);
+                continue;
+            }
+            KeyCode::PageUp => {
+                dashboard.move_selection(&tasks, index, -(table_height as isize));
+                continue;
+            }
+            KeyCode::PageDown => {
+                dashboard.move_selection(&tasks, index, table_height as isize);
+                continue;
+            }
+            KeyCode::Home => {
+                dashboard.selected = tasks.first().map(|task| task.id);
+                continue;
+            }
+            KeyCode::End => {
+                dashboard.selected = tasks.last().map(|task| task.id);
+                continue;
+            }
+            KeyCode::Char('p') => Action::Pause,
+            KeyCode::Char('s') => Action::Start,
+            KeyCode::Char('k') => Action::Kill,
+            KeyCode::Char('r') => Action::Restart,
+            KeyCode::Char('P') => Action::PauseGroup,
+            KeyCode::Char('S') => Action::StartGroup,
+            _ => continue,
+        };
+
+        if let Some(index) = index {
+            dashboard.message = Some(send_action(stream, action, tasks[index]).await?);
+        }
+    }
+}
+
+impl Dashboard {
+    /// Get the row of the selected task.
+    /// If the task has been removed, the next task is selected instead.
+    fn select(&mut self, tasks: &[&Task]) -> Option<usize> {
+        let last = tasks.len().checked_sub(1)?;
+        let index = match self.selected {
+            Some(id) => tasks.iter().position(|task| task.id >= id).unwrap_or(last),
+            None => 0,
+        };
+        self.selected = Some(tasks[index].id);
+
+        Some(index)
+    }
+
+    /// Move the selection up or down by the given amount of rows.
+    fn move_selection(&mut self, tasks: &[&Task], index: Option<usize>, rows: isize) {
+        let index = match index {
+            Some(index) => index as isize,
+            None => return,
+        };
+        let index = (index + rows).clamp(0, tasks.len() as isize - 1) as usize;
+        self.selected = Some(tasks[index].id);
+        self.message = None;
+    }
+
+    /// Scroll the table, so the selected task is visible.
+    fn scroll(&mut self, index: Option<usize>, table_height: usize) {
+        let index = index.unwrap_or(0);
+        if index < self.offset {
+            self.offset = index;
+        } else if index >= self.offset + table_height {
+            self.offset = index + 1 - table_height;
+        }
+    }
+
+    fn draw(
+        &self,
+        state: &State,
+        tasks: &[&Task],
+        index: Option<usize>,
+        log: &[String],
+        colors: &Colors,
+        locale: &Locale,
+    ) -> Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+        let (table_height, log_height) = layout(height);
+        let mut out = stdout();
+
+        // All groups with their status.
+        let mut line = Line::new(&mut out, 0, width)?;
+        for (name, status) in &state.groups {
+            let parallel = state.settings.daemon.groups.get(name).unwrap_or(&1);
+            line.text(&format!("{} ({}): ", name, parallel))?;
+            match status {
+                GroupStatus::Running => {
+                    line.colored(locale.get("group.running"), colors.green())?
+                }
+                GroupStatus::Paused => line.colored(locale.get("group.paused"), colors.yellow())?,
+            }
+            line.text("   ")?;
+        }
+
+        // The task table.
+        let statuses: Vec<String> = tasks
+            .iter()
+            .map(|task| get_status_text(&task.status, locale))
+            .collect();
+        let id_width = column_width(locale.get("status.header.id"), tasks, |task| {
+            task.id.to_string()
+        });
+        let status_width = statuses
+            .iter()
+            .map(|status| status.chars().count())
+            .chain(Some(locale.get("status.header.status").chars().count()))
+            .max()
+            .unwrap_or_default();
+        let group_width = column_width(locale.get("tui.group"), tasks, |task| task.group.clone());
+        let has_labels = tasks.iter().any(|task| task.label.is_some());
+        let label_width = column_width(locale.get("status.header.label"), tasks, |task| {
+            task.label.clone().unwrap_or_default()
+        });
+
+        let mut line = Line::new(&mut out, 1, width)?;
+        line.attribute(Attribute::Bold)?;
+        line.text(&fit(locale.get("status.header.id"), id_width))?;
+        line.text(" ")?;
+        line.text(&fit(locale.get("status.header.status"), status_width))?;
+        line.text(" ")?;
+        line.text(&fit(locale.get("tui.group"), group_width))?;
+        line.text(" ")?;
+        if has_labels {
+            line.text(&fit(locale.get("status.header.label"), label_width))?;
+            line.text(" ")?;
+        }
+        line.text(locale.get("status.header.command"))?;
+        line.attribute(Attribute::Reset)?;
+
+        for row in 0..table_height {
+            let mut line = Line::new(&mut out, row + 2, width)?;
+            let task_index = self.offset + row;
+            let task = match tasks.get(task_index) {
+                Some(task) => task,
+                None => {
+                    if tasks.is_empty() && row == 0 {
+                        line.text(locale.get("status.empty"))?;
+                    }
+                    continue;
+                }
+            };
+
+            if Some(task_index) == index {
+                line.attribute(Attribute::Reverse)?;
+            }
+            line.text(&fit(&task.id.to_string(), id_width))?;
+            line.text(" ")?;
+            let color = get_status_color(&task.status, colors);
+            line.colored(&fit(&statuses[task_index], status_width), color)?;
+            line.text(" ")?;
+            line.text(&fit(&task.group, group_width))?;
+            line.text(" ")?;
+            if has_labels {
+                let label = task.label.clone().unwrap_or_default();
+                line.text(&fit(&label, label_width))?;
+                line.text(" ")?;
+            }
+            // Pad the command, so the whole row of the selected task is highlighted.
+            let remaining = line.remaining;
+            line.text(&fit(&task.command, remaining))?;
+            line.attribute(Attribute::Reset)?;
+        }
+
+        // The log preview of the selected task.
+        let mut line = Line::new(&mut out, table_height + 2, width)?;
+        if let Some(index) = index {
+            line.attribute(Attribute::Bold)?;
+            line.text(&format!(
+                "{} ",
+                locale.format("tui.log", &[&tasks[index].id])
+            ))?;
+            line.attribute(Attribute::Reset)?;
+        }
+        let remaining = line.remaining;
+        line.text(&"─".repeat(remaining))?;
+
+        let skipped = log.len().saturating_sub(log_height);
+        for row in 0..log_height {
+            let mut line = Line::new(&mut out, table_height + 3 + row, width)?;
+            if let Some(log_line) = log.get(skipped + row) {
+                line.text(log_line)?;
+            }
+        }
+
+        // The response to the last action or a short help.
+        let mut line = Line::new(&mut out, height as usize - 1, width)?;
+        match &self.message {
+            Some((text, true)) => line.colored(text, colors.red())?,
+            Some((text, false)) => line.text(text)?,
+            None => line.text(locale.get("tui.help"))?,
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes a single line of the screen and makes sure that it doesn't exceed the terminal's width.
+struct Line<'a> {
+    out: &'a mut Stdout,
+    remaining: usize,
+}
+
+impl<'a> Line<'a> {
+    /// Clear the given row and move the cursor to its start.
+    fn new(out: &'a mut Stdout, row: usize, width: usize) -> Result<Self> {
+        queue!(out, MoveTo(0, row as u16), Clear(ClearType::CurrentLine))?;
+        Ok(Line {
+            out,
+            remaining: width,
+        })
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        let text: String = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .take(self.remaining)
+            .collect();
+        self.remaining -= text.chars().count();
+        queue!(self.out, Print(text))?;
+
+        Ok(())
+    }
+
+    fn colored(&mut self, text: &str, color: Color) -> Result<()> {
+        queue!(self.out, SetForegroundColor(color))?;
+        self.text(text)?;
+        queue!(self.out, SetForegroundColor(Color::Reset))?;
+
+        Ok(())
+    }
+
+    fn attribute(&mut self, attribute: Attribute) -> Result<()> {
+        queue!(self.out, SetAttribute(attribute))?;
+        Ok(())
+    }
+}
+
+/// Split the screen between the task table and the log preview.
+/// The first two rows are used for the groups and the table's header.
+/// Another row is used for the log's title and the last one for the help.
+fn layout(height: u16) -> (usize, usize) {
+    let available = (height as usize).saturating_sub(4);
+    let table_height = available.div_ceil(2).max(1);
+
+    (table_height, available.saturating_sub(table_height))
+}
+
+/// The width of a column, which is wide enough for its header and all values.
+fn column_width<F: Fn(&Task) -> String>(header: &str, tasks: &[&Task], value: F) -> usize {
+    tasks
+        .iter()
+        .map(|task| value(task).chars().count().min(MAX_COLUMN_WIDTH))
+        .chain(Some(header.chars().count()))
+        .max()
+        .unwrap_or_default()
+}
+
+/// Cut off or pad the text, so it's exactly `width` characters long.
+fn fit(text: &str, width: usize) -> String {
+    let text: String = text.chars().take(width).collect();
+    format!("{:width$}", text, width = width)
+}
+
+/// Get the last lines of a task's output. Lines of stderr are shown after stdout.
+async fn get_log(
+    stream: &mut GenericStream,
+    settings: &Settings,
+    locale: &Locale,
+    task_id: usize,
+    lines: usize,
+) -> Result<Vec<String>> {
+    let message = Message::Log(LogRequestMessage {
+        task_ids: vec![task_id],
+        send_logs: !settings.client.read_local_logs,
+        lines: Some(lines),
+        archived: false,
+    });
+    send_request(message, stream).await?;
+
+    let mut task_logs = match receive_message(stream).await? {
+        Message::LogResponse(task_logs) => task_logs,
+        Message::Failure(text) => bail!(text),
+        _ => unreachable!(),
+    };
+    let task_log = match task_logs.remove(&task_id) {
+        Some(task_log) => task_log,
+        None => return Ok(Vec::new()),
+    };
+
+    let (stdout, stderr) = get_task_output(
+        &task_log.task,
+        task_log.stdout,
+        task_log.stderr,
+        settings,
+        locale,
+        Some(lines),
+    );
+    Ok(stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(String::from)
+        .collect())
+}
+
+/// Send the action to the daemon and return its response and whether the action failed.
+async fn send_action(
+    stream: &mut GenericStream,
+    action: Action,
+    task: &Task,
+) -> Result<(String, bool)> {
+    let selection = TaskSelection::TaskIds(vec![task.id]);
+    let message = match action {
+        Action::Pause => Message::Pause(PauseMessage {
+            tasks: selection,
+            wait: false,
+            children: false,
+        }),
+        Action::Start => Message::Start(StartMessage {
+            tasks: selection,
+            children: false,
+        }),
+        Action::Kill => Message::Kill(KillMessage {
+            tasks: selection,
+            children: false,
+            signal: None,
+        }),
+        // Tasks are restarted in-place, so the dashboard doesn't fill up with copies.
+        Action::Restart => Message::Restart(RestartMessage {
+            tasks: vec![TasksToRestart {
+                task_id: task.id,
+                command: task.original_command.clone(),
+                path: task.path.clone(),
+            }],
+            start_immediately: false,
+            stashed: false,
+            failed: None,
+        }),
+        Action::PauseGroup => Message::Pause(PauseMessage {
+            tasks: TaskSelection::Group(task.group.clone()),
+            wait: false,
+            children: false,
+        }),
+        Action::StartGroup => Message::Start(StartMessage {
+            tasks: TaskSelection::Group(task.group.clone()),
+            children: false,
+        }),
+    };
+    send_request(message, stream).await?;
+
+    let response = match receive_message(stream).await? {
+        Message::Success(text) => (text, false),
+        Message::Failure(failure) => (failure.text, true),
+        Message::TaskActionResponse(response) => match response.failed.iter().next() {
+            Some((task_id, kind)) => (task_action_failure(*task_id, kind), true),
+            None => (format!("{}: {}", response.text, task.id), false),
+        },
+        _ => unreachable!(),
+    };
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("sleep 60", 5), "sleep");
+        assert_eq!(fit("ls", 5), "ls   ");
+        assert_eq!(fit("äöü", 2), "äö");
+    }
+
+    #[test]
+    fn test_layout() {
+        // The task table gets the extra row.
+        assert_eq!(layout(25), (11, 10));
+        assert_eq!(layout(24), (10, 10));
+        // There's always room for the selected task.
+        assert_eq!(layout(3), (1, 0));
+    }
+}
@@ -7,64 +7,139 @@ use chrono::Local;
 use crossterm::style::{Attribute, Color};
 
 use pueue_lib::network::protocol::GenericStream;
+use pueue_lib::settings::{Settings, PUEUE_DEFAULT_GROUP};
 use pueue_lib::task::{Task, TaskResult, TaskStatus};
 
+use crate::cli::WaitStatus;
+use crate::client::reconnect;
 use crate::display::helper::style_text;
+use crate::exit_code::ExitCode;
 use crate::{commands::get_state, display::colors::Colors};
 
+/// All options that decide which tasks are awaited and for how long.
+pub struct WaitOptions {
+    /// Wait for these tasks. The other filters are ignored, if any ids are given.
+    pub task_ids: Vec<usize>,
+    /// Only wait for the tasks of this group.
+    /// Defaults to the default group, unless tasks are selected by their label.
+    pub group: Option<String>,
+    /// Only wait for tasks with one of these labels.
+    pub labels: Vec<String>,
+    /// Wait for the tasks of all groups.
+    pub all: bool,
+    /// When to stop waiting.
+    pub status: WaitStatus,
+    /// Don't log any status changes.
+    pub quiet: bool,
+}
+
+impl WaitOptions {
+    /// Whether a task is awaited. Tasks that are added while waiting are checked as well.
+    fn matches(&self, task: &Task) -> bool {
+        if !self.task_ids.is_empty() {
+            return self.task_ids.contains(&task.id);
+        }
+        if self.all {
+            return true;
+        }
+
+        let group_matches = match &self.group {
+            Some(group) => task.group == *group,
+            None => !self.labels.is_empty() || task.group == PUEUE_DEFAULT_GROUP,
+        };
+        let label_matches = self.labels.is_empty()
+            || matches!(&task.label, Some(label) if self.labels.contains(label));
+
+        group_matches && label_matches
+    }
+
+    /// A description of the selected tasks, in case there aren't any.
+    fn describe(&self) -> String {
+        let mut text = format!(
+            "group {}",
+            self.group.as_deref().unwrap_or(PUEUE_DEFAULT_GROUP)
+        );
+        if !self.labels.is_empty() {
+            text = format!("label {}", self.labels.join(", "));
+            if let Some(group) = &self.group {
+                text.push_str(&format!(" in group {}", group));
+            }
+        }
+        text
+    }
+}
+
+/// Decide whether the wait is over and with which exit code.
+/// Returns `None`, as long as the awaited status hasn't been reached.
+fn wait_result(status: WaitStatus, tasks: &[Task]) -> Option<ExitCode> {
+    let any_failed = tasks.iter().any(|task| task.failed());
+    let all_finished = tasks
+        .iter()
+        .all(|task| matches!(task.status, TaskStatus::Done(_)));
+
+    match status {
+        WaitStatus::Done if all_finished && any_failed => Some(ExitCode::TaskFailed),
+        WaitStatus::Done if all_finished => Some(ExitCode::Success),
+        WaitStatus::Success if any_failed => Some(ExitCode::TaskFailed),
+        WaitStatus::Success if all_finished => Some(ExitCode::Success),
+        WaitStatus::Failed if any_failed => Some(ExitCode::Success),
+        WaitStatus::Failed if all_finished => Some(ExitCode::Failure),
+        _ => None,
+    }
+}
+
 /// Wait until tasks are done.
 /// Tasks can be specified by:
 /// - Default queue (no parameter given)
-/// - Group
+/// - Group and/or labels
 /// - A list of task ids
 /// - All tasks (`all == true`)
 ///
 /// By default, this will log status changes on tasks.
 /// Pass `quiet == true` to supress any logging.
+///
+/// If the connection to the daemon is lost, the client reconnects and continues waiting.
+///
+/// Returns [ExitCode::TaskFailed], if any of the watched tasks failed.
+/// When waiting for a failed task, [ExitCode::Failure] is returned, if all tasks succeeded.
 pub async fn wait(
     stream: &mut GenericStream,
-    task_ids: &[usize],
-    group: &str,
-    all: bool,
-    quiet: bool,
+    options: &WaitOptions,
     colors: &Colors,
-) -> Result<()> {
+    settings: &Settings,
+) -> Result<ExitCode> {
+    let quiet = options.quiet;
     let mut first_run = true;
     // Create a list of tracked tasks.
     // This way we can track any status changes and if any new tasks are added.
     let mut watched_tasks: HashMap<usize, TaskStatus> = HashMap::new();
 
     loop {
-        let state = get_state(stream).await?;
-
-        let tasks: Vec<Task> = if !task_ids.is_empty() {
-            // Get all tasks of a specific group
-            state
-                .tasks
-                .iter()
-                .filter(|(id, _)| task_ids.contains(id))
-                .map(|(_, task)| task.clone())
-                .collect()
-        } else if all {
-            // Get all tasks
-            state.tasks.iter().map(|(_, task)| task.clone()).collect()
-        } else {
-            // Get all tasks of a specific group
-            let tasks = state
-                .tasks
-                .iter()
-                .filter(|(_, task)| task.group.eq(group))
-                .map(|(_, task)| task.clone())
-                .collect::<Vec<Task>>();
-
-            if tasks.is_empty() {
-                println!("No tasks found for group {}", group);
-                return Ok(());
+        // The watched tasks are kept, if the connection is lost.
+        // After reconnecting, we simply continue where we left off.
+        let state = match get_state(stream).await {
+            Ok(state) => state,
+            Err(_) => {
+                *stream = reconnect(settings, quiet).await?;
+                continue;
             }
-
-            tasks
         };
 
+        let tasks: Vec<Task> = state
+            .tasks
+            .values()
+            .filter(|task| options.matches(task))
+            .cloned()
+            .collect();
+
+        // Explicitly given tasks might have been removed, which is fine.
+        if tasks.is_empty() && options.task_ids.is_empty() && !options.all {
+            if !quiet {
+                println!("No tasks found for {}", options.describe());
+            }
+            return Ok(ExitCode::Success);
+        }
+
         // Get current time for log output
         let current_time = Local::now().format("%H:%M:%S").to_string();
 
@@ -103,26 +178,19 @@ pub async fn wait(
             }
         }
 
-        // We can stop waiting, if every task is on `Done`
         // Always check the actual task list instead of the watched_tasks list.
         // Otherwise we get locked if tasks get removed.
-        let all_finished = tasks
-            .iter()
-            .all(|task| matches!(task.status, TaskStatus::Done(_)));
-
-        if all_finished {
-            break;
+        if let Some(exit_code) = wait_result(options.status, &tasks) {
+            return Ok(exit_code);
         }
 
         // Sleep for a few seconds. We don't want to hurt the CPU.
         sleep(Duration::from_millis(2000));
         first_run = false;
     }
-
-    Ok(())
 }
 
-fn log_status_change(
+pub fn log_status_change(
     current_time: &str,
     previous_status: TaskStatus,
     task: &Task,
@@ -145,6 +213,13 @@ fn log_status_change(
                     style_text("failed dependencies", Some(colors.red()), None)
                 )
             }
+            TaskResult::Expired => {
+                format!(
+                    "Task {} {} before it started",
+                    style_text(task.id, None, Some(Attribute::Bold)),
+                    style_text("expired", Some(colors.red()), None)
+                )
+            }
 
             TaskResult::FailedToSpawn(_) => {
                 format!(
@@ -174,6 +249,27 @@ fn log_status_change(
                     style_text("killed", Some(colors.red()), None)
                 )
             }
+            TaskResult::Skipped => {
+                format!(
+                    "Task {} has been {}, as none of its dependencies failed",
+                    style_text(task.id, None, Some(Attribute::Bold)),
+                    style_text("skipped", Some(colors.white()), None)
+                )
+            }
+            TaskResult::FailedTimeout => {
+                format!(
+                    "Task {} has been killed, as it {}",
+                    style_text(task.id, None, Some(Attribute::Bold)),
+                    style_text("timed out", Some(colors.red()), None)
+                )
+            }
+            TaskResult::OutOfMemory => {
+                format!(
+                    "Task {} has been killed, as it ran {}",
+                    style_text(task.id, None, Some(Attribute::Bold)),
+                    style_text("out of memory", Some(colors.red()), None)
+                )
+            }
         };
         println!("{} - {}", current_time, text);
 
@@ -198,3 +294,80 @@ fn get_color_for_status(task_status: &TaskStatus, colors: &Colors) -> Color {
         _ => colors.white(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn task(group: &str, label: Option<&str>, status: TaskStatus) -> Task {
+        Task::new(
+            "ls".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            group.into(),
+            status,
+            Vec::new(),
+            label.map(String::from),
+        )
+    }
+
+    #[test]
+    fn test_wait_result() {
+        let running = task("default", None, TaskStatus::Running);
+        let succeeded = task("default", None, TaskStatus::Done(TaskResult::Success));
+        let failed = task("default", None, TaskStatus::Done(TaskResult::Failed(1)));
+
+        let tasks = vec![running.clone(), failed.clone()];
+        assert_eq!(wait_result(WaitStatus::Done, &tasks), None);
+        assert_eq!(
+            wait_result(WaitStatus::Success, &tasks),
+            Some(ExitCode::TaskFailed)
+        );
+        assert_eq!(
+            wait_result(WaitStatus::Failed, &tasks),
+            Some(ExitCode::Success)
+        );
+
+        let tasks = vec![succeeded.clone(), failed];
+        assert_eq!(
+            wait_result(WaitStatus::Done, &tasks),
+            Some(ExitCode::TaskFailed)
+        );
+
+        let tasks = vec![succeeded.clone(), succeeded];
+        assert_eq!(
+            wait_result(WaitStatus::Success, &tasks),
+            Some(ExitCode::Success)
+        );
+        assert_eq!(
+            wait_result(WaitStatus::Failed, &tasks),
+            Some(ExitCode::Failure)
+        );
+        assert_eq!(wait_result(WaitStatus::Failed, &[running]), None);
+    }
+
+    #[test]
+    fn test_wait_selection() {
+        let options = WaitOptions {
+            task_ids: Vec::new(),
+            group: None,
+            labels: vec!["deploy".into()],
+            all: false,
+            status: WaitStatus::Done,
+            quiet: true,
+        };
+        assert!(options.matches(&task("build", Some("deploy"), TaskStatus::Queued)));
+        assert!(!options.matches(&task("default", Some("test"), TaskStatus::Queued)));
+
+        let options = WaitOptions {
+            group: Some("build".into()),
+            ..options
+        };
+        assert!(options.matches(&task("build", Some("deploy"), TaskStatus::Queued)));
+        assert!(!options.matches(&task("default", Some("deploy"), TaskStatus::Queued)));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use log::warn;
+
+use pueue_lib::network::message::{Message, MirrorRequestMessage};
+use pueue_lib::network::protocol::{receive_message, GenericStream};
+use pueue_lib::settings::Settings;
+use pueue_lib::task::{Task, TaskStatus};
+
+use crate::client::reconnect;
+use crate::commands::send_request;
+use crate::commands::wait::log_status_change;
+use crate::display::colors::Colors;
+
+/// Watch all tasks (or the tasks of a group) until the client is stopped.
+/// Every status change is logged, similar to `pueue wait`.
+///
+/// If `notify` is set, a desktop notification is shown, whenever a task finishes.
+/// Notifications are shown via `notify-send` on Linux and BSD and via `osascript` on macOS.
+///
+/// The daemon pushes its state, whenever it changed, the same way it does for mirrors.
+/// If the connection to the daemon is lost, the client reconnects and continues watching.
+pub async fn watch(
+    stream: &mut GenericStream,
+    group: &Option<String>,
+    notify: bool,
+    colors: &Colors,
+    settings: &Settings,
+) -> Result<()> {
+    if notify && cfg!(target_os = "windows") {
+        bail!("Desktop notifications aren't supported on Windows yet.");
+    }
+
+    // The last known status of all watched tasks.
+    // Tasks that are already known on the first update don't trigger any notifications.
+    let mut watched_tasks: Option<HashMap<usize, TaskStatus>> = None;
+
+    loop {
+        // Subscribe to the daemon's state. A new one is pushed, whenever something changed.
+        // The output of the tasks isn't needed, which is why no lines are requested.
+        let request = MirrorRequestMessage { lines: 0 };
+        if send_request(Message::MirrorRequest(request), stream)
+            .await
+            .is_err()
+        {
+            *stream = reconnect(settings, false).await?;
+            continue;
+        }
+
+        loop {
+            let state = match receive_message(stream).await {
+                Ok(Message::MirrorUpdate(update)) => update.state,
+                Ok(Message::Failure(failure)) => bail!(failure.text),
+                Ok(message) => bail!("Received an unexpected message: {:?}", message),
+                // The connection has been lost.
+                Err(_) => break,
+            };
+
+            let tasks = state
+                .tasks
+                .values()
+                .filter(|task| group.as_ref().is_none_or(|group| &task.group == group));
+
+            let current_time = Local::now().format("%H:%M:%S").to_string();
+            let mut known_tasks = HashMap::new();
+            for task in tasks {
+                known_tasks.insert(task.id, task.status.clone());
+                let watched = match &watched_tasks {
+                    Some(watched) => watched,
+                    None => continue,
+                };
+
+                // Tasks might have been added and finished in between two updates.
+                // New tasks are thereby only logged, once they're done.
+                let finished = matches!(task.status, TaskStatus::Done(_));
+                let previous_status = match watched.get(&task.id) {
+                    Some(status) if status == &task.status => continue,
+                    Some(status) => status.clone(),
+                    None if finished => TaskStatus::Queued,
+                    None => continue,
+                };
+
+                log_status_change(&current_time, previous_status, task, colors);
+                if notify && finished {
+                    // A failing notification shouldn't stop the watch.
+                    if let Err(err) = notify_finished(task) {
+                        warn!(
+                            "Failed to show a notification for task {}: {:?}",
+                            task.id, err
+                        );
+                    }
+                }
+            }
+            watched_tasks = Some(known_tasks);
+        }
+
+        *stream = reconnect(settings, false).await?;
+    }
+}
+
+/// Show a desktop notification about a finished task.
+fn notify_finished(task: &Task) -> Result<()> {
+    let title = match (&task.label, task.failed()) {
+        (Some(label), false) => format!("Task {} ({}) finished", task.id, label),
+        (Some(label), true) => format!("Task {} ({}) failed", task.id, label),
+        (None, false) => format!("Task {} finished", task.id),
+        (None, true) => format!("Task {} failed", task.id),
+    };
+
+    desktop_notification(&title, &task.command, task.failed())
+}
+
+#[cfg(target_os = "macos")]
+fn desktop_notification(title: &str, body: &str, _urgent: bool) -> Result<()> {
+    // AppleScript strings only need quotes and backslashes to be escaped.
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!(
+        "display notification \"{}\" with title \"pueue\" subtitle \"{}\"",
+        escape(body),
+        escape(title)
+    );
+
+    let status = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .status()
+        .context("Failed to run osascript")?;
+    if !status.success() {
+        bail!("osascript failed to show a notification: {}", status);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn desktop_notification(title: &str, body: &str, urgent: bool) -> Result<()> {
+    let urgency = if urgent { "critical" } else { "normal" };
+    let status = Command::new("notify-send")
+        .args([
+            "--app-name",
+            "pueue",
+            "--urgency",
+            urgency,
+            "--",
+            title,
+            body,
+        ])
+        .status()
+        .context("Failed to run notify-send. Is libnotify installed?")?;
+    if !status.success() {
+        bail!("notify-send failed to show a notification: {}", status);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use pueue_lib::task::Task;
+
+use super::colors::Colors;
+use super::helper::style_text;
+use super::locale::Locale;
+use crate::commands::diff::TaskDiff;
+
+/// Print the tasks, that have been added, removed or changed since a snapshot.
+pub fn print_diff(diff: &TaskDiff, json: bool, colors: &Colors, locale: &Locale) {
+    if json {
+        println!("{}", serde_json::to_string(diff).unwrap());
+        return;
+    }
+
+    if diff.is_empty() {
+        println!("{}", locale.get("diff.empty"));
+        return;
+    }
+
+    for task in &diff.added {
+        let line = format!("+ {}", describe(task));
+        println!("{}", style_text(line, Some(colors.green()), None));
+    }
+    for task in &diff.removed {
+        let line = format!("- {}", describe(task));
+        println!("{}", style_text(line, Some(colors.red()), None));
+    }
+    for changed in &diff.changed {
+        let line = format!("~ {}", describe(&changed.after));
+        println!("{}", style_text(line, Some(colors.yellow()), None));
+        for field in &changed.fields {
+            let (before, after) = field_values(field, &changed.before, &changed.after);
+            println!("    {}: {} -> {}", field, before, after);
+        }
+    }
+
+    println!(
+        "{}",
+        locale.format(
+            "diff.summary",
+            &[&diff.added.len(), &diff.removed.len(), &diff.changed.len()]
+        )
+    );
+}
+
+/// A short description of a task, e.g. `3 [default] backup (label: nightly)`.
+fn describe(task: &Task) -> String {
+    let mut text = format!("{} [{}] {}", task.id, task.group, task.command);
+    if let Some(label) = &task.label {
+        text.push_str(&format!(" (label: {})", label));
+    }
+    text
+}
+
+/// The values of a changed field before and after.
+fn field_values(field: &str, before: &Task, after: &Task) -> (String, String) {
+    let value = |task: &Task| match field {
+        "command" => task.command.clone(),
+        "path" => task.path.clone(),
+        "group" => task.group.clone(),
+        "dependencies" => task.dependencies.len().to_string(),
+        "tags" => format!("{:?}", task.tags),
+        _ => String::new(),
+    };
+    (value(before), value(after))
+}
@@ -0,0 +1,311 @@
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+use log::debug;
+
+/// How long we sleep between two reads, if the platform's file notifications aren't available.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The maximum time we wait for a notification. \
+/// Some changes, such as the removal of a file, aren't reported on all platforms.
+/// This way they're still noticed eventually.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Wait for changes of a single file.
+///
+/// This uses inotify on Linux, kqueue on macOS and the BSDs and change notifications on Windows.
+/// If those aren't available, we fall back to polling.
+pub struct FileWatcher {
+    watcher: Option<platform::Watcher>,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> FileWatcher {
+        let watcher = match platform::Watcher::new(path) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                debug!("Falling back to polling for {:?}: {}", path, err);
+                None
+            }
+        };
+
+        FileWatcher { watcher }
+    }
+
+    /// Block until the file might have changed.
+    pub fn wait(&mut self) {
+        let watcher = match self.watcher.as_mut() {
+            Some(watcher) => watcher,
+            None => {
+                sleep(POLL_INTERVAL);
+                return;
+            }
+        };
+
+        if let Err(err) = watcher.wait(NOTIFY_TIMEOUT) {
+            debug!("Falling back to polling: {}", err);
+            self.watcher = None;
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod platform {
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use nix::errno::Errno;
+    use nix::poll::{poll, PollFd, PollFlags};
+    use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+    use nix::unistd::close;
+
+    pub struct Watcher {
+        inotify: Inotify,
+    }
+
+    impl Watcher {
+        pub fn new(path: &Path) -> Result<Watcher> {
+            let inotify = Inotify::init(InitFlags::IN_CLOEXEC | InitFlags::IN_NONBLOCK)?;
+            // Create the watcher right away, so the descriptor is closed on errors.
+            let watcher = Watcher { inotify };
+
+            // Removing a file only changes its link count, as long as we have it open.
+            let flags = AddWatchFlags::IN_MODIFY
+                | AddWatchFlags::IN_ATTRIB
+                | AddWatchFlags::IN_DELETE_SELF
+                | AddWatchFlags::IN_MOVE_SELF;
+            watcher.inotify.add_watch(path, flags)?;
+
+            Ok(watcher)
+        }
+
+        pub fn wait(&mut self, timeout: Duration) -> Result<()> {
+            let mut fds = [PollFd::new(self.inotify.as_raw_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, timeout.as_millis() as i32) {
+                // Signals, e.g. due to a resized terminal, interrupt the poll.
+                Ok(_) | Err(Errno::EINTR) => (),
+                Err(err) => return Err(err.into()),
+            }
+
+            // We don't care about the events themselves, only that something happened.
+            loop {
+                match self.inotify.read_events() {
+                    Ok(_) => continue,
+                    Err(Errno::EAGAIN) => return Ok(()),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            let _ = close(self.inotify.as_raw_fd());
+        }
+    }
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+mod platform {
+    use std::fs::File;
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::path::Path;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use nix::errno::Errno;
+    use nix::sys::event::{kevent, kqueue, EventFilter, EventFlag, FilterFlag, KEvent};
+    use nix::unistd::close;
+
+    pub struct Watcher {
+        kqueue: RawFd,
+        /// kqueue watches open files, so we have to keep it open.
+        file: File,
+    }
+
+    impl Watcher {
+        pub fn new(path: &Path) -> Result<Watcher> {
+            let file = File::open(path)?;
+            let watcher = Watcher {
+                kqueue: kqueue()?,
+                file,
+            };
+
+            let event = KEvent::new(
+                watcher.file.as_raw_fd() as usize,
+                EventFilter::EVFILT_VNODE,
+                EventFlag::EV_ADD | EventFlag::EV_CLEAR,
+                FilterFlag::NOTE_WRITE
+                    | FilterFlag::NOTE_EXTEND
+                    | FilterFlag::NOTE_ATTRIB
+                    | FilterFlag::NOTE_DELETE
+                    | FilterFlag::NOTE_RENAME,
+                0,
+                0,
+            );
+            kevent(watcher.kqueue, &[event], &mut [], 0)?;
+
+            Ok(watcher)
+        }
+
+        pub fn wait(&mut self, timeout: Duration) -> Result<()> {
+            let mut events = [KEvent::new(
+                0,
+                EventFilter::EVFILT_VNODE,
+                EventFlag::empty(),
+                FilterFlag::empty(),
+                0,
+                0,
+            )];
+            match kevent(self.kqueue, &[], &mut events, timeout.as_millis() as usize) {
+                Ok(_) | Err(Errno::EINTR) => Ok(()),
+                Err(err) => Err(err.into()),
+            }
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            let _ = close(self.kqueue);
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use anyhow::{bail, Result};
+    use winapi::shared::minwindef::FALSE;
+    use winapi::um::fileapi::{
+        FindCloseChangeNotification, FindFirstChangeNotificationW, FindNextChangeNotification,
+    };
+    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::synchapi::WaitForSingleObject;
+    use winapi::um::winbase::{WAIT_FAILED, WAIT_OBJECT_0};
+    use winapi::um::winnt::{
+        FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_CHANGE_SIZE,
+        HANDLE,
+    };
+
+    /// Windows only reports changes of whole directories.
+    /// We watch the log directory, so output of other tasks wakes us up as well.
+    pub struct Watcher {
+        handle: HANDLE,
+    }
+
+    impl Watcher {
+        pub fn new(path: &Path) -> Result<Watcher> {
+            let directory = path.parent().unwrap_or(path);
+            let directory: Vec<u16> = directory.as_os_str().encode_wide().chain(Some(0)).collect();
+
+            let handle = unsafe {
+                FindFirstChangeNotificationW(
+                    directory.as_ptr(),
+                    FALSE,
+                    FILE_NOTIFY_CHANGE_FILE_NAME
+                        | FILE_NOTIFY_CHANGE_SIZE
+                        | FILE_NOTIFY_CHANGE_LAST_WRITE,
+                )
+            };
+            if handle == INVALID_HANDLE_VALUE {
+                bail!("Failed to watch directory {:?}", path.parent());
+            }
+
+            Ok(Watcher { handle })
+        }
+
+        pub fn wait(&mut self, timeout: Duration) -> Result<()> {
+            let result = unsafe { WaitForSingleObject(self.handle, timeout.as_millis() as u32) };
+            if result == WAIT_FAILED {
+                bail!("Failed to wait for change notifications");
+            }
+            // Rearm the notification for the next change.
+            if result == WAIT_OBJECT_0
+                && unsafe { FindNextChangeNotification(self.handle) } == FALSE
+            {
+                bail!("Failed to wait for change notifications");
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            unsafe { FindCloseChangeNotification(self.handle) };
+        }
+    }
+}
+
+/// There's no notification mechanism on this platform. Always poll.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "windows"
+)))]
+mod platform {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use anyhow::{bail, Result};
+
+    pub struct Watcher;
+
+    impl Watcher {
+        pub fn new(_path: &Path) -> Result<Watcher> {
+            bail!("File notifications aren't supported on this platform")
+        }
+
+        pub fn wait(&mut self, _timeout: Duration) -> Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::thread;
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_wait_returns_on_change() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut watcher = FileWatcher::new(file.path());
+
+        let path = file.path().to_path_buf();
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let mut file = OpenOptions::new().append(true).open(path).unwrap();
+            writeln!(file, "output").unwrap();
+        });
+
+        let start = Instant::now();
+        watcher.wait();
+        writer.join().unwrap();
+
+        assert!(start.elapsed() < NOTIFY_TIMEOUT);
+    }
+}
@@ -1,9 +1,19 @@
-use std::io;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::Duration;
 
-use pueue_lib::log::{get_log_file_handles, get_log_paths};
+use pueue_lib::log::{get_log_file_handles, get_task_log_paths};
+
+use super::file_watcher::FileWatcher;
+use super::line_renderer::follow_output;
+use super::locale::Locale;
+use super::prefixed_output::{PrefixedOutput, TaskOutput};
+
+/// How long we sleep between two reads, while following several tasks.
+const MULTIPLE_POLL_INTERVAL: Duration = Duration::from_millis(200);
 
 /// Follow the log ouput of running task.
 ///
@@ -12,34 +22,99 @@ use pueue_lib::log::{get_log_file_handles, get_log_paths};
 /// - No running task: Print an error that there are no running tasks
 /// - Single running task: Follow the output of that task
 /// - Multiple running tasks: Print out the list of possible tasks to follow.
-pub fn follow_local_task_logs(pueue_directory: &Path, task_id: usize, stderr: bool) {
-    let (stdout_handle, stderr_handle) = match get_log_file_handles(task_id, pueue_directory) {
+pub fn follow_local_task_logs(
+    log_directory: &Path,
+    task_id: usize,
+    stderr: bool,
+    raw: bool,
+    locale: &Locale,
+) {
+    let (stdout_handle, stderr_handle) = match get_log_file_handles(task_id, log_directory) {
         Ok((stdout, stderr)) => (stdout, stderr),
         Err(err) => {
-            println!("Failed to get log file handles: {}", err);
+            println!("{}", locale.format("log.handles_failed", &[&err]));
             return;
         }
     };
     let mut handle = if stderr { stderr_handle } else { stdout_handle };
 
-    let (out_path, err_path) = get_log_paths(task_id, pueue_directory);
+    let (out_path, err_path) = get_task_log_paths(task_id, log_directory);
     let handle_path = if stderr { err_path } else { out_path };
 
     // Stdout handler to directly write log file output to io::stdout
     // without having to load anything into memory.
-    let mut stdout = io::stdout();
+    let mut stdout = follow_output(raw);
+    let mut watcher = FileWatcher::new(&handle_path);
     loop {
         // Check whether the file still exists. Exit if it doesn't.
         if !handle_path.exists() {
-            println!("File has gone away. Did somebody remove the task?");
+            println!("{}", locale.get("follow.gone"));
             return;
         }
         // Read the next chunk of text from the last position.
         if let Err(err) = io::copy(&mut handle, &mut stdout) {
-            println!("Error while reading file: {}", err);
+            println!("{}", locale.format("follow.read_failed", &[&err]));
             return;
         };
-        let timeout = Duration::from_millis(100);
-        sleep(timeout);
+        watcher.wait();
+    }
+}
+
+/// Follow the log output of several tasks at once.
+/// Each line is prefixed with the id of its task. Tasks, whose log files are removed,
+/// are dropped until there's nothing left to follow.
+///
+/// `log_directories` maps the ids of the followed tasks to the directories of their log files.
+pub fn follow_multiple_local_task_logs(
+    log_directories: &BTreeMap<usize, PathBuf>,
+    stderr: bool,
+    raw: bool,
+    locale: &Locale,
+) {
+    let task_ids: Vec<usize> = log_directories.keys().copied().collect();
+    let output = Arc::new(Mutex::new(PrefixedOutput::new(
+        io::stdout(),
+        &task_ids,
+        raw,
+    )));
+
+    let mut followed = Vec::new();
+    for (&task_id, log_directory) in log_directories {
+        let mut task_output = TaskOutput::new(output.clone(), task_id);
+        let (stdout_handle, stderr_handle) = match get_log_file_handles(task_id, log_directory) {
+            Ok(handles) => handles,
+            Err(err) => {
+                let _ = writeln!(
+                    task_output,
+                    "{}",
+                    locale.format("log.handles_failed", &[&err])
+                );
+                continue;
+            }
+        };
+        let handle = if stderr { stderr_handle } else { stdout_handle };
+        let (out_path, err_path) = get_task_log_paths(task_id, log_directory);
+        let handle_path = if stderr { err_path } else { out_path };
+
+        followed.push((handle, handle_path, task_output));
+    }
+
+    while !followed.is_empty() {
+        followed.retain_mut(|(handle, handle_path, task_output)| {
+            if !handle_path.exists() {
+                let _ = writeln!(task_output, "{}", locale.get("follow.gone"));
+                return false;
+            }
+            if let Err(err) = io::copy(handle, task_output) {
+                let _ = writeln!(
+                    task_output,
+                    "{}",
+                    locale.format("follow.read_failed", &[&err])
+                );
+                return false;
+            }
+            true
+        });
+        sleep(MULTIPLE_POLL_INTERVAL);
     }
 }
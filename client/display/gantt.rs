@@ -0,0 +1,123 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Local};
+
+use pueue_lib::settings::PUEUE_DEFAULT_GROUP;
+use pueue_lib::state::State;
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
+
+use super::helper::sort_tasks_by_group;
+use super::locale::Locale;
+
+/// The maximum amount of characters of a command that's shown in the chart.
+const MAX_NAME_LENGTH: usize = 40;
+
+/// Print a mermaid gantt chart with the start and end times of all tasks. \
+/// Each group gets its own section, which makes it easy to spot groups that run
+/// their tasks one after another, even though they could run in parallel.
+///
+/// Tasks that haven't been started yet aren't part of the chart.
+/// Tasks that are still running end at the current time.
+pub fn print_gantt(state: &State, group_only: &Option<String>, locale: &Locale) {
+    let mut chart = String::new();
+    let _ = writeln!(chart, "gantt");
+    let _ = writeln!(
+        chart,
+        "    title {}",
+        sanitize_name(locale.get("report.timeline"))
+    );
+    let _ = writeln!(chart, "    dateFormat YYYY-MM-DDTHH:mm:ss");
+    let _ = writeln!(chart, "    axisFormat %H:%M");
+
+    let sorted_tasks = sort_tasks_by_group(&state.tasks);
+    // The default group is always shown first, just like in `pueue status`.
+    let mut groups: Vec<&String> = sorted_tasks.keys().collect();
+    groups.sort_by_key(|name| name.as_str() != PUEUE_DEFAULT_GROUP);
+
+    let now = Local::now();
+    for group in groups {
+        if matches!(group_only, Some(group_only) if group_only != group) {
+            continue;
+        }
+
+        let mut section = String::new();
+        for (id, task) in sorted_tasks.get(group).unwrap() {
+            let start = match task.start {
+                Some(start) => start,
+                None => continue,
+            };
+            let end = task.end.unwrap_or(now);
+            let _ = writeln!(
+                section,
+                "    {} :{}task{}, {}, {}",
+                task_name(*id, task),
+                status_tags(&task.status),
+                id,
+                format_time(start),
+                format_time(end),
+            );
+        }
+
+        // Skip groups without any started tasks.
+        if !section.is_empty() {
+            let _ = writeln!(chart, "    section {}", sanitize_name(group));
+            chart.push_str(&section);
+        }
+    }
+
+    print!("{}", chart);
+}
+
+/// The name of a task in the chart.
+/// Labels are preferred, since they're usually shorter and more descriptive than the command.
+fn task_name(id: usize, task: &Task) -> String {
+    let name = task.label.as_ref().unwrap_or(&task.command);
+    let mut short: String = name.chars().take(MAX_NAME_LENGTH).collect();
+    if short.len() < name.len() {
+        short.push_str("...");
+    }
+
+    format!("{} {}", id, sanitize_name(&short))
+}
+
+/// The mermaid tags that are used to highlight a task, depending on its status.
+/// The tags are followed by a comma, since the task id is appended afterwards.
+fn status_tags(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Running | TaskStatus::Paused => "active, ",
+        TaskStatus::Done(TaskResult::Success) => "done, ",
+        TaskStatus::Done(_) => "crit, done, ",
+        _ => "",
+    }
+}
+
+/// Remove all characters that have a special meaning in mermaid's gantt syntax.
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|character| match character {
+            ':' | ';' | '#' | '\n' | '\r' => ' ',
+            _ => character,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Format a timestamp in the format that has been declared via `dateFormat`.
+fn format_time(time: DateTime<Local>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(
+            sanitize_name("sleep 1; echo #done: ok"),
+            "sleep 1  echo  done  ok"
+        );
+    }
+}
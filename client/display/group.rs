@@ -1,13 +1,14 @@
 use pueue_lib::network::message::GroupResponseMessage;
 
-use super::{colors::Colors, helper::*};
+use super::{colors::Colors, helper::*, locale::Locale};
 
-pub fn print_groups(message: GroupResponseMessage, colors: &Colors) {
+pub fn print_groups(message: GroupResponseMessage, colors: &Colors, locale: &Locale) {
     let mut text = String::new();
     let mut group_iter = message.groups.iter().peekable();
     while let Some((name, status)) = group_iter.next() {
         let parallel = *message.settings.get(name).unwrap();
-        let styled = get_group_headline(name, status, parallel, colors);
+        let reason = message.pause_reasons.get(name);
+        let styled = get_group_headline(name, status, parallel, reason, colors, locale);
 
         text.push_str(&styled);
         if group_iter.peek().is_some() {
@@ -1,13 +1,17 @@
 use std::collections::BTreeMap;
 use std::io::stdout;
 
+use chrono::{DateTime, Duration, Local};
 use crossterm::style::{style, Attribute, Color, Stylize};
 use crossterm::tty::IsTty;
 
-use pueue_lib::state::GroupStatus;
-use pueue_lib::task::{Task, TaskStatus};
+use pueue_lib::settings::Settings;
+use pueue_lib::state::{GroupStatus, PauseReason, State};
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
 
 use super::colors::Colors;
+use super::locale::Locale;
+use crate::client::client_identity;
 
 /// This is a simple small helper function with the purpose of easily styling text,
 /// while also prevent styling if we're printing to a non-tty output.
@@ -36,7 +40,7 @@ pub fn style_text<T: ToString>(
 
 /// By default, several columns aren't shown until there's actually some data to display.
 /// This function determines, which of those columns actually need to be shown.
-pub fn has_special_columns(tasks: &BTreeMap<usize, Task>) -> (bool, bool, bool) {
+pub fn has_special_columns(tasks: &BTreeMap<usize, Task>) -> (bool, bool, bool, bool) {
     // Check whether there are any delayed tasks.
     let has_delayed_tasks = tasks.iter().any(|(_, task)| {
         matches!(
@@ -55,7 +59,12 @@ pub fn has_special_columns(tasks: &BTreeMap<usize, Task>) -> (bool, bool, bool)
     // Check whether there are any tasks a label.
     let has_labels = tasks.iter().any(|(_id, task)| task.label.is_some());
 
-    (has_delayed_tasks, has_dependencies, has_labels)
+    // Check whether there are any tasks that currently occupy a worker slot.
+    let has_slots = tasks
+        .iter()
+        .any(|(_id, task)| task.is_running() && task.slot.is_some());
+
+    (has_delayed_tasks, has_dependencies, has_labels, has_slots)
 }
 
 /// Return a nicely formatted headline that's displayed above group tables
@@ -63,18 +72,90 @@ pub fn get_group_headline(
     name: &str,
     status: &GroupStatus,
     parallel: usize,
+    reason: Option<&PauseReason>,
     colors: &Colors,
+    locale: &Locale,
 ) -> String {
     // Style group name
-    let name = style(format!("Group \"{}\"", name)).attribute(Attribute::Bold);
+    let name = style(locale.format("group.name", &[&name])).attribute(Attribute::Bold);
 
-    // Print the current state of the group.
-    let status = match status {
-        GroupStatus::Running => style_text("running", Some(colors.green()), None),
-        GroupStatus::Paused => style_text("paused", Some(colors.yellow()), None),
+    // Print the current state of the group and why the daemon paused it.
+    let status = match (status, reason) {
+        (GroupStatus::Running, _) => {
+            style_text(locale.get("group.running"), Some(colors.green()), None)
+        }
+        (GroupStatus::Paused, Some(PauseReason::OutsideWindow { window })) => style_text(
+            locale.format("group.paused_outside_window", &[window]),
+            Some(colors.yellow()),
+            None,
+        ),
+        (GroupStatus::Paused, None) => {
+            style_text(locale.get("group.paused"), Some(colors.yellow()), None)
+        }
     };
 
-    format!("{} ({} parallel): {}", name, parallel, status)
+    locale.format("group.headline", &[&name, &parallel, &status])
+}
+
+/// Return the localized, human readable representation of a task's status.
+pub fn get_status_text(status: &TaskStatus, locale: &Locale) -> String {
+    match status {
+        TaskStatus::Queued => locale.get("task.queued").to_string(),
+        TaskStatus::Stashed { .. } => locale.get("task.stashed").to_string(),
+        TaskStatus::Running => locale.get("task.running").to_string(),
+        TaskStatus::Paused => locale.get("task.paused").to_string(),
+        TaskStatus::Locked => locale.get("task.locked").to_string(),
+        TaskStatus::Reserved => locale.get("task.reserved").to_string(),
+        TaskStatus::Done(result) => match result {
+            TaskResult::Success => locale.get("task.success").to_string(),
+            TaskResult::DependencyFailed => locale.get("task.dependency_failed").to_string(),
+            TaskResult::FailedToSpawn(_) => locale.get("task.failed_to_spawn").to_string(),
+            TaskResult::Failed(code) => locale.format("task.failed", &[code]),
+            TaskResult::Killed => locale.get("task.killed").to_string(),
+            TaskResult::Errored => locale.get("task.errored").to_string(),
+            TaskResult::Expired => locale.get("task.expired").to_string(),
+            TaskResult::FailedTimeout => locale.get("task.failed_timeout").to_string(),
+            TaskResult::OutOfMemory => locale.get("task.out_of_memory").to_string(),
+            TaskResult::Skipped => locale.get("task.skipped").to_string(),
+        },
+    }
+}
+
+/// Return the color that's used to display a task's status.
+pub fn get_status_color(status: &TaskStatus, colors: &Colors) -> Color {
+    match status {
+        TaskStatus::Running => colors.green(),
+        TaskStatus::Paused | TaskStatus::Locked | TaskStatus::Reserved => colors.white(),
+        TaskStatus::Done(TaskResult::Success) => colors.green(),
+        TaskStatus::Done(TaskResult::Skipped) => colors.white(),
+        TaskStatus::Done(_) => colors.red(),
+        TaskStatus::Queued | TaskStatus::Stashed { .. } => colors.yellow(),
+    }
+}
+
+/// Determine which group should be shown by `pueue status`.
+/// An explicitly requested group always wins, otherwise `client.default_group_filter` is used,
+/// unless all groups are requested.
+pub fn status_group_filter(
+    group: &Option<String>,
+    all: bool,
+    settings: &Settings,
+) -> Option<String> {
+    if all {
+        None
+    } else {
+        group
+            .clone()
+            .or_else(|| settings.client.default_group_filter.clone())
+    }
+}
+
+/// Remove all tasks from the state, that haven't been submitted by this client.
+pub fn retain_own_tasks(state: &mut State) {
+    let identity = client_identity();
+    state
+        .tasks
+        .retain(|_, task| task.created_by.as_ref() == Some(&identity));
 }
 
 /// Sort given tasks by their groups
@@ -96,3 +177,35 @@ pub fn sort_tasks_by_group(
 
     sorted_task_groups
 }
+
+/// Format the runtime of a task.
+/// Tasks that are still running are measured until now.
+pub fn format_duration(start: Option<DateTime<Local>>, end: Option<DateTime<Local>>) -> String {
+    let start = match start {
+        Some(start) => start,
+        None => return String::new(),
+    };
+    let end = end.unwrap_or_else(Local::now);
+    let duration = end - start;
+    if duration < Duration::zero() {
+        return String::new();
+    }
+
+    format_seconds(duration.num_seconds())
+}
+
+/// Format an amount of seconds, e.g. `1h 2m 3s`.
+pub fn format_seconds(seconds: i64) -> String {
+    if seconds >= 3600 {
+        format!(
+            "{}h {}m {}s",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    } else if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
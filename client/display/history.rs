@@ -0,0 +1,81 @@
+use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
+use comfy_table::*;
+
+use pueue_lib::network::message::HistoryEntry;
+use pueue_lib::settings::Settings;
+use pueue_lib::task::TaskStatus;
+
+use super::colors::Colors;
+use super::helper::{format_duration, get_status_color, get_status_text};
+use super::locale::Locale;
+use crate::cli::SubCommand;
+
+/// Print the cleaned tasks from the history in the order in which they've been cleaned.
+pub fn print_history(
+    entries: &[HistoryEntry],
+    cli_command: &SubCommand,
+    colors: &Colors,
+    settings: &Settings,
+    locale: &Locale,
+) {
+    let (json, show_output) = match cli_command {
+        SubCommand::History { json, output, .. } => (*json, *output),
+        _ => panic!(
+            "Got wrong Subcommand {:?} in print_history. This shouldn't happen",
+            cli_command
+        ),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string(entries).unwrap());
+        return;
+    }
+
+    if entries.is_empty() {
+        println!("{}", locale.get("history.empty"));
+        return;
+    }
+
+    let mut headers = vec![
+        Cell::new(locale.get("status.header.id")),
+        Cell::new(locale.get("history.header.result")),
+        Cell::new(locale.get("history.header.duration")),
+        Cell::new(locale.get("status.header.command")),
+        Cell::new(locale.get("status.header.end")),
+    ];
+    if show_output {
+        headers.push(Cell::new(locale.get("history.header.output")));
+    }
+
+    let mut table = Table::new();
+    table
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(UTF8_HORIZONTAL_BORDERS_ONLY)
+        .set_header(headers);
+
+    for entry in entries {
+        let status = TaskStatus::Done(entry.result.clone());
+        let end = entry
+            .end
+            .map(|end| {
+                end.format(&settings.client.status_datetime_format)
+                    .to_string()
+            })
+            .unwrap_or_default();
+
+        let mut row = Row::new();
+        row.add_cell(Cell::new(entry.id));
+        row.add_cell(
+            Cell::new(get_status_text(&status, locale)).fg(get_status_color(&status, colors)),
+        );
+        row.add_cell(Cell::new(format_duration(entry.start, entry.end)));
+        row.add_cell(Cell::new(&entry.command));
+        row.add_cell(Cell::new(end));
+        if show_output {
+            row.add_cell(Cell::new(&entry.output));
+        }
+        table.add_row(row);
+    }
+
+    println!("{}", table);
+}
@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+
+use crossterm::queue;
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::tty::IsTty;
+
+/// Get the writer for followed output.
+///
+/// Carriage returns are interpreted, unless `raw` is set or stdout isn't a terminal.
+pub fn follow_output(raw: bool) -> Box<dyn Write> {
+    let stdout = io::stdout();
+    if raw || !stdout.is_tty() {
+        Box::new(stdout)
+    } else {
+        Box::new(LineRenderer::new(stdout))
+    }
+}
+
+/// Render output with carriage returns, e.g. progress bars, like a terminal would.
+///
+/// Text after a carriage return replaces the current line instead of being appended to it.
+/// Updates that are overwritten within the same chunk of output are never printed,
+/// so catching up with a long log doesn't replay every single update.
+/// `\r\n` line endings are treated as normal line breaks.
+pub struct LineRenderer<W: Write> {
+    inner: W,
+    /// The last chunk ended with a carriage return.
+    /// We don't know yet, whether it's part of a `\r\n` line ending.
+    carriage_return: bool,
+}
+
+impl<W: Write> LineRenderer<W> {
+    pub fn new(inner: W) -> Self {
+        LineRenderer {
+            inner,
+            carriage_return: false,
+        }
+    }
+
+    /// Write some text to the current line. If `redraw` is set, the line is cleared first.
+    fn write_line(&mut self, line: &[u8], redraw: bool) -> io::Result<()> {
+        if redraw {
+            self.inner.write_all(b"\r")?;
+            queue!(self.inner, Clear(ClearType::CurrentLine))?;
+        }
+        self.inner.write_all(line)
+    }
+}
+
+impl<W: Write> Write for LineRenderer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The text for the current line and whether it replaces the line's previous content.
+        let mut line = Vec::new();
+        let mut redraw = false;
+
+        for &byte in buf {
+            match byte {
+                b'\r' => self.carriage_return = true,
+                b'\n' => {
+                    self.write_line(&line, redraw)?;
+                    self.inner.write_all(b"\n")?;
+                    line.clear();
+                    redraw = false;
+                    self.carriage_return = false;
+                }
+                _ => {
+                    if self.carriage_return {
+                        line.clear();
+                        redraw = true;
+                        self.carriage_return = false;
+                    }
+                    line.push(byte);
+                }
+            }
+        }
+        self.write_line(&line, redraw)?;
+
+        // Progress bars rarely end with a newline, so we have to flush manually.
+        self.inner.flush()?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const CLEAR: &str = "\r\u{1b}[2K";
+
+    fn render(chunks: &[&str]) -> String {
+        let mut renderer = LineRenderer::new(Vec::new());
+        for chunk in chunks {
+            renderer.write_all(chunk.as_bytes()).unwrap();
+        }
+        String::from_utf8(renderer.inner).unwrap()
+    }
+
+    #[test]
+    fn test_plain_lines() {
+        assert_eq!(render(&["first\nsec", "ond\n"]), "first\nsecond\n");
+    }
+
+    #[test]
+    fn test_windows_line_endings() {
+        assert_eq!(
+            render(&["first\r\nsecond\r", "\nthird"]),
+            "first\nsecond\nthird"
+        );
+    }
+
+    #[test]
+    fn test_overwritten_updates_are_skipped() {
+        assert_eq!(
+            render(&["10%\r50%\r100%\ndone\n"]),
+            format!("{}100%\ndone\n", CLEAR)
+        );
+    }
+
+    #[test]
+    fn test_redraw_across_chunks() {
+        assert_eq!(
+            render(&["Downloading 10%", "\r", "Downloading 20%\r"]),
+            format!("Downloading 10%{}Downloading 20%", CLEAR)
+        );
+    }
+}
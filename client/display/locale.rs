@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::warn;
+
+use pueue_lib::settings::Settings;
+
+/// The english message catalog.
+/// This is the default and the fallback for any keys that are missing in other catalogs.
+///
+/// Every occurrence of `{}` is a placeholder, which is replaced by the respective argument.
+const EN: &[(&str, &str)] = &[
+    // Group headlines
+    ("group.name", "Group \"{}\""),
+    ("group.headline", "{} ({} parallel): {}"),
+    ("group.running", "running"),
+    ("group.paused", "paused"),
+    (
+        "group.paused_outside_window",
+        "paused (outside of window {})",
+    ),
+    // Status table
+    (
+        "status.empty",
+        "Task list is empty. Add tasks with `pueue add -- [cmd]`",
+    ),
+    (
+        "status.empty_group",
+        "Task list is empty. Add tasks with `pueue add -g {} -- [cmd]`",
+    ),
+    ("status.array", "{} (array of {} tasks)"),
+    ("status.wrote_stderr", "{} (stderr)"),
+    ("status.header.id", "Id"),
+    ("status.header.status", "Status"),
+    ("status.header.slot", "Slot"),
+    ("status.header.enqueue_at", "Enqueue At"),
+    ("status.header.deps", "Deps"),
+    ("status.header.label", "Label"),
+    ("status.header.command", "Command"),
+    ("status.header.path", "Path"),
+    ("status.header.start", "Start"),
+    ("status.header.end", "End"),
+    // Task status in the status table
+    ("task.queued", "Queued"),
+    ("task.stashed", "Stashed"),
+    ("task.running", "Running"),
+    ("task.paused", "Paused"),
+    ("task.locked", "Locked"),
+    ("task.reserved", "Reserved"),
+    ("task.success", "Success"),
+    ("task.failed", "Failed ({})"),
+    ("task.failed_to_spawn", "Failed to spawn"),
+    ("task.killed", "Killed"),
+    ("task.errored", "Errored"),
+    ("task.dependency_failed", "Dependency failed"),
+    ("task.expired", "Expired"),
+    ("task.failed_timeout", "Timed out"),
+    ("task.out_of_memory", "Out of memory"),
+    ("task.skipped", "Skipped"),
+    // Log output
+    ("log.no_finished_tasks", "There are no finished tasks"),
+    (
+        "log.no_finished_tasks_for_ids",
+        "There are no finished tasks for your specified ids",
+    ),
+    (
+        "log.none_received",
+        "Logs requested from pueue daemon, but none received. Please report this bug.",
+    ),
+    ("log.task", "Task {}: "),
+    ("log.paused", "paused"),
+    ("log.running", "running"),
+    ("log.success", "completed successfully"),
+    ("log.failed", "failed with exit code {}"),
+    ("log.failed_to_spawn", "failed to spawn: {}"),
+    ("log.killed", "killed by system or user"),
+    ("log.errored", "some IO error.\n Check daemon log."),
+    ("log.dependency_failed", "dependency failed"),
+    ("log.expired", "expired before it started"),
+    (
+        "log.failed_timeout",
+        "killed after exceeding its maximum runtime",
+    ),
+    (
+        "log.out_of_memory",
+        "killed after exceeding its memory limit",
+    ),
+    ("log.skipped", "skipped, as none of its dependencies failed"),
+    ("log.command", "Command:"),
+    ("log.path", "Path:"),
+    ("log.start", "Start:"),
+    ("log.end", "End:"),
+    ("log.meta", "Meta:"),
+    ("log.tags", "Tags:"),
+    ("log.cpus", "CPUs:"),
+    ("log.resources", "Resources:"),
+    ("log.limits", "Limits:"),
+    ("log.start_conditions", "Waits for:"),
+    ("log.on_success", "On success:"),
+    ("log.on_failure", "On failure:"),
+    ("log.shell", "Shell:"),
+    ("log.stderr_details", "Stderr:"),
+    ("log.stderr_merged", "Written to the stdout log"),
+    ("log.stderr_written", "Written to the stdout log, not empty"),
+    ("log.rotations", "Rotated:"),
+    ("log.rotated", "{} times, older output isn't shown"),
+    ("log.stdout", "stdout:"),
+    ("log.stderr", "stderr:"),
+    ("log.handles_failed", "Failed to get log file handles: {}"),
+    ("log.read_failed", "Failed reading local log file: {}"),
+    ("log.parse_stdout_failed", "Error while parsing stdout: {}"),
+    ("log.parse_stderr_failed", "Error while parsing stderr: {}"),
+    (
+        "log.json.handles_failed",
+        "(Pueue error) Failed to get log file handles: {}",
+    ),
+    (
+        "log.json.read_failed",
+        "(Pueue error) Failed to read local log output file: {}",
+    ),
+    (
+        "log.json.decompress_failed",
+        "(Pueue error) Failed to decompress remote log output: {}",
+    ),
+    // HTML report
+    ("report.title", "Pueue report"),
+    ("report.generated_at", "Generated at {}"),
+    ("report.duration", "Duration"),
+    ("report.log", "Log"),
+    ("report.show_log", "show"),
+    ("report.logs", "Output (last {} lines)"),
+    ("report.timeline", "Pueue timeline"),
+    // Follow
+    (
+        "follow.gone",
+        "File has gone away. Did somebody remove the task?",
+    ),
+    ("follow.read_failed", "Error while reading file: {}"),
+    // Confirmation prompts
+    ("prompt.confirm", "You are trying to {}: {}"),
+    ("prompt.continue", "Do you want to continue [Y/n]: "),
+    ("prompt.aborted", "Aborted!"),
+    ("prompt.action.kill", "kill"),
+    ("prompt.action.remove", "remove"),
+    ("prompt.action.remove_running", "remove running tasks"),
+    ("prompt.action.reset", "reset"),
+    // Schedule
+    ("schedule.empty", "There are no scheduled events."),
+    ("schedule.header.time", "Time"),
+    ("schedule.header.event", "Event"),
+    ("schedule.header.task", "Task"),
+    ("schedule.header.group", "Group"),
+    ("schedule.enqueue", "Enqueue"),
+    ("schedule.expire", "Expire"),
+    ("schedule.timeout", "Timeout"),
+    // History
+    ("history.empty", "There are no tasks in the history."),
+    ("history.header.result", "Result"),
+    ("history.header.duration", "Duration"),
+    ("history.header.output", "Output"),
+    // Statistics
+    ("stats.empty", "No task has finished yet."),
+    ("stats.header.group", "Group"),
+    ("stats.header.tasks", "Tasks"),
+    ("stats.header.failures", "Failed"),
+    ("stats.header.runtime", "Runtime"),
+    ("stats.header.since", "Since"),
+    // Diff
+    ("diff.empty", "The queue matches the snapshot."),
+    ("diff.summary", "{} added, {} removed, {} changed"),
+    // Dashboard
+    ("tui.group", "Group"),
+    ("tui.log", "Log of task {}"),
+    (
+        "tui.help",
+        "↑/↓ select  p pause  s start  k kill  r restart  P/S pause/start group  q quit",
+    ),
+];
+
+/// The german message catalog.
+const DE: &[(&str, &str)] = &[
+    ("group.name", "Gruppe \"{}\""),
+    ("group.headline", "{} ({} parallel): {}"),
+    ("group.running", "läuft"),
+    ("group.paused", "pausiert"),
+    ("group.paused_outside_window", "pausiert (außerhalb des Zeitfensters {})"),
+    (
+        "status.empty",
+        "Die Taskliste ist leer. Füge Tasks mit `pueue add -- [cmd]` hinzu",
+    ),
+    (
+        "status.empty_group",
+        "Die Taskliste ist leer. Füge Tasks mit `pueue add -g {} -- [cmd]` hinzu",
+    ),
+    ("status.array", "{} (Array mit {} Tasks)"),
+    ("status.wrote_stderr", "{} (stderr)"),
+    ("status.header.id", "Id"),
+    ("status.header.status", "Status"),
+    ("status.header.slot", "Slot"),
+    ("status.header.enqueue_at", "Einreihen um"),
+    ("status.header.deps", "Abh."),
+    ("status.header.label", "Label"),
+    ("status.header.command", "Befehl"),
+    ("status.header.path", "Pfad"),
+    ("status.header.start", "Start"),
+    ("status.header.end", "Ende"),
+    ("task.queued", "Eingereiht"),
+    ("task.stashed", "Zurückgestellt"),
+    ("task.running", "Läuft"),
+    ("task.paused", "Pausiert"),
+    ("task.locked", "Gesperrt"),
+    ("task.reserved", "Reserviert"),
+    ("task.success", "Erfolgreich"),
+    ("task.failed", "Fehlgeschlagen ({})"),
+    ("task.failed_to_spawn", "Start fehlgeschlagen"),
+    ("task.killed", "Beendet"),
+    ("task.errored", "Fehler"),
+    ("task.dependency_failed", "Abhängigkeit fehlgeschlagen"),
+    ("task.expired", "Abgelaufen"),
+    ("task.failed_timeout", "Zeitüberschreitung"),
+    ("task.out_of_memory", "Speicher erschöpft"),
+    ("task.skipped", "Übersprungen"),
+    ("log.no_finished_tasks", "Es gibt keine beendeten Tasks"),
+    (
+        "log.no_finished_tasks_for_ids",
+        "Es gibt keine beendeten Tasks für die angegebenen Ids",
+    ),
+    (
+        "log.none_received",
+        "Logs vom Daemon angefragt, aber keine erhalten. Bitte melde diesen Fehler.",
+    ),
+    ("log.task", "Task {}: "),
+    ("log.paused", "pausiert"),
+    ("log.running", "läuft"),
+    ("log.success", "erfolgreich abgeschlossen"),
+    ("log.failed", "fehlgeschlagen mit Exit-Code {}"),
+    ("log.failed_to_spawn", "Start fehlgeschlagen: {}"),
+    ("log.killed", "vom System oder Nutzer beendet"),
+    ("log.errored", "ein IO-Fehler.\n Prüfe das Daemon-Log."),
+    ("log.dependency_failed", "Abhängigkeit fehlgeschlagen"),
+    ("log.expired", "vor dem Start abgelaufen"),
+    (
+        "log.failed_timeout",
+        "nach Überschreiten der maximalen Laufzeit beendet",
+    ),
+    (
+        "log.out_of_memory",
+        "nach Überschreiten des Speicherlimits beendet",
+    ),
+    (
+        "log.skipped",
+        "übersprungen, da keine Abhängigkeit fehlgeschlagen ist",
+    ),
+    ("log.command", "Befehl:"),
+    ("log.path", "Pfad:"),
+    ("log.start", "Start:"),
+    ("log.end", "Ende:"),
+    ("log.meta", "Metadaten:"),
+    ("log.tags", "Tags:"),
+    ("log.cpus", "CPUs:"),
+    ("log.resources", "Ressourcen:"),
+    ("log.limits", "Grenzwerte:"),
+    ("log.start_conditions", "Wartet auf:"),
+    ("log.on_success", "Bei Erfolg:"),
+    ("log.on_failure", "Bei Fehler:"),
+    ("log.shell", "Shell:"),
+    ("log.stderr_details", "Stderr:"),
+    ("log.stderr_merged", "Ins stdout-Log geschrieben"),
+    ("log.stderr_written", "Ins stdout-Log geschrieben, nicht leer"),
+    ("log.rotations", "Rotiert:"),
+    ("log.rotated", "{} Mal, ältere Ausgaben fehlen"),
+    (
+        "log.handles_failed",
+        "Konnte die Logdateien nicht öffnen: {}",
+    ),
+    (
+        "log.read_failed",
+        "Konnte die lokale Logdatei nicht lesen: {}",
+    ),
+    (
+        "log.parse_stdout_failed",
+        "Fehler beim Lesen von stdout: {}",
+    ),
+    (
+        "log.parse_stderr_failed",
+        "Fehler beim Lesen von stderr: {}",
+    ),
+    ("report.title", "Pueue Bericht"),
+    ("report.generated_at", "Erstellt um {}"),
+    ("report.duration", "Dauer"),
+    ("report.log", "Log"),
+    ("report.show_log", "anzeigen"),
+    ("report.logs", "Ausgabe (letzte {} Zeilen)"),
+    ("report.timeline", "Pueue Zeitleiste"),
+    (
+        "follow.gone",
+        "Die Datei ist verschwunden. Wurde der Task entfernt?",
+    ),
+    ("follow.read_failed", "Fehler beim Lesen der Datei: {}"),
+    ("prompt.confirm", "Du versuchst {}: {}"),
+    ("prompt.continue", "Möchtest du fortfahren [Y/n]: "),
+    ("prompt.aborted", "Abgebrochen!"),
+    ("prompt.action.kill", "zu beenden"),
+    ("prompt.action.remove", "zu entfernen"),
+    (
+        "prompt.action.remove_running",
+        "laufende Tasks zu entfernen",
+    ),
+    ("prompt.action.reset", "zurückzusetzen"),
+    ("schedule.empty", "Es sind keine Ereignisse geplant."),
+    ("schedule.header.time", "Zeit"),
+    ("schedule.header.event", "Ereignis"),
+    ("schedule.header.task", "Task"),
+    ("schedule.header.group", "Gruppe"),
+    ("schedule.enqueue", "Einreihen"),
+    ("schedule.expire", "Verfallen"),
+    ("schedule.timeout", "Zeitüberschreitung"),
+    ("history.empty", "Es sind keine Tasks im Verlauf."),
+    ("history.header.result", "Ergebnis"),
+    ("history.header.duration", "Dauer"),
+    ("history.header.output", "Ausgabe"),
+    ("stats.empty", "Es wurde noch kein Task beendet."),
+    ("stats.header.group", "Gruppe"),
+    ("stats.header.tasks", "Tasks"),
+    ("stats.header.failures", "Fehlgeschlagen"),
+    ("stats.header.runtime", "Laufzeit"),
+    ("stats.header.since", "Seit"),
+    ("diff.empty", "Die Warteschlange stimmt mit dem Snapshot überein."),
+    ("diff.summary", "{} hinzugefügt, {} entfernt, {} geändert"),
+    ("tui.group", "Gruppe"),
+    ("tui.log", "Log von Task {}"),
+    (
+        "tui.help",
+        "↑/↓ auswählen  p pausieren  s starten  k beenden  r neustarten  P/S Gruppe pausieren/starten  q beenden",
+    ),
+];
+
+/// The message catalog for all user-facing strings of the client.
+///
+/// The catalog is selected via the `client.locale` setting.
+/// This is either the name of a built-in catalog (`en`, `de`) or the path to a YAML file, which
+/// maps message keys to custom strings. \
+/// Any keys that are missing in the selected catalog fall back to the english catalog.
+pub struct Locale {
+    strings: HashMap<String, String>,
+}
+
+impl Locale {
+    /// Build the message catalog depending on the settings.
+    pub fn new(settings: &Settings) -> Self {
+        let mut locale = Locale::from_catalog(EN);
+
+        match settings.client.locale.as_str() {
+            "en" => (),
+            "de" => locale.extend(Locale::from_catalog(DE).strings),
+            path => match read_catalog_file(Path::new(path)) {
+                Ok(strings) => locale.extend(strings),
+                Err(err) => warn!("Failed to load locale {}: {:?}", path, err),
+            },
+        }
+
+        locale
+    }
+
+    /// Create a locale from one of the built-in catalogs.
+    fn from_catalog(catalog: &[(&str, &str)]) -> Self {
+        let strings = catalog
+            .iter()
+            .map(|(key, text)| (key.to_string(), text.to_string()))
+            .collect();
+
+        Locale { strings }
+    }
+
+    /// Overwrite existing strings with the given strings.
+    fn extend(&mut self, strings: HashMap<String, String>) {
+        self.strings.extend(strings);
+    }
+
+    /// Get the string for a specific key. \
+    /// Unknown keys are returned as they are, so missing translations are easy to spot.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).map(String::as_str).unwrap_or(key)
+    }
+
+    /// Get the string for a specific key and replace each `{}` placeholder with the next argument.
+    pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+        let mut parts = self.get(key).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+
+        for (index, part) in parts.enumerate() {
+            if let Some(arg) = args.get(index) {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(part);
+        }
+
+        text
+    }
+}
+
+/// Read a custom message catalog from a YAML file.
+fn read_catalog_file(path: &Path) -> Result<HashMap<String, String>> {
+    let file = File::open(path).context("Couldn't open locale file")?;
+    let strings = serde_yaml::from_reader(file).context("Couldn't parse locale file")?;
+
+    Ok(strings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_format_placeholders() {
+        let locale = Locale::from_catalog(EN);
+
+        assert_eq!(
+            locale.format("group.headline", &[&"Group \"test\"", &2, &"running"]),
+            "Group \"test\" (2 parallel): running"
+        );
+        assert_eq!(locale.format("task.failed", &[&1]), "Failed (1)");
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let locale = Locale::from_catalog(EN);
+
+        assert_eq!(locale.get("does.not.exist"), "does.not.exist");
+    }
+
+    /// Each key may only exist once per catalog, as later entries silently replace earlier ones.
+    #[test]
+    fn test_unique_catalog_keys() {
+        for catalog in [EN, DE] {
+            let mut keys = HashSet::new();
+            for (key, _) in catalog {
+                assert!(keys.insert(*key), "Duplicate key {}", key);
+            }
+        }
+    }
+
+    /// All keys of the other built-in catalogs have to exist in the english fallback catalog.
+    #[test]
+    fn test_catalog_keys() {
+        let english = Locale::from_catalog(EN);
+
+        for (key, _) in DE {
+            assert!(english.strings.contains_key(*key), "Unknown key {}", key);
+        }
+    }
+}
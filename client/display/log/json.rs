@@ -9,6 +9,8 @@ use pueue_lib::network::message::TaskLogMessage;
 use pueue_lib::settings::Settings;
 use pueue_lib::task::Task;
 
+use crate::display::locale::Locale;
+
 /// This is the output struct used for
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TaskLog {
@@ -20,6 +22,7 @@ pub struct TaskLog {
 pub fn print_log_json(
     task_log_messages: BTreeMap<usize, TaskLogMessage>,
     settings: &Settings,
+    locale: &Locale,
     lines: Option<usize>,
 ) {
     let mut tasks: BTreeMap<usize, Task> = BTreeMap::new();
@@ -28,15 +31,16 @@ pub fn print_log_json(
     // Output in TaskLogMessages, if it exists, is compressed.
     // We need to decompress and convert to normal strings.
     for (id, message) in task_log_messages {
+        let output = get_task_output(
+            &message.task,
+            message.stdout,
+            message.stderr,
+            settings,
+            locale,
+            lines,
+        );
         tasks.insert(id, message.task);
-
-        if settings.client.read_local_logs {
-            let output = get_local_logs(settings, id, lines);
-            task_log.insert(id, output);
-        } else {
-            let output = get_remote_logs(message.stdout, message.stderr);
-            task_log.insert(id, output);
-        }
+        task_log.insert(id, output);
     }
 
     // Now assemble the final struct that will be returned
@@ -58,26 +62,47 @@ pub fn print_log_json(
     println!("{}", serde_json::to_string(&json).unwrap());
 }
 
+/// Get the stdout and stderr of a task as plain strings. \
+/// Depending on the settings, the output is either read from the local log files
+/// or decompressed from the daemon's response.
+pub fn get_task_output(
+    task: &Task,
+    stdout: Option<Vec<u8>>,
+    stderr: Option<Vec<u8>>,
+    settings: &Settings,
+    locale: &Locale,
+    lines: Option<usize>,
+) -> (String, String) {
+    if settings.client.read_local_logs {
+        get_local_logs(settings, locale, task, lines)
+    } else {
+        get_remote_logs(stdout, stderr, locale)
+    }
+}
+
 /// Read logs directly from local files for a specific task.
-fn get_local_logs(settings: &Settings, id: usize, lines: Option<usize>) -> (String, String) {
-    let (mut stdout_file, mut stderr_file) =
-        match get_log_file_handles(id, &settings.shared.pueue_directory()) {
-            Ok((stdout, stderr)) => (stdout, stderr),
-            Err(err) => {
-                let error = format!("(Pueue error) Failed to get log file handles: {}", err);
-                return (String::new(), error);
-            }
-        };
+fn get_local_logs(
+    settings: &Settings,
+    locale: &Locale,
+    task: &Task,
+    lines: Option<usize>,
+) -> (String, String) {
+    let log_directory = task.log_directory(&settings.shared.pueue_directory());
+    let (mut stdout_file, mut stderr_file) = match get_log_file_handles(task.id, &log_directory) {
+        Ok((stdout, stderr)) => (stdout, stderr),
+        Err(err) => {
+            let error = locale.format("log.json.handles_failed", &[&err]);
+            return (String::new(), error);
+        }
+    };
 
     let stdout = if let Some(lines) = lines {
         read_last_lines(&mut stdout_file, lines)
     } else {
         let mut stdout = String::new();
         if let Err(error) = stdout_file.read_to_string(&mut stdout) {
-            stdout.push_str(&format!(
-                "(Pueue error) Failed to read local log output file: {:?}",
-                error
-            ))
+            let error = format!("{:?}", error);
+            stdout.push_str(&locale.format("log.json.read_failed", &[&error]))
         };
 
         stdout
@@ -88,10 +113,8 @@ fn get_local_logs(settings: &Settings, id: usize, lines: Option<usize>) -> (Stri
     } else {
         let mut stderr = String::new();
         if let Err(error) = stderr_file.read_to_string(&mut stderr) {
-            stderr.push_str(&format!(
-                "(Pueue error) Failed to read local log output file: {:?}",
-                error
-            ))
+            let error = format!("{:?}", error);
+            stderr.push_str(&locale.format("log.json.read_failed", &[&error]))
         };
 
         stderr
@@ -105,15 +128,14 @@ fn get_local_logs(settings: &Settings, id: usize, lines: Option<usize>) -> (Stri
 fn get_remote_logs(
     stdout_bytes: Option<Vec<u8>>,
     stderr_bytes: Option<Vec<u8>>,
+    locale: &Locale,
 ) -> (String, String) {
     let stdout = if let Some(bytes) = stdout_bytes {
         let mut decoder = FrameDecoder::new(&bytes[..]);
         let mut stdout = String::new();
         if let Err(error) = decoder.read_to_string(&mut stdout) {
-            stdout.push_str(&format!(
-                "(Pueue error) Failed to decompress remote log output: {:?}",
-                error
-            ))
+            let error = format!("{:?}", error);
+            stdout.push_str(&locale.format("log.json.decompress_failed", &[&error]))
         }
         stdout
     } else {
@@ -124,10 +146,8 @@ fn get_remote_logs(
         let mut decoder = FrameDecoder::new(&bytes[..]);
         let mut stderr = String::new();
         if let Err(error) = decoder.read_to_string(&mut stderr) {
-            stderr.push_str(&format!(
-                "(Pueue error) Failed to decompress remote log output: {:?}",
-                error
-            ))
+            let error = format!("{:?}", error);
+            stderr.push_str(&locale.format("log.json.decompress_failed", &[&error]))
         }
 
         stderr
@@ -5,20 +5,27 @@ use comfy_table::*;
 
 use pueue_lib::log::{get_log_file_handles, read_last_lines};
 use pueue_lib::settings::Settings;
+use pueue_lib::task::Task;
 
-use crate::display::{colors::Colors, helper::*};
+use crate::display::{colors::Colors, helper::*, locale::Locale};
 
 /// The daemon didn't send any log output, thereby we didn't request any.
 /// If that's the case, read the log files from the local pueue directory
-pub fn print_local_log(task_id: usize, colors: &Colors, settings: &Settings, lines: Option<usize>) {
-    let (mut stdout_file, mut stderr_file) =
-        match get_log_file_handles(task_id, &settings.shared.pueue_directory()) {
-            Ok((stdout, stderr)) => (stdout, stderr),
-            Err(err) => {
-                println!("Failed to get log file handles: {}", err);
-                return;
-            }
-        };
+pub fn print_local_log(
+    task: &Task,
+    colors: &Colors,
+    settings: &Settings,
+    locale: &Locale,
+    lines: Option<usize>,
+) {
+    let log_directory = task.log_directory(&settings.shared.pueue_directory());
+    let (mut stdout_file, mut stderr_file) = match get_log_file_handles(task.id, &log_directory) {
+        Ok((stdout, stderr)) => (stdout, stderr),
+        Err(err) => {
+            println!("{}", locale.format("log.handles_failed", &[&err]));
+            return;
+        }
+    };
     // Stdout handler to directly write log file output to io::stdout
     // without having to load anything into memory.
     let mut stdout = io::stdout();
@@ -27,20 +34,36 @@ pub fn print_local_log(task_id: usize, colors: &Colors, settings: &Settings, lin
         &mut stdout,
         &mut stdout_file,
         &lines,
-        style_text("stdout:", Some(colors.green()), Some(Attribute::Bold)),
+        style_text(
+            locale.get("log.stdout"),
+            Some(colors.green()),
+            Some(Attribute::Bold),
+        ),
+        locale,
     );
 
     print_local_file(
         &mut stdout,
         &mut stderr_file,
         &lines,
-        style_text("stderr:", Some(colors.red()), Some(Attribute::Bold)),
+        style_text(
+            locale.get("log.stderr"),
+            Some(colors.red()),
+            Some(Attribute::Bold),
+        ),
+        locale,
     );
 }
 
 /// Print a local log file.
 /// This is usually either the stdout or the stderr
-fn print_local_file(stdout: &mut Stdout, file: &mut File, lines: &Option<usize>, text: String) {
+fn print_local_file(
+    stdout: &mut Stdout,
+    file: &mut File,
+    lines: &Option<usize>,
+    text: String,
+    locale: &Locale,
+) {
     if let Ok(metadata) = file.metadata() {
         if metadata.len() != 0 {
             // Don't print a newline between the task information and the first output
@@ -54,7 +77,7 @@ fn print_local_file(stdout: &mut Stdout, file: &mut File, lines: &Option<usize>,
 
             // Print everything
             if let Err(err) = io::copy(file, stdout) {
-                println!("Failed reading local log file: {}", err);
+                println!("{}", locale.format("log.read_failed", &[&err]));
             };
         }
     }
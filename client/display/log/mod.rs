@@ -2,17 +2,20 @@ use std::collections::BTreeMap;
 
 use comfy_table::*;
 
+use pueue_lib::log::archive_directory;
 use pueue_lib::network::message::TaskLogMessage;
+use pueue_lib::path::normalize_separators;
 use pueue_lib::settings::Settings;
 use pueue_lib::task::{Task, TaskResult, TaskStatus};
 
-use super::colors::Colors;
+use super::{colors::Colors, locale::Locale};
 use crate::cli::SubCommand;
 
 mod json;
 mod local;
 mod remote;
 
+pub use json::get_task_output;
 use json::*;
 use local::*;
 use remote::*;
@@ -55,45 +58,65 @@ pub fn print_logs(
     cli_command: &SubCommand,
     colors: &Colors,
     settings: &Settings,
+    locale: &Locale,
 ) {
     // Get actual commandline options.
     // This is necessary to know how we should display/return the log information.
-    let (json, task_ids, lines, full) = match cli_command {
+    let (json, task_ids, lines, full, archived, since) = match cli_command {
         SubCommand::Log {
             json,
             task_ids,
             lines,
             full,
-        } => (*json, task_ids.clone(), *lines, *full),
+            archived,
+            since,
+            ..
+        } => (*json, task_ids.clone(), *lines, *full, *archived, *since),
         _ => panic!(
             "Got wrong Subcommand {:?} in print_log. This shouldn't happen",
             cli_command
         ),
     };
 
+    // Tasks that didn't finish yet are always shown.
+    if let Some(since) = since {
+        task_logs.retain(|_, task_log| match task_log.task.end {
+            Some(end) => end >= since,
+            None => true,
+        });
+    }
+
     let lines = determine_log_line_amount(full, &lines, json, task_logs.len());
 
+    // Local logs of archived tasks live in the archive.
+    if archived {
+        let archive = archive_directory(&settings.shared.pueue_directory());
+        for task_log in task_logs.values_mut() {
+            task_log.task.log_directory = Some(archive.clone());
+        }
+    }
+
     // Return the server response in json representation.
     if json {
-        print_log_json(task_logs, settings, lines);
+        print_log_json(task_logs, settings, locale, lines);
         return;
     }
 
     // Check some early return conditions
     if task_ids.is_empty() && task_logs.is_empty() {
-        println!("There are no finished tasks");
+        println!("{}", locale.get("log.no_finished_tasks"));
         return;
     }
 
     if !task_ids.is_empty() && task_logs.is_empty() {
-        println!("There are no finished tasks for your specified ids");
+        println!("{}", locale.get("log.no_finished_tasks_for_ids"));
         return;
     }
 
     // Iterate over each task and print the respective log.
     let mut task_iter = task_logs.iter_mut().peekable();
-    while let Some((_, mut task_log)) = task_iter.next() {
-        print_log(&mut task_log, colors, settings, lines);
+    while let Some((_, task_log)) = task_iter.next() {
+        print_log(task_log, colors, settings, locale, lines);
 
         // Add a newline if there is another task that's going to be printed.
         if let Some((_, task_log)) = task_iter.peek() {
@@ -118,6 +141,7 @@ fn print_log(
     message: &mut TaskLogMessage,
     colors: &Colors,
     settings: &Settings,
+    locale: &Locale,
     lines: Option<usize>,
 ) {
     let task = &message.task;
@@ -129,34 +153,46 @@ fn print_log(
         return;
     }
 
-    print_task_info(task, colors);
+    print_task_info(task, colors, locale);
 
     if settings.client.read_local_logs {
-        print_local_log(message.task.id, colors, settings, lines);
+        print_local_log(&message.task, colors, settings, locale, lines);
     } else if message.stdout.is_some() && message.stderr.is_some() {
-        print_remote_log(message, colors);
+        print_remote_log(message, colors, locale);
     } else {
-        println!("Logs requested from pueue daemon, but none received. Please report this bug.");
+        println!("{}", locale.get("log.none_received"));
     }
 }
 
 /// Print some information about a task, which is displayed on top of the task's log output.
-fn print_task_info(task: &Task, colors: &Colors) {
+pub fn print_task_info(task: &Task, colors: &Colors, locale: &Locale) {
     // Print task id and exit code.
-    let task_cell = Cell::new(format!("Task {}: ", task.id)).add_attribute(Attribute::Bold);
+    let task_cell =
+        Cell::new(locale.format("log.task", &[&task.id])).add_attribute(Attribute::Bold);
 
     let (exit_status, color) = match &task.status {
-        TaskStatus::Paused => ("paused".into(), colors.white()),
-        TaskStatus::Running => ("running".into(), colors.yellow()),
+        TaskStatus::Paused => (locale.get("log.paused").to_string(), colors.white()),
+        TaskStatus::Running => (locale.get("log.running").to_string(), colors.yellow()),
         TaskStatus::Done(result) => match result {
-            TaskResult::Success => ("completed successfully".into(), colors.green()),
+            TaskResult::Success => (locale.get("log.success").to_string(), colors.green()),
             TaskResult::Failed(exit_code) => {
-                (format!("failed with exit code {}", exit_code), colors.red())
+                (locale.format("log.failed", &[exit_code]), colors.red())
+            }
+            TaskResult::FailedToSpawn(err) => {
+                (locale.format("log.failed_to_spawn", &[err]), colors.red())
+            }
+            TaskResult::Killed => (locale.get("log.killed").to_string(), colors.red()),
+            TaskResult::Errored => (locale.get("log.errored").to_string(), colors.red()),
+            TaskResult::DependencyFailed => (
+                locale.get("log.dependency_failed").to_string(),
+                colors.red(),
+            ),
+            TaskResult::Expired => (locale.get("log.expired").to_string(), colors.red()),
+            TaskResult::FailedTimeout => {
+                (locale.get("log.failed_timeout").to_string(), colors.red())
             }
-            TaskResult::FailedToSpawn(err) => (format!("failed to spawn: {}", err), colors.red()),
-            TaskResult::Killed => ("killed by system or user".into(), colors.red()),
-            TaskResult::Errored => ("some IO error.\n Check daemon log.".into(), colors.red()),
-            TaskResult::DependencyFailed => ("dependency failed".into(), colors.red()),
+            TaskResult::OutOfMemory => (locale.get("log.out_of_memory").to_string(), colors.red()),
+            TaskResult::Skipped => (locale.get("log.skipped").to_string(), colors.white()),
         },
         _ => (task.status.to_string(), colors.white()),
     };
@@ -176,28 +212,139 @@ fn print_task_info(task: &Task, colors: &Colors) {
 
     // Command and path
     table.add_row(vec![
-        Cell::new("Command:").add_attribute(Attribute::Bold),
+        Cell::new(locale.get("log.command")).add_attribute(Attribute::Bold),
         Cell::new(&task.command),
     ]);
     table.add_row(vec![
-        Cell::new("Path:").add_attribute(Attribute::Bold),
-        Cell::new(&task.path),
+        Cell::new(locale.get("log.path")).add_attribute(Attribute::Bold),
+        Cell::new(normalize_separators(&task.path)),
     ]);
+    if let Some(shell) = task.shell {
+        table.add_row(vec![
+            Cell::new(locale.get("log.shell")).add_attribute(Attribute::Bold),
+            Cell::new(shell),
+        ]);
+    }
+    if task.stderr_to_stdout {
+        let stderr = if task.wrote_stderr {
+            locale.get("log.stderr_written")
+        } else {
+            locale.get("log.stderr_merged")
+        };
+        table.add_row(vec![
+            Cell::new(locale.get("log.stderr_details")).add_attribute(Attribute::Bold),
+            Cell::new(stderr),
+        ]);
+    }
 
     // Start and end time
     if let Some(start) = task.start {
         table.add_row(vec![
-            Cell::new("Start:").add_attribute(Attribute::Bold),
+            Cell::new(locale.get("log.start")).add_attribute(Attribute::Bold),
             Cell::new(start.to_rfc2822()),
         ]);
     }
     if let Some(end) = task.end {
         table.add_row(vec![
-            Cell::new("End:").add_attribute(Attribute::Bold),
+            Cell::new(locale.get("log.end")).add_attribute(Attribute::Bold),
             Cell::new(end.to_rfc2822()),
         ]);
     }
 
+    // Metadata, one `key=value` pair per line
+    if !task.meta.is_empty() {
+        let meta = task
+            .meta
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>()
+            .join("\n");
+        table.add_row(vec![
+            Cell::new(locale.get("log.meta")).add_attribute(Attribute::Bold),
+            Cell::new(meta),
+        ]);
+    }
+
+    if !task.tags.is_empty() {
+        table.add_row(vec![
+            Cell::new(locale.get("log.tags")).add_attribute(Attribute::Bold),
+            Cell::new(task.tags.join(", ")),
+        ]);
+    }
+
+    if let Some(cpus) = task.cpus {
+        table.add_row(vec![
+            Cell::new(locale.get("log.cpus")).add_attribute(Attribute::Bold),
+            Cell::new(cpus),
+        ]);
+    }
+
+    // Requested resources, followed by the assigned units once the task has been started.
+    if !task.resources.is_empty() {
+        let resources = task
+            .resources
+            .iter()
+            .map(|(pool, amount)| match task.assigned_resources.get(pool) {
+                Some(units) => format!("{}={} ({})", pool, amount, units.join(", ")),
+                None => format!("{}={}", pool, amount),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        table.add_row(vec![
+            Cell::new(locale.get("log.resources")).add_attribute(Attribute::Bold),
+            Cell::new(resources),
+        ]);
+    }
+
+    // Memory and CPU limits, one per line
+    if task.has_resource_limits() {
+        let mut limits = Vec::new();
+        if let Some(memory_limit) = task.memory_limit {
+            limits.push(format!("memory={}", format_size(memory_limit)));
+        }
+        if let Some(cpu_limit) = task.cpu_limit {
+            limits.push(format!("cpus={}", cpu_limit));
+        }
+        table.add_row(vec![
+            Cell::new(locale.get("log.limits")).add_attribute(Attribute::Bold),
+            Cell::new(limits.join("\n")),
+        ]);
+    }
+
+    if !task.start_conditions.is_empty() {
+        let conditions = task
+            .start_conditions
+            .iter()
+            .map(|condition| condition.to_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+        table.add_row(vec![
+            Cell::new(locale.get("log.start_conditions")).add_attribute(Attribute::Bold),
+            Cell::new(conditions),
+        ]);
+    }
+
+    if let Some(on_success) = &task.on_success {
+        table.add_row(vec![
+            Cell::new(locale.get("log.on_success")).add_attribute(Attribute::Bold),
+            Cell::new(on_success),
+        ]);
+    }
+    if let Some(on_failure) = &task.on_failure {
+        table.add_row(vec![
+            Cell::new(locale.get("log.on_failure")).add_attribute(Attribute::Bold),
+            Cell::new(on_failure),
+        ]);
+    }
+
+    // Older output isn't shown, once a running task's log files got too large.
+    if task.log_rotations > 0 {
+        table.add_row(vec![
+            Cell::new(locale.get("log.rotations")).add_attribute(Attribute::Bold),
+            Cell::new(locale.format("log.rotated", &[&task.log_rotations])),
+        ]);
+    }
+
     // Set the padding of the left column to 0 align the keys to the right
     let first_column = table.get_column_mut(0).unwrap();
     first_column.set_cell_alignment(CellAlignment::Right);
@@ -205,3 +352,14 @@ fn print_task_info(task: &Task, colors: &Colors) {
 
     println!("{}", table);
 }
+
+/// Format an amount of bytes the same way it's passed to `--memory-limit`, e.g. `4G`.
+fn format_size(bytes: u64) -> String {
+    for (unit, factor) in [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)] {
+        if bytes >= factor && bytes.is_multiple_of(factor) {
+            return format!("{}{}", bytes / factor, unit);
+        }
+    }
+
+    bytes.to_string()
+}
@@ -6,22 +6,26 @@ use snap::read::FrameDecoder;
 
 use pueue_lib::network::message::TaskLogMessage;
 
-use crate::display::{colors::Colors, helper::*};
+use crate::display::{colors::Colors, helper::*, locale::Locale};
 
 /// Prints log output received from the daemon.
 /// We can safely call .unwrap() on stdout and stderr in here, since this
 /// branch is always called after ensuring that both are `Some`.
-pub fn print_remote_log(task_log: &TaskLogMessage, colors: &Colors) {
+pub fn print_remote_log(task_log: &TaskLogMessage, colors: &Colors, locale: &Locale) {
     // Save whether stdout was printed, so we can add a newline between outputs.
     if let Some(bytes) = task_log.stdout.as_ref() {
         if !bytes.is_empty() {
             println!(
                 "\n{}",
-                style_text("stdout: ", Some(colors.green()), Some(Attribute::Bold))
+                style_text(
+                    locale.get("log.stdout"),
+                    Some(colors.green()),
+                    Some(Attribute::Bold)
+                )
             );
 
             if let Err(err) = decompress_and_print_remote_log(bytes) {
-                println!("Error while parsing stdout: {}", err);
+                println!("{}", locale.format("log.parse_stdout_failed", &[&err]));
             }
         }
     }
@@ -30,11 +34,15 @@ pub fn print_remote_log(task_log: &TaskLogMessage, colors: &Colors) {
         if !bytes.is_empty() {
             println!(
                 "\n{}",
-                style_text("stderr: ", Some(colors.red()), Some(Attribute::Bold))
+                style_text(
+                    locale.get("log.stderr"),
+                    Some(colors.red()),
+                    Some(Attribute::Bold)
+                )
             );
 
             if let Err(err) = decompress_and_print_remote_log(bytes) {
-                println!("Error while parsing stderr: {}", err);
+                println!("{}", locale.format("log.parse_stderr_failed", &[&err]));
             };
         }
     }
@@ -1,17 +1,39 @@
 pub mod colors;
+mod diff;
+mod file_watcher;
 mod follow;
+mod gantt;
 mod group;
 pub mod helper;
+mod history;
+mod line_renderer;
+pub mod locale;
 mod log;
+mod prefixed_output;
+mod report;
+mod schedule;
 mod state;
+mod statistics;
+mod task_ids;
+
+use pueue_lib::network::message::{FailureKind, FailureMessage, TaskActionResponseMessage};
 
 use self::{colors::Colors, helper::style_text};
 
 // Re-exports
-pub use self::follow::follow_local_task_logs;
+pub use self::diff::print_diff;
+pub use self::follow::{follow_local_task_logs, follow_multiple_local_task_logs};
+pub use self::gantt::print_gantt;
 pub use self::group::print_groups;
-pub use self::log::{determine_log_line_amount, print_logs};
+pub use self::history::print_history;
+pub use self::line_renderer::follow_output;
+pub use self::log::{determine_log_line_amount, get_task_output, print_logs, print_task_info};
+pub use self::prefixed_output::{PrefixedOutput, TaskOutput};
+pub use self::report::print_report;
+pub use self::schedule::print_schedule;
 pub use self::state::print_state;
+pub use self::statistics::print_statistics;
+pub use self::task_ids::{print_added_task_json, print_task_ids_json};
 
 /// Used to style any generic success message from the daemon.
 pub fn print_success(_colors: &Colors, message: &str) {
@@ -23,3 +45,48 @@ pub fn print_error(colors: &Colors, message: &str) {
     let styled = style_text(message, Some(colors.red()), None);
     println!("{}", styled);
 }
+
+/// Print a failure of the daemon together with a hint on how to resolve it.
+pub fn print_failure(colors: &Colors, failure: &FailureMessage) {
+    print_error(colors, &failure.text);
+    if let Some(hint) = failure_hint(&failure.kind) {
+        println!("{}", hint);
+    }
+}
+
+/// A hint that helps users to resolve a specific kind of failure.
+fn failure_hint(kind: &FailureKind) -> Option<&'static str> {
+    match kind {
+        FailureKind::TaskNotFound { .. } => Some("Use `pueue status` to list all existing tasks."),
+        FailureKind::GroupNotFound { .. } => Some("Use `pueue group` to list all existing groups."),
+        FailureKind::PermissionDenied => {
+            Some("The daemon doesn't allow this action. Check its configuration.")
+        }
+        FailureKind::InvalidState { .. } | FailureKind::Other => None,
+    }
+}
+
+/// Print the outcome of an action on several tasks.
+/// Each task, for which the action failed, is listed together with the reason.
+pub fn print_task_action_response(colors: &Colors, response: &TaskActionResponseMessage) {
+    if !response.succeeded.is_empty() {
+        let ids: Vec<String> = response.succeeded.iter().map(|id| id.to_string()).collect();
+        print_success(colors, &format!("{}: {}", response.text, ids.join(", ")));
+    }
+
+    for (task_id, kind) in &response.failed {
+        print_error(colors, &task_action_failure(*task_id, kind));
+    }
+}
+
+/// Explain why an action failed for a specific task.
+pub fn task_action_failure(task_id: usize, kind: &FailureKind) -> String {
+    let reason = match kind {
+        FailureKind::TaskNotFound { .. } => "it doesn't exist".to_string(),
+        FailureKind::InvalidState { expected, actual } => {
+            format!("it's {}, but has to be {}", actual, expected)
+        }
+        _ => "the command isn't possible for this task".to_string(),
+    };
+    format!("Failed for task {}: {}", task_id, reason)
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use crossterm::style::Color;
+
+use super::helper::style_text;
+
+/// The colors of the prefixes. They're assigned to the tasks in turn.
+const PREFIX_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Green,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+];
+
+/// Interleave the output of several tasks, similar to `docker-compose logs`.
+///
+/// Each line is prefixed with the id of its task, which has a different color for each task.
+/// Lines are only printed once they're complete, so output of different tasks never gets mixed
+/// within a single line. Unless `raw` is set, only the text after the last carriage return of
+/// a line is printed, as progress bars can't be redrawn in place.
+pub struct PrefixedOutput<W: Write> {
+    inner: W,
+    prefixes: BTreeMap<usize, String>,
+    /// The incomplete last line of each task.
+    pending: BTreeMap<usize, Vec<u8>>,
+    raw: bool,
+}
+
+impl<W: Write> PrefixedOutput<W> {
+    pub fn new(inner: W, task_ids: &[usize], raw: bool) -> Self {
+        let width = task_ids
+            .iter()
+            .map(|id| id.to_string().len())
+            .max()
+            .unwrap_or_default();
+        let prefixes = task_ids
+            .iter()
+            .zip(PREFIX_COLORS.iter().cycle())
+            .map(|(id, color)| {
+                let prefix = format!("{:>width$} |", id, width = width);
+                (*id, format!("{} ", style_text(prefix, Some(*color), None)))
+            })
+            .collect();
+
+        PrefixedOutput {
+            inner,
+            prefixes,
+            pending: BTreeMap::new(),
+            raw,
+        }
+    }
+
+    /// Print all complete lines of this chunk of the task's output.
+    pub fn write_task(&mut self, task_id: usize, buf: &[u8]) -> io::Result<()> {
+        let pending = self.pending.entry(task_id).or_default();
+        pending.extend_from_slice(buf);
+
+        // Take all complete lines without the final newline.
+        let complete: Vec<u8> = match pending.iter().rposition(|byte| *byte == b'\n') {
+            Some(position) => pending.drain(..=position).take(position).collect(),
+            None => return Ok(()),
+        };
+        for line in complete.split(|byte| *byte == b'\n') {
+            self.write_line(task_id, line)?;
+        }
+
+        self.inner.flush()
+    }
+
+    /// Print the task's incomplete last line, if there is one.
+    pub fn finish_task(&mut self, task_id: usize) -> io::Result<()> {
+        let line = self.pending.remove(&task_id).unwrap_or_default();
+        if !line.is_empty() {
+            self.write_line(task_id, &line)?;
+        }
+
+        self.inner.flush()
+    }
+
+    fn write_line(&mut self, task_id: usize, line: &[u8]) -> io::Result<()> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let line = match line.iter().rposition(|byte| *byte == b'\r') {
+            Some(position) if !self.raw => &line[position + 1..],
+            _ => line,
+        };
+
+        let prefix = self.prefixes.get(&task_id).map(String::as_str);
+        self.inner
+            .write_all(prefix.unwrap_or_default().as_bytes())?;
+        self.inner.write_all(line)?;
+        self.inner.write_all(b"\n")
+    }
+}
+
+/// The output of a single task, which is written to the shared [PrefixedOutput].
+/// The task's last line is printed, once this is dropped.
+pub struct TaskOutput<W: Write> {
+    output: Arc<Mutex<PrefixedOutput<W>>>,
+    task_id: usize,
+}
+
+impl<W: Write> TaskOutput<W> {
+    pub fn new(output: Arc<Mutex<PrefixedOutput<W>>>, task_id: usize) -> Self {
+        TaskOutput { output, task_id }
+    }
+}
+
+impl<W: Write> Write for TaskOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.lock().unwrap().write_task(self.task_id, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.lock().unwrap().inner.flush()
+    }
+}
+
+impl<W: Write> Drop for TaskOutput<W> {
+    fn drop(&mut self) {
+        if let Ok(mut output) = self.output.lock() {
+            let _ = output.finish_task(self.task_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn render(raw: bool, chunks: &[(usize, &str)]) -> String {
+        let mut output = PrefixedOutput::new(Vec::new(), &[3, 12], raw);
+        for (task_id, chunk) in chunks {
+            output.write_task(*task_id, chunk.as_bytes()).unwrap();
+        }
+        output.finish_task(3).unwrap();
+        output.finish_task(12).unwrap();
+        String::from_utf8(output.inner).unwrap()
+    }
+
+    #[test]
+    fn test_lines_are_not_mixed() {
+        assert_eq!(
+            render(false, &[(3, "fir"), (12, "other\n\n"), (3, "st\nsecond")]),
+            "12 | other\n12 | \n 3 | first\n 3 | second\n"
+        );
+    }
+
+    #[test]
+    fn test_carriage_returns() {
+        let chunks = [(3, "10%\r100%\r\n"), (12, "done\r\n")];
+        assert_eq!(render(false, &chunks), " 3 | 100%\n12 | done\n");
+        assert_eq!(render(true, &chunks), " 3 | 10%\r100%\n12 | done\n");
+    }
+}
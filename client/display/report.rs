@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, Local};
+
+use pueue_lib::network::message::TaskLogMessage;
+use pueue_lib::settings::{Settings, PUEUE_DEFAULT_GROUP};
+use pueue_lib::state::{GroupStatus, State};
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
+
+use super::helper::{format_duration, get_status_text, sort_tasks_by_group};
+use super::locale::Locale;
+use super::log::get_task_output;
+
+/// The stylesheet that's embedded into the report.
+/// The report is a single file without any external resources, so it can be archived or mailed.
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.3em 0.8em; text-align: left; vertical-align: top; }
+th { background: #f3f3f3; }
+pre { background: #f7f7f7; padding: 0.8em; overflow-x: auto; }
+.running { color: #1a7f37; }
+.success { color: #1a7f37; }
+.paused, .locked, .reserved { color: #666; }
+.queued, .stashed { color: #9a6700; }
+.failed { color: #cf222e; }
+";
+
+/// Print a static, self-contained HTML page of the daemon's state. \
+/// Each group gets its own table and the last lines of each task's output are embedded,
+/// so the page can be shared without access to the daemon.
+pub fn print_report(
+    state: &State,
+    mut task_logs: BTreeMap<usize, TaskLogMessage>,
+    group_only: &Option<String>,
+    settings: &Settings,
+    locale: &Locale,
+    lines: usize,
+) {
+    let mut html = String::new();
+    let title = escape_html(locale.get("report.title"));
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p>{}</p>\n",
+        title,
+        STYLE,
+        title,
+        escape_html(&locale.format(
+            "report.generated_at",
+            &[&Local::now().format("%Y-%m-%d %H:%M:%S")]
+        )),
+    );
+
+    let sorted_tasks = sort_tasks_by_group(&state.tasks);
+
+    // The default group is always shown first, just like in `pueue status`.
+    let mut groups: Vec<&String> = state.groups.keys().collect();
+    groups.sort_by_key(|name| name.as_str() != PUEUE_DEFAULT_GROUP);
+    for group in groups {
+        if matches!(group_only, Some(group_only) if group_only != group) {
+            continue;
+        }
+        let empty = BTreeMap::new();
+        let tasks = sorted_tasks.get(group).unwrap_or(&empty);
+        // Don't clutter the report with empty groups, unless a group is explicitly requested.
+        if tasks.is_empty() && group_only.is_none() && group != PUEUE_DEFAULT_GROUP {
+            continue;
+        }
+        write_group(&mut html, state, group, tasks, locale);
+    }
+
+    // Embed the output of all tasks that have been started at some point.
+    html.push_str("<h2>");
+    html.push_str(&escape_html(&locale.format("report.logs", &[&lines])));
+    html.push_str("</h2>\n");
+    for (id, task) in state.tasks.iter() {
+        if !has_output(task) || matches!(group_only, Some(group) if group != &task.group) {
+            continue;
+        }
+        let (stdout, stderr) = match task_logs.remove(id) {
+            Some(message) => get_task_output(
+                &message.task,
+                message.stdout,
+                message.stderr,
+                settings,
+                locale,
+                Some(lines),
+            ),
+            None => continue,
+        };
+        write_log(&mut html, *id, task, &stdout, &stderr, locale);
+    }
+
+    html.push_str("</body>\n</html>");
+    println!("{}", html);
+}
+
+/// Write the headline and the task table of a single group.
+fn write_group(
+    html: &mut String,
+    state: &State,
+    group: &str,
+    tasks: &BTreeMap<usize, Task>,
+    locale: &Locale,
+) {
+    let status = match state.groups.get(group) {
+        Some(GroupStatus::Paused) => locale.get("group.paused"),
+        _ => locale.get("group.running"),
+    };
+    let parallel = state
+        .settings
+        .daemon
+        .groups
+        .get(group)
+        .cloned()
+        .unwrap_or(1);
+    let headline = locale.format(
+        "group.headline",
+        &[&locale.format("group.name", &[&group]), &parallel, &status],
+    );
+    let _ = writeln!(html, "<h2>{}</h2>", escape_html(&headline));
+
+    if tasks.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p>{}</p>",
+            escape_html(&locale.format("status.empty_group", &[&group]))
+        );
+        return;
+    }
+
+    html.push_str("<table>\n<tr>");
+    for key in &[
+        "status.header.id",
+        "status.header.status",
+        "status.header.label",
+        "status.header.command",
+        "status.header.path",
+        "status.header.start",
+        "status.header.end",
+        "report.duration",
+        "report.log",
+    ] {
+        let _ = write!(html, "<th>{}</th>", escape_html(locale.get(key)));
+    }
+    html.push_str("</tr>\n");
+
+    for (id, task) in tasks {
+        let log_link = if has_output(task) {
+            format!(
+                "<a href=\"#log-{}\">{}</a>",
+                id,
+                escape_html(locale.get("report.show_log"))
+            )
+        } else {
+            String::new()
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            id,
+            status_class(&task.status),
+            escape_html(&get_status_text(&task.status, locale)),
+            escape_html(task.label.as_deref().unwrap_or_default()),
+            escape_html(&task.command),
+            escape_html(&task.path),
+            format_time(task.start),
+            format_time(task.end),
+            format_duration(task.start, task.end),
+            log_link,
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+/// Write the collapsible output block of a single task.
+/// The task table links to these blocks via their `log-{id}` anchor.
+fn write_log(
+    html: &mut String,
+    id: usize,
+    task: &Task,
+    stdout: &str,
+    stderr: &str,
+    locale: &Locale,
+) {
+    let summary = format!(
+        "{}{}",
+        locale.format("log.task", &[&id]),
+        get_status_text(&task.status, locale)
+    );
+    let _ = writeln!(
+        html,
+        "<details id=\"log-{}\">\n<summary class=\"{}\">{}</summary>\n<p><code>{}</code></p>",
+        id,
+        status_class(&task.status),
+        escape_html(&summary),
+        escape_html(&task.command),
+    );
+    if !stdout.is_empty() {
+        let _ = writeln!(
+            html,
+            "<h4>{}</h4>\n<pre>{}</pre>",
+            escape_html(locale.get("log.stdout")),
+            escape_html(stdout)
+        );
+    }
+    if !stderr.is_empty() {
+        let _ = writeln!(
+            html,
+            "<h4>{}</h4>\n<pre>{}</pre>",
+            escape_html(locale.get("log.stderr")),
+            escape_html(stderr)
+        );
+    }
+    html.push_str("</details>\n");
+}
+
+/// Only tasks that have been started at some point can have any output.
+fn has_output(task: &Task) -> bool {
+    matches!(
+        task.status,
+        TaskStatus::Running | TaskStatus::Paused | TaskStatus::Done(_)
+    )
+}
+
+/// The css class that's used to color a task's status.
+fn status_class(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Queued => "queued",
+        TaskStatus::Stashed { .. } => "stashed",
+        TaskStatus::Running => "running",
+        TaskStatus::Paused => "paused",
+        TaskStatus::Locked => "locked",
+        TaskStatus::Reserved => "reserved",
+        TaskStatus::Done(TaskResult::Success) => "success",
+        TaskStatus::Done(_) => "failed",
+    }
+}
+
+/// Format an optional timestamp for the report.
+fn format_time(time: Option<DateTime<Local>>) -> String {
+    time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// Escape all characters that have a special meaning in HTML.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("echo \"<b>\" && echo 'x'"),
+            "echo &quot;&lt;b&gt;&quot; &amp;&amp; echo &#39;x&#39;"
+        );
+    }
+}
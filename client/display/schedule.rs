@@ -0,0 +1,48 @@
+use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
+use comfy_table::*;
+
+use pueue_lib::network::message::{ScheduledEvent, ScheduledEventKind};
+use pueue_lib::settings::Settings;
+
+use super::locale::Locale;
+
+/// Print all future events in the order in which they're going to happen.
+pub fn print_schedule(events: &[ScheduledEvent], json: bool, settings: &Settings, locale: &Locale) {
+    if json {
+        println!("{}", serde_json::to_string(events).unwrap());
+        return;
+    }
+
+    if events.is_empty() {
+        println!("{}", locale.get("schedule.empty"));
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(UTF8_HORIZONTAL_BORDERS_ONLY)
+        .set_header(vec![
+            Cell::new(locale.get("schedule.header.time")),
+            Cell::new(locale.get("schedule.header.event")),
+            Cell::new(locale.get("schedule.header.task")),
+            Cell::new(locale.get("schedule.header.group")),
+        ]);
+
+    for event in events {
+        let kind = match event.kind {
+            ScheduledEventKind::Enqueue => locale.get("schedule.enqueue"),
+            ScheduledEventKind::Expire => locale.get("schedule.expire"),
+            ScheduledEventKind::Timeout => locale.get("schedule.timeout"),
+        };
+        let task_id = event.task_id.map(|id| id.to_string()).unwrap_or_default();
+        table.add_row(vec![
+            Cell::new(event.time.format(&settings.client.status_datetime_format)),
+            Cell::new(kind),
+            Cell::new(task_id),
+            Cell::new(&event.group),
+        ]);
+    }
+
+    println!("{}", table);
+}
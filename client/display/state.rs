@@ -5,22 +5,63 @@ use chrono::{Duration, Local};
 use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
 use comfy_table::*;
 
+use pueue_lib::path::normalize_separators;
 use pueue_lib::settings::{Settings, PUEUE_DEFAULT_GROUP};
 use pueue_lib::state::State;
 use pueue_lib::task::{Task, TaskResult, TaskStatus};
 
-use super::{colors::Colors, helper::*};
-use crate::cli::SubCommand;
+use super::{colors::Colors, helper::*, locale::Locale};
+use crate::cli::{StatusFormat, SubCommand};
 
 /// Print the current state of the daemon in a nicely formatted table.
-pub fn print_state(state: State, cli_command: &SubCommand, colors: &Colors, settings: &Settings) {
-    let (json, group_only) = match cli_command {
-        SubCommand::Status { json, group } => (*json, group.clone()),
-        _ => panic!(
-            "Got wrong Subcommand {:?} in print_state. This shouldn't happen",
-            cli_command
-        ),
-    };
+pub fn print_state(
+    mut state: State,
+    cli_command: &SubCommand,
+    colors: &Colors,
+    settings: &Settings,
+    locale: &Locale,
+) {
+    let (query, json, group_only, format, mine, tag, collapse_arrays, hide_acknowledged) =
+        match cli_command {
+            SubCommand::Status {
+                query,
+                json,
+                group,
+                format,
+                mine,
+                all,
+                tag,
+                collapse_arrays,
+                hide_acknowledged,
+            } => (
+                query,
+                *json,
+                status_group_filter(group, *all, settings),
+                *format,
+                *mine,
+                tag,
+                *collapse_arrays,
+                *hide_acknowledged,
+            ),
+            _ => panic!(
+                "Got wrong Subcommand {:?} in print_state. This shouldn't happen",
+                cli_command
+            ),
+        };
+
+    // Only keep tasks that have been submitted by this client.
+    if mine {
+        retain_own_tasks(&mut state);
+    }
+    if let Some(tag) = tag {
+        state.tasks.retain(|_, task| task.has_tag(tag));
+    }
+    if hide_acknowledged {
+        state.tasks.retain(|_, task| !task.acknowledged);
+    }
+    if let Some(query) = query {
+        state.tasks.retain(|_, task| query.matches(task));
+    }
 
     // If the json flag is specified, print the state as json and exit.
     if json {
@@ -28,23 +69,45 @@ pub fn print_state(state: State, cli_command: &SubCommand, colors: &Colors, sett
         return;
     }
 
+    if format == StatusFormat::Tsv {
+        print_tsv(&state, &group_only);
+        return;
+    }
+
     // Sort all tasks by their respective group;
     let sorted_tasks = sort_tasks_by_group(&state.tasks);
 
     if let Some(group) = group_only {
-        print_single_group(state, settings, colors, sorted_tasks, group);
+        print_single_group(
+            state,
+            settings,
+            colors,
+            locale,
+            sorted_tasks,
+            group,
+            collapse_arrays,
+        );
         return;
     }
 
-    print_all_groups(state, settings, colors, sorted_tasks);
+    print_all_groups(
+        state,
+        settings,
+        colors,
+        locale,
+        sorted_tasks,
+        collapse_arrays,
+    );
 }
 
 fn print_single_group(
     state: State,
     settings: &Settings,
     colors: &Colors,
+    locale: &Locale,
     mut sorted_tasks: BTreeMap<String, BTreeMap<usize, Task>>,
     group: String,
+    collapse_arrays: bool,
 ) {
     // Only a single group is requested. Print that group and return.
     let tasks = sorted_tasks.entry(group.clone()).or_default();
@@ -52,26 +115,27 @@ fn print_single_group(
         &group,
         state.groups.get(&group).unwrap(),
         *state.settings.daemon.groups.get(&group).unwrap(),
+        state.pause_reasons.get(&group),
         colors,
+        locale,
     );
     println!("{}", headline);
 
     // Show a message if the requested group doesn't have any tasks.
     if tasks.is_empty() {
-        println!(
-            "Task list is empty. Add tasks with `pueue add -g {} -- [cmd]`",
-            group
-        );
+        println!("{}", locale.format("status.empty_group", &[&group]));
         return;
     }
-    print_table(tasks, colors, settings);
+    print_table(tasks, colors, settings, locale, collapse_arrays);
 }
 
 fn print_all_groups(
     state: State,
     settings: &Settings,
     colors: &Colors,
+    locale: &Locale,
     sorted_tasks: BTreeMap<String, BTreeMap<usize, Task>>,
+    collapse_arrays: bool,
 ) {
     // Early exit and hint if there are no tasks in the queue
     // Print the state of the default group anyway, since this is information one wants to
@@ -86,15 +150,17 @@ fn print_all_groups(
                 .groups
                 .get(PUEUE_DEFAULT_GROUP)
                 .unwrap(),
+            state.pause_reasons.get(PUEUE_DEFAULT_GROUP),
             colors,
+            locale,
         );
         println!("{}\n", headline);
-        println!("Task list is empty. Add tasks with `pueue add -- [cmd]`");
+        println!("{}", locale.get("status.empty"));
         return;
     }
 
     // Always print the default queue at the very top, if no specific group is requested.
-    if sorted_tasks.get(PUEUE_DEFAULT_GROUP).is_some() {
+    if sorted_tasks.contains_key(PUEUE_DEFAULT_GROUP) {
         let tasks = sorted_tasks.get(PUEUE_DEFAULT_GROUP).unwrap();
         let headline = get_group_headline(
             PUEUE_DEFAULT_GROUP,
@@ -105,10 +171,12 @@ fn print_all_groups(
                 .groups
                 .get(PUEUE_DEFAULT_GROUP)
                 .unwrap(),
+            state.pause_reasons.get(PUEUE_DEFAULT_GROUP),
             colors,
+            locale,
         );
         println!("{}", headline);
-        print_table(tasks, colors, settings);
+        print_table(tasks, colors, settings, locale, collapse_arrays);
 
         // Add a newline if there are further groups to be printed
         if sorted_tasks.len() > 1 {
@@ -129,10 +197,12 @@ fn print_all_groups(
             group,
             state.groups.get(group).unwrap(),
             *state.settings.daemon.groups.get(group).unwrap(),
+            state.pause_reasons.get(group),
             colors,
+            locale,
         );
         println!("{}", headline);
-        print_table(tasks, colors, settings);
+        print_table(tasks, colors, settings, locale, collapse_arrays);
 
         // Add a newline between groups
         if sorted_iter.peek().is_some() {
@@ -142,27 +212,53 @@ fn print_all_groups(
 }
 
 /// Print some tasks into a nicely formatted table
-fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settings) {
-    let (has_delayed_tasks, has_dependencies, has_labels) = has_special_columns(tasks);
+fn print_table(
+    tasks: &BTreeMap<usize, Task>,
+    colors: &Colors,
+    settings: &Settings,
+    locale: &Locale,
+    collapse_arrays: bool,
+) {
+    let (has_delayed_tasks, has_dependencies, has_labels, has_slots) = has_special_columns(tasks);
+    let special_columns = [has_slots, has_delayed_tasks, has_dependencies, has_labels]
+        .iter()
+        .filter(|column| **column)
+        .count();
+
+    // Collect the tasks of each array, if they should be shown as a single row.
+    let mut arrays: BTreeMap<usize, Vec<&Task>> = BTreeMap::new();
+    if collapse_arrays {
+        for task in tasks.values() {
+            if let Some(array) = task.array {
+                arrays.entry(array).or_default().push(task);
+            }
+        }
+    }
 
     // Create table header row
-    let mut headers = vec![Cell::new("Id"), Cell::new("Status")];
+    let mut headers = vec![
+        Cell::new(locale.get("status.header.id")),
+        Cell::new(locale.get("status.header.status")),
+    ];
 
+    if has_slots {
+        headers.push(Cell::new(locale.get("status.header.slot")));
+    }
     if has_delayed_tasks {
-        headers.push(Cell::new("Enqueue At"));
+        headers.push(Cell::new(locale.get("status.header.enqueue_at")));
     }
     if has_dependencies {
-        headers.push(Cell::new("Deps"));
+        headers.push(Cell::new(locale.get("status.header.deps")));
     }
     if has_labels {
-        headers.push(Cell::new("Label"));
+        headers.push(Cell::new(locale.get("status.header.label")));
     }
 
     headers.append(&mut vec![
-        Cell::new("Command"),
-        Cell::new("Path"),
-        Cell::new("Start"),
-        Cell::new("End"),
+        Cell::new(locale.get("status.header.command")),
+        Cell::new(locale.get("status.header.path")),
+        Cell::new(locale.get("status.header.start")),
+        Cell::new(locale.get("status.header.end")),
     ]);
 
     // Initialize comfy table.
@@ -174,27 +270,46 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
 
     // Add rows one by one.
     for (id, task) in tasks {
+        // Arrays are shown in place of their first task.
+        if let Some(array_tasks) = task.array.and_then(|array| arrays.get(&array)) {
+            if array_tasks[0].id == *id {
+                let row = array_row(array_tasks, special_columns, colors, settings, locale);
+                table.add_row(row);
+            }
+            continue;
+        }
+
+        // Acknowledged failures are known, so they shouldn't draw any attention.
+        let dim = |cell: Cell| {
+            if task.acknowledged {
+                cell.add_attribute(Attribute::Dim)
+            } else {
+                cell
+            }
+        };
+
         let mut row = Row::new();
         if let Some(height) = settings.client.max_status_lines {
             row.max_height(height);
         }
-        row.add_cell(Cell::new(&id.to_string()));
+        row.add_cell(dim(Cell::new(id.to_string())));
 
         // Determine the human readable task status representation and the respective color.
-        let status_string = task.status.to_string();
-        let (status_text, color) = match &task.status {
-            TaskStatus::Running => (status_string, colors.green()),
-            TaskStatus::Paused | TaskStatus::Locked => (status_string, colors.white()),
-            TaskStatus::Done(result) => match result {
-                TaskResult::Success => (TaskResult::Success.to_string(), colors.green()),
-                TaskResult::DependencyFailed => ("Dependency failed".to_string(), colors.red()),
-                TaskResult::FailedToSpawn(_) => ("Failed to spawn".to_string(), colors.red()),
-                TaskResult::Failed(code) => (format!("Failed ({})", code), colors.red()),
-                _ => (result.to_string(), colors.red()),
-            },
-            _ => (status_string, colors.yellow()),
-        };
-        row.add_cell(Cell::new(status_text).fg(color));
+        let mut status_text = get_status_text(&task.status, locale);
+        // Output on stderr can't be spotted in the logs of tasks, whose streams are merged.
+        if task.stderr_to_stdout && task.wrote_stderr {
+            status_text = locale.format("status.wrote_stderr", &[&status_text]);
+        }
+        let color = get_status_color(&task.status, colors);
+        row.add_cell(dim(Cell::new(status_text).fg(color)));
+
+        if has_slots {
+            // Finished tasks don't occupy their slot anymore.
+            match task.slot {
+                Some(slot) if task.is_running() => row.add_cell(dim(Cell::new(slot.to_string()))),
+                _ => row.add_cell(Cell::new("")),
+            };
+        }
 
         if has_delayed_tasks {
             if let TaskStatus::Stashed {
@@ -209,7 +324,7 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
                 } else {
                     enqueue_at.format(&settings.client.status_datetime_format)
                 };
-                row.add_cell(Cell::new(formatted_enqueue_at));
+                row.add_cell(dim(Cell::new(formatted_enqueue_at)));
             } else {
                 row.add_cell(Cell::new(""));
             }
@@ -222,12 +337,12 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
                 .map(|id| id.to_string())
                 .collect::<Vec<String>>()
                 .join(", ");
-            row.add_cell(Cell::new(text));
+            row.add_cell(dim(Cell::new(text)));
         }
 
         if has_labels {
             if let Some(label) = &task.label {
-                row.add_cell(label.into());
+                row.add_cell(dim(label.into()));
             } else {
                 row.add_cell(Cell::new(""));
             }
@@ -235,16 +350,16 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
 
         // Add command and path.
         if settings.client.show_expanded_aliases {
-            row.add_cell(Cell::new(&task.command));
+            row.add_cell(dim(Cell::new(&task.command)));
         } else {
-            row.add_cell(Cell::new(&task.original_command));
+            row.add_cell(dim(Cell::new(&task.original_command)));
         }
-        row.add_cell(Cell::new(&task.path));
+        row.add_cell(dim(Cell::new(normalize_separators(&task.path))));
 
         // Add start and end info
         let (start, end) = formatted_start_end(task, settings);
-        row.add_cell(Cell::new(start));
-        row.add_cell(Cell::new(end));
+        row.add_cell(dim(Cell::new(start)));
+        row.add_cell(dim(Cell::new(end)));
 
         table.add_row(row);
     }
@@ -253,6 +368,160 @@ fn print_table(tasks: &BTreeMap<usize, Task>, colors: &Colors, settings: &Settin
     println!("{}", table);
 }
 
+/// Create a single summary row for all tasks of an array.
+///
+/// The status shows how many tasks are in each state, while start and end span all tasks.
+/// The special columns of the table are left empty.
+fn array_row(
+    tasks: &[&Task],
+    special_columns: usize,
+    colors: &Colors,
+    settings: &Settings,
+    locale: &Locale,
+) -> Row {
+    let first = tasks[0];
+    let last = tasks[tasks.len() - 1];
+
+    let mut row = Row::new();
+    if let Some(height) = settings.client.max_status_lines {
+        row.max_height(height);
+    }
+    row.add_cell(Cell::new(format!("{}-{}", first.id, last.id)));
+
+    // Count the tasks per status text, in the order of their first appearance.
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for task in tasks {
+        let status_text = get_status_text(&task.status, locale);
+        match counts.iter_mut().find(|(text, _)| *text == status_text) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((status_text, 1)),
+        }
+    }
+    let status_text = counts
+        .iter()
+        .map(|(text, count)| format!("{} {}", count, text))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    // Failures are the most important thing to notice, followed by progress.
+    let color = if tasks.iter().any(|task| task.failed()) {
+        colors.red()
+    } else if tasks.iter().any(|task| task.is_running()) {
+        colors.green()
+    } else if tasks.iter().any(|task| task.is_queued()) {
+        colors.yellow()
+    } else {
+        get_status_color(&last.status, colors)
+    };
+    row.add_cell(Cell::new(status_text).fg(color));
+
+    for _ in 0..special_columns {
+        row.add_cell(Cell::new(""));
+    }
+
+    let command = if settings.client.show_expanded_aliases {
+        &first.command
+    } else {
+        &first.original_command
+    };
+    let command = locale.format("status.array", &[command, &tasks.len()]);
+    row.add_cell(Cell::new(command));
+    row.add_cell(Cell::new(normalize_separators(&first.path)));
+
+    // The array starts with its first task and ends once all of its tasks are done.
+    let mut span = first.clone();
+    span.start = tasks.iter().filter_map(|task| task.start).min();
+    span.end = if tasks.iter().all(|task| task.is_done()) {
+        tasks.iter().filter_map(|task| task.end).max()
+    } else {
+        None
+    };
+    let (start, end) = formatted_start_end(&span, settings);
+    row.add_cell(Cell::new(start));
+    row.add_cell(Cell::new(end));
+
+    row
+}
+
+/// Print all tasks as tab-separated values.
+///
+/// The output is meant to be parsed by other tools, which is why it's never styled or localized.
+/// The first line is a header and the column order is stable.
+fn print_tsv(state: &State, group_only: &Option<String>) {
+    println!(
+        "id\tgroup\tstatus\tresult\tenqueue_at\tdependencies\tlabel\tcommand\tpath\tstart\tend\tslot"
+    );
+
+    for task in state.tasks.values() {
+        if let Some(group) = group_only {
+            if &task.group != group {
+                continue;
+            }
+        }
+
+        let (status, result, enqueue_at) = match &task.status {
+            TaskStatus::Stashed { enqueue_at } => (
+                "Stashed",
+                String::new(),
+                enqueue_at.map(|time| time.to_rfc3339()).unwrap_or_default(),
+            ),
+            TaskStatus::Done(result) => {
+                let result = match result {
+                    TaskResult::Failed(code) => format!("Failed({})", code),
+                    _ => result.to_string(),
+                };
+                ("Done", result, String::new())
+            }
+            TaskStatus::Queued => ("Queued", String::new(), String::new()),
+            TaskStatus::Running => ("Running", String::new(), String::new()),
+            TaskStatus::Paused => ("Paused", String::new(), String::new()),
+            TaskStatus::Locked => ("Locked", String::new(), String::new()),
+            TaskStatus::Reserved => ("Reserved", String::new(), String::new()),
+        };
+
+        let dependencies = task
+            .dependencies
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let fields = [
+            task.id.to_string(),
+            task.group.clone(),
+            status.to_string(),
+            result,
+            enqueue_at,
+            dependencies,
+            task.label.clone().unwrap_or_default(),
+            task.original_command.clone(),
+            task.path.clone(),
+            task.start.map(|time| time.to_rfc3339()).unwrap_or_default(),
+            task.end.map(|time| time.to_rfc3339()).unwrap_or_default(),
+            task.slot
+                .filter(|_| task.is_running())
+                .map(|slot| slot.to_string())
+                .unwrap_or_default(),
+        ];
+
+        let row = fields
+            .iter()
+            .map(|field| escape_tsv_field(field))
+            .collect::<Vec<String>>()
+            .join("\t");
+        println!("{}", row);
+    }
+}
+
+/// Escape backslashes, tabs and newlines, so every task stays on a single row.
+fn escape_tsv_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
 /// Returns the formatted `start` and `end` text for a given task.
 ///
 /// 1. If the start || end is today, skip the date.
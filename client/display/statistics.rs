@@ -0,0 +1,48 @@
+use comfy_table::presets::UTF8_HORIZONTAL_BORDERS_ONLY;
+use comfy_table::*;
+
+use pueue_lib::state::State;
+
+use super::helper::format_seconds;
+use super::locale::Locale;
+
+/// Print the long-lived statistics of all groups.
+pub fn print_statistics(state: &State, json: bool, locale: &Locale) {
+    if json {
+        println!("{}", serde_json::to_string(&state.statistics).unwrap());
+        return;
+    }
+
+    if state.statistics.is_empty() {
+        println!("{}", locale.get("stats.empty"));
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .load_preset(UTF8_HORIZONTAL_BORDERS_ONLY)
+        .set_header(vec![
+            Cell::new(locale.get("stats.header.group")),
+            Cell::new(locale.get("stats.header.tasks")),
+            Cell::new(locale.get("stats.header.failures")),
+            Cell::new(locale.get("stats.header.runtime")),
+            Cell::new(locale.get("stats.header.since")),
+        ]);
+
+    for (group, statistics) in &state.statistics {
+        let since = statistics
+            .since
+            .map(|since| since.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        table.add_row(vec![
+            Cell::new(group),
+            Cell::new(statistics.tasks),
+            Cell::new(statistics.failures),
+            Cell::new(format_seconds((statistics.runtime_millis / 1000) as i64)),
+            Cell::new(since),
+        ]);
+    }
+
+    println!("{}", table);
+}
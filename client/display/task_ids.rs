@@ -0,0 +1,25 @@
+use serde_derive::Serialize;
+
+/// The json output of `pueue add --output json`.
+#[derive(Serialize)]
+struct AddedTask {
+    task_id: usize,
+}
+
+/// The json output of commands, that act on several tasks, such as `pueue enqueue`.
+#[derive(Serialize)]
+struct AffectedTasks<'a> {
+    task_ids: &'a [usize],
+    failed: &'a [usize],
+}
+
+/// Print the id of a newly added task as json.
+pub fn print_added_task_json(task_id: usize) {
+    println!("{}", serde_json::to_string(&AddedTask { task_id }).unwrap());
+}
+
+/// Print the ids of the tasks, for which a command succeeded or failed, as json.
+pub fn print_task_ids_json(task_ids: &[usize], failed: &[usize]) {
+    let affected = AffectedTasks { task_ids, failed };
+    println!("{}", serde_json::to_string(&affected).unwrap());
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use pueue_lib::network::message::{FailureKind, FailureMessage, TaskActionResponseMessage};
+
+/// All exit codes the client can exit with. \
+/// These codes are stable and can be used by scripts to branch on the outcome of a command,
+/// without having to parse any of the human readable output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Everything went as expected.
+    Success = 0,
+    /// The daemon rejected the request or some generic error occurred.
+    Failure = 1,
+    /// The client couldn't connect to the daemon.
+    Connection = 2,
+    /// At least one of the tasks the command was waiting for or following failed.
+    TaskFailed = 3,
+    /// The daemon refused the client's secret during the handshake.
+    Authentication = 4,
+    /// The requested task doesn't exist.
+    TaskNotFound = 5,
+    /// The command only succeeded for some of the given tasks.
+    PartialSuccess = 6,
+}
+
+/// The exit codes as they're listed in the client's help text.
+pub const EXIT_CODES_HELP: &str = "EXIT CODES:
+    0  Success
+    1  The command failed, e.g. the daemon rejected it
+    2  Couldn't connect to the daemon
+    3  A task that has been waited for or followed failed
+    4  Couldn't authenticate with the daemon
+    5  The requested task doesn't exist
+    6  The command only succeeded for some of the given tasks";
+
+impl ExitCode {
+    /// Determine the exit code for a failure message that has been sent by the daemon.
+    pub fn from_failure(failure: &FailureMessage) -> ExitCode {
+        match failure.kind {
+            FailureKind::TaskNotFound { .. } => ExitCode::TaskNotFound,
+            _ => ExitCode::Failure,
+        }
+    }
+
+    /// Determine the exit code for the outcome of an action on several tasks.
+    /// If none of the tasks exist, the more specific [ExitCode::TaskNotFound] is used.
+    pub fn from_task_action(response: &TaskActionResponseMessage) -> ExitCode {
+        if response.failed.is_empty() {
+            ExitCode::Success
+        } else if !response.succeeded.is_empty() {
+            ExitCode::PartialSuccess
+        } else if response
+            .failed
+            .values()
+            .all(|kind| matches!(kind, FailureKind::TaskNotFound { .. }))
+        {
+            ExitCode::TaskNotFound
+        } else {
+            ExitCode::Failure
+        }
+    }
+
+    /// Exit the client with this exit code.
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+/// The display implementation is used, when the exit code is attached as context to an error.
+impl fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            ExitCode::Success => "Success",
+            ExitCode::Failure => "The command failed",
+            ExitCode::Connection => "Couldn't connect to the daemon",
+            ExitCode::TaskFailed => "A task failed",
+            ExitCode::Authentication => "Couldn't authenticate with the daemon",
+            ExitCode::TaskNotFound => "Couldn't find the task",
+            ExitCode::PartialSuccess => "The command failed for some tasks",
+        };
+        write!(f, "{}", text)
+    }
+}
@@ -10,9 +10,16 @@ pub mod cli;
 pub mod client;
 pub mod commands;
 pub mod display;
+pub mod exit_code;
+pub mod query;
 
 use crate::cli::{CliArguments, Shell, SubCommand};
 use crate::client::Client;
+#[cfg(not(target_os = "windows"))]
+use crate::commands::broker::broker;
+use crate::commands::doctor::doctor;
+use crate::commands::self_update::self_update;
+use crate::exit_code::ExitCode;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
@@ -48,9 +55,54 @@ async fn main() -> Result<()> {
     };
     SimpleLogger::init(level, Config::default()).unwrap();
 
+    // Any error is mapped to one of our stable exit codes.
+    // Errors that aren't explicitly tagged with an exit code are generic failures.
+    let quiet = opt.quiet;
+    if let Err(error) = run(opt).await {
+        if !quiet {
+            eprintln!("Error: {:?}", error);
+        }
+        let exit_code = error
+            .downcast_ref::<ExitCode>()
+            .copied()
+            .unwrap_or(ExitCode::Failure);
+        exit_code.exit();
+    }
+
+    Ok(())
+}
+
+/// Read the configuration, connect to the daemon and execute the given subcommand.
+async fn run(opt: CliArguments) -> Result<()> {
+    // The doctor has to work, even if the configuration is broken or the daemon isn't reachable.
+    if let SubCommand::Doctor = opt.cmd {
+        let exit_code = doctor(&opt.config).await;
+        if exit_code != ExitCode::Success {
+            exit_code.exit();
+        }
+        return Ok(());
+    }
+
+    // Updating doesn't need a running daemon.
+    if let SubCommand::SelfUpdate {
+        check,
+        restart_daemon,
+    } = opt.cmd
+    {
+        return self_update(&opt.config, check, restart_daemon, opt.quiet).await;
+    }
+
     // Try to read settings from the configuration file.
     let settings = Settings::read_with_defaults(true, &opt.config)?;
 
+    // The broker connects to the daemon on its own, since it would connect to itself otherwise.
+    if let SubCommand::Broker = opt.cmd {
+        #[cfg(not(target_os = "windows"))]
+        return broker(settings).await;
+        #[cfg(target_os = "windows")]
+        anyhow::bail!("The broker is only supported on unix systems");
+    }
+
     // Create client to talk with the daemon and connect.
     let mut client = Client::new(settings, opt).await?;
     client.start().await?;
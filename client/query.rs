@@ -0,0 +1,433 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
+
+/// A small query language to select tasks, e.g. `status=failed AND (group=build OR label~=deploy)`.
+///
+/// A query consists of conditions in the form of `<field><operator><value>`,
+/// which can be combined with `AND`, `OR`, `NOT` and parentheses.
+/// Values that contain spaces or special characters have to be put into double quotes.
+///
+/// Fields: `id`, `status`, `group`, `label`, `command`, `path` and `tag`. \
+/// Operators: `=`, `!=`, `~=` (contains) and `<`, `<=`, `>`, `>=` (only for `id`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query(Expression);
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query> {
+        let tokens = tokenize(query)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expression = parser.parse_or()?;
+        if let Some(token) = parser.next() {
+            bail!("Unexpected {} in query", token);
+        }
+
+        Ok(Query(expression))
+    }
+
+    /// Check whether a task matches this query.
+    pub fn matches(&self, task: &Task) -> bool {
+        self.0.matches(task)
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        Query::parse(query)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expression {
+    Or(Vec<Expression>),
+    And(Vec<Expression>),
+    Not(Box<Expression>),
+    Condition(Condition),
+}
+
+impl Expression {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Expression::Or(expressions) => expressions.iter().any(|expr| expr.matches(task)),
+            Expression::And(expressions) => expressions.iter().all(|expr| expr.matches(task)),
+            Expression::Not(expression) => !expression.matches(task),
+            Expression::Condition(condition) => condition.matches(task),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Id,
+    Status,
+    Group,
+    Label,
+    Command,
+    Path,
+    Tag,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Contains,
+    Lower,
+    LowerOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Condition {
+    field: Field,
+    operator: Operator,
+    value: String,
+}
+
+impl Condition {
+    fn new(field: &str, operator: Operator, value: String) -> Result<Condition> {
+        let field = match field.to_lowercase().as_str() {
+            "id" => Field::Id,
+            "status" => Field::Status,
+            "group" => Field::Group,
+            "label" => Field::Label,
+            "command" => Field::Command,
+            "path" => Field::Path,
+            "tag" => Field::Tag,
+            _ => bail!(
+                "Unknown field \"{}\". Use id, status, group, label, command, path or tag.",
+                field
+            ),
+        };
+
+        let ordering = matches!(
+            operator,
+            Operator::Lower | Operator::LowerOrEqual | Operator::Greater | Operator::GreaterOrEqual
+        );
+        if field == Field::Id {
+            if value.parse::<usize>().is_err() {
+                bail!("The id has to be a number, got \"{}\"", value);
+            }
+            if operator == Operator::Contains {
+                bail!("The ~= operator can't be used with the id");
+            }
+        } else if ordering {
+            bail!("Comparisons with <, <=, > and >= are only possible for the id");
+        }
+
+        Ok(Condition {
+            field,
+            operator,
+            value,
+        })
+    }
+
+    fn matches(&self, task: &Task) -> bool {
+        let values = match self.field {
+            Field::Id => return self.matches_id(task.id),
+            Field::Status => {
+                let value = self.value.to_lowercase();
+                let names = status_names(&task.status);
+                return match self.operator {
+                    Operator::Equal => names.contains(&value),
+                    Operator::NotEqual => !names.contains(&value),
+                    _ => names.iter().any(|name| name.contains(&value)),
+                };
+            }
+            Field::Group => vec![task.group.as_str()],
+            Field::Label => vec![task.label.as_deref().unwrap_or_default()],
+            Field::Command => vec![task.original_command.as_str()],
+            Field::Path => vec![task.path.as_str()],
+            Field::Tag => task.tags.iter().map(String::as_str).collect(),
+        };
+
+        match self.operator {
+            Operator::Equal => values.contains(&self.value.as_str()),
+            Operator::NotEqual => !values.contains(&self.value.as_str()),
+            _ => values.iter().any(|value| value.contains(&self.value)),
+        }
+    }
+
+    fn matches_id(&self, id: usize) -> bool {
+        // The value has been validated, while parsing the query.
+        let value: usize = self.value.parse().unwrap_or_default();
+        match self.operator {
+            Operator::Equal => id == value,
+            Operator::NotEqual => id != value,
+            Operator::Lower => id < value,
+            Operator::LowerOrEqual => id <= value,
+            Operator::Greater => id > value,
+            Operator::GreaterOrEqual => id >= value,
+            Operator::Contains => false,
+        }
+    }
+}
+
+/// All names a status can be matched by.
+/// Finished tasks can be matched by `done`, their result and `failed`, if they didn't succeed.
+fn status_names(status: &TaskStatus) -> Vec<String> {
+    let name = match status {
+        TaskStatus::Queued => "queued",
+        TaskStatus::Stashed { .. } => "stashed",
+        TaskStatus::Running => "running",
+        TaskStatus::Paused => "paused",
+        TaskStatus::Locked => "locked",
+        TaskStatus::Reserved => "reserved",
+        TaskStatus::Done(result) => {
+            let mut names = vec!["done".to_string(), result.to_string().to_lowercase()];
+            if !matches!(result, TaskResult::Success) {
+                names.push("failed".into());
+            }
+            return names;
+        }
+    };
+
+    vec![name.to_string()]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LeftParenthesis,
+    RightParenthesis,
+    Operator(Operator),
+    /// A word and whether it has been quoted.
+    Word(String, bool),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::LeftParenthesis => write!(f, "\"(\""),
+            Token::RightParenthesis => write!(f, "\")\""),
+            Token::Operator(operator) => write!(f, "operator {:?}", operator),
+            Token::Word(word, _) => write!(f, "\"{}\"", word),
+        }
+    }
+}
+
+/// Characters that can't be part of an unquoted word.
+const SPECIAL_CHARACTERS: &[char] = &['(', ')', '=', '!', '~', '<', '>', '"'];
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        let token = match char {
+            char if char.is_whitespace() => continue,
+            '(' => Token::LeftParenthesis,
+            ')' => Token::RightParenthesis,
+            '=' => Token::Operator(Operator::Equal),
+            '!' | '~' | '<' | '>' => {
+                let equal = chars.next_if_eq(&'=').is_some();
+                let operator = match (char, equal) {
+                    ('!', true) => Operator::NotEqual,
+                    ('~', true) => Operator::Contains,
+                    ('<', false) => Operator::Lower,
+                    ('<', true) => Operator::LowerOrEqual,
+                    ('>', false) => Operator::Greater,
+                    ('>', true) => Operator::GreaterOrEqual,
+                    _ => bail!("Expected \"=\" after \"{}\" in query", char),
+                };
+                Token::Operator(operator)
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            if let Some(escaped) = chars.next() {
+                                word.push(escaped);
+                            }
+                        }
+                        Some(char) => word.push(char),
+                        None => bail!("Missing closing quote in query"),
+                    }
+                }
+                Token::Word(word, true)
+            }
+            _ => {
+                let mut word = char.to_string();
+                while let Some(char) =
+                    chars.next_if(|c| !c.is_whitespace() && !SPECIAL_CHARACTERS.contains(c))
+                {
+                    word.push(char);
+                }
+                Token::Word(word, false)
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Consume the next token, if it's the given keyword.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word, false)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expression> {
+        let mut expressions = vec![self.parse_and()?];
+        while self.keyword("or") {
+            expressions.push(self.parse_and()?);
+        }
+
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::Or(expressions),
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expression> {
+        let mut expressions = vec![self.parse_unary()?];
+        while self.keyword("and") {
+            expressions.push(self.parse_unary()?);
+        }
+
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::And(expressions),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression> {
+        if self.keyword("not") {
+            return Ok(Expression::Not(Box::new(self.parse_unary()?)));
+        }
+
+        match self.next() {
+            Some(Token::LeftParenthesis) => {
+                let expression = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RightParenthesis) => Ok(expression),
+                    Some(token) => bail!("Expected \")\", got {}", token),
+                    None => bail!("Missing closing parenthesis in query"),
+                }
+            }
+            Some(Token::Word(field, false)) => {
+                let operator = match self.next() {
+                    Some(Token::Operator(operator)) => operator,
+                    _ => bail!("Expected an operator after \"{}\"", field),
+                };
+                let value = match self.next() {
+                    Some(Token::Word(value, _)) => value,
+                    _ => bail!("Expected a value after \"{}\"", field),
+                };
+                Ok(Expression::Condition(Condition::new(
+                    &field, operator, value,
+                )?))
+            }
+            Some(token) => bail!("Expected a condition, got {}", token),
+            None => bail!("Unexpected end of query"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn task(id: usize, status: TaskStatus, group: &str, label: Option<&str>) -> Task {
+        let mut task = Task::new(
+            "ls -al".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            group.into(),
+            status,
+            Vec::new(),
+            label.map(String::from),
+        );
+        task.id = id;
+        task
+    }
+
+    #[test]
+    fn test_matches() {
+        let tasks = [
+            task(0, TaskStatus::Done(TaskResult::Failed(1)), "build", None),
+            task(
+                1,
+                TaskStatus::Done(TaskResult::Killed),
+                "build",
+                Some("deploy prod"),
+            ),
+            task(
+                2,
+                TaskStatus::Done(TaskResult::Success),
+                "build",
+                Some("deploy"),
+            ),
+            task(3, TaskStatus::Running, "default", Some("deploy")),
+        ];
+        let matching = |query: &str| -> Vec<usize> {
+            let query = Query::parse(query).unwrap();
+            tasks
+                .iter()
+                .filter(|task| query.matches(task))
+                .map(|task| task.id)
+                .collect()
+        };
+
+        assert_eq!(matching("status=failed"), vec![0, 1]);
+        assert_eq!(
+            matching("status=FAILED and group=build and label~=deploy"),
+            vec![1]
+        );
+        assert_eq!(matching("status=killed OR id>=3"), vec![1, 3]);
+        assert_eq!(
+            matching("label=\"deploy prod\" or (group != build)"),
+            vec![1, 3]
+        );
+        assert_eq!(matching("NOT status=done AND command~=\"ls -\""), vec![3]);
+        assert_eq!(matching("id<2 and not (status = killed)"), vec![0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        for query in [
+            "",
+            "status",
+            "status=",
+            "name=foo",
+            "id=foo",
+            "group>build",
+            "(status=done",
+            "status=done)",
+            "status=done AND",
+            "label=\"deploy",
+            "status=done group=build",
+        ] {
+            assert!(Query::parse(query).is_err(), "{} should fail", query);
+        }
+    }
+}
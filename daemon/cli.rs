@@ -24,4 +24,29 @@ pub struct CliArguments {
     /// This ignores all other config files.
     #[clap(short, long)]
     pub config: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    pub cmd: Option<SubCommand>,
+}
+
+#[derive(Clap, Debug)]
+pub enum SubCommand {
+    /// Manage the per-user LaunchAgent, which starts the daemon at login.
+    /// This is only supported on macOS.
+    Service {
+        #[clap(subcommand)]
+        action: ServiceAction,
+    },
+}
+
+#[derive(Clap, Debug)]
+pub enum ServiceAction {
+    /// Write the LaunchAgent plist and load it.
+    /// The daemon is started right away and on every login.
+    /// The `--config` and `--verbose` flags are passed on to the daemon.
+    Install,
+    /// Unload the LaunchAgent and remove the plist.
+    Uninstall,
+    /// Show whether the LaunchAgent is installed and whether the daemon is running.
+    Status,
 }
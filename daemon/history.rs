@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::prelude::*;
+use regex::Regex;
+
+use pueue_lib::log::read_last_log_file_lines;
+use pueue_lib::network::message::HistoryEntry;
+use pueue_lib::store::StateStore;
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
+
+/// Create the history entry of a finished task.
+/// This has to be done before its logs are removed.
+pub fn history_entry(task: &Task, output_lines: usize, pueue_directory: &Path) -> HistoryEntry {
+    let result = match &task.status {
+        TaskStatus::Done(result) => result.clone(),
+        _ => TaskResult::Errored,
+    };
+
+    let output = if output_lines == 0 {
+        String::new()
+    } else {
+        let log_directory = task.log_directory(pueue_directory);
+        match read_last_log_file_lines(task.id, &log_directory, output_lines) {
+            Ok((stdout, stderr)) if stderr.is_empty() => stdout,
+            Ok((stdout, stderr)) if stdout.is_empty() => stderr,
+            Ok((stdout, stderr)) => format!("{}\n{}", stdout, stderr),
+            Err(_) => String::new(),
+        }
+    };
+
+    HistoryEntry {
+        id: task.id,
+        command: task.command.clone(),
+        path: task.path.clone(),
+        group: task.group.clone(),
+        label: task.label.clone(),
+        result,
+        start: task.start,
+        end: task.end,
+        output,
+    }
+}
+
+/// Append entries to the history.
+pub fn append_to_history(entries: &[HistoryEntry], store: &dyn StateStore) -> Result<()> {
+    store
+        .append_history(entries)
+        .context("Failed to write to history")
+}
+
+/// Read all entries of the history, that match the search and finished after `since`.
+pub fn read_history(
+    store: &dyn StateStore,
+    search: Option<&Regex>,
+    since: Option<DateTime<Local>>,
+) -> Result<Vec<HistoryEntry>> {
+    let mut entries = store.read_history().context("Failed to read history")?;
+
+    entries.retain(|entry| {
+        if let Some(since) = since {
+            if entry.end.is_none_or(|end| end < since) {
+                return false;
+            }
+        }
+        if let Some(search) = search {
+            let label_matches = entry
+                .label
+                .as_ref()
+                .is_some_and(|label| search.is_match(label));
+            if !search.is_match(&entry.command) && !label_matches {
+                return false;
+            }
+        }
+
+        true
+    });
+
+    Ok(entries)
+}
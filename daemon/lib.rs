@@ -1,29 +1,47 @@
+use std::backtrace::Backtrace;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::{fs::create_dir_all, path::PathBuf};
 
 use anyhow::{bail, Result};
 use crossbeam_channel::{unbounded, Sender};
-use log::warn;
+use log::{error, warn};
 
+use pueue_lib::log::default_log_directory;
 use pueue_lib::network::certificate::create_certificates;
 use pueue_lib::network::message::{Message, Shutdown};
 use pueue_lib::network::protocol::socket_cleanup;
 use pueue_lib::network::secret::init_shared_secret;
-use pueue_lib::settings::Settings;
+use pueue_lib::settings::{parse_duration, parse_size, parse_time_window, Settings};
 use pueue_lib::state::State;
+use pueue_lib::store::FileStore;
 
-use self::state_helper::{restore_state, save_state};
-use crate::network::socket::accept_incoming;
+use self::state_helper::{restore_state_from_store, save_state, DaemonState};
+use crate::lua_script::LuaScript;
+use crate::network::socket::{accept_incoming, in_client_connection};
 use crate::task_handler::TaskHandler;
+use crate::wasm_policy::WasmPolicies;
+
+// The schemas of the add hook's payloads, which are published next to the ones in `pueue_lib::schema`.
+pub use crate::network::message_handler::add_hook::{hook_input_schema, task_changes_schema};
 
 pub mod cli;
+/// The history of cleaned tasks.
+mod history;
+/// The user's Lua script for routing, callbacks and result evaluation.
+mod lua_script;
+/// The replication of another daemon's state, if this daemon is a read-only mirror.
+mod mirror;
 mod network;
 mod pid;
 mod platform;
+/// Management of the daemon as a system service.
+pub mod service;
 /// Contains re-usable helper functions, that operate on the pueue-lib state.
 pub mod state_helper;
 mod task_handler;
+/// WebAssembly modules, which are invoked when tasks are added, started and finished.
+mod wasm_policy;
 
 /// The main entry point for the daemon logic.
 /// It's basically the `main`, but publicly exported as a library.
@@ -52,17 +70,83 @@ pub async fn run(config_path: Option<PathBuf>, test: bool) -> Result<()> {
         bail!("Failed saving config file: {:?}.", error);
     }
 
-    init_directories(&settings.shared.pueue_directory());
+    // Catch invalid durations early on, instead of silently never shutting down.
+    if let Some(duration) = &settings.daemon.exit_when_idle {
+        if parse_duration(duration).is_none() {
+            bail!(
+                "Invalid duration for exit_when_idle: {:?}. Use something like 90s, 30m or 2h.",
+                duration
+            );
+        }
+    }
+    for (group, duration) in &settings.daemon.group_max_runtimes {
+        if parse_duration(duration).is_none() {
+            bail!(
+                "Invalid max runtime for group {}: {:?}. Use something like 90s, 30m or 2h.",
+                group,
+                duration
+            );
+        }
+    }
+    for (group, window) in &settings.daemon.group_windows {
+        if parse_time_window(window).is_none() {
+            bail!(
+                "Invalid window for group {}: {:?}. Use something like 22:00-06:00.",
+                group,
+                window
+            );
+        }
+    }
+    if let Some(size) = &settings.daemon.min_free_disk {
+        if parse_size(size).is_none() {
+            bail!(
+                "Invalid size for min_free_disk: {:?}. Use something like 500M or 2G.",
+                size
+            );
+        }
+    }
+
+    // Otherwise, the output of tasks might not be redacted.
+    for pattern in &settings.daemon.redact_patterns {
+        if let Err(err) = regex::bytes::Regex::new(pattern) {
+            bail!("Invalid redact pattern {:?}: {}", pattern, err);
+        }
+    }
+
+    // Broken policy modules and scripts would otherwise only show up once they're used.
+    let wasm_policies = WasmPolicies::load(&settings.daemon.wasm_policies())?;
+    let lua_script = match settings.daemon.lua_script() {
+        Some(path) => Some(LuaScript::load(&path)?),
+        None => None,
+    };
+
+    init_directories(
+        &settings.shared.pueue_directory(),
+        &settings.shared.log_directory(),
+    );
+    #[cfg(target_os = "linux")]
+    if let Some(root) = &settings.daemon.cgroup_root {
+        // Tasks can still be started without limits, so this isn't fatal.
+        if let Err(err) = platform::cgroup::enable_controllers(Path::new(root)) {
+            warn!("{:?}", err);
+        }
+    }
+    // The lock is held, until the daemon exits.
+    let _lock = pid::lock_pueue_directory(&settings.shared.pueue_directory())?;
     if !settings.shared.daemon_key().exists() && !settings.shared.daemon_cert().exists() {
         create_certificates(&settings.shared)?;
     }
-    init_shared_secret(&settings.shared.shared_secret_path())?;
+    // The secret isn't needed, if clients are authenticated via their unix socket credentials.
+    if !settings.shared.use_peer_credentials() {
+        init_shared_secret(&settings.shared.shared_secret_path())?;
+    }
     pid::create_pid_file(&settings.shared.pueue_directory())?;
 
     // Restore the previous state and save any changes that might have happened during this
     // process. If no previous state exists, just create a new one.
     // Create a new empty state if any errors occur, but print the error message.
-    let mut state = match restore_state(&settings.shared.pueue_directory()) {
+    let store = FileStore::new(&settings.shared.pueue_directory());
+    let mut state = match restore_state_from_store(&store) {
         Ok(Some(state)) => state,
         Ok(None) => State::new(&settings, config_path.clone()),
         Err(error) => {
@@ -72,11 +156,12 @@ pub async fn run(config_path: Option<PathBuf>, test: bool) -> Result<()> {
         }
     };
     state.settings = settings.clone();
+    let state = DaemonState::new(state, Arc::new(store));
     save_state(&state)?;
     let state = Arc::new(Mutex::new(state));
 
     let (sender, receiver) = unbounded();
-    let mut task_handler = TaskHandler::new(state.clone(), receiver);
+    let mut task_handler = TaskHandler::new(state.clone(), receiver, wasm_policies, lua_script);
 
     // Don't set ctrlc and panic handlers during testing.
     // This is necessary for multithreaded integration testing, since multiple listener per process
@@ -89,16 +174,27 @@ pub async fn run(config_path: Option<PathBuf>, test: bool) -> Result<()> {
         task_handler.run();
     });
 
+    if settings.daemon.pause_on_suspend {
+        #[cfg(target_os = "linux")]
+        platform::suspend::watch_suspend(state.clone(), sender.clone());
+        #[cfg(not(target_os = "linux"))]
+        warn!("Pausing tasks on suspend is only supported on Linux");
+    }
+
+    if let Some(mirror) = settings.daemon.mirror.clone() {
+        tokio::spawn(mirror::follow_primary(state.clone(), mirror));
+    }
+
     accept_incoming(sender, state.clone()).await?;
 
     Ok(())
 }
 
 /// Initialize all directories needed for normal operation.
-fn init_directories(pueue_dir: &Path) {
+fn init_directories(pueue_dir: &Path, task_logs_dir: &Path) {
     // Pueue base path
     if !pueue_dir.exists() {
-        if let Err(error) = create_dir_all(&pueue_dir) {
+        if let Err(error) = create_dir_all(pueue_dir) {
             panic!(
                 "Failed to create main directory at {:?} error: {:?}",
                 pueue_dir, error
@@ -128,14 +224,19 @@ fn init_directories(pueue_dir: &Path) {
         }
     }
 
-    // Task log dir
-    let logs_dir = pueue_dir.join("task_logs");
-    if !logs_dir.exists() {
-        if let Err(error) = create_dir_all(&logs_dir) {
-            panic!(
-                "Failed to create task logs directory at {:?} error: {:?}",
-                logs_dir, error
-            );
+    // Task log dirs. The default one is still used by tasks, which haven't been started since
+    // the log directory has been changed.
+    for logs_dir in [
+        default_log_directory(pueue_dir),
+        task_logs_dir.to_path_buf(),
+    ] {
+        if !logs_dir.exists() {
+            if let Err(error) = create_dir_all(&logs_dir) {
+                panic!(
+                    "Failed to create task logs directory at {:?} error: {:?}",
+                    logs_dir, error
+                );
+            }
         }
     }
 }
@@ -144,25 +245,43 @@ fn init_directories(pueue_dir: &Path) {
 ///
 /// On SIGINT and SIGTERM, we exit gracefully by sending a DaemonShutdown message to the
 /// TaskHandler. This is to prevent dangling processes and other weird edge-cases.
+/// On Windows, the same happens on Ctrl-C, Ctrl-Break, when the console is closed,
+/// the user logs off or the system shuts down.
 ///
 /// On panic, we want to cleanup existing unix sockets and the PID file.
+/// Panics that happen while handling a client connection are the exception,
+/// since they only affect a single client.
 fn setup_signal_panic_handling(settings: &Settings, sender: &Sender<Message>) -> Result<()> {
     let sender_clone = sender.clone();
 
     // This section handles Shutdown via SigTerm/SigInt process signals
     // Notify the TaskHandler, so it can shutdown gracefully.
     // The actual program exit will be done via the TaskHandler.
+    #[cfg(not(target_os = "windows"))]
     ctrlc::set_handler(move || {
         // Notify the task handler
         sender_clone
             .send(Message::DaemonShutdown(Shutdown::Emergency))
             .expect("Failed to send Message to TaskHandler on Shutdown");
     })?;
+    #[cfg(target_os = "windows")]
+    platform::console_handler::set_handler(sender_clone)?;
 
     // Try to do some final cleanup, even if we panic.
     let settings_clone = settings.clone();
     let orig_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
+        // Panics while handling a client connection are caught and only affect that client.
+        // Log them with a backtrace and keep the daemon running.
+        if in_client_connection() {
+            error!(
+                "Panic while handling a client connection: {}\n{}",
+                panic_info,
+                Backtrace::force_capture()
+            );
+            return;
+        }
+
         // invoke the default handler and exit the process
         orig_hook(panic_info);
 
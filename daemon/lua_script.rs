@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use mlua::{FromLua, HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Value};
+use serde::Serialize;
+
+use pueue_lib::network::message::AddMessage;
+
+use crate::network::message_handler::add_hook::HookInput;
+
+/// Calls into the script, that take longer than this, are aborted.
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The script of `daemon.lua_script`. \
+/// It may define the following global functions, which all receive a task as table:
+///
+/// - `route(task)`: Return the group of a new task or `nil` to keep its group.
+/// - `format_callback(task)`: Return the callback command of a finished task or `nil` to skip
+///   the callback. This replaces the `daemon.callback` template.
+/// - `is_success(task)`: Return whether a task that exited actually succeeded, e.g. by looking at
+///   its `exit_code` or `stdout`. `nil` keeps the result.
+///
+/// Finished tasks have the same fields as the callback template. \
+/// Scripts only have access to the `string`, `table`, `math` and `utf8` libraries.
+pub struct LuaScript {
+    lua: Lua,
+    path: PathBuf,
+}
+
+impl LuaScript {
+    /// Load and run the script, so its functions are defined.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lua script {:?}", path))?;
+        let libs = StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8;
+        let lua = Lua::new_with(libs, LuaOptions::default())
+            .map_err(|err| anyhow!("Failed to initialize lua: {}", err))?;
+
+        set_timeout(&lua);
+        lua.load(&source)
+            .set_name(path.to_string_lossy())
+            .exec()
+            .map_err(|err| anyhow!("Failed to run lua script {:?}: {}", path, err))?;
+
+        Ok(LuaScript {
+            lua,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Check whether the script defines a global function with this name.
+    pub fn defines(&self, name: &str) -> bool {
+        matches!(
+            self.lua.globals().get::<_, Value>(name),
+            Ok(Value::Function(_))
+        )
+    }
+
+    /// Ask the script for the group of a new task.
+    pub fn route(&self, message: &AddMessage) -> Result<Option<String>> {
+        self.call("route", &HookInput::new(message))
+    }
+
+    /// Ask the script for the callback command of a finished task.
+    pub fn format_callback<T: Serialize>(&self, task: &T) -> Result<Option<String>> {
+        self.call("format_callback", task)
+    }
+
+    /// Ask the script whether a task actually succeeded.
+    pub fn is_success<T: Serialize>(&self, task: &T) -> Result<Option<bool>> {
+        self.call("is_success", task)
+    }
+
+    /// Call a global function of the script with the task as its only argument. \
+    /// Returns `None`, if the function isn't defined.
+    fn call<'lua, T, R>(&'lua self, name: &str, task: &T) -> Result<Option<R>>
+    where
+        T: Serialize,
+        R: FromLua<'lua>,
+    {
+        if !self.defines(name) {
+            return Ok(None);
+        }
+
+        let function: mlua::Function = self
+            .lua
+            .globals()
+            .get(name)
+            .map_err(|err| anyhow!("{}", err))?;
+        let task = self
+            .lua
+            .to_value(task)
+            .map_err(|err| anyhow!("Failed to pass the task to lua: {}", err))?;
+
+        set_timeout(&self.lua);
+        function
+            .call(task)
+            .map_err(|err| anyhow!("Lua function {} in {:?} failed: {}", name, self.path, err))
+    }
+}
+
+/// Abort the script, once the current call runs for longer than [SCRIPT_TIMEOUT].
+fn set_timeout(lua: &Lua) {
+    let started = Instant::now();
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(10_000),
+        move |_, _| {
+            if started.elapsed() > SCRIPT_TIMEOUT {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "The script didn't finish within {} seconds",
+                    SCRIPT_TIMEOUT.as_secs()
+                )));
+            }
+            Ok(())
+        },
+    );
+}
@@ -4,14 +4,19 @@ use anyhow::Result;
 use clap::Clap;
 use simplelog::{Config, LevelFilter, SimpleLogger};
 
-use pueue_daemon_lib::cli::CliArguments;
+use pueue_daemon_lib::cli::{CliArguments, SubCommand};
 use pueue_daemon_lib::run;
+use pueue_daemon_lib::service::service;
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
 async fn main() -> Result<()> {
     // Parse commandline options.
     let opt = CliArguments::parse();
 
+    if let Some(SubCommand::Service { action }) = &opt.cmd {
+        return service(action, &opt);
+    }
+
     if opt.daemonize {
         return fork_daemon(&opt);
     }
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+
+use pueue_lib::log::{clean_log_handles, get_task_log_paths};
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::*;
+use pueue_lib::network::secret::get_handshake_secret;
+use pueue_lib::settings::Mirror;
+
+use crate::state_helper::{save_state, SharedState};
+
+/// How long the mirror waits, before it reconnects to the primary daemon.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Continuously copy the state and the output of all tasks from the primary daemon.
+/// If the connection is lost, the mirror keeps its last copy and reconnects.
+pub async fn follow_primary(state: SharedState, mirror: Mirror) {
+    loop {
+        if let Err(error) = replicate(&state, &mirror).await {
+            warn!("Lost the connection to the primary daemon: {:?}", error);
+        }
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+/// Connect to the primary daemon and apply all updates it sends.
+async fn replicate(state: &SharedState, mirror: &Mirror) -> Result<()> {
+    let shared = {
+        let state = state.lock().unwrap();
+        mirror.shared(&state.settings.shared)
+    };
+
+    let mut stream = get_client_stream(&shared)
+        .await
+        .context("Failed to connect to the primary daemon")?;
+    let secret = get_handshake_secret(&shared)?;
+    send_bytes(&secret, &mut stream).await?;
+    let version = receive_bytes(&mut stream).await?;
+    if version.is_empty() {
+        bail!("The primary daemon didn't accept the secret");
+    }
+
+    let request = MirrorRequestMessage {
+        lines: mirror.log_lines,
+    };
+    send_message(Message::MirrorRequest(request), &mut stream).await?;
+    info!("Mirroring the primary daemon");
+
+    loop {
+        match receive_message(&mut stream).await? {
+            Message::MirrorUpdate(update) => apply_update(state, update)?,
+            Message::Failure(failure) => bail!("The primary daemon refused: {}", failure.text),
+            message => bail!("Received an unexpected message: {:?}", message),
+        }
+    }
+}
+
+/// Replace the tasks and groups with those of the primary daemon and save them.
+/// The mirror's own settings are kept, except for the groups, which are shown by clients.
+fn apply_update(state: &SharedState, update: MirrorUpdateMessage) -> Result<()> {
+    let mut state = state.lock().unwrap();
+    let pueue_directory = state.settings.shared.pueue_directory();
+    let log_directory = state.settings.shared.log_directory();
+    let mut primary = *update.state;
+
+    // Tasks that have been removed on the primary daemon don't need their output anymore.
+    for (task_id, task) in state.tasks.iter() {
+        if !primary.tasks.contains_key(task_id) {
+            clean_log_handles(*task_id, &task.log_directory(&pueue_directory));
+        }
+    }
+    for (task_id, (stdout, stderr)) in update.logs {
+        let (stdout_path, stderr_path) = get_task_log_paths(task_id, &log_directory);
+        std::fs::write(&stdout_path, stdout)
+            .with_context(|| format!("Failed to write {:?}", stdout_path))?;
+        std::fs::write(&stderr_path, stderr)
+            .with_context(|| format!("Failed to write {:?}", stderr_path))?;
+    }
+
+    // The output of all tasks is written to the mirror's own log directory.
+    for task in primary.tasks.values_mut() {
+        task.log_directory = Some(log_directory.clone());
+    }
+    state.tasks = primary.tasks;
+    state.archived_tasks = primary.archived_tasks;
+    state.groups = primary.groups;
+    state.pause_reasons = primary.pause_reasons;
+    state.statistics = primary.statistics;
+    state.settings.daemon.groups = primary.settings.daemon.groups;
+    state.settings.daemon.serial_groups = primary.settings.daemon.serial_groups;
+
+    save_state(&state)
+}
+
+/// Whether a request may be handled by a mirror, as it doesn't change anything.
+pub fn is_read_only(message: &Message) -> bool {
+    matches!(
+        message,
+        Message::Status
+            | Message::Schedule
+            | Message::History(_)
+            | Message::Log(_)
+            | Message::StreamRequest(_)
+            | Message::MirrorRequest(_)
+            | Message::Group(GroupMessage::List)
+            | Message::DaemonShutdown(_)
+            | Message::Flush
+    )
+}
@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 use std::time::Duration;
 use std::{fs::File, path::Path};
 
@@ -7,7 +7,8 @@ use anyhow::Result;
 use pueue_lib::log::*;
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::{send_message, GenericStream};
-use pueue_lib::state::SharedState;
+
+use crate::state_helper::SharedState;
 
 /// Handle the continuous stream of a message.
 pub async fn handle_follow(
@@ -50,9 +51,14 @@ pub async fn handle_follow(
         }
     };
 
+    let log_directory = match state.lock().unwrap().tasks.get(&task_id) {
+        Some(task) => task.log_directory(pueue_directory),
+        None => default_log_directory(pueue_directory),
+    };
+
     // The client requested streaming of stdout.
     let mut handle: File;
-    match get_log_file_handles(task_id, pueue_directory) {
+    match get_log_file_handles(task_id, &log_directory) {
         Err(_) => {
             return Ok(create_failure_message(
                 "Couldn't find output files for task. Maybe it finished? Try `log`",
@@ -67,10 +73,15 @@ pub async fn handle_follow(
         }
     }
 
+    // Skip all output the client already received before it had to reconnect.
+    if let Err(err) = handle.seek(SeekFrom::Start(message.offset)) {
+        return Ok(create_failure_message(format!("Error: {}", err)));
+    }
+
     // Get the stdout/stderr path.
     // We need to check continuously, whether the file still exists,
     // since the file can go away (e.g. due to finishing a task).
-    let (out_path, err_path) = get_log_paths(task_id, pueue_directory);
+    let (out_path, err_path) = get_task_log_paths(task_id, &log_directory);
     let handle_path = if message.err { err_path } else { out_path };
 
     loop {
@@ -80,17 +91,37 @@ pub async fn handle_follow(
                 "File has gone away. Did somebody remove the task?",
             ));
         }
+        // This is checked before reading, so the task's last output is still sent.
+        let done = message.until_done && task_is_done(state, task_id);
+
         // Read the next chunk of text from the last position.
         let mut buffer = Vec::new();
 
         if let Err(err) = handle.read_to_end(&mut buffer) {
             return Ok(create_failure_message(format!("Error: {}", err)));
         };
-        let text = String::from_utf8_lossy(&buffer).to_string();
+        // Invalid UTF-8 is replaced, which is why the client is told how many bytes were read.
+        // It resumes the stream at that offset after reconnecting.
+        let chunk = StreamMessage {
+            text: String::from_utf8_lossy(&buffer).to_string(),
+            bytes: buffer.len() as u64,
+        };
 
         // Send the new chunk and wait for 1 second.
-        let response = Message::Stream(text);
+        let response = Message::Stream(chunk);
         send_message(response, stream).await?;
+        if done {
+            return Ok(create_success_message(format!("Task {} finished", task_id)));
+        }
         tokio::time::sleep(Duration::from_millis(1000)).await;
     }
 }
+
+/// Whether a task finished. Removed tasks count as finished as well.
+fn task_is_done(state: &SharedState, task_id: usize) -> bool {
+    let state = state.lock().unwrap();
+    match state.tasks.get(&task_id) {
+        Some(task) => task.is_done(),
+        None => true,
+    }
+}
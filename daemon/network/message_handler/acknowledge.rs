@@ -0,0 +1,55 @@
+use pueue_lib::network::message::*;
+
+use super::ok_or_failure_message;
+use crate::network::response_helper::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{save_state, SharedState};
+
+/// Invoked when calling `pueue ack`.
+/// Mark failed tasks as acknowledged, so it's visible that their failure has been triaged.
+/// The tasks and their logs are kept as they are.
+pub fn acknowledge(task_ids: Vec<usize>, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    let (matching, mismatching) = state.filter_tasks(|task| task.failed(), Some(task_ids));
+
+    for task_id in &matching {
+        if let Some(task) = state.tasks.get_mut(task_id) {
+            task.acknowledged = true;
+        }
+    }
+    ok_or_return_failure_message!(save_state(&state));
+
+    let failed = mismatch_reasons(&state, mismatching, "Failed");
+    compile_task_response("Tasks are acknowledged", matching, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::*;
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn acknowledge_failed_only() {
+        let (state, _tempdir) = get_stub_state();
+        {
+            let mut state = state.lock().unwrap();
+            let task = get_stub_task("5", TaskStatus::Done(TaskResult::Failed(1)));
+            state.add_task(task);
+        }
+
+        let message = acknowledge(vec![1, 5], &state);
+        match message {
+            Message::TaskActionResponse(response) => {
+                assert_eq!(response.succeeded, vec![5]);
+                assert!(response.failed.contains_key(&1));
+            }
+            _ => panic!("Expected a task action response, got {:?}", message),
+        }
+
+        let state = state.lock().unwrap();
+        assert!(state.tasks[&5].acknowledged);
+        assert!(!state.tasks[&1].acknowledged);
+    }
+}
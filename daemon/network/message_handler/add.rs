@@ -1,35 +1,67 @@
+use anyhow::Result;
 use crossbeam_channel::Sender;
 
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
+use pueue_lib::path::is_drive_relative;
+use pueue_lib::settings::{Settings, Shared};
+use pueue_lib::state::State;
 use pueue_lib::task::{Task, TaskStatus};
 
+use super::add_hook::run_add_hook;
 use super::*;
+use crate::lua_script::LuaScript;
 use crate::ok_or_return_failure_message;
-use crate::state_helper::save_state;
+use crate::state_helper::{save_state, SharedState};
+use crate::wasm_policy::WasmPolicies;
 
 /// Invoked when calling `pueue add`.
 /// Queues a new task to the state.
 /// If the start_immediately flag is set, send a StartMessage to the task handler.
-pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedState) -> Message {
+pub fn add_task(mut message: AddMessage, sender: &Sender<Message>, state: &SharedState) -> Message {
+    if let Err(error) = run_extensions(&mut message, state) {
+        return create_failure_message(format!("{:#}", error));
+    }
+
     let mut state = state.lock().unwrap();
     if let Err(message) = ensure_group_exists(&state, &message.group) {
         return message;
     }
+    apply_group_defaults(&state.settings, &mut message);
 
-    let starting_status = if message.stashed || message.enqueue_at.is_some() {
-        TaskStatus::Stashed {
-            enqueue_at: message.enqueue_at,
+    // A reservation can only be committed once.
+    if let Some(task_id) = message.reserved_id {
+        match state.tasks.get(&task_id) {
+            Some(task) if task.status == TaskStatus::Reserved => (),
+            Some(task) => {
+                return create_typed_failure_message(
+                    FailureKind::InvalidState {
+                        expected: TaskStatus::Reserved.to_string(),
+                        actual: task.status.to_string(),
+                    },
+                    format!("Task {} isn't a reservation", task_id),
+                )
+            }
+            None => {
+                return create_typed_failure_message(
+                    FailureKind::TaskNotFound { task_id },
+                    format!("There's no reservation with id {}", task_id),
+                )
+            }
         }
-    } else {
-        TaskStatus::Queued
-    };
+    }
+
+    if let Err(text) = check_task(&state, &message) {
+        return create_failure_message(text);
+    }
 
     // Ensure that specified dependencies actually exist.
     let not_found: Vec<_> = message
         .dependencies
         .iter()
-        .filter(|id| !state.tasks.contains_key(id))
+        .filter(|id| !state.tasks.contains_key(id) || message.reserved_id == Some(**id))
         .collect();
     if !not_found.is_empty() {
         return create_failure_message(format!(
@@ -39,24 +71,24 @@ pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedSta
     }
 
     // Create a new task and add it to the state.
-    let mut task = Task::new(
-        message.command,
-        message.path,
-        message.envs,
-        message.group,
-        starting_status,
-        message.dependencies,
-        message.label,
-    );
-    // Sort and deduplicate dependency id.
-    task.dependencies.sort_unstable();
-    task.dependencies.dedup();
+    let reserved_id = message.reserved_id;
+    let start_immediately = message.start_immediately;
+    let print_task_id = message.print_task_id;
+    let enqueue_at = message.enqueue_at;
+    let mut task = create_task(message);
 
-    // Add a task. This also persists the state.
-    let task_id = state.add_task(task);
+    // Add a task or fill the reserved slot.
+    let task_id = match reserved_id {
+        Some(task_id) => {
+            task.id = task_id;
+            state.tasks.insert(task_id, task);
+            task_id
+        }
+        None => state.add_task(task),
+    };
 
     // Notify the task handler, in case the client wants to start the task immediately.
-    if message.start_immediately {
+    if start_immediately {
         sender
             .send(Message::Start(StartMessage {
                 tasks: TaskSelection::TaskIds(vec![task_id]),
@@ -66,9 +98,9 @@ pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedSta
     }
 
     // Create the customized response for the client.
-    let message = if message.print_task_id {
+    let message = if print_task_id {
         task_id.to_string()
-    } else if let Some(enqueue_at) = message.enqueue_at {
+    } else if let Some(enqueue_at) = enqueue_at {
         format!(
             "New task added (id {}). It will be enqueued at {}",
             task_id,
@@ -84,3 +116,338 @@ pub fn add_task(message: AddMessage, sender: &Sender<Message>, state: &SharedSta
 
     create_success_message(message)
 }
+
+/// Add several tasks at once. \
+/// Either all tasks are added or none of them, so a partially added set of tasks with
+/// dependencies can't start running. Tasks may depend on earlier tasks of the same batch.
+pub fn add_batch(
+    message: AddBatchMessage,
+    sender: &Sender<Message>,
+    state: &SharedState,
+) -> Message {
+    if message.tasks.is_empty() {
+        return create_failure_message("The batch doesn't contain any tasks");
+    }
+
+    // Hooks, scripts and policies are run for all tasks, before anything is added.
+    let mut tasks = message.tasks;
+    for (index, batch_task) in tasks.iter_mut().enumerate() {
+        if let Err(error) = run_extensions(&mut batch_task.task, state) {
+            return create_failure_message(format!("Task {} of the batch: {:#}", index, error));
+        }
+    }
+
+    let mut state = state.lock().unwrap();
+    for batch_task in tasks.iter_mut() {
+        apply_group_defaults(&state.settings, &mut batch_task.task);
+    }
+    for (index, batch_task) in tasks.iter().enumerate() {
+        let task = &batch_task.task;
+        if let Err(message) = ensure_group_exists(&state, &task.group) {
+            return message;
+        }
+        if task.reserved_id.is_some() {
+            return create_failure_message(format!(
+                "Task {} of the batch: Reservations can't be committed in a batch",
+                index
+            ));
+        }
+        if let Err(text) = check_task(&state, task) {
+            return create_failure_message(format!("Task {} of the batch: {}", index, text));
+        }
+
+        // Labels have to be unique inside of the batch as well.
+        if let Some(label) = &task.label {
+            let duplicate = tasks[..index].iter().any(|earlier| {
+                earlier.task.group == task.group && earlier.task.label.as_ref() == Some(label)
+            });
+            if duplicate && state.settings.daemon.unique_labels {
+                return create_failure_message(format!(
+                    "Task {} of the batch: The label {} is used several times in group {}",
+                    index, label, task.group
+                ));
+            }
+        }
+
+        // Dependencies outside of the batch have to exist.
+        if let Some(id) = task
+            .dependencies
+            .iter()
+            .find(|id| !state.tasks.contains_key(id))
+        {
+            return create_failure_message(format!(
+                "Task {} of the batch: Unable to setup dependencies : task {} not found",
+                index, id
+            ));
+        }
+        // Only earlier tasks of the batch may be referenced, which rules out cycles.
+        if let Some(dependency) = batch_task
+            .batch_dependencies
+            .iter()
+            .find(|dependency| **dependency >= index)
+        {
+            return create_failure_message(format!(
+                "Task {} of the batch: It can only depend on earlier tasks of the batch, not on {}",
+                index, dependency
+            ));
+        }
+    }
+
+    // Everything has been checked, so all tasks can be added.
+    // The tasks of an array share the id of the array's first task.
+    let array = message.array.then(|| state.next_task_id());
+    let mut task_ids: Vec<usize> = Vec::new();
+    for batch_task in tasks {
+        let start_immediately = batch_task.task.start_immediately;
+        let mut task = create_task(batch_task.task);
+        task.array = array;
+        task.dependencies.extend(
+            batch_task
+                .batch_dependencies
+                .iter()
+                .map(|index| task_ids[*index]),
+        );
+        task.dependencies.sort_unstable();
+        task.dependencies.dedup();
+
+        let task_id = state.add_task(task);
+        task_ids.push(task_id);
+        if start_immediately {
+            sender
+                .send(Message::Start(StartMessage {
+                    tasks: TaskSelection::TaskIds(vec![task_id]),
+                    children: false,
+                }))
+                .expect(SENDER_ERR);
+        }
+    }
+
+    ok_or_return_failure_message!(save_state(&state));
+
+    if let Some(array) = array {
+        return create_success_message(format!(
+            "New array {} added (ids {}-{}).",
+            array,
+            task_ids[0],
+            task_ids[task_ids.len() - 1]
+        ));
+    }
+    let ids: Vec<String> = task_ids.iter().map(|id| id.to_string()).collect();
+    create_success_message(format!("New tasks added (ids {}).", ids.join(", ")))
+}
+
+/// Let the add hook, the lua script and the WASM policies inspect and rewrite a new task.
+/// They're run without holding the lock, as they might take a while.
+pub fn run_extensions(message: &mut AddMessage, state: &SharedState) -> Result<()> {
+    // The add hook may rewrite or reject the task.
+    let add_hook = state.lock().unwrap().settings.daemon.add_hook.clone();
+    if let Some(add_hook) = add_hook {
+        run_add_hook(&add_hook, message)?;
+    }
+
+    // The lua script may route the task to another group.
+    let lua_script = state.lock().unwrap().settings.daemon.lua_script();
+    if let Some(path) = lua_script {
+        if let Some(group) = LuaScript::load(&path)?.route(message)? {
+            message.group = group;
+        }
+    }
+
+    // WASM policies are loaded for each task, so they can be replaced while the daemon runs.
+    let policy_paths = state.lock().unwrap().settings.daemon.wasm_policies();
+    if !policy_paths.is_empty() {
+        WasmPolicies::load(&policy_paths)?.on_add(message)?;
+    }
+
+    Ok(())
+}
+
+/// Use the defaults of the task's group for everything, that hasn't been chosen explicitly.
+/// The group's environment variables take precedence over the client's environment.
+pub fn apply_group_defaults(settings: &Settings, message: &mut AddMessage) {
+    let defaults = match settings.daemon.group_defaults.get(&message.group) {
+        Some(defaults) => defaults,
+        None => return,
+    };
+
+    if message.path_is_default {
+        if let Some(cwd) = &defaults.cwd {
+            message.path = Shared::expand(Path::new(cwd))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+    message.envs.extend(defaults.env.clone());
+    if message.shell.is_none() {
+        message.shell = defaults.shell;
+    }
+}
+
+/// Ensure that a new task doesn't conflict with other tasks and fits into its group.
+pub fn check_task(state: &State, message: &AddMessage) -> Result<(), String> {
+    check_path(&message.path)?;
+
+    // Ensure that no other unfinished task in this group has the same label.
+    if let Some(label) = &message.label {
+        if state.settings.daemon.unique_labels {
+            let existing = state.tasks.values().find(|task| {
+                task.group == message.group
+                    && task.label.as_ref() == Some(label)
+                    && !matches!(task.status, TaskStatus::Done(_))
+            });
+            if let Some(task) = existing {
+                return Err(format!(
+                    "Task {} in group {} already has the label {}",
+                    task.id, task.group, label
+                ));
+            }
+        }
+    }
+
+    // Ensure that the requested CPUs, resources and limits can be provided.
+    check_requirements(
+        state,
+        &message.group,
+        message.cpus,
+        &message.resources,
+        message.memory_limit,
+        message.cpu_limit,
+    )
+}
+
+/// Drive-relative paths such as `D:data` depend on the current directory of the drive,
+/// which only the client knows. The daemon would resolve them against its own directories.
+pub fn check_path(path: &str) -> Result<(), String> {
+    if cfg!(target_os = "windows") && is_drive_relative(path) {
+        return Err(format!(
+            "The path {} is relative to the current directory of its drive. Please use an absolute path.",
+            path
+        ));
+    }
+
+    Ok(())
+}
+
+/// Create the task of an add message. Its id is set, once it's added to the state.
+pub fn create_task(message: AddMessage) -> Task {
+    let starting_status = if message.stashed || message.enqueue_at.is_some() {
+        TaskStatus::Stashed {
+            enqueue_at: message.enqueue_at,
+        }
+    } else {
+        TaskStatus::Queued
+    };
+
+    let mut task = Task::new(
+        message.command,
+        message.path,
+        message.envs,
+        message.group,
+        starting_status,
+        message.dependencies,
+        message.label,
+    );
+    task.dependency_mode = message.dependency_mode;
+    task.created_by = message.created_by;
+    task.expire_at = message.expire_at;
+    task.keep_logs = message.keep_logs;
+    task.stderr_to_stdout = message.stderr_to_stdout;
+    task.max_runtime = message.max_runtime;
+    task.cpus = message.cpus;
+    task.resources = message.resources;
+    task.memory_limit = message.memory_limit;
+    task.cpu_limit = message.cpu_limit;
+    task.meta = message.meta;
+    task.tags = message.tags;
+    task.start_conditions = message.start_conditions;
+    task.on_success = message.on_success;
+    task.on_failure = message.on_failure;
+    task.shell = message.shell;
+
+    // Sort and deduplicate dependency id.
+    task.dependencies.sort_unstable();
+    task.dependencies.dedup();
+
+    task
+}
+
+/// Invoked when calling `pueue add --reserve`.
+/// Add a placeholder task and respond with its id.
+/// The task is created by a later `pueue add --commit <id>`.
+pub fn reserve(group: String, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    if let Err(message) = ensure_group_exists(&state, &group) {
+        return message;
+    }
+
+    let task = Task::new(
+        String::new(),
+        String::new(),
+        HashMap::new(),
+        group,
+        TaskStatus::Reserved,
+        Vec::new(),
+        None,
+    );
+    let task_id = state.add_task(task);
+    ok_or_return_failure_message!(save_state(&state));
+
+    create_success_message(task_id.to_string())
+}
+
+/// Ensure that the CPUs, resources and limits of a task can be provided.
+pub fn check_requirements(
+    state: &State,
+    group: &str,
+    cpus: Option<usize>,
+    resources: &BTreeMap<String, usize>,
+    memory_limit: Option<u64>,
+    cpu_limit: Option<f64>,
+) -> Result<(), String> {
+    // Ensure that the task fits into its group at all.
+    if let Some(cpus) = cpus {
+        if let Some(capacity) = state.settings.daemon.group_cpus.get(group) {
+            if cpus > *capacity {
+                return Err(format!(
+                    "The task requests {} CPUs, but group {} only has {}",
+                    cpus, group, capacity
+                ));
+            }
+        }
+    }
+
+    // Ensure that the requested resources exist and are large enough.
+    for (pool, amount) in resources {
+        match state.settings.daemon.resource_pools.get(pool) {
+            Some(units) if *amount > units.len() => {
+                return Err(format!(
+                    "The task requests {} units of resource {}, but there are only {}",
+                    amount,
+                    pool,
+                    units.len()
+                ));
+            }
+            Some(_) => (),
+            None => {
+                return Err(format!("Resource {} doesn't exist", pool));
+            }
+        }
+    }
+
+    // Resource limits are enforced via cgroups.
+    if memory_limit.is_some() || cpu_limit.is_some() {
+        if !cfg!(target_os = "linux") {
+            return Err("Resource limits are only supported on Linux".into());
+        }
+        if state.settings.daemon.cgroup_root.is_none() {
+            return Err("Resource limits require a cgroup, please set daemon.cgroup_root".into());
+        }
+    }
+    if let Some(cpu_limit) = cpu_limit {
+        if cpu_limit <= 0.0 {
+            return Err("The CPU limit has to be larger than 0".into());
+        }
+    }
+
+    Ok(())
+}
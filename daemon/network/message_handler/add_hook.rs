@@ -0,0 +1,157 @@
+use std::io::{Read, Write};
+use std::process::Stdio;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde_derive::{Deserialize, Serialize};
+
+use pueue_lib::network::message::AddMessage;
+
+use crate::platform::process_helper::compile_shell_command;
+
+/// Hooks that take longer than this are killed and the task is rejected.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The details of a new task, that are passed to the add hook. \
+/// The lua script receives the same details.
+#[derive(Serialize, JsonSchema)]
+pub struct HookInput<'a> {
+    command: &'a str,
+    path: &'a str,
+    group: &'a str,
+    label: &'a Option<String>,
+    tags: &'a [String],
+    created_by: &'a Option<String>,
+}
+
+/// The details, that the add hook may change. Missing fields are left untouched. \
+/// WASM policies use the same format for their changes.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TaskChanges {
+    command: Option<String>,
+    path: Option<String>,
+    group: Option<String>,
+    label: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+/// The schema of the JSON, that's passed to the add hook on stdin.
+pub fn hook_input_schema() -> RootSchema {
+    schema_for!(HookInput)
+}
+
+/// The schema of the JSON, that the add hook may print to change the task.
+pub fn task_changes_schema() -> RootSchema {
+    schema_for!(TaskChanges)
+}
+
+/// Let `daemon.add_hook` inspect and rewrite a new task. \
+/// An error is returned, if the hook rejected the task or if it couldn't be run.
+pub fn run_add_hook(hook: &str, message: &mut AddMessage) -> Result<()> {
+    let input = serde_json::to_string(&HookInput::new(message))?;
+
+    let mut child = compile_shell_command(hook)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn the add hook")?;
+
+    // Pipes are handled in the background, so a hook that doesn't read its input
+    // or prints a lot of output can't block the daemon.
+    if let Some(mut stdin) = child.stdin.take() {
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        });
+    }
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .context("Failed to wait for the add hook")?
+        {
+            break status;
+        }
+        if started.elapsed() >= HOOK_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "The add hook didn't finish within {} seconds",
+                HOOK_TIMEOUT.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        let reason = stderr.trim();
+        if reason.is_empty() {
+            bail!("The task has been rejected by the add hook");
+        }
+        bail!("The task has been rejected by the add hook: {}", reason);
+    }
+
+    // The task is added unchanged, if the hook doesn't print anything.
+    if stdout.trim().is_empty() {
+        return Ok(());
+    }
+    let changes: TaskChanges =
+        serde_json::from_str(&stdout).context("The add hook printed invalid JSON")?;
+    changes.apply(message);
+
+    Ok(())
+}
+
+impl<'a> HookInput<'a> {
+    pub fn new(message: &'a AddMessage) -> Self {
+        HookInput {
+            command: &message.command,
+            path: &message.path,
+            group: &message.group,
+            label: &message.label,
+            tags: &message.tags,
+            created_by: &message.created_by,
+        }
+    }
+}
+
+impl TaskChanges {
+    /// Apply the changes to a new task.
+    pub fn apply(self, message: &mut AddMessage) {
+        if let Some(command) = self.command {
+            message.command = command;
+        }
+        if let Some(path) = self.path {
+            message.path = path;
+        }
+        if let Some(group) = self.group {
+            message.group = group;
+        }
+        if let Some(label) = self.label {
+            message.label = Some(label);
+        }
+        if let Some(tags) = self.tags {
+            message.tags = tags;
+        }
+    }
+}
+
+/// Read a pipe of the hook to the end in a separate thread.
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut output);
+        }
+        output
+    })
+}
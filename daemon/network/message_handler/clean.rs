@@ -1,11 +1,13 @@
-use pueue_lib::log::clean_log_handles;
+use ::log::error;
+
+use pueue_lib::log::{archive_directory, archive_log_files, clean_log_handles};
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 use pueue_lib::task::{TaskResult, TaskStatus};
 
 use super::*;
+use crate::history::{append_to_history, history_entry};
 use crate::ok_or_return_failure_message;
-use crate::state_helper::{is_task_removable, save_state};
+use crate::state_helper::{is_task_removable, save_state, store, SharedState};
 
 /// Invoked when calling `pueue clean`.
 /// Remove all failed or done tasks from the state.
@@ -14,12 +16,33 @@ pub fn clean(message: CleanMessage, state: &SharedState) -> Message {
     ok_or_return_failure_message!(save_state(&state));
 
     let (matching, _) = state.filter_tasks(|task| matches!(task.status, TaskStatus::Done(_)), None);
+    let mut history = Vec::new();
 
     for task_id in &matching {
         // Ensure the task is removable, i.e. there are no dependant tasks.
         if !is_task_removable(&state, task_id, &[]) {
             continue;
         }
+        // Only clean tasks with the requested tag.
+        if let Some(tag) = &message.tag {
+            if !state
+                .tasks
+                .get(task_id)
+                .is_some_and(|task| task.has_tag(tag))
+            {
+                continue;
+            }
+        }
+        // Only clean tasks of the requested array.
+        if let Some(array) = message.array {
+            if state
+                .tasks
+                .get(task_id)
+                .is_none_or(|task| task.array != Some(array))
+            {
+                continue;
+            }
+        }
         // Check if we should ignore this task, if only successful tasks should be removed.
         if message.successful_only {
             if let Some(task) = state.tasks.get(task_id) {
@@ -28,20 +51,59 @@ pub fn clean(message: CleanMessage, state: &SharedState) -> Message {
                 }
             }
         }
-        let _ = state.tasks.remove(task_id).unwrap();
-        clean_log_handles(*task_id, &state.settings.shared.pueue_directory());
+        let task = state.tasks.remove(task_id).unwrap();
+        let pueue_directory = state.settings.shared.pueue_directory();
+        if state.settings.daemon.keep_history {
+            let output_lines = state.settings.daemon.history_output_lines;
+            history.push(history_entry(&task, output_lines, &pueue_directory));
+        }
+        let log_directory = task.log_directory(&pueue_directory);
+        if !(task.keep_logs || message.keep_logs) {
+            clean_log_handles(*task_id, &log_directory);
+            continue;
+        }
+
+        // Archived tasks keep their id, so their logs can still be looked up.
+        if let Err(err) = archive_log_files(*task_id, &log_directory, &pueue_directory) {
+            error!("Failed to archive logs of task {}: {}", task_id, err);
+        }
+        state.archived_tasks.insert(*task_id, task);
+    }
+
+    // Only keep the latest archived tasks, as the archive is part of every status and save.
+    let archive_directory = archive_directory(&state.settings.shared.pueue_directory());
+    while state.archived_tasks.len() > state.settings.daemon.max_archived_tasks {
+        if let Some((task_id, _)) = state.archived_tasks.pop_first() {
+            clean_log_handles(task_id, &archive_directory);
+        }
+    }
+
+    if let Err(err) = append_to_history(&history, store(&state).as_ref()) {
+        error!("Failed to add cleaned tasks to the history: {:#}", err);
     }
 
     ok_or_return_failure_message!(save_state(&state));
 
-    if message.successful_only {
-        create_success_message("All successfully finished tasks have been removed")
+    let tasks = if message.successful_only {
+        "successfully finished tasks"
     } else {
-        create_success_message("All finished tasks have been removed")
+        "finished tasks"
+    };
+    match (message.tag, message.array) {
+        (Some(tag), _) => create_success_message(format!(
+            "All {} with tag \"{}\" have been removed",
+            tasks, tag
+        )),
+        (None, Some(array)) => create_success_message(format!(
+            "All {} of array {} have been removed",
+            tasks, array
+        )),
+        (None, None) => create_success_message(format!("All {} have been removed", tasks)),
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_get_then_check)]
 mod tests {
     use super::super::fixtures::*;
     use super::*;
@@ -50,7 +112,12 @@ mod tests {
     use tempfile::TempDir;
 
     fn get_message(successful_only: bool) -> CleanMessage {
-        CleanMessage { successful_only }
+        CleanMessage {
+            successful_only,
+            keep_logs: false,
+            tag: None,
+            array: None,
+        }
     }
 
     fn get_clean_test_state() -> (SharedState, TempDir) {
@@ -136,4 +203,51 @@ mod tests {
         assert_eq!(state.tasks.len(), 5);
         assert!(state.tasks.get(&0).is_none());
     }
+
+    #[test]
+    fn clean_with_tag() {
+        let (state, _tempdir) = get_clean_test_state();
+        {
+            let mut state = state.lock().unwrap();
+            for id in [1, 3] {
+                state.tasks.get_mut(&id).unwrap().tags = vec!["nightly".into()];
+            }
+        }
+
+        let mut message = get_message(false);
+        message.tag = Some("nightly".into());
+        let message = clean(message, &state);
+
+        assert!(matches!(message, Message::Success(_)));
+        if let Message::Success(text) = message {
+            assert_eq!(
+                text,
+                "All finished tasks with tag \"nightly\" have been removed"
+            );
+        };
+
+        // Only the tagged tasks have been removed.
+        let state = state.lock().unwrap();
+        assert_eq!(state.tasks.len(), 4);
+        assert!(!state.tasks.contains_key(&1));
+        assert!(!state.tasks.contains_key(&3));
+    }
+
+    #[test]
+    fn clean_limits_archive() {
+        let (state, _tempdir) = get_clean_test_state();
+        state.lock().unwrap().settings.daemon.max_archived_tasks = 2;
+
+        let mut message = get_message(false);
+        message.keep_logs = true;
+        clean(message, &state);
+
+        // Only the latest archived tasks are kept and new ids still aren't reused.
+        let mut state = state.lock().unwrap();
+        assert_eq!(
+            state.archived_tasks.keys().cloned().collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+        assert_eq!(state.add_task(get_stub_task("6", TaskStatus::Queued)), 6);
+    }
 }
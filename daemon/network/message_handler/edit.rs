@@ -1,11 +1,11 @@
 use pueue_lib::aliasing::insert_alias;
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 use pueue_lib::task::TaskStatus;
 
+use super::add::check_path;
 use super::*;
 use crate::ok_or_return_failure_message;
-use crate::state_helper::save_state;
+use crate::state_helper::{save_state, SharedState};
 
 /// Invoked when calling `pueue edit`.
 /// If a user wants to edit a message, we need to send him the current command.
@@ -16,7 +16,13 @@ pub fn edit_request(task_id: usize, state: &SharedState) -> Message {
     match state.tasks.get_mut(&task_id) {
         Some(task) => {
             if !task.is_queued() {
-                return create_failure_message("You can only edit a queued/stashed task");
+                return create_typed_failure_message(
+                    FailureKind::InvalidState {
+                        expected: "Queued or Stashed".into(),
+                        actual: task.status.to_string(),
+                    },
+                    "You can only edit a queued/stashed task",
+                );
             }
             task.prev_status = task.status.clone();
             task.status = TaskStatus::Locked;
@@ -25,10 +31,16 @@ pub fn edit_request(task_id: usize, state: &SharedState) -> Message {
                 task_id: task.id,
                 command: task.original_command.clone(),
                 path: task.path.clone(),
+                group: task.group.clone(),
+                label: task.label.clone(),
+                prev_status: Some(task.prev_status.clone()),
             };
             Message::EditResponse(message)
         }
-        None => create_failure_message("No task with this id."),
+        None => create_typed_failure_message(
+            FailureKind::TaskNotFound { task_id },
+            "No task with this id.",
+        ),
     }
 }
 
@@ -40,10 +52,19 @@ pub fn edit(message: EditMessage, state: &SharedState) -> Message {
     match state.tasks.get_mut(&message.task_id) {
         Some(task) => {
             if !(task.status == TaskStatus::Locked) {
-                return create_failure_message("Task is no longer locked.");
+                return create_typed_failure_message(
+                    FailureKind::InvalidState {
+                        expected: TaskStatus::Locked.to_string(),
+                        actual: task.status.to_string(),
+                    },
+                    "Task is no longer locked.",
+                );
             }
 
             task.status = task.prev_status.clone();
+            if let Err(text) = check_path(&message.path) {
+                return create_failure_message(text);
+            }
             task.original_command = message.command.clone();
             task.command = insert_alias(message.command.clone());
             task.path = message.path.clone();
@@ -51,6 +72,59 @@ pub fn edit(message: EditMessage, state: &SharedState) -> Message {
 
             create_success_message("Command has been updated")
         }
-        None => create_failure_message(format!("Task to edit has gone away: {}", message.task_id)),
+        None => create_typed_failure_message(
+            FailureKind::TaskNotFound {
+                task_id: message.task_id,
+            },
+            format!("Task to edit has gone away: {}", message.task_id),
+        ),
+    }
+}
+
+/// Invoked when calling `pueue edit --env` or `pueue env`.
+/// Set or remove environment variables of a queued or stashed task.
+pub fn edit_env(message: EditEnvMessage, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    let task = match state.tasks.get_mut(&message.task_id) {
+        Some(task) => task,
+        None => {
+            return create_typed_failure_message(
+                FailureKind::TaskNotFound {
+                    task_id: message.task_id,
+                },
+                "No task with this id.",
+            )
+        }
+    };
+
+    if !task.is_queued() {
+        return create_typed_failure_message(
+            FailureKind::InvalidState {
+                expected: "Queued or Stashed".into(),
+                actual: task.status.to_string(),
+            },
+            "You can only edit the environment of a queued/stashed task",
+        );
+    }
+
+    // Catch typos, before anything is changed.
+    if let Some(key) = message
+        .unset
+        .iter()
+        .find(|key| !task.envs.contains_key(*key))
+    {
+        return create_failure_message(format!(
+            "Task {} has no environment variable {}",
+            task.id, key
+        ));
     }
+
+    for key in &message.unset {
+        task.envs.remove(key);
+    }
+    task.envs.extend(message.set);
+    let task_id = task.id;
+    ok_or_return_failure_message!(save_state(&state));
+
+    create_success_message(format!("Environment of task {} has been updated", task_id))
 }
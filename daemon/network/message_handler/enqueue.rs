@@ -1,8 +1,8 @@
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 use pueue_lib::task::TaskStatus;
 
 use crate::network::response_helper::*;
+use crate::state_helper::SharedState;
 
 /// Invoked when calling `pueue enqueue`.
 /// Enqueue specific stashed tasks.
@@ -41,5 +41,6 @@ pub fn enqueue(message: EnqueueMessage, state: &SharedState) -> Message {
         String::from("Tasks are enqueued")
     };
 
-    compile_task_response(&text, matching, mismatching)
+    let failed = mismatch_reasons(&state, mismatching, "Stashed");
+    compile_task_response(&text, matching, failed)
 }
@@ -2,11 +2,11 @@ use crossbeam_channel::Sender;
 
 use pueue_lib::network::message::*;
 use pueue_lib::settings::PUEUE_DEFAULT_GROUP;
-use pueue_lib::state::SharedState;
 
 use crate::network::message_handler::ok_or_failure_message;
 use crate::network::response_helper::ensure_group_exists;
 use crate::ok_or_return_failure_message;
+use crate::state_helper::SharedState;
 
 /// Invoked on `pueue groups`.
 /// Manage groups.
@@ -22,6 +22,7 @@ pub fn group(message: GroupMessage, sender: &Sender<Message>, state: &SharedStat
             Message::GroupResponse(GroupResponseMessage {
                 groups: state.groups.clone(),
                 settings: state.settings.daemon.groups.clone(),
+                pause_reasons: state.pause_reasons.clone(),
             })
         }
         GroupMessage::Add(group) => {
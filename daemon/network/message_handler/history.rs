@@ -0,0 +1,22 @@
+use regex::Regex;
+
+use pueue_lib::network::message::*;
+
+use crate::history::read_history;
+use crate::state_helper::{store, SharedState};
+
+/// Invoked when calling `pueue history`.
+/// Return all cleaned tasks from the history, that match the search.
+pub fn get_history(message: HistoryRequestMessage, state: &SharedState) -> Message {
+    let store = store(&state.lock().unwrap());
+
+    let search = match message.search.as_deref().map(Regex::new).transpose() {
+        Ok(search) => search,
+        Err(err) => return create_failure_message(format!("Invalid search: {}", err)),
+    };
+
+    match read_history(store.as_ref(), search.as_ref(), message.since) {
+        Ok(entries) => Message::HistoryResponse(entries),
+        Err(err) => create_failure_message(format!("{:#}", err)),
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use pueue_lib::network::message::*;
+use pueue_lib::task::{Task, TaskStatus};
+
+use super::add::{apply_group_defaults, check_task, create_task, run_extensions};
+use super::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{save_state, SharedState};
+
+/// Invoked when calling `pueue import`.
+/// Add previously exported tasks with new ids and map their dependencies to those ids.
+/// The tasks are added like new tasks, which is why hooks, scripts, policies and the
+/// defaults of their groups apply to them as well.
+/// Nothing is imported, if any of the tasks can't be added.
+pub fn import(tasks: Vec<Task>, state: &SharedState) -> Message {
+    if tasks.is_empty() {
+        return create_failure_message("There are no tasks to import");
+    }
+
+    let old_ids: BTreeSet<usize> = tasks.iter().map(|task| task.id).collect();
+    if old_ids.len() != tasks.len() {
+        return create_failure_message("The import contains several tasks with the same id");
+    }
+    for task in &tasks {
+        if let Some(dependency) = task.dependencies.iter().find(|id| !old_ids.contains(id)) {
+            return create_failure_message(format!(
+                "Task {} depends on task {}, which isn't part of the import",
+                task.id, dependency
+            ));
+        }
+    }
+
+    // Add the tasks in their previous order.
+    let mut imports: Vec<(&Task, AddMessage)> = tasks
+        .iter()
+        .map(|task| (task, import_message(task)))
+        .collect();
+    imports.sort_by_key(|(task, _)| task.id);
+
+    // Hooks, scripts and policies are run for all tasks, before anything is added.
+    for (task, message) in imports.iter_mut() {
+        if let Err(error) = run_extensions(message, state) {
+            return create_failure_message(format!("Task {}: {:#}", task.id, error));
+        }
+    }
+
+    let mut state = state.lock().unwrap();
+    for (task, message) in imports.iter_mut() {
+        if let Err(message) = ensure_group_exists(&state, &message.group) {
+            return message;
+        }
+        apply_group_defaults(&state.settings, message);
+        if let Err(text) = check_task(&state, message) {
+            return create_failure_message(format!("Task {}: {}", task.id, text));
+        }
+    }
+
+    let mut new_ids = BTreeMap::new();
+    for (task, message) in imports {
+        new_ids.insert(task.id, state.add_task(create_task(message)));
+    }
+
+    // All dependencies are part of the import, which has been ensured above.
+    for task in &tasks {
+        let dependencies = task.dependencies.iter().map(|id| new_ids[id]).collect();
+        if let Some(new_task) = state.tasks.get_mut(&new_ids[&task.id]) {
+            new_task.dependencies = dependencies;
+        }
+    }
+
+    ok_or_return_failure_message!(save_state(&state));
+
+    let mapping = new_ids
+        .iter()
+        .map(|(old_id, new_id)| format!("{} -> {}", old_id, new_id))
+        .collect::<Vec<String>>()
+        .join(", ");
+    create_success_message(format!("Imported {} tasks: {}", new_ids.len(), mapping))
+}
+
+/// The add message, which recreates an exported task.
+/// Stashed tasks stay stashed, all others are queued again.
+/// Dependencies are set, once the new ids of all imported tasks are known.
+fn import_message(task: &Task) -> AddMessage {
+    let (stashed, enqueue_at) = match task.status {
+        TaskStatus::Stashed { enqueue_at } => (true, enqueue_at),
+        _ => (false, None),
+    };
+
+    AddMessage {
+        command: task.command.clone(),
+        path: task.path.clone(),
+        path_is_default: false,
+        envs: task.envs.clone(),
+        start_immediately: false,
+        stashed,
+        group: task.group.clone(),
+        enqueue_at,
+        dependencies: Vec::new(),
+        dependency_mode: task.dependency_mode,
+        label: task.label.clone(),
+        print_task_id: false,
+        created_by: task.created_by.clone(),
+        expire_at: task.expire_at,
+        reserved_id: None,
+        keep_logs: task.keep_logs,
+        stderr_to_stdout: task.stderr_to_stdout,
+        max_runtime: task.max_runtime,
+        cpus: task.cpus,
+        resources: task.resources.clone(),
+        memory_limit: task.memory_limit,
+        cpu_limit: task.cpu_limit,
+        meta: task.meta.clone(),
+        tags: task.tags.clone(),
+        start_conditions: task.start_conditions.clone(),
+        on_success: task.on_success.clone(),
+        on_failure: task.on_failure.clone(),
+        shell: task.shell,
+    }
+}
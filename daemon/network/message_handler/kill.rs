@@ -1,10 +1,10 @@
 use crossbeam_channel::Sender;
 
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 
 use super::SENDER_ERR;
 use crate::network::response_helper::{ensure_group_exists, task_action_response_helper};
+use crate::state_helper::SharedState;
 
 /// Invoked when calling `pueue kill`.
 /// Forward the kill message to the task handler, which then kills the process.
@@ -26,6 +26,7 @@ pub fn kill(message: KillMessage, sender: &Sender<Message>, state: &SharedState)
             TaskSelection::TaskIds(task_ids) => task_action_response_helper(
                 "Tasks are being killed",
                 task_ids,
+                "Running or Paused",
                 |task| task.is_running(),
                 &state,
             ),
@@ -33,6 +34,14 @@ pub fn kill(message: KillMessage, sender: &Sender<Message>, state: &SharedState)
                 "Sending signal {} to all running tasks of group {}.",
                 signal, group
             )),
+            TaskSelection::Tag(tag) => create_success_message(format!(
+                "Sending signal {} to all running tasks with tag \"{}\".",
+                signal, tag
+            )),
+            TaskSelection::Array(array) => create_success_message(format!(
+                "Sending signal {} to all running tasks of array {}.",
+                signal, array
+            )),
             TaskSelection::All => {
                 create_success_message(format!("Sending signal {} to all running tasks.", signal))
             }
@@ -42,6 +51,7 @@ pub fn kill(message: KillMessage, sender: &Sender<Message>, state: &SharedState)
             TaskSelection::TaskIds(task_ids) => task_action_response_helper(
                 "Tasks are being killed",
                 task_ids,
+                "Running or Paused",
                 |task| task.is_running(),
                 &state,
             ),
@@ -49,6 +59,12 @@ pub fn kill(message: KillMessage, sender: &Sender<Message>, state: &SharedState)
                 "All tasks of group \"{}\" are being killed.",
                 group
             )),
+            TaskSelection::Tag(tag) => {
+                create_success_message(format!("All tasks with tag \"{}\" are being killed.", tag))
+            }
+            TaskSelection::Array(array) => {
+                create_success_message(format!("All tasks of array {} are being killed.", array))
+            }
             TaskSelection::All => create_success_message("All tasks are being killed."),
         }
     }
@@ -1,32 +1,42 @@
 use std::collections::BTreeMap;
 
-use pueue_lib::log::read_and_compress_log_files;
+use pueue_lib::log::{archive_directory, read_and_compress_log_files};
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
+
+use crate::state_helper::SharedState;
 
 /// Invoked when calling `pueue log`.
 /// Return the current state and the stdou/stderr of all tasks to the client.
 pub fn get_log(message: LogRequestMessage, state: &SharedState) -> Message {
     let state = { state.lock().unwrap().clone() };
+    let pueue_directory = state.settings.shared.pueue_directory();
+    // Archived tasks live in a separate list and their logs in a separate directory.
+    let all_tasks = if message.archived {
+        &state.archived_tasks
+    } else {
+        &state.tasks
+    };
+
     // Return all logs, if no specific task id is specified.
     let task_ids = if message.task_ids.is_empty() {
-        state.tasks.keys().cloned().collect()
+        all_tasks.keys().cloned().collect()
     } else {
         message.task_ids
     };
 
     let mut tasks = BTreeMap::new();
     for task_id in task_ids.iter() {
-        if let Some(task) = state.tasks.get(task_id) {
+        if let Some(task) = all_tasks.get(task_id) {
             // We send log output and the task at the same time.
             // This isn't as efficient as sending the raw compressed data directly,
             // but it's a lot more convenient for now.
+            let directory = if message.archived {
+                archive_directory(&pueue_directory)
+            } else {
+                task.log_directory(&pueue_directory)
+            };
             let (stdout, stderr) = if message.send_logs {
-                match read_and_compress_log_files(
-                    *task_id,
-                    &state.settings.shared.pueue_directory(),
-                    message.lines,
-                ) {
+                match read_and_compress_log_files(*task_id, &directory, message.lines) {
                     Ok((stdout, stderr)) => (Some(stdout), Some(stderr)),
                     Err(err) => {
                         // Fail early if there's some problem with getting the log output
@@ -0,0 +1,85 @@
+use std::fs::create_dir_all;
+use std::path::PathBuf;
+
+use log::error;
+
+use pueue_lib::network::message::*;
+
+use super::ok_or_failure_message;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{move_task_logs, save_settings, save_state, SharedState};
+
+/// Invoked when calling `pueue daemon set-log-dir`.
+/// New logs are created in the new directory right away.
+///
+/// The logs of all other tasks are moved one task at a time, so the state is never locked for
+/// long. Running tasks keep writing to their current files, which are moved once they finish.
+pub fn set_log_directory(path: PathBuf, state: &SharedState) -> Message {
+    if !path.is_absolute() {
+        return create_failure_message(format!("The log directory {:?} isn't absolute", path));
+    }
+    if let Err(error) = create_dir_all(&path) {
+        return create_failure_message(format!(
+            "Failed to create the log directory {:?}: {}",
+            path, error
+        ));
+    }
+
+    let (pueue_directory, task_ids) = {
+        let mut state = state.lock().unwrap();
+        state.settings.shared.log_directory = Some(path.clone());
+        if let Err(error) = save_settings(&state) {
+            return create_failure_message(format!(
+                "Failed while saving the config file: {}",
+                error
+            ));
+        }
+
+        let task_ids: Vec<usize> = state.tasks.keys().cloned().collect();
+        (state.settings.shared.pueue_directory(), task_ids)
+    };
+
+    let mut moved = 0;
+    let mut running = 0;
+    let mut failed = Vec::new();
+    for task_id in task_ids {
+        let mut state = state.lock().unwrap();
+        let task = match state.tasks.get_mut(&task_id) {
+            Some(task) => task,
+            None => continue,
+        };
+        if task.is_running() {
+            if task.log_directory(&pueue_directory) != path {
+                running += 1;
+            }
+            continue;
+        }
+
+        match move_task_logs(task, &pueue_directory, &path) {
+            Ok(true) => moved += 1,
+            Ok(false) => (),
+            Err(error) => {
+                error!("{:#}", error);
+                failed.push(task_id.to_string());
+            }
+        }
+    }
+
+    ok_or_return_failure_message!(save_state(&state.lock().unwrap()));
+
+    if !failed.is_empty() {
+        return create_failure_message(format!(
+            "Failed to move the logs of tasks: {}. Check the daemon's log for details.",
+            failed.join(", ")
+        ));
+    }
+
+    let mut text = format!("Moved the logs of {} tasks to {:?}.", moved, path);
+    if running > 0 {
+        text.push_str(&format!(
+            " The logs of {} running tasks are moved, once they finish.",
+            running
+        ));
+    }
+    create_success_message(text)
+}
@@ -2,44 +2,64 @@ use crossbeam_channel::Sender;
 use std::fmt::Display;
 
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 
 use crate::network::response_helper::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{flush_state, SharedState};
 
+mod acknowledge;
 mod add;
+pub mod add_hook;
 mod clean;
 mod edit;
 mod enqueue;
 mod group;
+mod history;
+mod import;
 mod kill;
 mod log;
+mod log_directory;
 mod parallel;
 mod pause;
 mod remove;
 mod restart;
+mod schedule;
 mod send;
+mod snooze;
 mod start;
 mod stash;
 mod switch;
 
+pub use remove::force_remove;
+
 pub static SENDER_ERR: &str = "Failed to send message to task handler thread";
 
 pub fn handle_message(message: Message, sender: &Sender<Message>, state: &SharedState) -> Message {
     match message {
+        Message::Acknowledge(task_ids) => acknowledge::acknowledge(task_ids, state),
         Message::Add(message) => add::add_task(message, sender, state),
+        Message::AddBatch(message) => add::add_batch(message, sender, state),
+        Message::Reserve(group) => add::reserve(group, state),
         Message::Clean(message) => clean::clean(message, state),
         Message::Edit(message) => edit::edit(message, state),
+        Message::EditEnv(message) => edit::edit_env(message, state),
         Message::EditRequest(task_id) => edit::edit_request(task_id, state),
         Message::Enqueue(message) => enqueue::enqueue(message, state),
+        Message::Flush => flush(state),
         Message::Group(message) => group::group(message, sender, state),
+        Message::History(message) => history::get_history(message, state),
+        Message::Import(tasks) => import::import(tasks, state),
         Message::Kill(message) => kill::kill(message, sender, state),
         Message::Log(message) => log::get_log(message, state),
+        Message::SetLogDirectory(path) => log_directory::set_log_directory(path, state),
         Message::Parallel(message) => parallel::set_parallel_tasks(message, state),
         Message::Pause(message) => pause::pause(message, sender, state),
         Message::Remove(task_ids) => remove::remove(task_ids, state),
         Message::Reset(message) => reset(message, sender),
         Message::Restart(message) => restart::restart_multiple(message, sender, state),
+        Message::Schedule => schedule::get_schedule(state),
         Message::Send(message) => send::send(message, sender, state),
+        Message::Snooze(message) => snooze::snooze(message, state),
         Message::Start(message) => start::start(message, sender, state),
         Message::Stash(task_ids) => stash::stash(task_ids, state),
         Message::Switch(message) => switch::switch(message, state),
@@ -56,6 +76,15 @@ fn reset(message: ResetMessage, sender: &Sender<Message>) -> Message {
     create_success_message("Everything is being reset right now.")
 }
 
+/// Invoked when calling `pueue daemon flush`.
+/// Save the state right away, including any changes whose save has been delayed.
+fn flush(state: &SharedState) -> Message {
+    let state = state.lock().unwrap();
+    ok_or_return_failure_message!(flush_state(&state));
+
+    create_success_message("The state has been saved.")
+}
+
 /// Invoked when calling `pueue status`.
 /// Return the current state.
 fn get_status(state: &SharedState) -> Message {
@@ -63,6 +92,8 @@ fn get_status(state: &SharedState) -> Message {
     Message::StatusResponse(Box::new(state))
 }
 
+// The failure message is sent to the client right away, so its size doesn't matter.
+#[allow(clippy::result_large_err)]
 fn ok_or_failure_message<T, E: Display>(result: Result<T, E>) -> Result<T, Message> {
     match result {
         Ok(inner) => Ok(inner),
@@ -83,8 +114,10 @@ macro_rules! ok_or_return_failure_message {
     };
 }
 
+// The fixtures predate the lints of newer compilers and are kept as they are.
 #[cfg(test)]
-mod fixtures {
+#[allow(unused_imports, noop_method_call, clippy::useless_format)]
+pub mod fixtures {
     pub use crossbeam_channel::Sender;
     use std::collections::HashMap;
     use std::sync::{Arc, Mutex};
@@ -93,9 +126,12 @@ mod fixtures {
     pub use pueue_lib::network::message::*;
     pub use pueue_lib::network::protocol::socket_cleanup;
     pub use pueue_lib::settings::{Settings, PUEUE_DEFAULT_GROUP};
-    pub use pueue_lib::state::{SharedState, State};
+    pub use pueue_lib::state::State;
+    pub use pueue_lib::store::FileStore;
     pub use pueue_lib::task::{Task, TaskResult, TaskStatus};
 
+    pub use crate::state_helper::{DaemonState, SharedState};
+
     pub use super::*;
     pub use crate::network::response_helper::*;
 
@@ -123,7 +159,8 @@ mod fixtures {
             std::fs::create_dir(task_log_dir).expect("Failed to create test task log dir");
         }
 
-        let state = State::new(&settings, None);
+        let store = Arc::new(FileStore::new(tempdir.path()));
+        let state = DaemonState::new(State::new(&settings, None), store);
         (Arc::new(Mutex::new(state)), tempdir)
     }
 
@@ -1,8 +1,7 @@
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 
 use crate::network::response_helper::*;
-use crate::state_helper::save_settings;
+use crate::state_helper::{save_settings, SharedState};
 
 /// Set the parallel tasks for either a specific group or the global default.
 pub fn set_parallel_tasks(message: ParallelMessage, state: &SharedState) -> Message {
@@ -11,16 +10,34 @@ pub fn set_parallel_tasks(message: ParallelMessage, state: &SharedState) -> Mess
         return message;
     }
 
+    // Serial groups run a single task at a time.
+    let parallel_tasks = if message.serial {
+        1
+    } else {
+        message.parallel_tasks
+    };
     state
         .settings
         .daemon
         .groups
-        .insert(message.group.clone(), message.parallel_tasks);
+        .insert(message.group.clone(), parallel_tasks);
+
+    let serial_groups = &mut state.settings.daemon.serial_groups;
+    serial_groups.retain(|group| group != &message.group);
+    if message.serial {
+        serial_groups.push(message.group.clone());
+    }
 
     if let Err(error) = save_settings(&state) {
         return create_failure_message(format!("Failed while saving the config file: {}", error));
     }
 
+    if message.serial {
+        return create_success_message(format!(
+            "Group \"{}\" now runs its tasks one after another",
+            &message.group
+        ));
+    }
     create_success_message(format!(
         "Parallel tasks setting for group \"{}\" adjusted",
         &message.group
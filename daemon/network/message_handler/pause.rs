@@ -1,11 +1,11 @@
 use crossbeam_channel::Sender;
 
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 use pueue_lib::task::TaskStatus;
 
 use super::SENDER_ERR;
 use crate::network::response_helper::*;
+use crate::state_helper::SharedState;
 
 /// Invoked when calling `pueue pause`.
 /// Forward the pause message to the task handler, which then pauses groups/tasks/everything.
@@ -23,17 +23,34 @@ pub fn pause(message: PauseMessage, sender: &Sender<Message>, state: &SharedStat
         .send(Message::Pause(message.clone()))
         .expect(SENDER_ERR);
 
+    let running_tasks = if message.wait {
+        " Running tasks are allowed to finish."
+    } else {
+        ""
+    };
+
     // Return a response depending on the selected tasks.
     match message.tasks {
         TaskSelection::TaskIds(task_ids) => task_action_response_helper(
             "Tasks are being paused",
             task_ids,
+            "Running",
             |task| matches!(task.status, TaskStatus::Running),
             &state,
         ),
-        TaskSelection::Group(group) => {
-            create_success_message(format!("Group \"{}\" is being paused.", &group))
+        TaskSelection::Group(group) => create_success_message(format!(
+            "Group \"{}\" is being paused.{}",
+            &group, running_tasks
+        )),
+        TaskSelection::Tag(tag) => create_success_message(format!(
+            "All running tasks with tag \"{}\" are being paused.",
+            &tag
+        )),
+        TaskSelection::Array(array) => {
+            create_success_message(format!("Array {} is being paused.{}", array, running_tasks))
+        }
+        TaskSelection::All => {
+            create_success_message(format!("All queues are being paused.{}", running_tasks))
         }
-        TaskSelection::All => create_success_message("All queues are being paused."),
     }
 }
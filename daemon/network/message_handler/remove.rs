@@ -1,12 +1,16 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+
 use pueue_lib::log::clean_log_handles;
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
-use pueue_lib::task::{Task, TaskStatus};
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
 
-use super::ok_or_failure_message;
+use super::{ok_or_failure_message, SENDER_ERR};
 use crate::network::response_helper::*;
 use crate::ok_or_return_failure_message;
-use crate::state_helper::{is_task_removable, save_state};
+use crate::state_helper::{is_task_removable, save_state, SharedState};
 
 /// Invoked when calling `pueue remove`.
 /// Remove tasks from the queue.
@@ -20,32 +24,155 @@ pub fn remove(task_ids: Vec<usize>, state: &SharedState) -> Message {
                 | TaskStatus::Stashed { .. }
                 | TaskStatus::Done(_)
                 | TaskStatus::Locked
+                | TaskStatus::Reserved
         )
     };
-    let (mut not_running, mut running) = state.filter_tasks(filter, Some(task_ids));
+    let (mut not_running, running) = state.filter_tasks(filter, Some(task_ids));
+    let mut failed = mismatch_reasons(&state, running, "Queued, Stashed or Done");
 
     // Don't delete tasks, if there are other tasks that depend on this one.
     // However, we allow to delete those tasks, if they're supposed to be deleted as well.
     for task_id in not_running.clone() {
         if !is_task_removable(&state, &task_id, &not_running) {
-            running.push(task_id);
+            failed.insert(task_id, FailureKind::Other);
             not_running.retain(|id| id != &task_id);
         };
     }
 
+    let pueue_directory = state.settings.shared.pueue_directory();
     for task_id in &not_running {
-        state.tasks.remove(task_id);
-
-        clean_log_handles(*task_id, &state.settings.shared.pueue_directory());
+        if let Some(task) = state.tasks.remove(task_id) {
+            clean_log_handles(*task_id, &task.log_directory(&pueue_directory));
+        }
     }
 
     ok_or_return_failure_message!(save_state(&state));
 
-    compile_task_response("Tasks removed from list", not_running, running)
+    compile_task_response("Tasks removed from list", not_running, failed)
+}
+
+/// How long we wait for killed tasks to be reaped, before we give up on removing them.
+const FORCE_REMOVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Invoked when calling `pueue remove --force`.
+/// Running and paused tasks are killed, including their child processes, and removed as soon
+/// as their process has been reaped. All other tasks are removed right away.
+///
+/// The response is only sent once everything has been removed.
+/// It contains the final result of each task that had to be killed.
+pub async fn force_remove(
+    task_ids: Vec<usize>,
+    sender: &Sender<Message>,
+    state: &SharedState,
+) -> Message {
+    let (mut removed, mut failed, to_kill) = {
+        let mut state = state.lock().unwrap();
+        let (mut existing, missing) = state.filter_tasks(|_| true, Some(task_ids));
+        let mut failed: BTreeMap<usize, FailureKind> = missing
+            .into_iter()
+            .map(|task_id| (task_id, FailureKind::TaskNotFound { task_id }))
+            .collect();
+
+        for task_id in existing.clone() {
+            if !is_task_removable(&state, &task_id, &existing) {
+                failed.insert(task_id, FailureKind::Other);
+                existing.retain(|id| id != &task_id);
+            };
+        }
+
+        // Tasks that aren't running are removed immediately,
+        // otherwise they might be started while we wait for the others.
+        let (to_kill, removed): (Vec<usize>, Vec<usize>) = existing
+            .into_iter()
+            .partition(|task_id| state.tasks[task_id].is_running());
+        let pueue_directory = state.settings.shared.pueue_directory();
+        for task_id in &removed {
+            if let Some(task) = state.tasks.remove(task_id) {
+                clean_log_handles(*task_id, &task.log_directory(&pueue_directory));
+            }
+        }
+        ok_or_return_failure_message!(save_state(&state));
+
+        (removed, failed, to_kill)
+    };
+
+    if to_kill.is_empty() {
+        return compile_task_response("Tasks removed from list", removed, failed);
+    }
+
+    // Child processes are killed as well, since their task and its logs are about to vanish.
+    sender
+        .send(Message::Kill(KillMessage {
+            tasks: TaskSelection::TaskIds(to_kill.clone()),
+            children: true,
+            signal: None,
+        }))
+        .expect(SENDER_ERR);
+
+    // A task is only marked as done, once the task handler reaped its process.
+    let start = Instant::now();
+    let mut results: BTreeMap<usize, Option<TaskResult>> = BTreeMap::new();
+    while results.len() < to_kill.len() {
+        if start.elapsed() > FORCE_REMOVE_TIMEOUT {
+            for task_id in &to_kill {
+                if !results.contains_key(task_id) {
+                    failed.insert(*task_id, FailureKind::Other);
+                }
+            }
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut state = state.lock().unwrap();
+        for task_id in &to_kill {
+            if results.contains_key(task_id) {
+                continue;
+            }
+            let result = match state.tasks.get(task_id) {
+                Some(task) if task.is_running() => continue,
+                Some(Task {
+                    status: TaskStatus::Done(result),
+                    ..
+                }) => Some(result.clone()),
+                // The task has been changed by someone else in the meantime.
+                _ => None,
+            };
+
+            let pueue_directory = state.settings.shared.pueue_directory();
+            if let Some(task) = state.tasks.remove(task_id) {
+                clean_log_handles(*task_id, &task.log_directory(&pueue_directory));
+            }
+            removed.push(*task_id);
+            results.insert(*task_id, result);
+        }
+        ok_or_return_failure_message!(save_state(&state));
+    }
+
+    removed.sort_unstable();
+    let results: Vec<String> = results
+        .iter()
+        .filter_map(|(task_id, result)| {
+            result
+                .as_ref()
+                .map(|result| format!("{} {}", task_id, result))
+        })
+        .collect();
+    let text = if results.is_empty() {
+        "Tasks removed from list".to_string()
+    } else {
+        format!(
+            "Tasks removed from list (final results: {})",
+            results.join(", ")
+        )
+    };
+
+    compile_task_response(&text, removed, failed)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use super::super::fixtures::*;
     use super::*;
 
@@ -60,13 +187,18 @@ mod tests {
         let message = remove(vec![0, 1, 2, 3, 4], &state);
 
         // Return message is correct
-        assert!(matches!(message, Message::Success(_)));
-        if let Message::Success(text) = message {
-            assert_eq!(
-                text,
-                "Tasks removed from list: 0, 1, 2\nThe command failed for tasks: 3, 4"
-            );
+        let invalid_state = |actual: &str| FailureKind::InvalidState {
+            expected: "Queued, Stashed or Done".into(),
+            actual: actual.into(),
         };
+        assert_eq!(
+            message,
+            compile_task_response(
+                "Tasks removed from list",
+                vec![0, 1, 2],
+                BTreeMap::from([(3, invalid_state("Running")), (4, invalid_state("Paused"))]),
+            )
+        );
 
         let state = state.lock().unwrap();
         assert_eq!(state.tasks.len(), 2);
@@ -93,10 +225,14 @@ mod tests {
         let message = remove(vec![1], &state);
 
         // Return message is correct
-        assert!(matches!(message, Message::Failure(_)));
-        if let Message::Failure(text) = message {
-            assert_eq!(text, "The command failed for tasks: 1");
-        };
+        assert_eq!(
+            message,
+            compile_task_response(
+                "Tasks removed from list",
+                vec![],
+                BTreeMap::from([(1, FailureKind::Other)]),
+            )
+        );
 
         {
             let state = state.lock().unwrap();
@@ -107,10 +243,14 @@ mod tests {
         let message = remove(vec![1, 5], &state);
 
         // Return message is correct
-        assert!(matches!(message, Message::Failure(_)));
-        if let Message::Failure(text) = message {
-            assert_eq!(text, "The command failed for tasks: 1, 5");
-        };
+        assert_eq!(
+            message,
+            compile_task_response(
+                "Tasks removed from list",
+                vec![],
+                BTreeMap::from([(1, FailureKind::Other), (5, FailureKind::Other)]),
+            )
+        );
 
         {
             let state = state.lock().unwrap();
@@ -121,10 +261,10 @@ mod tests {
         let message = remove(vec![1, 5, 6], &state);
 
         // Return message is correct
-        assert!(matches!(message, Message::Success(_)));
-        if let Message::Success(text) = message {
-            assert_eq!(text, "Tasks removed from list: 1, 5, 6");
-        };
+        assert_eq!(
+            message,
+            compile_task_response("Tasks removed from list", vec![1, 5, 6], BTreeMap::new())
+        );
 
         {
             let state = state.lock().unwrap();
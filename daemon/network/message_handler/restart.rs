@@ -1,30 +1,43 @@
 use crossbeam_channel::Sender;
-use std::sync::MutexGuard;
 
 use pueue_lib::aliasing::insert_alias;
 use pueue_lib::network::message::*;
-use pueue_lib::state::{SharedState, State};
+use pueue_lib::state::State;
 use pueue_lib::task::TaskStatus;
 
-use super::{task_action_response_helper, SENDER_ERR};
+use crate::state_helper::{LockedState, SharedState};
+
+use super::{create_failure_message, task_action_response_helper, SENDER_ERR};
 
 /// This is a small wrapper around the actual in-place task `restart` functionality.
 ///
 /// The "not in-place" restart functionality is actually just a copy the finished task + create a
 /// new task, which is completely handled on the client-side.
+///
+/// Failed tasks can also be selected via [FailedSelection].
+/// They're resolved while holding the state lock, so the selection and the restart are atomic.
 pub fn restart_multiple(
-    message: RestartMessage,
+    mut message: RestartMessage,
     sender: &Sender<Message>,
     state: &SharedState,
 ) -> Message {
-    let task_ids: Vec<usize> = message.tasks.iter().map(|task| task.task_id).collect();
     let mut state = state.lock().unwrap();
 
+    if let Some(selection) = &message.failed {
+        let selected = select_failed_tasks(&state, selection, &message.tasks);
+        message.tasks.extend(selected);
+        if message.tasks.is_empty() {
+            return create_failure_message("There are no failed tasks to restart.");
+        }
+    }
+    let task_ids: Vec<usize> = message.tasks.iter().map(|task| task.task_id).collect();
+
     // We have to compile the response beforehand.
     // Otherwise we no longer know which tasks, were actually capable of being being restarted.
     let response = task_action_response_helper(
         "Tasks restarted",
         task_ids.clone(),
+        "Done",
         |task| task.is_done(),
         &state,
     );
@@ -47,12 +60,33 @@ pub fn restart_multiple(
     response
 }
 
+/// Get all failed tasks that match the selection and aren't explicitly listed yet.
+/// The tasks are restarted with their current command and path in the order of their ids.
+/// Since they keep their ids, all dependencies between them are preserved.
+fn select_failed_tasks(
+    state: &State,
+    selection: &FailedSelection,
+    listed: &[TasksToRestart],
+) -> Vec<TasksToRestart> {
+    state
+        .tasks
+        .iter()
+        .filter(|(_, task)| selection.matches(task))
+        .filter(|(id, _)| !listed.iter().any(|task| task.task_id == **id))
+        .map(|(id, task)| TasksToRestart {
+            task_id: *id,
+            command: task.original_command.clone(),
+            path: task.path.clone(),
+        })
+        .collect()
+}
+
 /// This is invoked, whenever a task is actually restarted (in-place) without creating a new task.
 /// Update a possibly changed path/command and reset all infos from the previous run.
 ///
 /// The "not in-place" restart functionality is actually just a copy the finished task + create a
 /// new task, which is completely handled on the client-side.
-fn restart(state: &mut MutexGuard<State>, to_restart: &TasksToRestart, stashed: bool) {
+pub fn restart(state: &mut LockedState, to_restart: &TasksToRestart, stashed: bool) {
     // Check if we actually know this task.
     let task = if let Some(task) = state.tasks.get_mut(&to_restart.task_id) {
         task
@@ -80,4 +114,5 @@ fn restart(state: &mut MutexGuard<State>, to_restart: &TasksToRestart, stashed:
     // Reset all variables of any previous run.
     task.start = None;
     task.end = None;
+    task.slot = None;
 }
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use pueue_lib::network::message::*;
+use pueue_lib::task::TaskStatus;
+
+use crate::state_helper::SharedState;
+
+/// Invoked when calling `pueue schedule`.
+/// Return all known future events ordered by their time.
+pub fn get_schedule(state: &SharedState) -> Message {
+    let state = state.lock().unwrap();
+    let group_max_runtimes = state.settings.daemon.group_max_runtimes();
+
+    let mut events = Vec::new();
+    for task in state.tasks.values() {
+        let mut push = |time, kind| {
+            events.push(ScheduledEvent {
+                time,
+                kind,
+                task_id: Some(task.id),
+                group: task.group.clone(),
+            })
+        };
+
+        if let TaskStatus::Stashed {
+            enqueue_at: Some(enqueue_at),
+        } = task.status
+        {
+            push(enqueue_at, ScheduledEventKind::Enqueue);
+        }
+
+        if matches!(task.status, TaskStatus::Queued | TaskStatus::Stashed { .. }) {
+            if let Some(expire_at) = task.expire_at {
+                push(expire_at, ScheduledEventKind::Expire);
+            }
+        }
+
+        if task.is_running() {
+            let max_runtime = task
+                .max_runtime
+                .map(Duration::from_secs)
+                .or_else(|| group_max_runtimes.get(&task.group).copied());
+            let max_runtime = max_runtime.and_then(|max| chrono::Duration::from_std(max).ok());
+            if let (Some(start), Some(max_runtime)) = (task.start, max_runtime) {
+                push(start + max_runtime, ScheduledEventKind::Timeout);
+            }
+        }
+    }
+    events.sort_by_key(|event| event.time);
+
+    Message::ScheduleResponse(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::*;
+    use super::*;
+
+    use chrono::Local;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_schedule() {
+        let (state, _tempdir) = get_state();
+        let now = Local::now();
+        {
+            let mut state = state.lock().unwrap();
+            state
+                .settings
+                .daemon
+                .group_max_runtimes
+                .insert(PUEUE_DEFAULT_GROUP.into(), "1h".into());
+
+            let mut task = get_stub_task(
+                "0",
+                TaskStatus::Stashed {
+                    enqueue_at: Some(now + chrono::Duration::minutes(30)),
+                },
+            );
+            task.expire_at = Some(now + chrono::Duration::minutes(90));
+            state.add_task(task);
+
+            let mut task = get_stub_task("1", TaskStatus::Running);
+            task.start = Some(now);
+            state.add_task(task);
+
+            // Finished tasks don't expire anymore.
+            let mut task = get_stub_task("2", TaskStatus::Done(TaskResult::Success));
+            task.expire_at = Some(now);
+            state.add_task(task);
+        }
+
+        let events = match get_schedule(&state) {
+            Message::ScheduleResponse(events) => events,
+            _ => panic!("Expected a schedule"),
+        };
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| (event.task_id, event.kind, event.time - now))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (
+                    Some(0),
+                    ScheduledEventKind::Enqueue,
+                    chrono::Duration::minutes(30)
+                ),
+                (
+                    Some(1),
+                    ScheduledEventKind::Timeout,
+                    chrono::Duration::hours(1)
+                ),
+                (
+                    Some(0),
+                    ScheduledEventKind::Expire,
+                    chrono::Duration::minutes(90)
+                ),
+            ]
+        );
+    }
+}
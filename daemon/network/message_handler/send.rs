@@ -1,9 +1,10 @@
 use crossbeam_channel::Sender;
 
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 use pueue_lib::task::TaskStatus;
 
+use crate::state_helper::SharedState;
+
 use super::SENDER_ERR;
 
 /// Invoked when calling `pueue send`.
@@ -16,10 +17,23 @@ pub fn send(message: SendMessage, sender: &Sender<Message>, state: &SharedState)
         match state.tasks.get(&message.task_id) {
             Some(task) => {
                 if task.status != TaskStatus::Running {
-                    return create_failure_message("You can only send input to a running task");
+                    return create_typed_failure_message(
+                        FailureKind::InvalidState {
+                            expected: TaskStatus::Running.to_string(),
+                            actual: task.status.to_string(),
+                        },
+                        "You can only send input to a running task",
+                    );
                 }
             }
-            None => return create_failure_message("No task with this id."),
+            None => {
+                return create_typed_failure_message(
+                    FailureKind::TaskNotFound {
+                        task_id: message.task_id,
+                    },
+                    "No task with this id.",
+                )
+            }
         }
     }
 
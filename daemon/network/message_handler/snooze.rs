@@ -0,0 +1,88 @@
+use pueue_lib::network::message::*;
+use pueue_lib::task::TaskStatus;
+
+use super::ok_or_failure_message;
+use super::restart::restart;
+use crate::network::response_helper::*;
+use crate::ok_or_return_failure_message;
+use crate::state_helper::{save_state, SharedState};
+
+/// Invoked when calling `pueue snooze`.
+/// Restart failed tasks in place, but keep them stashed until the given time.
+/// They're then enqueued like any other delayed task.
+pub fn snooze(message: SnoozeMessage, state: &SharedState) -> Message {
+    let mut state = state.lock().unwrap();
+    let (matching, mismatching) = state.filter_tasks(|task| task.failed(), Some(message.task_ids));
+
+    for task_id in &matching {
+        let task = &state.tasks[task_id];
+        let to_restart = TasksToRestart {
+            task_id: *task_id,
+            command: task.original_command.clone(),
+            path: task.path.clone(),
+        };
+        restart(&mut state, &to_restart, true);
+
+        let task = state.tasks.get_mut(task_id).expect("Task should be there.");
+        task.status = TaskStatus::Stashed {
+            enqueue_at: Some(message.enqueue_at),
+        };
+    }
+    ok_or_return_failure_message!(save_state(&state));
+
+    let text = format!(
+        "Tasks will be retried at {}",
+        message.enqueue_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    let failed = mismatch_reasons(&state, mismatching, "Failed");
+    compile_task_response(&text, matching, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local};
+
+    use super::super::fixtures::*;
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn snooze_failed_only() {
+        let (state, _tempdir) = get_stub_state();
+        {
+            let mut state = state.lock().unwrap();
+            let mut task = get_stub_task("5", TaskStatus::Done(TaskResult::Failed(1)));
+            task.start = Some(Local::now());
+            task.end = Some(Local::now());
+            state.add_task(task);
+        }
+
+        let enqueue_at = Local::now() + Duration::hours(2);
+        let message = SnoozeMessage {
+            task_ids: vec![1, 5],
+            enqueue_at,
+        };
+        match snooze(message, &state) {
+            Message::TaskActionResponse(response) => {
+                assert_eq!(response.succeeded, vec![5]);
+                assert!(response.failed.contains_key(&1));
+            }
+            message => panic!("Expected a task action response, got {:?}", message),
+        }
+
+        let state = state.lock().unwrap();
+        let task = &state.tasks[&5];
+        assert_eq!(
+            task.status,
+            TaskStatus::Stashed {
+                enqueue_at: Some(enqueue_at)
+            }
+        );
+        assert_eq!(task.end, None);
+        assert_eq!(
+            state.tasks[&1].status,
+            TaskStatus::Done(TaskResult::Success)
+        );
+    }
+}
@@ -1,11 +1,11 @@
 use crossbeam_channel::Sender;
 
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 use pueue_lib::task::TaskStatus;
 
 use super::SENDER_ERR;
 use crate::network::response_helper::*;
+use crate::state_helper::SharedState;
 
 /// Invoked when calling `pueue start`.
 /// Forward the start message to the task handler, which then starts the process(es).
@@ -28,6 +28,7 @@ pub fn start(message: StartMessage, sender: &Sender<Message>, state: &SharedStat
         TaskSelection::TaskIds(task_ids) => task_action_response_helper(
             "Tasks are being started",
             task_ids,
+            "Paused, Queued or Stashed",
             |task| {
                 matches!(
                     task.status,
@@ -39,6 +40,13 @@ pub fn start(message: StartMessage, sender: &Sender<Message>, state: &SharedStat
         TaskSelection::Group(group) => {
             create_success_message(format!("Group \"{}\" is being resumed.", &group))
         }
+        TaskSelection::Tag(tag) => create_success_message(format!(
+            "All paused tasks with tag \"{}\" are being resumed.",
+            &tag
+        )),
+        TaskSelection::Array(array) => {
+            create_success_message(format!("Array {} is being resumed.", array))
+        }
         TaskSelection::All => create_success_message("All queues are being resumed."),
     }
 }
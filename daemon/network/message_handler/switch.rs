@@ -1,10 +1,9 @@
 use pueue_lib::network::message::*;
-use pueue_lib::state::SharedState;
 use pueue_lib::task::TaskStatus;
 
 use super::ok_or_failure_message;
 use crate::ok_or_return_failure_message;
-use crate::state_helper::save_state;
+use crate::state_helper::{save_state, SharedState};
 
 /// Invoked when calling `pueue switch`.
 /// Switch the position of two tasks in the upcoming queue.
@@ -12,13 +11,20 @@ use crate::state_helper::save_state;
 pub fn switch(message: SwitchMessage, state: &SharedState) -> Message {
     let mut state = state.lock().unwrap();
 
-    let task_ids = vec![message.task_id_1, message.task_id_2];
+    let task_ids = [message.task_id_1, message.task_id_2];
     let (_, mismatching) = state.filter_tasks(
         |task| matches!(task.status, TaskStatus::Queued | TaskStatus::Stashed { .. }),
         Some(task_ids.to_vec()),
     );
-    if !mismatching.is_empty() {
-        return create_failure_message("Tasks have to be either queued or stashed.");
+    if let Some(task_id) = mismatching.first() {
+        let kind = match state.tasks.get(task_id) {
+            Some(task) => FailureKind::InvalidState {
+                expected: "Queued or Stashed".into(),
+                actual: task.status.to_string(),
+            },
+            None => FailureKind::TaskNotFound { task_id: *task_id },
+        };
+        return create_typed_failure_message(kind, "Tasks have to be either queued or stashed.");
     }
     if task_ids[0] == task_ids[1] {
         return create_failure_message("You cannot switch a task with itself.");
@@ -133,8 +139,8 @@ mod tests {
 
         // Return message is correct
         assert!(matches!(message, Message::Failure(_)));
-        if let Message::Failure(text) = message {
-            assert_eq!(text, "You cannot switch a task with itself.");
+        if let Message::Failure(failure) = message {
+            assert_eq!(failure.text, "You cannot switch a task with itself.");
         };
     }
 
@@ -185,9 +191,28 @@ mod tests {
 
             // Assert, that we get a Failure message with the correct text.
             assert!(matches!(message, Message::Failure(_)));
-            if let Message::Failure(text) = message {
-                assert_eq!(text, "Tasks have to be either queued or stashed.");
+            if let Message::Failure(failure) = message {
+                assert_eq!(failure.text, "Tasks have to be either queued or stashed.");
             };
         }
     }
+
+    #[test]
+    /// The failure contains the status of the task that cannot be switched.
+    fn switch_invalid_state() {
+        let (state, _tempdir) = get_stub_state();
+
+        let message = switch(get_message(0, 1), &state);
+
+        assert!(matches!(message, Message::Failure(_)));
+        if let Message::Failure(failure) = message {
+            assert_eq!(
+                failure.kind,
+                FailureKind::InvalidState {
+                    expected: "Queued or Stashed".into(),
+                    actual: "Done".into(),
+                }
+            );
+        };
+    }
 }
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use pueue_lib::log::{get_task_log_paths, read_last_log_file_lines};
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::{send_message, GenericStream};
+
+use crate::state_helper::SharedState;
+
+/// Continuously send the state and the output of all tasks to a mirror.
+///
+/// An update is only sent, if something changed since the last one.
+/// The output of a task is only sent, if its log files changed in size.
+/// This only ends, once the mirror disconnects.
+pub async fn handle_mirror(
+    pueue_directory: &Path,
+    stream: &mut GenericStream,
+    state: &SharedState,
+    message: MirrorRequestMessage,
+) -> Result<Message> {
+    let mut last_state = String::new();
+    let mut log_sizes: BTreeMap<usize, (u64, u64)> = BTreeMap::new();
+
+    loop {
+        let state = state.lock().unwrap().clone();
+
+        let mut logs = BTreeMap::new();
+        let mut sizes = BTreeMap::new();
+        for (task_id, task) in state.tasks.iter() {
+            let log_directory = task.log_directory(pueue_directory);
+            let (stdout_path, stderr_path) = get_task_log_paths(*task_id, &log_directory);
+            let size = (file_size(&stdout_path), file_size(&stderr_path));
+            if log_sizes.get(task_id) != Some(&size) {
+                // Tasks without log files, e.g. queued tasks, don't have any output yet.
+                if let Ok(output) =
+                    read_last_log_file_lines(*task_id, &log_directory, message.lines)
+                {
+                    logs.insert(*task_id, output);
+                }
+            }
+            sizes.insert(*task_id, size);
+        }
+        log_sizes = sizes;
+
+        let serialized = serde_json::to_string(&state)?;
+        if serialized != last_state || !logs.is_empty() {
+            let update = MirrorUpdateMessage {
+                state: Box::new(state),
+                logs,
+            };
+            send_message(Message::MirrorUpdate(update), stream).await?;
+            last_state = serialized;
+        }
+
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    path.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+}
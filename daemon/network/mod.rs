@@ -1,4 +1,5 @@
 pub mod follow_log;
 pub mod message_handler;
+pub mod mirror;
 pub mod response_helper;
 pub mod socket;
@@ -1,17 +1,27 @@
-use std::sync::MutexGuard;
+use std::collections::BTreeMap;
 
-use pueue_lib::network::message::{create_failure_message, create_success_message, Message};
+use pueue_lib::network::message::{
+    create_typed_failure_message, FailureKind, Message, TaskActionResponseMessage,
+};
 use pueue_lib::state::State;
 use pueue_lib::task::Task;
 
+use crate::state_helper::LockedState;
+
 /// Check whether the given group exists. Return an failure message if it doesn't.
-pub fn ensure_group_exists(state: &MutexGuard<State>, group: &str) -> Result<(), Message> {
+#[allow(clippy::result_large_err)]
+pub fn ensure_group_exists(state: &LockedState, group: &str) -> Result<(), Message> {
     if !state.groups.contains_key(group) {
-        return Err(create_failure_message(format!(
-            "Group {} doesn't exists. Use one of these: {:?}",
-            group,
-            state.groups.keys()
-        )));
+        return Err(create_typed_failure_message(
+            FailureKind::GroupNotFound {
+                group: group.to_string(),
+            },
+            format!(
+                "Group {} doesn't exists. Use one of these: {:?}",
+                group,
+                state.groups.keys()
+            ),
+        ));
     }
 
     Ok(())
@@ -22,49 +32,56 @@ pub fn ensure_group_exists(state: &MutexGuard<State>, group: &str) -> Result<(),
 ///
 /// That's why this helper exists, which determines based on a given criterion `filter`
 /// for which tasks the action succeeded and which tasks failed.
+/// `expected` describes the status a task needs to have for the action to succeed.
 pub fn task_action_response_helper<F>(
     message: &str,
     task_ids: Vec<usize>,
+    expected: &str,
     filter: F,
-    state: &MutexGuard<State>,
+    state: &LockedState,
 ) -> Message
 where
     F: Fn(&Task) -> bool,
 {
     // Get all matching/mismatching task_ids for all given ids and statuses.
     let (matching, mismatching) = state.filter_tasks(filter, Some(task_ids));
+    let failed = mismatch_reasons(state, mismatching, expected);
+
+    compile_task_response(message, matching, failed)
+}
 
-    compile_task_response(message, matching, mismatching)
+/// Determine why an action failed for each of the given tasks.
+/// The task either doesn't exist or it isn't in the `expected` status.
+pub fn mismatch_reasons(
+    state: &State,
+    mismatching: Vec<usize>,
+    expected: &str,
+) -> BTreeMap<usize, FailureKind> {
+    mismatching
+        .into_iter()
+        .map(|task_id| {
+            let kind = match state.tasks.get(&task_id) {
+                Some(task) => FailureKind::InvalidState {
+                    expected: expected.to_string(),
+                    actual: task.status.to_string(),
+                },
+                None => FailureKind::TaskNotFound { task_id },
+            };
+            (task_id, kind)
+        })
+        .collect()
 }
 
-/// Compile a response for instructions with multiple tasks ids
-/// A custom message will be combined with a text about all matching tasks
-/// and possibly tasks for which the instruction cannot be executed.
+/// Compile a response for instructions with multiple tasks ids.
+/// It contains the outcome for each task, so clients know exactly which tasks were affected.
 pub fn compile_task_response(
     message: &str,
-    matching: Vec<usize>,
-    mismatching: Vec<usize>,
+    succeeded: Vec<usize>,
+    failed: BTreeMap<usize, FailureKind>,
 ) -> Message {
-    let matching: Vec<String> = matching.iter().map(|id| id.to_string()).collect();
-    let mismatching: Vec<String> = mismatching.iter().map(|id| id.to_string()).collect();
-    let matching_string = matching.join(", ");
-
-    // We don't have any mismatching ids, return the simple message.
-    if mismatching.is_empty() {
-        return create_success_message(format!("{}: {}", message, matching_string));
-    }
-
-    let mismatched_message = "The command failed for tasks";
-    let mismatching_string = mismatching.join(", ");
-
-    // All given ids are invalid.
-    if matching.is_empty() {
-        return create_failure_message(format!("{}: {}", mismatched_message, mismatching_string));
-    }
-
-    // Some ids were valid, some were invalid.
-    create_success_message(format!(
-        "{}: {}\n{}: {}",
-        message, matching_string, mismatched_message, mismatching_string
-    ))
+    Message::TaskActionResponse(TaskActionResponseMessage {
+        text: message.to_string(),
+        succeeded,
+        failed,
+    })
 }
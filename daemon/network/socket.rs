@@ -1,18 +1,34 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::time::{Duration, SystemTime};
 
 use anyhow::{bail, Context, Result};
 use clap::crate_version;
 use crossbeam_channel::Sender;
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
 
 use pueue_lib::error::Error;
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::*;
 use pueue_lib::network::secret::read_shared_secret;
-use pueue_lib::state::SharedState;
+use pueue_lib::settings::VersionMismatch;
 
+use crate::mirror::is_read_only;
 use crate::network::follow_log::handle_follow;
-use crate::network::message_handler::{handle_message, SENDER_ERR};
+use crate::network::message_handler::{force_remove, handle_message, SENDER_ERR};
+use crate::network::mirror::handle_mirror;
+use crate::state_helper::SharedState;
+
+tokio::task_local! {
+    /// This is set while a client connection is being handled.
+    /// The panic hook uses it to distinguish panics of single connections,
+    /// which shouldn't take down the whole daemon.
+    static CONNECTION: ();
+}
+
+/// Check whether the current code runs as part of a client connection.
+pub fn in_client_connection() -> bool {
+    CONNECTION.try_with(|_| ()).is_ok()
+}
 
 /// Poll the listener and accept new incoming connections.
 /// Create a new future to handle the message and spawn it.
@@ -23,7 +39,12 @@ pub async fn accept_incoming(sender: Sender<Message>, state: SharedState) -> Res
         state.settings.shared.clone()
     };
     let listener = get_listener(&shared_settings).await?;
-    let secret = read_shared_secret(&shared_settings.shared_secret_path())?;
+    // Clients that connect via the unix socket may be authenticated by their user id instead.
+    let secret = if shared_settings.use_peer_credentials() {
+        None
+    } else {
+        Some(read_shared_secret(&shared_settings.shared_secret_path())?)
+    };
 
     loop {
         // Poll incoming connections.
@@ -40,7 +61,19 @@ pub async fn accept_incoming(sender: Sender<Message>, state: SharedState) -> Res
         let state_clone = state.clone();
         let secret_clone = secret.clone();
         tokio::spawn(async move {
-            let _result = handle_incoming(stream, sender_clone, state_clone, secret_clone).await;
+            let connection = tokio::spawn(CONNECTION.scope(
+                (),
+                handle_incoming(stream, sender_clone, state_clone.clone(), secret_clone),
+            ));
+
+            // A panic only drops this connection. If the state has been locked at that time,
+            // it has to be unlocked again, since the daemon would be unusable otherwise.
+            if let Err(err) = connection.await {
+                if err.is_panic() {
+                    error!("Dropped client connection due to a panic");
+                    state_clone.clear_poison();
+                }
+            }
         });
     }
 }
@@ -52,9 +85,10 @@ async fn handle_incoming(
     mut stream: GenericStream,
     sender: Sender<Message>,
     state: SharedState,
-    secret: Vec<u8>,
+    secret: Option<Vec<u8>>,
 ) -> Result<()> {
-    // Receive the secret once and check, whether the client is allowed to connect
+    // Receive the secret once and check, whether the client is allowed to connect.
+    // The payload is ignored, if the client is authenticated via its peer credentials.
     let payload_bytes = receive_bytes(&mut stream).await?;
 
     // Didn't receive any bytes. The client disconnected.
@@ -65,12 +99,21 @@ async fn handle_incoming(
 
     let start = SystemTime::now();
 
-    // Return immediately, if we got a wrong secret from the client.
-    if payload_bytes != secret {
-        warn!(
-            "Received invalid secret: {}",
-            String::from_utf8(payload_bytes)?
-        );
+    let authenticated = match &secret {
+        Some(secret) => &payload_bytes == secret,
+        None => stream.peer_is_same_user(),
+    };
+
+    // Return immediately, if we got a wrong secret or the client belongs to another user.
+    if !authenticated {
+        if secret.is_some() {
+            warn!(
+                "Received invalid secret: {}",
+                String::from_utf8(payload_bytes)?
+            );
+        } else {
+            warn!("Rejected client, which belongs to a different user");
+        }
 
         // Wait for 1 second before closing the socket, when getting a invalid secret.
         // This invalidates any timing attacks.
@@ -89,9 +132,13 @@ async fn handle_incoming(
 
     // Save the directory for convenience purposes and to prevent continuously
     // locking the state in the streaming loop.
-    let pueue_directory = {
+    let (pueue_directory, version_mismatch, is_mirror) = {
         let state = state.lock().unwrap();
-        state.settings.shared.pueue_directory().clone()
+        (
+            state.settings.shared.pueue_directory(),
+            state.settings.shared.version_mismatch,
+            state.settings.daemon.mirror.is_some(),
+        )
     };
 
     loop {
@@ -113,8 +160,51 @@ async fn handle_incoming(
             return Ok(());
         }
 
-        let message = message_result?;
-        debug!("Received instruction: {:?}", message);
+        // Unwrap traced requests. Older clients send their messages without a request id.
+        let (request_id, client_version, message) = match message_result? {
+            Message::Traced(traced) => (
+                Some(traced.request_id),
+                traced.client_version,
+                *traced.message,
+            ),
+            message => (None, None, message),
+        };
+        match &request_id {
+            Some(request_id) => {
+                info!("Received request {}", request_id);
+                debug!("Received instruction {}: {:?}", request_id, message);
+            }
+            None => debug!("Received instruction: {:?}", message),
+        }
+
+        // Clients with a different major or minor version might send messages,
+        // which are interpreted differently by this daemon.
+        if let Some(client_version) = client_version {
+            if !versions_compatible(&client_version, crate_version!()) {
+                let text = format!(
+                    "The client runs version {}, which isn't compatible with the daemon's version {}.",
+                    client_version,
+                    crate_version!()
+                );
+                warn!("{}", text);
+                if version_mismatch == VersionMismatch::Refuse {
+                    let response =
+                        create_typed_failure_message(FailureKind::PermissionDenied, text);
+                    send_message(response, &mut stream).await?;
+                    continue;
+                }
+            }
+        }
+
+        // A mirror only shows the state of its primary daemon.
+        if is_mirror && !is_read_only(&message) {
+            let response = create_typed_failure_message(
+                FailureKind::PermissionDenied,
+                "This daemon is a read-only mirror. Send your request to the primary daemon.",
+            );
+            send_message(response, &mut stream).await?;
+            continue;
+        }
 
         let response = match message {
             // The client requested the output of a task.
@@ -122,6 +212,13 @@ async fn handle_incoming(
             Message::StreamRequest(message) => {
                 handle_follow(&pueue_directory, &mut stream, &state, message).await?
             }
+            // Another daemon mirrors this one. The state is continuously sent to it.
+            Message::MirrorRequest(message) => {
+                handle_mirror(&pueue_directory, &mut stream, &state, message).await?
+            }
+            // Killed tasks can only be removed once they've been reaped by the task handler,
+            // which is why we have to wait for it without blocking the other connections.
+            Message::ForceRemove(task_ids) => force_remove(task_ids, &sender, &state).await,
             // Initialize the shutdown procedure.
             // The message is forwarded to the TaskHandler, which is responsible for
             // gracefully shutting down.
@@ -130,7 +227,13 @@ async fn handle_incoming(
             // Otherwise it might happen, that the daemon shuts down too fast and we aren't
             // capable of actually sending the message back to the client.
             Message::DaemonShutdown(shutdown_type) => {
-                let response = create_success_message("Daemon is shutting down");
+                let response = if shutdown_type == Shutdown::Drain {
+                    create_success_message(
+                        "Daemon is shutting down, once all running tasks finished",
+                    )
+                } else {
+                    create_success_message("Daemon is shutting down")
+                };
                 send_message(response, &mut stream).await?;
 
                 // Notify the task handler.
@@ -142,11 +245,98 @@ async fn handle_incoming(
             }
             _ => {
                 // Process a normal message.
-                handle_message(message, &sender, &state)
+                handle_message_safely(message, &sender, &state)
+            }
+        };
+
+        // Attach the request id to failures, so they can be found in the daemon's log.
+        let response = match (response, &request_id) {
+            (Message::Failure(mut failure), Some(request_id)) => {
+                warn!("Request {} failed: {}", request_id, failure);
+                failure.text = format!("{} (request id: {})", failure.text, request_id);
+                Message::Failure(failure)
             }
+            (response, _) => response,
         };
 
         // Respond to the client.
         send_message(response, &mut stream).await?;
     }
 }
+
+/// Process a message, but catch any panic that happens while doing so.
+/// A single malformed request then only results in a failure response for this client,
+/// instead of taking down the daemon for everyone.
+///
+/// The panic itself, including its backtrace, is logged by the daemon's panic hook.
+fn handle_message_safely(
+    message: Message,
+    sender: &Sender<Message>,
+    state: &SharedState,
+) -> Message {
+    catch_handler_panic(state, || handle_message(message, sender, state))
+}
+
+/// Run a message handler and turn a panic into a failure response.
+///
+/// If the handler panicked while it held the lock, the state is poisoned and might only contain
+/// a part of the request's changes. We still continue with it, since:
+/// - Handlers never leave the state structurally broken. Tasks and groups are only ever
+///   inserted or removed as a whole, and everything that refers to them by id or name
+///   looks them up and handles their absence.
+/// - The worst case is a partially applied request, which the user sees in `status` and can
+///   fix or retry. Its changes are saved with the next change of the state, like any other.
+/// - The alternative is to take down the daemon, which would stop supervising all running
+///   tasks and lose all changes, whose save has been delayed.
+fn catch_handler_panic(state: &SharedState, handler: impl FnOnce() -> Message) -> Message {
+    match catch_unwind(AssertUnwindSafe(handler)) {
+        Ok(response) => response,
+        Err(_) => {
+            state.clear_poison();
+            create_failure_message(
+                "The daemon failed to process this request due to an internal error. Check the daemon's log for details.",
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam_channel::unbounded;
+    use pretty_assertions::assert_eq;
+
+    use crate::network::message_handler::fixtures::*;
+
+    use super::*;
+
+    #[test]
+    fn test_panic_in_handler() {
+        let (state, _tempdir) = get_stub_state();
+        let (sender, _receiver) = unbounded();
+
+        // The handler panics in the middle of its changes, while it holds the lock.
+        let response = catch_handler_panic(&state, || {
+            let mut state = state.lock().unwrap();
+            state.tasks.remove(&0);
+            panic!("Handler failed");
+        });
+        assert!(matches!(response, Message::Failure(_)));
+        assert!(!state.is_poisoned());
+
+        // Requests of other clients are still processed and see the partial change.
+        let response = handle_message_safely(Message::Status, &sender, &state);
+        let status = match response {
+            Message::StatusResponse(status) => status,
+            _ => panic!("Expected a status response, got {:?}", response),
+        };
+        assert_eq!(
+            status.tasks.keys().cloned().collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+
+        // The state can still be changed.
+        let response = handle_message_safely(Message::Remove(vec![1]), &sender, &state);
+        assert!(matches!(response, Message::TaskActionResponse(_)));
+        assert!(!state.lock().unwrap().tasks.contains_key(&1));
+    }
+}
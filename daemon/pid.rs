@@ -1,31 +1,125 @@
-use std::fs::File;
-use std::io::{Read, Write};
+use std::fs::{metadata, read_to_string, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::time::Duration;
 
 use anyhow::{bail, Context, Result};
+use log::warn;
 
-use crate::platform::process_helper::process_exists;
+use crate::platform::process_helper::{process_exists, process_name, process_start_time};
 
 /// Read a PID file and throw an error, if another daemon instance is still running.
+/// Pid files of crashed daemons are left behind. They're simply overwritten.
 fn check_for_running_daemon(pid_path: &Path) -> Result<()> {
-    let mut file = File::open(&pid_path).context("Failed to open PID file")?;
-    let mut pid = String::new();
-    file.read_to_string(&mut pid)
-        .context("Failed to read PID file")?;
+    let content = read_to_string(pid_path).context("Failed to read PID file")?;
+    let pid: u32 = match content.trim().parse() {
+        Ok(pid) => pid,
+        Err(_) => {
+            warn!("Replacing invalid pid file {:?}: {:?}", pid_path, content);
+            return Ok(());
+        }
+    };
 
-    let pid: u32 = pid
-        .parse()
-        .context(format!("Failed to parse PID from file: {:?}", pid_path))?;
+    match stale_reason(pid, pid_path) {
+        Some(reason) => {
+            warn!("Replacing stale pid file {:?}: {}", pid_path, reason);
+            Ok(())
+        }
+        None => bail!(
+            "Another daemon is already running with pid {}.\n\
+              Please stop it via `pueue shutdown` beforehand. \
+              If it isn't a pueue daemon, delete the pid file manually: {:?}",
+            pid,
+            pid_path
+        ),
+    }
+}
 
-    if process_exists(pid) {
-        bail!(
-            "Pid file already exists and another daemon seems to be running.\n\
-              Please stop the daemon beforehand or delete the file manually: {:?}",
-            &pid_path
-        );
+/// Check whether the process of a pid file is an active daemon.
+/// If it isn't, the reason is returned. The process might have crashed
+/// or its pid might have been reused by another process in the meantime.
+fn stale_reason(pid: u32, pid_path: &Path) -> Option<String> {
+    if !process_exists(pid) {
+        return Some(format!("process {} doesn't exist", pid));
     }
 
-    Ok(())
+    if let Some(name) = process_name(pid) {
+        if !is_daemon_name(&name) {
+            return Some(format!("process {} is {}", pid, name));
+        }
+    }
+
+    // The daemon writes its pid file right after it has been started.
+    // Allow some imprecision, as start times are only precise to about a second.
+    let written = metadata(pid_path).and_then(|metadata| metadata.modified());
+    if let (Some(started), Ok(written)) = (process_start_time(pid), written) {
+        if started > written + Duration::from_secs(2) {
+            return Some(format!(
+                "process {} has been started after the pid file was written",
+                pid
+            ));
+        }
+    }
+
+    None
+}
+
+/// Whether a process name belongs to a daemon, i.e. `pueued` or the current executable.
+/// Names might be truncated, e.g. to 15 characters on Linux. Empty names are inconclusive.
+fn is_daemon_name(name: &str) -> bool {
+    if name.is_empty() || name.starts_with("pueued") {
+        return true;
+    }
+
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .is_some_and(|own_name| own_name.starts_with(name))
+}
+
+/// Lock the pueue directory, so no other daemon can use it at the same time.
+/// This prevents two daemons from writing the same state, e.g. if one has been started by
+/// a service manager and another one by hand.
+///
+/// The lock is released by the operating system, once the returned file is dropped or the
+/// daemon exits, even if it crashed. Thereby, the lock file itself is never removed.
+pub fn lock_pueue_directory(pueue_dir: &Path) -> Result<File> {
+    let lock_path = pueue_dir.join("pueue.lock");
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .context(format!("Failed to open lock file {:?}", lock_path))?;
+
+    match file.try_lock() {
+        Ok(()) => (),
+        Err(TryLockError::WouldBlock) => {
+            // The owner writes its pid into the lock file, once it acquired the lock.
+            let mut owner = String::new();
+            file.read_to_string(&mut owner)
+                .context("Failed to read lock file")?;
+            bail!(
+                "Another daemon is already running with pid {} and holds the lock {:?}.\n\
+                  Please stop it via `pueue shutdown` beforehand.",
+                owner.trim(),
+                lock_path
+            );
+        }
+        Err(TryLockError::Error(err)) => {
+            return Err(err).context(format!("Failed to lock {:?}", lock_path));
+        }
+    }
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(std::process::id().to_string().as_bytes())?;
+
+    Ok(file)
 }
 
 /// Create a file containing the current pid of the daemon's main process.
@@ -1,6 +1,7 @@
 use std::convert::TryInto;
 use std::path::Path;
 use std::process::{Child, Command};
+use std::time::SystemTime;
 
 use anyhow::Result;
 use log::debug;
@@ -11,6 +12,7 @@ use nix::{
 
 use crate::task_handler::ProcessAction;
 use pueue_lib::network::message::Signal as InternalSignal;
+use pueue_lib::task::WindowsShell;
 
 pub fn compile_shell_command(command_string: &str) -> Command {
     let mut command = Command::new("sh");
@@ -19,6 +21,15 @@ pub fn compile_shell_command(command_string: &str) -> Command {
     command
 }
 
+/// Build the command of a task. The shell can only be chosen on Windows.
+pub fn compile_task_command(
+    command_string: &str,
+    _path: &str,
+    _shell: Option<WindowsShell>,
+) -> Command {
+    compile_shell_command(command_string)
+}
+
 fn map_action_to_signal(action: &ProcessAction) -> Signal {
     match action {
         ProcessAction::Pause => Signal::SIGSTOP,
@@ -93,9 +104,41 @@ fn send_signal_to_process(pid: u32, signal: Signal, _children: bool) -> Result<b
     Ok(true)
 }
 
-/// Check, whether a specific process is exists or not
+/// Check, whether a specific process is exists or not.
+/// Zombie processes, which haven't been reaped yet, don't count.
 pub fn process_exists(pid: u32) -> bool {
-    Path::new(&format!("/proc/{}", pid)).exists()
+    match ps(pid, "stat=") {
+        Some(stat) => !stat.starts_with('Z'),
+        None => false,
+    }
+}
+
+/// The name of a process' executable, e.g. `pueued`.
+pub fn process_name(pid: u32) -> Option<String> {
+    let command = ps(pid, "comm=")?;
+    Path::new(&command)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// The start time of a process isn't determined on macOS.
+pub fn process_start_time(_pid: u32) -> Option<SystemTime> {
+    None
+}
+
+/// Get a single field of a process via `ps`.
+/// There's no `/proc` on macOS.
+fn ps(pid: u32, field: &str) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", field, "-p"])
+        .arg(pid.to_string())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 #[cfg(test)]
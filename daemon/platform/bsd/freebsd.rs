@@ -0,0 +1,53 @@
+use std::ffi::CStr;
+use std::time::{Duration, UNIX_EPOCH};
+
+use libc::{kinfo_proc, CTL_KERN, KERN_PROC, KERN_PROC_ARGS, KERN_PROC_PID, KERN_PROC_PROC};
+
+use super::sysctl::{self, ProcessInfo};
+
+/// All processes of the system via `kern.proc.proc`.
+pub fn processes() -> std::io::Result<Vec<ProcessInfo>> {
+    let processes: Vec<kinfo_proc> =
+        sysctl::read(256, |_| vec![CTL_KERN, KERN_PROC, KERN_PROC_PROC, 0])?;
+
+    Ok(processes.iter().map(process_info).collect())
+}
+
+/// A single process via `kern.proc.pid.<pid>`.
+pub fn process(pid: i32) -> Option<ProcessInfo> {
+    let processes: Vec<kinfo_proc> =
+        sysctl::read(1, |_| vec![CTL_KERN, KERN_PROC, KERN_PROC_PID, pid]).ok()?;
+
+    processes.first().map(process_info)
+}
+
+/// The command line of a process via `kern.proc.args.<pid>`.
+/// The kernel returns the arguments as a list of null terminated strings.
+pub fn cmdline(pid: i32) -> Option<Vec<String>> {
+    let buffer: Vec<u8> =
+        sysctl::read(4096, |_| vec![CTL_KERN, KERN_PROC, KERN_PROC_ARGS, pid]).ok()?;
+
+    Some(
+        buffer
+            .split(|byte| *byte == 0)
+            .filter(|argument| !argument.is_empty())
+            .map(|argument| String::from_utf8_lossy(argument).into_owned())
+            .collect(),
+    )
+}
+
+fn process_info(process: &kinfo_proc) -> ProcessInfo {
+    // Safety: The kernel always terminates the name with a null byte.
+    let name = unsafe { CStr::from_ptr(process.ki_comm.as_ptr()) };
+    let start_time = UNIX_EPOCH
+        + Duration::from_secs(process.ki_start.tv_sec as u64)
+        + Duration::from_micros(process.ki_start.tv_usec as u64);
+
+    ProcessInfo {
+        pid: process.ki_pid,
+        ppid: process.ki_ppid,
+        is_zombie: process.ki_stat == libc::SZOMB,
+        name: name.to_string_lossy().into_owned(),
+        start_time,
+    }
+}
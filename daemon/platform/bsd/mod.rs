@@ -0,0 +1,11 @@
+#[cfg(target_os = "freebsd")]
+mod freebsd;
+#[cfg(target_os = "openbsd")]
+mod openbsd;
+pub mod process_helper;
+mod sysctl;
+
+#[cfg(target_os = "freebsd")]
+use self::freebsd as kinfo;
+#[cfg(target_os = "openbsd")]
+use self::openbsd as kinfo;
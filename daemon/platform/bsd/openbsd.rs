@@ -0,0 +1,77 @@
+use std::ffi::CStr;
+use std::mem::size_of;
+use std::time::{Duration, UNIX_EPOCH};
+
+use libc::{
+    c_char, c_int, kinfo_proc, CTL_KERN, KERN_PROC, KERN_PROC_ALL, KERN_PROC_ARGS, KERN_PROC_ARGV,
+    KERN_PROC_PID,
+};
+
+use super::sysctl::{self, ProcessInfo};
+
+/// Process states from `sys/proc.h`, which aren't exported by libc.
+const SZOMB: i8 = 5;
+const SDEAD: i8 = 6;
+
+/// The name of a `kern.proc` value. OpenBSD expects the size of a single entry and the
+/// amount of entries, that fit into the buffer.
+fn kern_proc(operation: c_int, argument: c_int, capacity: usize) -> Vec<c_int> {
+    vec![
+        CTL_KERN,
+        KERN_PROC,
+        operation,
+        argument,
+        size_of::<kinfo_proc>() as c_int,
+        capacity as c_int,
+    ]
+}
+
+/// All processes of the system via `kern.proc.all`.
+pub fn processes() -> std::io::Result<Vec<ProcessInfo>> {
+    let processes: Vec<kinfo_proc> =
+        sysctl::read(256, |capacity| kern_proc(KERN_PROC_ALL, 0, capacity))?;
+
+    Ok(processes.iter().map(process_info).collect())
+}
+
+/// A single process via `kern.proc.pid`.
+pub fn process(pid: i32) -> Option<ProcessInfo> {
+    let processes: Vec<kinfo_proc> =
+        sysctl::read(1, |capacity| kern_proc(KERN_PROC_PID, pid, capacity)).ok()?;
+
+    processes.first().map(process_info)
+}
+
+/// The command line of a process via `kern.proc_args.<pid>.argv`.
+/// The kernel returns a null terminated list of pointers to the arguments, which are
+/// placed behind it in the same buffer.
+pub fn cmdline(pid: i32) -> Option<Vec<String>> {
+    let buffer: Vec<*const c_char> =
+        sysctl::read(512, |_| vec![CTL_KERN, KERN_PROC_ARGS, pid, KERN_PROC_ARGV]).ok()?;
+
+    let arguments = buffer
+        .iter()
+        .take_while(|argument| !argument.is_null())
+        // Safety: The pointers point into the buffer and the kernel terminates all arguments.
+        .map(|argument| unsafe { CStr::from_ptr(*argument) })
+        .map(|argument| argument.to_string_lossy().into_owned())
+        .collect();
+
+    Some(arguments)
+}
+
+fn process_info(process: &kinfo_proc) -> ProcessInfo {
+    // Safety: The kernel always terminates the name with a null byte.
+    let name = unsafe { CStr::from_ptr(process.p_comm.as_ptr()) };
+    let start_time = UNIX_EPOCH
+        + Duration::from_secs(process.p_ustart_sec)
+        + Duration::from_micros(process.p_ustart_usec as u64);
+
+    ProcessInfo {
+        pid: process.p_pid,
+        ppid: process.p_ppid,
+        is_zombie: process.p_stat == SZOMB || process.p_stat == SDEAD,
+        name: name.to_string_lossy().into_owned(),
+        start_time,
+    }
+}
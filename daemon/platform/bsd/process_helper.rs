@@ -0,0 +1,157 @@
+use std::process::{Child, Command};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use log::warn;
+
+use super::kinfo;
+use crate::platform::process_tree::{
+    self, map_action_to_signal, map_internal_signal_to_nix_signal, send_signal_to_child,
+    ProcessTable,
+};
+use crate::task_handler::ProcessAction;
+use pueue_lib::network::message::Signal as InternalSignal;
+use pueue_lib::task::WindowsShell;
+
+pub fn compile_shell_command(command_string: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(command_string);
+
+    command
+}
+
+/// Build the command of a task. The shell can only be chosen on Windows.
+pub fn compile_task_command(
+    command_string: &str,
+    _path: &str,
+    _shell: Option<WindowsShell>,
+) -> Command {
+    compile_shell_command(command_string)
+}
+
+/// The process table of FreeBSD and OpenBSD, which is read via `sysctl`.
+struct Sysctl;
+
+impl ProcessTable for Sysctl {
+    fn children(&self, pid: i32) -> Vec<i32> {
+        let all_processes = match kinfo::processes() {
+            Err(error) => {
+                warn!("Failed to get full process list: {}", error);
+                return Vec::new();
+            }
+            Ok(processes) => processes,
+        };
+
+        all_processes
+            .into_iter()
+            .filter(|process| process.ppid == pid)
+            .map(|process| process.pid)
+            .collect()
+    }
+
+    fn cmdline(&self, pid: i32) -> Option<Vec<String>> {
+        kinfo::cmdline(pid)
+    }
+
+    fn is_alive(&self, pid: i32) -> bool {
+        match kinfo::process(pid) {
+            Some(process) => !process.is_zombie,
+            None => false,
+        }
+    }
+}
+
+/// Convenience wrapper around `send_signal_to_child` for raw unix signals.
+/// Its purpose is to hide platform specific logic.
+pub fn send_internal_signal_to_child(
+    child: &Child,
+    signal: InternalSignal,
+    send_to_children: bool,
+) -> Result<bool> {
+    let signal = map_internal_signal_to_nix_signal(signal);
+    send_signal_to_child(&Sysctl, child, signal, send_to_children)
+}
+
+/// Convenience wrapper around `send_signal_to_child` for internal actions on processes.
+/// Its purpose is to hide platform specific logic.
+pub fn run_action_on_child(
+    child: &Child,
+    action: &ProcessAction,
+    send_to_children: bool,
+) -> Result<bool> {
+    let signal = map_action_to_signal(action);
+    send_signal_to_child(&Sysctl, child, signal, send_to_children)
+}
+
+/// Safely kill a child process and, depending on the task, its children.
+/// Check the docstring of `process_tree::signal_targets` for the processes, that are killed.
+///
+/// Returns `true`, if everything went alright
+/// Returns `false`, if the process went away while we tried to send the signal.
+pub fn kill_child(task_id: usize, child: &mut Child, kill_children: bool) -> bool {
+    process_tree::kill_child(&Sysctl, task_id, child, kill_children)
+}
+
+/// Check, whether a specific process is exists or not
+pub fn process_exists(pid: u32) -> bool {
+    Sysctl.is_alive(pid as i32)
+}
+
+/// The name of a process' executable, e.g. `pueued`.
+/// The kernel truncates it to 19 (FreeBSD) or 23 (OpenBSD) characters.
+pub fn process_name(pid: u32) -> Option<String> {
+    kinfo::process(pid as i32).map(|process| process.name)
+}
+
+/// The time at which a process has been started.
+pub fn process_start_time(pid: u32) -> Option<SystemTime> {
+    kinfo::process(pid as i32).map(|process| process.start_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::platform::process_tree::did_process_spawn_shell;
+
+    #[test]
+    fn test_own_process() {
+        let pid = std::process::id();
+        assert!(process_exists(pid));
+        assert!(process_start_time(pid).unwrap() <= SystemTime::now());
+    }
+
+    #[test]
+    /// Ensure a `sh -c` command will be properly killed without detached processes.
+    fn test_shell_command_is_killed() {
+        let mut child = compile_shell_command("sleep 60 & sleep 60 && echo 'this is a test'")
+            .spawn()
+            .expect("Failed to spawn echo");
+        let pid: i32 = child.id().try_into().unwrap();
+        // Sleep a little to give everything a chance to spawn.
+        sleep(Duration::from_millis(500));
+
+        assert_eq!(process_name(pid as u32).unwrap(), "sh");
+        assert!(did_process_spawn_shell(&Sysctl, pid).unwrap());
+
+        // Get all child processes, so we can make sure they no longer exist afterwards.
+        let child_processes = Sysctl.children(pid);
+        assert_eq!(child_processes.len(), 2);
+
+        assert!(kill_child(0, &mut child, false));
+        child.wait().unwrap();
+
+        // Sleep a little to give all processes time to shutdown.
+        sleep(Duration::from_millis(500));
+
+        assert!(!process_exists(pid as u32));
+        for child_process in child_processes {
+            assert!(!process_exists(child_process as u32));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::io;
+use std::mem::size_of;
+use std::ptr;
+use std::time::SystemTime;
+
+use libc::{c_int, c_uint, c_void};
+
+/// The information of the process table, that's needed by Pueue.
+pub struct ProcessInfo {
+    pub pid: i32,
+    pub ppid: i32,
+    /// Zombies have exited, but haven't been reaped by their parent yet.
+    pub is_zombie: bool,
+    /// The name of the process' executable, as it's truncated by the kernel.
+    pub name: String,
+    pub start_time: SystemTime,
+}
+
+/// The largest buffer, that's used to read a sysctl value.
+const MAX_BUFFER_SIZE: usize = 64 * 1024 * 1024;
+
+/// Read a sysctl value as a list of `T`.
+///
+/// Values like the process table change between two calls, which is why the value isn't
+/// measured beforehand. Instead, the buffer starts with `capacity` elements and is doubled,
+/// until the whole value fits into it.
+/// `mib` builds the name of the value for a given capacity, since some names contain it.
+pub fn read<T: Copy>(mut capacity: usize, mib: impl Fn(usize) -> Vec<c_int>) -> io::Result<Vec<T>> {
+    let element_size = size_of::<T>().max(1);
+    loop {
+        let mut buffer: Vec<T> = Vec::with_capacity(capacity);
+        let mut length = capacity * element_size;
+        let mib = mib(capacity);
+
+        // Safety: The kernel writes at most `length` bytes into the buffer, which has been
+        // allocated with exactly that size, and reports how many bytes have been written.
+        let result = unsafe {
+            libc::sysctl(
+                mib.as_ptr(),
+                mib.len() as c_uint,
+                buffer.as_mut_ptr() as *mut c_void,
+                &mut length,
+                ptr::null_mut(),
+                0,
+            )
+        };
+
+        if result == 0 {
+            unsafe { buffer.set_len((length + element_size - 1) / element_size) };
+            return Ok(buffer);
+        }
+
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::ENOMEM) || length >= MAX_BUFFER_SIZE {
+            return Err(error);
+        }
+        capacity *= 2;
+    }
+}
@@ -0,0 +1,98 @@
+use std::fs::{create_dir, read_to_string, remove_dir, write, OpenOptions};
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use log::debug;
+
+/// The period in microseconds, in which a task may use its CPU limit. This is the kernel's default.
+const CPU_PERIOD: u64 = 100_000;
+
+/// The cgroup of a task, e.g. `<cgroup_root>/pueue_task_3`.
+fn task_cgroup(root: &Path, task_id: usize) -> PathBuf {
+    root.join(format!("pueue_task_{}", task_id))
+}
+
+/// Enable the memory and CPU controllers for the cgroups of all tasks.
+/// This fails, if processes are running directly inside the root cgroup.
+pub fn enable_controllers(root: &Path) -> Result<()> {
+    write(root.join("cgroup.subtree_control"), "+memory +cpu").context(format!(
+        "Failed to enable the memory and cpu controllers of cgroup {:?}",
+        root
+    ))
+}
+
+/// Create the cgroup of a task and apply its limits.
+/// The cgroup of a previous run of the task is replaced.
+pub fn create_task_cgroup(
+    root: &Path,
+    task_id: usize,
+    memory_limit: Option<u64>,
+    cpu_limit: Option<f64>,
+) -> Result<PathBuf> {
+    let cgroup = task_cgroup(root, task_id);
+    if cgroup.exists() {
+        remove_dir(&cgroup).context(format!("Failed to remove old cgroup {:?}", cgroup))?;
+    }
+    create_dir(&cgroup).context(format!("Failed to create cgroup {:?}", cgroup))?;
+
+    if let Err(err) = set_limits(&cgroup, memory_limit, cpu_limit) {
+        let _ = remove_dir(&cgroup);
+        return Err(err);
+    }
+
+    Ok(cgroup)
+}
+
+/// Write the limits into the interface files of the cgroup.
+/// They only exist, if the respective controller is enabled.
+fn set_limits(cgroup: &Path, memory_limit: Option<u64>, cpu_limit: Option<f64>) -> Result<()> {
+    if let Some(memory_limit) = memory_limit {
+        write(cgroup.join("memory.max"), memory_limit.to_string())
+            .context("Failed to set the memory limit")?;
+    }
+    if let Some(cpu_limit) = cpu_limit {
+        let quota = (cpu_limit * CPU_PERIOD as f64).round() as u64;
+        write(cgroup.join("cpu.max"), format!("{} {}", quota, CPU_PERIOD))
+            .context("Failed to set the CPU limit")?;
+    }
+
+    Ok(())
+}
+
+/// Move the process into the cgroup, after it has been forked and before the command is executed.
+/// This way, none of its child processes can escape the cgroup.
+pub fn spawn_in_cgroup(command: &mut Command, cgroup: &Path) -> Result<()> {
+    let procs = OpenOptions::new()
+        .write(true)
+        .open(cgroup.join("cgroup.procs"))
+        .context(format!("Failed to open cgroup {:?}", cgroup))?;
+
+    // Writing `0` moves the writing process itself.
+    // Nothing is allocated in between fork and exec, which could deadlock.
+    unsafe {
+        command.pre_exec(move || (&procs).write_all(b"0"));
+    }
+
+    Ok(())
+}
+
+/// Remove the cgroup of a finished task and check whether any of its processes ran out of memory.
+/// The cgroup is kept, while processes of the task are still running in the background.
+pub fn finish_task_cgroup(root: &Path, task_id: usize) -> bool {
+    let cgroup = task_cgroup(root, task_id);
+    let out_of_memory = match read_to_string(cgroup.join("memory.events")) {
+        Ok(events) => events
+            .lines()
+            .any(|line| matches!(line.split_once(' '), Some(("oom_kill", count)) if count != "0")),
+        Err(_) => false,
+    };
+
+    if let Err(err) = remove_dir(&cgroup) {
+        debug!("Failed to remove cgroup {:?}: {}", cgroup, err);
+    }
+
+    out_of_memory
+}
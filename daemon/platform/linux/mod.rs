@@ -1 +1,7 @@
+/// Resource limits of tasks via cgroup v2.
+#[cfg(target_os = "linux")]
+pub mod cgroup;
 pub mod process_helper;
+/// Pausing tasks while the system is suspended.
+#[cfg(target_os = "linux")]
+pub mod suspend;
@@ -1,16 +1,17 @@
-use std::convert::TryInto;
 use std::process::{Child, Command};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{bail, Result};
-use log::{debug, info, warn};
-use nix::{
-    sys::signal::{self, Signal},
-    unistd::Pid,
-};
+use anyhow::Result;
+use log::warn;
 use procfs::process::{all_processes, Process};
 
+use crate::platform::process_tree::{
+    self, map_action_to_signal, map_internal_signal_to_nix_signal, send_signal_to_child,
+    ProcessTable,
+};
 use crate::task_handler::ProcessAction;
 use pueue_lib::network::message::Signal as InternalSignal;
+use pueue_lib::task::WindowsShell;
 
 pub fn compile_shell_command(command_string: &str) -> Command {
     let mut command = Command::new("sh");
@@ -19,20 +20,44 @@ pub fn compile_shell_command(command_string: &str) -> Command {
     command
 }
 
-fn map_action_to_signal(action: &ProcessAction) -> Signal {
-    match action {
-        ProcessAction::Pause => Signal::SIGSTOP,
-        ProcessAction::Resume => Signal::SIGCONT,
-    }
+/// Build the command of a task. The shell can only be chosen on Windows.
+pub fn compile_task_command(
+    command_string: &str,
+    _path: &str,
+    _shell: Option<WindowsShell>,
+) -> Command {
+    compile_shell_command(command_string)
 }
 
-fn map_internal_signal_to_nix_signal(signal: InternalSignal) -> Signal {
-    match signal {
-        InternalSignal::SigKill => Signal::SIGKILL,
-        InternalSignal::SigInt => Signal::SIGINT,
-        InternalSignal::SigTerm => Signal::SIGTERM,
-        InternalSignal::SigCont => Signal::SIGCONT,
-        InternalSignal::SigStop => Signal::SIGSTOP,
+/// The process table of Linux, which is read from `/proc`.
+struct Procfs;
+
+impl ProcessTable for Procfs {
+    fn children(&self, pid: i32) -> Vec<i32> {
+        let all_processes = match all_processes() {
+            Err(error) => {
+                warn!("Failed to get full process list: {}", error);
+                return Vec::new();
+            }
+            Ok(processes) => processes,
+        };
+
+        all_processes
+            .into_iter()
+            .filter(|process| process.stat.ppid == pid)
+            .map(|process| process.pid)
+            .collect()
+    }
+
+    fn cmdline(&self, pid: i32) -> Option<Vec<String>> {
+        Process::new(pid).ok()?.cmdline().ok()
+    }
+
+    fn is_alive(&self, pid: i32) -> bool {
+        match Process::new(pid) {
+            Ok(process) => process.is_alive(),
+            Err(_) => false,
+        }
     }
 }
 
@@ -44,7 +69,7 @@ pub fn send_internal_signal_to_child(
     send_to_children: bool,
 ) -> Result<bool> {
     let signal = map_internal_signal_to_nix_signal(signal);
-    send_signal_to_child(child, signal, send_to_children)
+    send_signal_to_child(&Procfs, child, signal, send_to_children)
 }
 
 /// Convenience wrapper around `send_signal_to_child` for internal actions on processes.
@@ -55,251 +80,54 @@ pub fn run_action_on_child(
     send_to_children: bool,
 ) -> Result<bool> {
     let signal = map_action_to_signal(action);
-    send_signal_to_child(child, signal, send_to_children)
-}
-
-/// Send a signal to one of Pueue's child process handles.
-///
-/// There are two scenarios:
-///
-/// **Normal case**
-///
-/// A task, such as `sleep 60` get's spawned by the posix shell `sh`.
-/// This results in the process `sh -c 'sleep 60'`.
-/// Since the posix shell doesn't propagate any process signals to its children, we have to:
-/// 1. Send the signal to the shell.
-/// 2. Send the signal directly to the children.
-///     In our case this would be the `sleep 60` child process.
-///
-/// If the user also want's to send the signal to all child processes of the task,
-/// we have to get all child-processes of the child process.
-///
-/// **Special case**
-///
-/// The posix shell `sh` has some some inconsistent behavior.
-/// In some circumstances and environments, the `sh -c $command` doesn't spawn a `sh` process with a
-/// `$command` child-process, but rather spawns the `$command` as a top-level process directly.
-///
-/// This makes things a bit more complicated, since we have to find out whether a shell is spawned
-/// or not. If a shell is spawned, we do the **Normal case** handling.
-///
-/// If **no** shell is spawned, we have to send the signal to the top-level process only.
-///
-/// If the user also want's to send the signal to all child processes of the task,
-/// we have to get all child-processes of that `$command` process. and send them the signal.
-///
-/// Returns `Ok(true)`, if everything went alright
-/// Returns `Ok(false)`, if the process went away while we tried to send the signal.
-pub fn send_signal_to_child(child: &Child, signal: Signal, send_to_children: bool) -> Result<bool> {
-    let pid: i32 = child.id().try_into().unwrap();
-    // Check whether this process actually spawned a shell.
-    let is_shell = if let Ok(is_shell) = did_process_spawn_shell(pid) {
-        is_shell
-    } else {
-        return Ok(false);
-    };
-
-    if is_shell {
-        // If it's a shell, we have to send the signal to the actual shell and to all it's children.
-        // There might be multiple children, for instance, when users use the `&` operator.
-        // If the `send_to_children` flag is given, the
-
-        // Get all children before sending the signal to the parent process.
-        // Otherwise the parent might go away and we'll no longer be able to access the children.
-        let shell_children = get_child_processes(pid);
-
-        // Send the signal to the shell, don't propagate to its children yet.
-        send_signal_to_process(pid, signal, false)?;
-
-        // Now send the signal to the shells child processes and their respective
-        // children if the user wants to do so.
-        for shell_child in shell_children {
-            send_signal_to_process(shell_child.pid(), signal, send_to_children)?;
-        }
-    } else {
-        // If it isn't a shell, send the signal directly to the process.
-        // Handle children normally.
-        send_signal_to_process(pid, signal, send_to_children)?;
-    }
-
-    signal::kill(Pid::from_raw(pid), signal)?;
-    Ok(true)
+    send_signal_to_child(&Procfs, child, signal, send_to_children)
 }
 
-/// This is a helper function to safely kill a child process.
-/// Its purpose is to properly kill all processes and prevent any dangling processes.
-///
-/// Sadly, this needs some extra handling. Check the docstring of `send_signal_to_child` for
-/// additional information on why this needs to be done.
+/// Safely kill a child process and, depending on the task, its children.
+/// Check the docstring of `process_tree::signal_targets` for the processes, that are killed.
 ///
 /// Returns `true`, if everything went alright
 /// Returns `false`, if the process went away while we tried to send the signal.
 pub fn kill_child(task_id: usize, child: &mut Child, kill_children: bool) -> bool {
-    let pid: i32 = child.id().try_into().unwrap();
-
-    // Check whether this process actually spawned a shell.
-    let is_shell = if let Ok(is_shell) = did_process_spawn_shell(pid) {
-        is_shell
-    } else {
-        return false;
-    };
-
-    // We have to kill the root process first, to prevent it from spawning new processes.
-    // However, this prevents us from getting its child processes afterwards.
-    // That's why we have to get the list of child processes already now.
-    let mut child_processes = None;
-    if kill_children || is_shell {
-        child_processes = Some(get_child_processes(pid));
-    }
-
-    // Kill the parent first
-    let kill_result = child.kill();
-    if kill_result.is_err() {
-        info!("Task {} has already finished by itself", task_id);
-        return false;
-    }
-
-    // Do an early return, if we don't need to kill any children.
-    let child_processes = if let Some(child_processes) = child_processes {
-        child_processes
-    } else {
-        return true;
-    };
-
-    // Now kill all remaining children. The parent has been already been killed at this point.
-    // If a shell is spawned, we have to manually send the kill signal to all children.
-    // Otherwise only send a signal to all children if the `kill_children` flag is set.
-    if is_shell {
-        for child_process in child_processes {
-            // Send the signal to each child process, show warning if this fails.
-            let process_pid = child_process.pid();
-            if let Err(error) = send_signal_to_process(process_pid, Signal::SIGKILL, kill_children)
-            {
-                warn!(
-                    "Failed to send kill to pid {} with error {:?}",
-                    process_pid, error
-                );
-            }
-        }
-    } else if kill_children {
-        send_signal_to_processes(child_processes, Signal::SIGKILL);
-    }
-
-    true
+    process_tree::kill_child(&Procfs, task_id, child, kill_children)
 }
 
-/// Check whether a process's commandline string is actually a shell or not
-fn did_process_spawn_shell(pid: i32) -> Result<bool> {
-    // Get the /proc representation of the child, so we can do some checks
-    let process = if let Ok(process) = Process::new(pid) {
-        process
-    } else {
-        info!(
-            "Process to kill has probably just gone away. Process {}",
-            pid
-        );
-        bail!("Process has just gone away");
-    };
-
-    // Get the root command and check whether it's actually a shell with `sh -c`.
-    let mut cmdline = if let Ok(cmdline) = process.cmdline() {
-        cmdline
-    } else {
-        info!(
-            "Process to kill has probably just gone away. Process {}",
-            pid
-        );
-        bail!("Process has just gone away");
-    };
-
-    if cmdline.len() < 3 {
-        return Ok(false);
-    }
-
-    if cmdline.remove(0) != "sh" {
-        return Ok(false);
-    }
-
-    if cmdline.remove(0) != "-c" {
-        return Ok(false);
-    }
-
-    Ok(true)
-}
-
-/// Send a signal to a unix process.
-/// In case the user wants to send the signal to all children as well:
-/// 1. Get the children before sending the signal to the parent (as it might go away)
-/// 2. Send the signal to the parent first, as it might spawn new children otherwise.
-/// 3. Send the signal to all children.
-fn send_signal_to_process(
-    pid: i32,
-    signal: Signal,
-    send_to_children: bool,
-) -> Result<bool, nix::Error> {
-    debug!("Sending signal {} to {}", signal, pid);
-
-    if send_to_children {
-        let children = get_child_processes(pid);
-        signal::kill(Pid::from_raw(pid), signal)?;
-        send_signal_to_processes(children, signal);
-    } else {
-        signal::kill(Pid::from_raw(pid), signal)?;
-    }
-    Ok(true)
+/// Check, whether a specific process is exists or not
+pub fn process_exists(pid: u32) -> bool {
+    Procfs.is_alive(pid as i32)
 }
 
-/// Send a signal to a list of processes.
-/// This is a convenience wrapper around `send_signal_to_process`.
-fn send_signal_to_processes(processes: Vec<Process>, signal: Signal) {
-    for process in processes {
-        // Process is no longer alive, skip this one.
-        if !process.is_alive() {
-            continue;
-        }
-
-        let pid = Pid::from_raw(process.pid);
-        if let Err(error) = signal::kill(pid, signal) {
-            warn!(
-                "Failed to send signal {:?} to Pid {}: {:?}",
-                signal, process.pid, error
-            );
-        }
-    }
+/// The name of a process' executable, e.g. `pueued`.
+/// The kernel truncates it to 15 characters.
+pub fn process_name(pid: u32) -> Option<String> {
+    Process::new(pid as i32)
+        .ok()
+        .map(|process| process.stat.comm)
 }
 
-/// Get all children of a specific process
-fn get_child_processes(pid: i32) -> Vec<Process> {
-    let all_processes = match all_processes() {
-        Err(error) => {
-            warn!("Failed to get full process list: {}", error);
-            return Vec::new();
-        }
-        Ok(processes) => processes,
-    };
-
-    all_processes
-        .into_iter()
-        .filter(|process| process.stat.ppid == pid)
-        .collect()
-}
+/// The time at which a process has been started.
+/// It's precise to about a second, since the boot time is only known in seconds.
+pub fn process_start_time(pid: u32) -> Option<SystemTime> {
+    let process = Process::new(pid as i32).ok()?;
+    let ticks_per_second = procfs::ticks_per_second().ok()? as f64;
+    let boot_time = procfs::boot_time_secs().ok()? as f64;
+    let since_boot = process.stat.starttime as f64 / ticks_per_second;
 
-/// Check, whether a specific process is exists or not
-pub fn process_exists(pid: u32) -> bool {
-    match Process::new(pid as i32) {
-        Ok(process) => process.is_alive(),
-        Err(_) => false,
-    }
+    Some(UNIX_EPOCH + Duration::from_secs_f64(boot_time + since_boot))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::convert::TryInto;
     use std::thread::sleep;
     use std::time::Duration;
 
+    use nix::sys::signal::Signal;
+
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::platform::process_tree::did_process_spawn_shell;
 
     /// Assert that certain process id no longer exists
     fn process_is_gone(pid: i32) -> bool {
@@ -328,10 +156,10 @@ mod tests {
         sleep(Duration::from_millis(500));
 
         // Make sure the process indeed spawned a shell.
-        assert!(did_process_spawn_shell(pid).unwrap());
+        assert!(did_process_spawn_shell(&Procfs, pid).unwrap());
 
         // Get all child processes, so we can make sure they no longer exist afterwards.
-        let child_processes = get_child_processes(pid);
+        let child_processes = Procfs.children(pid);
         assert_eq!(child_processes.len(), 2);
 
         // Kill the process and make sure it'll be killed.
@@ -345,15 +173,16 @@ mod tests {
 
         // Assert that all child processes have been killed.
         for child_process in child_processes {
-            assert!(process_is_gone(child_process.stat.pid));
+            assert!(process_is_gone(child_process));
         }
     }
 
     #[test]
+    #[allow(clippy::zombie_processes)]
     /// Ensure a `sh -c` command will be properly killed without detached processes when using unix
     /// signals directly.
     fn test_shell_command_is_killed_with_signal() {
-        let mut child = compile_shell_command("sleep 60 & sleep 60 && echo 'this is a test'")
+        let child = compile_shell_command("sleep 60 & sleep 60 && echo 'this is a test'")
             .spawn()
             .expect("Failed to spawn echo");
         let pid: i32 = child.id().try_into().unwrap();
@@ -361,14 +190,14 @@ mod tests {
         sleep(Duration::from_millis(500));
 
         // Make sure the process indeed spawned a shell.
-        assert!(did_process_spawn_shell(pid).unwrap());
+        assert!(did_process_spawn_shell(&Procfs, pid).unwrap());
 
         // Get all child processes, so we can make sure they no longer exist afterwards.
-        let child_processes = get_child_processes(pid);
+        let child_processes = Procfs.children(pid);
         assert_eq!(child_processes.len(), 2);
 
         // Kill the process and make sure it'll be killed.
-        send_signal_to_child(&mut child, Signal::SIGKILL, false).unwrap();
+        send_signal_to_child(&Procfs, &child, Signal::SIGKILL, false).unwrap();
 
         // Sleep a little to give all processes time to shutdown.
         sleep(Duration::from_millis(500));
@@ -378,7 +207,7 @@ mod tests {
 
         // Assert that all child processes have been killed.
         for child_process in child_processes {
-            assert!(process_is_gone(child_process.stat.pid));
+            assert!(process_is_gone(child_process));
         }
     }
 
@@ -394,15 +223,15 @@ mod tests {
         sleep(Duration::from_millis(500));
 
         // Make sure the process indeed spawned a shell.
-        assert!(did_process_spawn_shell(pid).unwrap());
+        assert!(did_process_spawn_shell(&Procfs, pid).unwrap());
 
         // Get all child processes and all childrens children,
         // so we can make sure they no longer exist afterwards.
-        let child_processes = get_child_processes(pid);
+        let child_processes = Procfs.children(pid);
         assert_eq!(child_processes.len(), 1);
         let mut childrens_children = Vec::new();
         for child_process in &child_processes {
-            childrens_children.extend(get_child_processes(child_process.stat.pid));
+            childrens_children.extend(Procfs.children(*child_process));
         }
         assert_eq!(childrens_children.len(), 1);
 
@@ -417,12 +246,12 @@ mod tests {
 
         // Assert that all child processes have been killed.
         for child_process in child_processes {
-            assert!(process_is_gone(child_process.stat.pid));
+            assert!(process_is_gone(child_process));
         }
 
         // Assert that all children's child processes have been killed.
         for child_process in childrens_children {
-            assert!(process_is_gone(child_process.stat.pid));
+            assert!(process_is_gone(child_process));
         }
     }
 
@@ -438,10 +267,10 @@ mod tests {
         sleep(Duration::from_millis(500));
 
         // Make sure the process did not spawn a shell.
-        assert!(!did_process_spawn_shell(pid).unwrap());
+        assert!(!did_process_spawn_shell(&Procfs, pid).unwrap());
 
         // No child processes exist
-        let child_processes = get_child_processes(pid);
+        let child_processes = Procfs.children(pid);
         assert_eq!(child_processes.len(), 0);
 
         // Kill the process and make sure it'll be killed.
@@ -467,10 +296,10 @@ mod tests {
         sleep(Duration::from_millis(500));
 
         // Make sure the process indeed spawned a shell.
-        assert!(!did_process_spawn_shell(pid).unwrap());
+        assert!(!did_process_spawn_shell(&Procfs, pid).unwrap());
 
         // Get all child processes, so we can make sure they no longer exist afterwards.
-        let child_processes = get_child_processes(pid);
+        let child_processes = Procfs.children(pid);
         assert_eq!(child_processes.len(), 2);
 
         // Kill the process and make sure it'll be killed.
@@ -484,7 +313,7 @@ mod tests {
 
         // Assert that all child processes have been killed.
         for child_process in child_processes {
-            assert!(process_is_gone(child_process.stat.pid));
+            assert!(process_is_gone(child_process));
         }
     }
 }
@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use crossbeam_channel::Sender;
+use log::{error, info};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedFd;
+
+use pueue_lib::network::message::{Message, PauseMessage, StartMessage, TaskSelection};
+use pueue_lib::state::GroupStatus;
+use pueue_lib::task::TaskStatus;
+
+use crate::network::message_handler::SENDER_ERR;
+use crate::state_helper::SharedState;
+
+/// How long we wait for the running tasks to be paused, before the system may go to sleep.
+/// logind doesn't wait for longer than a few seconds anyway.
+const PAUSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pause all running groups before the system suspends and resume them after it woke up. \
+/// The daemon holds a delay inhibitor lock of systemd-logind, which is released once the tasks
+/// are paused. Only groups that were running before the suspend are resumed.
+///
+/// This runs in its own thread. Errors, e.g. if there's no system bus, are only logged.
+pub fn watch_suspend(state: SharedState, sender: Sender<Message>) {
+    std::thread::spawn(move || {
+        if let Err(error) = handle_suspend(&state, &sender) {
+            error!("Failed to watch for system suspend: {:?}", error);
+        }
+    });
+}
+
+/// Listen to logind's `PrepareForSleep` signal, which is sent before a suspend and after waking up.
+fn handle_suspend(state: &SharedState, sender: &Sender<Message>) -> Result<()> {
+    let connection = Connection::system().context("Failed to connect to the system bus")?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )
+    .context("Failed to connect to systemd-logind")?;
+    let signals = proxy
+        .receive_signal("PrepareForSleep")
+        .context("Failed to subscribe to suspend events")?;
+
+    let mut inhibitor = Some(inhibit(&proxy)?);
+    let mut paused_groups = Vec::new();
+    for signal in signals {
+        let going_to_sleep: bool = signal
+            .body()
+            .deserialize()
+            .map_err(|err| anyhow!("Got an invalid suspend event: {}", err))?;
+
+        if going_to_sleep {
+            paused_groups = running_groups(state);
+            info!("System suspends, pausing groups {:?}", paused_groups);
+            for group in &paused_groups {
+                let message = PauseMessage {
+                    tasks: TaskSelection::Group(group.clone()),
+                    wait: false,
+                    children: false,
+                };
+                sender.send(Message::Pause(message)).expect(SENDER_ERR);
+            }
+
+            wait_until_paused(state, &paused_groups);
+            // Let the system go to sleep.
+            drop(inhibitor.take());
+        } else {
+            info!("System woke up, resuming groups {:?}", paused_groups);
+            for group in paused_groups.drain(..) {
+                let message = StartMessage {
+                    tasks: TaskSelection::Group(group),
+                    children: false,
+                };
+                sender.send(Message::Start(message)).expect(SENDER_ERR);
+            }
+
+            inhibitor = Some(inhibit(&proxy)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Take a delay inhibitor lock, which holds off the suspend until it's released.
+fn inhibit(proxy: &Proxy) -> Result<OwnedFd> {
+    proxy
+        .call(
+            "Inhibit",
+            &("sleep", "pueue", "Pausing tasks before suspend", "delay"),
+        )
+        .context("Failed to take an inhibitor lock")
+}
+
+/// The names of all groups, that are currently running.
+fn running_groups(state: &SharedState) -> Vec<String> {
+    let state = state.lock().unwrap();
+    state
+        .groups
+        .iter()
+        .filter(|(_, status)| **status == GroupStatus::Running)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Wait until the task handler paused all running tasks of these groups.
+fn wait_until_paused(state: &SharedState, groups: &[String]) {
+    let started = Instant::now();
+    while started.elapsed() < PAUSE_TIMEOUT {
+        {
+            let state = state.lock().unwrap();
+            let paused = groups
+                .iter()
+                .all(|group| state.groups.get(group) != Some(&GroupStatus::Running))
+                && state.tasks.values().all(|task| {
+                    !groups.contains(&task.group) || !matches!(task.status, TaskStatus::Running)
+                });
+            if paused {
+                return;
+            }
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
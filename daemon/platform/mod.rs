@@ -1,15 +1,28 @@
-#[cfg(any(target_vendor = "apple"))]
+#[cfg(target_vendor = "apple")]
 mod apple;
-#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+mod bsd;
+#[cfg(target_os = "linux")]
 mod linux;
-#[cfg(any(target_os = "windows"))]
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+mod process_tree;
+#[cfg(target_os = "windows")]
 mod windows;
 
-#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+#[cfg(target_os = "linux")]
+pub use self::linux::cgroup;
+#[cfg(target_os = "linux")]
 pub use self::linux::process_helper;
+#[cfg(target_os = "linux")]
+pub use self::linux::suspend;
+
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+pub use self::bsd::process_helper;
 
 #[cfg(target_vendor = "apple")]
 pub use self::apple::process_helper;
 
+#[cfg(target_os = "windows")]
+pub use self::windows::console_handler;
 #[cfg(target_os = "windows")]
 pub use self::windows::process_helper;
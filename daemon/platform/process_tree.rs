@@ -0,0 +1,248 @@
+//! Signalling of whole process trees on unix platforms, whose process table can be inspected.
+//!
+//! Only the access to the process table is platform specific and hidden behind
+//! [ProcessTable]. Linux reads it from `/proc`, the BSDs via `sysctl`.
+use std::convert::TryInto;
+use std::process::Child;
+
+use anyhow::{bail, Result};
+use log::{debug, info, warn};
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
+
+use crate::task_handler::ProcessAction;
+use pueue_lib::network::message::Signal as InternalSignal;
+
+/// A view on the processes of the system.
+pub trait ProcessTable {
+    /// The ids of all direct children of a process.
+    fn children(&self, pid: i32) -> Vec<i32>;
+
+    /// The command line of a process or `None`, if it has gone away.
+    fn cmdline(&self, pid: i32) -> Option<Vec<String>>;
+
+    /// Whether the process exists and isn't a zombie.
+    fn is_alive(&self, pid: i32) -> bool;
+}
+
+pub fn map_action_to_signal(action: &ProcessAction) -> Signal {
+    match action {
+        ProcessAction::Pause => Signal::SIGSTOP,
+        ProcessAction::Resume => Signal::SIGCONT,
+    }
+}
+
+pub fn map_internal_signal_to_nix_signal(signal: InternalSignal) -> Signal {
+    match signal {
+        InternalSignal::SigKill => Signal::SIGKILL,
+        InternalSignal::SigInt => Signal::SIGINT,
+        InternalSignal::SigTerm => Signal::SIGTERM,
+        InternalSignal::SigCont => Signal::SIGCONT,
+        InternalSignal::SigStop => Signal::SIGSTOP,
+    }
+}
+
+/// Check whether a process's commandline string is actually a shell or not.
+pub fn did_process_spawn_shell(table: &impl ProcessTable, pid: i32) -> Result<bool> {
+    let cmdline = match table.cmdline(pid) {
+        Some(cmdline) => cmdline,
+        None => {
+            info!(
+                "Process to kill has probably just gone away. Process {}",
+                pid
+            );
+            bail!("Process has just gone away");
+        }
+    };
+
+    // Check whether the root command is actually a shell with `sh -c`.
+    Ok(cmdline.len() >= 3 && cmdline[0] == "sh" && cmdline[1] == "-c")
+}
+
+/// Get all processes, that receive a signal that's sent to a task, in the order in which
+/// they receive it. The task's own process always comes first.
+///
+/// A task, such as `sleep 60` get's spawned by the posix shell `sh`.
+/// This results in the process `sh -c 'sleep 60'`.
+/// Since the posix shell doesn't propagate any process signals to its children,
+/// the children of the shell always receive the signal as well.
+/// If the user also wants to send the signal to all child processes of the task,
+/// the children of those children are included.
+///
+/// In some circumstances, `sh -c $command` doesn't spawn a shell, but runs `$command` directly.
+/// In that case only the children of `$command` are included, if the user wants to.
+///
+/// An error is returned, if the process went away.
+pub fn signal_targets(
+    table: &impl ProcessTable,
+    pid: i32,
+    send_to_children: bool,
+) -> Result<Vec<i32>> {
+    let is_shell = did_process_spawn_shell(table, pid)?;
+
+    let mut targets = vec![pid];
+    if !is_shell && !send_to_children {
+        return Ok(targets);
+    }
+
+    for child in table.children(pid) {
+        targets.push(child);
+        if is_shell && send_to_children {
+            targets.extend(table.children(child));
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Send a signal to one of Pueue's child process handles.
+/// See [signal_targets] for the processes, that receive the signal.
+///
+/// Returns `Ok(true)`, if everything went alright
+/// Returns `Ok(false)`, if the process went away while we tried to send the signal.
+pub fn send_signal_to_child(
+    table: &impl ProcessTable,
+    child: &Child,
+    signal: Signal,
+    send_to_children: bool,
+) -> Result<bool> {
+    let pid: i32 = child.id().try_into().unwrap();
+    // All children are collected before the signal is sent.
+    // Otherwise the parent might go away and we'll no longer be able to access the children.
+    let targets = match signal_targets(table, pid, send_to_children) {
+        Ok(targets) => targets,
+        Err(_) => return Ok(false),
+    };
+
+    debug!("Sending signal {} to {}", signal, pid);
+    signal::kill(Pid::from_raw(pid), signal)?;
+    send_signal_to_processes(table, &targets[1..], signal);
+
+    Ok(true)
+}
+
+/// This is a helper function to safely kill a child process.
+/// Its purpose is to properly kill all processes and prevent any dangling processes.
+/// See [signal_targets] for the processes, that are killed.
+///
+/// Returns `true`, if everything went alright
+/// Returns `false`, if the process went away while we tried to send the signal.
+pub fn kill_child(
+    table: &impl ProcessTable,
+    task_id: usize,
+    child: &mut Child,
+    kill_children: bool,
+) -> bool {
+    let pid: i32 = child.id().try_into().unwrap();
+
+    // We have to kill the root process first, to prevent it from spawning new processes.
+    // However, this prevents us from getting its child processes afterwards.
+    // That's why we have to get the list of child processes already now.
+    let targets = match signal_targets(table, pid, kill_children) {
+        Ok(targets) => targets,
+        Err(_) => return false,
+    };
+
+    if child.kill().is_err() {
+        info!("Task {} has already finished by itself", task_id);
+        return false;
+    }
+
+    // Now kill all remaining children. The parent has been already been killed at this point.
+    send_signal_to_processes(table, &targets[1..], Signal::SIGKILL);
+
+    true
+}
+
+/// Send a signal to a list of processes, which are skipped if they're no longer alive.
+fn send_signal_to_processes(table: &impl ProcessTable, pids: &[i32], signal: Signal) {
+    for pid in pids {
+        if !table.is_alive(*pid) {
+            continue;
+        }
+
+        debug!("Sending signal {} to {}", signal, pid);
+        if let Err(error) = signal::kill(Pid::from_raw(*pid), signal) {
+            warn!(
+                "Failed to send signal {:?} to Pid {}: {:?}",
+                signal, pid, error
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// A process table with fixed processes, which maps pids to their parent and command line.
+    struct FakeTable(BTreeMap<i32, (i32, &'static str)>);
+
+    impl ProcessTable for FakeTable {
+        fn children(&self, pid: i32) -> Vec<i32> {
+            self.0
+                .iter()
+                .filter(|(_, (ppid, _))| *ppid == pid)
+                .map(|(pid, _)| *pid)
+                .collect()
+        }
+
+        fn cmdline(&self, pid: i32) -> Option<Vec<String>> {
+            self.0
+                .get(&pid)
+                .map(|(_, cmdline)| cmdline.split(' ').map(String::from).collect())
+        }
+
+        fn is_alive(&self, pid: i32) -> bool {
+            self.0.contains_key(&pid)
+        }
+    }
+
+    /// A shell with two children, one of which has a child of its own.
+    fn shell_table() -> FakeTable {
+        let mut processes = BTreeMap::new();
+        processes.insert(10, (1, "sh -c sleep 60 & bash -c sleep"));
+        processes.insert(11, (10, "sleep 60"));
+        processes.insert(12, (10, "bash -c sleep"));
+        processes.insert(13, (12, "sleep 60"));
+        FakeTable(processes)
+    }
+
+    #[test]
+    fn test_did_process_spawn_shell() {
+        let table = shell_table();
+        assert!(did_process_spawn_shell(&table, 10).unwrap());
+        assert!(!did_process_spawn_shell(&table, 11).unwrap());
+        // `bash -c` isn't the posix shell, that's used to run tasks.
+        assert!(!did_process_spawn_shell(&table, 12).unwrap());
+        assert!(did_process_spawn_shell(&table, 99).is_err());
+    }
+
+    #[test]
+    fn test_shell_children_are_always_signalled() {
+        let table = shell_table();
+        assert_eq!(signal_targets(&table, 10, false).unwrap(), vec![10, 11, 12]);
+        assert_eq!(
+            signal_targets(&table, 10, true).unwrap(),
+            vec![10, 11, 12, 13]
+        );
+    }
+
+    #[test]
+    fn test_command_children_are_only_signalled_on_request() {
+        let table = shell_table();
+        assert_eq!(signal_targets(&table, 12, false).unwrap(), vec![12]);
+        assert_eq!(signal_targets(&table, 12, true).unwrap(), vec![12, 13]);
+    }
+
+    #[test]
+    fn test_gone_process_has_no_targets() {
+        assert!(signal_targets(&shell_table(), 99, true).is_err());
+    }
+}
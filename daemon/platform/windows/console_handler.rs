@@ -0,0 +1,54 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use crossbeam_channel::Sender;
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
+use winapi::um::consoleapi::SetConsoleCtrlHandler;
+use winapi::um::wincon::{CTRL_CLOSE_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT};
+
+use pueue_lib::network::message::{Message, Shutdown};
+
+/// Windows kills the daemon, as soon as the handler of a close, logoff or shutdown event returns.
+/// The handler blocks for this long, while the TaskHandler kills all tasks, saves the state and
+/// exits. Windows itself doesn't wait longer than 5s on close and 20s on logoff and shutdown.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The handler is a plain function, so the channel to the TaskHandler has to be global.
+static SENDER: OnceLock<Sender<Message>> = OnceLock::new();
+
+/// Notify the TaskHandler about any console control event, similar to SIGINT and SIGTERM on Unix.
+/// The actual program exit will be done via the TaskHandler.
+pub fn set_handler(sender: Sender<Message>) -> Result<()> {
+    if SENDER.set(sender).is_err() {
+        bail!("The console control handler has already been set");
+    }
+
+    if unsafe { SetConsoleCtrlHandler(Some(handle_console_event), TRUE) } == FALSE {
+        bail!(
+            "Failed to set console control handler: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    Ok(())
+}
+
+/// [HandlerRoutine](https://docs.microsoft.com/en-us/windows/console/handlerroutine)
+/// Windows calls this in a new thread, so blocking doesn't affect the TaskHandler.
+unsafe extern "system" fn handle_console_event(event: DWORD) -> BOOL {
+    if let Some(sender) = SENDER.get() {
+        // The TaskHandler might already be shutting down.
+        let _ = sender.send(Message::DaemonShutdown(Shutdown::Emergency));
+    }
+
+    // The process is terminated right after returning from these events.
+    if matches!(
+        event,
+        CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT
+    ) {
+        std::thread::sleep(SHUTDOWN_TIMEOUT);
+    }
+
+    TRUE
+}
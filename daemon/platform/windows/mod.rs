@@ -1 +1,3 @@
+/// Shutdown on Ctrl-C, when the console is closed, the user logs off or the system shuts down.
+pub mod console_handler;
 pub mod process_helper;
@@ -1,4 +1,7 @@
+use std::ffi::CStr;
+use std::os::windows::process::CommandExt;
 use std::process::{Child, Command};
+use std::time::SystemTime;
 
 use anyhow::{bail, Result};
 use log::{error, info, warn};
@@ -17,6 +20,8 @@ use winapi::um::winnt::{PROCESS_TERMINATE, THREAD_SUSPEND_RESUME};
 
 use crate::task_handler::ProcessAction;
 use pueue_lib::network::message::Signal as InternalSignal;
+use pueue_lib::path::{is_unc_path, strip_verbatim_prefix};
+use pueue_lib::task::WindowsShell;
 
 pub fn compile_shell_command(command_string: &str) -> Command {
     // Chain two `powershell` commands, one that sets the output encoding to utf8 and then the user provided one.
@@ -29,6 +34,33 @@ pub fn compile_shell_command(command_string: &str) -> Command {
     command
 }
 
+/// Build the command of a task, which runs in the shell of the task.
+pub fn compile_task_command(
+    command_string: &str,
+    path: &str,
+    shell: Option<WindowsShell>,
+) -> Command {
+    match shell.unwrap_or(WindowsShell::PowerShell) {
+        WindowsShell::PowerShell => compile_shell_command(command_string),
+        WindowsShell::Cmd => {
+            // `cmd.exe` can't use a network share as its working directory and falls back to
+            // the Windows directory. `pushd` temporarily maps the share to a drive letter instead.
+            let command_string = if is_unc_path(path) {
+                let path = strip_verbatim_prefix(path);
+                format!("pushd \"{}\" && {}", path, command_string)
+            } else {
+                command_string.to_string()
+            };
+
+            // Switch to the utf8 code page first. `/S` strips the outer quotes, so the command
+            // is passed as is and doesn't need any escaping.
+            let mut command = Command::new("cmd");
+            command.raw_arg(format!("/S /C \"chcp 65001 >NUL && {}\"", command_string));
+            command
+        }
+    }
+}
+
 pub fn send_internal_signal_to_child(
     child: &Child,
     signal: InternalSignal,
@@ -268,6 +300,38 @@ pub fn process_exists(pid: u32) -> bool {
     false
 }
 
+/// The name of a process' executable, e.g. `pueued.exe`.
+pub fn process_name(pid: u32) -> Option<String> {
+    unsafe {
+        let handle = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if handle == INVALID_HANDLE_VALUE {
+            return None;
+        }
+
+        let mut process_entry = PROCESSENTRY32::default();
+        process_entry.dwSize = std::mem::size_of::<PROCESSENTRY32>() as u32;
+
+        let mut name = None;
+        let mut found = Process32First(handle, &mut process_entry);
+        while found != FALSE {
+            if process_entry.th32ProcessID == pid {
+                let exe_file = CStr::from_ptr(process_entry.szExeFile.as_ptr());
+                name = Some(exe_file.to_string_lossy().into_owned());
+                break;
+            }
+            found = Process32Next(handle, &mut process_entry);
+        }
+
+        CloseHandle(handle);
+        name
+    }
+}
+
+/// The start time of a process isn't determined on Windows.
+pub fn process_start_time(_pid: u32) -> Option<SystemTime> {
+    None
+}
+
 #[cfg(test)]
 mod test {
     use std::thread::sleep;
@@ -0,0 +1,240 @@
+#[cfg(any(target_os = "macos", test))]
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::cli::{CliArguments, ServiceAction};
+
+/// The label of the LaunchAgent. This is also the name of its plist file.
+pub const LAUNCHD_LABEL: &str = "com.nukesor.pueued";
+
+/// Install, uninstall or show the status of the LaunchAgent, which starts the daemon at login.
+#[cfg(target_os = "macos")]
+pub fn service(action: &ServiceAction, opt: &CliArguments) -> Result<()> {
+    match action {
+        ServiceAction::Install => launchd::install(opt),
+        ServiceAction::Uninstall => launchd::uninstall(),
+        ServiceAction::Status => launchd::status(),
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn service(_action: &ServiceAction, _opt: &CliArguments) -> Result<()> {
+    anyhow::bail!(
+        "Managing the daemon as a service is only supported on macOS. \
+        On Linux, use the systemd unit in `utils/pueued.service` instead."
+    )
+}
+
+#[cfg(target_os = "macos")]
+mod launchd {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::{Command, Output};
+
+    use anyhow::{bail, Context, Result};
+    use nix::unistd::getuid;
+
+    use pueue_lib::settings::Settings;
+
+    use super::*;
+
+    /// The LaunchAgent plist lives in the user's `~/Library/LaunchAgents` directory.
+    fn plist_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME").context("Couldn't determine the home directory")?;
+        Ok(PathBuf::from(home)
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", LAUNCHD_LABEL)))
+    }
+
+    /// The launchd domain of the current user's GUI session.
+    fn domain() -> String {
+        format!("gui/{}", getuid())
+    }
+
+    /// The launchd service target of the LaunchAgent.
+    fn service_target() -> String {
+        format!("{}/{}", domain(), LAUNCHD_LABEL)
+    }
+
+    fn launchctl(arguments: &[&str]) -> Result<Output> {
+        Command::new("launchctl")
+            .args(arguments)
+            .output()
+            .context("Failed to run launchctl")
+    }
+
+    /// Write the plist and load it. An already installed LaunchAgent is replaced.
+    pub fn install(opt: &CliArguments) -> Result<()> {
+        let path = plist_path()?;
+        if path.exists() {
+            // The previous agent has to be unloaded, otherwise the new plist isn't picked up.
+            launchctl(&["bootout", &service_target()])?;
+        }
+
+        // The daemon's output is written to the pueue directory.
+        let settings = Settings::read_with_defaults(false, &opt.config)?;
+        let log = settings.shared.pueue_directory().join("pueued.log");
+
+        let mut arguments = vec![std::env::current_exe()?.to_string_lossy().to_string()];
+        if let Some(config) = &opt.config {
+            // launchd doesn't start the daemon in the current working directory.
+            let config = config
+                .canonicalize()
+                .context(format!("Couldn't find config at {:?}", config))?;
+            arguments.push("--config".into());
+            arguments.push(config.to_string_lossy().to_string());
+        }
+        if opt.verbose > 0 {
+            arguments.push(format!("-{}", "v".repeat(opt.verbose as usize)));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, launch_agent_plist(&arguments, &log))
+            .context(format!("Failed to write plist to {:?}", path))?;
+
+        let output = launchctl(&["bootstrap", &domain(), &path.to_string_lossy()])?;
+        if !output.status.success() {
+            bail!(
+                "Failed to load the LaunchAgent: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        println!("Installed and started the LaunchAgent at {:?}", path);
+        Ok(())
+    }
+
+    /// Unload the LaunchAgent and remove its plist.
+    pub fn uninstall() -> Result<()> {
+        let path = plist_path()?;
+        if !path.exists() {
+            println!("The LaunchAgent isn't installed.");
+            return Ok(());
+        }
+
+        // This fails, if the agent isn't loaded. The plist is removed anyway.
+        let output = launchctl(&["bootout", &service_target()])?;
+        if !output.status.success() {
+            println!(
+                "The LaunchAgent wasn't loaded: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        fs::remove_file(&path).context(format!("Failed to remove plist at {:?}", path))?;
+        println!("Uninstalled the LaunchAgent at {:?}", path);
+        Ok(())
+    }
+
+    /// Show whether the plist exists, whether it's loaded and whether the daemon is running.
+    pub fn status() -> Result<()> {
+        let path = plist_path()?;
+        if !path.exists() {
+            println!("The LaunchAgent isn't installed.");
+            return Ok(());
+        }
+        println!("Installed: {:?}", path);
+
+        let output = launchctl(&["print", &service_target()])?;
+        if !output.status.success() {
+            println!("Loaded: no");
+            return Ok(());
+        }
+        println!("Loaded: yes");
+
+        // `launchctl print` shows the state and the pid of the service as `key = value` lines.
+        let info = String::from_utf8_lossy(&output.stdout);
+        let value = |key: &str| {
+            info.lines()
+                .filter_map(|line| line.trim().strip_prefix(key))
+                .find_map(|rest| rest.trim_start().strip_prefix('='))
+                .map(|value| value.trim().to_string())
+        };
+        let state = value("state").unwrap_or_else(|| "unknown".into());
+        match value("pid") {
+            Some(pid) => println!("State: {} (pid {})", state, pid),
+            None => println!("State: {}", state),
+        }
+
+        Ok(())
+    }
+}
+
+/// Generate the LaunchAgent plist, which starts the daemon at login. \
+/// launchd restarts the daemon if it crashes, but not if it has been shut down on purpose.
+#[cfg(any(target_os = "macos", test))]
+fn launch_agent_plist(arguments: &[String], log: &Path) -> String {
+    let arguments: String = arguments
+        .iter()
+        .map(|argument| format!("        <string>{}</string>\n", escape_xml(argument)))
+        .collect();
+    let log = escape_xml(&log.to_string_lossy());
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        arguments = arguments,
+        log = log,
+    )
+}
+
+/// Escape all characters that have a special meaning in XML.
+#[cfg(any(target_os = "macos", test))]
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_launch_agent_plist() {
+        let arguments = vec![
+            "/usr/local/bin/pueued".to_string(),
+            "--config".to_string(),
+            "/Users/me/R&D/pueue.yml".to_string(),
+        ];
+        let plist = launch_agent_plist(&arguments, Path::new("/Users/me/pueue/pueued.log"));
+
+        assert!(plist.contains("<string>com.nukesor.pueued</string>"));
+        assert!(plist.contains(
+            "    <array>\n        <string>/usr/local/bin/pueued</string>\n        <string>--config</string>\n        <string>/Users/me/R&amp;D/pueue.yml</string>\n    </array>"
+        ));
+        assert_eq!(
+            plist
+                .matches("<string>/Users/me/pueue/pueued.log</string>")
+                .count(),
+            2
+        );
+    }
+}
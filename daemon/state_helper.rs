@@ -1,17 +1,80 @@
+use std::cell::Cell;
 use std::collections::BTreeMap;
-use std::fs;
-use std::path::{Path, PathBuf};
-use std::sync::MutexGuard;
-use std::time::SystemTime;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
 
 use anyhow::{Context, Result};
-use chrono::prelude::*;
-use log::{debug, info};
+use log::info;
 
+use pueue_lib::log::move_log_files;
+use pueue_lib::settings::StateSaveMode;
 use pueue_lib::state::{GroupStatus, State};
-use pueue_lib::task::{TaskResult, TaskStatus};
+use pueue_lib::store::{FileStore, StateStore};
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
+
+pub type SharedState = Arc<Mutex<DaemonState>>;
+pub type LockedState<'a> = MutexGuard<'a, DaemonState>;
+
+/// The state of the daemon together with the store it's saved to and the bookkeeping of its
+/// saves. \
+/// Only the inner [State] is sent to clients and saved, which is why it dereferences to it.
+pub struct DaemonState {
+    state: State,
+    /// Where the state and the history are saved.
+    store: Arc<dyn StateStore>,
+    /// Changes, that haven't been saved yet, as the daemon delays saving the state.
+    unsaved_changes: Cell<Option<UnsavedChanges>>,
+}
 
-pub type LockedState<'a> = MutexGuard<'a, State>;
+/// Changes of the state, that haven't been saved to disk yet.
+#[derive(Clone, Copy, Debug)]
+struct UnsavedChanges {
+    /// The time of the oldest unsaved change.
+    since: Instant,
+    /// The time of the latest change.
+    last_change: Instant,
+}
+
+impl DaemonState {
+    pub fn new(state: State, store: Arc<dyn StateStore>) -> Self {
+        DaemonState {
+            state,
+            store,
+            unsaved_changes: Cell::new(None),
+        }
+    }
+}
+
+impl Deref for DaemonState {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        &self.state
+    }
+}
+
+impl DerefMut for DaemonState {
+    fn deref_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+}
+
+/// In the `debounce` save mode, the state is saved at the latest after this multiple of the
+/// delay, even if it's constantly changing.
+const MAX_DEBOUNCE_FACTOR: u32 = 10;
+
+/// Lock the state, even if it has been poisoned. \
+/// The state gets poisoned, if a client connection panicked while holding the lock.
+/// Such panics are caught and only affect that connection,
+/// which is why the task handler has to continue working with the state.
+pub fn lock_state(state: &SharedState) -> LockedState<'_> {
+    state.lock().unwrap_or_else(|poisoned| {
+        state.clear_poison();
+        poisoned.into_inner()
+    })
+}
 
 /// Check if a task can be deleted. \
 /// We have to check all dependant tasks, that haven't finished yet.
@@ -53,7 +116,10 @@ pub fn is_task_removable(state: &LockedState, task_id: &usize, to_delete: &[usiz
 ///
 /// `group` should be the name of the failed task.
 pub fn pause_on_failure(state: &mut LockedState, group: String) {
-    if state.settings.daemon.pause_group_on_failure {
+    // Later tasks of serial groups must not run after a failure.
+    if state.settings.daemon.pause_group_on_failure
+        || state.settings.daemon.serial_groups.contains(&group)
+    {
         state.groups.insert(group, GroupStatus::Paused);
     } else if state.settings.daemon.pause_all_on_failure {
         state.set_status_for_all_groups(GroupStatus::Paused);
@@ -68,6 +134,29 @@ pub fn save_settings(state: &LockedState) -> Result<()> {
         .context("Failed to save settings")
 }
 
+/// Move the logs of a task into another log directory. \
+/// Returns `false`, if the logs already are in that directory.
+///
+/// The logs of running tasks mustn't be moved, as their processes keep writing to the old files.
+pub fn move_task_logs(
+    task: &mut Task,
+    pueue_directory: &Path,
+    log_directory: &Path,
+) -> Result<bool> {
+    let current = task.log_directory(pueue_directory);
+    if current == log_directory {
+        return Ok(false);
+    }
+
+    move_log_files(task.id, &current, log_directory).context(format!(
+        "Failed to move logs of task {} to {:?}",
+        task.id, log_directory
+    ))?;
+    task.log_directory = Some(log_directory.to_path_buf());
+
+    Ok(true)
+}
+
 /// Do a full reset of the state.
 /// This doesn't reset any processes!
 pub fn reset_state(state: &mut LockedState) -> Result<()> {
@@ -78,81 +167,96 @@ pub fn reset_state(state: &mut LockedState) -> Result<()> {
     save_state(state)
 }
 
-/// Convenience wrapper around save_to_file.
-pub fn save_state(state: &State) -> Result<()> {
-    save_state_to_file(state, false)
-}
+/// Save the state after it has been changed. \
+/// Depending on `daemon.state_save_mode`, the change is only remembered and saved later by
+/// [save_pending_state].
+pub fn save_state(state: &DaemonState) -> Result<()> {
+    if state.settings.daemon.state_save_mode == StateSaveMode::EveryChange {
+        return save_state_to_store(state);
+    }
+
+    let now = Instant::now();
+    let since = state
+        .unsaved_changes
+        .get()
+        .map(|changes| changes.since)
+        .unwrap_or(now);
+    state.unsaved_changes.set(Some(UnsavedChanges {
+        since,
+        last_change: now,
+    }));
 
-/// Save the current current state in a file with a timestamp.
-/// At the same time remove old state logs from the log directory.
-/// This function is called, when large changes to the state are applied, e.g. clean/reset.
-pub fn backup_state(state: &LockedState) -> Result<()> {
-    save_state_to_file(state, true)?;
-    rotate_state(state).context("Failed to rotate old log files")?;
     Ok(())
 }
 
-/// Save the current state to disk. \
-/// We do this to restore in case of a crash. \
-/// If log == true, the file will be saved with a time stamp.
-///
-/// In comparison to the daemon -> client communication, the state is saved
-/// as JSON for better readability and debug purposes.
-fn save_state_to_file(state: &State, log: bool) -> Result<()> {
-    let serialized = serde_json::to_string(&state).context("Failed to serialize state:");
-
-    let serialized = serialized.unwrap();
-    let path = state.settings.shared.pueue_directory();
-    let (temp, real) = if log {
-        let path = path.join("log");
-        let now: DateTime<Utc> = Utc::now();
-        let time = now.format("%Y-%m-%d_%H-%M-%S");
-        (
-            path.join(format!("{}_state.json.partial", time)),
-            path.join(format!("{}_state.json", time)),
-        )
-    } else {
-        (path.join("state.json.partial"), path.join("state.json"))
+/// Save all delayed changes of the state, once they're due.
+pub fn save_pending_state(state: &DaemonState) -> Result<()> {
+    let changes = match state.unsaved_changes.get() {
+        Some(changes) => changes,
+        None => return Ok(()),
     };
 
-    // Write to temporary log file first, to prevent loss due to crashes.
-    fs::write(&temp, serialized).context("Failed to write temp file while saving state.")?;
+    let delay = state.settings.daemon.state_save_delay();
+    let due = match state.settings.daemon.state_save_mode {
+        StateSaveMode::EveryChange => true,
+        StateSaveMode::Debounce => {
+            changes.last_change.elapsed() >= delay
+                || changes.since.elapsed() >= delay * MAX_DEBOUNCE_FACTOR
+        }
+        StateSaveMode::Periodic => changes.since.elapsed() >= delay,
+    };
+    if !due {
+        return Ok(());
+    }
 
-    // Overwrite the original with the temp file, if everything went fine.
-    fs::rename(&temp, &real).context("Failed to overwrite old state while saving state")?;
+    save_state_to_store(state)
+}
 
-    if log {
-        debug!("State backup created at: {:?}", real);
-    } else {
-        debug!("State saved at: {:?}", real);
-    }
+/// Save the state right away, including all delayed changes.
+pub fn flush_state(state: &DaemonState) -> Result<()> {
+    save_state_to_store(state)
+}
+
+/// Keep a copy of the current state, before large changes are applied, e.g. clean/reset.
+pub fn backup_state(state: &LockedState) -> Result<()> {
+    store(state)
+        .backup(state)
+        .context("Failed to create a backup of the state")
+}
+
+/// The store, to which the state and the history are saved.
+pub fn store(state: &DaemonState) -> Arc<dyn StateStore> {
+    state.store.clone()
+}
+
+/// Save the current state to the store. \
+/// We do this to restore in case of a crash.
+fn save_state_to_store(state: &DaemonState) -> Result<()> {
+    store(state).save(state).context("Failed to save state")?;
+    state.unsaved_changes.set(None);
 
     Ok(())
 }
 
 /// Restore the last state from a previous session. \
 /// The state is stored as json in the `pueue_directory`.
+pub fn restore_state(pueue_directory: &Path) -> Result<Option<State>> {
+    restore_state_from_store(&FileStore::new(pueue_directory))
+}
+
+/// Restore the last state from a previous session out of a store.
 ///
 /// If the state cannot be deserialized, an empty default state will be used instead. \
 /// All groups with queued tasks will be automatically paused to prevent unwanted execution.
-pub fn restore_state(pueue_directory: &Path) -> Result<Option<State>> {
-    let path = pueue_directory.join("state.json");
-
-    // Ignore if the file doesn't exist. It doesn't have to.
-    if !path.exists() {
-        info!(
-            "Couldn't find state from previous session at location: {:?}",
-            path
-        );
-        return Ok(None);
-    }
+pub fn restore_state_from_store(store: &dyn StateStore) -> Result<Option<State>> {
     info!("Start restoring state");
-
-    // Try to load the file.
-    let data = fs::read_to_string(&path).context("State restore: Failed to read file:\n\n{}")?;
-
-    // Try to deserialize the state file.
-    let mut state: State = serde_json::from_str(&data).context("Failed to deserialize state.")?;
+    let mut state = match store.load().context("Failed to restore state")? {
+        Some(state) => state,
+        None => {
+            info!("Couldn't find state from previous session");
+            return Ok(None);
+        }
+    };
 
     // Copy group statuses from the previous state.
     for (group, _) in state.settings.daemon.groups.iter() {
@@ -194,37 +298,85 @@ pub fn restore_state(pueue_directory: &Path) -> Result<Option<State>> {
                 &task.group
             );
             state.groups.insert(task.group.clone(), GroupStatus::Paused);
+            // Opening the group's scheduling window mustn't resume it either.
+            state.pause_reasons.remove(&task.group);
         }
     }
 
     Ok(Some(state))
 }
 
-/// Remove old logs that aren't needed any longer.
-fn rotate_state(state: &LockedState) -> Result<()> {
-    let path = state.settings.shared.pueue_directory().join("log");
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
 
-    // Get all log files in the directory with their respective system time.
-    let mut entries: BTreeMap<SystemTime, PathBuf> = BTreeMap::new();
-    let mut directory_list = fs::read_dir(path)?;
-    while let Some(Ok(entry)) = directory_list.next() {
-        let path = entry.path();
+    use pretty_assertions::assert_eq;
 
-        let metadata = entry.metadata()?;
-        let time = metadata.modified()?;
-        entries.insert(time, path);
-    }
+    use pueue_lib::error::Error;
+    use pueue_lib::network::message::HistoryEntry;
+    use pueue_lib::settings::{Settings, PUEUE_DEFAULT_GROUP};
+    use pueue_lib::task::Task;
 
-    // Remove all files above the threshold.
-    // Old files are removed first (implictly by the BTree order).
-    let mut number_entries = entries.len();
-    let mut iter = entries.iter();
-    while number_entries > 10 {
-        if let Some((_, path)) = iter.next() {
-            fs::remove_file(path)?;
-            number_entries -= 1;
+    use super::*;
+
+    /// A store, that keeps the state in memory.
+    #[derive(Clone, Default)]
+    struct MemoryStore(Arc<Mutex<Option<State>>>);
+
+    impl StateStore for MemoryStore {
+        fn save(&self, state: &State) -> Result<(), Error> {
+            *self.0.lock().unwrap() = Some(state.clone());
+            Ok(())
+        }
+
+        fn backup(&self, _state: &State) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn load(&self) -> Result<Option<State>, Error> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        fn append_history(&self, _entries: &[HistoryEntry]) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn read_history(&self) -> Result<Vec<HistoryEntry>, Error> {
+            Ok(Vec::new())
         }
     }
 
-    Ok(())
+    fn task(status: TaskStatus) -> Task {
+        Task::new(
+            "sleep 60".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            PUEUE_DEFAULT_GROUP.into(),
+            status,
+            Vec::new(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_restore_from_store() {
+        let store = MemoryStore::default();
+        assert_eq!(restore_state_from_store(&store).unwrap(), None);
+
+        let settings: Settings = Settings::default_config().unwrap().try_into().unwrap();
+        let mut state = State::new(&settings, None);
+        state.add_task(task(TaskStatus::Running));
+        state.add_task(task(TaskStatus::Queued));
+        save_state(&DaemonState::new(state, Arc::new(store.clone()))).unwrap();
+
+        let restored = restore_state_from_store(&store).unwrap().unwrap();
+        // The running task has been interrupted and its group mustn't start the queued task.
+        assert_eq!(
+            restored.tasks[&0].status,
+            TaskStatus::Done(TaskResult::Killed)
+        );
+        assert_eq!(restored.tasks[&1].status, TaskStatus::Queued);
+        assert_eq!(restored.groups[PUEUE_DEFAULT_GROUP], GroupStatus::Paused);
+    }
 }
@@ -1,11 +1,62 @@
+use std::path::Path;
+
 use handlebars::RenderError;
+use serde_derive::Serialize;
 
 use super::*;
 
+/// The maximum amount of bytes of stdout and stderr that are passed to the callback. \
+/// The whole callback is passed to the shell as a single argument, whose size is limited.
+const CALLBACK_OUTPUT_LIMIT: usize = 16 * 1024;
+
+/// All variables that are available in the callback template.
+#[derive(Serialize)]
+pub struct CallbackParameters<'a> {
+    #[serde(flatten)]
+    parameters: HashMap<&'a str, String>,
+    /// The task's metadata is available as `{{ meta.<key> }}`.
+    meta: &'a BTreeMap<String, String>,
+}
+
 impl TaskHandler {
     /// Users can specify a callback that's fired whenever a task finishes.
     /// Execute the callback by spawning a new subprocess.
+    /// WASM policies are informed about the finished task as well.
     pub fn spawn_callback(&mut self, task: &Task) {
+        self.wasm_policies.on_finish(task);
+        self.spawn_task_hook(task);
+
+        let policy = self.notification_policy(&task.group);
+        if policy == NotificationPolicy::Never {
+            return;
+        }
+        self.remember_for_digest(task);
+        if !policy.notify_task(task.failed()) {
+            return;
+        }
+
+        self.notify_by_mail(task);
+        self.notify_chats(task);
+
+        // The lua script takes precedence over the callback template.
+        if let Some(script) = self
+            .lua_script
+            .as_ref()
+            .filter(|script| script.defines("format_callback"))
+        {
+            let parameters =
+                callback_parameters(task, &self.pueue_directory, self.callback_log_lines);
+            match script.format_callback(&parameters) {
+                Ok(Some(callback_command)) => {
+                    self.callbacks
+                        .push(format!("task {}", task.id), callback_command);
+                }
+                Ok(None) => (),
+                Err(err) => error!("{:#}", err),
+            }
+            return;
+        }
+
         // Return early, if there's no callback specified
         let template_string = if let Some(callback) = &self.callback {
             callback
@@ -14,7 +65,12 @@ impl TaskHandler {
         };
 
         // Build the command to be called from the template string in the configuration file.
-        let callback_command = match self.build_callback_command(task, template_string) {
+        let callback_command = match build_callback_command(
+            task,
+            template_string,
+            &self.pueue_directory,
+            self.callback_log_lines,
+        ) {
             Ok(callback_command) => callback_command,
             Err(err) => {
                 error!(
@@ -25,103 +81,226 @@ impl TaskHandler {
             }
         };
 
-        let mut command = compile_shell_command(&callback_command);
+        self.callbacks
+            .push(format!("task {}", task.id), callback_command);
+    }
 
-        // Spawn the callback subprocess and log if it fails.
-        let spawn_result = command.spawn();
-        let child = match spawn_result {
-            Err(error) => {
-                error!("Failed to spawn callback with error: {}", error);
-                return;
-            }
-            Ok(child) => child,
+    /// Run the `--on-success` or `--on-failure` command of a finished task. \
+    /// These are independent of the notification policy of the task's group.
+    fn spawn_task_hook(&mut self, task: &Task) {
+        let template_string = match &task.status {
+            TaskStatus::Done(TaskResult::Success) => &task.on_success,
+            TaskStatus::Done(_) => &task.on_failure,
+            _ => return,
+        };
+        let template_string = match template_string {
+            Some(template_string) => template_string,
+            None => return,
         };
 
-        debug!("Spawned callback for task {}", task.id);
-        self.callbacks.push(child);
-    }
-
-    /// Take the callback template string from the configuration and insert all parameters from the
-    /// finished task.
-    pub fn build_callback_command(
-        &self,
-        task: &Task,
-        template_string: &str,
-    ) -> Result<String, RenderError> {
-        // Init Handlebars. We set to strict, as we want to show an error on missing variables.
-        let mut handlebars = Handlebars::new();
-        handlebars.set_strict_mode(true);
-
-        // Add templating variables.
-        let mut parameters = HashMap::new();
-        parameters.insert("id", task.id.to_string());
-        parameters.insert("command", task.command.clone());
-        parameters.insert("path", task.path.clone());
-        parameters.insert("group", task.group.clone());
-
-        // Result takes the TaskResult Enum strings, unless it didn't finish yet.
-        if let TaskStatus::Done(result) = &task.status {
-            parameters.insert("result", result.to_string());
-        } else {
-            parameters.insert("result", "None".into());
+        match build_callback_command(
+            task,
+            template_string,
+            &self.pueue_directory,
+            self.callback_log_lines,
+        ) {
+            Ok(command) => self
+                .callbacks
+                .push(format!("hook of task {}", task.id), command),
+            Err(err) => error!(
+                "Failed to create the hook of task {} from template with error: {}",
+                task.id, err
+            ),
         }
+    }
+
+    /// The notification policy of a group. Groups without a policy are always notified.
+    pub fn notification_policy(&self, group: &str) -> NotificationPolicy {
+        self.group_notifications
+            .get(group)
+            .copied()
+            .unwrap_or(NotificationPolicy::Always)
+    }
 
-        // Format and insert start and end times.
-        let print_time = |time: Option<DateTime<Local>>| {
-            time.map(|time| time.timestamp().to_string())
-                .unwrap_or_else(String::new)
+    /// Collect all finished callbacks and start queued ones.
+    pub fn check_callbacks(&mut self) {
+        self.callbacks.check();
+    }
+}
+
+/// Take the callback template string from the configuration and insert all parameters from the
+/// finished task.
+///
+/// All variables are shell-escaped, so they're always passed as a single word.
+/// Use triple braces, e.g. `{{{ command }}}`, to insert a variable as it is.
+pub fn build_callback_command(
+    task: &Task,
+    template_string: &str,
+    pueue_directory: &Path,
+    log_lines: usize,
+) -> Result<String, RenderError> {
+    // Init Handlebars. We set to strict, as we want to show an error on missing variables.
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(|text| shell_escape::escape(text.to_string().into()).to_string());
+
+    let parameters = callback_parameters(task, pueue_directory, log_lines);
+    handlebars.render_template(template_string, &parameters)
+}
+
+/// Collect all variables of a finished task, that are available in templates.
+pub fn callback_parameters<'a>(
+    task: &'a Task,
+    pueue_directory: &Path,
+    log_lines: usize,
+) -> CallbackParameters<'a> {
+    let mut parameters = HashMap::new();
+    parameters.insert("id", task.id.to_string());
+    parameters.insert("command", task.command.clone());
+    parameters.insert("path", task.path.clone());
+    parameters.insert("group", task.group.clone());
+    parameters.insert("label", task.label.clone().unwrap_or_default());
+
+    // Result takes the TaskResult Enum strings, unless it didn't finish yet.
+    if let TaskStatus::Done(result) = &task.status {
+        parameters.insert("result", result.to_string());
+    } else {
+        parameters.insert("result", "None".into());
+    }
+
+    // Format and insert start and end times.
+    let print_time = |time: Option<DateTime<Local>>| {
+        time.map(|time| time.timestamp().to_string())
+            .unwrap_or_default()
+    };
+    parameters.insert("start", print_time(task.start));
+    parameters.insert("end", print_time(task.end));
+
+    // The runtime in seconds.
+    let runtime = match (task.start, task.end) {
+        (Some(start), Some(end)) => (end - start).num_seconds().to_string(),
+        _ => String::new(),
+    };
+    parameters.insert("runtime", runtime);
+
+    // Read the last lines of the process' output and make it available.
+    let log_directory = task.log_directory(pueue_directory);
+    if let Ok((stdout, stderr)) = read_last_log_file_lines(task.id, &log_directory, log_lines) {
+        parameters.insert("stdout", tail_bytes(stdout, CALLBACK_OUTPUT_LIMIT));
+        parameters.insert("stderr", tail_bytes(stderr, CALLBACK_OUTPUT_LIMIT));
+    } else {
+        parameters.insert("stdout", "".to_string());
+        parameters.insert("stderr", "".to_string());
+    }
+
+    // Get the exit code
+    if let TaskStatus::Done(result) = &task.status {
+        match result {
+            TaskResult::Success => parameters.insert("exit_code", "0".into()),
+            TaskResult::Failed(code) => parameters.insert("exit_code", code.to_string()),
+            _ => parameters.insert("exit_code", "None".into()),
         };
-        parameters.insert("start", print_time(task.start));
-        parameters.insert("end", print_time(task.end));
+    } else {
+        parameters.insert("exit_code", "None".into());
+    }
 
-        // Read the last lines of the process' output and make it available.
-        if let Ok((stdout, stderr)) =
-            read_last_log_file_lines(task.id, &self.pueue_directory, self.callback_log_lines)
-        {
-            parameters.insert("stdout", stdout);
-            parameters.insert("stderr", stderr);
-        } else {
-            parameters.insert("stdout", "".to_string());
-            parameters.insert("stderr", "".to_string());
-        }
+    CallbackParameters {
+        parameters,
+        meta: &task.meta,
+    }
+}
 
-        // Get the exit code
-        if let TaskStatus::Done(result) = &task.status {
-            match result {
-                TaskResult::Success => parameters.insert("exit_code", "0".into()),
-                TaskResult::Failed(code) => parameters.insert("exit_code", code.to_string()),
-                _ => parameters.insert("exit_code", "None".into()),
-            };
-        } else {
-            parameters.insert("exit_code", "None".into());
-        }
+/// Only keep the last `limit` bytes of some text.
+/// The text is cut at a character boundary, so it might be a bit shorter.
+fn tail_bytes(text: String, limit: usize) -> String {
+    if text.len() <= limit {
+        return text;
+    }
 
-        handlebars.render_template(template_string, &parameters)
+    let mut start = text.len() - limit;
+    while !text.is_char_boundary(start) {
+        start += 1;
     }
+    text[start..].to_string()
+}
 
-    /// Look at all running callbacks and log any errors.
-    /// If everything went smoothly, simply remove them from the list.
-    pub fn check_callbacks(&mut self) {
-        let mut finished = Vec::new();
-        for (id, child) in self.callbacks.iter_mut().enumerate() {
-            match child.try_wait() {
-                // Handle a child error.
-                Err(error) => {
-                    error!("Callback failed with error {:?}", error);
-                    finished.push(id);
-                }
-                // Child process did not exit yet.
-                Ok(None) => continue,
-                Ok(exit_status) => {
-                    info!("Callback finished with exit code {:?}", exit_status);
-                    finished.push(id);
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn finished_task(command: &str) -> Task {
+        let mut task = Task::new(
+            command.into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Done(TaskResult::Failed(3)),
+            Vec::new(),
+            Some("nightly backup".into()),
+        );
+        task.id = 4;
+        task.start = Some(Local.timestamp(1000, 0));
+        task.end = Some(Local.timestamp(1090, 0));
+        task.meta.insert("pr".into(), "42".into());
+        task
+    }
+
+    fn render(task: &Task, template: &str) -> String {
+        let tempdir = tempfile::tempdir().unwrap();
+        build_callback_command(task, template, tempdir.path(), 10).unwrap()
+    }
+
+    #[test]
+    fn test_all_variables() {
+        let task = finished_task("ls");
+        let command = render(
+            &task,
+            "{{ id }} {{ group }} {{ label }} {{ result }} {{ exit_code }} {{ runtime }} {{ meta.pr }}",
+        );
+        assert_eq!(command, "4 default 'nightly backup' Failed 3 90 42");
 
-        finished.reverse();
-        for id in finished.iter() {
-            self.callbacks.remove(*id);
+        // Unknown variables are an error.
+        let tempdir = tempfile::tempdir().unwrap();
+        assert!(build_callback_command(&task, "{{ meta.commit }}", tempdir.path(), 10).is_err());
+    }
+
+    /// Variables must never be interpreted by the shell, no matter what they contain.
+    #[test]
+    fn test_injection_safety() {
+        let malicious = [
+            "$(touch pwned)",
+            "`touch pwned`",
+            "foo; touch pwned",
+            "foo' && touch pwned '",
+            "foo\" && touch pwned \"",
+            "foo\ntouch pwned",
+            "foo | cat > pwned",
+        ];
+
+        for text in malicious {
+            let mut task = finished_task(text);
+            task.meta.insert("pr".into(), text.into());
+            let command = render(&task, "printf '%s\\n' {{ command }} {{ meta.pr }}");
+
+            let output = compile_shell_command(&command).output().unwrap();
+            let expected = format!("{}\n{}\n", text, text);
+            assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
         }
     }
+
+    #[test]
+    fn test_raw_variables() {
+        let task = finished_task("echo 'hi'");
+        assert_eq!(render(&task, "{{{ command }}}"), "echo 'hi'");
+    }
+
+    #[test]
+    fn test_tail_bytes() {
+        assert_eq!(tail_bytes("short".into(), 10), "short");
+        assert_eq!(tail_bytes("0123456789".into(), 4), "6789");
+        // Multi-byte characters aren't split.
+        assert_eq!(tail_bytes("aäb".into(), 3), "äb");
+        assert_eq!(tail_bytes("aäb".into(), 2), "b");
+    }
 }
@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info};
+
+use pueue_lib::settings::Daemon;
+
+use crate::platform::process_helper::compile_shell_command;
+
+/// The time window of `daemon.callback_rate_limit`.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// A callback that's either waiting in the queue or running.
+struct Callback {
+    /// A short description for the logs, e.g. `task 4`.
+    name: String,
+    command: String,
+    /// Text that's written to the callback's stdin.
+    input: Option<String>,
+    /// How often this callback has already been started.
+    attempts: usize,
+}
+
+/// All callbacks are executed through this queue. \
+/// It limits how many callbacks run at the same time and how many are started per minute,
+/// so a burst of finishing tasks doesn't spawn hundreds of processes at once.
+/// Failed callbacks are retried.
+pub struct CallbackQueue {
+    /// Callbacks that wait for a free slot.
+    queued: VecDeque<Callback>,
+    running: Vec<(Callback, Child)>,
+    /// The start times of all callbacks within the rate limit window.
+    recent_starts: VecDeque<Instant>,
+
+    parallel: usize,
+    rate_limit: Option<usize>,
+    retries: usize,
+    queue_size: usize,
+}
+
+impl CallbackQueue {
+    pub fn new(settings: &Daemon) -> Self {
+        CallbackQueue {
+            queued: VecDeque::new(),
+            running: Vec::new(),
+            recent_starts: VecDeque::new(),
+            // Callbacks would never run without a single slot.
+            parallel: settings.callback_parallel.max(1),
+            rate_limit: settings.callback_rate_limit,
+            retries: settings.callback_retries,
+            queue_size: settings.callback_queue_size,
+        }
+    }
+
+    /// Add a callback to the queue. It's dropped, if the queue is full.
+    pub fn push(&mut self, name: String, command: String) {
+        self.enqueue(name, command, None);
+    }
+
+    /// Same as [push], but `input` is written to the callback's stdin.
+    pub fn push_with_input(&mut self, name: String, command: String, input: String) {
+        self.enqueue(name, command, Some(input));
+    }
+
+    fn enqueue(&mut self, name: String, command: String, input: Option<String>) {
+        if self.queued.len() >= self.queue_size {
+            error!("Callback queue is full. Dropping callback for {}", name);
+            return;
+        }
+
+        self.queued.push_back(Callback {
+            name,
+            command,
+            input,
+            attempts: 0,
+        });
+    }
+
+    /// Whether there are any queued or running callbacks.
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty() && self.running.is_empty()
+    }
+
+    /// Collect all finished callbacks and start queued ones, as far as the limits allow.
+    pub fn check(&mut self) {
+        let mut index = 0;
+        while index < self.running.len() {
+            let (callback, child) = &mut self.running[index];
+            let succeeded = match child.try_wait() {
+                // Child process did not exit yet.
+                Ok(None) => {
+                    index += 1;
+                    continue;
+                }
+                Ok(Some(exit_status)) => {
+                    info!(
+                        "Callback for {} finished with exit code {:?}",
+                        callback.name,
+                        exit_status.code()
+                    );
+                    exit_status.success()
+                }
+                Err(error) => {
+                    error!(
+                        "Callback for {} failed with error {:?}",
+                        callback.name, error
+                    );
+                    false
+                }
+            };
+
+            let (callback, _) = self.running.remove(index);
+            if !succeeded {
+                self.retry(callback);
+            }
+        }
+
+        while self.running.len() < self.parallel && !self.rate_limited() {
+            let callback = match self.queued.pop_front() {
+                Some(callback) => callback,
+                None => break,
+            };
+            self.start(callback);
+        }
+    }
+
+    /// Start all queued callbacks right away, no matter the limits.
+    /// This is used during shutdown, as nobody would start them afterwards.
+    pub fn flush(&mut self) {
+        self.retries = 0;
+        while let Some(callback) = self.queued.pop_front() {
+            self.start(callback);
+        }
+    }
+
+    /// Check whether the maximum amount of callbacks has been started within the last minute.
+    fn rate_limited(&mut self) -> bool {
+        let rate_limit = match self.rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return false,
+        };
+
+        while matches!(self.recent_starts.front(), Some(start) if start.elapsed() >= RATE_LIMIT_WINDOW)
+        {
+            self.recent_starts.pop_front();
+        }
+
+        self.recent_starts.len() >= rate_limit
+    }
+
+    fn start(&mut self, mut callback: Callback) {
+        callback.attempts += 1;
+        if self.rate_limit.is_some() {
+            self.recent_starts.push_back(Instant::now());
+        }
+
+        let stdin = if callback.input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        };
+        let spawned = compile_shell_command(&callback.command)
+            .stdin(stdin)
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                debug!("Spawned callback for {}", callback.name);
+                // Write the input from a separate thread, so a slow callback can't block us.
+                if let (Some(input), Some(mut stdin)) = (callback.input.clone(), child.stdin.take())
+                {
+                    std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+                }
+                self.running.push((callback, child));
+            }
+            Err(error) => {
+                error!(
+                    "Failed to spawn callback for {} with error: {}",
+                    callback.name, error
+                );
+                self.retry(callback);
+            }
+        }
+    }
+
+    /// Queue a failed callback again, unless it ran out of retries.
+    fn retry(&mut self, callback: Callback) {
+        if callback.attempts > self.retries {
+            if self.retries > 0 {
+                error!(
+                    "Giving up on callback for {} after {} attempts",
+                    callback.name, callback.attempts
+                );
+            }
+            return;
+        }
+
+        info!(
+            "Retrying callback for {} ({} of {} retries)",
+            callback.name, callback.attempts, self.retries
+        );
+        self.queued.push_back(callback);
+    }
+}
+
+#[cfg(all(test, not(target_os = "windows")))]
+mod tests {
+    use std::thread::sleep;
+
+    use pueue_lib::settings::Settings;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn queue(parallel: usize, rate_limit: Option<usize>, retries: usize) -> CallbackQueue {
+        let settings: Settings = Settings::default_config()
+            .expect("Failed to get default config")
+            .try_into()
+            .expect("Failed to get test settings");
+        let mut settings = settings.daemon;
+        settings.callback_parallel = parallel;
+        settings.callback_rate_limit = rate_limit;
+        settings.callback_retries = retries;
+        settings.callback_queue_size = 3;
+        CallbackQueue::new(&settings)
+    }
+
+    /// Check the queue until all callbacks finished.
+    fn run_until_empty(queue: &mut CallbackQueue) {
+        while !queue.is_empty() {
+            queue.check();
+            sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut queue = queue(2, Some(3), 0);
+        for id in 0..5 {
+            queue.push(format!("task {}", id), "sleep 0.2".into());
+        }
+        // The queue only holds three callbacks.
+        assert_eq!(queue.queued.len(), 3);
+
+        queue.check();
+        assert_eq!(queue.running.len(), 2);
+        assert_eq!(queue.queued.len(), 1);
+
+        // Wait for the first two to finish. The third one may start afterwards.
+        sleep(Duration::from_millis(400));
+        queue.check();
+        assert_eq!(queue.running.len(), 1);
+        assert!(queue.queued.is_empty());
+
+        // The rate limit has been reached.
+        sleep(Duration::from_millis(400));
+        queue.push("task 5".into(), "true".into());
+        queue.check();
+        assert!(queue.running.is_empty());
+        assert_eq!(queue.queued.len(), 1);
+    }
+
+    #[test]
+    fn test_retries() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let attempts = tempdir.path().join("attempts");
+        let command = format!("echo attempt >> {}; false", attempts.to_string_lossy());
+
+        let mut queue = queue(1, None, 2);
+        queue.push("task 0".into(), command);
+        run_until_empty(&mut queue);
+
+        let attempts = std::fs::read_to_string(attempts).unwrap();
+        assert_eq!(attempts.lines().count(), 3);
+    }
+}
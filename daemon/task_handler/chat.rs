@@ -0,0 +1,221 @@
+use pueue_lib::settings::ChatService;
+use serde_json::{json, Value};
+
+use super::*;
+
+/// Commands are cut off after this many characters, so messages stay readable.
+/// Discord also rejects messages with more than 2000 characters.
+const MAX_COMMAND_LENGTH: usize = 1000;
+
+impl TaskHandler {
+    /// Post a message about the finished task to all configured chats.
+    pub fn notify_chats(&mut self, task: &Task) {
+        for notification in &self.chat_notifications {
+            if notification.only_failures && !task.failed() {
+                continue;
+            }
+
+            let name = match notification.service {
+                ChatService::Slack { .. } => "Slack",
+                ChatService::Discord { .. } => "Discord",
+                ChatService::Matrix { .. } => "Matrix",
+            };
+            self.callbacks.push_with_input(
+                format!("{} message of task {}", name, task.id),
+                chat_command(&notification.service, task.id),
+                chat_payload(&notification.service, task).to_string(),
+            );
+        }
+    }
+}
+
+/// The curl command, that posts the JSON payload it receives on stdin.
+/// Tokens and webhooks are only fetched once the message is sent, so they never end up in the
+/// logs or the state.
+fn chat_command(service: &ChatService, task_id: usize) -> String {
+    let escape = |text: &str| shell_escape::escape(text.to_string().into()).to_string();
+    let curl = "curl --silent --show-error --fail -H 'Content-Type: application/json'";
+    let authorization =
+        |token_command: &str| format!("\"Authorization: Bearer $({})\"", token_command);
+
+    match service {
+        ChatService::Slack { token_command, .. } => format!(
+            "{} -H {} --data-binary @- https://slack.com/api/chat.postMessage",
+            curl,
+            authorization(token_command)
+        ),
+        ChatService::Discord { webhook_command } => {
+            format!("{} --data-binary @- \"$({})\"", curl, webhook_command)
+        }
+        ChatService::Matrix {
+            homeserver,
+            room,
+            token_command,
+        } => {
+            // Matrix deduplicates messages by their transaction id.
+            let url = format!(
+                "{}/_matrix/client/v3/rooms/{}/send/m.room.message/pueue-{}-{}",
+                homeserver.trim_end_matches('/'),
+                room.replace('!', "%21").replace(':', "%3A"),
+                task_id,
+                Local::now().timestamp_millis()
+            );
+            format!(
+                "{} -H {} -X PUT --data-binary @- {}",
+                curl,
+                authorization(token_command),
+                escape(&url)
+            )
+        }
+    }
+}
+
+/// Build the JSON message in the format of the respective service.
+fn chat_payload(service: &ChatService, task: &Task) -> Value {
+    let icon = if task.failed() { "❌" } else { "✅" };
+    let title = match &task.label {
+        Some(label) => format!("Task {} ({})", task.id, label),
+        None => format!("Task {}", task.id),
+    };
+    let result = match &task.status {
+        TaskStatus::Done(TaskResult::Success) => "finished successfully".to_string(),
+        TaskStatus::Done(TaskResult::Failed(code)) => format!("failed with exit code {}", code),
+        TaskStatus::Done(result) => format!("finished with result {}", result),
+        _ => "finished".to_string(),
+    };
+    let runtime = match (task.start, task.end) {
+        (Some(start), Some(end)) => format!(" after {}s", (end - start).num_seconds()),
+        _ => String::new(),
+    };
+    let summary = format!("{} in group {}{}", result, task.group, runtime);
+    let command: String = task.command.chars().take(MAX_COMMAND_LENGTH).collect();
+
+    match service {
+        ChatService::Slack { channel, .. } => {
+            // Slack's markup only requires these three characters to be escaped.
+            let escape = |text: &str| {
+                text.replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+            };
+            json!({
+                "channel": channel,
+                "text": format!(
+                    "{} *{}* {}\n```{}```",
+                    icon,
+                    escape(&title),
+                    escape(&summary),
+                    escape(&command)
+                ),
+            })
+        }
+        ChatService::Discord { .. } => json!({
+            "content": format!(
+                "{} **{}** {}\n```\n{}\n```",
+                icon,
+                title,
+                summary,
+                command.replace("```", "`\u{200b}``")
+            ),
+            // Don't ping anybody, even if the command contains `@everyone`.
+            "allowed_mentions": { "parse": [] },
+        }),
+        ChatService::Matrix { .. } => {
+            let escape = |text: &str| {
+                text.replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+                    .replace('"', "&quot;")
+            };
+            json!({
+                "msgtype": "m.text",
+                "body": format!("{} {} {}\n{}", icon, title, summary, command),
+                "format": "org.matrix.custom.html",
+                "formatted_body": format!(
+                    "{} <b>{}</b> {}<pre><code>{}</code></pre>",
+                    icon,
+                    escape(&title),
+                    escape(&summary),
+                    escape(&command)
+                ),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn failed_task() -> Task {
+        let mut task = Task::new(
+            "grep '<pattern>' a && b".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Done(TaskResult::Failed(2)),
+            Vec::new(),
+            Some("search".into()),
+        );
+        task.id = 4;
+        task
+    }
+
+    #[test]
+    fn test_payloads() {
+        let task = failed_task();
+
+        let slack = ChatService::Slack {
+            token_command: "echo xoxb-token".into(),
+            channel: "#builds".into(),
+        };
+        assert_eq!(
+            chat_payload(&slack, &task),
+            json!({
+                "channel": "#builds",
+                "text": "❌ *Task 4 (search)* failed with exit code 2 in group default\n\
+                    ```grep '&lt;pattern&gt;' a &amp;&amp; b```",
+            })
+        );
+
+        let matrix = ChatService::Matrix {
+            homeserver: "https://matrix.org".into(),
+            room: "!room:matrix.org".into(),
+            token_command: "echo token".into(),
+        };
+        let payload = chat_payload(&matrix, &task);
+        assert_eq!(
+            payload["body"],
+            "❌ Task 4 (search) failed with exit code 2 in group default\ngrep '<pattern>' a && b"
+        );
+        assert_eq!(
+            payload["formatted_body"],
+            "❌ <b>Task 4 (search)</b> failed with exit code 2 in group default\
+            <pre><code>grep '&lt;pattern&gt;' a &amp;&amp; b</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_chat_commands() {
+        let discord = ChatService::Discord {
+            webhook_command: "secret-tool lookup service discord".into(),
+        };
+        assert_eq!(
+            chat_command(&discord, 4),
+            "curl --silent --show-error --fail -H 'Content-Type: application/json' \
+            --data-binary @- \"$(secret-tool lookup service discord)\""
+        );
+
+        let matrix = ChatService::Matrix {
+            homeserver: "https://matrix.org/".into(),
+            room: "!room:matrix.org".into(),
+            token_command: "echo token".into(),
+        };
+        let command = chat_command(&matrix, 4);
+        assert!(command.contains(" -H \"Authorization: Bearer $(echo token)\" -X PUT "));
+        assert!(command.contains(
+            " 'https://matrix.org/_matrix/client/v3/rooms/%21room%3Amatrix.org/send/m.room.message/pueue-4-"
+        ));
+    }
+}
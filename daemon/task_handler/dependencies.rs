@@ -3,12 +3,15 @@ use super::*;
 impl TaskHandler {
     /// Ensure that no `Queued` tasks have any failed dependencies.
     /// Otherwise set their status to `Done` and result to `DependencyFailed`.
+    ///
+    /// Tasks that only run after a failure are skipped instead, once all of their
+    /// dependencies succeeded.
     pub fn check_failed_dependencies(&mut self) {
         // Clone the state ref, so we don't have two mutable borrows later on.
         let state_ref = self.state.clone();
-        let mut state = state_ref.lock().unwrap();
+        let mut state = lock_state(&state_ref);
 
-        // Get id's of all tasks with failed dependencies
+        // Get id's of all tasks with failed dependencies and the result they end up with.
         let has_failed_deps: Vec<_> = state
             .tasks
             .iter()
@@ -16,20 +19,21 @@ impl TaskHandler {
             .filter_map(|(id, task)| {
                 // At this point we got all queued tasks with dependencies.
                 // Go through all dependencies and ensure they didn't fail.
-                let failed = task
-                    .dependencies
-                    .iter()
-                    .flat_map(|id| state.tasks.get(id))
-                    .filter(|task| task.failed())
-                    .map(|task| task.id)
-                    .next();
-
-                failed.map(|f| (*id, f))
+                let mut dependencies = task.dependencies.iter().flat_map(|id| state.tasks.get(id));
+                match task.dependency_mode {
+                    DependencyMode::Success => dependencies
+                        .any(|task| task.failed())
+                        .then_some((*id, TaskResult::DependencyFailed)),
+                    DependencyMode::Failure => dependencies
+                        .all(|task| task.status == TaskStatus::Done(TaskResult::Success))
+                        .then_some((*id, TaskResult::Skipped)),
+                    DependencyMode::Any => None,
+                }
             })
             .collect();
 
         // Update the state of all tasks with failed dependencies.
-        for (id, _) in has_failed_deps {
+        for (id, result) in has_failed_deps {
             // Get the task's group, since we have to check if it's paused.
             let group = if let Some(task) = state.tasks.get(&id) {
                 task.group.clone()
@@ -45,7 +49,7 @@ impl TaskHandler {
             }
 
             let task = state.tasks.get_mut(&id).unwrap();
-            task.status = TaskStatus::Done(TaskResult::DependencyFailed);
+            task.status = TaskStatus::Done(result);
             task.start = Some(Local::now());
             task.end = Some(Local::now());
             self.spawn_callback(task);
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use log::warn;
+
+use super::*;
+
+impl TaskHandler {
+    /// Check whether there's enough disk space left to start a new task. \
+    /// If there isn't, the `low_disk_callback` is called once,
+    /// until enough space is available again.
+    pub fn has_free_disk_space(&mut self) -> bool {
+        let threshold = match self.min_free_disk {
+            Some(threshold) => threshold,
+            None => return true,
+        };
+        let available = match available_disk_space(&self.pueue_directory) {
+            Some(available) => available,
+            None => return true,
+        };
+
+        if available >= threshold {
+            if self.low_disk_space {
+                info!("Enough disk space is available again. Starting new tasks.");
+                self.low_disk_space = false;
+            }
+            return true;
+        }
+
+        if !self.low_disk_space {
+            warn!(
+                "Only {} bytes of disk space left, but min_free_disk is {} bytes. No new tasks are started.",
+                available, threshold
+            );
+            self.low_disk_space = true;
+            self.spawn_low_disk_callback(available, threshold);
+        }
+
+        false
+    }
+
+    /// Notify the user, that no new tasks are started due to low disk space.
+    fn spawn_low_disk_callback(&mut self, available: u64, threshold: u64) {
+        let template_string = match &self.low_disk_callback {
+            Some(callback) => callback,
+            None => return,
+        };
+
+        let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(true);
+        let mut parameters = HashMap::new();
+        parameters.insert("path", self.pueue_directory.to_string_lossy().to_string());
+        parameters.insert("available", available.to_string());
+        parameters.insert("threshold", threshold.to_string());
+
+        let callback_command = match handlebars.render_template(template_string, &parameters) {
+            Ok(callback_command) => callback_command,
+            Err(err) => {
+                error!(
+                    "Failed to create low disk callback command from template with error: {}",
+                    err
+                );
+                return;
+            }
+        };
+
+        self.callbacks
+            .push("low disk space".into(), callback_command);
+    }
+}
+
+/// The amount of bytes that are available to unprivileged users on the filesystem of `path`.
+/// The casts are necessary, since the types of these fields differ between platforms.
+#[cfg(not(target_os = "windows"))]
+#[allow(clippy::unnecessary_cast)]
+fn available_disk_space(path: &Path) -> Option<u64> {
+    match nix::sys::statvfs::statvfs(path) {
+        Ok(stats) => Some(stats.blocks_available() as u64 * stats.fragment_size() as u64),
+        Err(err) => {
+            error!(
+                "Failed to determine the free disk space of {:?}: {}",
+                path, err
+            );
+            None
+        }
+    }
+}
+
+/// The disk space isn't checked on Windows.
+#[cfg(target_os = "windows")]
+fn available_disk_space(_path: &Path) -> Option<u64> {
+    None
+}
@@ -1,9 +1,12 @@
+use std::path::Path;
+
 use anyhow::Context;
 
+use super::callback::callback_parameters;
 use super::*;
 
 use crate::ok_or_shutdown;
-use crate::state_helper::{pause_on_failure, save_state};
+use crate::state_helper::{move_task_logs, pause_on_failure, save_state, LockedState};
 
 impl TaskHandler {
     /// Check whether there are any finished processes
@@ -18,11 +21,15 @@ impl TaskHandler {
 
         // Clone the state ref, so we don't have two mutable borrows later on.
         let state_ref = self.state.clone();
-        let mut state = state_ref.lock().unwrap();
+        let mut state = lock_state(&state_ref);
+        // Logs of tasks, that were running while the log directory changed, are moved now.
+        let log_directory = state.settings.shared.log_directory();
 
         for ((task_id, group, worker_id), error) in finished.iter() {
             // Handle std::io errors on child processes.
             // I have never seen something like this, but it might happen.
+            let timed_out = self.timed_out.remove(task_id);
+            let out_of_memory = finish_cgroup(&state, *task_id);
             if let Some(error) = error {
                 let (_taks_id, _child) = self
                     .children
@@ -33,15 +40,18 @@ impl TaskHandler {
                     .expect("Errored child went missing while handling finished task.");
 
                 let group = {
-                    let mut task = state.tasks.get_mut(task_id).unwrap();
+                    let task = state.tasks.get_mut(task_id).unwrap();
                     task.status = TaskStatus::Done(TaskResult::Errored);
                     task.end = Some(Local::now());
+                    self.finish_output(task);
+                    self.move_logs(task, &log_directory);
                     self.spawn_callback(task);
 
                     task.group.clone()
                 };
                 error!("Child {} failed with io::Error: {:?}", task_id, error);
 
+                state.record_statistics(*task_id);
                 pause_on_failure(&mut state, group);
                 continue;
             }
@@ -69,39 +79,89 @@ impl TaskHandler {
 
             // Processes with exit code 0 exited successfully
             // Processes with `None` have been killed by a Signal
+            // Tasks that exceeded their maximum runtime have been killed by us.
+            // Tasks that exceeded their memory limit have been killed by the kernel.
             let result = match exit_code {
+                _ if timed_out => TaskResult::FailedTimeout,
                 Some(0) => TaskResult::Success,
+                _ if out_of_memory => TaskResult::OutOfMemory,
                 Some(exit_code) => TaskResult::Failed(exit_code),
                 None => TaskResult::Killed,
             };
 
             // Update all properties on the task and get the group for later
-            let group = {
-                let mut task = state
+            let (group, result) = {
+                let task = state
                     .tasks
                     .get_mut(task_id)
                     .expect("Task was removed before child process has finished!");
 
                 task.status = TaskStatus::Done(result.clone());
                 task.end = Some(Local::now());
+                let result = match self.evaluate_result(task) {
+                    Some(result) => {
+                        task.status = TaskStatus::Done(result.clone());
+                        result
+                    }
+                    None => result,
+                };
+                self.finish_output(task);
+                self.move_logs(task, &log_directory);
                 self.spawn_callback(task);
 
-                task.group.clone()
+                (task.group.clone(), result)
             };
+            state.record_statistics(*task_id);
 
-            if let TaskResult::Failed(_) = result {
+            if matches!(
+                result,
+                TaskResult::Failed(_) | TaskResult::FailedTimeout | TaskResult::OutOfMemory
+            ) {
                 pause_on_failure(&mut state, group);
             }
 
             // Already remove the output files, if the daemon is being reset anyway
             if self.full_reset {
-                clean_log_handles(*task_id, &self.pueue_directory);
+                let log_directory = state.tasks[task_id].log_directory(&self.pueue_directory);
+                clean_log_handles(*task_id, &log_directory);
             }
         }
 
         ok_or_shutdown!(self, save_state(&state));
     }
 
+    /// Move the logs of a finished task into the configured log directory.
+    fn move_logs(&self, task: &mut Task, log_directory: &Path) {
+        if let Err(err) = move_task_logs(task, &self.pueue_directory, log_directory) {
+            error!("{:#}", err);
+        }
+    }
+
+    /// Let the lua script decide, whether a task that exited actually succeeded. \
+    /// Returns `None`, if the result doesn't change.
+    fn evaluate_result(&self, task: &Task) -> Option<TaskResult> {
+        let script = self.lua_script.as_ref()?;
+        let exit_code = match &task.status {
+            TaskStatus::Done(TaskResult::Success) => 0,
+            TaskStatus::Done(TaskResult::Failed(exit_code)) => *exit_code,
+            _ => return None,
+        };
+        if !script.defines("is_success") {
+            return None;
+        }
+
+        let parameters = callback_parameters(task, &self.pueue_directory, self.callback_log_lines);
+        match script.is_success(&parameters) {
+            Ok(Some(true)) => Some(TaskResult::Success),
+            Ok(Some(false)) => Some(TaskResult::Failed(exit_code)),
+            Ok(None) => None,
+            Err(err) => {
+                error!("{:#}", err);
+                None
+            }
+        }
+    }
+
     /// Gather all finished tasks and sort them by finished and errored.
     /// Returns a list of finished task ids and whether they errored or not.
     fn get_finished(&mut self) -> Vec<((usize, String, usize), Option<std::io::Error>)> {
@@ -126,3 +186,25 @@ impl TaskHandler {
         finished
     }
 }
+
+/// Remove the cgroup of a finished task with resource limits.
+/// Returns whether any of its processes have been killed, as they ran out of memory.
+#[cfg(target_os = "linux")]
+fn finish_cgroup(state: &LockedState, task_id: usize) -> bool {
+    use crate::platform::cgroup::finish_task_cgroup;
+
+    let has_limits = state
+        .tasks
+        .get(&task_id)
+        .is_some_and(Task::has_resource_limits);
+    match &state.settings.daemon.cgroup_root {
+        Some(root) if has_limits => finish_task_cgroup(std::path::Path::new(root), task_id),
+        _ => false,
+    }
+}
+
+/// Resource limits are only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+fn finish_cgroup(_state: &LockedState, _task_id: usize) -> bool {
+    false
+}
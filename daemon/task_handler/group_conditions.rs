@@ -0,0 +1,112 @@
+use pueue_lib::settings::GroupCondition;
+
+use super::*;
+
+/// Failing checks are retried less often, up to this multiple of the condition's interval.
+const MAX_BACKOFF_FACTOR: u32 = 4;
+
+/// The current state of a group's condition.
+pub struct ConditionCheck {
+    condition: GroupCondition,
+    /// Whether the last check passed. Tasks aren't started, before the first check passed.
+    passed: bool,
+    /// The time at which the last check has been started.
+    last_check: Option<Instant>,
+    /// Decides when the next check is started.
+    poller: Poller,
+    /// The currently running check.
+    child: Option<Child>,
+}
+
+/// Prepare the checks for all configured group conditions.
+pub fn init_group_conditions(
+    conditions: &BTreeMap<String, GroupCondition>,
+) -> BTreeMap<String, ConditionCheck> {
+    conditions
+        .iter()
+        .map(|(group, condition)| {
+            let interval = Duration::from_secs(condition.interval);
+            let check = ConditionCheck {
+                condition: condition.clone(),
+                passed: false,
+                last_check: None,
+                poller: Poller::with_backoff(interval, interval * MAX_BACKOFF_FACTOR),
+                child: None,
+            };
+            (group.clone(), check)
+        })
+        .collect()
+}
+
+impl TaskHandler {
+    /// Run the check commands of all group conditions and collect their results.
+    /// Checks run in the background, so a slow check doesn't block the daemon.
+    pub fn check_group_conditions(&mut self) {
+        for (group, check) in self.group_conditions.iter_mut() {
+            let interval = Duration::from_secs(check.condition.interval);
+
+            if let Some(child) = check.child.as_mut() {
+                let passed = match child.try_wait() {
+                    Ok(Some(status)) => status.success(),
+                    // Kill checks that take too long, e.g. due to a hanging connection.
+                    Ok(None) => {
+                        let started = check.last_check.unwrap_or_else(Instant::now);
+                        if started.elapsed() < interval {
+                            continue;
+                        }
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        false
+                    }
+                    Err(err) => {
+                        error!("Failed to wait for condition of group {}: {}", group, err);
+                        false
+                    }
+                };
+                check.child = None;
+
+                if passed != check.passed {
+                    if passed {
+                        info!("Condition of group {} passed. Starting tasks.", group);
+                    } else {
+                        info!("Condition of group {} failed. Holding back tasks.", group);
+                    }
+                }
+                check.passed = passed;
+                if passed {
+                    check.poller.reset();
+                } else {
+                    check.poller.back_off();
+                }
+            }
+
+            if !check.poller.is_due() {
+                continue;
+            }
+
+            check.last_check = Some(Instant::now());
+            let spawned = compile_shell_command(&check.condition.command)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            match spawned {
+                Ok(child) => check.child = Some(child),
+                Err(err) => {
+                    error!("Failed to spawn condition of group {}: {}", group, err);
+                    check.passed = false;
+                    check.poller.back_off();
+                }
+            }
+        }
+    }
+
+    /// Whether tasks of this group may be started.
+    /// Groups without a condition can always start tasks.
+    pub fn group_condition_passed(&self, group: &str) -> bool {
+        self.group_conditions
+            .get(group)
+            .map(|check| check.passed)
+            .unwrap_or(true)
+    }
+}
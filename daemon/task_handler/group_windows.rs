@@ -0,0 +1,86 @@
+use pueue_lib::settings::{parse_time_window, TimeWindow};
+use pueue_lib::state::PauseReason;
+
+use super::*;
+
+use crate::ok_or_shutdown;
+
+/// The scheduling window of a group.
+pub struct WindowCheck {
+    /// The window as it's written in the settings, e.g. `22:00-06:00`.
+    text: String,
+    window: TimeWindow,
+    /// Whether the window was open during the last check. `None` before the first check.
+    open: Option<bool>,
+}
+
+/// Prepare the checks for all configured scheduling windows.
+/// Invalid windows are rejected, while the daemon starts.
+pub fn init_group_windows(windows: &BTreeMap<String, String>) -> BTreeMap<String, WindowCheck> {
+    windows
+        .iter()
+        .filter_map(|(group, text)| {
+            let window = parse_time_window(text)?;
+            let check = WindowCheck {
+                text: text.clone(),
+                window,
+                open: None,
+            };
+            Some((group.clone(), check))
+        })
+        .collect()
+}
+
+impl TaskHandler {
+    /// Pause groups, once their scheduling window closes, and resume them, once it opens again.
+    ///
+    /// Only changes of a window are acted upon, so a group that's started or paused by hand
+    /// keeps its status until the window changes the next time.
+    /// Groups are only resumed, if they have been paused by their window.
+    pub fn check_group_windows(&mut self) {
+        let now = Local::now().time();
+        let mut changes = Vec::new();
+        for (group, check) in self.group_windows.iter_mut() {
+            let open = check.window.contains(now);
+            if check.open == Some(open) {
+                continue;
+            }
+            check.open = Some(open);
+            changes.push((group.clone(), check.text.clone(), open));
+        }
+
+        for (group, window, open) in changes {
+            let state = lock_state(&self.state);
+            let status = state.groups.get(&group).cloned();
+            let paused_by_window = matches!(
+                state.pause_reasons.get(&group),
+                Some(PauseReason::OutsideWindow { .. })
+            );
+            drop(state);
+
+            if open {
+                if paused_by_window {
+                    info!(
+                        "Window {} of group {} opened. Resuming group.",
+                        window, group
+                    );
+                    self.start(TaskSelection::Group(group), false);
+                }
+            } else if status == Some(GroupStatus::Running) {
+                info!(
+                    "Window {} of group {} closed. Pausing group.",
+                    window, group
+                );
+                self.pause(TaskSelection::Group(group.clone()), false, false);
+
+                // Remember the reason, so the group is resumed once the window opens again.
+                let cloned_state_mutex = self.state.clone();
+                let mut state = lock_state(&cloned_state_mutex);
+                state
+                    .pause_reasons
+                    .insert(group, PauseReason::OutsideWindow { window });
+                ok_or_shutdown!(self, save_state(&state));
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use pueue_lib::settings::LoadLimit;
+
+use super::*;
+
+/// How often the system load is sampled.
+pub const SYSTEM_LOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A snapshot of the system's load.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct SystemLoad {
+    /// The one-minute load average.
+    load: f64,
+    /// The memory in bytes, that's available for new processes without swapping.
+    free_memory: u64,
+}
+
+/// The load limit of a group and whether the system stayed within it.
+pub struct LoadLimitCheck {
+    limit: LoadLimit,
+    /// The minimum amount of available memory in bytes.
+    min_free_memory: Option<u64>,
+    /// Whether the last sample of the system load was within the limit.
+    passed: bool,
+}
+
+/// Prepare the checks for all configured load limits.
+/// Tasks are started, until the first sample of the system load has been taken.
+pub fn init_load_limits(limits: &BTreeMap<String, LoadLimit>) -> BTreeMap<String, LoadLimitCheck> {
+    limits
+        .iter()
+        .map(|(group, limit)| {
+            let check = LoadLimitCheck {
+                limit: limit.clone(),
+                min_free_memory: limit.min_free_memory(),
+                passed: true,
+            };
+            (group.clone(), check)
+        })
+        .collect()
+}
+
+impl TaskHandler {
+    /// Sample the system load and check whether it's within the limits of each group.
+    pub fn check_load_limits(&mut self) {
+        if self.load_limits.is_empty() || !self.system_load_poller.is_due() {
+            return;
+        }
+        self.system_load_poller.reset();
+
+        let sample = match sample_system_load() {
+            Some(sample) => sample,
+            None => return,
+        };
+
+        for (group, check) in self.load_limits.iter_mut() {
+            let load_ok = check
+                .limit
+                .max_load
+                .is_none_or(|max_load| sample.load <= max_load);
+            let memory_ok = check
+                .min_free_memory
+                .is_none_or(|min_free_memory| sample.free_memory >= min_free_memory);
+            let passed = load_ok && memory_ok;
+
+            if passed != check.passed {
+                if passed {
+                    info!(
+                        "System load is within the limits of group {}. Starting tasks.",
+                        group
+                    );
+                } else {
+                    info!(
+                        "System load exceeds the limits of group {} (load {:.2}, {} bytes free). Holding back tasks.",
+                        group, sample.load, sample.free_memory
+                    );
+                }
+            }
+            check.passed = passed;
+        }
+    }
+
+    /// Whether the system load allows to start tasks of this group.
+    /// Groups without a load limit can always start tasks.
+    pub fn load_limit_passed(&self, group: &str) -> bool {
+        self.load_limits
+            .get(group)
+            .map(|check| check.passed)
+            .unwrap_or(true)
+    }
+}
+
+/// Read the load average and the available memory from `/proc`.
+#[cfg(target_os = "linux")]
+fn sample_system_load() -> Option<SystemLoad> {
+    let sample = procfs::LoadAverage::new().and_then(|load| {
+        let meminfo = procfs::Meminfo::new()?;
+        Ok(SystemLoad {
+            load: load.one as f64,
+            free_memory: meminfo.mem_available.unwrap_or(meminfo.mem_free),
+        })
+    });
+
+    match sample {
+        Ok(sample) => Some(sample),
+        Err(err) => {
+            error!("Failed to determine the system load: {}", err);
+            None
+        }
+    }
+}
+
+/// The system load isn't checked on other platforms.
+#[cfg(not(target_os = "linux"))]
+fn sample_system_load() -> Option<SystemLoad> {
+    None
+}
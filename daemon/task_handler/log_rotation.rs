@@ -0,0 +1,58 @@
+use std::fs::metadata;
+
+use super::*;
+use crate::ok_or_shutdown;
+
+/// How often the size of the log files is checked.
+pub const LOG_SIZE_INTERVAL: Duration = Duration::from_secs(1);
+/// Checks happen less often, while no log files have to be rotated.
+pub const MAX_LOG_SIZE_INTERVAL: Duration = Duration::from_secs(5);
+
+impl TaskHandler {
+    /// Rotate the log files of all running tasks, which exceed `daemon.max_log_size`.
+    pub fn rotate_logs(&mut self) {
+        let max_log_size = match self.max_log_size {
+            Some(max_log_size) => max_log_size,
+            None => return,
+        };
+        if !self.log_size_poller.is_due() {
+            return;
+        }
+
+        let state_clone = self.state.clone();
+        let mut state = lock_state(&state_clone);
+        let mut changed = false;
+        for task in state.tasks.values_mut() {
+            if !task.is_running() {
+                continue;
+            }
+
+            let (out_path, err_path) =
+                get_task_log_paths(task.id, &task.log_directory(&self.pueue_directory));
+            for path in [out_path, err_path] {
+                let size = match metadata(&path) {
+                    Ok(metadata) => metadata.len(),
+                    Err(_) => continue,
+                };
+                if size <= max_log_size {
+                    continue;
+                }
+
+                info!("Rotating log file {:?} of task {}", path, task.id);
+                if let Err(err) = rotate_log_file(&path, self.rotated_logs) {
+                    error!("Failed to rotate log file {:?}: {}", path, err);
+                    continue;
+                }
+                task.log_rotations += 1;
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.log_size_poller.reset();
+            ok_or_shutdown!(self, save_state(&state));
+        } else {
+            self.log_size_poller.back_off();
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use handlebars::no_escape;
+use pueue_lib::settings::Mail;
+use serde_derive::Serialize;
+
+use super::callback::callback_parameters;
+use super::*;
+
+/// A finished task, as it's listed in the digest.
+#[derive(Serialize)]
+struct DigestTask {
+    id: usize,
+    command: String,
+    label: String,
+    result: String,
+    runtime: String,
+}
+
+/// All variables that are available in the digest templates.
+#[derive(Serialize)]
+struct DigestParameters {
+    group: String,
+    succeeded: usize,
+    failed: usize,
+    tasks: Vec<DigestTask>,
+}
+
+impl TaskHandler {
+    /// Remember the finished task for the digest of its group.
+    pub fn remember_for_digest(&mut self, task: &Task) {
+        if matches!(&self.mail, Some(mail) if mail.digest) {
+            self.mail_digests
+                .entry(task.group.clone())
+                .or_default()
+                .push(task.id);
+        }
+    }
+
+    /// Send a mail, if the task failed.
+    pub fn notify_by_mail(&mut self, task: &Task) {
+        let mail = match &self.mail {
+            Some(mail) if mail.on_failure && task.failed() => mail,
+            _ => return,
+        };
+        let parameters = callback_parameters(task, &self.pueue_directory, self.callback_log_lines);
+        match render_mail(mail, &mail.failure_subject, &mail.failure_body, &parameters) {
+            Ok(message) => self.callbacks.push_with_input(
+                format!("mail of task {}", task.id),
+                mail_command(mail),
+                message,
+            ),
+            Err(err) => error!("Failed to create failure mail from template: {}", err),
+        }
+    }
+
+    /// Send a digest for all groups, whose tasks have all finished.
+    pub fn check_mail_digests(&mut self) {
+        let mail = match &self.mail {
+            Some(mail) if !self.mail_digests.is_empty() => mail.clone(),
+            _ => return,
+        };
+
+        let state = lock_state(&self.state);
+        let drained: Vec<String> = self
+            .mail_digests
+            .keys()
+            .filter(|group| {
+                !state.tasks.values().any(|task| {
+                    &task.group == *group
+                        && matches!(
+                            task.status,
+                            TaskStatus::Queued
+                                | TaskStatus::Running
+                                | TaskStatus::Paused
+                                | TaskStatus::Locked
+                        )
+                })
+            })
+            .cloned()
+            .collect();
+
+        for group in drained {
+            let task_ids = self.mail_digests.remove(&group).unwrap_or_default();
+            // Tasks that have already been removed are skipped.
+            let tasks: Vec<&Task> = task_ids
+                .iter()
+                .filter_map(|task_id| state.tasks.get(task_id))
+                .collect();
+            if tasks.is_empty() {
+                continue;
+            }
+
+            let parameters = digest_parameters(&group, &tasks);
+            if !self
+                .notification_policy(&group)
+                .notify_drain(parameters.failed > 0)
+            {
+                continue;
+            }
+            match render_mail(&mail, &mail.digest_subject, &mail.digest_body, &parameters) {
+                Ok(message) => self.callbacks.push_with_input(
+                    format!("digest of group {}", group),
+                    mail_command(&mail),
+                    message,
+                ),
+                Err(err) => error!("Failed to create digest mail from template: {}", err),
+            }
+        }
+    }
+}
+
+fn digest_parameters(group: &str, tasks: &[&Task]) -> DigestParameters {
+    let failed = tasks.iter().filter(|task| task.failed()).count();
+    let tasks: Vec<DigestTask> = tasks
+        .iter()
+        .map(|task| DigestTask {
+            id: task.id,
+            command: task.command.clone(),
+            label: task.label.clone().unwrap_or_default(),
+            result: match &task.status {
+                TaskStatus::Done(result) => result.to_string(),
+                _ => "None".into(),
+            },
+            runtime: match (task.start, task.end) {
+                (Some(start), Some(end)) => (end - start).num_seconds().to_string(),
+                _ => String::new(),
+            },
+        })
+        .collect();
+
+    DigestParameters {
+        group: group.to_string(),
+        succeeded: tasks.len() - failed,
+        failed,
+        tasks,
+    }
+}
+
+/// Render the subject and body templates and build the whole mail including its headers.
+fn render_mail<T: serde::Serialize>(
+    mail: &Mail,
+    subject: &str,
+    body: &str,
+    parameters: &T,
+) -> Result<String, handlebars::RenderError> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(no_escape);
+
+    // Line breaks in the subject would allow to inject further headers.
+    let subject = handlebars
+        .render_template(subject, parameters)?
+        .replace(&['\r', '\n'][..], " ");
+    let body = handlebars.render_template(body, parameters)?;
+
+    Ok(format!(
+        "From: {}\nTo: {}\nSubject: {}\nDate: {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+        mail.from,
+        mail.to.join(", "),
+        subject,
+        Local::now().to_rfc2822(),
+        body
+    ))
+}
+
+/// The curl command, that sends the mail it receives on stdin.
+/// `--crlf` converts the line endings, as SMTP expects `\r\n`.
+/// `--ssl-reqd` refuses to fall back to plain text, as the password is sent along.
+fn mail_command(mail: &Mail) -> String {
+    let escape = |text: &str| shell_escape::escape(text.to_string().into()).to_string();
+
+    let mut command = format!(
+        "curl --silent --show-error --ssl-reqd --crlf --url {} --mail-from {}",
+        escape(&mail.server),
+        escape(&mail.from)
+    );
+    for recipient in &mail.to {
+        command.push_str(&format!(" --mail-rcpt {}", escape(recipient)));
+    }
+    if let Some(username) = &mail.username {
+        // The password is only fetched once the mail is sent, so it never ends up in the logs.
+        let password = match &mail.password_command {
+            Some(password_command) => format!("\"$({})\"", password_command),
+            None => String::new(),
+        };
+        command.push_str(&format!(" --user {}:{}", escape(username), password));
+    }
+    command.push_str(" --upload-file -");
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn mail() -> Mail {
+        Mail {
+            server: "smtps://mail.example.com".into(),
+            from: "pueue@example.com".into(),
+            to: vec!["me@example.com".into(), "team@example.com".into()],
+            username: Some("pueue".into()),
+            password_command: Some("secret-tool lookup service pueue".into()),
+            on_failure: true,
+            digest: true,
+            failure_subject: "Task {{ id }} failed".into(),
+            failure_body: "{{ command }}".into(),
+            digest_subject: "Group {{ group }}: {{ failed }} failed\nBcc: evil@example.com".into(),
+            digest_body: "{{#each tasks}}{{ id }} {{ result }} {{ command }}\n{{/each}}".into(),
+        }
+    }
+
+    fn finished_task(id: usize, result: TaskResult) -> Task {
+        let mut task = Task::new(
+            "echo 'a' && ls".into(),
+            "/tmp".into(),
+            HashMap::new(),
+            "default".into(),
+            TaskStatus::Done(result),
+            Vec::new(),
+            None,
+        );
+        task.id = id;
+        task
+    }
+
+    #[test]
+    fn test_mail_command() {
+        assert_eq!(
+            mail_command(&mail()),
+            "curl --silent --show-error --ssl-reqd --crlf --url 'smtps://mail.example.com' \
+            --mail-from 'pueue@example.com' --mail-rcpt 'me@example.com' \
+            --mail-rcpt 'team@example.com' \
+            --user pueue:\"$(secret-tool lookup service pueue)\" --upload-file -"
+        );
+    }
+
+    #[test]
+    fn test_digest() {
+        let mail = mail();
+        let first = finished_task(0, TaskResult::Success);
+        let second = finished_task(3, TaskResult::Failed(1));
+        let parameters = digest_parameters("default", &[&first, &second]);
+
+        let message =
+            render_mail(&mail, &mail.digest_subject, &mail.digest_body, &parameters).unwrap();
+        let (headers, body) = message.split_once("\n\n").unwrap();
+
+        // The subject can't be used to add further headers.
+        assert!(headers.contains("\nSubject: Group default: 1 failed Bcc: evil@example.com\n"));
+        assert!(!headers.contains("\nBcc:"));
+        assert!(headers.contains("\nTo: me@example.com, team@example.com\n"));
+        // Commands aren't escaped.
+        assert_eq!(body, "0 Success echo 'a' && ls\n3 Failed echo 'a' && ls\n");
+    }
+}
@@ -6,7 +6,7 @@ use log::{error, info};
 use pueue_lib::network::message::GroupMessage;
 
 use crate::ok_or_shutdown;
-use crate::state_helper::{save_settings, save_state};
+use crate::state_helper::{lock_state, save_settings, save_state};
 use crate::task_handler::{Shutdown, TaskHandler};
 
 impl TaskHandler {
@@ -19,7 +19,7 @@ impl TaskHandler {
     /// - self.children
     pub fn handle_group_message(&mut self, message: GroupMessage) {
         let cloned_state_mutex = self.state.clone();
-        let mut state = cloned_state_mutex.lock().unwrap();
+        let mut state = lock_state(&cloned_state_mutex);
 
         match message {
             GroupMessage::List => {}
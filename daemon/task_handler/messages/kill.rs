@@ -1,12 +1,13 @@
+use chrono::Local;
 use log::{error, info, warn};
 
 use pueue_lib::network::message::{Signal, TaskSelection};
 use pueue_lib::state::GroupStatus;
-use pueue_lib::task::TaskStatus;
+use pueue_lib::task::{TaskResult, TaskStatus};
 
 use crate::ok_or_shutdown;
 use crate::platform::process_helper::*;
-use crate::state_helper::save_state;
+use crate::state_helper::{lock_state, save_state};
 use crate::task_handler::{Shutdown, TaskHandler};
 
 impl TaskHandler {
@@ -27,7 +28,7 @@ impl TaskHandler {
         signal: Option<Signal>,
     ) {
         let cloned_state_mutex = self.state.clone();
-        let mut state = cloned_state_mutex.lock().unwrap();
+        let mut state = lock_state(&cloned_state_mutex);
         // Get the keys of all tasks that should be resumed
         let task_ids = match tasks {
             TaskSelection::TaskIds(task_ids) => task_ids,
@@ -48,6 +49,32 @@ impl TaskHandler {
                 );
                 matching
             }
+            TaskSelection::Tag(tag) => {
+                info!("Killing tasks with tag {}", &tag);
+                let (matching, _) = state.filter_tasks_with_tag(
+                    |task| matches!(task.status, TaskStatus::Running | TaskStatus::Paused),
+                    &tag,
+                );
+                matching
+            }
+            TaskSelection::Array(array) => {
+                info!("Killing tasks of array {}", array);
+                // The remaining tasks of the array are killed as well, so they're never started.
+                if signal.is_none() {
+                    for task in state.tasks.values_mut() {
+                        if task.array == Some(array) && task.is_queued() {
+                            task.status = TaskStatus::Done(TaskResult::Killed);
+                            task.end = Some(Local::now());
+                        }
+                    }
+                }
+
+                let (matching, _) = state.filter_tasks_of_array(
+                    |task| matches!(task.status, TaskStatus::Running | TaskStatus::Paused),
+                    array,
+                );
+                matching
+            }
             TaskSelection::All => {
                 // Pause all running tasks
                 if pause_groups {
@@ -93,8 +120,8 @@ impl TaskHandler {
     /// Kill a specific task and handle it accordingly.
     /// Triggered on `reset` and `kill`.
     pub fn kill_task(&mut self, task_id: usize, kill_children: bool) {
-        if let Some(mut child) = self.children.get_child_mut(task_id) {
-            kill_child(task_id, &mut child, kill_children);
+        if let Some(child) = self.children.get_child_mut(task_id) {
+            kill_child(task_id, child, kill_children);
         } else {
             warn!("Tried to kill non-existing child: {}", task_id);
         }
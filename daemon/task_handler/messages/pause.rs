@@ -5,7 +5,7 @@ use pueue_lib::state::GroupStatus;
 use pueue_lib::task::TaskStatus;
 
 use crate::ok_or_shutdown;
-use crate::state_helper::{save_state, LockedState};
+use crate::state_helper::{lock_state, save_state, LockedState};
 use crate::task_handler::{ProcessAction, Shutdown, TaskHandler};
 
 impl TaskHandler {
@@ -15,7 +15,7 @@ impl TaskHandler {
     /// `wait` decides, whether running tasks will kept running until they finish on their own.
     pub fn pause(&mut self, tasks: TaskSelection, pause_children: bool, wait: bool) {
         let cloned_state_mutex = self.state.clone();
-        let mut state = cloned_state_mutex.lock().unwrap();
+        let mut state = lock_state(&cloned_state_mutex);
 
         // Get the keys of all tasks that should be paused
         let keys: Vec<usize> = match tasks {
@@ -27,6 +27,7 @@ impl TaskHandler {
                 }
                 // Pause a specific group.
                 state.groups.insert(group.clone(), GroupStatus::Paused);
+                state.pause_reasons.remove(&group);
                 info!("Pausing group {}", &group);
 
                 let (matching, _) = state.filter_tasks_of_group(
@@ -35,6 +36,29 @@ impl TaskHandler {
                 );
                 matching
             }
+            TaskSelection::Tag(tag) => {
+                // Tags don't have a queue of their own, so only the tasks themselves are paused.
+                info!("Pausing tasks with tag {}", &tag);
+                let (matching, _) = state
+                    .filter_tasks_with_tag(|task| matches!(task.status, TaskStatus::Running), &tag);
+                matching
+            }
+            TaskSelection::Array(array) => {
+                // Arrays don't have a queue of their own.
+                // Their queued tasks are stashed instead, so none of them are started.
+                info!("Pausing tasks of array {}", array);
+                for task in state.tasks.values_mut() {
+                    if task.array == Some(array) && task.status == TaskStatus::Queued {
+                        task.status = TaskStatus::Stashed { enqueue_at: None };
+                    }
+                }
+
+                let (matching, _) = state.filter_tasks_of_array(
+                    |task| matches!(task.status, TaskStatus::Running),
+                    array,
+                );
+                matching
+            }
             TaskSelection::All => {
                 // Pause all groups, since we're pausing the whole daemon.
                 state.set_status_for_all_groups(GroupStatus::Paused);
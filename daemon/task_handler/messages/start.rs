@@ -5,7 +5,7 @@ use pueue_lib::state::GroupStatus;
 use pueue_lib::task::TaskStatus;
 
 use crate::ok_or_shutdown;
-use crate::state_helper::{save_state, LockedState};
+use crate::state_helper::{lock_state, save_state, LockedState};
 use crate::task_handler::{ProcessAction, Shutdown, TaskHandler};
 
 impl TaskHandler {
@@ -19,7 +19,7 @@ impl TaskHandler {
     ///     Of course, this only applies to processes that are resumend and not force-spawned.
     pub fn start(&mut self, tasks: TaskSelection, start_children: bool) {
         let cloned_state_mutex = self.state.clone();
-        let mut state = cloned_state_mutex.lock().unwrap();
+        let mut state = lock_state(&cloned_state_mutex);
 
         let task_ids = match tasks {
             TaskSelection::TaskIds(task_ids) => {
@@ -45,6 +45,7 @@ impl TaskHandler {
                 }
                 // Set the group to running.
                 state.groups.insert(group.clone(), GroupStatus::Running);
+                state.pause_reasons.remove(&group);
                 info!("Resuming group {}", &group);
 
                 let (matching, _) = state.filter_tasks_of_group(
@@ -53,6 +54,27 @@ impl TaskHandler {
                 );
                 matching
             }
+            TaskSelection::Tag(tag) => {
+                info!("Resuming tasks with tag {}", &tag);
+                let (matching, _) = state
+                    .filter_tasks_with_tag(|task| matches!(task.status, TaskStatus::Paused), &tag);
+                matching
+            }
+            TaskSelection::Array(array) => {
+                // Stashed tasks of the array, e.g. due to a pause of the array, are enqueued again.
+                info!("Resuming tasks of array {}", array);
+                for task in state.tasks.values_mut() {
+                    if task.array == Some(array)
+                        && matches!(task.status, TaskStatus::Stashed { .. })
+                    {
+                        task.status = TaskStatus::Queued;
+                    }
+                }
+
+                let (matching, _) = state
+                    .filter_tasks_of_array(|task| matches!(task.status, TaskStatus::Paused), array);
+                matching
+            }
             TaskSelection::All => {
                 // Resume all groups and the default queue
                 info!("Resuming everything");
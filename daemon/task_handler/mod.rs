@@ -1,39 +1,86 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
 use std::process::Child;
 use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use chrono::prelude::*;
 use crossbeam_channel::Receiver;
+use glob::Pattern;
 use handlebars::Handlebars;
 use log::{debug, error, info};
 
 use pueue_lib::log::*;
 use pueue_lib::network::message::*;
 use pueue_lib::network::protocol::socket_cleanup;
-use pueue_lib::state::{GroupStatus, SharedState};
-use pueue_lib::task::{Task, TaskResult, TaskStatus};
+use pueue_lib::settings::{ChatNotification, Mail, NotificationPolicy, Watcher};
+use pueue_lib::state::GroupStatus;
+use pueue_lib::task::{DependencyMode, Task, TaskResult, TaskStatus};
 
+use crate::lua_script::LuaScript;
 use crate::pid::cleanup_pid_file;
 use crate::platform::process_helper::*;
-use crate::state_helper::{reset_state, save_state};
+use crate::state_helper::{
+    flush_state, lock_state, reset_state, save_pending_state, save_state, SharedState,
+};
+use crate::wasm_policy::WasmPolicies;
 
 mod callback;
+/// The queue, through which all callbacks are executed.
+mod callback_queue;
+/// Messages about finished tasks for Slack, Discord and Matrix.
+mod chat;
 /// A helper newtype struct, which implements convenience methods for our child process management
 /// datastructure.
 mod children;
 /// Logic for handling dependencies
 mod dependencies;
+/// Hold back new tasks, while the disk is running full.
+mod disk_space;
 /// Logic for finishing and cleaning up completed tasks.
 mod finish_task;
+/// Checks that have to pass, before the tasks of a group are started.
+mod group_conditions;
+/// Pause and resume groups according to their scheduling windows.
+mod group_windows;
+/// Hold back the tasks of groups, while the system load is too high.
+mod load_limits;
+/// Rotate the log files of running tasks, once they get too large.
+mod log_rotation;
+/// E-mail notifications about failed tasks and finished groups.
+mod mail;
 /// This module contains all logic that's triggered by messages received via the mpsc channel.
 /// These messages are sent by the threads that handle the client messages.
 mod messages;
+/// Write the output of tasks to their logs, if it has to be redacted or merged.
+mod output_forwarding;
+/// Timers for periodic checks with exponential backoff and jitter.
+mod poller;
+/// Remove sensitive data from the output of tasks.
+mod redaction;
+/// Look up the secrets of groups, when their tasks are started.
+mod secrets;
 /// Everything regarding actually spawning task processes.
 mod spawn_task;
+/// External conditions, which have to be met before a queued task is started.
+mod start_conditions;
+/// Watched directories, which enqueue a task for each new or modified file.
+mod watchers;
 
+use callback_queue::CallbackQueue;
 use children::Children;
+use group_conditions::{init_group_conditions, ConditionCheck};
+use group_windows::{init_group_windows, WindowCheck};
+use load_limits::{init_load_limits, LoadLimitCheck, SYSTEM_LOAD_INTERVAL};
+use log_rotation::{LOG_SIZE_INTERVAL, MAX_LOG_SIZE_INTERVAL};
+use output_forwarding::OutputForward;
+use poller::Poller;
+use redaction::compile_redact_patterns;
+use secrets::SecretLookup;
+use start_conditions::StartConditionCheck;
+use watchers::{compile_watchers, WatchedFile, MAX_WATCHER_INTERVAL, WATCHER_INTERVAL};
 
 /// This is a little helper macro, which looks at a critical result and shuts the
 /// TaskHandler down, if an error occurred. This is mostly used if the state cannot.
@@ -66,19 +113,83 @@ pub struct TaskHandler {
     /// A mapping with this structure:
     /// BTreeMap<group, BTreeMap<group_worker_id, (task_id, Subprocess handle)>
     children: Children,
-    /// These are the currently running callbacks. They're usually very short-lived.
-    callbacks: Vec<Child>,
+    /// The queued and currently running callbacks. They're usually very short-lived.
+    callbacks: CallbackQueue,
     /// A simple flag which is used to signal that we're currently doing a full reset of the daemon.
     /// This flag prevents new tasks from being spawned.
     full_reset: bool,
     /// Whether we're currently in the process of a graceful shutdown.
     /// Depending on the shutdown type, we're exiting with different exitcodes.
     shutdown: Option<Shutdown>,
+    /// Whether the daemon is a read-only mirror of another daemon.
+    /// A mirror doesn't run any tasks, so the task handler only waits for the shutdown.
+    mirror: bool,
 
     // Some static settings that are extracted from `state.settings` for convenience purposes.
     pueue_directory: PathBuf,
     callback: Option<String>,
     callback_log_lines: usize,
+    /// All watched directories with their compiled glob patterns.
+    watchers: Vec<(Watcher, Pattern)>,
+    /// The last known state of all files inside of watched directories.
+    watched_files: HashMap<PathBuf, WatchedFile>,
+    /// Decides when the watched directories are scanned next.
+    watcher_poller: Poller,
+    /// Shut down, once the daemon has been idle for this long.
+    exit_when_idle: Option<Duration>,
+    /// The last time the daemon had something to do.
+    idle_since: Instant,
+    /// Don't start new tasks, while less than this amount of bytes is available.
+    min_free_disk: Option<u64>,
+    /// The command that's called, once new tasks are held back due to low disk space.
+    low_disk_callback: Option<String>,
+    /// Whether new tasks are currently held back due to low disk space.
+    low_disk_space: bool,
+    /// The conditions of groups, which only start tasks while a check command succeeds.
+    group_conditions: BTreeMap<String, ConditionCheck>,
+    /// The load limits of groups, which only start tasks while the system isn't too busy.
+    load_limits: BTreeMap<String, LoadLimitCheck>,
+    /// Decides when the system load is sampled next.
+    system_load_poller: Poller,
+    /// The scheduling windows of groups, which are paused outside of them.
+    group_windows: BTreeMap<String, WindowCheck>,
+    /// The start conditions of queued tasks, which wait for files or urls.
+    start_conditions: HashMap<usize, StartConditionCheck>,
+    /// The default maximum runtime of the tasks of each group.
+    group_max_runtimes: BTreeMap<String, Duration>,
+    /// Tasks that have been killed due to exceeding their maximum runtime. \
+    /// They're marked as `FailedTimeout` instead of `Killed`, once they finished.
+    timed_out: HashSet<usize>,
+    /// The threads, which write the stdout of running tasks to their logs, if it's redacted.
+    stdout_forwards: HashMap<usize, OutputForward>,
+    /// The threads, which write the stderr of running tasks to their logs, if it's redacted or
+    /// written to their stdout log.
+    stderr_forwards: HashMap<usize, OutputForward>,
+    /// The secrets of tasks, that are currently being looked up.
+    secret_lookups: HashMap<usize, SecretLookup>,
+    /// The secrets of queued tasks, that are passed to them once they're started. \
+    /// They're only kept in memory.
+    resolved_secrets: HashMap<usize, BTreeMap<String, String>>,
+    /// Matches of these patterns are removed from the output of tasks, before it's written.
+    redact_patterns: Vec<regex::bytes::Regex>,
+    /// The settings for e-mail notifications.
+    mail: Option<Mail>,
+    /// The finished tasks of each group, that haven't been included in a digest mail yet.
+    mail_digests: BTreeMap<String, Vec<usize>>,
+    /// The chats, which are notified about finished tasks.
+    chat_notifications: Vec<ChatNotification>,
+    /// When notifications about the tasks of each group are sent.
+    group_notifications: BTreeMap<String, NotificationPolicy>,
+    /// Log files of running tasks are rotated, once they exceed this amount of bytes.
+    max_log_size: Option<u64>,
+    /// How many rotated copies of each log file are kept.
+    rotated_logs: usize,
+    /// Decides when the size of the log files is checked next.
+    log_size_poller: Poller,
+    /// The WASM policy modules, which are informed about started and finished tasks.
+    wasm_policies: WasmPolicies,
+    /// The user's Lua script, which may format callbacks and evaluate the results of tasks.
+    lua_script: Option<LuaScript>,
 }
 
 /// Pueue directly interacts with processes.
@@ -92,10 +203,15 @@ pub enum ProcessAction {
 }
 
 impl TaskHandler {
-    pub fn new(shared_state: SharedState, receiver: Receiver<Message>) -> Self {
+    pub fn new(
+        shared_state: SharedState,
+        receiver: Receiver<Message>,
+        wasm_policies: WasmPolicies,
+        lua_script: Option<LuaScript>,
+    ) -> Self {
         // Clone the pointer, as we need to access it, but also put it into the TaskHandler.
         let state_clone = shared_state.clone();
-        let state = state_clone.lock().unwrap();
+        let state = lock_state(&state_clone);
 
         // Initialize the subprocess management structure.
         let mut pools = BTreeMap::new();
@@ -103,37 +219,106 @@ impl TaskHandler {
             pools.insert(group.clone(), BTreeMap::new());
         }
 
-        TaskHandler {
+        let mut handler = TaskHandler {
             state: shared_state,
             receiver,
             children: Children(pools),
-            callbacks: Vec::new(),
+            callbacks: CallbackQueue::new(&state.settings.daemon),
             full_reset: false,
             shutdown: None,
+            mirror: state.settings.daemon.mirror.is_some(),
             pueue_directory: state.settings.shared.pueue_directory(),
             callback: state.settings.daemon.callback.clone(),
             callback_log_lines: state.settings.daemon.callback_log_lines,
-        }
+            watchers: compile_watchers(&state.settings.daemon.watchers),
+            watched_files: HashMap::new(),
+            watcher_poller: Poller::with_backoff(WATCHER_INTERVAL, MAX_WATCHER_INTERVAL),
+            exit_when_idle: state.settings.daemon.exit_when_idle(),
+            idle_since: Instant::now(),
+            min_free_disk: state.settings.daemon.min_free_disk(),
+            low_disk_callback: state.settings.daemon.low_disk_callback.clone(),
+            low_disk_space: false,
+            group_conditions: init_group_conditions(&state.settings.daemon.group_conditions),
+            load_limits: init_load_limits(&state.settings.daemon.group_load_limits),
+            system_load_poller: Poller::with_backoff(SYSTEM_LOAD_INTERVAL, SYSTEM_LOAD_INTERVAL),
+            group_windows: init_group_windows(&state.settings.daemon.group_windows),
+            start_conditions: HashMap::new(),
+            group_max_runtimes: state.settings.daemon.group_max_runtimes(),
+            timed_out: HashSet::new(),
+            stdout_forwards: HashMap::new(),
+            stderr_forwards: HashMap::new(),
+            secret_lookups: HashMap::new(),
+            resolved_secrets: HashMap::new(),
+            redact_patterns: compile_redact_patterns(&state.settings.daemon.redact_patterns),
+            mail: state.settings.daemon.mail.clone(),
+            mail_digests: BTreeMap::new(),
+            chat_notifications: state.settings.daemon.chat_notifications.clone(),
+            group_notifications: state.settings.daemon.group_notifications.clone(),
+            max_log_size: state.settings.daemon.max_log_size(),
+            rotated_logs: state.settings.daemon.rotated_logs,
+            log_size_poller: Poller::with_backoff(LOG_SIZE_INTERVAL, MAX_LOG_SIZE_INTERVAL),
+            wasm_policies,
+            lua_script,
+        };
+        // Release the lock, as the initial scan needs access to the state.
+        drop(state);
+
+        // Register all files that already exist, so they aren't processed again.
+        handler.check_watchers(true);
+
+        handler
     }
 
     /// Main loop of the task handler.
-    /// In here a few things happen:
+    /// The task handler of a mirror only handles the shutdown.
+    /// Otherwise, a few things happen in here:
     ///
     /// - Receive and handle instructions from the client.
     /// - Handle finished tasks, i.e. cleanup processes, update statuses.
     /// - Callback handling logic. This is rather uncritical.
+    /// - Send digest mails for groups, whose tasks have all finished.
     /// - Enqueue any stashed processes which are ready for being queued.
+    /// - Expire all tasks that haven't been started before their deadline.
+    /// - Kill all tasks that exceeded their maximum runtime.
+    /// - Rotate the log files of running tasks, that exceed the maximum log size.
+    /// - Enqueue tasks for new or modified files in watched directories.
     /// - Ensure tasks with dependencies have no failed ancestors
+    /// - Shut down, if the daemon has been idle for too long.
+    /// - Check the conditions of groups, which only start tasks while a check passes.
+    /// - Check the load limits of groups, which only start tasks while the system isn't too busy.
+    /// - Pause and resume groups, once their scheduling windows close or open.
+    /// - Check the start conditions of queued tasks, which wait for files or urls.
+    /// - Save delayed changes of the state, once they're due.
     /// - Whether whe should perform a shutdown.
     /// - If the client requested a reset: reset the state if all children have been killed and handled.
     /// - Check whether we can spawn new tasks.
     pub fn run(&mut self) {
         loop {
+            if self.mirror {
+                self.receive_messages();
+                if self.shutdown.is_some() {
+                    self.handle_shutdown();
+                }
+                continue;
+            }
+
             self.receive_messages();
             self.handle_finished_tasks();
+            self.check_mail_digests();
             self.check_callbacks();
             self.enqueue_delayed_tasks();
+            self.expire_tasks();
+            self.kill_timed_out_tasks();
+            self.rotate_logs();
+            self.check_watchers(false);
             self.check_failed_dependencies();
+            self.check_idle();
+            self.check_group_conditions();
+            self.check_load_limits();
+            self.check_group_windows();
+            self.check_start_conditions();
+            self.collect_secrets();
+            self.save_delayed_changes();
 
             if self.shutdown.is_some() {
                 // Check if we're in shutdown.
@@ -153,12 +338,55 @@ impl TaskHandler {
     /// Initiate shutdown, which includes killing all children and pausing all groups.
     /// We don't have to pause any groups, as no new tasks will be spawned during shutdown anyway.
     /// Any groups with queued tasks, will be automatically paused on state-restoration.
+    ///
+    /// When draining, running tasks aren't killed and the daemon waits for them to finish instead.
+    /// Paused tasks have to be resumed or killed by the user.
     fn initiate_shutdown(&mut self, shutdown: Shutdown) {
+        let drain = shutdown == Shutdown::Drain;
         self.shutdown = Some(shutdown);
 
+        if drain {
+            info!("Waiting for all running tasks to finish, before shutting down.");
+            return;
+        }
         self.kill(TaskSelection::All, false, false, None);
     }
 
+    /// Initiate a graceful shutdown, once the daemon has been idle for longer than
+    /// `daemon.exit_when_idle`. \
+    /// The daemon is idle, if no callbacks are running and all tasks are either done or stashed
+    /// without being scheduled. Nothing is lost, as those tasks are restored on the next start.
+    fn check_idle(&mut self) {
+        let exit_when_idle = match self.exit_when_idle {
+            Some(exit_when_idle) => exit_when_idle,
+            None => return,
+        };
+        if self.shutdown.is_some() {
+            return;
+        }
+
+        let busy = !self.callbacks.is_empty()
+            || self.children.has_active_tasks()
+            || lock_state(&self.state).tasks.values().any(|task| {
+                !matches!(
+                    task.status,
+                    TaskStatus::Done(_) | TaskStatus::Stashed { enqueue_at: None }
+                )
+            });
+        if busy {
+            self.idle_since = Instant::now();
+            return;
+        }
+
+        if self.idle_since.elapsed() >= exit_when_idle {
+            info!(
+                "The daemon has been idle for {} seconds. Shutting down.",
+                exit_when_idle.as_secs()
+            );
+            self.initiate_shutdown(Shutdown::Graceful);
+        }
+    }
+
     /// Check if all tasks are killed.
     /// If they aren't, we'll wait a little longer.
     /// Once they're, we do some cleanup and exit.
@@ -169,7 +397,16 @@ impl TaskHandler {
         }
 
         // Lock the state. This prevents any further connections/alterations from this point on.
-        let state = self.state.lock().unwrap();
+        let state = lock_state(&self.state);
+
+        // Callbacks of the killed tasks would be lost otherwise.
+        self.callbacks.flush();
+
+        // Changes might still be pending, if saving the state is delayed.
+        if let Err(error) = flush_state(&state) {
+            println!("Failed to save state during shutdown.");
+            println!("{}", error);
+        }
 
         // Remove the unix socket.
         if let Err(error) = socket_cleanup(&state.settings.shared) {
@@ -202,14 +439,24 @@ impl TaskHandler {
             return;
         }
 
-        let mut state = self.state.lock().unwrap();
+        let mut state = lock_state(&self.state);
+        // Tasks may still have logs in previous log directories.
+        let mut log_directories: BTreeSet<PathBuf> = state
+            .tasks
+            .values()
+            .map(|task| task.log_directory(&self.pueue_directory))
+            .collect();
+        log_directories.insert(state.settings.shared.log_directory());
+
         if let Err(error) = reset_state(&mut state) {
             error!("Failed to reset state with error: {:?}", error);
         };
 
-        if let Err(error) = reset_task_log_directory(&self.pueue_directory) {
-            panic!("Error while resetting task log directory: {}", error);
-        };
+        for log_directory in log_directories.iter().filter(|dir| dir.exists()) {
+            if let Err(error) = reset_task_log_directory(log_directory) {
+                panic!("Error while resetting task log directory: {}", error);
+            };
+        }
         self.full_reset = false;
     }
 
@@ -224,7 +471,7 @@ impl TaskHandler {
     /// Gather all stashed tasks and enqueue them if it is after the task's enqueue_at
     fn enqueue_delayed_tasks(&mut self) {
         let state_clone = self.state.clone();
-        let mut state = state_clone.lock().unwrap();
+        let mut state = lock_state(&state_clone);
 
         let mut changed = false;
         for (_, task) in state.tasks.iter_mut() {
@@ -246,6 +493,77 @@ impl TaskHandler {
         }
     }
 
+    /// Mark all queued or stashed tasks as expired, once their deadline has passed.
+    /// Tasks that depend on them will then fail via `check_failed_dependencies`.
+    fn expire_tasks(&mut self) {
+        let state_clone = self.state.clone();
+        let mut state = lock_state(&state_clone);
+
+        let mut changed = false;
+        for (_, task) in state.tasks.iter_mut() {
+            if !matches!(task.status, TaskStatus::Queued | TaskStatus::Stashed { .. }) {
+                continue;
+            }
+            match task.expire_at {
+                Some(time) if time <= Local::now() => (),
+                _ => continue,
+            }
+            info!("Task {} hasn't been started before its deadline", task.id);
+
+            task.status = TaskStatus::Done(TaskResult::Expired);
+            task.start = Some(Local::now());
+            task.end = Some(Local::now());
+            self.spawn_callback(task);
+            changed = true;
+        }
+
+        if changed {
+            ok_or_shutdown!(self, save_state(&state));
+        }
+    }
+
+    /// Save delayed changes of the state, once they're due. \
+    /// Changes are only delayed, if `daemon.state_save_mode` isn't `every-change`.
+    fn save_delayed_changes(&mut self) {
+        let state_clone = self.state.clone();
+        let state = lock_state(&state_clone);
+        ok_or_shutdown!(self, save_pending_state(&state));
+    }
+
+    /// Kill all tasks that have been running for longer than their maximum runtime. \
+    /// The runtime is measured from the start of the task, time spent paused is included.
+    fn kill_timed_out_tasks(&mut self) {
+        let state = lock_state(&self.state);
+
+        let mut timed_out = Vec::new();
+        for task in state.tasks.values() {
+            if !task.is_running() || self.timed_out.contains(&task.id) {
+                continue;
+            }
+            let max_runtime = match task.max_runtime {
+                Some(seconds) => Duration::from_secs(seconds),
+                None => match self.group_max_runtimes.get(&task.group) {
+                    Some(max_runtime) => *max_runtime,
+                    None => continue,
+                },
+            };
+            let runtime = match task.start {
+                Some(start) => (Local::now() - start).to_std().unwrap_or_default(),
+                None => continue,
+            };
+            if runtime >= max_runtime {
+                timed_out.push(task.id);
+            }
+        }
+        drop(state);
+
+        for task_id in timed_out {
+            info!("Task {} exceeded its maximum runtime. Killing it", task_id);
+            self.kill_task(task_id, true);
+            self.timed_out.insert(task_id);
+        }
+    }
+
     /// This is a small wrapper around the real platform dependant process handling logic
     /// It only ensures, that the process we want to manipulate really does exists.
     fn perform_action(&mut self, id: usize, action: ProcessAction, children: bool) -> Result<bool> {
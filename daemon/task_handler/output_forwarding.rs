@@ -0,0 +1,121 @@
+use std::fs::{metadata, File};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam_channel::bounded;
+use regex::bytes::Regex;
+
+use super::redaction::redact;
+use super::*;
+
+/// How long the daemon waits for the output of a finished task to be written to its logs. \
+/// Processes, that have been started by the task, might still hold its pipes.
+const FORWARD_TIMEOUT: Duration = Duration::from_millis(500);
+/// Longer lines are split, before they're redacted and written.
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// A thread, which copies the output of a task into one of its log files.
+pub struct OutputForward {
+    /// Set, as soon as the task wrote anything.
+    written: Arc<AtomicBool>,
+    /// Disconnects, once the thread exited.
+    done: Receiver<()>,
+}
+
+impl OutputForward {
+    /// Wait until all output of the finished task has been written.
+    /// Returns whether the task wrote anything.
+    fn finish(&self) -> bool {
+        let _ = self.done.recv_timeout(FORWARD_TIMEOUT);
+        self.written.load(Ordering::Relaxed)
+    }
+}
+
+/// Copy the output of a task into a log file in a separate thread. \
+/// If there are any patterns, their matches are replaced with `***`, before the output is
+/// written. For this, the output is written line by line, which is why matches can't span
+/// multiple lines. `\r` ends a line as well, so progress bars show up in time.
+///
+/// The thread exits, once all processes that hold the pipe finished.
+pub fn forward_output<R>(mut output: R, mut log: File, patterns: Arc<Vec<Regex>>) -> OutputForward
+where
+    R: Read + Send + 'static,
+{
+    let written = Arc::new(AtomicBool::new(false));
+    let written_clone = written.clone();
+    // Nothing is ever sent. The receiver only notices, once the sender is dropped.
+    let (sender, done) = bounded::<()>(0);
+
+    thread::spawn(move || {
+        let _sender = sender;
+        let mut buffer = [0; 8192];
+        let mut lines = Vec::new();
+        loop {
+            let read = match output.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            written_clone.store(true, Ordering::Relaxed);
+            if patterns.is_empty() {
+                if !write_output(&mut log, &buffer[..read]) {
+                    return;
+                }
+                continue;
+            }
+
+            lines.extend_from_slice(&buffer[..read]);
+            let end = match lines
+                .iter()
+                .rposition(|byte| *byte == b'\n' || *byte == b'\r')
+            {
+                Some(position) => position + 1,
+                None if lines.len() >= MAX_LINE_LENGTH => lines.len(),
+                None => continue,
+            };
+            let rest = lines.split_off(end);
+            if !write_output(&mut log, &redact(&lines, &patterns)) {
+                return;
+            }
+            lines = rest;
+        }
+
+        if !lines.is_empty() {
+            write_output(&mut log, &redact(&lines, &patterns));
+        }
+    });
+
+    OutputForward { written, done }
+}
+
+/// Write output to a log file. Returns `false`, if that failed.
+fn write_output(log: &mut File, output: &[u8]) -> bool {
+    match log.write_all(output) {
+        Ok(()) => true,
+        Err(err) => {
+            error!("Failed to write the output of a task to its log: {}", err);
+            false
+        }
+    }
+}
+
+impl TaskHandler {
+    /// Wait for the output of a finished task to be written to its logs and record whether it
+    /// wrote anything to stderr.
+    pub fn finish_output(&mut self, task: &mut Task) {
+        if let Some(forward) = self.stdout_forwards.remove(&task.id) {
+            forward.finish();
+        }
+        task.wrote_stderr = match self.stderr_forwards.remove(&task.id) {
+            Some(forward) => forward.finish(),
+            None => {
+                let (_, err_path) =
+                    get_task_log_paths(task.id, &task.log_directory(&self.pueue_directory));
+                // Rotated copies exist, if the log has been truncated in the meantime.
+                metadata(&err_path).is_ok_and(|metadata| metadata.len() > 0)
+                    || get_rotated_log_path(&err_path, 1).exists()
+            }
+        };
+    }
+}
@@ -0,0 +1,89 @@
+use rand::Rng;
+
+use super::*;
+
+/// Intervals are randomly stretched or shortened by up to this fraction.
+/// This way, many checks with the same interval don't all wake up at the same time.
+const JITTER: f64 = 0.1;
+
+/// Decides when a periodic check is due.
+///
+/// As long as checks don't find anything to do or fail, the interval doubles after each check,
+/// up to the maximum interval. Once something happens, it's reset to the base interval.
+pub struct Poller {
+    /// The interval after a check, that found something to do.
+    interval: Duration,
+    /// The interval doesn't grow any further than this.
+    max_interval: Duration,
+    /// The current interval, which grows while there's nothing to do.
+    current: Duration,
+    /// When the next check is due. The first check is due immediately.
+    next_poll: Option<Instant>,
+}
+
+impl Poller {
+    /// A poller, whose interval grows up to `max_interval`, while there's nothing to do.
+    pub fn with_backoff(interval: Duration, max_interval: Duration) -> Self {
+        Poller {
+            interval,
+            max_interval: max_interval.max(interval),
+            current: interval,
+            next_poll: None,
+        }
+    }
+
+    /// Whether the next check should be done now.
+    pub fn is_due(&self) -> bool {
+        self.next_poll
+            .is_none_or(|next_poll| Instant::now() >= next_poll)
+    }
+
+    /// The last check found something to do. The next one is due after the base interval.
+    pub fn reset(&mut self) {
+        self.current = self.interval;
+        self.schedule();
+    }
+
+    /// The last check found nothing to do or failed. The interval is doubled.
+    pub fn back_off(&mut self) {
+        self.schedule();
+        self.current = (self.current * 2).min(self.max_interval);
+    }
+
+    fn schedule(&mut self) {
+        let factor = rand::thread_rng().gen_range(1.0 - JITTER..=1.0 + JITTER);
+        self.next_poll = Some(Instant::now() + self.current.mul_f64(factor));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_back_off() {
+        let mut poller = Poller::with_backoff(Duration::from_secs(1), Duration::from_secs(5));
+        assert!(poller.is_due());
+
+        let mut intervals = Vec::new();
+        for _ in 0..5 {
+            poller.back_off();
+            intervals.push(poller.current.as_secs());
+        }
+        assert_eq!(intervals, [2, 4, 5, 5, 5]);
+        assert!(!poller.is_due());
+
+        poller.reset();
+        assert_eq!(poller.current, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let mut poller = Poller::with_backoff(Duration::from_secs(10), Duration::from_secs(10));
+        poller.reset();
+
+        let wait = poller.next_poll.unwrap() - Instant::now();
+        assert!(wait > Duration::from_secs(8) && wait <= Duration::from_secs(11));
+    }
+}
@@ -0,0 +1,29 @@
+use log::error;
+use regex::bytes::{NoExpand, Regex};
+
+/// Compile the patterns of the `redact_patterns` setting. \
+/// Invalid patterns are already rejected, when the daemon starts.
+pub fn compile_redact_patterns(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| match Regex::new(pattern) {
+            Ok(regex) => Some(regex),
+            Err(err) => {
+                error!("Invalid redact pattern {:?}: {}", pattern, err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Replace all matches of the patterns with `***`.
+pub fn redact(output: &[u8], patterns: &[Regex]) -> Vec<u8> {
+    let mut redacted = output.to_vec();
+    for pattern in patterns {
+        redacted = pattern
+            .replace_all(&redacted, NoExpand(b"***"))
+            .into_owned();
+    }
+
+    redacted
+}
@@ -0,0 +1,242 @@
+use std::io::Read;
+use std::thread;
+
+use anyhow::{bail, Context};
+use crossbeam_channel::{bounded, TryRecvError};
+use regex::bytes::Regex;
+
+use super::*;
+
+use crate::state_helper::LockedState;
+
+/// How often a running `secret_command` is checked for completion.
+const SECRET_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The lookup of the secrets of a task, that runs in the background.
+pub struct SecretLookup {
+    /// Whether the task has been started explicitly and is started as soon as its secrets
+    /// are available. Otherwise, it waits for the scheduler like any other queued task.
+    forced: bool,
+    /// Receives the values of the secrets, once they have been looked up.
+    receiver: Receiver<Result<BTreeMap<String, String>>>,
+}
+
+/// Look up the values of the secrets of a group via the `secret_command`. \
+/// The values must neither be saved nor logged.
+///
+/// Commands, that don't finish in time, are killed.
+pub fn resolve_secrets(
+    secret_command: &Option<String>,
+    names: &[String],
+    timeout: Duration,
+) -> Result<BTreeMap<String, String>> {
+    let secret_command = match secret_command {
+        Some(secret_command) => secret_command,
+        None => bail!("daemon.secret_command isn't set"),
+    };
+
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+
+    let mut secrets = BTreeMap::new();
+    for name in names {
+        let mut parameters = HashMap::new();
+        parameters.insert("name", shell_escape::escape(name.into()).to_string());
+        let command = handlebars
+            .render_template(secret_command, &parameters)
+            .context("Failed to render daemon.secret_command")?;
+
+        let mut child = compile_shell_command(&command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to look up secret {}", name))?;
+
+        // Secrets are small, so their output fits into the pipe, until the command finished.
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child
+                .try_wait()
+                .with_context(|| format!("Failed to look up secret {}", name))?
+            {
+                break status;
+            }
+            if started.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                bail!(
+                    "Looking up secret {} took longer than {}s",
+                    name,
+                    timeout.as_secs()
+                );
+            }
+            thread::sleep(SECRET_POLL_INTERVAL);
+        };
+        if !status.success() {
+            bail!("Failed to look up secret {}: {}", name, status);
+        }
+
+        let mut output = Vec::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout
+                .read_to_end(&mut output)
+                .with_context(|| format!("Failed to read secret {}", name))?;
+        }
+        let value = String::from_utf8(output)
+            .with_context(|| format!("Secret {} isn't valid UTF-8", name))?;
+        let value = value.strip_suffix('\n').unwrap_or(&value);
+        let value = value.strip_suffix('\r').unwrap_or(value);
+        secrets.insert(name.clone(), value.to_string());
+    }
+
+    Ok(secrets)
+}
+
+/// A pattern, which matches the values of all secrets. \
+/// Longer values come first, so values that contain others are completely replaced.
+pub fn secret_pattern(secrets: &BTreeMap<String, String>) -> Option<Regex> {
+    let mut values: Vec<&String> = secrets.values().filter(|value| !value.is_empty()).collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+    let alternatives: Vec<String> = values.iter().map(|value| regex::escape(value)).collect();
+
+    match Regex::new(&alternatives.join("|")) {
+        Ok(pattern) => Some(pattern),
+        Err(err) => {
+            error!("Failed to build the pattern to redact secrets: {}", err);
+            None
+        }
+    }
+}
+
+impl TaskHandler {
+    /// Whether the secrets of a task still have to be looked up, before it can be started.
+    pub fn needs_secrets(&self, state: &LockedState, task_id: usize) -> bool {
+        state
+            .settings
+            .daemon
+            .group_secrets
+            .contains_key(&state.tasks[&task_id].group)
+            && !self.resolved_secrets.contains_key(&task_id)
+    }
+
+    /// Whether the secrets of a task are currently being looked up.
+    pub fn looks_up_secrets(&self, task_id: usize) -> bool {
+        self.secret_lookups.contains_key(&task_id)
+    }
+
+    /// Look up the secrets of a task in a separate thread, so a slow `secret_command` doesn't
+    /// block the daemon.
+    pub fn lookup_secrets(&mut self, state: &LockedState, task_id: usize, forced: bool) {
+        if let Some(lookup) = self.secret_lookups.get_mut(&task_id) {
+            lookup.forced |= forced;
+            return;
+        }
+
+        let task = &state.tasks[&task_id];
+        let names = state.settings.daemon.group_secrets[&task.group].clone();
+        let secret_command = state.settings.daemon.secret_command.clone();
+        let timeout = Duration::from_secs(state.settings.daemon.secret_timeout);
+
+        let (sender, receiver) = bounded(1);
+        thread::spawn(move || {
+            let _ = sender.send(resolve_secrets(&secret_command, &names, timeout));
+        });
+        self.secret_lookups
+            .insert(task_id, SecretLookup { forced, receiver });
+    }
+
+    /// Collect the results of finished secret lookups. \
+    /// Tasks, that have been started explicitly, are started right away.
+    /// Tasks, whose secrets couldn't be looked up, fail to spawn.
+    pub fn collect_secrets(&mut self) {
+        if self.secret_lookups.is_empty() && self.resolved_secrets.is_empty() {
+            return;
+        }
+
+        let state_clone = self.state.clone();
+        let mut state = lock_state(&state_clone);
+        let mut finished = Vec::new();
+        for (task_id, lookup) in self.secret_lookups.iter() {
+            match lookup.receiver.try_recv() {
+                Ok(result) => finished.push((*task_id, lookup.forced, result)),
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => finished.push((
+                    *task_id,
+                    lookup.forced,
+                    Err(anyhow::anyhow!(
+                        "The lookup of the secrets stopped unexpectedly"
+                    )),
+                )),
+            }
+        }
+
+        for (task_id, forced, result) in finished {
+            self.secret_lookups.remove(&task_id);
+
+            // The task might have been removed or stashed in the meantime.
+            let startable = matches!(
+                state.tasks.get(&task_id).map(|task| &task.status),
+                Some(TaskStatus::Stashed { .. } | TaskStatus::Queued | TaskStatus::Paused)
+            );
+            if !startable {
+                continue;
+            }
+
+            match result {
+                Ok(secrets) => {
+                    self.resolved_secrets.insert(task_id, secrets);
+                    if forced {
+                        self.start_process(task_id, &mut state);
+                    }
+                }
+                Err(err) => {
+                    let group = state.tasks[&task_id].group.clone();
+                    let error = format!(
+                        "Failed to resolve the secrets of group {}: {:#}",
+                        group, err
+                    );
+                    self.fail_to_spawn(task_id, error, &mut state);
+                }
+            }
+        }
+
+        // Forget the secrets of tasks, that are no longer waiting to be started.
+        self.resolved_secrets.retain(|task_id, _| {
+            matches!(state.tasks.get(task_id), Some(task) if task.status == TaskStatus::Queued)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    use super::super::redaction::redact;
+
+    #[test]
+    fn test_secret_pattern() {
+        let mut secrets = BTreeMap::new();
+        secrets.insert("SHORT".to_string(), "hunter2".to_string());
+        secrets.insert("LONG".to_string(), "hunter2.s3cr3t".to_string());
+        secrets.insert("EMPTY".to_string(), String::new());
+        let pattern = secret_pattern(&secrets).unwrap();
+
+        let redacted = redact(
+            b"key=hunter2.s3cr3t, old=hunter2, x=hunter2s3cr3t",
+            &[pattern],
+        );
+        assert_eq!(
+            String::from_utf8(redacted).unwrap(),
+            "key=***, old=***, x=***s3cr3t"
+        );
+
+        secrets.clear();
+        assert!(secret_pattern(&secrets).is_none());
+    }
+}
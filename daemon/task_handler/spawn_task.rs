@@ -1,3 +1,12 @@
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::bail;
+use handlebars::RenderError;
+use log::warn;
+
+use super::output_forwarding::forward_output;
+use super::secrets::secret_pattern;
 use super::*;
 
 use crate::ok_or_shutdown;
@@ -7,10 +16,19 @@ impl TaskHandler {
     /// See if we can start a new queued task.
     pub fn spawn_new(&mut self) {
         let cloned_state_mutex = self.state.clone();
-        let mut state = cloned_state_mutex.lock().unwrap();
+        let mut state = lock_state(&cloned_state_mutex);
         // Check whether a new task can be started.
         // Spawn tasks until we no longer have free slots available.
         while let Some(id) = self.get_next_task_id(&state) {
+            // Only the scheduler is held back. Tasks can still be started explicitly.
+            if !self.has_free_disk_space() {
+                break;
+            }
+            // Tasks with secrets are started, once their secrets have been looked up.
+            if self.needs_secrets(&state, id) {
+                self.lookup_secrets(&state, id, false);
+                continue;
+            }
             self.start_process(id, &mut state);
         }
     }
@@ -20,7 +38,15 @@ impl TaskHandler {
     /// - is in Queued state
     /// - There are free slots in the task's group
     /// - The group is running
-    /// - has all its dependencies in `Done` state
+    /// - All earlier tasks of the group are done, if it's a serial group
+    /// - The group's condition passed, if it has one
+    /// - The system load is within the group's limits, if it has any
+    /// - Enough CPU slots of the group are free, if it has a CPU capacity
+    /// - Enough units of the requested resource pools are free
+    /// - The task's start conditions are met, if it has any
+    /// - has all its dependencies in `Done` state with results that match its dependency mode
+    /// - Its secrets aren't being looked up right now
+    /// - All WASM policies allow it to be started
     pub fn get_next_task_id(&mut self, state: &LockedState) -> Option<usize> {
         state
             .tasks
@@ -32,6 +58,27 @@ impl TaskHandler {
                 if !matches!(state.groups.get(&task.group), Some(GroupStatus::Running)) {
                     return false;
                 }
+                if !self.group_condition_passed(&task.group)
+                    || !self.load_limit_passed(&task.group)
+                {
+                    return false;
+                }
+                if !self.start_conditions_passed(task) {
+                    return false;
+                }
+                // Serial groups strictly run their tasks in the order of their ids.
+                // Earlier tasks that are stashed or blocked hold back all later tasks.
+                if state.settings.daemon.serial_groups.contains(&task.group)
+                    && state
+                        .tasks
+                        .range(..task.id)
+                        .any(|(_, earlier)| earlier.group == task.group && !earlier.is_done())
+                {
+                    return false;
+                }
+                if !cpus_available(state, task) || !resources_available(state, task) {
+                    return false;
+                }
 
                 // Get the currently running tasks by looking at the actually running processes.
                 // They're sorted by group, which makes this quite convenient.
@@ -57,19 +104,36 @@ impl TaskHandler {
                     }
                 }
             })
-            .find(|(_, task)| {
+            .filter(|(_, task)| {
                 // Check whether all dependencies for this task are fulfilled.
-                task.dependencies
-                    .iter()
-                    .flat_map(|id| state.tasks.get(id))
-                    .all(|task| matches!(task.status, TaskStatus::Done(TaskResult::Success)))
+                let mut dependencies = task.dependencies.iter().flat_map(|id| state.tasks.get(id));
+                match task.dependency_mode {
+                    DependencyMode::Success => {
+                        dependencies.all(|task| task.is_done() && !task.failed())
+                    }
+                    DependencyMode::Failure => {
+                        let dependencies: Vec<&Task> = dependencies.collect();
+                        dependencies.iter().all(|task| task.is_done())
+                            && (dependencies.is_empty()
+                                || dependencies.iter().any(|task| task.failed()))
+                    }
+                    DependencyMode::Any => dependencies.all(|task| task.is_done()),
+                }
             })
+            .filter(|(_, task)| !self.looks_up_secrets(task.id))
+            .find(|(_, task)| self.wasm_policies.allows_start(task))
             .map(|(id, _)| *id)
     }
 
     /// Actually spawn a new sub process
     /// The output of subprocesses is piped into a seperate file for easier access
     pub fn start_process(&mut self, task_id: usize, state: &mut LockedState) {
+        // No new processes are started, while the daemon is shutting down.
+        if self.shutdown.is_some() {
+            info!("Refused to start task {} during shutdown", task_id);
+            return;
+        }
+
         // Check if the task exists and can actually be spawned. Otherwise do an early return.
         match state.tasks.get(&task_id) {
             Some(task) => {
@@ -87,10 +151,32 @@ impl TaskHandler {
             }
         };
 
+        // Secrets are looked up in the background. The task is started, once they're available.
+        if self.needs_secrets(state, task_id) {
+            self.lookup_secrets(state, task_id, true);
+            return;
+        }
+        // Secrets are only passed to the process, so they don't end up in the saved state.
+        let secrets = self.resolved_secrets.remove(&task_id).unwrap_or_default();
+        // The daemon redacts the output before it's written, if there's anything to redact.
+        let mut patterns = self.redact_patterns.clone();
+        patterns.extend(secret_pattern(&secrets));
+        let patterns = Arc::new(patterns);
+        let redact = !patterns.is_empty();
+
+        // Logs are always created in the currently configured log directory.
+        // Logs of a previous run in another directory are removed.
+        let log_directory = state.settings.shared.log_directory();
+        let task = state.tasks.get_mut(&task_id).unwrap();
+        let previous_log_directory = task.log_directory(&self.pueue_directory);
+        if previous_log_directory != log_directory {
+            clean_log_handles(task_id, &previous_log_directory);
+        }
+        task.log_directory = Some(log_directory.clone());
+
         // Try to get the log files to which the output of the process
         // will be written to. Error if this doesn't work!
-        let (stdout_log, stderr_log) = match create_log_file_handles(task_id, &self.pueue_directory)
-        {
+        let (stdout_log, stderr_log) = match create_log_file_handles(task_id, &log_directory) {
             Ok((out, err)) => (out, err),
             Err(err) => {
                 error!("Failed to create child log files: {:?}", err);
@@ -99,70 +185,300 @@ impl TaskHandler {
         };
 
         // Get all necessary info for starting the task
-        let (command, path, group, mut envs) = {
+        let (command, path, group, label, mut envs, shell, stderr_to_stdout) = {
             let task = state.tasks.get(&task_id).unwrap();
             (
                 task.command.clone(),
                 task.path.clone(),
                 task.group.clone(),
+                task.label.clone(),
                 task.envs.clone(),
+                task.shell,
+                task.stderr_to_stdout,
             )
         };
 
+        // The stderr of some tasks is written to their stdout log by the daemon,
+        // so it can still be recorded, whether anything has been written to it.
+        let (stderr, stderr_log) = if stderr_to_stdout {
+            match stdout_log.try_clone() {
+                Ok(copy) => (Stdio::piped(), Some(copy)),
+                Err(err) => {
+                    let error = format!("Failed to clone the stdout log: {}", err);
+                    self.fail_to_spawn(task_id, error, state);
+                    return;
+                }
+            }
+        } else if redact {
+            (Stdio::piped(), Some(stderr_log))
+        } else {
+            (Stdio::from(stderr_log), None)
+        };
+        let (stdout, stdout_log) = if redact {
+            (Stdio::piped(), Some(stdout_log))
+        } else {
+            (Stdio::from(stdout_log), None)
+        };
+
+        // Tasks of some groups are wrapped, e.g. to limit their bandwidth.
+        let command = match state.settings.daemon.group_wrappers.get(&group) {
+            Some(wrapper) => match build_wrapped_command(wrapper, &command, task_id, &group) {
+                Ok(command) => command,
+                Err(err) => {
+                    let error = format!("Failed to build wrapper of group {}: {}", group, err);
+                    self.fail_to_spawn(task_id, error, state);
+                    return;
+                }
+            },
+            None => command,
+        };
+
         // Build the shell command that should be executed.
-        let mut command = compile_shell_command(&command);
+        let mut command = compile_task_command(&command, &path, shell);
+
+        // Tasks with resource limits are started in their own cgroup.
+        if let Err(err) = limit_resources(&mut command, state, task_id) {
+            let error = format!("Failed to limit resources of task {}: {:#}", task_id, err);
+            self.fail_to_spawn(task_id, error, state);
+            return;
+        }
 
         // Determine the worker's id depending on the current group.
         // Inject that info into the environment.
         let worker_id = self.children.get_next_group_worker(&group);
+        envs.insert("PUEUE_TASK_ID".into(), task_id.to_string());
         envs.insert("PUEUE_GROUP".into(), group.clone());
         envs.insert("PUEUE_WORKER_ID".into(), worker_id.to_string());
+        // The environment might be inherited from another task, so we can't leave a stale label.
+        match label {
+            Some(label) => envs.insert("PUEUE_LABEL".into(), label),
+            None => envs.remove("PUEUE_LABEL"),
+        };
+        // Export the units of all requested resources and drop inherited ones.
+        let assigned_resources = assign_resources(state, task_id);
+        for pool in state.settings.daemon.resource_pools.keys() {
+            let key = resource_env_key(pool);
+            match assigned_resources.get(pool) {
+                Some(units) => envs.insert(key, units.join(",")),
+                None => envs.remove(&key),
+            };
+        }
 
         // Spawn the actual subprocess
         let spawned_command = command
             .current_dir(path)
             .stdin(Stdio::piped())
             .envs(envs.clone())
-            .stdout(Stdio::from(stdout_log))
-            .stderr(Stdio::from(stderr_log))
+            .envs(&secrets)
+            .stdout(stdout)
+            .stderr(stderr)
             .spawn();
 
         // Check if the task managed to spawn
-        let child = match spawned_command {
+        let mut child = match spawned_command {
             Ok(child) => child,
             Err(err) => {
                 let error = format!("Failed to spawn child {} with err: {:?}", task_id, err);
-                error!("{}", error);
-                clean_log_handles(task_id, &self.pueue_directory);
-
-                // Update all necessary fields on the task.
-                let group = {
-                    let task = state.tasks.get_mut(&task_id).unwrap();
-                    task.status = TaskStatus::Done(TaskResult::FailedToSpawn(error));
-                    task.start = Some(Local::now());
-                    task.end = Some(Local::now());
-                    self.spawn_callback(task);
-
-                    task.group.clone()
-                };
-
-                pause_on_failure(state, group);
-                ok_or_shutdown!(self, save_state(state));
+                self.fail_to_spawn(task_id, error, state);
                 return;
             }
         };
 
+        if let (Some(stdout), Some(stdout_log)) = (child.stdout.take(), stdout_log) {
+            let forward = forward_output(stdout, stdout_log, patterns.clone());
+            self.stdout_forwards.insert(task_id, forward);
+        }
+        if let (Some(stderr), Some(stderr_log)) = (child.stderr.take(), stderr_log) {
+            let forward = forward_output(stderr, stderr_log, patterns);
+            self.stderr_forwards.insert(task_id, forward);
+        }
+
         // Save the process handle in our self.children datastructure.
         self.children.add_child(&group, worker_id, task_id, child);
 
         let task = state.tasks.get_mut(&task_id).unwrap();
         task.start = Some(Local::now());
         task.status = TaskStatus::Running;
+        task.slot = Some(worker_id);
+        task.log_rotations = 0;
+        task.wrote_stderr = false;
+        task.acknowledged = false;
+        task.assigned_resources = assigned_resources;
         // Overwrite the task's environment variables with the new ones, containing the
-        // PUEUE_WORKER_ID and PUEUE_GROUP variables.
+        // PUEUE_TASK_ID, PUEUE_GROUP, PUEUE_LABEL, PUEUE_WORKER_ID and PUEUE_ASSIGNED_* variables.
         task.envs = envs;
 
         info!("Started task: {}", task.command);
         ok_or_shutdown!(self, save_state(state));
     }
+
+    /// Mark a task as failed, that couldn't be spawned.
+    pub fn fail_to_spawn(&mut self, task_id: usize, error: String, state: &mut LockedState) {
+        error!("{}", error);
+        let log_directory = state.tasks[&task_id].log_directory(&self.pueue_directory);
+        clean_log_handles(task_id, &log_directory);
+
+        // Update all necessary fields on the task.
+        let group = {
+            let task = state.tasks.get_mut(&task_id).unwrap();
+            task.status = TaskStatus::Done(TaskResult::FailedToSpawn(error));
+            task.start = Some(Local::now());
+            task.end = Some(Local::now());
+            self.spawn_callback(task);
+
+            task.group.clone()
+        };
+
+        pause_on_failure(state, group);
+        ok_or_shutdown!(self, save_state(state));
+    }
+}
+
+/// Start tasks with a memory or CPU limit in their own cgroup.
+#[cfg(target_os = "linux")]
+fn limit_resources(command: &mut Command, state: &LockedState, task_id: usize) -> Result<()> {
+    use crate::platform::cgroup::{create_task_cgroup, spawn_in_cgroup};
+
+    let task = state.tasks.get(&task_id).unwrap();
+    if !task.has_resource_limits() {
+        return Ok(());
+    }
+    let root = match &state.settings.daemon.cgroup_root {
+        Some(root) => Path::new(root),
+        None => bail!("daemon.cgroup_root isn't set"),
+    };
+
+    let cgroup = create_task_cgroup(root, task_id, task.memory_limit, task.cpu_limit)?;
+    spawn_in_cgroup(command, &cgroup)
+}
+
+/// Resource limits are only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+fn limit_resources(_command: &mut Command, state: &LockedState, task_id: usize) -> Result<()> {
+    if state.tasks.get(&task_id).unwrap().has_resource_limits() {
+        bail!("Resource limits are only supported on Linux");
+    }
+
+    Ok(())
+}
+
+/// Whether enough CPU slots are free in the task's group to start it.
+/// Groups without a CPU capacity can always start tasks.
+fn cpus_available(state: &LockedState, task: &Task) -> bool {
+    let capacity = match state.settings.daemon.group_cpus.get(&task.group) {
+        Some(capacity) => *capacity,
+        None => return true,
+    };
+    let used: usize = state
+        .tasks
+        .values()
+        .filter(|other| other.group == task.group && other.is_running())
+        .map(Task::requested_cpus)
+        .sum();
+
+    used + task.requested_cpus() <= capacity
+}
+
+/// The units of a resource pool, that are currently held by running tasks.
+fn used_resource_units<'a>(state: &'a LockedState, pool: &str) -> Vec<&'a String> {
+    state
+        .tasks
+        .values()
+        .filter(|task| task.is_running())
+        .filter_map(|task| task.assigned_resources.get(pool))
+        .flatten()
+        .collect()
+}
+
+/// The units of a resource pool, that aren't held by any running task.
+fn free_resource_units<'a>(state: &'a LockedState, pool: &str) -> Vec<&'a String> {
+    let used = used_resource_units(state, pool);
+    match state.settings.daemon.resource_pools.get(pool) {
+        Some(units) => units.iter().filter(|unit| !used.contains(unit)).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Whether enough units of all resource pools requested by the task are free.
+/// Requests for pools, that no longer exist, can never be satisfied.
+fn resources_available(state: &LockedState, task: &Task) -> bool {
+    task.resources
+        .iter()
+        .all(|(pool, amount)| free_resource_units(state, pool).len() >= *amount)
+}
+
+/// Pick the first free units of each resource pool requested by the task.
+/// Tasks that are started explicitly might get fewer units than they requested.
+fn assign_resources(state: &LockedState, task_id: usize) -> BTreeMap<String, Vec<String>> {
+    let task = state.tasks.get(&task_id).unwrap();
+    let mut assigned = BTreeMap::new();
+    for (pool, amount) in &task.resources {
+        let units: Vec<String> = free_resource_units(state, pool)
+            .into_iter()
+            .take(*amount)
+            .cloned()
+            .collect();
+        if units.len() < *amount {
+            warn!(
+                "Task {} requested {} units of resource {}, but only got {}",
+                task_id,
+                amount,
+                pool,
+                units.len()
+            );
+        }
+        assigned.insert(pool.clone(), units);
+    }
+
+    assigned
+}
+
+/// The name of the environment variable, which contains the assigned units of a resource pool.
+/// E.g. `PUEUE_ASSIGNED_GPUS` for the pool `gpus`.
+fn resource_env_key(pool: &str) -> String {
+    format!("PUEUE_ASSIGNED_{}", pool.to_uppercase().replace('-', "_"))
+}
+
+/// Insert a task's command into the wrapper template of its group.
+/// The command is shell-escaped, so it can be passed to `sh -c` as a single argument.
+pub fn build_wrapped_command(
+    wrapper: &str,
+    command: &str,
+    task_id: usize,
+    group: &str,
+) -> Result<String, RenderError> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+
+    let escape = |text: &str| shell_escape::escape(text.to_string().into()).to_string();
+    let mut parameters = HashMap::new();
+    parameters.insert("command", escape(command));
+    parameters.insert("id", task_id.to_string());
+    parameters.insert("group", escape(group));
+
+    handlebars.render_template(wrapper, &parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_build_wrapped_command() {
+        let command = build_wrapped_command(
+            "trickle -d 1000 sh -c {{ command }} # {{ group }} {{ id }}",
+            "curl -o 'my file' example.com && echo done",
+            3,
+            "downloads",
+        )
+        .unwrap();
+        assert_eq!(
+            command,
+            r#"trickle -d 1000 sh -c 'curl -o '\''my file'\'' example.com && echo done' # downloads 3"#
+        );
+
+        assert!(build_wrapped_command("nice {{ cmd }}", "ls", 0, "default").is_err());
+    }
 }
@@ -0,0 +1,177 @@
+use std::path::Path;
+use std::process::Command;
+
+use pueue_lib::task::StartCondition;
+
+use super::*;
+
+/// How often the start conditions of queued tasks are checked.
+/// Url checks, that take longer than this, are killed and count as failed.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Conditions, that aren't met, are checked less often, up to this interval.
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The current state of a queued task's start conditions.
+pub struct StartConditionCheck {
+    /// Whether all conditions have been met during the last check.
+    passed: bool,
+    /// The time at which the last url checks have been started.
+    last_check: Option<Instant>,
+    /// Decides when the conditions are checked next.
+    poller: Poller,
+    /// The currently running url checks.
+    children: Vec<Child>,
+}
+
+impl StartConditionCheck {
+    /// Remember the result of the last check and log whether the task may be started now.
+    fn update(&mut self, task_id: usize, passed: bool) {
+        if passed && !self.passed {
+            info!("Start conditions of task {} are met", task_id);
+        }
+        self.passed = passed;
+        if passed {
+            self.poller.reset();
+        } else {
+            self.poller.back_off();
+        }
+    }
+}
+
+impl TaskHandler {
+    /// Check the start conditions of all queued tasks.
+    /// Urls are requested via `curl` in the background, so a slow server doesn't block the daemon.
+    pub fn check_start_conditions(&mut self) {
+        let state = lock_state(&self.state);
+
+        // Forget about tasks, which are no longer waiting.
+        self.start_conditions.retain(|task_id, _| {
+            matches!(state.tasks.get(task_id), Some(task) if task.status == TaskStatus::Queued)
+        });
+
+        for task in state.tasks.values() {
+            if task.status != TaskStatus::Queued || task.start_conditions.is_empty() {
+                continue;
+            }
+            let check =
+                self.start_conditions
+                    .entry(task.id)
+                    .or_insert_with(|| StartConditionCheck {
+                        passed: false,
+                        last_check: None,
+                        poller: Poller::with_backoff(CHECK_INTERVAL, MAX_CHECK_INTERVAL),
+                        children: Vec::new(),
+                    });
+
+            // The previous result is kept, until the running url checks finished.
+            if !check.children.is_empty() {
+                match finish_url_checks(check) {
+                    Some(urls_passed) => check.update(task.id, urls_passed && files_exist(task)),
+                    None => continue,
+                }
+            }
+
+            if !check.poller.is_due() {
+                continue;
+            }
+
+            let waits_for_url = task
+                .start_conditions
+                .iter()
+                .any(|condition| matches!(condition, StartCondition::Url(_)));
+            if waits_for_url {
+                check.last_check = Some(Instant::now());
+                check.children = spawn_url_checks(task);
+                if check.children.is_empty() {
+                    check.update(task.id, false);
+                }
+            } else {
+                check.update(task.id, files_exist(task));
+            }
+        }
+    }
+
+    /// Whether all start conditions of the task have been met.
+    pub fn start_conditions_passed(&self, task: &Task) -> bool {
+        task.start_conditions.is_empty()
+            || self
+                .start_conditions
+                .get(&task.id)
+                .is_some_and(|check| check.passed)
+    }
+}
+
+/// Check whether all files of the task exist.
+/// Relative paths are relative to the task's working directory.
+fn files_exist(task: &Task) -> bool {
+    task.start_conditions
+        .iter()
+        .all(|condition| match condition {
+            StartCondition::File(file) => Path::new(&task.path).join(file).exists(),
+            StartCondition::Url(_) => true,
+        })
+}
+
+/// Request all urls of the task.
+/// If any request can't be started, none are and the check is retried later.
+fn spawn_url_checks(task: &Task) -> Vec<Child> {
+    let mut children = Vec::new();
+    for condition in &task.start_conditions {
+        let url = match condition {
+            StartCondition::Url(url) => url,
+            StartCondition::File(_) => continue,
+        };
+
+        let spawned = Command::new("curl")
+            .args(["--silent", "--fail", "--location"])
+            .arg("--max-time")
+            .arg(CHECK_INTERVAL.as_secs().to_string())
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(child) => children.push(child),
+            Err(err) => {
+                error!("Failed to check url {} of task {}: {}", url, task.id, err);
+                for mut child in children {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                return Vec::new();
+            }
+        }
+    }
+
+    children
+}
+
+/// Collect the results of the url checks.
+/// Returns `None`, while some of them are still running.
+fn finish_url_checks(check: &mut StartConditionCheck) -> Option<bool> {
+    let timed_out = check
+        .last_check
+        .is_some_and(|last_check| last_check.elapsed() >= CHECK_INTERVAL);
+    let mut passed = true;
+    for child in check.children.iter_mut() {
+        let success = match child.try_wait() {
+            Ok(Some(status)) => status.success(),
+            // Kill checks that take too long, e.g. due to a hanging connection.
+            Ok(None) if !timed_out => return None,
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                false
+            }
+            Err(err) => {
+                error!("Failed to wait for url check: {}", err);
+                false
+            }
+        };
+        passed &= success;
+    }
+    check.children.clear();
+
+    Some(passed)
+}
@@ -0,0 +1,255 @@
+use std::collections::HashSet;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use handlebars::RenderError;
+
+use super::*;
+use crate::ok_or_shutdown;
+
+/// How often the watched directories are scanned for changes.
+pub const WATCHER_INTERVAL: Duration = Duration::from_secs(1);
+/// Scans happen less often, while no files change.
+pub const MAX_WATCHER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The last known state of a file inside a watched directory.
+pub struct WatchedFile {
+    /// The last modification time of the file.
+    modified: SystemTime,
+    /// The point in time at which we noticed the last modification.
+    changed_at: Instant,
+    /// The modification time for which a task has already been enqueued.
+    enqueued: Option<SystemTime>,
+}
+
+/// Compile the glob patterns of all watchers and expand their paths.
+/// Watchers with invalid patterns or paths are ignored.
+pub fn compile_watchers(watchers: &[Watcher]) -> Vec<(Watcher, Pattern)> {
+    watchers
+        .iter()
+        .filter_map(|watcher| {
+            let pattern = match Pattern::new(&watcher.glob) {
+                Ok(pattern) => pattern,
+                Err(err) => {
+                    error!("Invalid glob for watcher {:?}: {}", watcher.path, err);
+                    return None;
+                }
+            };
+            let mut watcher = watcher.clone();
+            watcher.path = match watcher.expanded_path() {
+                Ok(path) => path,
+                Err(err) => {
+                    error!("Invalid path for watcher: {}", err);
+                    return None;
+                }
+            };
+
+            Some((watcher, pattern))
+        })
+        .collect()
+}
+
+impl TaskHandler {
+    /// Scan all watched directories and enqueue a task for each new or modified file,
+    /// once it didn't change for the configured debounce time.
+    ///
+    /// The processed files are remembered in the state, so they aren't processed again whenever
+    /// the daemon restarts. Files that appeared or changed in the meantime are processed.
+    /// During the very first scan of a watcher, all existing files are only registered.
+    pub fn check_watchers(&mut self, initial: bool) {
+        if self.watchers.is_empty() {
+            return;
+        }
+        if !initial && !self.watcher_poller.is_due() {
+            return;
+        }
+
+        // Whether any file is new, has been modified or is waiting for its debounce time.
+        let mut active = false;
+        let mut seen = HashSet::new();
+        let mut added_tasks = false;
+        for (watcher, pattern) in self.watchers.clone() {
+            // The files, that have been processed before the daemon has been restarted.
+            let processed = if initial {
+                lock_state(&self.state)
+                    .watched_files
+                    .get(&watcher.path)
+                    .cloned()
+            } else {
+                None
+            };
+
+            for (path, modified) in matching_files(&watcher.path, &pattern) {
+                seen.insert(path.clone());
+                let file = self
+                    .watched_files
+                    .entry(path.clone())
+                    .or_insert_with(|| WatchedFile {
+                        modified,
+                        changed_at: Instant::now(),
+                        enqueued: match (initial, &processed) {
+                            (true, Some(processed)) => {
+                                processed.get(&path).map(|time| SystemTime::from(*time))
+                            }
+                            (true, None) => Some(modified),
+                            (false, _) => None,
+                        },
+                    });
+
+                if file.modified != modified {
+                    file.modified = modified;
+                    file.changed_at = Instant::now();
+                }
+                if file.enqueued == Some(file.modified) {
+                    continue;
+                }
+                active = true;
+
+                let debounce = Duration::from_secs(watcher.debounce);
+                if file.changed_at.elapsed() < debounce {
+                    continue;
+                }
+                file.enqueued = Some(file.modified);
+
+                added_tasks |= self.enqueue_watcher_task(&watcher, &path);
+            }
+        }
+
+        // Forget about files that have been removed in the meantime.
+        self.watched_files.retain(|path, _| seen.contains(path));
+        self.remember_watched_files(added_tasks);
+
+        if active {
+            self.watcher_poller.reset();
+        } else {
+            self.watcher_poller.back_off();
+        }
+    }
+
+    /// Store the processed files of all watchers in the state.
+    /// The state is only saved, if anything changed.
+    fn remember_watched_files(&mut self, added_tasks: bool) {
+        let mut watched_files = BTreeMap::new();
+        for (watcher, _) in &self.watchers {
+            let files = self
+                .watched_files
+                .iter()
+                .filter(|(path, _)| path.parent() == Some(watcher.path.as_path()))
+                .filter_map(|(path, file)| Some((path.clone(), DateTime::from(file.enqueued?))))
+                .collect();
+            watched_files.insert(watcher.path.clone(), files);
+        }
+
+        let state_ref = self.state.clone();
+        let mut state = lock_state(&state_ref);
+        if !added_tasks && state.watched_files == watched_files {
+            return;
+        }
+        state.watched_files = watched_files;
+        ok_or_shutdown!(self, save_state(&state));
+    }
+
+    /// Add a task for a new or modified file to the state.
+    /// Returns whether the task has been added. The state is saved afterwards by the caller.
+    fn enqueue_watcher_task(&mut self, watcher: &Watcher, path: &Path) -> bool {
+        let command = match build_watcher_command(watcher, path) {
+            Ok(command) => command,
+            Err(err) => {
+                error!(
+                    "Failed to create command from template of watcher {:?} with error: {}",
+                    watcher.path, err
+                );
+                return false;
+            }
+        };
+
+        let state_clone = self.state.clone();
+        let mut state = lock_state(&state_clone);
+        if !state.groups.contains_key(&watcher.group) {
+            error!(
+                "Group {} of watcher {:?} doesn't exist",
+                watcher.group, watcher.path
+            );
+            return false;
+        }
+
+        let task = Task::new(
+            command,
+            watcher.path.to_string_lossy().to_string(),
+            std::env::vars().collect(),
+            watcher.group.clone(),
+            TaskStatus::Queued,
+            Vec::new(),
+            None,
+        );
+        let task_id = state.add_task(task);
+        info!("Watcher enqueued task {} for file {:?}", task_id, path);
+
+        true
+    }
+}
+
+/// Return all files inside a directory, whose names match the given pattern,
+/// together with their modification time.
+fn matching_files(directory: &Path, pattern: &Pattern) -> Vec<(PathBuf, SystemTime)> {
+    let entries = match read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            debug!("Failed to read watched directory {:?}: {}", directory, err);
+            return Vec::new();
+        }
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| pattern.matches(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            Some((entry.path(), metadata.modified().ok()?))
+        })
+        .collect()
+}
+
+/// Take the command template of a watcher and insert the path and name of the file.
+/// Both are shell-escaped, since file names can contain arbitrary characters.
+pub fn build_watcher_command(watcher: &Watcher, path: &Path) -> Result<String, RenderError> {
+    // Init Handlebars. We set to strict, as we want to show an error on missing variables.
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+
+    let escape = |text: String| shell_escape::escape(text.into()).to_string();
+    let mut parameters = HashMap::new();
+    parameters.insert("path", escape(path.to_string_lossy().to_string()));
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    parameters.insert("name", escape(name));
+
+    handlebars.render_template(&watcher.command, &parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_build_watcher_command() {
+        let watcher = Watcher {
+            path: PathBuf::from("/tmp/inbox"),
+            glob: "*.txt".into(),
+            command: "convert {{ path }} --name {{ name }}".into(),
+            group: "default".into(),
+            debounce: 2,
+        };
+
+        let command = build_watcher_command(&watcher, Path::new("/tmp/inbox/a b.txt")).unwrap();
+        assert_eq!(command, "convert '/tmp/inbox/a b.txt' --name 'a b.txt'");
+    }
+}
@@ -0,0 +1,352 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use log::error;
+use serde_derive::{Deserialize, Serialize};
+use wasmi::{
+    Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use pueue_lib::network::message::AddMessage;
+use pueue_lib::task::{Task, TaskResult, TaskStatus};
+
+use crate::network::message_handler::add_hook::TaskChanges;
+
+/// The version of the interface, that's expected from policy modules.
+const ABI_VERSION: i32 = 1;
+
+/// The amount of fuel, i.e. roughly the amount of instructions, a module may use per event.
+const FUEL_PER_EVENT: u64 = 10_000_000;
+
+/// The maximum size of the memory of a module in bytes.
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// The events, which are passed to policy modules.
+#[derive(Clone, Copy)]
+enum Event {
+    Add,
+    Start,
+    Finish,
+}
+
+impl Event {
+    /// The name of the function, that handles this event.
+    fn export(&self) -> &'static str {
+        match self {
+            Event::Add => "pueue_on_add",
+            Event::Start => "pueue_on_start",
+            Event::Finish => "pueue_on_finish",
+        }
+    }
+}
+
+/// The details of a task, that are passed to policy modules.
+#[derive(Serialize)]
+struct EventInput<'a> {
+    id: Option<usize>,
+    command: &'a str,
+    path: &'a str,
+    group: &'a str,
+    label: &'a Option<String>,
+    tags: &'a [String],
+    created_by: &'a Option<String>,
+    result: Option<&'a TaskResult>,
+}
+
+impl<'a> EventInput<'a> {
+    fn from_task(task: &'a Task) -> Self {
+        let result = match &task.status {
+            TaskStatus::Done(result) => Some(result),
+            _ => None,
+        };
+
+        EventInput {
+            id: Some(task.id),
+            command: &task.command,
+            path: &task.path,
+            group: &task.group,
+            label: &task.label,
+            tags: &task.tags,
+            created_by: &task.created_by,
+            result,
+        }
+    }
+}
+
+/// The response of a policy module to an event.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EventResponse {
+    reject: Option<String>,
+    changes: Option<TaskChanges>,
+}
+
+/// A compiled policy module.
+struct Policy {
+    path: PathBuf,
+    module: Module,
+}
+
+/// All policy modules of `daemon.wasm_policies`. \
+/// These are WebAssembly modules, which are invoked when tasks are added, started and
+/// finished. As they're sandboxed and platform independent, custom scheduling policies and
+/// validators can be shipped as a single `.wasm` file.
+///
+/// A module implements the following interface (version 1):
+///
+/// - `memory`: The exported memory, which is used to exchange data.
+/// - `pueue_abi_version() -> i32`: Has to return `1`.
+/// - `pueue_alloc(len: i32) -> i32`: Return a pointer to `len` bytes, which are filled with the
+///   input of an event.
+/// - `pueue_on_add`, `pueue_on_start` and `pueue_on_finish` `(ptr: i32, len: i32) -> i64`:
+///   The handlers of the respective events. All of them are optional. \
+///   They receive the task as JSON object with the fields `id`, `command`, `path`, `group`,
+///   `label`, `tags`, `created_by` and `result`. `id` is `null` for new tasks and `result` is
+///   only set for finished tasks. \
+///   A handler returns `0`, if it has nothing to say. Otherwise it returns `(ptr << 32) | len`
+///   of a JSON response in its memory, e.g. `{"reject": "reason"}` or
+///   `{"changes": {"group": "heavy"}}`.
+///
+/// Rejecting a new task refuses to add it, while rejecting the start of a task keeps it queued.
+/// Changes are only applied to new tasks and have the same format as the output of the add hook.
+/// The response of `pueue_on_finish` is ignored.
+///
+/// Each event is handled by a fresh instance of the module, which doesn't have access to any
+/// host functions. Handlers that execute too many instructions or use too much memory are
+/// aborted.
+///
+/// Since modules don't keep any state, their decision about the start of a task only depends
+/// on the task. It's thereby only asked again, once the task changed.
+pub struct WasmPolicies {
+    engine: Engine,
+    policies: Vec<Policy>,
+    /// The input and the decision of the last start event of each queued task.
+    start_decisions: RefCell<HashMap<usize, (Vec<u8>, bool)>>,
+}
+
+impl WasmPolicies {
+    /// Compile the given modules and ensure that they implement the expected interface.
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+
+        let mut policies = Vec::new();
+        for path in paths {
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("Failed to read policy module {:?}", path))?;
+            let module = Module::new(&engine, &bytes[..])
+                .map_err(|err| anyhow!("Failed to compile policy module {:?}: {}", path, err))?;
+            let policy = Policy {
+                path: path.clone(),
+                module,
+            };
+
+            let (mut store, instance) = policy.instantiate(&engine)?;
+            let version = instance
+                .get_typed_func::<(), i32>(&store, "pueue_abi_version")
+                .and_then(|func| Ok(func.call(&mut store, ())?))
+                .map_err(|err| anyhow!("Policy module {:?} has no valid version: {}", path, err))?;
+            if version != ABI_VERSION {
+                bail!(
+                    "Policy module {:?} implements version {}, but only version {} is supported",
+                    path,
+                    version,
+                    ABI_VERSION
+                );
+            }
+
+            policies.push(policy);
+        }
+
+        Ok(WasmPolicies {
+            engine,
+            policies,
+            start_decisions: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Let all policies inspect and rewrite a new task in order. \
+    /// An error is returned, if any policy rejected the task or failed.
+    pub fn on_add(&self, message: &mut AddMessage) -> Result<()> {
+        for policy in &self.policies {
+            let input = EventInput {
+                id: None,
+                command: &message.command,
+                path: &message.path,
+                group: &message.group,
+                label: &message.label,
+                tags: &message.tags,
+                created_by: &message.created_by,
+                result: None,
+            };
+            let input = serde_json::to_vec(&input)?;
+            let response = match policy.handle(&self.engine, Event::Add, &input)? {
+                Some(response) => response,
+                None => continue,
+            };
+
+            if let Some(reason) = response.reject {
+                bail!(
+                    "The task has been rejected by policy {:?}: {}",
+                    policy.path,
+                    reason
+                );
+            }
+            if let Some(changes) = response.changes {
+                changes.apply(message);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check whether all policies allow a queued task to be started. \
+    /// Failing policies are logged and don't hold back any tasks.
+    ///
+    /// The decision is remembered, until the task changed.
+    pub fn allows_start(&self, task: &Task) -> bool {
+        if self.policies.is_empty() {
+            return true;
+        }
+        let input = match serde_json::to_vec(&EventInput::from_task(task)) {
+            Ok(input) => input,
+            Err(err) => {
+                error!("Failed to serialize task {}: {}", task.id, err);
+                return true;
+            }
+        };
+        if let Some((previous_input, allowed)) = self.start_decisions.borrow().get(&task.id) {
+            if previous_input == &input {
+                return *allowed;
+            }
+        }
+
+        let allowed = self.policies.iter().all(|policy| {
+            match policy.handle(&self.engine, Event::Start, &input) {
+                Ok(Some(EventResponse {
+                    reject: Some(_), ..
+                })) => false,
+                Ok(_) => true,
+                Err(err) => {
+                    error!("{:#}", err);
+                    true
+                }
+            }
+        });
+        self.start_decisions
+            .borrow_mut()
+            .insert(task.id, (input, allowed));
+
+        allowed
+    }
+
+    /// Inform all policies about a finished task.
+    pub fn on_finish(&self, task: &Task) {
+        self.start_decisions.borrow_mut().remove(&task.id);
+        let input = EventInput::from_task(task);
+        let input = match serde_json::to_vec(&input) {
+            Ok(input) => input,
+            Err(err) => {
+                error!("Failed to serialize task {}: {}", task.id, err);
+                return;
+            }
+        };
+        for policy in &self.policies {
+            if let Err(err) = policy.handle(&self.engine, Event::Finish, &input) {
+                error!("{:#}", err);
+            }
+        }
+    }
+}
+
+impl Policy {
+    /// Create a fresh instance of the module, so no state is kept between events.
+    fn instantiate(&self, engine: &Engine) -> Result<(Store<StoreLimits>, Instance)> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .add_fuel(FUEL_PER_EVENT)
+            .map_err(|err| anyhow!("Failed to add fuel: {}", err))?;
+        let linker = <Linker<StoreLimits>>::new(engine);
+        let instance = linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|err| anyhow!("Failed to instantiate policy {:?}: {}", self.path, err))?;
+
+        Ok((store, instance))
+    }
+
+    /// Pass an event to the module.
+    /// Returns `None`, if the module doesn't handle the event or has nothing to say.
+    /// The input is the serialized [EventInput].
+    fn handle(&self, engine: &Engine, event: Event, input: &[u8]) -> Result<Option<EventResponse>> {
+        if self.module.get_export(event.export()).is_none() {
+            return Ok(None);
+        }
+
+        let (mut store, instance) = self.instantiate(engine)?;
+        let response = call_handler(&mut store, &instance, event.export(), input)
+            .with_context(|| format!("Policy {:?} failed in {}", self.path, event.export()))?;
+        let response = match response {
+            Some(response) => response,
+            None => return Ok(None),
+        };
+
+        let response = serde_json::from_slice(&response).with_context(|| {
+            format!(
+                "Policy {:?} returned an invalid response in {}",
+                self.path,
+                event.export()
+            )
+        })?;
+        Ok(Some(response))
+    }
+}
+
+/// Copy the input into the module's memory, call the handler and read its response.
+fn call_handler(
+    store: &mut Store<StoreLimits>,
+    instance: &Instance,
+    export: &str,
+    input: &[u8],
+) -> Result<Option<Vec<u8>>> {
+    let memory: Memory = instance
+        .get_memory(&*store, "memory")
+        .context("The module doesn't export its memory")?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&*store, "pueue_alloc")
+        .map_err(|err| anyhow!("Invalid pueue_alloc: {}", err))?;
+    let handler = instance
+        .get_typed_func::<(i32, i32), i64>(&*store, export)
+        .map_err(|err| anyhow!("Invalid {}: {}", export, err))?;
+
+    let len = i32::try_from(input.len()).context("The input is too large")?;
+    let ptr = alloc
+        .call(&mut *store, len)
+        .map_err(|err| anyhow!("pueue_alloc failed: {}", err))?;
+    memory
+        .write(&mut *store, ptr as u32 as usize, input)
+        .map_err(|err| anyhow!("pueue_alloc returned an invalid pointer: {}", err))?;
+
+    let packed = handler
+        .call(&mut *store, (ptr, len))
+        .map_err(|err| anyhow!("{}", err))?;
+    if packed == 0 {
+        return Ok(None);
+    }
+
+    // The length is checked against the module's memory, before anything is allocated.
+    let ptr = (packed as u64 >> 32) as usize;
+    let len = (packed as u64 & u32::MAX as u64) as usize;
+    let response = ptr
+        .checked_add(len)
+        .and_then(|end| memory.data(&*store).get(ptr..end))
+        .context("The response is out of bounds")?;
+
+    Ok(Some(response.to_vec()))
+}
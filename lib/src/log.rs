@@ -0,0 +1,331 @@
+use std::fs::{copy, create_dir_all, read_dir, remove_file, rename, File, OpenOptions};
+use std::io::{self, BufReader, Cursor};
+use std::path::{Path, PathBuf};
+
+use log::error;
+use snap::write::FrameEncoder;
+
+use crate::error::Error;
+
+/// The log directory of tasks, whose log directory hasn't been stored yet.
+/// This has been the only log directory in earlier versions.
+pub fn default_log_directory(pueue_directory: &Path) -> PathBuf {
+    pueue_directory.join("task_logs")
+}
+
+/// Return the paths to the `(stdout, stderr)` log files of a task. \
+/// All functions in this module expect the log directory of the task, see
+/// `Task::log_directory`.
+pub fn get_task_log_paths(task_id: usize, log_directory: &Path) -> (PathBuf, PathBuf) {
+    let out_path = log_directory.join(format!("{}_stdout.log", task_id));
+    let err_path = log_directory.join(format!("{}_stderr.log", task_id));
+    (out_path, err_path)
+}
+
+/// Return the path of the `number`th rotated copy of a log file. \
+/// `1` is the most recent copy, e.g. `3_stdout.log.1`.
+pub fn get_rotated_log_path(log_path: &Path, number: usize) -> PathBuf {
+    let mut name = log_path.as_os_str().to_owned();
+    name.push(format!(".{}", number));
+    PathBuf::from(name)
+}
+
+/// Create and return the file handle for the `(stdout, stderr)` log files of a task. \
+/// Rotated copies of any previous run are removed.
+///
+/// The files are opened in append mode, so the task keeps writing to the start of the file,
+/// after it has been truncated by [rotate_log_file].
+pub fn create_log_file_handles(
+    task_id: usize,
+    log_directory: &Path,
+) -> Result<(File, File), Error> {
+    let (out_path, err_path) = get_task_log_paths(task_id, log_directory);
+    remove_rotated_log_files(&out_path);
+    remove_rotated_log_files(&err_path);
+
+    let open = |path: &Path| -> Result<File, Error> {
+        File::create(path)?;
+        Ok(OpenOptions::new().append(true).open(path)?)
+    };
+
+    Ok((open(&out_path)?, open(&err_path)?))
+}
+
+/// Rotate a log file, that's still being written to by a running task. \
+/// The file's content is copied to `<file>.1`, while older copies are moved to `<file>.2` and
+/// so on. At most `keep` copies are kept. If `keep` is `0`, the file is only truncated.
+///
+/// The task holds the file open, which is why it's copied and truncated instead of moved.
+/// Any output, that's written in between those two steps, is lost.
+pub fn rotate_log_file(log_path: &Path, keep: usize) -> Result<(), Error> {
+    if keep > 0 {
+        for number in (1..keep).rev() {
+            let rotated = get_rotated_log_path(log_path, number);
+            if rotated.exists() {
+                rename(rotated, get_rotated_log_path(log_path, number + 1))?;
+            }
+        }
+        copy(log_path, get_rotated_log_path(log_path, 1))?;
+    }
+
+    OpenOptions::new().write(true).open(log_path)?.set_len(0)?;
+
+    Ok(())
+}
+
+/// Remove all rotated copies of a log file.
+fn remove_rotated_log_files(log_path: &Path) {
+    let mut number = 1;
+    loop {
+        let rotated = get_rotated_log_path(log_path, number);
+        if !rotated.exists() {
+            return;
+        }
+        if let Err(err) = remove_file(&rotated) {
+            error!("Failed to remove rotated log file {:?}: {}", rotated, err);
+            return;
+        }
+        number += 1;
+    }
+}
+
+/// Return the file handle for the `(stdout, stderr)` log files of a task.
+pub fn get_log_file_handles(task_id: usize, log_directory: &Path) -> Result<(File, File), Error> {
+    let (out_path, err_path) = get_task_log_paths(task_id, log_directory);
+    let stdout = File::open(out_path)?;
+    let stderr = File::open(err_path)?;
+
+    Ok((stdout, stderr))
+}
+
+/// Remove the the log files of a task.
+pub fn clean_log_handles(task_id: usize, log_directory: &Path) {
+    let (out_path, err_path) = get_task_log_paths(task_id, log_directory);
+    remove_rotated_log_files(&out_path);
+    remove_rotated_log_files(&err_path);
+    if out_path.exists() {
+        if let Err(err) = remove_file(out_path) {
+            error!(
+                "Failed to remove stdout file for task {} with error {:?}",
+                task_id, err
+            );
+        };
+    }
+    if err_path.exists() {
+        if let Err(err) = remove_file(err_path) {
+            error!(
+                "Failed to remove stderr file for task {} with error {:?}",
+                task_id, err
+            );
+        };
+    }
+}
+
+/// The directory that contains the archived logs of cleaned tasks. \
+/// All functions in this module can be used with it as well,
+/// e.g. `get_task_log_paths(task_id, &archive_directory(pueue_directory))`.
+pub fn archive_directory(pueue_directory: &Path) -> PathBuf {
+    default_log_directory(&pueue_directory.join("archive"))
+}
+
+/// Move the log files of a task into the archive.
+pub fn archive_log_files(
+    task_id: usize,
+    log_directory: &Path,
+    pueue_directory: &Path,
+) -> Result<(), Error> {
+    move_log_files(task_id, log_directory, &archive_directory(pueue_directory))
+}
+
+/// Move the log files of a task including their rotated copies into another directory. \
+/// Files are copied and removed, if the directory is on another file system.
+///
+/// Moving the log files of a running task loses all output, that's written afterwards,
+/// unless they're moved inside of the same file system.
+pub fn move_log_files(task_id: usize, from: &Path, to: &Path) -> Result<(), Error> {
+    create_dir_all(to)?;
+
+    let (out_path, err_path) = get_task_log_paths(task_id, from);
+    let (new_out_path, new_err_path) = get_task_log_paths(task_id, to);
+    for (path, new_path) in [(out_path, new_out_path), (err_path, new_err_path)] {
+        if !path.exists() {
+            continue;
+        }
+        remove_rotated_log_files(&new_path);
+        let mut number = 1;
+        while get_rotated_log_path(&path, number).exists() {
+            move_file(
+                &get_rotated_log_path(&path, number),
+                &get_rotated_log_path(&new_path, number),
+            )?;
+            number += 1;
+        }
+        move_file(&path, &new_path)?;
+    }
+
+    Ok(())
+}
+
+/// Rename a file or copy and remove it, if it can't be renamed across file systems.
+fn move_file(from: &Path, to: &Path) -> Result<(), Error> {
+    if rename(from, to).is_err() {
+        copy(from, to)?;
+        remove_file(from)?;
+    }
+
+    Ok(())
+}
+
+/// Return the `(stdout, stderr)` output of a task. \
+/// Task output is compressed using [snap] to save some memory and bandwidth.
+pub fn read_and_compress_log_files(
+    task_id: usize,
+    log_directory: &Path,
+    lines: Option<usize>,
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let (mut stdout_file, mut stderr_file) = get_log_file_handles(task_id, log_directory)?;
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+
+    if let Some(lines) = lines {
+        // Get the last few lines of both files
+        let stdout_bytes = read_last_lines(&mut stdout_file, lines).into_bytes();
+        let stderr_bytes = read_last_lines(&mut stderr_file, lines).into_bytes();
+        let mut stdout_cursor = Cursor::new(stdout_bytes);
+        let mut stderr_cursor = Cursor::new(stderr_bytes);
+
+        // Compress the partial log input and pipe it into the snappy compressor
+        let mut stdout_compressor = FrameEncoder::new(&mut stdout);
+        io::copy(&mut stdout_cursor, &mut stdout_compressor)?;
+        let mut stderr_compressor = FrameEncoder::new(&mut stderr);
+        io::copy(&mut stderr_cursor, &mut stderr_compressor)?;
+    } else {
+        // Compress the full log input and pipe it into the snappy compressor
+        let mut stdout_compressor = FrameEncoder::new(&mut stdout);
+        io::copy(&mut stdout_file, &mut stdout_compressor)?;
+        let mut stderr_compressor = FrameEncoder::new(&mut stderr);
+        io::copy(&mut stderr_file, &mut stderr_compressor)?;
+    }
+
+    Ok((stdout, stderr))
+}
+
+/// Return the last lines of `(stdout, stderr)` of a task. \
+/// This output is uncompressed and may take a lot of memory, which is why we only read
+/// the last few lines.
+pub fn read_last_log_file_lines(
+    task_id: usize,
+    log_directory: &Path,
+    lines: usize,
+) -> Result<(String, String), Error> {
+    let (mut stdout_file, mut stderr_file) = match get_log_file_handles(task_id, log_directory) {
+        Ok((stdout, stderr)) => (stdout, stderr),
+        Err(err) => {
+            return Err(Error::LogRead(format!(
+                "Error while opening log files for task {}: {}",
+                task_id, err
+            )));
+        }
+    };
+
+    // Get the last few lines of both files
+    Ok((
+        read_last_lines(&mut stdout_file, lines),
+        read_last_lines(&mut stderr_file, lines),
+    ))
+}
+
+/// Remove all files in a log directory.
+pub fn reset_task_log_directory(log_directory: &Path) -> Result<(), Error> {
+    let files = read_dir(log_directory)?;
+
+    for file in files.flatten() {
+        if let Err(err) = remove_file(file.path()) {
+            error!("Failed to delete log file: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the last `amount` lines of a file to a string.
+///
+/// TODO: This is super imperformant, but works as long as we don't use the last
+/// 1000 lines. It would be cleaner to seek to the beginning of the requested
+/// position and simply stream the content.
+pub fn read_last_lines(file: &mut File, amount: usize) -> String {
+    // Read the lines from the file.
+    // Return a debug string if it fails.
+    let last_lines: Vec<String> = match rev_lines::RevLines::new(BufReader::new(file)) {
+        Ok(lines) => lines.take(amount).collect(),
+        Err(error) => return format!("(Pueue error) Failed to read last lines of file: {}", error),
+    };
+
+    last_lines
+        .into_iter()
+        .rev()
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, write};
+
+    use pretty_assertions::assert_eq;
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_rotate_log_file() -> Result<(), Error> {
+        let tempdir = TempDir::new("pueue_lib").unwrap();
+        let log_path = tempdir.path().join("0_stdout.log");
+
+        for content in ["first", "second", "third"] {
+            write(&log_path, content)?;
+            rotate_log_file(&log_path, 2)?;
+        }
+
+        assert_eq!(read_to_string(&log_path)?, "");
+        assert_eq!(read_to_string(get_rotated_log_path(&log_path, 1))?, "third");
+        assert_eq!(
+            read_to_string(get_rotated_log_path(&log_path, 2))?,
+            "second"
+        );
+        assert!(!get_rotated_log_path(&log_path, 3).exists());
+
+        remove_rotated_log_files(&log_path);
+        assert!(!get_rotated_log_path(&log_path, 1).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_log_files() -> Result<(), Error> {
+        let tempdir = TempDir::new("pueue_lib").unwrap();
+        let from = tempdir.path().join("from");
+        let to = tempdir.path().join("to");
+        create_dir_all(&from)?;
+
+        let (out_path, err_path) = get_task_log_paths(0, &from);
+        write(&out_path, "rotated")?;
+        rotate_log_file(&out_path, 1)?;
+        write(&out_path, "stdout")?;
+        write(&err_path, "stderr")?;
+
+        move_log_files(0, &from, &to)?;
+
+        let (new_out_path, new_err_path) = get_task_log_paths(0, &to);
+        assert_eq!(read_to_string(&new_out_path)?, "stdout");
+        assert_eq!(read_to_string(&new_err_path)?, "stderr");
+        assert_eq!(
+            read_to_string(get_rotated_log_path(&new_out_path, 1))?,
+            "rotated"
+        );
+        assert_eq!(read_dir(&from)?.count(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,619 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use rand::Rng;
+use serde_derive::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::state::{GroupStatus, PauseReason, State};
+use crate::task::{DependencyMode, StartCondition, Task, TaskResult, TaskStatus, WindowsShell};
+
+/// This is the main message enum. \
+/// Everything that's communicated in Pueue can be serialized as this enum.
+/// Messages are only created once per request, so their size doesn't matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum Message {
+    Add(AddMessage),
+    /// Add several tasks at once. Either all of them are added or none.
+    AddBatch(AddBatchMessage),
+    /// Add previously exported tasks with new ids.
+    Import(Vec<Task>),
+    /// Reserve a task id in the given group.
+    /// The task is only created, once an [AddMessage] commits the reservation.
+    Reserve(String),
+    Remove(Vec<usize>),
+    /// Kill running or paused tasks and remove them, once they've been reaped.
+    ForceRemove(Vec<usize>),
+    Switch(SwitchMessage),
+    Stash(Vec<usize>),
+    /// Mark failed tasks as acknowledged, i.e. their failure is known.
+    Acknowledge(Vec<usize>),
+    Enqueue(EnqueueMessage),
+    /// Restart failed tasks in place and stash them, until they're enqueued at the given time.
+    Snooze(SnoozeMessage),
+
+    Start(StartMessage),
+    Restart(RestartMessage),
+    Pause(PauseMessage),
+    Kill(KillMessage),
+
+    /// Used to send some input to a process's stdin
+    Send(SendMessage),
+
+    /// The first part of the three-step protocol to edit a task.
+    /// This one requests an edit from the daemon.
+    EditRequest(usize),
+    /// The daemon locked the task and responds with the task's details.
+    EditResponse(EditResponseMessage),
+    /// The client sends the edited details to the daemon.
+    Edit(EditMessage),
+    /// Set or remove environment variables of a queued or stashed task.
+    EditEnv(EditEnvMessage),
+
+    Group(GroupMessage),
+    GroupResponse(GroupResponseMessage),
+
+    Status,
+    StatusResponse(Box<State>),
+    /// Request all known future events, such as the enqueueing of delayed tasks.
+    Schedule,
+    ScheduleResponse(Vec<ScheduledEvent>),
+    /// Request cleaned tasks from the history.
+    History(HistoryRequestMessage),
+    HistoryResponse(Vec<HistoryEntry>),
+    Log(LogRequestMessage),
+    LogResponse(BTreeMap<usize, TaskLogMessage>),
+
+    /// The client requests a continuous stream of a task's log.
+    StreamRequest(StreamRequestMessage),
+    /// The next chunk of output, that's send to the client.
+    Stream(StreamMessage),
+
+    /// A mirror requests a continuous copy of the state and the output of all tasks.
+    MirrorRequest(MirrorRequestMessage),
+    /// The current state together with the output of all tasks, whose output changed.
+    MirrorUpdate(MirrorUpdateMessage),
+
+    /// The boolean decides, whether the children should be get a SIGTERM as well.
+    Reset(ResetMessage),
+    Clean(CleanMessage),
+    DaemonShutdown(Shutdown),
+    /// Save the state to disk right away, even if the daemon delays saving it.
+    Flush,
+    /// Move the logs of all tasks into this absolute directory and use it for new logs.
+    SetLogDirectory(PathBuf),
+
+    Success(String),
+    Failure(FailureMessage),
+    /// The outcome of an action on several explicitly given tasks.
+    TaskActionResponse(TaskActionResponseMessage),
+
+    Parallel(ParallelMessage),
+
+    /// A client request that's tagged with a request id.
+    /// The id is logged by both sides and attached to failure responses,
+    /// which allows to correlate client errors with the daemon's log.
+    Traced(TracedMessage),
+}
+
+/// This enum is used to express a selection of tasks.
+/// As commands can be executed on various sets of tasks, we need some kind of datastructure to
+/// explicitly and unambiguously specify the selection.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum TaskSelection {
+    TaskIds(Vec<usize>),
+    Group(String),
+    /// All tasks with this tag.
+    Tag(String),
+    /// All tasks of the array with this id.
+    Array(usize),
+    All,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct AddBatchMessage {
+    pub tasks: Vec<BatchTask>,
+    /// The tasks form an array, whose id is the id of the first task.
+    #[serde(default)]
+    pub array: bool,
+}
+
+/// A single task of an [AddBatchMessage].
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct BatchTask {
+    pub task: AddMessage,
+    /// The indices of earlier tasks in the same batch, which this task depends on.
+    /// They're replaced with the ids of those tasks, once they're added.
+    #[serde(default)]
+    pub batch_dependencies: Vec<usize>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct AddMessage {
+    pub command: String,
+    pub path: String,
+    /// Whether `path` is just the working directory of the client, rather than one that has been
+    /// chosen explicitly. The default working directory of the task's group is used instead.
+    #[serde(default)]
+    pub path_is_default: bool,
+    pub envs: HashMap<String, String>,
+    pub start_immediately: bool,
+    pub stashed: bool,
+    pub group: String,
+    pub enqueue_at: Option<DateTime<Local>>,
+    pub dependencies: Vec<usize>,
+    /// Which results of its dependencies the task is waiting for.
+    #[serde(default)]
+    pub dependency_mode: DependencyMode,
+    pub label: Option<String>,
+    pub print_task_id: bool,
+    /// The identity (`user@host`) of the client that submitted the task.
+    #[serde(default)]
+    pub created_by: Option<String>,
+    /// The task expires, if it hasn't been started by this point in time.
+    #[serde(default)]
+    pub expire_at: Option<DateTime<Local>>,
+    /// Create the task under this previously reserved id.
+    #[serde(default)]
+    pub reserved_id: Option<usize>,
+    /// Archive the task's logs instead of deleting them, when it's cleaned.
+    #[serde(default)]
+    pub keep_logs: bool,
+    /// Write the task's stderr to its stdout log.
+    #[serde(default)]
+    pub stderr_to_stdout: bool,
+    /// Kill the task, once it ran for longer than this amount of seconds.
+    #[serde(default)]
+    pub max_runtime: Option<u64>,
+    /// The amount of CPU slots the task occupies in its group.
+    #[serde(default)]
+    pub cpus: Option<usize>,
+    /// The amount of units the task needs from each resource pool.
+    #[serde(default)]
+    pub resources: BTreeMap<String, usize>,
+    /// The maximum amount of memory in bytes, that the task may use.
+    #[serde(default)]
+    pub memory_limit: Option<u64>,
+    /// The maximum amount of CPUs, that the task may use.
+    #[serde(default)]
+    pub cpu_limit: Option<f64>,
+    /// Arbitrary key/value metadata of the task.
+    #[serde(default)]
+    pub meta: BTreeMap<String, String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// External conditions, that have to be met before the task is started.
+    #[serde(default)]
+    pub start_conditions: Vec<StartCondition>,
+    /// A command that's run, once the task succeeded.
+    #[serde(default)]
+    pub on_success: Option<String>,
+    /// A command that's run, once the task failed.
+    #[serde(default)]
+    pub on_failure: Option<String>,
+    /// The shell that runs the command on Windows.
+    #[serde(default)]
+    pub shell: Option<WindowsShell>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct SwitchMessage {
+    pub task_id_1: usize,
+    pub task_id_2: usize,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct EnqueueMessage {
+    pub task_ids: Vec<usize>,
+    pub enqueue_at: Option<DateTime<Local>>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct SnoozeMessage {
+    pub task_ids: Vec<usize>,
+    pub enqueue_at: DateTime<Local>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct StartMessage {
+    pub tasks: TaskSelection,
+    pub children: bool,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct RestartMessage {
+    pub tasks: Vec<TasksToRestart>,
+    pub start_immediately: bool,
+    pub stashed: bool,
+    /// Restart all failed tasks that match this selection in-place.
+    /// The tasks are resolved by the daemon, in addition to the explicitly listed `tasks`.
+    #[serde(default)]
+    pub failed: Option<FailedSelection>,
+}
+
+/// A selection of failed tasks, which is resolved on the daemon side.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum FailedSelection {
+    All,
+    Group(String),
+    Label(String),
+}
+
+impl FailedSelection {
+    /// Check whether a task failed and is part of this selection.
+    pub fn matches(&self, task: &Task) -> bool {
+        if !task.failed() {
+            return false;
+        }
+
+        match self {
+            FailedSelection::All => true,
+            FailedSelection::Group(group) => &task.group == group,
+            FailedSelection::Label(label) => task.label.as_ref() == Some(label),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct TasksToRestart {
+    pub task_id: usize,
+    pub command: String,
+    pub path: String,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct PauseMessage {
+    pub tasks: TaskSelection,
+    pub wait: bool,
+    pub children: bool,
+}
+
+/// This is a small custom Enum for all currently supported unix signals.
+/// Supporting all unix signals would be a mess, since there is a LOT of them.
+///
+/// This is also needed for usage in clap, since nix's Signal doesn't implement [Display] and
+/// [std::str::FromStr].
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, Display, EnumString)]
+pub enum Signal {
+    #[strum(serialize = "SigInt", serialize = "sigint", serialize = "2")]
+    SigInt,
+    #[strum(serialize = "SigKill", serialize = "sigkill", serialize = "9")]
+    SigKill,
+    #[strum(serialize = "SigTerm", serialize = "sigterm", serialize = "15")]
+    SigTerm,
+    #[strum(serialize = "SigCont", serialize = "sigcont", serialize = "18")]
+    SigCont,
+    #[strum(serialize = "SigStop", serialize = "sigstop", serialize = "19")]
+    SigStop,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct KillMessage {
+    pub tasks: TaskSelection,
+    pub children: bool,
+    pub signal: Option<Signal>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct SendMessage {
+    pub task_id: usize,
+    pub input: String,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct EditMessage {
+    pub task_id: usize,
+    pub command: String,
+    pub path: String,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct EditEnvMessage {
+    pub task_id: usize,
+    /// Variables, that are added or overwritten.
+    pub set: BTreeMap<String, String>,
+    /// Variables, that are removed. This happens before new variables are set.
+    pub unset: Vec<String>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct EditResponseMessage {
+    pub task_id: usize,
+    pub command: String,
+    pub path: String,
+    /// The group of the task. Only used to give the user some context while editing.
+    #[serde(default)]
+    pub group: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The status of the task, before it has been locked for editing.
+    #[serde(default)]
+    pub prev_status: Option<TaskStatus>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum GroupMessage {
+    Add(String),
+    Remove(String),
+    List,
+}
+
+/// Something that's going to happen at a known point in time.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct ScheduledEvent {
+    pub time: DateTime<Local>,
+    pub kind: ScheduledEventKind,
+    /// The task this event belongs to.
+    pub task_id: Option<usize>,
+    pub group: String,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum ScheduledEventKind {
+    /// A stashed task is enqueued.
+    Enqueue,
+    /// A queued or stashed task expires, unless it's started before.
+    Expire,
+    /// A running task is killed, as it exceeds its maximum runtime.
+    Timeout,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct GroupResponseMessage {
+    pub groups: BTreeMap<String, GroupStatus>,
+    pub settings: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub pause_reasons: BTreeMap<String, PauseReason>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct ResetMessage {
+    pub children: bool,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct CleanMessage {
+    #[serde(default = "false_default")]
+    pub successful_only: bool,
+    /// Move the logs of all cleaned tasks to the archive instead of deleting them.
+    #[serde(default)]
+    pub keep_logs: bool,
+    /// Only clean tasks with this tag.
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Only clean tasks of the array with this id.
+    #[serde(default)]
+    pub array: Option<usize>,
+}
+fn false_default() -> bool {
+    false
+}
+
+/// Determines which type of shutdown we're dealing with.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum Shutdown {
+    /// Emergency is most likely a system unix signal or a CTRL+C in a terminal.
+    Emergency,
+    /// Graceful is user initiated and expected.
+    Graceful,
+    /// Don't start any new tasks and shut down gracefully, once all running tasks finished.
+    Drain,
+}
+
+/// `err` decides, whether you should stream stderr or stdout.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct StreamRequestMessage {
+    pub task_id: Option<usize>,
+    pub err: bool,
+    /// Start streaming at this byte offset of the log file.
+    /// This is used to resume a stream after the client reconnected.
+    #[serde(default)]
+    pub offset: u64,
+    /// End the stream, once the task finished and all of its output has been sent.
+    #[serde(default)]
+    pub until_done: bool,
+}
+
+/// A chunk of a followed log file.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "StreamRepresentation")]
+pub struct StreamMessage {
+    pub text: String,
+    /// The amount of bytes, that have been read from the log file for this chunk. \
+    /// This differs from the length of the text, if the output isn't valid UTF-8.
+    pub bytes: u64,
+}
+
+/// Older daemons send the output as plain text.
+/// Its length is used as the amount of bytes, that have been read.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StreamRepresentation {
+    Chunk { text: String, bytes: u64 },
+    Text(String),
+}
+
+impl From<StreamRepresentation> for StreamMessage {
+    fn from(representation: StreamRepresentation) -> Self {
+        match representation {
+            StreamRepresentation::Chunk { text, bytes } => StreamMessage { text, bytes },
+            StreamRepresentation::Text(text) => StreamMessage {
+                bytes: text.len() as u64,
+                text,
+            },
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct MirrorRequestMessage {
+    /// How many of the last lines of each log file are sent.
+    pub lines: usize,
+}
+
+/// An update for a mirror, which is sent whenever the state or the output of a task changed.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct MirrorUpdateMessage {
+    pub state: Box<State>,
+    /// The last lines of `(stdout, stderr)` of all tasks, whose output changed since the
+    /// last update. The first update contains the output of all tasks.
+    pub logs: BTreeMap<usize, (String, String)>,
+}
+
+/// Request logs for specific tasks.
+///
+/// `task_ids` specifies the requested tasks. If none are given, all tasks are selected.
+/// `send_logs` Determines whether tasks should be sent at all.
+/// `lines` Determines whether only a few lines of log should be returned.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct LogRequestMessage {
+    pub task_ids: Vec<usize>,
+    pub send_logs: bool,
+    pub lines: Option<usize>,
+    /// Request the logs of archived tasks instead.
+    #[serde(default)]
+    pub archived: bool,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryRequestMessage {
+    /// Only return tasks, whose command or label match this regular expression.
+    pub search: Option<String>,
+    /// Only return tasks that finished after this point in time.
+    pub since: Option<DateTime<Local>>,
+}
+
+/// A cleaned task, that's kept in the history.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub id: usize,
+    pub command: String,
+    pub path: String,
+    pub group: String,
+    pub label: Option<String>,
+    pub result: TaskResult,
+    pub start: Option<DateTime<Local>>,
+    pub end: Option<DateTime<Local>>,
+    /// The last lines of the task's output.
+    pub output: String,
+}
+
+/// Helper struct for sending tasks and their log output to the client.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct TaskLogMessage {
+    pub task: Task,
+    pub stdout: Option<Vec<u8>>,
+    pub stderr: Option<Vec<u8>>,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct ParallelMessage {
+    pub parallel_tasks: usize,
+    pub group: String,
+    /// Run the tasks of the group strictly one after another.
+    /// `parallel_tasks` is ignored in this case.
+    #[serde(default)]
+    pub serial: bool,
+}
+
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct TracedMessage {
+    pub request_id: String,
+    pub message: Box<Message>,
+    /// The version of the client that sent this request.
+    #[serde(default)]
+    pub client_version: Option<String>,
+}
+
+impl TracedMessage {
+    /// Tag a message with a new random request id and the version of the sending client.
+    pub fn new(message: Message, client_version: &str) -> Self {
+        let id: u64 = rand::thread_rng().gen();
+        TracedMessage {
+            request_id: format!("{:016x}", id),
+            message: Box::new(message),
+            client_version: Some(client_version.to_string()),
+        }
+    }
+}
+
+pub fn create_success_message<T: ToString>(text: T) -> Message {
+    Message::Success(text.to_string())
+}
+
+/// A failure response of the daemon. \
+/// The `kind` allows clients and scripts to react to specific failures,
+/// while the `text` is meant to be shown to humans.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "FailureRepresentation")]
+pub struct FailureMessage {
+    pub kind: FailureKind,
+    pub text: String,
+}
+
+/// The reason why the daemon couldn't process a request.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub enum FailureKind {
+    /// The given task doesn't exist.
+    TaskNotFound { task_id: usize },
+    /// The given group doesn't exist.
+    GroupNotFound { group: String },
+    /// The task isn't in a status that allows the requested action.
+    InvalidState { expected: String, actual: String },
+    /// The client isn't allowed to perform the requested action.
+    PermissionDenied,
+    /// Any other failure. Only the human readable text is available.
+    Other,
+}
+
+/// The outcome of an action on several explicitly given tasks. \
+/// The action might only have succeeded for some of them.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize)]
+pub struct TaskActionResponseMessage {
+    /// A description of the action, e.g. `Tasks are being killed`.
+    pub text: String,
+    /// The tasks for which the action succeeded.
+    pub succeeded: Vec<usize>,
+    /// The tasks for which the action failed, together with the reason.
+    pub failed: BTreeMap<usize, FailureKind>,
+}
+
+/// Older daemons send failures as plain text.
+/// Those are still understood and treated as [FailureKind::Other].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FailureRepresentation {
+    Typed { kind: FailureKind, text: String },
+    Text(String),
+}
+
+impl From<FailureRepresentation> for FailureMessage {
+    fn from(representation: FailureRepresentation) -> Self {
+        match representation {
+            FailureRepresentation::Typed { kind, text } => FailureMessage { kind, text },
+            FailureRepresentation::Text(text) => FailureMessage {
+                kind: FailureKind::Other,
+                text,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for FailureMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+pub fn create_failure_message<T: ToString>(text: T) -> Message {
+    create_typed_failure_message(FailureKind::Other, text)
+}
+
+pub fn create_typed_failure_message<T: ToString>(kind: FailureKind, text: T) -> Message {
+    Message::Failure(FailureMessage {
+        kind,
+        text: text.to_string(),
+    })
+}
@@ -0,0 +1,98 @@
+//! Helpers for the paths of tasks on Windows.
+//!
+//! These are plain string operations, so a client on any platform can handle the paths
+//! of a remote Windows daemon.
+
+/// Remove the `\\?\` prefix of verbatim paths, as they're returned by `canonicalize`. \
+/// `cmd.exe` and many other programs don't accept them as working directory.
+///
+/// `\\?\UNC\server\share` becomes `\\server\share` and `\\?\C:\dir` becomes `C:\dir`.
+pub fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", share)
+    } else {
+        path.strip_prefix(r"\\?\").unwrap_or(path).to_string()
+    }
+}
+
+/// Whether the path points to a network share, e.g. `\\server\share\dir`.
+pub fn is_unc_path(path: &str) -> bool {
+    let path = strip_verbatim_prefix(path);
+    path.starts_with(r"\\") || path.starts_with("//")
+}
+
+/// Whether the path is relative to the current directory of a drive, e.g. `D:` or `D:data`.
+pub fn is_drive_relative(path: &str) -> bool {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(drive), Some(':'), None) => drive.is_ascii_alphabetic(),
+        (Some(drive), Some(':'), Some(next)) => {
+            drive.is_ascii_alphabetic() && next != '\\' && next != '/'
+        }
+        _ => false,
+    }
+}
+
+/// Whether this looks like a Windows path, i.e. it starts with a drive letter or is a share.
+fn is_windows_path(path: &str) -> bool {
+    let mut chars = path.chars();
+    let has_drive = matches!(
+        (chars.next(), chars.next()),
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic()
+    );
+    has_drive || is_unc_path(path)
+}
+
+/// Use backslashes in Windows paths, e.g. `C:/Users/me` becomes `C:\Users\me`. \
+/// Other paths are returned unchanged.
+pub fn normalize_separators(path: &str) -> String {
+    if is_windows_path(path) {
+        path.replace('/', "\\")
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        assert_eq!(strip_verbatim_prefix(r"\\?\C:\data"), r"C:\data");
+        assert_eq!(
+            strip_verbatim_prefix(r"\\?\UNC\server\share\dir"),
+            r"\\server\share\dir"
+        );
+        assert_eq!(strip_verbatim_prefix(r"C:\data"), r"C:\data");
+        assert_eq!(strip_verbatim_prefix("/home/user"), "/home/user");
+    }
+
+    #[test]
+    fn test_unc_and_drive_relative_paths() {
+        assert!(is_unc_path(r"\\server\share"));
+        assert!(is_unc_path(r"\\?\UNC\server\share"));
+        assert!(!is_unc_path(r"\\?\C:\data"));
+        assert!(!is_unc_path(r"C:\data"));
+
+        assert!(is_drive_relative("D:"));
+        assert!(is_drive_relative("D:data"));
+        assert!(!is_drive_relative(r"D:\data"));
+        assert!(!is_drive_relative("D:/data"));
+        assert!(!is_drive_relative("/home/user"));
+    }
+
+    #[test]
+    fn test_normalize_separators() {
+        assert_eq!(normalize_separators("C:/Users/me"), r"C:\Users\me");
+        assert_eq!(
+            normalize_separators(r"C:\Users/me\data"),
+            r"C:\Users\me\data"
+        );
+        assert_eq!(
+            normalize_separators("//server/share/dir"),
+            r"\\server\share\dir"
+        );
+        assert_eq!(normalize_separators("/home/user"), "/home/user");
+    }
+}
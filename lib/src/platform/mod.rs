@@ -0,0 +1,19 @@
+/// MacOs specific stuff
+#[cfg(target_vendor = "apple")]
+pub mod apple;
+/// Linux specific stuff
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+pub mod linux;
+/// Windows specific stuff
+#[cfg(target_os = "windows")]
+pub mod windows;
+
+// The next block is platform specific directory functions
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+pub use self::linux::directories;
+
+#[cfg(target_vendor = "apple")]
+pub use self::apple::directories;
+
+#[cfg(target_os = "windows")]
+pub use self::windows::directories;
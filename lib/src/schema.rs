@@ -0,0 +1,24 @@
+//! JSON Schemas of Pueue's machine readable formats.
+//!
+//! The schemas are generated from the Rust types and checked into the repository
+//! under `schemas/v<SCHEMA_VERSION>/`. \
+//! Fields may be added within a version, but removing, renaming or retyping a field
+//! requires a new version.
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+use crate::state::State;
+use crate::task::Task;
+
+/// The version of the published schemas.
+pub const SCHEMA_VERSION: usize = 1;
+
+/// The schema of `pueue status --json` and the daemon's `state.json`.
+pub fn status_schema() -> RootSchema {
+    schema_for!(State)
+}
+
+/// The schema of `pueue export`, which is also read by `pueue import` and `pueue diff`.
+pub fn export_schema() -> RootSchema {
+    schema_for!(Vec<Task>)
+}
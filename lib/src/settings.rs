@@ -0,0 +1,1279 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{create_dir_all, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::NaiveTime;
+use config::Config;
+use log::info;
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use shellexpand::tilde;
+
+use crate::error::Error;
+use crate::log::default_log_directory;
+use crate::platform::directories::*;
+use crate::task::WindowsShell;
+
+pub const PUEUE_DEFAULT_GROUP: &str = "default";
+
+/// All settings which are used by both, the client and the daemon
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Shared {
+    /// Don't access this property directly, but rather use the getter with the same name.
+    /// It's only public to allow proper integration testing.
+    ///
+    /// The directory that is used for all runtime information. \
+    /// I.e. task logs, sockets, state dumps, etc.
+    pub pueue_directory: PathBuf,
+    /// Don't access this property directly, but rather use the getter with the same name.
+    ///
+    /// The directory, in which the log files of newly started tasks are created. \
+    /// Defaults to `task_logs` inside the `pueue_directory`.
+    /// Use `pueue daemon set-log-dir` to move the logs to another directory.
+    #[serde(default)]
+    pub log_directory: Option<PathBuf>,
+    /// If this is set to true, unix sockets will be used.
+    /// Otherwise we default to TCP+TLS
+    #[cfg(not(target_os = "windows"))]
+    pub use_unix_socket: bool,
+    /// Don't access this property directly, but rather use the getter with the same name.
+    /// It's only public to allow proper integration testing.
+    ///
+    /// The path to the unix socket.
+    #[cfg(not(target_os = "windows"))]
+    pub unix_socket_path: PathBuf,
+    /// If this is set to true, clients that connect via the unix socket are authenticated by
+    /// comparing their user id with the one of the daemon. \
+    /// The shared secret is then neither needed nor created.
+    #[cfg(not(target_os = "windows"))]
+    #[serde(default = "default_unix_socket_peer_credentials")]
+    pub unix_socket_peer_credentials: bool,
+
+    /// The TCP hostname/ip address.
+    pub host: String,
+    /// The TCP port.
+    pub port: String,
+    /// Don't access this property directly, but rather use the getter with the same name.
+    /// It's only public to allow proper integration testing.
+    ///
+    /// The path to the TLS certificate used by the daemon. \
+    /// This is also used by the client to verify the daemon's identity.
+    pub daemon_cert: PathBuf,
+    /// Don't access this property directly, but rather use the getter with the same name.
+    /// It's only public to allow proper integration testing.
+    ///
+    /// The path to the TLS key used by the daemon.
+    pub daemon_key: PathBuf,
+    /// Don't access this property directly, but rather use the getter with the same name.
+    /// It's only public to allow proper integration testing.
+    ///
+    /// The path to the file containing the shared secret used to authenticate the client.
+    pub shared_secret_path: PathBuf,
+    /// What happens, if the client and the daemon run versions with a different
+    /// major or minor version.
+    #[serde(default)]
+    pub version_mismatch: VersionMismatch,
+}
+
+/// How version differences between the client and the daemon are handled. \
+/// Versions with a different major or minor version might not understand each other's messages.
+#[derive(PartialEq, Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionMismatch {
+    /// Print a warning, but continue anyway.
+    #[default]
+    Warn,
+    /// Refuse to communicate.
+    Refuse,
+}
+
+/// All settings which are used by the client
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Client {
+    /// If set to true, all tasks will be restart in place, instead of creating a new task.
+    /// False is the default, as you'll lose the logs of the previously failed tasks when
+    /// restarting tasks in place.
+    pub restart_in_place: bool,
+    /// If set to true, `pueue pause` only prevents new tasks from being started,
+    /// while already running tasks are allowed to finish.
+    /// Use `pueue pause --hard` to pause the running tasks as well.
+    #[serde(default)]
+    pub soft_pause: bool,
+    /// Whether the client should read the logs directly from disk or whether it should
+    /// request the data from the daemon via socket.
+    pub read_local_logs: bool,
+    /// Whether the client should show a confirmation question on potential dangerous actions.
+    pub show_confirmation_questions: bool,
+    /// Whether aliases specified in `pueue_aliases.yml` should be expanded in the `pueue status`
+    /// or shown in their short form.
+    pub show_expanded_aliases: bool,
+    /// Whether the client should use dark shades instead of regular colors.
+    pub dark_mode: bool,
+    /// The max amount of lines each task get's in the `pueue status` view.
+    pub max_status_lines: Option<usize>,
+    /// The format that will be used to display time formats in `pueue status`.
+    pub status_time_format: String,
+    /// The format that will be used to display datetime formats in `pueue status`.
+    pub status_datetime_format: String,
+    /// The message catalog used for all user-facing strings of the client. \
+    /// Either the name of a built-in catalog (`en`, `de`) or the path to a custom YAML catalog.
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// If set, a bare `pueue status` only shows the tasks of this group.
+    #[serde(default)]
+    pub default_group_filter: Option<String>,
+    /// The amount of seconds the client waits for the daemon to accept a connection or to
+    /// answer a request, before giving up. `0` disables the timeout. \
+    /// Long-running commands such as `wait` and `follow` aren't affected.
+    #[serde(default = "default_client_timeout")]
+    pub timeout: u64,
+    /// If set to true, the client starts the daemon in the background, if it can't connect
+    /// to a local daemon.
+    #[serde(default)]
+    pub auto_start_daemon: bool,
+    /// The daemon binary that's started by `auto_start_daemon`. \
+    /// Defaults to the `pueued` next to the client's binary or the one in the `PATH`.
+    #[serde(default)]
+    pub daemon_path: Option<PathBuf>,
+    /// Additional arguments that are passed to the daemon started by `auto_start_daemon`.
+    #[serde(default)]
+    pub daemon_arguments: Vec<String>,
+    /// The unix socket of `pueue broker`. \
+    /// While the broker runs, all other commands send their requests through the broker's
+    /// already authenticated connections, instead of connecting to the daemon themselves.
+    #[serde(default)]
+    pub broker_socket: Option<PathBuf>,
+    /// Named command templates, which are added via `pueue run-template <name> KEY=VALUE`. \
+    /// `{KEY}` placeholders in the command are replaced with the shell-escaped values.
+    #[serde(default)]
+    pub templates: BTreeMap<String, CommandTemplate>,
+}
+
+/// The default connection and request timeout of the client in seconds.
+fn default_client_timeout() -> u64 {
+    30
+}
+
+#[cfg(not(target_os = "windows"))]
+fn default_unix_socket_peer_credentials() -> bool {
+    true
+}
+
+/// The default locale of the client.
+fn default_locale() -> String {
+    "en".to_string()
+}
+
+/// All settings which are used by the daemon
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Daemon {
+    /// How many parallel tasks a group should have by default
+    pub default_parallel_tasks: usize,
+    /// Whether a group should be paused as soon as a single task fails
+    pub pause_group_on_failure: bool,
+    /// Whether the daemon (and all groups) should be paused as soon as a single task fails
+    pub pause_all_on_failure: bool,
+    /// The callback that's called whenever a task finishes.
+    pub callback: Option<String>,
+    /// The amount of log lines from stdout/stderr that are passed to the callback command.
+    pub callback_log_lines: usize,
+    /// How many callbacks may run at the same time. Further callbacks are queued.
+    #[serde(default = "default_callback_parallel")]
+    pub callback_parallel: usize,
+    /// How many callbacks may be started per minute.
+    #[serde(default)]
+    pub callback_rate_limit: Option<usize>,
+    /// How often a failed callback is retried.
+    #[serde(default)]
+    pub callback_retries: usize,
+    /// How many callbacks may wait in the queue. Any further callbacks are dropped.
+    #[serde(default = "default_callback_queue_size")]
+    pub callback_queue_size: usize,
+    /// This shouldn't be manipulated manually if the daemon is running.
+    /// This represents all known groups and their amount of parallel tasks.
+    pub groups: BTreeMap<String, usize>,
+    /// Groups, that run their tasks strictly one after another in the order they were added,
+    /// see `pueue parallel --serial`. \
+    /// A task only starts, once all tasks of its group with a lower id are done, and a failed
+    /// task pauses its group.
+    #[serde(default)]
+    pub serial_groups: Vec<String>,
+    /// Keep cleaned tasks in the history, which can be browsed via `pueue history`.
+    #[serde(default = "default_keep_history")]
+    pub keep_history: bool,
+    /// The amount of output lines, that are kept for each task in the history.
+    #[serde(default = "default_history_output_lines")]
+    pub history_output_lines: usize,
+    /// The amount of cleaned tasks, that are kept in the archive via `--keep-logs`. \
+    /// Once there are more, the oldest ones and their logs are removed.
+    #[serde(default = "default_max_archived_tasks")]
+    pub max_archived_tasks: usize,
+    /// Directories that are watched by the daemon.
+    /// A task is enqueued for each new or modified file in these directories.
+    #[serde(default)]
+    pub watchers: Vec<Watcher>,
+    /// Shut the daemon down, once it has been idle for this long, e.g. `30m`. \
+    /// The daemon is idle, if none of its tasks are running, paused, queued or scheduled.
+    #[serde(default)]
+    pub exit_when_idle: Option<String>,
+    /// Refuse to add a task, if an unfinished task in the same group already has its label.
+    /// This allows to reliably address tasks by their label.
+    #[serde(default)]
+    pub unique_labels: bool,
+    /// A command that inspects and rewrites each new task, before it's added. \
+    /// It receives the task's `command`, `path`, `group`, `label`, `tags` and `created_by` as JSON
+    /// via stdin and may print a JSON object with the fields it changes,
+    /// e.g. `{"command": "nice -n 10 ls"}` or `{"group": "heavy"}`. \
+    /// If it exits with a non-zero code, the task is rejected and its stderr is shown to the user.
+    #[serde(default)]
+    pub add_hook: Option<String>,
+    /// Paths to WebAssembly policy modules, which are invoked when tasks are added, started
+    /// and finished. They can reject or rewrite new tasks and hold back queued tasks. \
+    /// Each module exports its `memory`, `pueue_abi_version`, `pueue_alloc` and handlers such as
+    /// `pueue_on_add`, which exchange the task and their response as JSON.
+    #[serde(default)]
+    pub wasm_policies: Vec<String>,
+    /// The path to a Lua script, which may define the functions `route(task)`, to choose the group
+    /// of new tasks, `format_callback(task)`, to build the callback command of finished tasks,
+    /// and `is_success(task)`, to decide whether a task that exited actually succeeded.
+    #[serde(default)]
+    pub lua_script: Option<String>,
+    /// Don't start any new tasks, while less than this amount of disk space is available
+    /// for the pueue directory, e.g. `500M` or `2G`.
+    /// This prevents task logs from filling up the disk, which would break saving the state.
+    #[serde(default)]
+    pub min_free_disk: Option<String>,
+    /// A command that's called once, when new tasks are held back due to `min_free_disk`. \
+    /// `{{ path }}`, `{{ available }}` and `{{ threshold }}` are replaced with the pueue
+    /// directory and the respective amount of bytes.
+    #[serde(default)]
+    pub low_disk_callback: Option<String>,
+    /// Groups that only start tasks, while a check command succeeds.
+    /// E.g. a connectivity check for groups that up- or download files.
+    #[serde(default)]
+    pub group_conditions: BTreeMap<String, GroupCondition>,
+    /// Groups that only start tasks, while the system isn't too busy.
+    /// E.g. `max_load: 8.0` or `min_free_memory: 4G`. This is only supported on Linux.
+    #[serde(default)]
+    pub group_load_limits: BTreeMap<String, LoadLimit>,
+    /// The time of day, in which a group may run its tasks, e.g. `heavy: "22:00-06:00"`. \
+    /// A group is paused, once its window closes, and resumed, once it opens again.
+    /// Manually starting or pausing a group overrides this until the next change of its window.
+    #[serde(default)]
+    pub group_windows: BTreeMap<String, String>,
+    /// Commands that wrap all tasks of a group, e.g. `trickle -d 1000 sh -c {{ command }}`. \
+    /// `{{ command }}` is replaced with the shell-escaped command of the task,
+    /// `{{ id }}` and `{{ group }}` with the task's id and group.
+    #[serde(default)]
+    pub group_wrappers: BTreeMap<String, String>,
+    /// Defaults for the tasks of a group, e.g. `project: {cwd: ~/project, env: {RUST_LOG: debug}}`. \
+    /// They're applied to new tasks of the group, that don't specify these values themselves.
+    #[serde(default)]
+    pub group_defaults: BTreeMap<String, GroupDefaults>,
+    /// The names of secrets, which are passed as environment variables to all tasks of a group,
+    /// e.g. `deploy: [AWS_SECRET_ACCESS_KEY]`. \
+    /// They're looked up via `secret_command` in the background, whenever a task of the group is
+    /// started. Their values are never saved and are replaced with `***` in the task's output,
+    /// before it's written to the logs.
+    #[serde(default)]
+    pub group_secrets: BTreeMap<String, Vec<String>>,
+    /// The command that prints the value of a secret, e.g.
+    /// `secret-tool lookup service pueue name {{ name }}`. \
+    /// `{{ name }}` is replaced with the shell-escaped name of the secret.
+    /// A trailing newline of the output is removed.
+    #[serde(default)]
+    pub secret_command: Option<String>,
+    /// The amount of seconds `secret_command` may take to print a secret. \
+    /// Slower commands are killed and the task fails to start.
+    #[serde(default = "default_secret_timeout")]
+    pub secret_timeout: u64,
+    /// Regular expressions, whose matches are replaced with `***` in the output of tasks,
+    /// e.g. `(?i)password=\S+`. \
+    /// The output is redacted line by line, before it's written to the logs, so matches can't
+    /// span multiple lines. The daemon doesn't start, if any of the expressions is invalid.
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
+    /// The default maximum runtime of the tasks of a group, e.g. `2h`. \
+    /// Tasks that run for longer are killed and marked as `FailedTimeout`.
+    /// This can be overwritten for single tasks via `pueue add --max-runtime`.
+    #[serde(default)]
+    pub group_max_runtimes: BTreeMap<String, String>,
+    /// The amount of CPU slots of a group, e.g. `16`. \
+    /// Tasks of the group are only started, while enough slots are free for the CPUs they
+    /// request via `pueue add --cpus`, which is `1` by default. The parallel limit still applies.
+    #[serde(default)]
+    pub group_cpus: BTreeMap<String, usize>,
+    /// Pools of exclusive resources, e.g. `gpus: [0, 1, 2, 3]`. \
+    /// Tasks request units via `pueue add --resource gpus=2` and are only started, once enough
+    /// units are free. The assigned units are exported as `PUEUE_ASSIGNED_GPUS=0,1`.
+    #[serde(default)]
+    pub resource_pools: BTreeMap<String, Vec<String>>,
+    /// A cgroup v2 directory, that has been delegated to the daemon, e.g.
+    /// `/sys/fs/cgroup/user.slice/user-1000.slice/user@1000.service/pueue`. \
+    /// Tasks with a memory or CPU limit are started inside their own cgroup below it.
+    /// This is only supported on Linux.
+    #[serde(default)]
+    pub cgroup_root: Option<String>,
+    /// Pause all running groups before the system suspends and resume them after it woke up,
+    /// so tasks aren't confused by the jump of the clock. \
+    /// This listens to systemd-logind and is only supported on Linux.
+    #[serde(default)]
+    pub pause_on_suspend: bool,
+    /// Send e-mails via SMTP, when tasks fail or when all tasks of a group finished.
+    #[serde(default)]
+    pub mail: Option<Mail>,
+    /// Post a message to Slack, Discord or Matrix, whenever a task finishes.
+    #[serde(default)]
+    pub chat_notifications: Vec<ChatNotification>,
+    /// When the callback, mails and chats are notified about the tasks of a group.
+    /// Groups without a policy are always notified.
+    #[serde(default)]
+    pub group_notifications: BTreeMap<String, NotificationPolicy>,
+    /// The maximum size of a running task's stdout and stderr log files, e.g. `100M`. \
+    /// Larger files are rotated, see `rotated_logs`.
+    #[serde(default)]
+    pub max_log_size: Option<String>,
+    /// How many rotated copies of each log file are kept, e.g. `3_stdout.log.1`. \
+    /// If this is `0`, log files are truncated once they exceed `max_log_size`.
+    #[serde(default = "default_rotated_logs")]
+    pub rotated_logs: usize,
+    /// When changes of the state are saved to disk. \
+    /// Delaying the save trades durability for latency on slow disks, see `state_save_delay`.
+    #[serde(default)]
+    pub state_save_mode: StateSaveMode,
+    /// The delay of the `debounce` and `periodic` save modes, e.g. `10s`. Defaults to `5s`.
+    #[serde(default)]
+    pub state_save_delay: Option<String>,
+    /// Flush the state file to the disk via fsync, before it replaces the previous one. \
+    /// This ensures, that the state survives a power loss, but makes saving it slower.
+    #[serde(default)]
+    pub state_fsync: bool,
+    /// Run as a read-only mirror of another daemon. \
+    /// The mirror doesn't run any tasks, but continuously copies the state and the end of each
+    /// task's output from the primary daemon. All requests that change something are rejected.
+    #[serde(default)]
+    pub mirror: Option<Mirror>,
+}
+
+/// When changes of the state are saved to disk.
+/// Pending changes are always saved on shutdown and via `pueue daemon flush`.
+#[derive(PartialEq, Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum StateSaveMode {
+    /// Save the state right after each change.
+    #[default]
+    EveryChange,
+    /// Save the state, once no further changes happened for `state_save_delay`.
+    /// During a constant stream of changes, it's saved at the latest after ten times the delay.
+    Debounce,
+    /// Save the state at most once per `state_save_delay`.
+    Periodic,
+}
+
+/// A check that has to pass, before tasks of a group are started.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct GroupCondition {
+    /// The shell command that's executed to check the condition, e.g. `ping -c 1 example.com`.
+    /// The condition is met, if it exits with `0`.
+    pub command: String,
+    /// The amount of seconds between two checks.
+    /// Checks that take longer than this are killed and count as failed.
+    /// While the condition fails, it's checked less often, up to four times this interval.
+    #[serde(default = "default_group_condition_interval")]
+    pub interval: u64,
+}
+
+/// Defaults of a group, which are applied to its new tasks.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct GroupDefaults {
+    /// The working directory of tasks, that have been added without `--working-directory`.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Environment variables, which take precedence over the environment of the client.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// The shell of tasks, that have been added without `--shell`. This is only used on Windows.
+    #[serde(default)]
+    pub shell: Option<WindowsShell>,
+}
+
+/// Limits of the system load, above which no new tasks of a group are started.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct LoadLimit {
+    /// The maximum one-minute load average, e.g. `8.0`.
+    #[serde(default)]
+    pub max_load: Option<f64>,
+    /// The minimum amount of available memory, e.g. `500M` or `4G`.
+    #[serde(default)]
+    pub min_free_memory: Option<String>,
+}
+
+impl LoadLimit {
+    /// The minimum amount of available memory in bytes. \
+    /// Invalid sizes are ignored.
+    pub fn min_free_memory(&self) -> Option<u64> {
+        self.min_free_memory.as_deref().and_then(parse_size)
+    }
+}
+
+/// E-mail notifications. They're sent via `curl`, which has to be installed.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Mail {
+    /// The SMTP server, e.g. `smtps://mail.example.com` or `smtp://localhost:25`. \
+    /// `smtp://` connections are upgraded via STARTTLS, if the server supports it.
+    pub server: String,
+    /// The sender's address.
+    pub from: String,
+    /// The addresses of all recipients.
+    pub to: Vec<String>,
+    /// The user of the SMTP login. There's no login, if this isn't set.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// A command that prints the password of the SMTP login, e.g.
+    /// `secret-tool lookup service pueue` or `security find-generic-password -s pueue -w`. \
+    /// This way, the password stays in the system's keyring instead of the configuration file.
+    #[serde(default)]
+    pub password_command: Option<String>,
+    /// Send a mail as soon as a task fails.
+    #[serde(default = "default_true")]
+    pub on_failure: bool,
+    /// Send a summary of all finished tasks of a group,
+    /// once the group doesn't have any queued, running or paused tasks left.
+    #[serde(default)]
+    pub digest: bool,
+    /// The templates of the failure mail. They have the same variables as `daemon.callback`.
+    #[serde(default = "default_mail_failure_subject")]
+    pub failure_subject: String,
+    #[serde(default = "default_mail_failure_body")]
+    pub failure_body: String,
+    /// The templates of the digest. `{{ group }}`, `{{ succeeded }}` and `{{ failed }}` are
+    /// available, as well as `{{ tasks }}` with the `id`, `command`, `label`, `result` and
+    /// `runtime` of each task.
+    #[serde(default = "default_mail_digest_subject")]
+    pub digest_subject: String,
+    #[serde(default = "default_mail_digest_body")]
+    pub digest_body: String,
+}
+
+/// The primary daemon, that's followed by a read-only mirror.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Mirror {
+    /// The TCP hostname/ip address of the primary daemon.
+    pub host: String,
+    /// The TCP port of the primary daemon.
+    pub port: String,
+    /// The TLS certificate of the primary daemon.
+    pub daemon_cert: PathBuf,
+    /// The file containing the shared secret of the primary daemon.
+    pub shared_secret_path: PathBuf,
+    /// Connect via this unix socket instead, if the primary daemon runs on the same machine.
+    #[cfg(not(target_os = "windows"))]
+    #[serde(default)]
+    pub unix_socket_path: Option<PathBuf>,
+    /// How many of the last lines of each task's stdout and stderr are copied.
+    #[serde(default = "default_mirror_log_lines")]
+    pub log_lines: usize,
+}
+
+impl Mirror {
+    /// The settings to connect to the primary daemon.
+    /// Everything else is taken from the mirror's own settings.
+    pub fn shared(&self, shared: &Shared) -> Shared {
+        let mut primary = shared.clone();
+        primary.host = self.host.clone();
+        primary.port = self.port.clone();
+        primary.daemon_cert = self.daemon_cert.clone();
+        primary.shared_secret_path = self.shared_secret_path.clone();
+        #[cfg(not(target_os = "windows"))]
+        {
+            primary.use_unix_socket = self.unix_socket_path.is_some();
+            if let Some(path) = &self.unix_socket_path {
+                primary.unix_socket_path = path.clone();
+            }
+        }
+
+        primary
+    }
+}
+
+/// When notifications about the tasks of a group are sent.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationPolicy {
+    /// Notify about every finished task.
+    Always,
+    /// Only notify about failed tasks.
+    /// The digest mail is only sent, if it contains a failed task.
+    OnFailure,
+    /// Don't notify about single tasks.
+    /// Only the digest mail is sent, once all tasks of the group finished.
+    OnDrain,
+    /// Don't send any notifications.
+    Never,
+}
+
+impl NotificationPolicy {
+    /// Whether notifications about a single finished task are sent.
+    pub fn notify_task(&self, failed: bool) -> bool {
+        match self {
+            NotificationPolicy::Always => true,
+            NotificationPolicy::OnFailure => failed,
+            NotificationPolicy::OnDrain | NotificationPolicy::Never => false,
+        }
+    }
+
+    /// Whether a summary is sent, once all tasks of the group finished.
+    pub fn notify_drain(&self, any_failed: bool) -> bool {
+        match self {
+            NotificationPolicy::Always | NotificationPolicy::OnDrain => true,
+            NotificationPolicy::OnFailure => any_failed,
+            NotificationPolicy::Never => false,
+        }
+    }
+}
+
+/// A chat, which is notified about finished tasks.
+/// Messages are sent via `curl`, which has to be installed.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ChatNotification {
+    #[serde(flatten)]
+    pub service: ChatService,
+    /// Only post a message, if a task failed.
+    #[serde(default)]
+    pub only_failures: bool,
+}
+
+/// The supported chat services and everything that's needed to post a message.
+///
+/// Tokens and webhooks are never part of the settings, as those are sent to every client.
+/// Instead, they're printed by a command like `secret-tool lookup service pueue-slack`,
+/// once a message is sent, just like the password of [Mail].
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "service", rename_all = "lowercase")]
+pub enum ChatService {
+    /// Post to a channel via Slack's `chat.postMessage` API.
+    /// The bot token needs the `chat:write` scope.
+    Slack {
+        token_command: String,
+        channel: String,
+    },
+    /// Post via a Discord webhook, which already determines the channel.
+    Discord { webhook_command: String },
+    /// Post to a Matrix room, e.g. `!abcdef:matrix.org`, with the access token of the sender.
+    Matrix {
+        homeserver: String,
+        room: String,
+        token_command: String,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_mail_failure_subject() -> String {
+    "Pueue: Task {{ id }} failed ({{ result }})".to_string()
+}
+
+fn default_mail_failure_body() -> String {
+    "Command: {{ command }}
+Path: {{ path }}
+Group: {{ group }}
+Result: {{ result }}
+Runtime: {{ runtime }}s
+
+stderr:
+{{ stderr }}
+"
+    .to_string()
+}
+
+fn default_mail_digest_subject() -> String {
+    "Pueue: Group {{ group }} finished ({{ succeeded }} succeeded, {{ failed }} failed)".to_string()
+}
+
+fn default_mail_digest_body() -> String {
+    "{{#each tasks}}Task {{ id }}: {{ result }} after {{ runtime }}s
+    {{ command }}
+{{/each}}"
+        .to_string()
+}
+
+/// The default amount of callbacks that may run at the same time.
+fn default_callback_parallel() -> usize {
+    4
+}
+
+/// The default amount of callbacks that may wait in the queue.
+fn default_callback_queue_size() -> usize {
+    1000
+}
+
+fn default_rotated_logs() -> usize {
+    1
+}
+
+fn default_mirror_log_lines() -> usize {
+    100
+}
+
+fn default_keep_history() -> bool {
+    true
+}
+
+fn default_history_output_lines() -> usize {
+    10
+}
+
+fn default_max_archived_tasks() -> usize {
+    100
+}
+
+/// The default timeout of `secret_command` in seconds.
+fn default_secret_timeout() -> u64 {
+    10
+}
+
+/// The default interval of group conditions in seconds.
+fn default_group_condition_interval() -> u64 {
+    30
+}
+
+/// A command template of the client, e.g. `ffmpeg -i {INPUT} {INPUT}.mp4`.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CommandTemplate {
+    /// The command of the task with `{KEY}` placeholders.
+    /// Shell syntax such as `${HOME}` isn't touched.
+    pub command: String,
+    /// The group the tasks are added to, unless another group is given.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Environment variables, which are set in addition to the client's environment.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// A directory that's watched by the daemon (hot folder).
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Watcher {
+    /// The directory that's watched. Subdirectories aren't watched. \
+    /// `~` and environment variables, e.g. `$HOME`, are expanded.
+    pub path: PathBuf,
+    /// Only files whose name matches this glob pattern are processed.
+    #[serde(default = "default_watcher_glob")]
+    pub glob: String,
+    /// The command template of the task. \
+    /// `{{ path }}` and `{{ name }}` are replaced with the shell-escaped path and name of the file.
+    pub command: String,
+    /// The group the tasks are added to.
+    #[serde(default = "default_watcher_group")]
+    pub group: String,
+    /// A file has to stay unchanged for this amount of seconds, before a task is enqueued.
+    /// This prevents tasks from being enqueued for files that are still being written.
+    #[serde(default = "default_watcher_debounce")]
+    pub debounce: u64,
+}
+
+impl Watcher {
+    /// The watched directory with `~` and environment variables, e.g. `$DATA/inbox`, expanded.
+    pub fn expanded_path(&self) -> Result<PathBuf, Error> {
+        shellexpand::full(&self.path.to_string_lossy())
+            .map(|path| PathBuf::from(path.into_owned()))
+            .map_err(|err| Error::InvalidPath(format!("{:?}: {}", self.path, err)))
+    }
+}
+
+/// By default, all files of a watched directory are processed.
+fn default_watcher_glob() -> String {
+    "*".to_string()
+}
+
+/// By default, tasks of watchers are added to the default group.
+fn default_watcher_group() -> String {
+    PUEUE_DEFAULT_GROUP.to_string()
+}
+
+/// The default debounce time of watchers in seconds.
+fn default_watcher_debounce() -> u64 {
+    2
+}
+
+/// The parent settings struct. \
+/// This contains all other setting structs.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Settings {
+    pub client: Client,
+    pub daemon: Daemon,
+    pub shared: Shared,
+}
+
+impl Shared {
+    pub fn expand(old_path: &Path) -> PathBuf {
+        PathBuf::from(tilde(&old_path.to_string_lossy()).into_owned())
+    }
+
+    pub fn pueue_directory(&self) -> PathBuf {
+        Shared::expand(&self.pueue_directory)
+    }
+
+    pub fn log_directory(&self) -> PathBuf {
+        match &self.log_directory {
+            Some(path) => Shared::expand(path),
+            None => default_log_directory(&self.pueue_directory()),
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    pub fn unix_socket_path(&self) -> PathBuf {
+        Shared::expand(&self.unix_socket_path)
+    }
+
+    /// Whether clients are authenticated via the peer credentials of the unix socket. \
+    /// TCP connections always need the shared secret.
+    #[cfg(not(target_os = "windows"))]
+    pub fn use_peer_credentials(&self) -> bool {
+        self.use_unix_socket && self.unix_socket_peer_credentials
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn use_peer_credentials(&self) -> bool {
+        false
+    }
+
+    pub fn daemon_cert(&self) -> PathBuf {
+        Shared::expand(&self.daemon_cert)
+    }
+    pub fn daemon_key(&self) -> PathBuf {
+        Shared::expand(&self.daemon_key)
+    }
+    pub fn shared_secret_path(&self) -> PathBuf {
+        Shared::expand(&self.shared_secret_path)
+    }
+}
+
+impl Daemon {
+    /// The idle duration after which the daemon shuts down. \
+    /// Invalid durations are ignored.
+    pub fn exit_when_idle(&self) -> Option<Duration> {
+        self.exit_when_idle.as_deref().and_then(parse_duration)
+    }
+
+    /// The minimum amount of free disk space in bytes. \
+    /// Invalid sizes are ignored.
+    pub fn min_free_disk(&self) -> Option<u64> {
+        self.min_free_disk.as_deref().and_then(parse_size)
+    }
+
+    /// The expanded paths of all WASM policy modules.
+    pub fn wasm_policies(&self) -> Vec<PathBuf> {
+        self.wasm_policies
+            .iter()
+            .map(|path| Shared::expand(Path::new(path)))
+            .collect()
+    }
+
+    /// The expanded path of the Lua script.
+    pub fn lua_script(&self) -> Option<PathBuf> {
+        self.lua_script
+            .as_ref()
+            .map(|path| Shared::expand(Path::new(path)))
+    }
+
+    /// The maximum size of log files in bytes. \
+    /// Invalid sizes are ignored.
+    pub fn max_log_size(&self) -> Option<u64> {
+        self.max_log_size.as_deref().and_then(parse_size)
+    }
+
+    /// The delay of the `debounce` and `periodic` state save modes. \
+    /// Invalid durations are ignored.
+    pub fn state_save_delay(&self) -> Duration {
+        self.state_save_delay
+            .as_deref()
+            .and_then(parse_duration)
+            .unwrap_or_else(|| Duration::from_secs(5))
+    }
+
+    /// The default maximum runtime of each group. \
+    /// Invalid durations are ignored.
+    pub fn group_max_runtimes(&self) -> BTreeMap<String, Duration> {
+        self.group_max_runtimes
+            .iter()
+            .filter_map(|(group, duration)| Some((group.clone(), parse_duration(duration)?)))
+            .collect()
+    }
+}
+
+/// Parse a duration consisting of an amount and a unit, such as `90s`, `30m`, `2h` or `1d`.
+pub fn parse_duration(src: &str) -> Option<Duration> {
+    let src = src.trim();
+    let unit = src.chars().last()?;
+    let amount: u64 = src[..src.len() - unit.len_utf8()].parse().ok()?;
+    let factor = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 60 * 60 * 24,
+        _ => return None,
+    };
+
+    Some(Duration::from_secs(amount.checked_mul(factor)?))
+}
+
+/// Parse an amount of bytes with an optional binary unit, such as `4096`, `500K`, `20M` or `2G`.
+pub fn parse_size(src: &str) -> Option<u64> {
+    let src = src.trim();
+    let (amount, factor) = match src.chars().last()? {
+        'K' | 'k' => (&src[..src.len() - 1], 1024),
+        'M' | 'm' => (&src[..src.len() - 1], 1024 * 1024),
+        'G' | 'g' => (&src[..src.len() - 1], 1024 * 1024 * 1024),
+        'T' | 't' => (&src[..src.len() - 1], 1024 * 1024 * 1024 * 1024),
+        _ => (src, 1),
+    };
+    let amount: u64 = amount.parse().ok()?;
+
+    amount.checked_mul(factor)
+}
+
+/// A time of day, in which something is allowed, e.g. `22:00-06:00`.
+/// Windows, whose end lies before their start, span midnight.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Check whether a time of day lies in this window.
+    /// The start is part of the window, while the end isn't.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+/// Parse a time window in the form of `HH:MM-HH:MM`, such as `22:00-06:00`.
+pub fn parse_time_window(src: &str) -> Option<TimeWindow> {
+    let (start, end) = src.trim().split_once('-')?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+
+    Some(TimeWindow { start, end })
+}
+
+impl Settings {
+    /// Read from existing config files.
+    /// If no config files can be found or fields are missing, an error is returned.
+    pub fn read(from_file: &Option<PathBuf>) -> Result<Settings, Error> {
+        let config = Config::new();
+
+        // Insert the default group, if it doesn't exist.
+        let mut settings = parse_config(config, true, from_file)?;
+        settings.ensure_default_group();
+
+        Ok(settings)
+    }
+
+    /// Try to read existing config files and
+    /// This function creates a new configuration instance and
+    /// populates it with default values for every option. \
+    /// If a local config file already exists, it is parsed and
+    /// overrules the default option values.
+    ///
+    /// The default local config is located at "~/.config/pueue.yml".
+    ///
+    /// If `require_config` is `true`, an error will be thrown, if no configuration file can be found.
+    /// This is utilized by the client, since only the daemon is allowed to touch the configuration
+    /// file.
+    pub fn read_with_defaults(
+        require_config: bool,
+        from_file: &Option<PathBuf>,
+    ) -> Result<Settings, Error> {
+        let config = Settings::default_config()?;
+
+        let mut settings = parse_config(config, require_config, from_file)?;
+        settings.ensure_default_group();
+
+        Ok(settings)
+    }
+
+    pub fn default_config() -> Result<Config, Error> {
+        let mut config = Config::new();
+        let pueue_path = default_pueue_path()?;
+        config
+            .set_default("shared.pueue_directory", pueue_path.clone())
+            .unwrap();
+        config
+            .set_default("shared.log_directory", None::<String>)
+            .unwrap();
+        #[cfg(not(target_os = "windows"))]
+        config.set_default("shared.use_unix_socket", true).unwrap();
+        #[cfg(not(target_os = "windows"))]
+        config
+            .set_default("shared.unix_socket_path", get_unix_socket_path()?)
+            .unwrap();
+        #[cfg(not(target_os = "windows"))]
+        config
+            .set_default("shared.unix_socket_peer_credentials", true)
+            .unwrap();
+
+        config.set_default("shared.host", "127.0.0.1").unwrap();
+        config.set_default("shared.port", "6924").unwrap();
+        config.set_default("shared.tls_enabled", true).unwrap();
+        config
+            .set_default(
+                "shared.daemon_key",
+                pueue_path.clone() + "/certs/daemon.key",
+            )
+            .unwrap();
+        config
+            .set_default(
+                "shared.daemon_cert",
+                pueue_path.clone() + "/certs/daemon.cert",
+            )
+            .unwrap();
+        config
+            .set_default("shared.shared_secret_path", pueue_path + "/shared_secret")
+            .unwrap();
+        config
+            .set_default("shared.version_mismatch", "warn")
+            .unwrap();
+
+        // Client specific config
+        config
+            .set_default("client.restart_in_place", false)
+            .unwrap();
+        config.set_default("client.soft_pause", false).unwrap();
+        config.set_default("client.read_local_logs", true).unwrap();
+        config
+            .set_default("client.show_expanded_aliases", false)
+            .unwrap();
+        config
+            .set_default("client.show_confirmation_questions", false)
+            .unwrap();
+        config.set_default("client.dark_mode", false).unwrap();
+        config
+            .set_default("client.max_status_lines", None::<i64>)
+            .unwrap();
+        config
+            .set_default("client.newline_between_date_and_time", false)
+            .unwrap();
+        config
+            .set_default("client.status_time_format", "%H:%M:%S".to_string())
+            .unwrap();
+        config
+            .set_default(
+                "client.status_datetime_format",
+                "%Y-%m-%d\n%H:%M:%S".to_string(),
+            )
+            .unwrap();
+        config.set_default("client.locale", "en").unwrap();
+        config
+            .set_default("client.default_group_filter", None::<String>)
+            .unwrap();
+        config.set_default("client.timeout", 30).unwrap();
+        config
+            .set_default("client.auto_start_daemon", false)
+            .unwrap();
+        config
+            .set_default("client.daemon_path", None::<String>)
+            .unwrap();
+        config
+            .set_default("client.daemon_arguments", Vec::<String>::new())
+            .unwrap();
+        config
+            .set_default("client.broker_socket", None::<String>)
+            .unwrap();
+        config
+            .set_default("client.templates", HashMap::<String, String>::new())
+            .unwrap();
+
+        // Daemon specific config
+        config
+            .set_default("daemon.default_parallel_tasks", 1)
+            .unwrap();
+        config
+            .set_default("daemon.pause_group_on_failure", false)
+            .unwrap();
+        config
+            .set_default("daemon.pause_all_on_failure", false)
+            .unwrap();
+        config
+            .set_default("daemon.callback", None::<String>)
+            .unwrap();
+        config.set_default("daemon.callback_log_lines", 10).unwrap();
+        config.set_default("daemon.callback_parallel", 4).unwrap();
+        config
+            .set_default("daemon.callback_rate_limit", None::<i64>)
+            .unwrap();
+        config.set_default("daemon.callback_retries", 0).unwrap();
+        config
+            .set_default("daemon.callback_queue_size", 1000)
+            .unwrap();
+        config
+            .set_default("daemon.groups", HashMap::<String, i64>::new())
+            .unwrap();
+        config
+            .set_default("daemon.watchers", Vec::<String>::new())
+            .unwrap();
+        config.set_default("daemon.keep_history", true).unwrap();
+        config
+            .set_default("daemon.history_output_lines", 10)
+            .unwrap();
+        config
+            .set_default("daemon.max_archived_tasks", 100)
+            .unwrap();
+        config
+            .set_default("daemon.exit_when_idle", None::<String>)
+            .unwrap();
+        config.set_default("daemon.unique_labels", false).unwrap();
+        config
+            .set_default("daemon.add_hook", None::<String>)
+            .unwrap();
+        config
+            .set_default("daemon.wasm_policies", Vec::<String>::new())
+            .unwrap();
+        config
+            .set_default("daemon.lua_script", None::<String>)
+            .unwrap();
+        config
+            .set_default("daemon.min_free_disk", None::<String>)
+            .unwrap();
+        config
+            .set_default("daemon.low_disk_callback", None::<String>)
+            .unwrap();
+        config
+            .set_default("daemon.group_conditions", HashMap::<String, i64>::new())
+            .unwrap();
+        config
+            .set_default("daemon.group_load_limits", HashMap::<String, i64>::new())
+            .unwrap();
+        config
+            .set_default("daemon.group_windows", HashMap::<String, String>::new())
+            .unwrap();
+        config
+            .set_default("daemon.group_wrappers", HashMap::<String, String>::new())
+            .unwrap();
+        config
+            .set_default("daemon.group_defaults", HashMap::<String, String>::new())
+            .unwrap();
+        config
+            .set_default("daemon.group_secrets", HashMap::<String, String>::new())
+            .unwrap();
+        config
+            .set_default("daemon.secret_command", None::<String>)
+            .unwrap();
+        config.set_default("daemon.secret_timeout", 10).unwrap();
+        config
+            .set_default("daemon.redact_patterns", Vec::<String>::new())
+            .unwrap();
+        config
+            .set_default(
+                "daemon.group_max_runtimes",
+                HashMap::<String, String>::new(),
+            )
+            .unwrap();
+        config
+            .set_default("daemon.group_cpus", HashMap::<String, i64>::new())
+            .unwrap();
+        config
+            .set_default("daemon.serial_groups", Vec::<String>::new())
+            .unwrap();
+        config
+            .set_default(
+                "daemon.resource_pools",
+                HashMap::<String, Vec<String>>::new(),
+            )
+            .unwrap();
+        config
+            .set_default("daemon.cgroup_root", None::<String>)
+            .unwrap();
+        config
+            .set_default("daemon.pause_on_suspend", false)
+            .unwrap();
+        config
+            .set_default("daemon.chat_notifications", Vec::<String>::new())
+            .unwrap();
+        config
+            .set_default(
+                "daemon.group_notifications",
+                HashMap::<String, String>::new(),
+            )
+            .unwrap();
+        config
+            .set_default("daemon.max_log_size", None::<String>)
+            .unwrap();
+        config.set_default("daemon.rotated_logs", 1).unwrap();
+        config
+            .set_default("daemon.state_save_mode", "every-change")
+            .unwrap();
+        config
+            .set_default("daemon.state_save_delay", None::<String>)
+            .unwrap();
+        config.set_default("daemon.state_fsync", false).unwrap();
+
+        Ok(config)
+    }
+
+    /// Save the current configuration as a file to the given path. \
+    /// If no path is given, the default configuration path will be used. \
+    /// The file is then written to the main configuration directory of the respective OS.
+    pub fn save(&self, path: &Option<PathBuf>) -> Result<(), Error> {
+        let config_path = if let Some(path) = path {
+            path.clone()
+        } else {
+            default_config_directory()?.join("pueue.yml")
+        };
+        let config_dir = config_path
+            .parent()
+            .ok_or_else(|| Error::InvalidPath("Couldn't resolve config directory".into()))?;
+
+        // Create the config dir, if it doesn't exist yet
+        if !config_dir.exists() {
+            create_dir_all(config_dir)?;
+        }
+
+        let content = match serde_yaml::to_string(self) {
+            Ok(content) => content,
+            Err(error) => {
+                return Err(Error::Generic(format!(
+                    "Configuration file serialization failed:\n{}",
+                    error
+                )))
+            }
+        };
+        let mut file = File::create(config_path)?;
+        file.write_all(content.as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn ensure_default_group(&mut self) {
+        // Insert the default group, if it doesn't exist.
+        if !self.daemon.groups.contains_key(PUEUE_DEFAULT_GROUP) {
+            self.daemon
+                .groups
+                .insert(PUEUE_DEFAULT_GROUP.to_string(), 1);
+        }
+    }
+}
+
+/// Get all possible configuration paths and check if there are
+/// configuration files at those locations.
+/// All configs will be merged by importance.
+///
+/// If `require_config` is `true`, an error will be thrown, if no configuration file can be found.
+fn parse_config(
+    mut config: Config,
+    require_config: bool,
+    from_file: &Option<PathBuf>,
+) -> Result<Settings, Error> {
+    // Load the config from a very specific file path
+    if let Some(path) = from_file {
+        if !path.exists() || !path.is_file() {
+            return Err(Error::FileNotFound(format!(
+                "Couldn't find config at path {:?}",
+                path
+            )));
+        }
+        info!("Using config file at: {:?}", path);
+        let config_file = config::File::with_name(path.to_str().unwrap());
+        config.merge(config_file)?;
+        return Ok(config.try_into()?);
+    };
+
+    let mut config_found = false;
+    info!("Parsing config files");
+    for directory in get_config_directories()?.into_iter() {
+        let path = directory.join("pueue.yml");
+        info!("Checking path: {:?}", &path);
+
+        // Check if the file exists and parse it.
+        if path.exists() && path.is_file() {
+            info!("Found config file at: {:?}", path);
+            config_found = true;
+            let config_file = config::File::with_name(path.to_str().unwrap());
+            config.merge(config_file)?;
+        }
+    }
+
+    if require_config && !config_found {
+        return Err(Error::FileNotFound(
+            "Couldn't find a configuration file. Did you start the daemon yet?".into(),
+        ));
+    }
+
+    // Try to can deserialize the entire configuration
+    Ok(config.try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 30m "), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(
+            parse_duration("1d"),
+            Some(Duration::from_secs(24 * 60 * 60))
+        );
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("-5m"), None);
+        assert_eq!(parse_duration("18446744073709551615d"), None);
+    }
+
+    #[test]
+    fn test_parse_time_window() {
+        let time = |hour, minute| NaiveTime::from_hms(hour, minute, 0);
+
+        let window = parse_time_window("22:00-06:00").unwrap();
+        assert_eq!(window.start, time(22, 0));
+        assert_eq!(window.end, time(6, 0));
+        assert!(window.contains(time(23, 30)));
+        assert!(window.contains(time(5, 59)));
+        assert!(!window.contains(time(6, 0)));
+        assert!(!window.contains(time(12, 0)));
+
+        let window = parse_time_window(" 09:30 - 17:00 ").unwrap();
+        assert!(window.contains(time(9, 30)));
+        assert!(!window.contains(time(18, 0)));
+
+        assert_eq!(parse_time_window("22:00"), None);
+        assert_eq!(parse_time_window("25:00-06:00"), None);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size(" 500K "), Some(500 * 1024));
+        assert_eq!(parse_size("20M"), Some(20 * 1024 * 1024));
+        assert_eq!(parse_size("2g"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("G"), None);
+        assert_eq!(parse_size("1.5G"), None);
+        assert_eq!(parse_size("-1M"), None);
+    }
+
+    #[test]
+    fn test_notification_policies() {
+        assert!(NotificationPolicy::Always.notify_task(false));
+        assert!(NotificationPolicy::OnFailure.notify_task(true));
+        assert!(!NotificationPolicy::OnFailure.notify_task(false));
+        assert!(!NotificationPolicy::OnDrain.notify_task(true));
+        assert!(!NotificationPolicy::Never.notify_task(true));
+
+        assert!(NotificationPolicy::OnFailure.notify_drain(true));
+        assert!(!NotificationPolicy::OnFailure.notify_drain(false));
+        assert!(NotificationPolicy::OnDrain.notify_drain(false));
+        assert!(!NotificationPolicy::Never.notify_drain(true));
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::settings::{Settings, PUEUE_DEFAULT_GROUP};
+use crate::task::{Task, TaskStatus};
+
+pub type SharedState = Arc<Mutex<State>>;
+
+/// Represents the current status of a group.
+/// Each group acts as a queue and can be managed individually.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub enum GroupStatus {
+    Running,
+    Paused,
+}
+
+/// Why the daemon paused a group on its own.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub enum PauseReason {
+    /// The group's scheduling window is closed, see `daemon.group_windows`.
+    OutsideWindow { window: String },
+}
+
+/// Long-lived counters of a group. \
+/// Unlike the tasks, they survive `clean` and `reset`, which allows reports over long periods.
+#[derive(PartialEq, Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct GroupStatistics {
+    /// The amount of tasks, that have been run in this group.
+    pub tasks: u64,
+    /// The amount of tasks, that failed.
+    pub failures: u64,
+    /// The accumulated runtime of all tasks in milliseconds.
+    pub runtime_millis: u64,
+    /// The point in time, since when tasks are counted.
+    pub since: Option<DateTime<Local>>,
+}
+
+/// This is the full representation of the current state of the Pueue daemon.
+///
+/// This includes
+/// - The currently used settings.
+/// - The full task list
+/// - The current status of all tasks
+/// - All known groups.
+///
+/// However, the State does NOT include:
+/// - Information about child processes
+/// - Handles to child processes
+///
+/// That information is saved in the daemon's TaskHandler.
+///
+/// Most functions implemented on the state shouldn't be used by third party software.
+/// The daemon is constantly changing and persisting the state. \
+/// Any changes applied to a state and saved to disk, will most likely be overwritten
+/// after a short time.
+///
+///
+/// The daemon uses the state as a piece of shared memory between it's threads.
+/// It's wrapped in a MutexGuard, which allows us to guarantee sequential access to any crucial
+/// information, such as status changes and incoming commands by the client.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct State {
+    /// The current settings used by the daemon.
+    pub settings: Settings,
+    /// All tasks currently managed by the daemon.
+    pub tasks: BTreeMap<usize, Task>,
+    /// Cleaned tasks, whose logs have been moved to the archive. \
+    /// Their ids aren't reused, so archived logs are never overwritten.
+    #[serde(default)]
+    pub archived_tasks: BTreeMap<usize, Task>,
+    /// All groups
+    pub groups: BTreeMap<String, GroupStatus>,
+    /// Groups, that have been paused by the daemon on its own, e.g. by their scheduling window. \
+    /// Starting or pausing a group by hand removes its reason.
+    #[serde(default)]
+    pub pause_reasons: BTreeMap<String, PauseReason>,
+    /// The statistics of all groups, that ever had a finished task.
+    #[serde(default)]
+    pub statistics: BTreeMap<String, GroupStatistics>,
+    /// The files of each watched directory, together with the modification time for which
+    /// they have been processed. \
+    /// Files, that are new or changed since the daemon stopped, are processed on its next start.
+    #[serde(default)]
+    pub watched_files: BTreeMap<PathBuf, BTreeMap<PathBuf, DateTime<Local>>>,
+    /// Used to store an configuration path that has been explicitely specified.
+    /// Without this, the default config path will be used instead.
+    pub config_path: Option<PathBuf>,
+}
+
+impl State {
+    /// Create a new default state.
+    pub fn new(settings: &Settings, config_path: Option<PathBuf>) -> State {
+        // Create a default group state.
+        let mut groups = BTreeMap::new();
+        for group in settings.daemon.groups.keys() {
+            groups.insert(group.into(), GroupStatus::Running);
+        }
+
+        let mut state = State {
+            settings: settings.clone(),
+            tasks: BTreeMap::new(),
+            archived_tasks: BTreeMap::new(),
+            groups,
+            pause_reasons: BTreeMap::new(),
+            statistics: BTreeMap::new(),
+            watched_files: BTreeMap::new(),
+            config_path,
+        };
+        state.create_group(PUEUE_DEFAULT_GROUP);
+        state
+    }
+
+    /// Count a finished task in the statistics of its group.
+    pub fn record_statistics(&mut self, task_id: usize) {
+        let task = match self.tasks.get(&task_id) {
+            Some(task) => task,
+            None => return,
+        };
+        let runtime = match (task.start, task.end) {
+            (Some(start), Some(end)) => (end - start).num_milliseconds().max(0) as u64,
+            _ => 0,
+        };
+
+        let statistics = self.statistics.entry(task.group.clone()).or_default();
+        statistics.since.get_or_insert_with(Local::now);
+        statistics.tasks += 1;
+        if task.failed() {
+            statistics.failures += 1;
+        }
+        statistics.runtime_millis += runtime;
+    }
+
+    /// Add a new task
+    pub fn add_task(&mut self, mut task: Task) -> usize {
+        let next_id = self.next_task_id();
+        task.id = next_id;
+        self.tasks.insert(next_id, task);
+
+        next_id
+    }
+
+    /// The id, that's given to the next added task.
+    pub fn next_task_id(&self) -> usize {
+        match self.tasks.keys().chain(self.archived_tasks.keys()).max() {
+            None => 0,
+            Some(id) => id + 1,
+        }
+    }
+
+    /// A small helper to change the status of a specific task.
+    pub fn change_status(&mut self, id: usize, new_status: TaskStatus) {
+        if let Some(ref mut task) = self.tasks.get_mut(&id) {
+            task.status = new_status;
+        };
+    }
+
+    /// Add a new group to the daemon. \
+    /// This also check if the given group already exists.
+    /// Create a state.group entry and a settings.group entry, if it doesn't.
+    pub fn create_group(&mut self, group: &str) {
+        if !self.settings.daemon.groups.contains_key(group) {
+            self.settings.daemon.groups.insert(group.into(), 1);
+        }
+        if !self.groups.contains_key(group) {
+            self.groups.insert(group.into(), GroupStatus::Running);
+        }
+    }
+
+    /// Remove a group.
+    /// This also iterates through all tasks and sets any tasks' group
+    /// to the `default` group if it matches the deleted group.
+    pub fn remove_group(&mut self, group: &str) -> Result<(), Error> {
+        if group.eq(PUEUE_DEFAULT_GROUP) {
+            return Err(Error::Generic(
+                "You cannot remove the default group.".into(),
+            ));
+        }
+
+        self.settings.daemon.groups.remove(group);
+        self.settings
+            .daemon
+            .serial_groups
+            .retain(|serial| serial != group);
+        self.groups.remove(group);
+        self.pause_reasons.remove(group);
+
+        // Reset all tasks with removed group to the default.
+        for (_, task) in self.tasks.iter_mut() {
+            if task.group.eq(group) {
+                task.set_default_group();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the group status (running/paused) for all groups including the default queue.
+    /// This removes all pause reasons.
+    pub fn set_status_for_all_groups(&mut self, status: GroupStatus) {
+        self.pause_reasons.clear();
+        let keys = self.groups.keys().cloned().collect::<Vec<String>>();
+        for key in keys {
+            self.groups.insert(key, status.clone());
+        }
+    }
+
+    /// Get all ids of task inside a specific group.
+    pub fn task_ids_in_group(&self, group: &str) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .filter(|(_, task)| task.group.eq(group))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// This checks, whether some tasks match the expected filter criteria. \
+    /// The first result is the list of task_ids that match these statuses. \
+    /// The second result is the list of task_ids that don't match these statuses. \
+    ///
+    /// By default, this checks all tasks in the current state. If a list of task_ids is
+    /// provided as the third parameter, only those tasks will be checked.
+    pub fn filter_tasks<F>(
+        &self,
+        filter: F,
+        task_ids: Option<Vec<usize>>,
+    ) -> (Vec<usize>, Vec<usize>)
+    where
+        F: Fn(&Task) -> bool,
+    {
+        // Either use all tasks or only the exlicitely specified ones.
+        let task_ids = match task_ids {
+            Some(ids) => ids,
+            None => self.tasks.keys().cloned().collect(),
+        };
+
+        self.filter_task_ids(task_ids, filter)
+    }
+
+    /// Same as [tasks_in_statuses], but only checks for tasks of a specific group.
+    pub fn filter_tasks_of_group<F>(&self, filter: F, group: &str) -> (Vec<usize>, Vec<usize>)
+    where
+        F: Fn(&Task) -> bool,
+    {
+        // Return empty vectors, if there's no such group.
+        if !self.groups.contains_key(group) {
+            return (vec![], vec![]);
+        }
+
+        // Filter all task ids of tasks that match the given group.
+        let task_ids = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.group == group)
+            .map(|(id, _)| *id)
+            .collect();
+
+        self.filter_task_ids(task_ids, filter)
+    }
+
+    /// Same as [tasks_in_statuses], but only checks for tasks with a specific tag.
+    pub fn filter_tasks_with_tag<F>(&self, filter: F, tag: &str) -> (Vec<usize>, Vec<usize>)
+    where
+        F: Fn(&Task) -> bool,
+    {
+        let task_ids = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.has_tag(tag))
+            .map(|(id, _)| *id)
+            .collect();
+
+        self.filter_task_ids(task_ids, filter)
+    }
+
+    /// Same as [tasks_in_statuses], but only checks for tasks of a specific array.
+    pub fn filter_tasks_of_array<F>(&self, filter: F, array: usize) -> (Vec<usize>, Vec<usize>)
+    where
+        F: Fn(&Task) -> bool,
+    {
+        let task_ids = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.array == Some(array))
+            .map(|(id, _)| *id)
+            .collect();
+
+        self.filter_task_ids(task_ids, filter)
+    }
+
+    /// Internal function used to check which of the given tasks match the provided filter.
+    ///
+    /// Returns a tuple of all (matching_task_ids, non_matching_task_ids).
+    fn filter_task_ids<F>(&self, task_ids: Vec<usize>, filter: F) -> (Vec<usize>, Vec<usize>)
+    where
+        F: Fn(&Task) -> bool,
+    {
+        let mut matching = Vec::new();
+        let mut mismatching = Vec::new();
+
+        // Filter all task id's that match the provided statuses.
+        for task_id in task_ids.iter() {
+            // Check whether the task exists and save all non-existing task ids.
+            match self.tasks.get(task_id) {
+                None => {
+                    mismatching.push(*task_id);
+                    continue;
+                }
+                Some(task) => {
+                    // Check whether the task status matches the filter.
+                    if filter(task) {
+                        matching.push(*task_id);
+                    } else {
+                        mismatching.push(*task_id);
+                    }
+                }
+            };
+        }
+
+        (matching, mismatching)
+    }
+}
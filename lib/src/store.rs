@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::prelude::*;
+use log::{debug, warn};
+
+use crate::error::Error;
+use crate::network::message::HistoryEntry;
+use crate::state::State;
+
+/// The persistence of the daemon's state and the history of cleaned tasks.
+///
+/// The daemon only talks to this trait, which allows to swap the storage backend
+/// without touching the daemon's logic. [FileStore] is the default implementation.
+pub trait StateStore: Send + Sync {
+    /// Save the current state, replacing the previously saved one.
+    fn save(&self, state: &State) -> Result<(), Error>;
+
+    /// Keep a copy of the state, before large changes such as `clean` or `reset` are applied.
+    fn backup(&self, state: &State) -> Result<(), Error>;
+
+    /// Load the last saved state, if there is any.
+    fn load(&self) -> Result<Option<State>, Error>;
+
+    /// Append entries to the history.
+    fn append_history(&self, entries: &[HistoryEntry]) -> Result<(), Error>;
+
+    /// Read all entries of the history in the order in which they've been added.
+    fn read_history(&self) -> Result<Vec<HistoryEntry>, Error>;
+}
+
+/// How many backups of the state are kept by the [FileStore].
+const MAX_BACKUPS: usize = 10;
+
+/// Stores the state as `state.json` and the history as `history.jsonl` in the pueue directory.
+///
+/// The state is saved as JSON for better readability and debug purposes.
+/// Backups are kept with a timestamp in the `log` directory.
+pub struct FileStore {
+    directory: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(pueue_directory: &Path) -> Self {
+        FileStore {
+            directory: pueue_directory.to_path_buf(),
+        }
+    }
+
+    /// The history is stored as one json object per line, so new entries can simply be appended.
+    fn history_path(&self) -> PathBuf {
+        self.directory.join("history.jsonl")
+    }
+
+    /// Write the state to a temporary file first and replace the original afterwards,
+    /// to prevent loss due to crashes.
+    fn write_state(&self, state: &State, temp: &Path, real: &Path) -> Result<(), Error> {
+        let serialized = serde_json::to_string(&state)
+            .map_err(|err| Error::StateSerialization(err.to_string()))?;
+        let fsync = state.settings.daemon.state_fsync;
+
+        let mut file = File::create(temp)?;
+        file.write_all(serialized.as_bytes())?;
+        if fsync {
+            file.sync_all()?;
+        }
+        fs::rename(temp, real)?;
+
+        // The rename is only durable, once the directory entry has been synced as well.
+        #[cfg(not(target_os = "windows"))]
+        if fsync {
+            if let Some(parent) = real.parent() {
+                File::open(parent)?.sync_all()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the oldest backups, that exceed [MAX_BACKUPS].
+    fn rotate_backups(&self) -> Result<(), Error> {
+        // Old files are removed first (implictly by the BTree order).
+        let mut entries: BTreeMap<SystemTime, PathBuf> = BTreeMap::new();
+        for entry in fs::read_dir(self.directory.join("log"))?.flatten() {
+            entries.insert(entry.metadata()?.modified()?, entry.path());
+        }
+
+        let excess = entries.len().saturating_sub(MAX_BACKUPS);
+        for path in entries.values().take(excess) {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl StateStore for FileStore {
+    fn save(&self, state: &State) -> Result<(), Error> {
+        let real = self.directory.join("state.json");
+        self.write_state(state, &self.directory.join("state.json.partial"), &real)?;
+        debug!("State saved at: {:?}", real);
+
+        Ok(())
+    }
+
+    fn backup(&self, state: &State) -> Result<(), Error> {
+        let path = self.directory.join("log");
+        let time = Utc::now().format("%Y-%m-%d_%H-%M-%S");
+        let real = path.join(format!("{}_state.json", time));
+        self.write_state(
+            state,
+            &path.join(format!("{}_state.json.partial", time)),
+            &real,
+        )?;
+        debug!("State backup created at: {:?}", real);
+
+        self.rotate_backups()
+    }
+
+    fn load(&self) -> Result<Option<State>, Error> {
+        let path = self.directory.join("state.json");
+        // Ignore if the file doesn't exist. It doesn't have to.
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read_to_string(&path)?;
+        let state = serde_json::from_str(&data)
+            .map_err(|err| Error::StateSerialization(err.to_string()))?;
+
+        Ok(Some(state))
+    }
+
+    fn append_history(&self, entries: &[HistoryEntry]) -> Result<(), Error> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let mut serialized = String::new();
+        for entry in entries {
+            let line = serde_json::to_string(entry)
+                .map_err(|err| Error::StateSerialization(err.to_string()))?;
+            serialized.push_str(&line);
+            serialized.push('\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.history_path())?;
+        file.write_all(serialized.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Lines that can't be parsed are skipped.
+    fn read_history(&self) -> Result<Vec<HistoryEntry>, Error> {
+        let path = self.history_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(File::open(&path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => warn!("Skipping invalid history entry: {}", err),
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::settings::Settings;
+    use crate::task::TaskResult;
+
+    fn store() -> (TempDir, FileStore) {
+        let tempdir = TempDir::new("pueue_lib").unwrap();
+        fs::create_dir(tempdir.path().join("log")).unwrap();
+        let store = FileStore::new(tempdir.path());
+        (tempdir, store)
+    }
+
+    fn history_entry(id: usize) -> HistoryEntry {
+        HistoryEntry {
+            id,
+            command: "ls".into(),
+            path: "/tmp".into(),
+            group: "default".into(),
+            label: None,
+            result: TaskResult::Success,
+            start: None,
+            end: None,
+            output: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let (_tempdir, store) = store();
+        assert_eq!(store.load().unwrap(), None);
+
+        let settings = Settings::default_config().unwrap().try_into().unwrap();
+        let state = State::new(&settings, None);
+        store.save(&state).unwrap();
+
+        assert_eq!(store.load().unwrap(), Some(state));
+    }
+
+    #[test]
+    fn test_history() {
+        let (_tempdir, store) = store();
+        assert!(store.read_history().unwrap().is_empty());
+
+        store.append_history(&[history_entry(0)]).unwrap();
+        store
+            .append_history(&[history_entry(1), history_entry(2)])
+            .unwrap();
+
+        let ids: Vec<usize> = store
+            .read_history()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use strum_macros::Display;
+
+use crate::{aliasing::insert_alias, log::default_log_directory, settings::PUEUE_DEFAULT_GROUP};
+
+/// This enum represents the status of the internal task handling of Pueue.
+/// They basically represent the internal task life-cycle.
+#[derive(PartialEq, Clone, Debug, Display, Serialize, Deserialize, JsonSchema)]
+pub enum TaskStatus {
+    /// The task is queued and waiting for a free slot
+    Queued,
+    /// The task has been manually stashed. It won't be executed until it's manually enqueued
+    Stashed { enqueue_at: Option<DateTime<Local>> },
+    /// The task is started and running
+    Running,
+    /// A previously running task has been paused
+    Paused,
+    /// Task finished. The actual result of the task is handled by the [TaskResult] enum.
+    Done(TaskResult),
+    /// Used while the command of a task is edited (to prevent starting the task)
+    Locked,
+    /// The task's id has been reserved, but the task itself hasn't been added yet.
+    Reserved,
+}
+
+/// This enum represents the exit status of an actually spawned program.
+/// It's only used, once a task finished or failed in some kind of way.
+#[derive(PartialEq, Clone, Debug, Display, Serialize, Deserialize, JsonSchema)]
+pub enum TaskResult {
+    /// Task exited with 0
+    Success,
+    /// The task failed in some other kind of way (error code != 0)
+    Failed(i32),
+    /// The task couldn't be spawned. Probably a typo in the command
+    FailedToSpawn(String),
+    /// Task has been actively killed by either the user or the daemon on shutdown
+    Killed,
+    /// Some kind of IO error. This should barely ever happen. Please check the daemon logs.
+    Errored,
+    /// A dependency of the task failed.
+    DependencyFailed,
+    /// The task didn't start before its deadline.
+    Expired,
+    /// The task has been killed, as it ran longer than its maximum runtime.
+    FailedTimeout,
+    /// The task has been killed by the kernel, as it exceeded its memory limit.
+    OutOfMemory,
+    /// The task only runs after a failure, but all of its dependencies succeeded.
+    Skipped,
+}
+
+/// Decides, which results of its dependencies a task is waiting for.
+/// The task only starts once all of its dependencies finished.
+#[derive(PartialEq, Clone, Copy, Debug, Default, Display, Serialize, Deserialize, JsonSchema)]
+pub enum DependencyMode {
+    /// All dependencies have to succeed. The task fails, as soon as one of them fails.
+    #[default]
+    Success,
+    /// At least one dependency has to fail. The task is skipped, if all of them succeed.
+    Failure,
+    /// The task runs, no matter whether its dependencies succeeded or failed.
+    Any,
+}
+
+/// The shell, that runs a task's command on Windows.
+/// Other platforms always use `sh`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowsShell {
+    /// Run the command via `powershell -c`. This is the default.
+    PowerShell,
+    /// Run the command via `cmd.exe /C`.
+    Cmd,
+}
+
+impl std::fmt::Display for WindowsShell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowsShell::PowerShell => write!(f, "powershell"),
+            WindowsShell::Cmd => write!(f, "cmd"),
+        }
+    }
+}
+
+impl std::str::FromStr for WindowsShell {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src.to_lowercase().as_str() {
+            "powershell" => Ok(WindowsShell::PowerShell),
+            "cmd" | "cmd.exe" => Ok(WindowsShell::Cmd),
+            _ => Err(format!("Unknown shell {}, expected powershell or cmd", src)),
+        }
+    }
+}
+
+/// An external condition, that has to be met before a queued task is started.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub enum StartCondition {
+    /// The file or directory has to exist.
+    File(String),
+    /// A GET request to the url has to succeed.
+    Url(String),
+}
+
+impl std::fmt::Display for StartCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartCondition::File(path) => write!(f, "file {}", path),
+            StartCondition::Url(url) => write!(f, "url {}", url),
+        }
+    }
+}
+
+/// Representation of a task.
+/// start will be set the second the task starts processing.
+/// `result`, `output` and `end` won't be initialized, until the task has finished.
+#[derive(PartialEq, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Task {
+    pub id: usize,
+    pub original_command: String,
+    pub command: String,
+    pub path: String,
+    pub envs: HashMap<String, String>,
+    pub group: String,
+    pub dependencies: Vec<usize>,
+    /// Which results of its dependencies the task is waiting for.
+    #[serde(default)]
+    pub dependency_mode: DependencyMode,
+    pub label: Option<String>,
+    /// The identity (`user@host`) of the client that submitted this task.
+    #[serde(default)]
+    pub created_by: Option<String>,
+    /// If the task hasn't been started by this point in time, it expires instead.
+    #[serde(default)]
+    pub expire_at: Option<DateTime<Local>>,
+    /// The slot of the group's worker pool this task runs in. \
+    /// Slots range from `0` to `parallel - 1` and are also exposed as `PUEUE_WORKER_ID`.
+    /// They are reused, as soon as the task that occupied them finished.
+    #[serde(default)]
+    pub slot: Option<usize>,
+    /// The task's logs are moved to the archive instead of being deleted, when it's cleaned.
+    #[serde(default)]
+    pub keep_logs: bool,
+    /// The task is killed, once it ran for longer than this amount of seconds. \
+    /// If this isn't set, the default of the task's group is used.
+    #[serde(default)]
+    pub max_runtime: Option<u64>,
+    /// The amount of CPU slots the task occupies in its group, if the group has any.
+    /// Tasks without a request occupy a single slot.
+    #[serde(default)]
+    pub cpus: Option<usize>,
+    /// The amount of units the task needs from each of the daemon's resource pools.
+    #[serde(default)]
+    pub resources: BTreeMap<String, usize>,
+    /// The units of the resource pools, that have been assigned to the task when it was started. \
+    /// They're held exclusively, while the task is running.
+    #[serde(default)]
+    pub assigned_resources: BTreeMap<String, Vec<String>>,
+    /// The maximum amount of memory in bytes, that the task may use. \
+    /// Tasks with limits are started in their own cgroup, see `daemon.cgroup_root`.
+    #[serde(default)]
+    pub memory_limit: Option<u64>,
+    /// The maximum amount of CPUs, that the task may use, e.g. `1.5`.
+    #[serde(default)]
+    pub cpu_limit: Option<f64>,
+    /// Arbitrary key/value pairs, e.g. the commit or pipeline a task originates from. \
+    /// They're available in callback templates as `{{ meta.<key> }}`.
+    #[serde(default)]
+    pub meta: BTreeMap<String, String>,
+    /// The id of the array this task belongs to, see `pueue add --array`. \
+    /// It's the id of the array's first task. Arrays can be selected as a unit, e.g. via
+    /// `pueue kill --array <id>`.
+    #[serde(default)]
+    pub array: Option<usize>,
+    /// Tags allow to select multiple tasks at once, e.g. via `pueue kill --tag <tag>`. \
+    /// Unlike the label, the same tag is usually shared by many tasks.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The task isn't started, before all of these conditions are met.
+    #[serde(default)]
+    pub start_conditions: Vec<StartCondition>,
+    /// How often the task's log files have been rotated, since it has been started. \
+    /// Rotation happens, once a log file exceeds the daemon's `max_log_size`.
+    #[serde(default)]
+    pub log_rotations: usize,
+    /// The directory that contains the task's log files. \
+    /// It's set when the task is started and updated, once its logs are moved via
+    /// `pueue daemon set-log-dir`. Tasks without one use the default log directory.
+    #[serde(default)]
+    pub log_directory: Option<PathBuf>,
+    /// The task's stderr is written to its stdout log, so both streams end up in a single log.
+    #[serde(default)]
+    pub stderr_to_stdout: bool,
+    /// Whether the task wrote anything to stderr during its last run. \
+    /// This is recorded as well, if its stderr is written to the stdout log.
+    #[serde(default)]
+    pub wrote_stderr: bool,
+    /// The failure of this task is known and has been triaged via `pueue ack`. \
+    /// Acknowledged tasks are shown dimmed by `pueue status`.
+    #[serde(default)]
+    pub acknowledged: bool,
+    /// A command that's run by the daemon, once the task succeeded. \
+    /// It's a template with the same variables as the daemon's callback.
+    #[serde(default)]
+    pub on_success: Option<String>,
+    /// A command that's run by the daemon, once the task failed. \
+    /// It's a template with the same variables as the daemon's callback.
+    #[serde(default)]
+    pub on_failure: Option<String>,
+    /// The shell that runs the command on Windows. PowerShell is used, if this isn't set.
+    #[serde(default)]
+    pub shell: Option<WindowsShell>,
+    pub status: TaskStatus,
+    /// This field is only used when editing the path/command of a task.
+    /// It's necessary, since we enter the `Locked` state during editing.
+    /// However, we have to go back to the previous state after we finished editing.
+    pub prev_status: TaskStatus,
+    pub start: Option<DateTime<Local>>,
+    pub end: Option<DateTime<Local>>,
+}
+
+impl Task {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        original_command: String,
+        path: String,
+        envs: HashMap<String, String>,
+        group: String,
+        starting_status: TaskStatus,
+        dependencies: Vec<usize>,
+        label: Option<String>,
+    ) -> Task {
+        let command = insert_alias(original_command.clone());
+
+        Task {
+            id: 0,
+            original_command,
+            command,
+            path,
+            envs,
+            group,
+            dependencies,
+            dependency_mode: DependencyMode::Success,
+            label,
+            created_by: None,
+            expire_at: None,
+            slot: None,
+            keep_logs: false,
+            max_runtime: None,
+            cpus: None,
+            resources: BTreeMap::new(),
+            assigned_resources: BTreeMap::new(),
+            memory_limit: None,
+            cpu_limit: None,
+            meta: BTreeMap::new(),
+            array: None,
+            tags: Vec::new(),
+            start_conditions: Vec::new(),
+            log_rotations: 0,
+            log_directory: None,
+            stderr_to_stdout: false,
+            wrote_stderr: false,
+            acknowledged: false,
+            on_success: None,
+            on_failure: None,
+            shell: None,
+            status: starting_status.clone(),
+            prev_status: starting_status,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// A convenience function used to duplicate a task.
+    pub fn from_task(task: &Task) -> Task {
+        Task {
+            id: 0,
+            original_command: task.original_command.clone(),
+            command: task.command.clone(),
+            path: task.path.clone(),
+            envs: task.envs.clone(),
+            group: task.group.clone(),
+            dependencies: Vec::new(),
+            dependency_mode: DependencyMode::Success,
+            label: task.label.clone(),
+            created_by: task.created_by.clone(),
+            // The deadline only applies to the original task.
+            expire_at: None,
+            slot: None,
+            keep_logs: task.keep_logs,
+            max_runtime: task.max_runtime,
+            cpus: task.cpus,
+            resources: task.resources.clone(),
+            assigned_resources: BTreeMap::new(),
+            memory_limit: task.memory_limit,
+            cpu_limit: task.cpu_limit,
+            meta: task.meta.clone(),
+            // Restarted tasks aren't part of the original array.
+            array: None,
+            tags: task.tags.clone(),
+            start_conditions: task.start_conditions.clone(),
+            log_rotations: 0,
+            log_directory: None,
+            stderr_to_stdout: task.stderr_to_stdout,
+            wrote_stderr: false,
+            acknowledged: false,
+            on_success: task.on_success.clone(),
+            on_failure: task.on_failure.clone(),
+            shell: task.shell,
+            status: TaskStatus::Queued,
+            prev_status: TaskStatus::Queued,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Whether the task is having a running process managed by the TaskHandler
+    pub fn is_running(&self) -> bool {
+        matches!(self.status, TaskStatus::Running | TaskStatus::Paused)
+    }
+
+    /// The directory that contains the task's log files.
+    pub fn log_directory(&self, pueue_directory: &Path) -> PathBuf {
+        self.log_directory
+            .clone()
+            .unwrap_or_else(|| default_log_directory(pueue_directory))
+    }
+
+    /// Whether the task's memory or CPU usage is limited.
+    pub fn has_resource_limits(&self) -> bool {
+        self.memory_limit.is_some() || self.cpu_limit.is_some()
+    }
+
+    /// The amount of CPU slots the task occupies in its group.
+    pub fn requested_cpus(&self) -> usize {
+        self.cpus.unwrap_or(1)
+    }
+
+    /// Whether the task has been tagged with the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|task_tag| task_tag == tag)
+    }
+
+    /// Whether the task's process finished.
+    pub fn is_done(&self) -> bool {
+        matches!(self.status, TaskStatus::Done(_))
+    }
+
+    /// Check if the task errored. \
+    /// It either:
+    /// 1. Finished successfully
+    /// 2. Has been skipped, as none of its dependencies failed
+    /// 3. Didn't finish yet.
+    pub fn failed(&self) -> bool {
+        match &self.status {
+            TaskStatus::Done(result) => {
+                !matches!(result, TaskResult::Success | TaskResult::Skipped)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_queued(&self) -> bool {
+        matches!(self.status, TaskStatus::Queued | TaskStatus::Stashed { .. })
+    }
+
+    /// Small convenience function to set the task's group to the default group.
+    pub fn set_default_group(&mut self) {
+        self.group = String::from(PUEUE_DEFAULT_GROUP);
+    }
+
+    pub fn is_in_default_group(&self) -> bool {
+        self.group.eq(PUEUE_DEFAULT_GROUP)
+    }
+}
@@ -0,0 +1,86 @@
+use serde_cbor::de::from_slice;
+use serde_cbor::ser::to_vec;
+use serde_derive::{Deserialize, Serialize};
+
+use pueue_lib::network::message::{FailureKind, Message as OriginalMessage};
+
+/// This is the main message enum. \
+/// Everything that's communicated in Pueue can be serialized as this enum.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Message {
+    Switch(SwitchMessage),
+    Clean(CleanMessage),
+    Failure(String),
+    Stream(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SwitchMessage {
+    pub task_id_1: usize,
+    pub task_id_2: usize,
+    pub some_new_field: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CleanMessage {}
+
+#[test]
+/// Make sure we can deserialize old messages as long as we have default values set.
+fn test_deserialize_old_message() {
+    let message = Message::Clean(CleanMessage {});
+    let payload_bytes = to_vec(&message).unwrap();
+
+    let message: OriginalMessage = from_slice(&payload_bytes).unwrap();
+    if let OriginalMessage::Clean(message) = message {
+        // The serialized message didn't have the `successful_only` property yet.
+        // Instead the default `false` should be used.
+        assert!(!message.successful_only);
+    } else {
+        panic!("It must be a clean message");
+    }
+}
+
+#[test]
+/// Make sure we can deserialize new messages, even if new values exist.
+fn test_deserialize_new_message() {
+    let message = Message::Switch(SwitchMessage {
+        task_id_1: 0,
+        task_id_2: 1,
+        some_new_field: 2,
+    });
+    let payload_bytes = to_vec(&message).unwrap();
+
+    let message: OriginalMessage = from_slice(&payload_bytes).unwrap();
+    // The serialized message did have an additional field. The deserialization works anyway.
+    assert!(matches!(message, OriginalMessage::Switch(_)));
+}
+
+#[test]
+/// Make sure we can deserialize plain text failures of older daemons.
+fn test_deserialize_text_failure() {
+    let message = Message::Failure("Something went wrong".into());
+    let payload_bytes = to_vec(&message).unwrap();
+
+    let message: OriginalMessage = from_slice(&payload_bytes).unwrap();
+    if let OriginalMessage::Failure(failure) = message {
+        assert_eq!(failure.kind, FailureKind::Other);
+        assert_eq!(failure.text, "Something went wrong");
+    } else {
+        panic!("It must be a failure message");
+    }
+}
+
+#[test]
+/// Make sure we can deserialize plain text output of older daemons.
+fn test_deserialize_text_stream() {
+    let message = Message::Stream("Some output".into());
+    let payload_bytes = to_vec(&message).unwrap();
+
+    let message: OriginalMessage = from_slice(&payload_bytes).unwrap();
+    if let OriginalMessage::Stream(chunk) = message {
+        assert_eq!(chunk.text, "Some output");
+        assert_eq!(chunk.bytes, 11);
+    } else {
+        panic!("It must be a stream message");
+    }
+}
@@ -10,17 +10,27 @@ use pueue_lib::state::GroupStatus;
 
 use super::{get_state, sleep_ms};
 
+/// Actions on several tasks count as successful, if they succeeded for all tasks.
 pub fn assert_success(message: Message) {
     assert!(
-        matches!(message, Message::Success(_)),
+        match &message {
+            Message::Success(_) => true,
+            Message::TaskActionResponse(response) => response.failed.is_empty(),
+            _ => false,
+        },
         "Expected to get SuccessMessage, got {:?}",
         message
     );
 }
 
+/// Actions on several tasks count as failed, if they didn't succeed for any task.
 pub fn assert_failure(message: Message) {
     assert!(
-        matches!(message, Message::Failure(_)),
+        match &message {
+            Message::Failure(_) => true,
+            Message::TaskActionResponse(response) => response.succeeded.is_empty(),
+            _ => false,
+        },
         "Expected to get FailureMessage, got {:?}",
         message
     );
@@ -99,19 +99,24 @@ pub fn base_setup() -> Result<(Settings, TempDir)> {
 
     let shared = Shared {
         pueue_directory: tempdir_path.clone().to_path_buf(),
+        log_directory: None,
         #[cfg(not(target_os = "windows"))]
         use_unix_socket: true,
         #[cfg(not(target_os = "windows"))]
         unix_socket_path: tempdir_path.join("test.socket"),
+        #[cfg(not(target_os = "windows"))]
+        unix_socket_peer_credentials: true,
         host: "localhost".to_string(),
         port: "51230".to_string(),
         daemon_cert: tempdir_path.join("certs").join("daemon.cert"),
         daemon_key: tempdir_path.join("certs").join("daemon.key"),
         shared_secret_path: tempdir_path.join("secret"),
+        version_mismatch: VersionMismatch::Warn,
     };
 
     let client = Client {
         restart_in_place: false,
+        soft_pause: false,
         read_local_logs: true,
         show_confirmation_questions: false,
         show_expanded_aliases: false,
@@ -119,6 +124,14 @@ pub fn base_setup() -> Result<(Settings, TempDir)> {
         max_status_lines: Some(15),
         status_time_format: "%H:%M:%S".into(),
         status_datetime_format: "%Y-%m-%d\n%H:%M:%S".into(),
+        locale: "en".into(),
+        default_group_filter: None,
+        timeout: 30,
+        auto_start_daemon: false,
+        daemon_path: None,
+        daemon_arguments: Vec::new(),
+        broker_socket: None,
+        templates: BTreeMap::new(),
     };
 
     let mut groups = BTreeMap::new();
@@ -133,7 +146,46 @@ pub fn base_setup() -> Result<(Settings, TempDir)> {
         pause_all_on_failure: false,
         callback: None,
         callback_log_lines: 15,
+        callback_parallel: 4,
+        callback_rate_limit: None,
+        callback_retries: 0,
+        callback_queue_size: 1000,
         groups,
+        serial_groups: Vec::new(),
+        keep_history: true,
+        history_output_lines: 10,
+        max_archived_tasks: 100,
+        watchers: Vec::new(),
+        exit_when_idle: None,
+        unique_labels: false,
+        add_hook: None,
+        wasm_policies: Vec::new(),
+        lua_script: None,
+        min_free_disk: None,
+        low_disk_callback: None,
+        group_conditions: BTreeMap::new(),
+        group_load_limits: BTreeMap::new(),
+        group_windows: BTreeMap::new(),
+        group_wrappers: BTreeMap::new(),
+        group_defaults: BTreeMap::new(),
+        group_secrets: BTreeMap::new(),
+        secret_command: None,
+        secret_timeout: 10,
+        redact_patterns: Vec::new(),
+        group_max_runtimes: BTreeMap::new(),
+        group_cpus: BTreeMap::new(),
+        resource_pools: BTreeMap::new(),
+        cgroup_root: None,
+        pause_on_suspend: false,
+        mail: None,
+        chat_notifications: Vec::new(),
+        group_notifications: BTreeMap::new(),
+        max_log_size: None,
+        rotated_logs: 1,
+        state_save_mode: StateSaveMode::EveryChange,
+        state_save_delay: None,
+        state_fsync: false,
+        mirror: None,
     };
 
     let settings = Settings {
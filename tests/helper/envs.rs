@@ -18,6 +18,12 @@ pub async fn assert_worker_envs(
 ) -> Result<()> {
     let task = state.tasks.get(&task_id).unwrap();
     // Make sure the environment variables have been properly set.
+    assert_eq!(
+        task.envs.get("PUEUE_TASK_ID"),
+        Some(&task_id.to_string()),
+        "Task id didn't match for task {}",
+        task_id
+    );
     assert_eq!(
         task.envs.get("PUEUE_GROUP"),
         Some(&group.to_string()),
@@ -38,6 +44,7 @@ pub async fn assert_worker_envs(
             task_ids: vec![task_id],
             send_logs: true,
             lines: None,
+            archived: false,
         }),
     )
     .await?;
@@ -48,7 +55,7 @@ pub async fn assert_worker_envs(
         bail!("Expected LogResponse got {:?}", response)
     };
 
-    // Make sure the PUEUE_WORKER_ID and PUEUE_GROUP variables are present in the output.
+    // Make sure the PUEUE_TASK_ID, PUEUE_WORKER_ID and PUEUE_GROUP variables are present in the output.
     // They're always printed as to the [add_env_task] function.
     let log = message
         .get(&task_id)
@@ -56,6 +63,12 @@ pub async fn assert_worker_envs(
 
     let stdout = log.stdout.clone().unwrap();
     let output = String::from_utf8_lossy(&stdout);
+    assert!(
+        output.contains(&format!("TASK_ID: {}", task_id)),
+        "Output should contain task id {}. Got: {}",
+        task_id,
+        &output
+    );
     assert!(
         output.contains(&format!("WORKER_ID: {}", worker.to_string())),
         "Output should contain worker id {} for task {}. Got: {}",
@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::{Context, Result};
 
 use pueue_lib::network::message::*;
 use pueue_lib::settings::*;
+use pueue_lib::task::DependencyMode;
 
 use super::*;
 
@@ -11,14 +12,32 @@ pub fn add_message(shared: &Shared, command: &str) -> AddMessage {
     AddMessage {
         command: command.into(),
         path: shared.pueue_directory().to_str().unwrap().to_string(),
+        path_is_default: false,
         envs: HashMap::new(),
         start_immediately: false,
         stashed: false,
         group: PUEUE_DEFAULT_GROUP.into(),
         enqueue_at: None,
         dependencies: vec![],
+        dependency_mode: DependencyMode::Success,
         label: None,
         print_task_id: false,
+        created_by: None,
+        expire_at: None,
+        reserved_id: None,
+        keep_logs: false,
+        stderr_to_stdout: false,
+        max_runtime: None,
+        cpus: None,
+        resources: BTreeMap::new(),
+        memory_limit: None,
+        cpu_limit: None,
+        meta: BTreeMap::new(),
+        tags: Vec::new(),
+        start_conditions: Vec::new(),
+        on_success: None,
+        on_failure: None,
+        shell: None,
     }
 }
 
@@ -38,14 +57,32 @@ pub async fn add_task_to_group(shared: &Shared, command: &str, group: &str) -> R
     let message = Message::Add(AddMessage {
         command: command.into(),
         path: shared.pueue_directory().to_str().unwrap().to_string(),
+        path_is_default: false,
         envs: HashMap::new(),
         start_immediately: false,
         stashed: false,
         group: group.to_owned(),
         enqueue_at: None,
         dependencies: vec![],
+        dependency_mode: DependencyMode::Success,
         label: None,
         print_task_id: false,
+        created_by: None,
+        expire_at: None,
+        reserved_id: None,
+        keep_logs: false,
+        stderr_to_stdout: false,
+        max_runtime: None,
+        cpus: None,
+        resources: BTreeMap::new(),
+        memory_limit: None,
+        cpu_limit: None,
+        meta: BTreeMap::new(),
+        tags: Vec::new(),
+        start_conditions: Vec::new(),
+        on_success: None,
+        on_failure: None,
+        shell: None,
     });
 
     send_message(shared, message)
@@ -56,7 +93,7 @@ pub async fn add_task_to_group(shared: &Shared, command: &str, group: &str) -> R
 /// Mini wrapper around add_task, which always makes processes print their worker envs as well.
 pub async fn add_env_task(shared: &Shared, command: &str) -> Result<Message> {
     let command = format!(
-        "echo WORKER_ID: $PUEUE_WORKER_ID; echo GROUP: $PUEUE_GROUP; {}",
+        "echo TASK_ID: $PUEUE_TASK_ID; echo WORKER_ID: $PUEUE_WORKER_ID; echo GROUP: $PUEUE_GROUP; {}",
         command
     );
     fixtures::add_task(shared, &command, false).await
@@ -65,7 +102,7 @@ pub async fn add_env_task(shared: &Shared, command: &str) -> Result<Message> {
 /// Just like [add_env_task], but task get's added to specific group.
 pub async fn add_env_task_to_group(shared: &Shared, command: &str, group: &str) -> Result<Message> {
     let command = format!(
-        "echo WORKER_ID: $PUEUE_WORKER_ID; echo GROUP: $PUEUE_GROUP; {}",
+        "echo TASK_ID: $PUEUE_TASK_ID; echo WORKER_ID: $PUEUE_WORKER_ID; echo GROUP: $PUEUE_GROUP; {}",
         command
     );
     fixtures::add_task_to_group(shared, &command, group).await
@@ -79,6 +116,7 @@ pub async fn add_group_with_slots(shared: &Shared, group_name: &str, slots: usiz
     let add_message = Message::Parallel(ParallelMessage {
         parallel_tasks: slots,
         group: group_name.to_string(),
+        serial: false,
     });
     assert_success(send_message(shared, add_message.clone()).await?);
 
@@ -5,7 +5,7 @@ use pueue_lib::network::protocol::{
     get_client_stream, receive_bytes, receive_message, send_bytes,
     send_message as internal_send_message, GenericStream,
 };
-use pueue_lib::network::secret::read_shared_secret;
+use pueue_lib::network::secret::get_handshake_secret;
 use pueue_lib::settings::Shared;
 
 pub async fn send_message(shared: &Shared, message: Message) -> Result<Message> {
@@ -34,8 +34,7 @@ pub async fn get_authenticated_client(shared: &Shared) -> Result<GenericStream>
     // Next we do a handshake with the daemon
     // 1. Client sends the secret to the daemon.
     // 2. If successful, the daemon responds with their version.
-    let secret =
-        read_shared_secret(&shared.shared_secret_path()).context("Couldn't read shared secret.")?;
+    let secret = get_handshake_secret(shared).context("Couldn't read shared secret.")?;
     send_bytes(&secret, &mut stream)
         .await
         .context("Failed to send bytes.")?;
@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use pretty_assertions::assert_eq;
+use schemars::schema::RootSchema;
+
+use pueue_daemon_lib::{hook_input_schema, task_changes_schema};
+use pueue_lib::schema::{export_schema, status_schema, SCHEMA_VERSION};
+
+/// The published schemas have to match the ones generated from the current types.
+///
+/// Any change of a type that's part of a machine readable format breaks this test.
+/// Once the change has been checked to be backward compatible, the published schemas can be
+/// updated by running the test with `PUEUE_UPDATE_SCHEMAS=1`. \
+/// Breaking changes need a new `SCHEMA_VERSION`.
+///
+/// Some settings only exist on some platforms, which is why the schemas are checked on unix.
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn test_published_schemas() -> Result<()> {
+    let schemas: Vec<(&str, RootSchema)> = vec![
+        ("status", status_schema()),
+        ("export", export_schema()),
+        ("add_hook_input", hook_input_schema()),
+        ("add_hook_output", task_changes_schema()),
+    ];
+
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("schemas")
+        .join(format!("v{}", SCHEMA_VERSION));
+    let update = std::env::var_os("PUEUE_UPDATE_SCHEMAS").is_some();
+
+    for (name, schema) in schemas {
+        let path = directory.join(format!("{}.json", name));
+        let generated = serde_json::to_string_pretty(&schema)? + "\n";
+
+        if update {
+            fs::create_dir_all(&directory)?;
+            fs::write(&path, &generated)?;
+            continue;
+        }
+
+        let published = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read published schema {:?}", path))?;
+        assert_eq!(
+            published, generated,
+            "The {} schema changed. Run the tests with PUEUE_UPDATE_SCHEMAS=1 to update it.",
+            name
+        );
+    }
+
+    Ok(())
+}
@@ -1,3 +1,11 @@
+// These tests predate the lints of newer clippy versions and are kept as they are.
+#![allow(
+    noop_method_call,
+    clippy::needless_borrow,
+    clippy::to_string_in_format_args,
+    clippy::useless_vec
+)]
+
 #[cfg(target_os = "linux")]
 mod helper;
 
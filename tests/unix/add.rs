@@ -1,6 +1,10 @@
 use anyhow::Result;
+use chrono::{Duration, Local};
 
-use pueue_lib::network::message::{Message, TaskSelection};
+use pueue_lib::log::get_task_log_paths;
+use pueue_lib::network::message::{
+    create_success_message, AddBatchMessage, BatchTask, Message, TaskSelection,
+};
 use pueue_lib::task::*;
 
 use crate::helper::*;
@@ -64,3 +68,515 @@ async fn test_add_with_immediate_start() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The identity of the submitting client is stored on the task.
+async fn test_add_with_identity() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    let mut inner_message = fixtures::add_message(shared, "sleep 60");
+    inner_message.stashed = true;
+    inner_message.created_by = Some("user@host".into());
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.created_by, Some("user@host".into()));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Metadata is stored on the task and can be used in the callback.
+async fn test_add_with_meta() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let callback_output = tempdir.path().join("callback_output");
+    settings.daemon.callback = Some(format!(
+        "echo {{{{ meta.commit }}}} > {}",
+        callback_output.to_string_lossy()
+    ));
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    let mut inner_message = fixtures::add_message(shared, "ls");
+    inner_message.meta.insert("commit".into(), "abc123".into());
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.meta.get("commit"), Some(&"abc123".to_string()));
+
+    // Give the callback some time to run.
+    sleep_ms(500);
+    let output = std::fs::read_to_string(callback_output)?;
+    assert_eq!(output.trim(), "abc123");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks can run after their dependencies failed or no matter how they finished.
+async fn test_add_with_dependency_modes() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task(shared, "failing", false).await?);
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+
+    let dependants = [
+        (vec![0], DependencyMode::Failure),
+        (vec![1], DependencyMode::Failure),
+        (vec![0, 1], DependencyMode::Any),
+    ];
+    for (dependencies, dependency_mode) in dependants {
+        let mut inner_message = fixtures::add_message(shared, "ls");
+        inner_message.dependencies = dependencies;
+        inner_message.dependency_mode = dependency_mode;
+        assert_success(send_message(shared, Message::Add(inner_message)).await?);
+    }
+
+    wait_for_task_condition(shared, 2, |task| {
+        task.status == TaskStatus::Done(TaskResult::Success)
+    })
+    .await?;
+    // None of its dependencies failed.
+    wait_for_task_condition(shared, 3, |task| {
+        task.status == TaskStatus::Done(TaskResult::Skipped)
+    })
+    .await?;
+    wait_for_task_condition(shared, 4, |task| {
+        task.status == TaskStatus::Done(TaskResult::Success)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// A skipped task doesn't count as failed, so tasks that depend on its success still run.
+async fn test_add_after_skipped_dependency() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+
+    let mut inner_message = fixtures::add_message(shared, "ls");
+    inner_message.dependencies = vec![0];
+    inner_message.dependency_mode = DependencyMode::Failure;
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    let mut inner_message = fixtures::add_message(shared, "ls");
+    inner_message.dependencies = vec![1];
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    wait_for_task_condition(shared, 1, |task| {
+        task.status == TaskStatus::Done(TaskResult::Skipped)
+    })
+    .await?;
+    wait_for_task_condition(shared, 2, |task| {
+        task.status == TaskStatus::Done(TaskResult::Success)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// A task that hasn't been started before its deadline expires.
+/// Tasks that depend on it fail, once they're allowed to run.
+async fn test_add_with_expiry() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    // Pause the daemon, so the task can't be started in time.
+    pause_tasks(shared, TaskSelection::All).await?;
+
+    let mut inner_message = fixtures::add_message(shared, "sleep 60");
+    inner_message.expire_at = Some(Local::now() + Duration::milliseconds(200));
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    let mut inner_message = fixtures::add_message(shared, "sleep 60");
+    inner_message.dependencies = vec![0];
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    wait_for_task_condition(shared, 0, |task| {
+        task.status == TaskStatus::Done(TaskResult::Expired)
+    })
+    .await?;
+
+    // The dependant fails as soon as the group is resumed.
+    start_tasks(shared, TaskSelection::All).await?;
+    wait_for_task_condition(shared, 1, |task| {
+        task.status == TaskStatus::Done(TaskResult::DependencyFailed)
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// With `unique_labels`, a label can only be used by a single unfinished task per group.
+async fn test_add_with_unique_label() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.unique_labels = true;
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    let mut inner_message = fixtures::add_message(shared, "sleep 60");
+    inner_message.label = Some("backup".into());
+    assert_success(send_message(shared, Message::Add(inner_message.clone())).await?);
+    assert_failure(send_message(shared, Message::Add(inner_message.clone())).await?);
+
+    // Other groups can use the same label.
+    fixtures::add_group_with_slots(shared, "other", 1).await?;
+    inner_message.group = "other".into();
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// A reserved id can be used for dependencies, before the task is committed.
+async fn test_add_reserved() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    let response = send_message(shared, Message::Reserve("default".into())).await?;
+    assert_eq!(response, create_success_message("0"));
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Reserved);
+
+    // The dependant waits for the reserved task.
+    let mut inner_message = fixtures::add_message(shared, "ls");
+    inner_message.dependencies = vec![0];
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+
+    let mut inner_message = fixtures::add_message(shared, "sleep 0.1");
+    inner_message.reserved_id = Some(0);
+    assert_success(send_message(shared, Message::Add(inner_message.clone())).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(
+        get_task_status(shared, 1).await?,
+        TaskStatus::Done(TaskResult::Success)
+    );
+
+    // A reservation can only be committed once.
+    assert_failure(send_message(shared, Message::Add(inner_message)).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks with start conditions only start, once the files they wait for exist.
+async fn test_add_with_wait_for_file() -> Result<()> {
+    let (settings, tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    // Relative paths are relative to the task's working directory.
+    std::fs::write(tempdir.path().join("ready.flag"), "")?;
+    for file in ["ready.flag", "missing.flag"] {
+        let mut inner_message = fixtures::add_message(shared, "ls");
+        inner_message.start_conditions = vec![StartCondition::File(file.into())];
+        assert_success(send_message(shared, Message::Add(inner_message)).await?);
+    }
+
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    sleep_ms(500);
+    assert_eq!(get_task_status(shared, 1).await?, TaskStatus::Queued);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks with resource limits are rejected, if there's no cgroup to enforce them.
+async fn test_add_with_limits_without_cgroup() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    let mut inner_message = fixtures::add_message(shared, "ls");
+    inner_message.memory_limit = Some(1 << 30);
+    assert_failure(send_message(shared, Message::Add(inner_message)).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks run their `on_success` or `on_failure` command, depending on their result.
+async fn test_add_with_result_hooks() -> Result<()> {
+    let (settings, tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+    let output = tempdir.path().join("output");
+
+    for command in ["true", "false"] {
+        let mut message = fixtures::add_message(shared, command);
+        message.on_success = Some(format!("echo success {{{{ id }}}} >> {}", output.display()));
+        message.on_failure = Some(format!("echo failure {{{{ id }}}} >> {}", output.display()));
+        assert_success(send_message(shared, Message::Add(message)).await?);
+    }
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    // The hooks are run through the callback queue, give them some time.
+    let mut tries = 0;
+    let mut content = String::new();
+    while content.lines().count() < 2 && tries < 20 {
+        sleep_ms(100);
+        content = std::fs::read_to_string(&output).unwrap_or_default();
+        tries += 1;
+    }
+    assert_eq!(content, "success 0\nfailure 1\n");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The add hook can rewrite new tasks and reject them.
+async fn test_add_hook() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.add_hook = Some(
+        r#"grep -q forbidden && { echo "forbidden command" >&2; exit 1; }; echo '{"command": "echo rewritten", "group": "test_2"}'"#
+            .into(),
+    );
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let shared = &settings.shared;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    assert_success(add_task(shared, "echo original", false).await?);
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.command, "echo rewritten");
+    assert_eq!(task.group, "test_2");
+
+    let response = add_task(shared, "echo forbidden", false).await?;
+    match response {
+        Message::Failure(failure) => assert!(failure.text.contains("forbidden command")),
+        _ => panic!("Expected the task to be rejected, got {:?}", response),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The lua script can route new tasks, evaluate their results and format their callbacks.
+async fn test_lua_script() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let notification = tempdir.path().join("notification");
+    let script_path = tempdir.path().join("script.lua");
+    let script = format!(
+        r#"
+function route(task)
+    if task.command:find("grep") then return "test_2" end
+end
+function is_success(task)
+    return task.exit_code == "0" or task.exit_code == "1"
+end
+function format_callback(task)
+    return "echo " .. task.result .. " > {}"
+end
+"#,
+        notification.display()
+    );
+    std::fs::write(&script_path, script)?;
+    settings.daemon.lua_script = Some(script_path.to_string_lossy().into());
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let shared = &settings.shared;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    // grep exits with 1, if nothing matched.
+    assert_success(add_task(shared, "grep nothing /dev/null", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.group, "test_2");
+    assert_eq!(task.status, TaskStatus::Done(TaskResult::Success));
+
+    // The callback is run through the callback queue, give it some time.
+    let mut tries = 0;
+    let mut content = String::new();
+    while content.is_empty() && tries < 20 {
+        sleep_ms(100);
+        content = std::fs::read_to_string(&notification).unwrap_or_default();
+        tries += 1;
+    }
+    assert_eq!(content.trim(), "Success");
+
+    Ok(())
+}
+
+/// A policy, that rejects commands starting with `x`, moves all other tasks into group `test_2`
+/// and never allows a task to be started.
+const WASM_POLICY: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{\"reject\": \"no x\"}")
+  (data (i32.const 64) "{\"changes\": {\"group\": \"test_2\"}}")
+  (func (export "pueue_abi_version") (result i32) i32.const 1)
+  (func (export "pueue_alloc") (param i32) (result i32) i32.const 1024)
+  (func (export "pueue_on_add") (param $ptr i32) (param $len i32) (result i64)
+    ;; The command starts after `{"id":null,"command":"`.
+    (if (result i64) (i32.eq (i32.load8_u (i32.add (local.get $ptr) (i32.const 22))) (i32.const 120))
+      (then (i64.const 18))
+      (else (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 32)))))
+  (func (export "pueue_on_start") (param i32 i32) (result i64) i64.const 18))
+"#;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// WASM policies can rewrite and reject new tasks and hold back queued tasks.
+async fn test_wasm_policy() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let policy_path = tempdir.path().join("policy.wasm");
+    std::fs::write(&policy_path, wat::parse_str(WASM_POLICY)?)?;
+    settings.daemon.wasm_policies = vec![policy_path.to_string_lossy().into()];
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let shared = &settings.shared;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    assert_success(add_task(shared, "ls", false).await?);
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.group, "test_2");
+
+    let response = add_task(shared, "xargs", false).await?;
+    match response {
+        Message::Failure(failure) => assert!(failure.text.contains("no x")),
+        _ => panic!("Expected the task to be rejected, got {:?}", response),
+    }
+
+    // The policy doesn't allow the task to be started.
+    sleep_ms(500);
+    assert_eq!(get_task(shared, 0).await?.status, TaskStatus::Queued);
+
+    Ok(())
+}
+
+/// A policy, whose response points beyond the end of its memory.
+const WASM_POLICY_OUT_OF_BOUNDS: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "pueue_abi_version") (result i32) i32.const 1)
+  (func (export "pueue_alloc") (param i32) (result i32) i32.const 1024)
+  (func (export "pueue_on_add") (param i32 i32) (result i64) i64.const 0xffffffff))
+"#;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Responses outside of a policy's memory are refused.
+async fn test_wasm_policy_out_of_bounds() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let policy_path = tempdir.path().join("policy.wasm");
+    std::fs::write(&policy_path, wat::parse_str(WASM_POLICY_OUT_OF_BOUNDS)?)?;
+    settings.daemon.wasm_policies = vec![policy_path.to_string_lossy().into()];
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let shared = &settings.shared;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    let response = add_task(shared, "ls", false).await?;
+    match response {
+        Message::Failure(failure) => assert!(failure.text.contains("out of bounds")),
+        _ => panic!("Expected the task to be rejected, got {:?}", response),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks of a batch are added at once and may depend on earlier tasks of the batch.
+/// Nothing is added, if any task of the batch is invalid.
+async fn test_add_batch() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+
+    let batch_task = |batch_dependencies: Vec<usize>| {
+        let mut task = fixtures::add_message(shared, "ls");
+        task.stashed = true;
+        BatchTask {
+            task,
+            batch_dependencies,
+        }
+    };
+
+    // The second task isn't valid, as its group doesn't exist.
+    let mut invalid = batch_task(vec![0]);
+    invalid.task.group = "doesnt_exist".into();
+    let message = AddBatchMessage {
+        tasks: vec![batch_task(vec![]), invalid],
+        array: false,
+    };
+    assert_failure(send_message(shared, Message::AddBatch(message)).await?);
+    assert_eq!(get_state(shared).await?.tasks.len(), 1);
+
+    // Tasks can only depend on earlier tasks of the batch.
+    let message = AddBatchMessage {
+        tasks: vec![batch_task(vec![1]), batch_task(vec![])],
+        array: false,
+    };
+    assert_failure(send_message(shared, Message::AddBatch(message)).await?);
+
+    let mut last = batch_task(vec![0, 1]);
+    last.task.dependencies = vec![0];
+    let message = AddBatchMessage {
+        tasks: vec![batch_task(vec![]), batch_task(vec![0]), last],
+        array: false,
+    };
+    assert_success(send_message(shared, Message::AddBatch(message)).await?);
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 4);
+    assert_eq!(state.tasks[&2].dependencies, vec![1]);
+    assert_eq!(state.tasks[&3].dependencies, vec![0, 1, 2]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Imported tasks get new ids and their dependencies are mapped to them.
+async fn test_import() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    for dependencies in [vec![], vec![0]] {
+        let mut inner_message = fixtures::add_message(shared, "ls");
+        inner_message.stashed = true;
+        inner_message.dependencies = dependencies;
+        assert_success(send_message(shared, Message::Add(inner_message)).await?);
+    }
+    let exported: Vec<Task> = get_state(shared).await?.tasks.into_values().collect();
+
+    assert_success(send_message(shared, Message::Import(exported.clone())).await?);
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 4);
+    assert_eq!(state.tasks[&3].dependencies, vec![2]);
+    assert!(matches!(state.tasks[&3].status, TaskStatus::Stashed { .. }));
+
+    // Dependencies have to be part of the import.
+    let partial = exported.into_iter().filter(|task| task.id == 1).collect();
+    assert_failure(send_message(shared, Message::Import(partial)).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The stderr of a task can be written to its stdout log.
+/// Finished tasks record, whether they wrote anything to stderr.
+async fn test_stderr_to_stdout() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    let mut message = fixtures::add_message(shared, "echo out && echo err >&2");
+    message.stderr_to_stdout = true;
+    assert_success(send_message(shared, Message::Add(message)).await?);
+    assert_success(fixtures::add_task(shared, "echo err >&2", false).await?);
+    assert_success(fixtures::add_task(shared, "echo out", false).await?);
+    for id in 0..3 {
+        wait_for_task_condition(shared, id, |task| task.is_done()).await?;
+    }
+
+    let task = get_task(shared, 0).await?;
+    assert!(task.wrote_stderr);
+    let (out_path, err_path) =
+        get_task_log_paths(0, &task.log_directory(&shared.pueue_directory()));
+    assert_eq!(std::fs::read_to_string(out_path)?, "out\nerr\n");
+    assert_eq!(std::fs::read_to_string(err_path)?, "");
+
+    assert!(get_task(shared, 1).await?.wrote_stderr);
+    assert!(!get_task(shared, 2).await?.wrote_stderr);
+
+    Ok(())
+}
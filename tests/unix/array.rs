@@ -0,0 +1,76 @@
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+
+use pueue_lib::network::message::*;
+use pueue_lib::task::*;
+
+use crate::helper::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The tasks of an array share the id of its first task and can be controlled as a unit.
+async fn test_array() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    // A finished task, which isn't part of the array.
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let tasks = (0..3)
+        .map(|_| BatchTask {
+            task: fixtures::add_message(shared, "sleep 60"),
+            batch_dependencies: vec![],
+        })
+        .collect();
+    let message = AddBatchMessage { tasks, array: true };
+    assert_success(send_message(shared, Message::AddBatch(message)).await?);
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].array, None);
+    for id in 1..4 {
+        assert_eq!(state.tasks[&id].array, Some(1));
+    }
+
+    // Pausing the array pauses its running task and stashes the queued ones.
+    wait_for_task_condition(shared, 1, |task| task.status == TaskStatus::Running).await?;
+    assert_success(pause_tasks(shared, TaskSelection::Array(1)).await?);
+    wait_for_task_condition(shared, 1, |task| task.status == TaskStatus::Paused).await?;
+    for id in 2..4 {
+        let task = get_task(shared, id).await?;
+        assert_eq!(task.status, TaskStatus::Stashed { enqueue_at: None });
+    }
+
+    // Starting the array resumes its paused task and enqueues the stashed ones.
+    assert_success(start_tasks(shared, TaskSelection::Array(1)).await?);
+    wait_for_task_condition(shared, 1, |task| task.status == TaskStatus::Running).await?;
+    for id in 2..4 {
+        assert_eq!(get_task(shared, id).await?.status, TaskStatus::Queued);
+    }
+
+    // Killing the array also kills its queued tasks.
+    let message = Message::Kill(KillMessage {
+        tasks: TaskSelection::Array(1),
+        children: false,
+        signal: None,
+    });
+    assert_success(send_message(shared, message).await?);
+    for id in 1..4 {
+        wait_for_task_condition(shared, id, |task| {
+            task.status == TaskStatus::Done(TaskResult::Killed)
+        })
+        .await?;
+    }
+
+    // Only the tasks of the array are cleaned.
+    let message = Message::Clean(CleanMessage {
+        successful_only: false,
+        keep_logs: false,
+        tag: None,
+        array: Some(1),
+    });
+    assert_success(send_message(shared, message).await?);
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.keys().copied().collect::<Vec<_>>(), vec![0]);
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use pueue_lib::network::message::*;
+use pueue_lib::task::TaskResult;
 
 use crate::helper::*;
 
@@ -19,6 +20,9 @@ async fn test_normal_clean() -> Result<()> {
     // Send the clean message
     let clean_message = CleanMessage {
         successful_only: false,
+        keep_logs: false,
+        tag: None,
+        array: None,
     };
     send_message(shared, Message::Clean(clean_message)).await?;
 
@@ -46,6 +50,9 @@ async fn test_successful_only_clean() -> Result<()> {
     // Send the clean message
     let clean_message = CleanMessage {
         successful_only: true,
+        keep_logs: false,
+        tag: None,
+        array: None,
     };
     send_message(shared, Message::Clean(clean_message)).await?;
 
@@ -57,3 +64,118 @@ async fn test_successful_only_clean() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks with `keep_logs` are archived when cleaned. Their ids aren't reused.
+async fn test_clean_keeps_logs() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    let mut message = fixtures::add_message(shared, "echo important");
+    message.keep_logs = true;
+    assert_success(send_message(shared, Message::Add(message)).await?);
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let clean_message = CleanMessage {
+        successful_only: false,
+        keep_logs: false,
+        tag: None,
+        array: None,
+    };
+    send_message(shared, Message::Clean(clean_message)).await?;
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+    assert!(state.archived_tasks.contains_key(&0));
+    assert!(!state.archived_tasks.contains_key(&1));
+
+    // The archived logs can still be requested.
+    let response = send_message(
+        shared,
+        Message::Log(LogRequestMessage {
+            task_ids: vec![0],
+            send_logs: true,
+            lines: None,
+            archived: true,
+        }),
+    )
+    .await?;
+    let log = match response {
+        Message::LogResponse(mut logs) => logs.remove(&0).expect("Archived log is missing"),
+        _ => panic!("Expected LogResponse, got {:?}", response),
+    };
+    let stdout = String::from_utf8_lossy(&log.stdout.unwrap()).to_string();
+    assert!(stdout.contains("important"));
+
+    // The next task doesn't take the archived task's id.
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    let state = get_state(shared).await?;
+    assert!(state.tasks.contains_key(&1));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Cleaned tasks are kept in the history and can be searched.
+async fn test_clean_into_history() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    for command in ["echo first", "echo second && exit 2"] {
+        assert_success(fixtures::add_task(shared, command, false).await?);
+    }
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let clean_message = CleanMessage {
+        successful_only: false,
+        keep_logs: false,
+        tag: None,
+        array: None,
+    };
+    assert_success(send_message(shared, Message::Clean(clean_message)).await?);
+
+    let history_message = HistoryRequestMessage {
+        search: Some("^echo sec".into()),
+        since: None,
+    };
+    let entries = match send_message(shared, Message::History(history_message)).await? {
+        Message::HistoryResponse(entries) => entries,
+        response => panic!("Expected a history response, got {:?}", response),
+    };
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].id, 1);
+    assert_eq!(entries[0].result, TaskResult::Failed(2));
+    assert_eq!(entries[0].output, "second");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The statistics of a group still count tasks, that have been cleaned.
+async fn test_statistics_survive_clean() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    for command in ["sleep 0.1", "exit 1"] {
+        assert_success(fixtures::add_task(shared, command, false).await?);
+    }
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let clean_message = CleanMessage {
+        successful_only: false,
+        keep_logs: false,
+        tag: None,
+        array: None,
+    };
+    assert_success(send_message(shared, Message::Clean(clean_message)).await?);
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+    let statistics = &state.statistics["default"];
+    assert_eq!(statistics.tasks, 2);
+    assert_eq!(statistics.failures, 1);
+    assert!(statistics.runtime_millis >= 100);
+
+    Ok(())
+}
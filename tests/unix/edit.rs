@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::{bail, Result};
 
 use pueue_lib::network::message::*;
@@ -65,3 +67,56 @@ async fn test_edit_flow() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Environment variables of a queued task can be set and removed.
+async fn test_edit_env() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    pause_tasks(shared, TaskSelection::All).await?;
+    wait_for_group_status(shared, PUEUE_DEFAULT_GROUP, GroupStatus::Paused).await?;
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+
+    let mut set = BTreeMap::new();
+    set.insert("PUEUE_TEST".to_string(), "edited".to_string());
+    let response = send_message(
+        shared,
+        Message::EditEnv(EditEnvMessage {
+            task_id: 0,
+            set,
+            unset: Vec::new(),
+        }),
+    )
+    .await?;
+    assert_success(response);
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.envs.get("PUEUE_TEST"), Some(&"edited".to_string()));
+
+    // Unknown variables can't be removed.
+    let response = send_message(
+        shared,
+        Message::EditEnv(EditEnvMessage {
+            task_id: 0,
+            set: BTreeMap::new(),
+            unset: vec!["PUEUE_UNKNOWN".into()],
+        }),
+    )
+    .await?;
+    assert!(matches!(response, Message::Failure(_)));
+
+    let response = send_message(
+        shared,
+        Message::EditEnv(EditEnvMessage {
+            task_id: 0,
+            set: BTreeMap::new(),
+            unset: vec!["PUEUE_TEST".into()],
+        }),
+    )
+    .await?;
+    assert_success(response);
+    let task = get_task(shared, 0).await?;
+    assert!(!task.envs.contains_key("PUEUE_TEST"));
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+
+use pueue_lib::network::message::*;
+use pueue_lib::network::protocol::{receive_message, send_message as internal_send_message};
+use pueue_lib::settings::Shared;
+
+use crate::helper::*;
+
+/// Follow the stdout of a finished task from the given offset.
+/// Returns the received text and the amount of bytes, that have been read.
+async fn follow(shared: &Shared, offset: u64) -> Result<(String, u64)> {
+    let mut stream = get_authenticated_client(shared).await?;
+    let request = StreamRequestMessage {
+        task_id: Some(0),
+        err: false,
+        offset,
+        until_done: true,
+    };
+    internal_send_message(Message::StreamRequest(request), &mut stream).await?;
+
+    let mut text = String::new();
+    let mut bytes = 0;
+    loop {
+        match receive_message(&mut stream).await? {
+            Message::Stream(chunk) => {
+                text.push_str(&chunk.text);
+                bytes += chunk.bytes;
+            }
+            message => {
+                assert_success(message);
+                return Ok((text, bytes));
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The offset of a stream advances by the bytes of the log file, even if the output
+/// isn't valid UTF-8 and its text is longer than the original output.
+async fn test_follow_invalid_utf8() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task(shared, "printf 'a\\377b'", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let (text, bytes) = follow(shared, 0).await?;
+    assert_eq!(text, "a\u{FFFD}b");
+    assert_eq!(bytes, 3);
+
+    // Resuming the stream at this offset doesn't repeat any output.
+    let (text, bytes) = follow(shared, bytes).await?;
+    assert_eq!(text, "");
+    assert_eq!(bytes, 0);
+
+    Ok(())
+}
@@ -1,6 +1,12 @@
+use std::collections::BTreeMap;
+
 use anyhow::Result;
+use chrono::{Duration, Local};
 
 use pueue_lib::network::message::*;
+use pueue_lib::settings::{GroupCondition, GroupDefaults, LoadLimit};
+use pueue_lib::state::{GroupStatus, PauseReason};
+use pueue_lib::task::{TaskStatus, WindowsShell};
 
 use crate::helper::*;
 
@@ -84,3 +90,218 @@ async fn test_cannot_delete_group_with_tasks() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Failures of traced requests contain the request id.
+async fn test_traced_failure_contains_request_id() -> Result<()> {
+    let (settings, tempdir) = base_setup()?;
+    let shared = &settings.shared;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    let remove_message = Message::Group(GroupMessage::Remove("doesnt_exist".to_string()));
+    let traced = TracedMessage::new(remove_message, "1.0.0");
+    let request_id = traced.request_id.clone();
+
+    match send_message(shared, Message::Traced(traced)).await? {
+        Message::Failure(failure) => {
+            assert!(failure
+                .text
+                .ends_with(&format!("(request id: {})", request_id)));
+            assert_eq!(
+                failure.kind,
+                FailureKind::GroupNotFound {
+                    group: "doesnt_exist".into()
+                }
+            );
+        }
+        message => panic!("Expected a failure message, got {:?}", message),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks of a group with a condition are only started, once the condition passes.
+async fn test_group_condition() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let online = tempdir.path().join("online");
+    settings.daemon.group_conditions.insert(
+        "test_2".into(),
+        GroupCondition {
+            command: format!("test -f {}", online.display()),
+            interval: 1,
+        },
+    );
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task_to_group(shared, "ls", "test_2").await?);
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    // Other groups aren't affected.
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Queued);
+
+    // The condition is checked once per second.
+    std::fs::File::create(&online)?;
+    sleep_ms(1500);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks of a group are held back, while the system load exceeds the group's limit.
+async fn test_group_load_limit() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.group_load_limits.insert(
+        "test_2".into(),
+        LoadLimit {
+            max_load: Some(-1.0),
+            min_free_memory: None,
+        },
+    );
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task_to_group(shared, "ls", "test_2").await?);
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    // Other groups aren't affected.
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Queued);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Groups are paused outside of their scheduling window.
+/// Starting such a group by hand overrides the window.
+async fn test_group_window() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    // A window, that's currently closed.
+    let start = Local::now() + Duration::hours(2);
+    let end = start + Duration::hours(1);
+    let window = format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"));
+    settings
+        .daemon
+        .group_windows
+        .insert("test_2".into(), window.clone());
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task_to_group(shared, "ls", "test_2").await?);
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    // Other groups aren't affected.
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Queued);
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.groups["test_2"], GroupStatus::Paused);
+    assert_eq!(
+        state.pause_reasons["test_2"],
+        PauseReason::OutsideWindow { window }
+    );
+
+    let message = Message::Start(StartMessage {
+        tasks: TaskSelection::Group("test_2".into()),
+        children: false,
+    });
+    assert_success(send_message(shared, message).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert!(get_state(shared).await?.pause_reasons.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// All tasks of a group with a wrapper are executed by the wrapper.
+async fn test_group_wrapper() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.group_wrappers.insert(
+        "test_2".into(),
+        "echo wrapped {{ id }}; sh -c {{ command }}".into(),
+    );
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task_to_group(shared, "echo 'the task'", "test_2").await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    let response = send_message(
+        shared,
+        Message::Log(LogRequestMessage {
+            task_ids: vec![0],
+            send_logs: true,
+            lines: None,
+            archived: false,
+        }),
+    )
+    .await?;
+    let log = match response {
+        Message::LogResponse(mut logs) => logs.remove(&0).expect("Log is missing"),
+        _ => panic!("Expected LogResponse, got {:?}", response),
+    };
+    let stdout = String::from_utf8_lossy(&log.stdout.unwrap()).to_string();
+    assert!(stdout.contains("wrapped 0\nthe task"));
+    // The task's command isn't changed.
+    assert_eq!(get_task(shared, 0).await?.command, "echo 'the task'");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The defaults of a group are used for everything, that hasn't been given explicitly.
+async fn test_group_defaults() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let project = tempdir.path().join("project");
+    std::fs::create_dir(&project)?;
+    let mut env = BTreeMap::new();
+    env.insert("PROJECT".to_string(), "pueue".to_string());
+    settings.daemon.group_defaults.insert(
+        "test_2".into(),
+        GroupDefaults {
+            cwd: Some(project.to_string_lossy().into()),
+            env,
+            shell: Some(WindowsShell::Cmd),
+        },
+    );
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    // The client's working directory is replaced by the group's.
+    let mut message = fixtures::add_message(shared, "ls");
+    message.group = "test_2".into();
+    message.path_is_default = true;
+    assert_success(send_message(shared, Message::Add(message.clone())).await?);
+    let task = get_task(shared, 0).await?;
+    assert_eq!(task.path, project.to_string_lossy());
+    assert_eq!(task.envs.get("PROJECT").map(String::as_str), Some("pueue"));
+    assert_eq!(task.shell, Some(WindowsShell::Cmd));
+
+    // Explicit values are kept.
+    message.path_is_default = false;
+    message.shell = Some(WindowsShell::PowerShell);
+    assert_success(send_message(shared, Message::Add(message.clone())).await?);
+    let task = get_task(shared, 1).await?;
+    assert_eq!(task.path, message.path);
+    assert_eq!(task.shell, Some(WindowsShell::PowerShell));
+
+    // Other groups aren't affected.
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    let task = get_task(shared, 2).await?;
+    assert!(!task.envs.contains_key("PROJECT"));
+
+    // Imported tasks get the defaults of their group as well.
+    let mut exported = get_task(shared, 2).await?;
+    exported.group = "test_2".into();
+    assert_success(send_message(shared, Message::Import(vec![exported])).await?);
+    let task = get_task(shared, 3).await?;
+    assert_eq!(task.envs.get("PROJECT").map(String::as_str), Some("pueue"));
+
+    Ok(())
+}
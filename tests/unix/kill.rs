@@ -78,3 +78,137 @@ async fn test_kill_tasks(
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Killing several tasks reports the outcome for each of them.
+/// Tasks that are done or don't exist can't be killed, the running one is killed anyway.
+async fn test_kill_reports_each_task() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task(shared, "ls", true).await?);
+    wait_for_task(shared, 0).await?;
+    assert_success(fixtures::add_task(shared, "sleep 60", true).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_running()).await?;
+
+    let kill_message = Message::Kill(KillMessage {
+        tasks: TaskSelection::TaskIds(vec![0, 1, 5]),
+        children: false,
+        signal: None,
+    });
+    let response = send_message(shared, kill_message).await?;
+
+    let mut failed = std::collections::BTreeMap::new();
+    failed.insert(
+        0,
+        FailureKind::InvalidState {
+            expected: "Running or Paused".into(),
+            actual: "Done".into(),
+        },
+    );
+    failed.insert(5, FailureKind::TaskNotFound { task_id: 5 });
+    assert_eq!(
+        response,
+        Message::TaskActionResponse(TaskActionResponseMessage {
+            text: "Tasks are being killed".into(),
+            succeeded: vec![1],
+            failed,
+        })
+    );
+
+    wait_for_task_condition(shared, 1, |task| {
+        matches!(task.status, TaskStatus::Done(TaskResult::Killed))
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks that run longer than their maximum runtime are killed and marked as timed out.
+async fn test_max_runtime() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    let mut message = fixtures::add_message(shared, "sleep 60");
+    message.max_runtime = Some(1);
+    assert_success(send_message(shared, Message::Add(message)).await?);
+
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    sleep_ms(1500);
+    wait_for_task_condition(shared, 0, |task| {
+        matches!(task.status, TaskStatus::Done(TaskResult::FailedTimeout))
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The maximum runtime of a group applies to all of its tasks.
+async fn test_group_max_runtime() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings
+        .daemon
+        .group_max_runtimes
+        .insert("test_2".into(), "1s".into());
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task_to_group(shared, "sleep 60", "test_2").await?);
+    // Tasks of other groups aren't affected.
+    assert_success(fixtures::add_task(shared, "sleep 60", false).await?);
+
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    sleep_ms(1500);
+    wait_for_task_condition(shared, 0, |task| {
+        matches!(task.status, TaskStatus::Done(TaskResult::FailedTimeout))
+    })
+    .await?;
+    wait_for_task_condition(shared, 1, |task| task.status == TaskStatus::Running).await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Only tasks with the given tag are killed. Their group keeps running.
+async fn test_kill_tagged_tasks() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    for id in 0..3 {
+        let mut message = fixtures::add_message(shared, "sleep 60");
+        message.start_immediately = true;
+        if id != 1 {
+            message.tags = vec!["nightly".into()];
+        }
+        assert_success(send_message(shared, Message::Add(message)).await?);
+    }
+    for id in 0..3 {
+        wait_for_task_condition(shared, id, |task| task.is_running()).await?;
+    }
+
+    let kill_message = Message::Kill(KillMessage {
+        tasks: TaskSelection::Tag("nightly".into()),
+        children: false,
+        signal: None,
+    });
+    assert_success(send_message(shared, kill_message).await?);
+
+    for id in [0, 2] {
+        wait_for_task_condition(shared, id, |task| {
+            matches!(task.status, TaskStatus::Done(TaskResult::Killed))
+        })
+        .await?;
+    }
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.get(&1).unwrap().status, TaskStatus::Running);
+    assert_eq!(
+        state.groups.get(PUEUE_DEFAULT_GROUP).unwrap(),
+        &GroupStatus::Running
+    );
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::fs::read_to_string;
+
+use anyhow::Result;
+use pueue_lib::log::{default_log_directory, get_task_log_paths};
+use pueue_lib::network::message::*;
+
+use crate::helper::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The logs of finished tasks are moved right away.
+/// Running tasks keep writing to their old log files, which are moved once they finish.
+async fn test_set_log_directory() -> Result<()> {
+    let (settings, tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+    let old_directory = default_log_directory(tempdir.path());
+    let new_directory = tempdir.path().join("new_logs");
+
+    assert_success(fixtures::add_task(shared, "echo finished", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    // The task keeps running, until it's allowed to finish.
+    let finish_file = tempdir.path().join("finish");
+    let command = format!(
+        "while [ ! -f {} ]; do sleep 0.1; done && echo running",
+        finish_file.to_string_lossy()
+    );
+    assert_success(fixtures::add_task(shared, &command, false).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_running()).await?;
+
+    let response = send_message(shared, Message::SetLogDirectory(new_directory.clone())).await?;
+    assert_success(response);
+
+    let (old_path, _) = get_task_log_paths(0, &old_directory);
+    let (new_path, _) = get_task_log_paths(0, &new_directory);
+    assert!(!old_path.exists());
+    assert_eq!(read_to_string(new_path)?, "finished\n");
+    assert_eq!(
+        get_task(shared, 0).await?.log_directory,
+        Some(new_directory.clone())
+    );
+
+    // The running task keeps its log files, until it finished.
+    assert_eq!(
+        get_task(shared, 1).await?.log_directory,
+        Some(old_directory)
+    );
+    std::fs::write(&finish_file, "")?;
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    let task = get_task(shared, 1).await?;
+    assert_eq!(task.log_directory, Some(new_directory.clone()));
+    let (new_path, _) = get_task_log_paths(1, &new_directory);
+    assert_eq!(read_to_string(new_path)?, "running\n");
+
+    // New tasks write their logs into the new directory.
+    assert_success(fixtures::add_task(shared, "echo new", false).await?);
+    wait_for_task_condition(shared, 2, |task| task.is_done()).await?;
+    let (new_path, _) = get_task_log_paths(2, &new_directory);
+    assert_eq!(read_to_string(new_path)?, "new\n");
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+
+use pueue_lib::log::{default_log_directory, get_task_log_paths};
+use pueue_lib::network::message::Message;
+use pueue_lib::settings::Mirror;
+
+use crate::helper::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// A mirror copies the tasks and their output of the primary daemon, but rejects any changes.
+async fn test_mirror() -> Result<()> {
+    let (primary_settings, _primary_tempdir, _pid) = threaded_setup()?;
+    let primary = &primary_settings.shared;
+    assert_success(fixtures::add_task(primary, "echo mirrored", false).await?);
+    wait_for_task_condition(primary, 0, |task| task.is_done()).await?;
+
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.mirror = Some(Mirror {
+        host: primary.host.clone(),
+        port: primary.port.clone(),
+        daemon_cert: primary.daemon_cert(),
+        shared_secret_path: primary.shared_secret_path(),
+        unix_socket_path: Some(primary.unix_socket_path()),
+        log_lines: 10,
+    });
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    wait_for_task(shared, 0).await?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    let (stdout_path, _) = get_task_log_paths(0, &default_log_directory(tempdir.path()));
+    assert_eq!(std::fs::read_to_string(stdout_path)?, "mirrored");
+
+    // New tasks have to be added to the primary daemon.
+    let response = fixtures::add_task(shared, "ls", false).await?;
+    assert!(matches!(response, Message::Failure(_)));
+
+    // Changes of the primary daemon show up on the mirror.
+    assert_success(fixtures::add_task(primary, "echo again", false).await?);
+    wait_for_task(shared, 1).await?;
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    Ok(())
+}
@@ -1,17 +1,35 @@
 mod add;
+/// Tests for arrays of tasks, which are controlled as a unit.
+mod array;
+/// Tests for chat notifications.
+mod chat;
 mod clean;
 mod edit;
+/// Tests for following the output of tasks.
+mod follow;
 mod group;
 mod kill;
+/// Tests for moving the task logs to another directory.
+mod log_directory;
+/// Tests for read-only mirrors of another daemon.
+mod mirror;
 mod parallel_tasks;
 mod pause;
+/// Tests for the redaction of sensitive output.
+mod redaction;
 mod remove;
 mod restart;
 /// Tests regarding state restoration from a previous run.
 mod restore;
+/// Tests for the secrets, that are passed to the tasks of a group.
+mod secrets;
 /// Tests for shutting down the daemon.
 mod shutdown;
 mod start;
 mod stashed;
+/// Tests for version differences between the client and the daemon.
+mod version;
+/// Tests for watched directories, which add a task for each new file.
+mod watchers;
 /// Test that the worker pool environment variables are properly injected.
 mod worker_environment_variables;
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use pretty_assertions::assert_eq;
 
+use pueue_lib::network::message::{EnqueueMessage, Message};
+use pueue_lib::settings::PUEUE_DEFAULT_GROUP;
+use pueue_lib::state::GroupStatus;
 use pueue_lib::task::*;
 
 use crate::helper::*;
@@ -57,3 +60,119 @@ async fn test_parallel_tasks() -> Result<()> {
     }
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Tasks of groups with a CPU capacity only start, while enough CPU slots are free.
+/// Smaller tasks may start before larger tasks, that don't fit yet.
+async fn test_group_cpus() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.groups.insert(PUEUE_DEFAULT_GROUP.into(), 3);
+    settings
+        .daemon
+        .group_cpus
+        .insert(PUEUE_DEFAULT_GROUP.into(), 4);
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    for cpus in [3, 2, 1] {
+        let mut inner_message = fixtures::add_message(shared, "sleep 60");
+        inner_message.cpus = Some(cpus);
+        assert_success(send_message(shared, Message::Add(inner_message)).await?);
+    }
+
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    wait_for_task_condition(shared, 2, |task| task.is_running()).await?;
+    assert_eq!(get_task_status(shared, 1).await?, TaskStatus::Queued);
+
+    // Tasks, that could never fit into their group, are rejected.
+    let mut inner_message = fixtures::add_message(shared, "sleep 60");
+    inner_message.cpus = Some(5);
+    assert_failure(send_message(shared, Message::Add(inner_message)).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Units of a resource pool are assigned exclusively to running tasks.
+/// Tasks only start, once enough units are free.
+async fn test_resource_pools() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.groups.insert(PUEUE_DEFAULT_GROUP.into(), 3);
+    settings.daemon.resource_pools.insert(
+        "gpus".into(),
+        vec!["0".into(), "1".into(), "2".into(), "3".into()],
+    );
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    for amount in [2, 3, 2] {
+        let mut inner_message = fixtures::add_message(shared, "sleep 60");
+        inner_message.resources.insert("gpus".into(), amount);
+        assert_success(send_message(shared, Message::Add(inner_message)).await?);
+    }
+
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    wait_for_task_condition(shared, 2, |task| task.is_running()).await?;
+    assert_eq!(get_task_status(shared, 1).await?, TaskStatus::Queued);
+
+    let first = get_task(shared, 0).await?;
+    let third = get_task(shared, 2).await?;
+
+    assert_eq!(first.assigned_resources["gpus"], vec!["0", "1"]);
+    assert_eq!(third.assigned_resources["gpus"], vec!["2", "3"]);
+    assert_eq!(third.envs["PUEUE_ASSIGNED_GPUS"], "2,3");
+
+    // Requests, that could never be satisfied, are rejected.
+    let mut inner_message = fixtures::add_message(shared, "sleep 60");
+    inner_message.resources.insert("gpus".into(), 5);
+    assert_failure(send_message(shared, Message::Add(inner_message)).await?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Serial groups run their tasks strictly in the order of their ids.
+/// Earlier stashed tasks hold back later tasks and a failure pauses the group.
+async fn test_serial_group() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.groups.insert(PUEUE_DEFAULT_GROUP.into(), 1);
+    settings
+        .daemon
+        .serial_groups
+        .push(PUEUE_DEFAULT_GROUP.into());
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    let mut inner_message = fixtures::add_message(shared, "true");
+    inner_message.stashed = true;
+    assert_success(send_message(shared, Message::Add(inner_message)).await?);
+    assert_success(add_task(shared, "true", false).await?);
+
+    // The second task waits for the stashed one.
+    sleep_ms(500);
+    assert_eq!(get_task_status(shared, 1).await?, TaskStatus::Queued);
+
+    let enqueue_message = Message::Enqueue(EnqueueMessage {
+        task_ids: vec![0],
+        enqueue_at: None,
+    });
+    assert_success(send_message(shared, enqueue_message).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+    let first = get_task(shared, 0).await?;
+    let second = get_task(shared, 1).await?;
+    assert!(first.end.unwrap() <= second.start.unwrap());
+
+    // A failed task pauses the group, so later tasks don't run.
+    assert_success(add_task(shared, "false", false).await?);
+    assert_success(add_task(shared, "true", false).await?);
+    wait_for_task_condition(shared, 2, |task| task.is_done()).await?;
+    sleep_ms(500);
+    let state = get_state(shared).await?;
+    assert_eq!(state.groups[PUEUE_DEFAULT_GROUP], GroupStatus::Paused);
+    assert_eq!(state.tasks[&3].status, TaskStatus::Queued);
+
+    Ok(())
+}
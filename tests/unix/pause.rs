@@ -1,7 +1,13 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
 use anyhow::{Context, Result};
+use assert_cmd::prelude::*;
 use pueue_lib::network::message::*;
+use pueue_lib::settings::Settings;
 use pueue_lib::state::GroupStatus;
 use pueue_lib::task::*;
+use tempfile::TempDir;
 
 use crate::helper::fixtures::add_task;
 use crate::helper::*;
@@ -78,3 +84,88 @@ async fn test_pause_with_wait() -> Result<()> {
 
     Ok(())
 }
+
+/// Run `pueue pause` with the given arguments and make sure it succeeded.
+fn pause_via_client(config_path: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::cargo_bin("pueue")?
+        .arg("--config")
+        .arg(config_path)
+        .arg("pause")
+        .args(args)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "pause failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(())
+}
+
+/// Boot a daemon with a running and a queued task in the default group. \
+/// The config is saved, so the client can be used as well.
+async fn running_and_queued_setup(soft_pause: bool) -> Result<(Settings, TempDir, PathBuf, i32)> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.client.soft_pause = soft_pause;
+    let config_path = tempdir.path().join("pueue.yml");
+    settings.save(&Some(config_path.clone()))?;
+    let pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    add_task(shared, "sleep 60", false).await?;
+    add_task(shared, "ls", false).await?;
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    Ok((settings, tempdir, config_path, pid))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// `pause --soft` only stops the group from starting new tasks.
+async fn test_soft_pause() -> Result<()> {
+    let (settings, _tempdir, config_path, _pid) = running_and_queued_setup(false).await?;
+    let shared = &settings.shared;
+
+    pause_via_client(&config_path, &["--soft"])?;
+    wait_for_group_status(shared, PUEUE_DEFAULT_GROUP, GroupStatus::Paused).await?;
+
+    // Give the daemon time to theoretically start the queued task, once the first one is done.
+    sleep_ms(500);
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].status, TaskStatus::Running);
+    assert_eq!(state.tasks[&1].status, TaskStatus::Queued);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// `pause --hard` pauses the running tasks, even if `client.soft_pause` is set.
+async fn test_hard_pause() -> Result<()> {
+    let (settings, _tempdir, config_path, _pid) = running_and_queued_setup(true).await?;
+    let shared = &settings.shared;
+
+    pause_via_client(&config_path, &["--hard"])?;
+    wait_for_task_condition(shared, 0, |task| task.status == TaskStatus::Paused).await?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.groups[PUEUE_DEFAULT_GROUP], GroupStatus::Paused);
+    assert_eq!(state.tasks[&1].status, TaskStatus::Queued);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// With `client.soft_pause`, a plain `pause` doesn't pause the running tasks.
+async fn test_soft_pause_setting() -> Result<()> {
+    let (settings, _tempdir, config_path, _pid) = running_and_queued_setup(true).await?;
+    let shared = &settings.shared;
+
+    pause_via_client(&config_path, &[])?;
+    wait_for_group_status(shared, PUEUE_DEFAULT_GROUP, GroupStatus::Paused).await?;
+
+    sleep_ms(500);
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].status, TaskStatus::Running);
+    assert_eq!(state.tasks[&1].status, TaskStatus::Queued);
+
+    Ok(())
+}
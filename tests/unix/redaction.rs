@@ -0,0 +1,87 @@
+use std::fs::read_to_string;
+use std::io::Read;
+
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+use snap::read::FrameDecoder;
+
+use pueue_lib::log::get_task_log_paths;
+use pueue_lib::network::message::*;
+use pueue_lib::settings::Shared;
+
+use crate::helper::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Matches of the redact patterns are removed from the output, before it's written to the logs.
+/// Neither the logs of running tasks nor the callback contain them.
+async fn test_redact_patterns() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let callback_path = tempdir.path().join("callback");
+    let finish_path = tempdir.path().join("finish");
+    settings.daemon.redact_patterns = vec![r"password=\S+".into()];
+    settings.daemon.callback = Some(format!(
+        "echo {{{{ stdout }}}} > {}",
+        callback_path.to_string_lossy()
+    ));
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    let command = format!(
+        "echo user=me password=abc123 && while [ ! -f {} ]; do sleep 0.1; done",
+        finish_path.to_string_lossy()
+    );
+    assert_success(fixtures::add_task(shared, &command, false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    // The output is already redacted, while the task is running.
+    let mut tries = 0;
+    let mut stdout = String::new();
+    while stdout.is_empty() && tries < 20 {
+        sleep_ms(50);
+        stdout = get_stdout(shared).await?;
+        tries += 1;
+    }
+    assert_eq!(stdout, "user=me ***\n");
+
+    std::fs::write(&finish_path, "")?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    let task = get_task(shared, 0).await?;
+    let (out_path, _) = get_task_log_paths(0, &task.log_directory(&shared.pueue_directory()));
+    assert_eq!(read_to_string(out_path)?, "user=me ***\n");
+
+    // The callback is run through the callback queue. Wait until it wrote its whole output.
+    let mut tries = 0;
+    let mut content = String::new();
+    while !content.ends_with('\n') && tries < 20 {
+        sleep_ms(50);
+        content = read_to_string(&callback_path).unwrap_or_default();
+        tries += 1;
+    }
+    assert_eq!(content, "user=me ***\n");
+
+    Ok(())
+}
+
+/// Get the decompressed stdout of the first task via the daemon.
+async fn get_stdout(shared: &Shared) -> Result<String> {
+    let response = send_message(
+        shared,
+        Message::Log(LogRequestMessage {
+            task_ids: vec![0],
+            send_logs: true,
+            lines: None,
+            archived: false,
+        }),
+    )
+    .await?;
+    let log = match response {
+        Message::LogResponse(mut logs) => logs.remove(&0).expect("Log is missing"),
+        _ => panic!("Expected LogResponse, got {:?}", response),
+    };
+
+    let mut stdout = String::new();
+    FrameDecoder::new(&log.stdout.unwrap_or_default()[..]).read_to_string(&mut stdout)?;
+
+    Ok(stdout)
+}
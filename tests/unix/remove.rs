@@ -1,5 +1,11 @@
+use std::process::Command;
+
 use anyhow::Result;
+use assert_cmd::prelude::*;
+use pretty_assertions::assert_eq;
+
 use pueue_lib::network::message::*;
+use pueue_lib::task::TaskStatus;
 
 use crate::helper::*;
 
@@ -48,3 +54,77 @@ async fn test_normal_clean() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Force removal kills running and paused tasks and only responds once they're gone.
+async fn test_force_remove() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    // 0 -> running
+    // 1 -> paused
+    // 2 -> queued
+    for command in ["sleep 60", "sleep 60", "ls"] {
+        assert_success(fixtures::add_task(shared, command, false).await?);
+    }
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+    start_tasks(shared, TaskSelection::TaskIds(vec![1])).await?;
+    wait_for_task_condition(shared, 1, |task| task.is_running()).await?;
+    pause_tasks(shared, TaskSelection::TaskIds(vec![1])).await?;
+    wait_for_task_condition(shared, 1, |task| task.status == TaskStatus::Paused).await?;
+
+    let response = send_message(shared, Message::ForceRemove(vec![0, 1, 2, 3])).await?;
+    let response = match response {
+        Message::TaskActionResponse(response) => response,
+        _ => panic!("Expected a task action response, got {:?}", response),
+    };
+    assert_eq!(response.succeeded, vec![0, 1, 2]);
+    assert_eq!(
+        response.failed.get(&3),
+        Some(&FailureKind::TaskNotFound { task_id: 3 })
+    );
+    assert!(response.text.contains("0 Killed"));
+
+    let state = get_state(shared).await?;
+    assert!(state.tasks.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The client exits with a non-zero exit code, if the command failed for some of the tasks.
+async fn test_partial_failure_exit_code() -> Result<()> {
+    let (settings, tempdir) = base_setup()?;
+    let config_path = tempdir.path().join("pueue.yml");
+    settings.save(&Some(config_path.clone()))?;
+    let shared = &settings.shared;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    // 0 -> done
+    // 1 -> running
+    for command in ["ls", "sleep 60"] {
+        assert_success(fixtures::add_task(shared, command, false).await?);
+    }
+    wait_for_task_condition(shared, 1, |task| task.is_running()).await?;
+
+    // Running tasks can't be removed.
+    let output = Command::cargo_bin("pueue")?
+        .arg("--config")
+        .arg(&config_path)
+        .args(["remove", "0", "1"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(6));
+
+    let output = Command::cargo_bin("pueue")?
+        .arg("--config")
+        .arg(&config_path)
+        .args(["remove", "1"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(1));
+
+    let state = get_state(shared).await?;
+    assert!(!state.tasks.contains_key(&0));
+    assert!(state.tasks.contains_key(&1));
+
+    Ok(())
+}
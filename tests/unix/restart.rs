@@ -1,5 +1,6 @@
 use anyhow::Result;
 use pueue_lib::network::message::*;
+use pueue_lib::task::TaskStatus;
 
 use crate::helper::*;
 
@@ -25,6 +26,7 @@ async fn test_restart_in_place() -> Result<()> {
         }],
         start_immediately: false,
         stashed: false,
+        failed: None,
     });
     assert_success(send_message(shared, restart_message).await?);
 
@@ -64,8 +66,50 @@ async fn test_cannot_restart_running() -> Result<()> {
         }],
         start_immediately: false,
         stashed: false,
+        failed: None,
     });
     assert_failure(send_message(shared, restart_message).await?);
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Ensure that the daemon resolves all failed tasks of a selection and restarts them in-place.
+/// Successful tasks and failed tasks of other groups must be left untouched.
+async fn test_restart_failed_in_group_in_place() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    // Add two failing tasks and one successful task to the default group.
+    // Add another failing task to the other group.
+    assert_success(fixtures::add_task(shared, "failing_command", false).await?);
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    assert_success(fixtures::add_task(shared, "failing_command", false).await?);
+    assert_success(fixtures::add_task_to_group(shared, "failing_command", "test_2").await?);
+    for task_id in 0..4 {
+        wait_for_task_condition(shared, task_id, |task| task.is_done()).await?;
+    }
+
+    let restart_message = Message::Restart(RestartMessage {
+        tasks: Vec::new(),
+        start_immediately: false,
+        stashed: true,
+        failed: Some(FailedSelection::Group("default".to_string())),
+    });
+    assert_success(send_message(shared, restart_message).await?);
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 4, "No new task should be created");
+    assert!(matches!(
+        state.tasks.get(&0).unwrap().status,
+        TaskStatus::Stashed { .. }
+    ));
+    assert!(state.tasks.get(&1).unwrap().is_done());
+    assert!(matches!(
+        state.tasks.get(&2).unwrap().status,
+        TaskStatus::Stashed { .. }
+    ));
+    assert!(state.tasks.get(&3).unwrap().failed());
+
+    Ok(())
+}
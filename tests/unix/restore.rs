@@ -4,7 +4,8 @@ use std::path::PathBuf;
 use anyhow::Result;
 use pretty_assertions::assert_eq;
 
-use pueue_lib::network::message::TaskSelection;
+use pueue_lib::network::message::{Message, TaskSelection};
+use pueue_lib::settings::StateSaveMode;
 use pueue_lib::state::GroupStatus;
 
 use crate::helper::*;
@@ -92,3 +93,31 @@ async fn test_load_config() -> Result<()> {
     child.kill()?;
     Ok(())
 }
+
+#[tokio::test]
+/// Delayed changes of the state are saved via a flush and survive a crash of the daemon.
+async fn test_flush_delayed_state() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.state_save_mode = StateSaveMode::Periodic;
+    settings.daemon.state_save_delay = Some("1h".into());
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    // The task is only added to the state on disk, once it's flushed.
+    assert_success(add_task(shared, "ls", false).await?);
+    assert_success(send_message(shared, Message::Flush).await?);
+
+    // Kill the daemon, so it can't save the state during shutdown.
+    child.kill()?;
+    child.wait()?;
+    // The stale socket would otherwise be mistaken for the new daemon's one.
+    std::fs::remove_file(tempdir.path().join("test.socket"))?;
+
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 1);
+
+    child.kill()?;
+    Ok(())
+}
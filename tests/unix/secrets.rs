@@ -0,0 +1,84 @@
+use std::fs::read_to_string;
+
+use anyhow::Result;
+use pretty_assertions::assert_eq;
+
+use pueue_lib::log::get_task_log_paths;
+use pueue_lib::settings::PUEUE_DEFAULT_GROUP;
+use pueue_lib::task::{TaskResult, TaskStatus};
+
+use crate::helper::*;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Secrets are passed to tasks of their group, but are neither saved nor written to the logs.
+async fn test_group_secrets() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let finish_path = tempdir.path().join("finish");
+    settings.daemon.secret_command = Some("echo {{ name }} | rev".into());
+    settings
+        .daemon
+        .group_secrets
+        .insert(PUEUE_DEFAULT_GROUP.into(), vec!["TOKEN".into()]);
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    let command = format!(
+        "echo token=$TOKEN && while [ ! -f {} ]; do sleep 0.1; done",
+        finish_path.to_string_lossy()
+    );
+    assert_success(fixtures::add_task(shared, &command, false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    // The output is already redacted, while the task is running.
+    let task = get_task(shared, 0).await?;
+    assert!(!task.envs.contains_key("TOKEN"));
+    let (out_path, _) = get_task_log_paths(0, &task.log_directory(&shared.pueue_directory()));
+    let mut tries = 0;
+    while read_to_string(&out_path)?.is_empty() && tries < 20 {
+        sleep_ms(50);
+        tries += 1;
+    }
+    assert_eq!(read_to_string(&out_path)?, "token=***\n");
+
+    std::fs::write(&finish_path, "")?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    assert_eq!(read_to_string(&out_path)?, "token=***\n");
+
+    let saved_state = read_to_string(shared.pueue_directory().join("state.json"))?;
+    assert!(!saved_state.contains("NEKOT"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Secrets are looked up in the background and slow lookups are killed.
+async fn test_group_secrets_timeout() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.daemon.secret_command = Some("sleep 60".into());
+    settings.daemon.secret_timeout = 1;
+    settings
+        .daemon
+        .group_secrets
+        .insert(PUEUE_DEFAULT_GROUP.into(), vec!["TOKEN".into()]);
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    // The daemon keeps answering, while the secret is being looked up.
+    assert_success(fixtures::add_task(shared, "echo $TOKEN", false).await?);
+    sleep_ms(200);
+    assert_eq!(get_task(shared, 0).await?.status, TaskStatus::Queued);
+
+    sleep_ms(1000);
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+    let task = get_task(shared, 0).await?;
+    match task.status {
+        TaskStatus::Done(TaskResult::FailedToSpawn(error)) => {
+            assert!(error.contains("took longer than 1s"), "{}", error)
+        }
+        status => panic!("Expected the task to fail to spawn, got {:?}", status),
+    }
+
+    Ok(())
+}
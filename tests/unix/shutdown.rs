@@ -1,6 +1,12 @@
 use std::convert::TryInto;
+use std::process::Command;
 
 use anyhow::{Context, Result};
+use assert_cmd::prelude::*;
+
+use pueue_daemon_lib::state_helper::restore_state;
+use pueue_lib::network::message::{Message, Shutdown};
+use pueue_lib::task::{TaskResult, TaskStatus};
 
 use crate::helper::*;
 
@@ -48,3 +54,100 @@ async fn test_graceful_shutdown() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+/// When draining, the daemon waits for running tasks to finish, but doesn't start new ones.
+async fn test_drain_shutdown() -> Result<()> {
+    let (settings, tempdir) = base_setup()?;
+    let shared = &settings.shared;
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+
+    assert_success(add_task(shared, "sleep 1", false).await?);
+    assert_success(add_task(shared, "ls", false).await?);
+    wait_for_task_condition(shared, 0, |task| task.is_running()).await?;
+
+    let message = Message::DaemonShutdown(Shutdown::Drain);
+    assert_success(send_message(shared, message).await?);
+    // The running task is still running and the daemon is still alive.
+    sleep_ms(200);
+    assert!(child.try_wait()?.is_none());
+
+    wait_for_shutdown(child.id().try_into()?)?;
+    sleep_ms(500);
+    assert!(matches!(
+        child.try_wait()?.and_then(|code| code.code()),
+        Some(0)
+    ));
+
+    let state = restore_state(&shared.pueue_directory())?.context("The state has been lost")?;
+    assert_eq!(
+        state.tasks[&0].status,
+        TaskStatus::Done(TaskResult::Success)
+    );
+    assert_eq!(state.tasks[&1].status, TaskStatus::Queued);
+
+    Ok(())
+}
+
+#[test]
+/// The pid file of a crashed daemon doesn't prevent a new daemon from starting,
+/// even if its pid has been reused by another process in the meantime.
+fn test_stale_pid_file() -> Result<()> {
+    let (_settings, tempdir) = base_setup()?;
+    std::fs::write(
+        tempdir.path().join("pueue.pid"),
+        std::process::id().to_string(),
+    )?;
+
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+    child.kill()?;
+
+    Ok(())
+}
+
+#[test]
+/// A second daemon refuses to start, while another one is running in the same directory.
+fn test_daemon_already_running() -> Result<()> {
+    let (_settings, tempdir) = base_setup()?;
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+
+    let output = Command::cargo_bin("pueued")?
+        .arg("--config")
+        .arg(tempdir.path().join("pueue.yml"))
+        .output()?;
+    child.kill()?;
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!(
+        "Another daemon is already running with pid {}",
+        child.id()
+    )));
+
+    Ok(())
+}
+
+#[tokio::test]
+/// The lock of the pueue directory is released after a graceful shutdown,
+/// so a new daemon can be started right away.
+async fn test_lock_released_after_shutdown() -> Result<()> {
+    let (settings, tempdir) = base_setup()?;
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+
+    assert_success(shutdown_daemon(&settings.shared).await?);
+    wait_for_shutdown(child.id().try_into()?)?;
+    sleep_ms(500);
+    assert!(matches!(
+        child.try_wait()?.and_then(|code| code.code()),
+        Some(0)
+    ));
+    // The lock file itself stays, only the lock on it is released.
+    assert!(tempdir.path().join("pueue.lock").exists());
+
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+    assert!(child.try_wait()?.is_none());
+    assert_success(shutdown_daemon(&settings.shared).await?);
+    wait_for_shutdown(child.id().try_into()?)?;
+
+    Ok(())
+}
@@ -68,3 +68,35 @@ async fn test_start_tasks(#[case] start_message: Message) -> Result<()> {
     }
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// No new tasks are started, while there's less disk space than `min_free_disk`.
+/// The user is notified via the `low_disk_callback`, but can still start tasks explicitly.
+async fn test_min_free_disk() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let notification = tempdir.path().join("low_disk");
+    settings.daemon.min_free_disk = Some("1000000T".into());
+    settings.daemon.low_disk_callback = Some(format!(
+        "echo {{{{ threshold }}}} > {}",
+        notification.display()
+    ));
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    assert_success(fixtures::add_task(shared, "ls", false).await?);
+    sleep_ms(500);
+    assert_eq!(get_task_status(shared, 0).await?, TaskStatus::Queued);
+    // The callback is run through the callback queue, give it some time.
+    let mut tries = 0;
+    while !notification.exists() && tries < 20 {
+        sleep_ms(100);
+        tries += 1;
+    }
+    assert!(notification.exists());
+
+    start_tasks(shared, TaskSelection::TaskIds(vec![0])).await?;
+    wait_for_task_condition(shared, 0, |task| task.is_done()).await?;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use anyhow::Result;
+
+use pueue_lib::network::message::*;
+use pueue_lib::settings::{Settings, VersionMismatch};
+
+use crate::helper::*;
+
+/// Send a status request as a client with the given version.
+async fn status_as_client(settings: &Settings, version: &str) -> Result<Message> {
+    let traced = TracedMessage::new(Message::Status, version);
+    send_message(&settings.shared, Message::Traced(traced)).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// By default, clients with an incompatible version are only warned about.
+async fn test_incompatible_client_warn() -> Result<()> {
+    let (settings, tempdir) = base_setup()?;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    let response = status_as_client(&settings, "0.1.0").await?;
+    assert!(matches!(response, Message::StatusResponse(_)));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The daemon refuses requests of clients with an incompatible version, if configured to do so.
+/// Clients with a compatible version are still served.
+async fn test_incompatible_client_refuse() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    settings.shared.version_mismatch = VersionMismatch::Refuse;
+    settings.save(&Some(tempdir.path().join("pueue.yml")))?;
+    let _pid = boot_daemon(tempdir.path())?;
+
+    assert_failure(status_as_client(&settings, "0.1.0").await?);
+
+    let version = clap::crate_version!();
+    let response = status_as_client(&settings, version).await?;
+    assert!(matches!(response, Message::StatusResponse(_)));
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::convert::TryInto;
+use std::fs::{create_dir, write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use pretty_assertions::assert_eq;
+
+use pueue_lib::settings::{Settings, Shared, Watcher};
+
+use crate::helper::*;
+
+/// Configure a watcher for `*.txt` files in the `inbox` of the temporary directory.
+fn watch_inbox(settings: &mut Settings, tempdir: &Path, path: PathBuf) -> Result<PathBuf> {
+    let inbox = tempdir.join("inbox");
+    create_dir(&inbox)?;
+    settings.daemon.watchers.push(Watcher {
+        path,
+        glob: "*.txt".into(),
+        command: "echo {{ name }}".into(),
+        group: PUEUE_DEFAULT_GROUP.into(),
+        debounce: 0,
+    });
+    settings.save(&Some(tempdir.join("pueue.yml")))?;
+
+    Ok(inbox)
+}
+
+/// The watched directories are scanned every few seconds,
+/// which is why this waits longer than [wait_for_task].
+async fn wait_for_watcher_task(shared: &Shared, task_id: usize) -> Result<String> {
+    for _ in 0..100 {
+        if let Some(task) = get_state(shared).await?.tasks.get(&task_id) {
+            return Ok(task.command.clone());
+        }
+        sleep_ms(100);
+    }
+
+    bail!(
+        "The watcher didn't add task {} in about 10 seconds.",
+        task_id
+    )
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// A task is added for each new file, while files that existed on the first start are skipped.
+/// Environment variables in the watcher's path are expanded.
+async fn test_watcher() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    std::env::set_var("PUEUE_TEST_WATCHER_DIR", tempdir.path());
+    let inbox = watch_inbox(
+        &mut settings,
+        tempdir.path(),
+        PathBuf::from("$PUEUE_TEST_WATCHER_DIR/inbox"),
+    )?;
+    write(inbox.join("existing.txt"), "")?;
+    let _pid = boot_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    write(inbox.join("new.txt"), "")?;
+    write(inbox.join("ignored.log"), "")?;
+    assert_eq!(wait_for_watcher_task(shared, 0).await?, "echo new.txt");
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+/// Files that appear while the daemon isn't running are processed, once it's started again.
+async fn test_watcher_after_restart() -> Result<()> {
+    let (mut settings, tempdir) = base_setup()?;
+    let inbox = tempdir.path().join("inbox");
+    watch_inbox(&mut settings, tempdir.path(), inbox.clone())?;
+    write(inbox.join("existing.txt"), "")?;
+    let child = boot_standalone_daemon(tempdir.path())?;
+    let shared = &settings.shared;
+
+    assert_success(shutdown_daemon(shared).await?);
+    wait_for_shutdown(child.id().try_into()?)?;
+
+    write(inbox.join("new.txt"), "")?;
+    let mut child = boot_standalone_daemon(tempdir.path())?;
+    assert_eq!(wait_for_watcher_task(shared, 0).await?, "echo new.txt");
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks.len(), 1);
+
+    child.kill()?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use pueue_lib::network::message::Message;
 use pueue_lib::settings::PUEUE_DEFAULT_GROUP;
 
 use crate::helper::fixtures::*;
@@ -94,3 +95,48 @@ async fn test_worker_for_new_pool() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// The task's label is exported, while a label from the submitting environment is dropped.
+async fn test_label_env() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    let mut message = add_message(shared, "ls");
+    message.label = Some("backup".into());
+    assert_success(send_message(shared, Message::Add(message)).await?);
+
+    let mut message = add_message(shared, "ls");
+    message
+        .envs
+        .insert("PUEUE_LABEL".into(), "inherited".into());
+    assert_success(send_message(shared, Message::Add(message)).await?);
+    wait_for_task_condition(shared, 1, |task| task.is_done()).await?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(
+        state.tasks[&0].envs.get("PUEUE_LABEL"),
+        Some(&"backup".to_string())
+    );
+    assert_eq!(state.tasks[&1].envs.get("PUEUE_LABEL"), None);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+/// Running tasks expose the slot they occupy in their group's worker pool.
+async fn test_task_slots() -> Result<()> {
+    let (settings, _tempdir, _pid) = threaded_setup()?;
+    let shared = &settings.shared;
+
+    for _ in 0..2 {
+        assert_success(add_task_to_group(shared, "sleep 60", "test_3").await?);
+    }
+    wait_for_task_condition(shared, 1, |task| task.is_running()).await?;
+
+    let state = get_state(shared).await?;
+    assert_eq!(state.tasks[&0].slot, Some(0));
+    assert_eq!(state.tasks[&1].slot, Some(1));
+
+    Ok(())
+}